@@ -0,0 +1,557 @@
+//! `#[derive(Config)]` expansion.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Expr, Fields, GenericArgument, Ident, LitStr, PathArguments, Result, Type,
+};
+
+/// Struct-level `#[config(...)]` options.
+#[derive(Default)]
+struct StructAttrs {
+    name: Option<LitStr>,
+}
+
+/// A check from `#[config(validate(...))]`.
+enum Check {
+    Range {
+        min: Option<Box<Expr>>,
+        max: Option<Box<Expr>>,
+    },
+    NonEmpty,
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    /// Inner type if the field is an `Option<T>`.
+    option_of: Option<Type>,
+    default: Option<Expr>,
+    env: Option<LitStr>,
+    checks: Vec<Check>,
+    /// Whether the builder must set this field before `build()` compiles.
+    required: bool,
+    /// Doc comment, used as the schema description.
+    doc: Option<String>,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "#[derive(Config)] does not support generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => named
+                .named
+                .iter()
+                .map(parse_field)
+                .collect::<Result<Vec<_>>>()?,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "#[derive(Config)] requires named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "#[derive(Config)] only supports structs",
+            ))
+        }
+    };
+    let attrs = parse_struct_attrs(&input)?;
+
+    let mut tokens = TokenStream::new();
+    tokens.extend(expand_default(&input.ident, &fields));
+    tokens.extend(expand_config(&input, &attrs, &fields));
+    tokens.extend(expand_merge(&input.ident, &fields));
+    tokens.extend(expand_env(&input.ident, &fields));
+    tokens.extend(expand_diff(&input.ident, &fields));
+    tokens.extend(expand_builder(&input, &fields));
+    Ok(tokens)
+}
+
+fn parse_struct_attrs(input: &DeriveInput) -> Result<StructAttrs> {
+    let mut attrs = StructAttrs::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("config")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                attrs.name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown struct attribute, expected `name`"))
+            }
+        })?;
+    }
+    Ok(attrs)
+}
+
+fn parse_field(field: &syn::Field) -> Result<Field> {
+    let mut parsed = Field {
+        ident: field.ident.clone().expect("named field"),
+        ty: field.ty.clone(),
+        option_of: option_inner(&field.ty),
+        default: None,
+        env: None,
+        checks: Vec::new(),
+        required: false,
+        doc: doc_comment(&field.attrs),
+    };
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("config")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                parsed.default = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("env") {
+                parsed.env = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("required") {
+                parsed.required = true;
+            } else if meta.path.is_ident("validate") {
+                meta.parse_nested_meta(|check| {
+                    if check.path.is_ident("range") {
+                        let (mut min, mut max) = (None, None);
+                        check.parse_nested_meta(|bound| {
+                            if bound.path.is_ident("min") {
+                                min = Some(Box::new(bound.value()?.parse()?));
+                            } else if bound.path.is_ident("max") {
+                                max = Some(Box::new(bound.value()?.parse()?));
+                            } else {
+                                return Err(bound.error("expected `min` or `max`"));
+                            }
+                            Ok(())
+                        })?;
+                        parsed.checks.push(Check::Range { min, max });
+                    } else if check.path.is_ident("non_empty") {
+                        parsed.checks.push(Check::NonEmpty);
+                    } else {
+                        return Err(check.error("expected `range(...)` or `non_empty`"));
+                    }
+                    Ok(())
+                })?;
+            } else {
+                return Err(meta.error(
+                    "unknown field attribute, expected `default`, `env`, `required`, or `validate`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(parsed)
+}
+
+/// Join `///` doc comment lines into one description.
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<_> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    }),
+                ..
+            }) => Some(lit.value().trim().to_string()),
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join(" ").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// Render a literal expression as JSON text, for schema defaults.
+fn literal_json(expr: &Expr) -> Option<String> {
+    let Expr::Lit(syn::ExprLit { lit, .. }) = expr else {
+        return None;
+    };
+    match lit {
+        syn::Lit::Int(int) => Some(int.base10_digits().to_string()),
+        syn::Lit::Float(float) => Some(float.base10_digits().to_string()),
+        syn::Lit::Bool(b) => Some(b.value.to_string()),
+        syn::Lit::Str(s) => {
+            let mut json = String::from("\"");
+            for c in s.value().chars() {
+                match c {
+                    '"' => json.push_str("\\\""),
+                    '\\' => json.push_str("\\\\"),
+                    c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => json.push(c),
+                }
+            }
+            json.push('"');
+            Some(json)
+        }
+        _ => None,
+    }
+}
+
+/// Return a numeric literal as an `f64` literal, for schema bounds.
+fn literal_f64(expr: &Expr) -> Option<proc_macro2::Literal> {
+    let Expr::Lit(syn::ExprLit { lit, .. }) = expr else {
+        return None;
+    };
+    let value: f64 = match lit {
+        syn::Lit::Int(int) => int.base10_digits().parse().ok()?,
+        syn::Lit::Float(float) => float.base10_digits().parse().ok()?,
+        _ => return None,
+    };
+    Some(proc_macro2::Literal::f64_unsuffixed(value))
+}
+
+/// Return `T` if `ty` is `Option<T>`.
+fn option_inner(ty: &Type) -> Option<Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) => Some(inner.clone()),
+        _ => None,
+    }
+}
+
+/// Whether `ty` is `String`, whose setters and defaults accept `impl Into<String>`.
+fn is_string(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("String"))
+}
+
+fn expand_default(name: &Ident, fields: &[Field]) -> TokenStream {
+    let inits = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let Some(value) = &f.default else {
+            return quote! { #ident: ::core::default::Default::default() };
+        };
+        // String literals are parsed with `FromStr`, so `"30s"` works for a
+        // `HumanDuration`; everything else is used as written so integer
+        // literals infer the field type.
+        let value = match value {
+            Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(lit),
+                ..
+            }) => {
+                let message = format!("invalid default {:?} for `{ident}`", lit.value());
+                quote! { #value.parse().expect(#message) }
+            }
+            _ => quote! { #value },
+        };
+        if f.option_of.is_some() {
+            quote! { #ident: ::core::option::Option::Some(#value) }
+        } else {
+            quote! { #ident: #value }
+        }
+    });
+    quote! {
+        impl ::core::default::Default for #name {
+            fn default() -> Self {
+                Self { #(#inits,)* }
+            }
+        }
+    }
+}
+
+fn expand_config(input: &DeriveInput, attrs: &StructAttrs, fields: &[Field]) -> TokenStream {
+    let name = &input.ident;
+    let schema = expand_schema(input, attrs, fields);
+    let name_fn = attrs.name.as_ref().map(|lit| {
+        quote! {
+            fn name(&self) -> &str {
+                #lit
+            }
+        }
+    });
+    let checks = fields.iter().filter(|f| !f.checks.is_empty()).map(|f| {
+        let ident = &f.ident;
+        let label = ident.to_string();
+        let chain = f.checks.iter().map(|check| match check {
+            Check::Range { min, max } => match (min, max) {
+                (Some(min), Some(max)) => quote! { .range(#min..=#max) },
+                (Some(min), None) => quote! { .range(#min..) },
+                (None, Some(max)) => quote! { .range(..=#max) },
+                (None, None) => quote! {},
+            },
+            Check::NonEmpty => quote! { .non_empty() },
+        });
+        if f.option_of.is_some() {
+            quote! {
+                if let ::core::option::Option::Some(value) = &self.#ident {
+                    report.field(#label, value) #(#chain)*;
+                }
+            }
+        } else {
+            quote! {
+                report.field(#label, &self.#ident) #(#chain)*;
+            }
+        }
+    });
+    quote! {
+        impl ::rustratify::Config for #name {
+            #name_fn
+
+            fn schema() -> ::rustratify::ConfigSchema {
+                #schema
+            }
+
+            fn validation(&self) -> ::rustratify::ValidationReport {
+                #[allow(unused_mut)]
+                let mut report = ::rustratify::ValidationReport::new();
+                #(#checks)*
+                report
+            }
+        }
+
+        impl ::rustratify::SchemaValue for #name {
+            fn schema_type() -> ::rustratify::SchemaType {
+                ::rustratify::SchemaType::Object(::std::boxed::Box::new(
+                    <Self as ::rustratify::Config>::schema(),
+                ))
+            }
+        }
+    }
+}
+
+/// Build the `ConfigSchema` expression for `Config::schema`.
+fn expand_schema(input: &DeriveInput, attrs: &StructAttrs, fields: &[Field]) -> TokenStream {
+    let title = attrs
+        .name
+        .as_ref()
+        .map(LitStr::value)
+        .unwrap_or_else(|| input.ident.to_string());
+    let description = doc_comment(&input.attrs).map(|doc| quote! { .description(#doc) });
+    let properties = fields.iter().map(|f| {
+        let label = f.ident.to_string();
+        let ty = &f.ty;
+        let mut chain = Vec::new();
+        if let Some(doc) = &f.doc {
+            chain.push(quote! { .description(#doc) });
+        }
+        if let Some(json) = f.default.as_ref().and_then(literal_json) {
+            chain.push(quote! { .default_json(#json) });
+        }
+        if f.required {
+            chain.push(quote! { .required() });
+        }
+        for check in &f.checks {
+            match check {
+                Check::Range { min, max } => {
+                    if let Some(min) = min.as_deref().and_then(literal_f64) {
+                        chain.push(quote! { .minimum(#min) });
+                    }
+                    if let Some(max) = max.as_deref().and_then(literal_f64) {
+                        chain.push(quote! { .maximum(#max) });
+                    }
+                }
+                Check::NonEmpty => chain.push(quote! { .non_empty() }),
+            }
+        }
+        quote! {
+            .property(::rustratify::SchemaProperty::of::<#ty>(#label) #(#chain)*)
+        }
+    });
+    quote! {
+        ::rustratify::ConfigSchema::new()
+            .title(#title)
+            #description
+            #(#properties)*
+    }
+}
+
+fn expand_merge(name: &Ident, fields: &[Field]) -> TokenStream {
+    let merges = fields.iter().map(|f| {
+        let ident = &f.ident;
+        if f.option_of.is_some() {
+            quote! {
+                if other.#ident.is_some() {
+                    self.#ident = ::core::clone::Clone::clone(&other.#ident);
+                }
+            }
+        } else {
+            quote! {
+                if other.#ident != defaults.#ident {
+                    self.#ident = ::core::clone::Clone::clone(&other.#ident);
+                }
+            }
+        }
+    });
+    quote! {
+        impl ::rustratify::MergeableConfig for #name {
+            #[allow(unused_variables)]
+            fn merge(&mut self, other: &Self) {
+                let defaults = <Self as ::core::default::Default>::default();
+                #(#merges)*
+            }
+        }
+    }
+}
+
+fn expand_env(name: &Ident, fields: &[Field]) -> TokenStream {
+    let reads = fields.iter().filter_map(|f| {
+        let ident = &f.ident;
+        let var = f.env.as_ref()?;
+        Some(if f.option_of.is_some() {
+            quote! { fields.optional(#var, &mut self.#ident); }
+        } else {
+            quote! { fields.field(#var, &mut self.#ident); }
+        })
+    });
+    quote! {
+        impl ::rustratify::FromEnv for #name {
+            #[allow(unused_variables)]
+            fn apply_env(&mut self, fields: &mut ::rustratify::EnvFields<'_>) {
+                #(#reads)*
+            }
+        }
+    }
+}
+
+fn expand_diff(name: &Ident, fields: &[Field]) -> TokenStream {
+    let compares = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let label = ident.to_string();
+        quote! { fields.field(#label, &self.#ident, &new.#ident); }
+    });
+    quote! {
+        impl ::rustratify::DiffConfig for #name {
+            #[allow(unused_variables)]
+            fn diff_fields(&self, new: &Self, fields: &mut ::rustratify::DiffFields<'_>) {
+                #(#compares)*
+            }
+        }
+    }
+}
+
+fn expand_builder(input: &DeriveInput, fields: &[Field]) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let builder = format_ident!("{}Builder", name);
+    let doc = format!("Builder for [`{name}`], generated by `#[derive(Config)]`.");
+
+    // One type parameter per required field, moving from `Missing<M>` to
+    // `Set<M>` as its setter is called. `M` is a marker named after the
+    // field so compile errors say which one is missing.
+    let required: Vec<_> = fields.iter().filter(|f| f.required).collect();
+    let params: Vec<_> = (0..required.len())
+        .map(|i| format_ident!("__S{}", i))
+        .collect();
+    let markers: Vec<_> = required
+        .iter()
+        .map(|f| format_ident!("{}_{}", name, f.ident))
+        .collect();
+    let marker_defs = markers.iter().zip(&required).map(|(marker, f)| {
+        let doc = format!("Typestate marker for `{name}::{}`.", f.ident);
+        quote! {
+            #[doc = #doc]
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            #vis struct #marker;
+        }
+    });
+    let missing = markers.iter().map(|m| quote! { ::rustratify::Missing<#m> });
+    let set = markers.iter().map(|m| quote! { ::rustratify::Set<#m> });
+    let generics_def = if params.is_empty() {
+        quote! {}
+    } else {
+        let defaults = missing.clone();
+        quote! { <#(#params = #defaults),*> }
+    };
+    let generics = if params.is_empty() {
+        quote! {}
+    } else {
+        quote! { <#(#params),*> }
+    };
+    let start = if params.is_empty() {
+        quote! {}
+    } else {
+        quote! { <#(#missing),*> }
+    };
+    let complete = if params.is_empty() {
+        quote! {}
+    } else {
+        quote! { <#(#set),*> }
+    };
+
+    let setters = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let doc = format!("Set `{ident}`.");
+        let ty = f.option_of.as_ref().unwrap_or(&f.ty);
+        let (param, value) = if is_string(ty) {
+            (
+                quote! { impl ::core::convert::Into<#ty> },
+                quote! { ::core::convert::Into::into(value) },
+            )
+        } else {
+            (quote! { #ty }, quote! { value })
+        };
+        let value = if f.option_of.is_some() {
+            quote! { ::core::option::Option::Some(#value) }
+        } else {
+            value
+        };
+        let Some(index) = required.iter().position(|r| r.ident == *ident) else {
+            return quote! {
+                #[doc = #doc]
+                #vis fn #ident(mut self, value: #param) -> Self {
+                    self.inner.#ident = #value;
+                    self
+                }
+            };
+        };
+        let next = params.iter().enumerate().map(|(i, p)| {
+            if i == index {
+                let marker = &markers[i];
+                quote! { ::rustratify::Set<#marker> }
+            } else {
+                quote! { #p }
+            }
+        });
+        quote! {
+            #[doc = #doc]
+            #vis fn #ident(mut self, value: #param) -> #builder<#(#next),*> {
+                self.inner.#ident = #value;
+                #builder {
+                    inner: self.inner,
+                    state: ::core::marker::PhantomData,
+                }
+            }
+        }
+    });
+    quote! {
+        #(#marker_defs)*
+
+        #[doc = #doc]
+        #vis struct #builder #generics_def {
+            inner: #name,
+            state: ::core::marker::PhantomData<fn() -> (#(#params,)*)>,
+        }
+
+        impl #name {
+            /// Start a builder from the default values.
+            #vis fn builder() -> #builder #start {
+                #builder {
+                    inner: <#name as ::core::default::Default>::default(),
+                    state: ::core::marker::PhantomData,
+                }
+            }
+        }
+
+        impl #generics #builder #generics {
+            #(#setters)*
+        }
+
+        impl ::rustratify::ConfigBuilder for #builder #complete {
+            type Config = #name;
+
+            fn build(self) -> ::core::result::Result<#name, ::std::string::String> {
+                ::rustratify::Config::validate(&self.inner)?;
+                ::core::result::Result::Ok(self.inner)
+            }
+        }
+    }
+}
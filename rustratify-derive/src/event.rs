@@ -0,0 +1,188 @@
+//! `#[derive(EventKind)]` expansion.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, Ident, LitStr, Member, Result, Variant};
+
+/// Variant-level `#[event(...)]` options.
+struct VariantAttrs {
+    terminal: bool,
+    error: bool,
+    level: Option<Ident>,
+    kind: String,
+    /// Field marked `#[event(timestamp)]`.
+    timestamp: Option<Member>,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "#[derive(EventKind)] only supports enums",
+            ))
+        }
+    };
+    let variants = data
+        .variants
+        .iter()
+        .map(parse_variant)
+        .collect::<Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let arms = |value: &dyn Fn(&VariantAttrs) -> TokenStream| -> Vec<TokenStream> {
+        data.variants
+            .iter()
+            .zip(&variants)
+            .map(|(variant, attrs)| {
+                let ident = &variant.ident;
+                let value = value(attrs);
+                quote! { Self::#ident { .. } => #value, }
+            })
+            .collect()
+    };
+    let terminal = arms(&|attrs| {
+        let terminal = attrs.terminal;
+        quote! { #terminal }
+    });
+    let error = arms(&|attrs| {
+        let error = attrs.error;
+        quote! { #error }
+    });
+    let level = arms(&|attrs| match &attrs.level {
+        Some(level) => quote! { ::core::option::Option::Some(::rustratify::stream::Level::#level) },
+        None => quote! { ::core::option::Option::None },
+    });
+    let kind = arms(&|attrs| {
+        let kind = &attrs.kind;
+        quote! { #kind }
+    });
+    let timestamp: Vec<_> = data
+        .variants
+        .iter()
+        .zip(&variants)
+        .map(|(variant, attrs)| {
+            let ident = &variant.ident;
+            match &attrs.timestamp {
+                Some(member) => quote! {
+                    Self::#ident { #member: timestamp, .. } => {
+                        ::core::option::Option::Some(*timestamp)
+                    }
+                },
+                None => quote! { Self::#ident { .. } => ::core::option::Option::None, },
+            }
+        })
+        .collect();
+    // A reference to an empty enum is not matched exhaustively by zero arms.
+    let unreachable = data
+        .variants
+        .is_empty()
+        .then(|| quote! { _ => match *self {} });
+
+    Ok(quote! {
+        impl #impl_generics ::rustratify::stream::EventKind for #name #ty_generics #where_clause {
+            fn is_terminal(&self) -> bool {
+                match self { #(#terminal)* #unreachable }
+            }
+
+            fn is_error(&self) -> bool {
+                match self { #(#error)* #unreachable }
+            }
+
+            fn level(&self) -> ::core::option::Option<::rustratify::stream::Level> {
+                match self { #(#level)* #unreachable }
+            }
+
+            fn kind(&self) -> &str {
+                match self { #(#kind)* #unreachable }
+            }
+
+            fn timestamp(&self) -> ::core::option::Option<::std::time::SystemTime> {
+                match self { #(#timestamp)* #unreachable }
+            }
+        }
+    })
+}
+
+fn parse_variant(variant: &Variant) -> Result<VariantAttrs> {
+    let mut attrs = VariantAttrs {
+        terminal: false,
+        error: false,
+        level: None,
+        kind: snake_case(&variant.ident.to_string()),
+        timestamp: None,
+    };
+    for attr in variant.attrs.iter().filter(|a| a.path().is_ident("event")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("terminal") {
+                attrs.terminal = true;
+            } else if meta.path.is_ident("error") {
+                attrs.error = true;
+            } else if meta.path.is_ident("kind") {
+                attrs.kind = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("level") {
+                let level: LitStr = meta.value()?.parse()?;
+                attrs.level = Some(match level.value().as_str() {
+                    "trace" => format_ident!("Trace"),
+                    "debug" => format_ident!("Debug"),
+                    "info" => format_ident!("Info"),
+                    "warn" => format_ident!("Warn"),
+                    "error" => format_ident!("Error"),
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            level,
+                            "expected `trace`, `debug`, `info`, `warn`, or `error`",
+                        ))
+                    }
+                });
+            } else {
+                return Err(meta.error(
+                    "unknown variant attribute, expected `terminal`, `error`, `kind`, or `level`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+    attrs.timestamp = timestamp_field(&variant.fields)?;
+    Ok(attrs)
+}
+
+/// The field of a variant marked `#[event(timestamp)]`, if any.
+fn timestamp_field(fields: &Fields) -> Result<Option<Member>> {
+    let mut found = None;
+    for (index, field) in fields.iter().enumerate() {
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("event")) {
+            attr.parse_nested_meta(|meta| {
+                if !meta.path.is_ident("timestamp") {
+                    return Err(meta.error("unknown field attribute, expected `timestamp`"));
+                }
+                if found.is_some() {
+                    return Err(meta.error("only one field can be the timestamp"));
+                }
+                found = Some(match &field.ident {
+                    Some(ident) => Member::Named(ident.clone()),
+                    None => Member::Unnamed(index.into()),
+                });
+                Ok(())
+            })?;
+        }
+    }
+    Ok(found)
+}
+
+pub(crate) fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
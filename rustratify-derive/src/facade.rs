@@ -0,0 +1,244 @@
+//! `#[sea_facade]` expansion.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::meta::ParseNestedMeta;
+use syn::punctuated::Punctuated;
+use syn::{
+    FnArg, GenericArgument, Ident, Item, ItemFn, ItemMod, Pat, Path, PathArguments, Result,
+    ReturnType, Token, Type, Visibility,
+};
+
+/// `#[sea_facade(...)]` arguments.
+#[derive(Default)]
+pub(crate) struct Args {
+    api: Option<Path>,
+    core: Option<Path>,
+    handle: Option<Ident>,
+    reexports: Vec<Path>,
+}
+
+impl Args {
+    pub(crate) fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("api") {
+            self.api = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("core") {
+            self.core = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("handle") {
+            self.handle = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("reexport") {
+            let content;
+            syn::parenthesized!(content in meta.input);
+            let paths = Punctuated::<Path, Token![,]>::parse_terminated(&content)?;
+            self.reexports.extend(paths);
+        } else {
+            return Err(meta.error("expected `api`, `core`, `handle`, or `reexport(...)`"));
+        }
+        Ok(())
+    }
+}
+
+/// How a factory function returns the core type.
+enum Returns {
+    /// `-> Core`
+    Core,
+    /// `-> SomeResult<Core, ...>`
+    Result(Box<Type>),
+}
+
+pub(crate) fn expand(args: Args, mut module: ItemMod) -> Result<TokenStream> {
+    let api = args
+        .api
+        .ok_or_else(|| syn::Error::new_spanned(&module.ident, "missing `api = Trait`"))?;
+    let core = args
+        .core
+        .ok_or_else(|| syn::Error::new_spanned(&module.ident, "missing `core = Type`"))?;
+    let handle = match args.handle {
+        Some(handle) => handle,
+        None => {
+            let api_name = &api.segments.last().expect("non-empty path").ident;
+            format_ident!("{}Handle", api_name)
+        }
+    };
+    let Some((_, items)) = module.content.take() else {
+        return Err(syn::Error::new_spanned(
+            &module,
+            "#[sea_facade] requires an inline module",
+        ));
+    };
+
+    let mut output = Vec::new();
+    for item in items {
+        match item {
+            Item::Fn(function) if matches!(function.vis, Visibility::Public(_)) => {
+                output.extend(factory(function, &core, &handle)?);
+            }
+            Item::Use(_) => output.push(item),
+            item if is_public(&item) => {
+                return Err(syn::Error::new_spanned(
+                    item,
+                    "only factory functions and `pub use` re-exports may be `pub` in a \
+                     #[sea_facade] module",
+                ));
+            }
+            item => output.push(item),
+        }
+    }
+
+    let (api_path, core_path) = (from_parent(&api), from_parent(&core));
+    let reexports = args.reexports.iter().map(from_parent);
+    let doc = format!(
+        "Handle to the `{}` implementation.\n\nConsumers use it through the API trait it \
+         dereferences to; the implementation behind it is not reachable.",
+        api.to_token_stream()
+    );
+    output.push(syn::parse_quote! { pub use #api_path; });
+    for path in reexports {
+        output.push(syn::parse_quote! { pub use #path; });
+    }
+    output.push(syn::parse_quote! {
+        #[doc = #doc]
+        pub struct #handle {
+            inner: #core_path,
+        }
+    });
+    output.push(syn::parse_quote! {
+        impl #handle {
+            /// Box the handle as an API trait object.
+            pub fn boxed(self) -> ::std::boxed::Box<dyn #api_path> {
+                ::std::boxed::Box::new(self.inner)
+            }
+        }
+    });
+    output.push(syn::parse_quote! {
+        impl ::std::ops::Deref for #handle {
+            type Target = dyn #api_path;
+
+            fn deref(&self) -> &Self::Target {
+                &self.inner
+            }
+        }
+    });
+
+    let brace = syn::token::Brace::default();
+    module.content = Some((brace, output));
+    Ok(module.into_token_stream())
+}
+
+/// Rewrite a path written in the parent module so it resolves inside the
+/// facade module.
+fn from_parent(path: &Path) -> Path {
+    let first = &path.segments.first().expect("non-empty path").ident;
+    if path.leading_colon.is_some() || first == "crate" {
+        return path.clone();
+    }
+    let mut path = path.clone();
+    if first == "self" {
+        path.segments[0].ident = format_ident!("super");
+        path
+    } else {
+        syn::parse_quote!(super::#path)
+    }
+}
+
+fn is_public(item: &Item) -> bool {
+    let vis = match item {
+        Item::Const(item) => &item.vis,
+        Item::Enum(item) => &item.vis,
+        Item::ExternCrate(item) => &item.vis,
+        Item::Fn(item) => &item.vis,
+        Item::Mod(item) => &item.vis,
+        Item::Static(item) => &item.vis,
+        Item::Struct(item) => &item.vis,
+        Item::Trait(item) => &item.vis,
+        Item::TraitAlias(item) => &item.vis,
+        Item::Type(item) => &item.vis,
+        Item::Union(item) => &item.vis,
+        _ => return false,
+    };
+    matches!(vis, Visibility::Public(_))
+}
+
+/// Split a `pub fn` returning the core type into a private function with the
+/// original body and a `pub fn` wrapping its result in the handle.
+fn factory(function: ItemFn, core: &Path, handle: &Ident) -> Result<Vec<Item>> {
+    let returns = match &function.sig.output {
+        ReturnType::Type(_, ty) => returns(ty, core, handle),
+        ReturnType::Default => None,
+    };
+    let Some(returns) = returns else {
+        return Err(syn::Error::new_spanned(
+            &function.sig,
+            "facade functions must return the core type or a `Result` of it",
+        ));
+    };
+
+    let mut args = Vec::new();
+    for input in &function.sig.inputs {
+        match input {
+            FnArg::Typed(arg) => match &*arg.pat {
+                Pat::Ident(pat) => args.push(pat.ident.clone()),
+                pat => {
+                    return Err(syn::Error::new_spanned(
+                        pat,
+                        "facade function arguments must be plain identifiers",
+                    ))
+                }
+            },
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "facade functions cannot take `self`",
+                ))
+            }
+        }
+    }
+
+    let name = &function.sig.ident;
+    let mut inner = function.clone();
+    inner.vis = Visibility::Inherited;
+    inner.sig.ident = format_ident!("__sea_facade_{}", name);
+    inner.attrs.retain(|attr| !attr.path().is_ident("doc"));
+    let inner_name = &inner.sig.ident;
+
+    let call = match function.sig.asyncness {
+        Some(_) => quote! { #inner_name(#(#args),*).await },
+        None => quote! { #inner_name(#(#args),*) },
+    };
+    let mut outer = function;
+    let (output, body) = match returns {
+        Returns::Core => (quote! { #handle }, quote! { #handle { inner: #call } }),
+        Returns::Result(ty) => (
+            ty.into_token_stream(),
+            quote! { #call.map(|inner| #handle { inner }) },
+        ),
+    };
+    outer.sig.output = syn::parse_quote! { -> #output };
+    outer.block = syn::parse_quote! {{ #body }};
+    Ok(vec![Item::Fn(inner), Item::Fn(outer)])
+}
+
+/// Match `ty` against `core` or `...Result<core, ...>`, returning the
+/// type with `core` replaced by `handle` in the latter case.
+fn returns(ty: &Type, core: &Path, handle: &Ident) -> Option<Returns> {
+    let core = core.to_token_stream().to_string();
+    let Type::Path(path) = ty else { return None };
+    if path.qself.is_none() && path.path.to_token_stream().to_string() == core {
+        return Some(Returns::Core);
+    }
+    let mut ty = path.clone();
+    let last = ty.path.segments.last_mut()?;
+    if !last.ident.to_string().ends_with("Result") {
+        return None;
+    }
+    let PathArguments::AngleBracketed(generics) = &mut last.arguments else {
+        return None;
+    };
+    match generics.args.first_mut()? {
+        GenericArgument::Type(Type::Path(ok)) if ok.to_token_stream().to_string() == core => {
+            *ok = syn::parse_quote!(#handle);
+        }
+        _ => return None,
+    }
+    Some(Returns::Result(Box::new(Type::Path(ty))))
+}
@@ -0,0 +1,188 @@
+//! Derive and attribute macros for Rustratify.
+//!
+//! Use these through the `derive` feature of the `rustratify` crate rather
+//! than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ItemMod, ItemTrait};
+
+mod config;
+mod event;
+mod facade;
+mod spi;
+mod spi_trait;
+
+/// Derive `Config`, `MergeableConfig`, `FromEnv`, `Default`, and a builder.
+///
+/// See `rustratify::Config` for the supported `#[config(...)]` attributes.
+#[proc_macro_derive(Config, attributes(config))]
+pub fn derive_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    config::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `EventKind` for an event enum.
+///
+/// Variants are classified with `#[event(...)]` attributes: `terminal`,
+/// `error`, `level = "info"` (any of `trace`, `debug`, `info`, `warn`,
+/// `error`), and `kind = "name"` to override the default snake_case
+/// variant name. Mark a `SystemTime` field `#[event(timestamp)]` to report
+/// it from `EventKind::timestamp`.
+///
+/// ```rust,ignore
+/// #[derive(EventKind)]
+/// enum JobEvent {
+///     #[event(level = "debug")]
+///     Progress(u32),
+///     #[event(terminal)]
+///     Completed { #[event(timestamp)] at: SystemTime },
+///     #[event(terminal, error)]
+///     Failed(String),
+/// }
+/// ```
+#[proc_macro_derive(EventKind, attributes(event))]
+pub fn derive_event_kind(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    event::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `EventKind`, and with it `Event`, for an event enum.
+///
+/// Takes the same `#[event(...)]` attributes as `#[derive(EventKind)]`;
+/// derive one or the other.
+///
+/// ```rust,ignore
+/// #[derive(Event)]
+/// enum ProcessorEvent {
+///     Processed(PathBuf),
+///     #[event(terminal)]
+///     Completed,
+///     #[event(terminal, error)]
+///     Failed(String),
+/// }
+/// ```
+#[proc_macro_derive(Event, attributes(event))]
+pub fn derive_event(input: TokenStream) -> TokenStream {
+    derive_event_kind(input)
+}
+
+/// Generate the L5 facade of a SEA module from an inline module of factory
+/// functions.
+///
+/// `api` names the L3 API trait and `core` the L4 type implementing it,
+/// both as paths seen from the parent module. Every `pub fn` in the module
+/// must return `core` (or a `Result` of it); it is rewritten to return a
+/// sealed handle instead, named `handle` or `<Api>Handle`, which
+/// dereferences to `dyn Api` and gives no access to the core type. The
+/// module re-exports the API trait and any paths listed in `reexport(...)`,
+/// also seen from the parent module.
+/// Any other `pub` item in the module is a compile error, so the facade
+/// cannot leak internals.
+///
+/// ```rust,ignore
+/// #[sea_facade(api = FileProcessor, core = DefaultFileProcessor, reexport(ProcessorConfig))]
+/// pub mod facade {
+///     use super::*;
+///
+///     /// Create a file processor with the default providers.
+///     pub fn create_processor() -> DefaultFileProcessor {
+///         DefaultFileProcessor::new(default_registry())
+///     }
+/// }
+///
+/// let processor: facade::FileProcessorHandle = facade::create_processor();
+/// processor.process(paths, config).await?;
+/// ```
+#[proc_macro_attribute]
+pub fn sea_facade(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut attrs = facade::Args::default();
+    let parser = syn::meta::parser(|meta| attrs.parse(meta));
+    parse_macro_input!(args with parser);
+    let module = parse_macro_input!(item as ItemMod);
+    facade::expand(attrs, module)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Declare a provider trait with native `async fn` methods, plus a
+/// dyn-compatible twin for trait objects.
+///
+/// Each `async fn` in the trait becomes a method returning
+/// `impl Future<Output = T> + Send`, so statically dispatched calls cost no
+/// allocation. The attribute also generates `Dyn<Trait>` (or the name given
+/// by `dyn_trait = Name`), with the same supertraits and the async methods
+/// returning boxed futures, implemented for every type implementing the
+/// trait. Use the dyn trait where a trait object is needed, such as
+/// `Registry<dyn DynTrait>`.
+///
+/// Methods must take `&self` or `&mut self` and cannot have type
+/// parameters. In the dyn trait, elided and `'_` lifetimes are tied to the
+/// boxed future; lifetimes hidden in paths, such as `Cow<str>`, must be
+/// written as `'_`. Both traits have the same methods, so import only the
+/// one a module calls through; with both in scope, method calls on a
+/// concrete type are ambiguous.
+///
+/// ```rust,ignore
+/// #[spi]
+/// pub trait Formatter: Provider {
+///     async fn format(&self, source: &str) -> ProviderResult<String>;
+/// }
+///
+/// async fn run(formatter: &impl Formatter) {
+///     formatter.format("fn main() {}").await; // no boxing
+/// }
+///
+/// let registry: Registry<dyn DynFormatter> = Registry::new();
+/// ```
+#[proc_macro_attribute]
+pub fn spi(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut attrs = spi::Args::default();
+    let parser = syn::meta::parser(|meta| attrs.parse(meta));
+    parse_macro_input!(args with parser);
+    let item = parse_macro_input!(item as ItemTrait);
+    spi::expand(attrs, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generate the registry plumbing for a provider trait.
+///
+/// For a dyn-compatible trait extending `Provider`, such as one declared
+/// with `#[async_trait]`, generates:
+///
+/// - `<Trait>Registry`, an alias of `Registry<dyn Trait>`
+/// - `register_<trait>(&mut registry, provider)`, boxing the provider
+/// - `Cloneable<Trait>`, a subtrait with `clone_box` implemented for every
+///   `Clone` implementation, and `Clone` for its boxes
+/// - `From<Box<T>>` for `Box<dyn Trait>` and `Box<dyn Cloneable<Trait>>`,
+///   for `RegistryBuilder::with_default`
+/// - `is::<T>()` and `downcast_ref::<T>()` on `dyn Trait`
+///
+/// The names can be set with `registry = Name`, `register = name`, and
+/// `cloneable = Name`. Generated items share the trait's visibility.
+///
+/// ```rust,ignore
+/// #[spi_trait(registry = ProcessorRegistry)]
+/// #[async_trait]
+/// pub trait FileProcessorProvider: Provider {
+///     async fn process_file(&self, path: &Path) -> ProcessorResult<ProcessedFile>;
+/// }
+///
+/// let mut registry = ProcessorRegistry::new();
+/// register_file_processor_provider(&mut registry, RustProcessor);
+/// assert!(registry.get("rust").unwrap().is::<RustProcessor>());
+/// ```
+#[proc_macro_attribute]
+pub fn spi_trait(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut attrs = spi_trait::Args::default();
+    let parser = syn::meta::parser(|meta| attrs.parse(meta));
+    parse_macro_input!(args with parser);
+    let item = parse_macro_input!(item as ItemTrait);
+    spi_trait::expand(attrs, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
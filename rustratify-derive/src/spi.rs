@@ -0,0 +1,204 @@
+//! `#[spi]` expansion.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    FnArg, GenericParam, Ident, ItemTrait, Lifetime, Result, ReturnType, TraitItem, TraitItemFn,
+    Type, TypeReference,
+};
+
+/// `#[spi(...)]` arguments.
+#[derive(Default)]
+pub(crate) struct Args {
+    dyn_trait: Option<Ident>,
+}
+
+impl Args {
+    pub(crate) fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("dyn_trait") {
+            self.dyn_trait = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("expected `dyn_trait = Name`"))
+        }
+    }
+}
+
+pub(crate) fn expand(args: Args, mut item: ItemTrait) -> Result<TokenStream> {
+    if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "#[spi] traits cannot be generic",
+        ));
+    }
+    let name = &item.ident;
+    let dyn_name = args
+        .dyn_trait
+        .unwrap_or_else(|| format_ident!("Dyn{}", name));
+
+    let mut dyn_methods = Vec::new();
+    let mut forwards = Vec::new();
+    for trait_item in &mut item.items {
+        let TraitItem::Fn(method) = trait_item else {
+            return Err(syn::Error::new_spanned(
+                trait_item,
+                "#[spi] traits may only contain methods",
+            ));
+        };
+        let (dyn_method, forward) = dyn_method(name, method)?;
+        dyn_methods.push(dyn_method);
+        forwards.push(forward);
+        if method.sig.asyncness.is_some() {
+            make_native(method);
+        }
+    }
+
+    let vis = &item.vis;
+    let supertraits = &item.supertraits;
+    let colon = item.colon_token;
+    let doc = format!(
+        "Object-safe form of [`{name}`], for trait objects such as \
+         `Registry<dyn {dyn_name}>`.\n\nImplemented for every `{name}`; its async \
+         methods box the futures of the `{name}` methods they call."
+    );
+    Ok(quote! {
+        #item
+
+        #[doc = #doc]
+        #vis trait #dyn_name #colon #supertraits {
+            #(#dyn_methods)*
+        }
+
+        impl<T: #name> #dyn_name for T {
+            #(#forwards)*
+        }
+    })
+}
+
+/// Rewrite `async fn f(..) -> T` to `fn f(..) -> impl Future<Output = T> + Send`,
+/// so implementations must return `Send` futures and callers can rely on it.
+fn make_native(method: &mut TraitItemFn) {
+    method.sig.asyncness = None;
+    let output = output_type(&method.sig.output);
+    method.sig.output = syn::parse_quote! {
+        -> impl ::core::future::Future<Output = #output> + ::core::marker::Send
+    };
+    if let Some(block) = &method.default {
+        method.default = Some(syn::parse_quote! {{ async move #block }});
+    }
+}
+
+fn output_type(output: &ReturnType) -> Type {
+    match output {
+        ReturnType::Default => syn::parse_quote!(()),
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    }
+}
+
+/// The method as declared in the dyn trait, and its forwarding
+/// implementation.
+fn dyn_method(name: &Ident, method: &TraitItemFn) -> Result<(TokenStream, TokenStream)> {
+    let mut sig = method.sig.clone();
+    if let Some(param) = sig
+        .generics
+        .params
+        .iter()
+        .find(|param| !matches!(param, GenericParam::Lifetime(_)))
+    {
+        return Err(syn::Error::new_spanned(
+            param,
+            "generic methods cannot be called through `dyn`; use a lifetime or a concrete type",
+        ));
+    }
+    match sig.inputs.first() {
+        Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() => {}
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &sig,
+                "#[spi] methods must take `&self` or `&mut self`",
+            ))
+        }
+    }
+
+    let mut args = Vec::new();
+    for (i, input) in sig.inputs.iter_mut().skip(1).enumerate() {
+        if let FnArg::Typed(arg) = input {
+            if let Type::ImplTrait(ty) = &*arg.ty {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "`impl Trait` arguments cannot be called through `dyn`",
+                ));
+            }
+            let ident = format_ident!("arg{}", i);
+            arg.pat = syn::parse_quote!(#ident);
+            args.push(ident);
+        }
+    }
+
+    let method_name = &sig.ident;
+    let call = quote! { <T as #name>::#method_name(self, #(#args),*) };
+    if sig.asyncness.take().is_none() {
+        let attrs = &method.attrs;
+        return Ok((quote! { #(#attrs)* #sig; }, quote! { #sig { #call } }));
+    }
+
+    // Tie every borrow to one lifetime that the boxed future may capture
+    let spi = Lifetime::new("'spi", Span::call_site());
+    let outlives = sig.generics.lifetimes().map(|param| {
+        let lifetime = &param.lifetime;
+        quote! { #lifetime: #spi }
+    });
+    let outlives: Vec<_> = outlives.collect();
+    sig.generics.params.insert(0, syn::parse_quote!(#spi));
+    if !outlives.is_empty() {
+        let where_clause = sig.generics.make_where_clause();
+        where_clause.predicates.extend(
+            outlives
+                .into_iter()
+                .map(|predicate| -> syn::WherePredicate { syn::parse_quote!(#predicate) }),
+        );
+    }
+    if let Some(FnArg::Receiver(receiver)) = sig.inputs.first_mut() {
+        if let Some((_, lifetime)) = &mut receiver.reference {
+            lifetime.get_or_insert_with(|| spi.clone());
+        }
+        if let Type::Reference(ty) = &mut *receiver.ty {
+            ty.lifetime.get_or_insert_with(|| spi.clone());
+        }
+    }
+    let mut elided = NameElided(&spi);
+    for input in sig.inputs.iter_mut().skip(1) {
+        elided.visit_fn_arg_mut(input);
+    }
+    let mut output = output_type(&sig.output);
+    elided.visit_type_mut(&mut output);
+    sig.output = syn::parse_quote! {
+        -> ::core::pin::Pin<::std::boxed::Box<
+            dyn ::core::future::Future<Output = #output> + ::core::marker::Send + #spi
+        >>
+    };
+
+    let attrs = &method.attrs;
+    Ok((
+        quote! { #(#attrs)* #sig; },
+        quote! { #sig { ::std::boxed::Box::pin(#call) } },
+    ))
+}
+
+/// Replaces elided and `'_` lifetimes with the given one.
+struct NameElided<'a>(&'a Lifetime);
+
+impl VisitMut for NameElided<'_> {
+    fn visit_type_reference_mut(&mut self, ty: &mut TypeReference) {
+        ty.lifetime.get_or_insert_with(|| self.0.clone());
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = self.0.clone();
+        }
+    }
+}
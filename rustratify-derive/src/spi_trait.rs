@@ -0,0 +1,115 @@
+//! `#[spi_trait]` expansion.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::{Ident, ItemTrait, Result};
+
+use crate::event::snake_case;
+
+/// `#[spi_trait(...)]` arguments.
+#[derive(Default)]
+pub(crate) struct Args {
+    registry: Option<Ident>,
+    register: Option<Ident>,
+    cloneable: Option<Ident>,
+}
+
+impl Args {
+    pub(crate) fn parse(&mut self, meta: ParseNestedMeta) -> Result<()> {
+        if meta.path.is_ident("registry") {
+            self.registry = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("register") {
+            self.register = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("cloneable") {
+            self.cloneable = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `registry`, `register`, or `cloneable`"));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn expand(args: Args, item: ItemTrait) -> Result<TokenStream> {
+    if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "#[spi_trait] traits cannot be generic",
+        ));
+    }
+    let name = &item.ident;
+    let vis = &item.vis;
+    let registry = args
+        .registry
+        .unwrap_or_else(|| format_ident!("{}Registry", name));
+    let register = args
+        .register
+        .unwrap_or_else(|| format_ident!("register_{}", snake_case(&name.to_string())));
+    let cloneable = args
+        .cloneable
+        .unwrap_or_else(|| format_ident!("Cloneable{}", name));
+
+    let registry_doc = format!("A registry of [`{name}`] providers.");
+    let register_doc = format!("Register `provider` in a [`{registry}`].");
+    let cloneable_doc = format!(
+        "A [`{name}`] that can be cloned behind a trait object.\n\n\
+         Implemented for every `{name}` that is `Clone`."
+    );
+    Ok(quote! {
+        #item
+
+        #[doc = #registry_doc]
+        #vis type #registry = ::rustratify::Registry<dyn #name>;
+
+        #[doc = #register_doc]
+        #vis fn #register<T: #name + 'static>(registry: &mut #registry, provider: T) {
+            registry.register(::std::boxed::Box::new(provider));
+        }
+
+        #[doc = #cloneable_doc]
+        #vis trait #cloneable: #name {
+            /// Clone the provider into a boxed trait object.
+            fn clone_box(&self) -> ::std::boxed::Box<dyn #cloneable>;
+        }
+
+        impl<T: #name + ::core::clone::Clone + 'static> #cloneable for T {
+            fn clone_box(&self) -> ::std::boxed::Box<dyn #cloneable> {
+                ::std::boxed::Box::new(::core::clone::Clone::clone(self))
+            }
+        }
+
+        impl ::core::clone::Clone for ::std::boxed::Box<dyn #cloneable> {
+            fn clone(&self) -> Self {
+                (**self).clone_box()
+            }
+        }
+
+        impl<T: #name + 'static> ::core::convert::From<::std::boxed::Box<T>>
+            for ::std::boxed::Box<dyn #name>
+        {
+            fn from(provider: ::std::boxed::Box<T>) -> Self {
+                provider
+            }
+        }
+
+        impl<T: #name + ::core::clone::Clone + 'static> ::core::convert::From<::std::boxed::Box<T>>
+            for ::std::boxed::Box<dyn #cloneable>
+        {
+            fn from(provider: ::std::boxed::Box<T>) -> Self {
+                provider
+            }
+        }
+
+        impl dyn #name {
+            /// Whether this provider is a `T`.
+            #vis fn is<T: #name + 'static>(&self) -> bool {
+                ::rustratify::Provider::as_any(self).is::<T>()
+            }
+
+            /// This provider as a `T`, if it is one.
+            #vis fn downcast_ref<T: #name + 'static>(&self) -> ::core::option::Option<&T> {
+                ::rustratify::Provider::as_any(self).downcast_ref::<T>()
+            }
+        }
+    })
+}
@@ -0,0 +1,434 @@
+//! SEA layer dependency checks for Cargo workspaces.
+//!
+//! [`validate`] reads a workspace manifest and its members, assigns each
+//! crate a [`Layer`], and reports every dependency that points from a lower
+//! layer to a higher one, such as `*-common` depending on `*-core`.
+//!
+//! Layers come from crate name suffixes: `-common`, `-spi`, `-api`, and
+//! `-core` (or `_common` and so on). A crate `foo` is the facade when the
+//! workspace also has `foo-<layer>` crates. Anything else is unclassified
+//! and not checked, unless its manifest sets the layer explicitly:
+//!
+//! ```toml
+//! [package.metadata.rustratify]
+//! layer = "core"
+//! ```
+//!
+//! The same check runs from the command line as `cargo rustratify check`,
+//! installed with the `arch` feature.
+//!
+//! Requires the `arch` feature.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! let report = rustratify::arch::validate("Cargo.toml").unwrap();
+//! for violation in &report.violations {
+//!     eprintln!("{violation}");
+//! }
+//! assert!(report.is_ok());
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+/// A SEA layer, ordered from the bottom (L1) up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    /// L1: DTOs, models, errors
+    Common = 1,
+    /// L2: provider interfaces
+    Spi = 2,
+    /// L3: consumer contracts
+    Api = 3,
+    /// L4: implementation
+    Core = 4,
+    /// L5: consumer entry point
+    Facade = 5,
+}
+
+impl Layer {
+    const SUFFIXED: [Layer; 4] = [Layer::Common, Layer::Spi, Layer::Api, Layer::Core];
+
+    /// The lowercase name, e.g. `core`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Common => "common",
+            Self::Spi => "spi",
+            Self::Api => "api",
+            Self::Core => "core",
+            Self::Facade => "facade",
+        }
+    }
+
+    /// The layer number, 1 to 5.
+    pub fn level(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The crate name without its layer suffix, and the layer, if the name
+    /// ends in one.
+    fn split_suffix(name: &str) -> Option<(&str, Layer)> {
+        Self::SUFFIXED.into_iter().find_map(|layer| {
+            let base = name
+                .strip_suffix(layer.as_str())?
+                .strip_suffix(['-', '_'])?;
+            (!base.is_empty()).then_some((base, layer))
+        })
+    }
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L{} {}", self.level(), self.as_str())
+    }
+}
+
+impl FromStr for Layer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "common" => Ok(Self::Common),
+            "spi" => Ok(Self::Spi),
+            "api" => Ok(Self::Api),
+            "core" => Ok(Self::Core),
+            "facade" => Ok(Self::Facade),
+            _ => Err(format!(
+                "unknown layer `{s}`: expected common, spi, api, core, or facade"
+            )),
+        }
+    }
+}
+
+/// A workspace crate and its layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateInfo {
+    /// Package name
+    pub name: String,
+    /// Layer, or `None` if the crate is unclassified
+    pub layer: Option<Layer>,
+    /// Path to the crate's manifest
+    pub manifest: PathBuf,
+}
+
+/// A dependency from a lower layer on a higher one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The depending crate
+    pub from: String,
+    /// Its layer
+    pub from_layer: Layer,
+    /// The crate depended on
+    pub to: String,
+    /// Its layer
+    pub to_layer: Layer,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}) depends on {} ({}), a higher layer",
+            self.from, self.from_layer, self.to, self.to_layer
+        )
+    }
+}
+
+/// The result of [`validate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchReport {
+    /// Every workspace crate, sorted by name
+    pub crates: Vec<CrateInfo>,
+    /// Upward dependencies, sorted by depending crate
+    pub violations: Vec<Violation>,
+}
+
+impl ArchReport {
+    /// Whether no crate depends upward.
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// The crate named `name`.
+    pub fn get(&self, name: &str) -> Option<&CrateInfo> {
+        self.crates.iter().find(|c| c.name == name)
+    }
+}
+
+impl fmt::Display for ArchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for info in &self.crates {
+            match info.layer {
+                Some(layer) => writeln!(f, "{:<10} {}", layer.to_string(), info.name)?,
+                None => writeln!(f, "{:<10} {}", "-", info.name)?,
+            }
+        }
+        for violation in &self.violations {
+            writeln!(f, "error: {violation}")?;
+        }
+        Ok(())
+    }
+}
+
+struct Package {
+    info: CrateInfo,
+    /// Names of packages depended on, renames resolved
+    dependencies: BTreeSet<String>,
+}
+
+/// Check the layering of the workspace whose root manifest is at
+/// `workspace_manifest`.
+///
+/// Normal and build dependencies, including target-specific ones, are
+/// checked; dev-dependencies are not, so tests may use any layer. Only
+/// dependencies between workspace members are considered. Errors are
+/// unreadable or malformed manifests.
+pub fn validate(workspace_manifest: impl AsRef<Path>) -> Result<ArchReport, String> {
+    let root_manifest = workspace_manifest.as_ref();
+    let root = read_manifest(root_manifest)?;
+    let root_dir = root_manifest.parent().unwrap_or(Path::new(""));
+
+    let mut manifests = Vec::new();
+    if root.contains_key("package") {
+        manifests.push(root_manifest.to_path_buf());
+    }
+    if let Some(workspace) = root.get("workspace").and_then(Value::as_table) {
+        let excluded: Vec<PathBuf> = strings(workspace.get("exclude"))
+            .map(|dir| root_dir.join(dir))
+            .collect();
+        for member in strings(workspace.get("members")) {
+            for dir in expand_member(root_dir, member)? {
+                if !excluded.contains(&dir) {
+                    manifests.push(dir.join("Cargo.toml"));
+                }
+            }
+        }
+    }
+
+    let mut packages = Vec::new();
+    for path in manifests {
+        let manifest = if path == root_manifest {
+            root.clone()
+        } else {
+            read_manifest(&path)?
+        };
+        packages.push(package(&manifest, path)?);
+    }
+    Ok(check(packages))
+}
+
+fn check(mut packages: Vec<Package>) -> ArchReport {
+    packages.sort_by(|a, b| a.info.name.cmp(&b.info.name));
+    let names: BTreeSet<String> = packages.iter().map(|p| p.info.name.clone()).collect();
+    let bases: BTreeSet<&str> = names
+        .iter()
+        .filter_map(|name| Layer::split_suffix(name).map(|(base, _)| base))
+        .collect();
+    let facades: Vec<String> = names
+        .iter()
+        .filter(|name| bases.contains(name.as_str()))
+        .cloned()
+        .collect();
+    for package in &mut packages {
+        if package.info.layer.is_none() && facades.contains(&package.info.name) {
+            package.info.layer = Some(Layer::Facade);
+        }
+    }
+
+    let layers: BTreeMap<&str, Layer> = packages
+        .iter()
+        .filter_map(|p| Some((p.info.name.as_str(), p.info.layer?)))
+        .collect();
+    let mut violations = Vec::new();
+    for package in &packages {
+        let Some(from_layer) = package.info.layer else {
+            continue;
+        };
+        for dependency in &package.dependencies {
+            match layers.get(dependency.as_str()) {
+                Some(&to_layer) if to_layer > from_layer => violations.push(Violation {
+                    from: package.info.name.clone(),
+                    from_layer,
+                    to: dependency.clone(),
+                    to_layer,
+                }),
+                _ => {}
+            }
+        }
+    }
+    ArchReport {
+        crates: packages.into_iter().map(|p| p.info).collect(),
+        violations,
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<Table, String> {
+    let input =
+        fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    input
+        .parse::<Table>()
+        .map_err(|e| format!("failed to parse {}: {e}", path.display()))
+}
+
+fn strings(value: Option<&Value>) -> impl Iterator<Item = &str> {
+    value
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+}
+
+/// Expand a `members` entry; a trailing `/*` matches every subdirectory
+/// with a manifest.
+fn expand_member(root: &Path, member: &str) -> Result<Vec<PathBuf>, String> {
+    let Some(parent) = member.strip_suffix("/*") else {
+        return Ok(vec![root.join(member)]);
+    };
+    let parent = root.join(parent);
+    let entries =
+        fs::read_dir(&parent).map_err(|e| format!("failed to read {}: {e}", parent.display()))?;
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|dir| dir.join("Cargo.toml").is_file())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn package(manifest: &Table, path: PathBuf) -> Result<Package, String> {
+    let package = manifest
+        .get("package")
+        .and_then(Value::as_table)
+        .ok_or_else(|| format!("{} has no [package] section", path.display()))?;
+    let name = package
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("{} has no package name", path.display()))?
+        .to_string();
+    let declared = package
+        .get("metadata")
+        .and_then(|m| m.get("rustratify"))
+        .and_then(|m| m.get("layer"))
+        .and_then(Value::as_str);
+    let layer = match declared {
+        Some(layer) => Some(
+            layer
+                .parse()
+                .map_err(|e| format!("{}: {e}", path.display()))?,
+        ),
+        None => Layer::split_suffix(&name).map(|(_, layer)| layer),
+    };
+
+    let mut tables: Vec<&Table> = Vec::new();
+    for key in ["dependencies", "build-dependencies"] {
+        tables.extend(manifest.get(key).and_then(Value::as_table));
+        let targets = manifest.get("target").and_then(Value::as_table);
+        for target in targets.into_iter().flat_map(|t| t.values()) {
+            tables.extend(target.get(key).and_then(Value::as_table));
+        }
+    }
+    let dependencies = tables
+        .into_iter()
+        .flatten()
+        .map(|(key, spec)| {
+            spec.get("package")
+                .and_then(Value::as_str)
+                .unwrap_or(key)
+                .to_string()
+        })
+        .collect();
+
+    Ok(Package {
+        info: CrateInfo {
+            name,
+            layer,
+            manifest: path,
+        },
+        dependencies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(name: &str, crates: &[(&str, &str)]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("rustratify-arch-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (dir, manifest) in crates {
+            let dir = root.join(dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+        }
+        root.join("Cargo.toml")
+    }
+
+    fn manifest(name: &str, deps: &str) -> String {
+        format!("[package]\nname = \"{name}\"\n\n[dependencies]\n{deps}\n")
+    }
+
+    #[test]
+    fn test_layers_from_names() {
+        assert_eq!(
+            Layer::split_suffix("proc-core"),
+            Some(("proc", Layer::Core))
+        );
+        assert_eq!(Layer::split_suffix("proc_spi"), Some(("proc", Layer::Spi)));
+        assert_eq!(Layer::split_suffix("score"), None);
+        assert_eq!(Layer::split_suffix("-api"), None);
+        assert_eq!("Facade".parse(), Ok(Layer::Facade));
+        assert_eq!(Layer::Api.to_string(), "L3 api");
+    }
+
+    #[test]
+    fn test_validate_reports_upward_dependencies() {
+        let path = workspace(
+            "upward",
+            &[
+                ("", "[workspace]\nmembers = [\"crates/*\", \"tools\"]\n"),
+                ("crates/proc-common", &manifest("proc-common", "proc-core = { path = \"../proc-core\" }")),
+                ("crates/proc-spi", &manifest("proc-spi", "proc-common = { path = \"../proc-common\" }\nserde = \"1\"")),
+                ("crates/proc-core", &manifest("proc-core", "spi = { package = \"proc-spi\", path = \"../proc-spi\" }")),
+                ("crates/proc", &manifest("proc", "proc-core = { path = \"../proc-core\" }")),
+                ("tools", "[package]\nname = \"tools\"\n[package.metadata.rustratify]\nlayer = \"spi\"\n[dev-dependencies]\nproc = { path = \"../crates/proc\" }\n[target.'cfg(unix)'.dependencies]\nproc-core = { path = \"../crates/proc-core\" }\n"),
+            ],
+        );
+        let report = validate(&path).unwrap();
+        assert_eq!(report.crates.len(), 5);
+        assert_eq!(report.get("proc").unwrap().layer, Some(Layer::Facade));
+        let violations: Vec<(&str, &str)> = report
+            .violations
+            .iter()
+            .map(|v| (v.from.as_str(), v.to.as_str()))
+            .collect();
+        assert_eq!(
+            violations,
+            vec![("proc-common", "proc-core"), ("tools", "proc-core")]
+        );
+        assert!(!report.is_ok());
+        assert!(report.to_string().contains(
+            "error: proc-common (L1 common) depends on proc-core (L4 core), a higher layer"
+        ));
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        let path = workspace("bad", &[("", "[workspace]\nmembers = [\"missing\"]\n")]);
+        assert!(validate(&path).unwrap_err().contains("failed to read"));
+        let path = workspace(
+            "layer",
+            &[(
+                "",
+                "[package]\nname = \"x\"\n[package.metadata.rustratify]\nlayer = \"top\"\n",
+            )],
+        );
+        assert!(validate(&path).unwrap_err().contains("unknown layer `top`"));
+    }
+}
@@ -0,0 +1,521 @@
+//! Append-only structured records of significant actions.
+//!
+//! An [`AuditLog`] stamps each [`AuditRecord`] with a sequence number and
+//! timestamp and appends it to every configured [`AuditSink`]. Sinks only
+//! ever append; nothing in this module edits or removes a record.
+//!
+//! Built-in sinks are [`MemorySink`] and, with the `serde` feature,
+//! [`JsonLinesSink`], which writes one JSON object per line to stdout, a
+//! file, or any writer. Registries record provider registration and removal
+//! when given a log with [`Registry::with_audit`](crate::Registry::with_audit).
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::audit::{AuditAction, AuditLog, AuditRecord, MemorySink};
+//!
+//! let sink = MemorySink::new();
+//! let log = AuditLog::new().with_sink(sink.clone());
+//!
+//! log.run_started("run-1").unwrap();
+//! log.record(AuditRecord::new(AuditAction::RunCancelled, "run-1").actor("alice"))
+//!     .unwrap();
+//!
+//! let records = sink.records();
+//! assert_eq!(records[1].sequence, 1);
+//! assert_eq!(records[1].actor.as_deref(), Some("alice"));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::config::ConfigDiff;
+use crate::context::Context;
+use crate::error::{FieldValue, MultiError, ProviderResult};
+
+/// What happened.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", from = "String")
+)]
+pub enum AuditAction {
+    /// A provider was added to a registry (`provider.registered`)
+    ProviderRegistered,
+    /// A provider was removed from a registry (`provider.removed`)
+    ProviderRemoved,
+    /// A run started (`run.started`)
+    RunStarted,
+    /// A run was cancelled (`run.cancelled`)
+    RunCancelled,
+    /// Configuration changed (`config.changed`)
+    ConfigChanged,
+    /// An application-defined action
+    Other(String),
+}
+
+impl AuditAction {
+    /// The dotted name of the action, e.g. `provider.registered`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::ProviderRegistered => "provider.registered",
+            Self::ProviderRemoved => "provider.removed",
+            Self::RunStarted => "run.started",
+            Self::RunCancelled => "run.cancelled",
+            Self::ConfigChanged => "config.changed",
+            Self::Other(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<String> for AuditAction {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "provider.registered" => Self::ProviderRegistered,
+            "provider.removed" => Self::ProviderRemoved,
+            "run.started" => Self::RunStarted,
+            "run.cancelled" => Self::RunCancelled,
+            "config.changed" => Self::ConfigChanged,
+            _ => Self::Other(name),
+        }
+    }
+}
+
+impl From<AuditAction> for String {
+    fn from(action: AuditAction) -> Self {
+        match action {
+            AuditAction::Other(name) => name,
+            action => action.as_str().to_string(),
+        }
+    }
+}
+
+/// One audited action.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditRecord {
+    /// Position in the log, assigned by [`AuditLog::record`]
+    pub sequence: u64,
+    /// When the action was recorded
+    pub timestamp: SystemTime,
+    /// What happened
+    pub action: AuditAction,
+    /// What it happened to, e.g. a provider name or run ID
+    pub target: String,
+    /// Who did it, if known
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub actor: Option<String>,
+    /// The run it happened in, if any
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub run_id: Option<String>,
+    /// The request it happened for, if any
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub correlation_id: Option<String>,
+    /// Further details
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub details: BTreeMap<String, FieldValue>,
+}
+
+impl AuditRecord {
+    /// Create a record of `action` on `target`.
+    pub fn new(action: AuditAction, target: impl Into<String>) -> Self {
+        Self {
+            sequence: 0,
+            timestamp: SystemTime::now(),
+            action,
+            target: target.into(),
+            actor: None,
+            run_id: None,
+            correlation_id: None,
+            details: BTreeMap::new(),
+        }
+    }
+
+    /// Set who performed the action.
+    pub fn actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    /// Set the run the action belongs to.
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Set the request the action belongs to.
+    pub fn correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Set the actor, run and request from `cx`, where it has them.
+    ///
+    /// The context's subject becomes the actor.
+    pub fn context(mut self, cx: &Context) -> Self {
+        if let Some(subject) = cx.subject() {
+            self.actor = Some(subject.to_string());
+        }
+        if let Some(run_id) = cx.run_id() {
+            self.run_id = Some(run_id.to_string());
+        }
+        if let Some(correlation_id) = cx.correlation_id() {
+            self.correlation_id = Some(correlation_id.to_string());
+        }
+        self
+    }
+
+    /// Add a detail.
+    pub fn detail(mut self, key: impl Into<String>, value: impl Into<FieldValue>) -> Self {
+        self.details.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// Destination for audit records.
+///
+/// Implementations must only append: a record, once accepted, is never
+/// changed or removed by the sink.
+pub trait AuditSink: Send + Sync {
+    /// Sink name, used to label failures.
+    fn name(&self) -> &str;
+
+    /// Append `record`.
+    fn append(&self, record: &AuditRecord) -> ProviderResult<()>;
+
+    /// Make appended records durable. Default does nothing.
+    fn flush(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+}
+
+/// A shared audit log writing to one or more sinks.
+///
+/// Cloning is cheap; clones share the sequence counter, so sequence numbers
+/// are unique and increasing across the application.
+#[derive(Clone, Default)]
+pub struct AuditLog {
+    sinks: Arc<Vec<Arc<dyn AuditSink>>>,
+    sequence: Arc<AtomicU64>,
+    actor: Option<String>,
+}
+
+impl AuditLog {
+    /// Create a log with no sinks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sink.
+    pub fn with_sink(mut self, sink: impl AuditSink + 'static) -> Self {
+        Arc::make_mut(&mut self.sinks).push(Arc::new(sink));
+        self
+    }
+
+    /// Set the actor for records that do not name one, e.g. the service
+    /// account the application runs as.
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    /// Append `record` to every sink.
+    ///
+    /// Every sink is tried even if an earlier one fails; the failures are
+    /// returned together, labelled by sink name.
+    pub fn record(&self, mut record: AuditRecord) -> Result<(), MultiError> {
+        record.sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
+        if record.actor.is_none() {
+            record.actor = self.actor.clone();
+        }
+        let mut errors = MultiError::new();
+        for sink in self.sinks.iter() {
+            if let Err(err) = sink.append(&record) {
+                errors.push(sink.name(), err);
+            }
+        }
+        errors.into_result()
+    }
+
+    /// Flush every sink.
+    pub fn flush(&self) -> Result<(), MultiError> {
+        let mut errors = MultiError::new();
+        for sink in self.sinks.iter() {
+            if let Err(err) = sink.flush() {
+                errors.push(sink.name(), err);
+            }
+        }
+        errors.into_result()
+    }
+
+    /// Record that `provider` was registered.
+    pub fn provider_registered(&self, provider: &str) -> Result<(), MultiError> {
+        self.record(AuditRecord::new(AuditAction::ProviderRegistered, provider))
+    }
+
+    /// Record that `provider` was removed.
+    pub fn provider_removed(&self, provider: &str) -> Result<(), MultiError> {
+        self.record(AuditRecord::new(AuditAction::ProviderRemoved, provider))
+    }
+
+    /// Record that run `run_id` started.
+    pub fn run_started(&self, run_id: &str) -> Result<(), MultiError> {
+        self.record(AuditRecord::new(AuditAction::RunStarted, run_id).run_id(run_id))
+    }
+
+    /// Record that run `run_id` was cancelled.
+    pub fn run_cancelled(&self, run_id: &str) -> Result<(), MultiError> {
+        self.record(AuditRecord::new(AuditAction::RunCancelled, run_id).run_id(run_id))
+    }
+
+    /// Record a configuration change to `target`, with one detail per
+    /// changed field holding `"old -> new"`.
+    ///
+    /// Values come from the diff, so secrets stay redacted.
+    pub fn config_changed(&self, target: &str, diff: &ConfigDiff) -> Result<(), MultiError> {
+        let record = diff.changes().iter().fold(
+            AuditRecord::new(AuditAction::ConfigChanged, target),
+            |r, c| r.detail(c.path.clone(), format!("{} -> {}", c.old, c.new)),
+        );
+        self.record(record)
+    }
+}
+
+impl fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sinks: Vec<&str> = self.sinks.iter().map(|s| s.name()).collect();
+        f.debug_struct("AuditLog")
+            .field("sinks", &sinks)
+            .field("actor", &self.actor)
+            .finish()
+    }
+}
+
+/// Keeps records in memory, for tests and in-process inspection.
+///
+/// Clones share the same records.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    records: Arc<Mutex<Vec<AuditRecord>>>,
+}
+
+impl MemorySink {
+    /// Create an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The records appended so far.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl AuditSink for MemorySink {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn append(&self, record: &AuditRecord) -> ProviderResult<()> {
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(record.clone());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+pub use json::JsonLinesSink;
+
+#[cfg(feature = "serde")]
+mod json {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    use std::fs::OpenOptions;
+    use std::io::{self, Write};
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use super::{AuditRecord, AuditSink};
+    use crate::error::{ProviderError, ProviderResult};
+
+    /// Writes each record as one line of JSON.
+    ///
+    /// Requires the `serde` feature.
+    pub struct JsonLinesSink {
+        name: String,
+        writer: Mutex<Box<dyn Write + Send>>,
+    }
+
+    impl JsonLinesSink {
+        /// Write to `writer`.
+        pub fn new(name: impl Into<String>, writer: impl Write + Send + 'static) -> Self {
+            Self {
+                name: name.into(),
+                writer: Mutex::new(Box::new(writer)),
+            }
+        }
+
+        /// Write to standard output.
+        pub fn stdout() -> Self {
+            Self::new("stdout", io::stdout())
+        }
+
+        /// Append to the file at `path`, creating it if needed.
+        ///
+        /// Not available on `wasm32-unknown-unknown`.
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        pub fn file(path: impl AsRef<Path>) -> ProviderResult<Self> {
+            let path = path.as_ref();
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Ok(Self::new(path.display().to_string(), file))
+        }
+    }
+
+    impl AuditSink for JsonLinesSink {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn append(&self, record: &AuditRecord) -> ProviderResult<()> {
+            let mut line = serde_json::to_vec(record)
+                .map_err(|e| ProviderError::ExecutionFailed(e.to_string()))?;
+            line.push(b'\n');
+            let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+            writer.write_all(&line)?;
+            Ok(())
+        }
+
+        fn flush(&self) -> ProviderResult<()> {
+            self.writer
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .flush()?;
+            Ok(())
+        }
+    }
+
+    impl std::fmt::Debug for JsonLinesSink {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("JsonLinesSink")
+                .field("name", &self.name)
+                .finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProviderError;
+
+    struct FailingSink;
+
+    impl AuditSink for FailingSink {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn append(&self, _: &AuditRecord) -> ProviderResult<()> {
+            Err(ProviderError::IoError("disk full".into()))
+        }
+    }
+
+    #[test]
+    fn test_record_from_context() {
+        let cx = Context::new()
+            .with_subject("alice")
+            .with_run_id("r1")
+            .with_correlation_id("req-7");
+        let record = AuditRecord::new(AuditAction::RunStarted, "r1").context(&cx);
+        assert_eq!(record.actor.as_deref(), Some("alice"));
+        assert_eq!(record.run_id.as_deref(), Some("r1"));
+        assert_eq!(record.correlation_id.as_deref(), Some("req-7"));
+    }
+
+    #[test]
+    fn test_sequence_and_default_actor() {
+        let sink = MemorySink::new();
+        let log = AuditLog::new()
+            .with_sink(sink.clone())
+            .with_actor("svc-indexer");
+        log.provider_registered("rust").unwrap();
+        log.clone().provider_removed("rust").unwrap();
+
+        let records = sink.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].action, AuditAction::ProviderRegistered);
+        assert_eq!(records[1].sequence, 1);
+        assert_eq!(records[1].actor.as_deref(), Some("svc-indexer"));
+    }
+
+    #[test]
+    fn test_failing_sink_does_not_stop_others() {
+        let sink = MemorySink::new();
+        let log = AuditLog::new()
+            .with_sink(FailingSink)
+            .with_sink(sink.clone());
+        let err = log.run_cancelled("r1").unwrap_err();
+        assert_eq!(err.labels().collect::<Vec<_>>(), vec!["failing"]);
+        assert_eq!(sink.records()[0].run_id.as_deref(), Some("r1"));
+    }
+
+    #[test]
+    fn test_action_names_round_trip() {
+        for action in [
+            AuditAction::ProviderRegistered,
+            AuditAction::ConfigChanged,
+            AuditAction::Other("user.login".into()),
+        ] {
+            assert_eq!(AuditAction::from(String::from(action.clone())), action);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_lines_sink() {
+        #[derive(Clone, Default)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let out = Shared::default();
+        let log = AuditLog::new().with_sink(JsonLinesSink::new("test", out.clone()));
+        log.record(AuditRecord::new(AuditAction::RunStarted, "r1").detail("attempt", 2))
+            .unwrap();
+        log.provider_registered("rust").unwrap();
+
+        let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["action"], "run.started");
+        assert_eq!(lines[0]["details"]["attempt"], 2);
+        assert_eq!(lines[1]["sequence"], 1);
+    }
+}
@@ -0,0 +1,372 @@
+//! Ready-made `axum` routers for the operational HTTP surface of a SEA
+//! module.
+//!
+//! Each function returns a [`Router`] for one concern; merge the ones you
+//! need into the application router:
+//!
+//! | Router | Routes |
+//! |--------|--------|
+//! | [`registry_router`] | `GET /providers`, `GET /providers/{name}` |
+//! | [`health_router`] | `GET /health` |
+//! | [`metrics_router`] | `GET /metrics` (Prometheus text) |
+//! | [`runs_router`] | `GET /runs`, `POST /runs/{kind}` |
+//! | [`run_events_router`] | `GET /runs/{id}/events` (SSE) |
+//!
+//! Errors are returned as [`WireError`] JSON with a status derived from the
+//! error category.
+//!
+//! Requires the `axum` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//!
+//! use rustratify::axum::{health_router, metrics_router, registry_router, HealthChecks};
+//! use rustratify::metrics::Metrics;
+//! use rustratify::shutdown::Shutdown;
+//! use rustratify::{Provider, Registry};
+//!
+//! let registry: Arc<Registry<dyn Provider>> = Arc::new(Registry::new());
+//! let shutdown = Shutdown::new();
+//!
+//! let ops: axum::Router = registry_router(registry)
+//!     .merge(health_router(HealthChecks::new().shutdown(&shutdown)))
+//!     .merge(metrics_router(Metrics::new()));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+
+use ::axum::body::{Body, Bytes};
+use ::axum::extract::Path;
+use ::axum::http::{header, StatusCode};
+use ::axum::response::{IntoResponse, Response};
+use ::axum::routing::{get, post};
+use ::axum::{Json, Router};
+use serde::Serialize;
+
+use crate::error::{ErrorCategory, ErrorCode, ProviderError, WireError};
+use crate::metrics::{Metrics, PrometheusExporter};
+use crate::provider::Provider;
+use crate::queue::JobQueue;
+use crate::registry::Registry;
+use crate::shutdown::Shutdown;
+use crate::stream::sse::{to_sse, SseConfig};
+use crate::stream::StreamRegistry;
+
+/// A [`ProviderError`] as an HTTP response.
+///
+/// `NotFound` maps to 404 and `PermissionDenied` to 403; otherwise user
+/// errors map to 400, transient errors to 503, and the rest to 500.
+#[derive(Debug)]
+pub struct ApiError(pub ProviderError);
+
+impl From<ProviderError> for ApiError {
+    fn from(err: ProviderError) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match (&self.0, self.0.category()) {
+            (ProviderError::NotFound(_), _) => StatusCode::NOT_FOUND,
+            (ProviderError::PermissionDenied(_), _) => StatusCode::FORBIDDEN,
+            (_, ErrorCategory::User) => StatusCode::BAD_REQUEST,
+            (_, ErrorCategory::Transient) => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(WireError::from(&self.0))).into_response()
+    }
+}
+
+/// Routes listing the providers of `registry`.
+///
+/// `GET /providers` returns the [`RegistryManifest`](crate::RegistryManifest);
+/// `GET /providers/{name}` returns one
+/// [`ProviderInfo`](crate::ProviderInfo).
+pub fn registry_router<P, S>(registry: Arc<Registry<P, S>>) -> Router
+where
+    P: Provider + ?Sized + 'static,
+    S: BuildHasher + 'static,
+    Registry<P, S>: Send + Sync,
+{
+    let one = Arc::clone(&registry);
+    Router::new()
+        .route(
+            "/providers",
+            get(move || async move { Json(registry.manifest()) }),
+        )
+        .route(
+            "/providers/{name}",
+            get(move |Path(name): Path<String>| async move {
+                one.manifest()
+                    .get(&name)
+                    .cloned()
+                    .map(Json)
+                    .ok_or(ApiError(ProviderError::NotFound(name)))
+            }),
+        )
+}
+
+type Check = Arc<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// The checks behind [`health_router`].
+#[derive(Clone, Default)]
+pub struct HealthChecks {
+    checks: Vec<(String, Check)>,
+    shutdown: Option<Shutdown>,
+}
+
+impl HealthChecks {
+    /// Create an empty set of checks, which reports healthy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a check; `Err` marks the service unhealthy.
+    pub fn check<F>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.checks.push((name.into(), Arc::new(check)));
+        self
+    }
+
+    /// Report [`HealthStatus::ShuttingDown`] once `shutdown` is triggered,
+    /// so load balancers stop routing here.
+    pub fn shutdown(mut self, shutdown: &Shutdown) -> Self {
+        self.shutdown = Some(shutdown.clone());
+        self
+    }
+
+    /// Run every check.
+    pub fn report(&self) -> HealthReport {
+        let checks: BTreeMap<_, _> = self
+            .checks
+            .iter()
+            .map(|(name, check)| (name.clone(), CheckResult::from(check())))
+            .collect();
+        let status = if self.shutdown.as_ref().is_some_and(Shutdown::is_triggered) {
+            HealthStatus::ShuttingDown
+        } else if checks.values().all(|c| c.ok) {
+            HealthStatus::Ok
+        } else {
+            HealthStatus::Unhealthy
+        };
+        HealthReport { status, checks }
+    }
+}
+
+impl fmt::Debug for HealthChecks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HealthChecks")
+            .field(
+                "checks",
+                &self.checks.iter().map(|(n, _)| n).collect::<Vec<_>>(),
+            )
+            .field("shutdown", &self.shutdown)
+            .finish()
+    }
+}
+
+/// Overall service health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Every check passed
+    Ok,
+    /// At least one check failed
+    Unhealthy,
+    /// Shutdown has been triggered
+    ShuttingDown,
+}
+
+/// The outcome of one health check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckResult {
+    /// Whether the check passed
+    pub ok: bool,
+    /// Why it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<Result<(), String>> for CheckResult {
+    fn from(result: Result<(), String>) -> Self {
+        Self {
+            ok: result.is_ok(),
+            error: result.err(),
+        }
+    }
+}
+
+/// The body of `GET /health`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    /// Overall status
+    pub status: HealthStatus,
+    /// Each check by name
+    pub checks: BTreeMap<String, CheckResult>,
+}
+
+impl HealthReport {
+    /// Whether the status is [`HealthStatus::Ok`].
+    pub fn is_healthy(&self) -> bool {
+        self.status == HealthStatus::Ok
+    }
+}
+
+/// `GET /health`: the [`HealthReport`], with status 200 when healthy and
+/// 503 otherwise.
+pub fn health_router(checks: HealthChecks) -> Router {
+    Router::new().route(
+        "/health",
+        get(move || async move {
+            let report = checks.report();
+            let status = match report.is_healthy() {
+                true => StatusCode::OK,
+                false => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            (status, Json(report))
+        }),
+    )
+}
+
+/// `GET /metrics`: a snapshot of `metrics` in the Prometheus text format.
+pub fn metrics_router(metrics: Metrics) -> Router {
+    Router::new().route(
+        "/metrics",
+        get(move || async move {
+            (
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                PrometheusExporter::render(&metrics.snapshot()),
+            )
+        }),
+    )
+}
+
+/// Routes submitting runs to `queue`.
+///
+/// `POST /runs/{kind}` enqueues the request body as the payload and
+/// returns 202 with `{"id": "job-N"}`; `GET /runs` returns
+/// `{"pending": N}`.
+pub fn runs_router(queue: Arc<dyn JobQueue>) -> Router {
+    let submit = Arc::clone(&queue);
+    Router::new()
+        .route(
+            "/runs",
+            get(move || async move {
+                let pending = queue.pending().await.map_err(ApiError)?;
+                Ok::<_, ApiError>(Json(serde_json::json!({ "pending": pending })))
+            }),
+        )
+        .route(
+            "/runs/{kind}",
+            post(move |Path(kind): Path<String>, payload: Bytes| async move {
+                let id = submit
+                    .enqueue(&kind, payload.to_vec())
+                    .await
+                    .map_err(ApiError)?;
+                Ok::<_, ApiError>((
+                    StatusCode::ACCEPTED,
+                    Json(serde_json::json!({ "id": id.to_string() })),
+                ))
+            }),
+        )
+}
+
+/// `GET /runs/{id}/events`: the run's event stream from `streams` as
+/// server-sent events.
+///
+/// Returns 404 if no stream is open for the run and 409 if another client
+/// has already attached to it.
+pub fn run_events_router<T>(streams: StreamRegistry<String, T>) -> Router
+where
+    T: Serialize + Send + 'static,
+{
+    Router::new().route(
+        "/runs/{id}/events",
+        get(move |Path(id): Path<String>| async move {
+            let Some(stream) = streams.attach(&id) else {
+                let status = match streams.is_attached(&id) {
+                    true => StatusCode::CONFLICT,
+                    false => StatusCode::NOT_FOUND,
+                };
+                return status.into_response();
+            };
+            let body = Body::from_stream(to_sse(stream, SseConfig::new()));
+            (
+                [
+                    (header::CONTENT_TYPE, "text/event-stream"),
+                    (header::CACHE_CONTROL, "no-cache"),
+                ],
+                body,
+            )
+                .into_response()
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn call(router: Router, method: &str, uri: &str) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from("payload"))
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = ::axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_health_reports_failures_and_shutdown() {
+        let shutdown = Shutdown::new();
+        let checks = HealthChecks::new()
+            .check("db", || Ok(()))
+            .shutdown(&shutdown);
+        let (status, body) = call(health_router(checks.clone()), "GET", "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"status":"ok","checks":{"db":{"ok":true}}}"#);
+
+        let failing = checks.clone().check("cache", || Err("down".into()));
+        let (status, body) = call(health_router(failing), "GET", "/health").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body.contains(r#""cache":{"ok":false,"error":"down"}"#));
+
+        shutdown.trigger(crate::shutdown::ShutdownReason::Requested);
+        assert_eq!(checks.report().status, HealthStatus::ShuttingDown);
+    }
+
+    #[tokio::test]
+    async fn test_runs_submit_and_stream_events() {
+        let queue: Arc<dyn JobQueue> = Arc::new(crate::queue::MemoryQueue::new());
+        let (status, body) = call(runs_router(Arc::clone(&queue)), "POST", "/runs/build").await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body, r#"{"id":"job-0"}"#);
+        assert_eq!(queue.pending().await.unwrap(), 1);
+
+        let streams = StreamRegistry::<String, u32>::new();
+        let sender = streams.open("job-0".into()).unwrap();
+        sender.send(7).await.unwrap();
+        drop(sender);
+        let router = run_events_router(streams);
+        let (status, _) = call(router.clone(), "GET", "/runs/job-1/events").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, body) = call(router, "GET", "/runs/job-0/events").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("data: 7\n"), "{body}");
+    }
+}
@@ -0,0 +1,433 @@
+//! Running one async operation over many items.
+//!
+//! [`run_batch`] is for the "process these 10k files" shape: it calls a
+//! closure on every item, a bounded number at a time, and returns a
+//! [`BatchHandle`] to watch it. The handle reports aggregate
+//! [`BatchProgress`], streams a [`BatchEvent`] as each item finishes, and
+//! [`join`](BatchHandle::join)s into every item's result.
+//!
+//! With [`BatchOptions::abort_after`], the batch stops starting items once
+//! that many have failed. Items already running are left to finish; items
+//! never started have no result.
+//!
+//! Requires the `tokio` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::batch::{run_batch, BatchOptions};
+//! use rustratify::ProviderError;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let files = vec!["a.rs", "b.rs", "broken.rs", "c.rs"];
+//! let batch = run_batch(files, BatchOptions::new().concurrency(1).abort_after(1), |file| async move {
+//!     if file.starts_with("broken") {
+//!         return Err(ProviderError::ExecutionFailed(format!("cannot parse {file}")));
+//!     }
+//!     Ok(file.len())
+//! });
+//!
+//! let report = batch.join().await;
+//! assert!(report.aborted);
+//! assert_eq!(report.progress.done, 2);
+//! assert_eq!(report.progress.failed, 1);
+//! assert!(report.results[3].is_none());
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::error::{ProviderError, ProviderResult};
+use crate::stream::{EventSender, EventStream, StreamBuilder};
+use crate::task::{spawn_named, spawn_named_in};
+
+/// How a batch runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchOptions {
+    concurrency: usize,
+    abort_after: Option<usize>,
+    buffer_size: usize,
+}
+
+impl BatchOptions {
+    /// Run 8 items at a time, never abort, and buffer 256 events.
+    pub fn new() -> Self {
+        Self {
+            concurrency: 8,
+            abort_after: None,
+            buffer_size: 256,
+        }
+    }
+
+    /// Run at most `n` items at a time. Zero is treated as one.
+    pub fn concurrency(mut self, n: usize) -> Self {
+        self.concurrency = n.max(1);
+        self
+    }
+
+    /// Stop starting items once `failures` items have failed. Zero is
+    /// treated as one.
+    pub fn abort_after(mut self, failures: usize) -> Self {
+        self.abort_after = Some(failures.max(1));
+        self
+    }
+
+    /// Buffer up to `size` unread events; later events are dropped rather
+    /// than slowing the batch down.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Aggregate progress of a batch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchProgress {
+    /// Number of items in the batch
+    pub total: usize,
+    /// Items that succeeded
+    pub done: usize,
+    /// Items that failed
+    pub failed: usize,
+    /// Items that have not finished, including running ones
+    pub remaining: usize,
+}
+
+/// Something that happened in a batch, reported on
+/// [`BatchHandle::events`].
+#[derive(Debug, Clone)]
+pub enum BatchEvent {
+    /// The item at `index` succeeded
+    Succeeded {
+        /// Position of the item in the batch
+        index: usize,
+        /// Progress after the item
+        progress: BatchProgress,
+    },
+    /// The item at `index` failed
+    Failed {
+        /// Position of the item in the batch
+        index: usize,
+        /// Why it failed
+        error: ProviderError,
+        /// Progress after the item
+        progress: BatchProgress,
+    },
+    /// The batch was aborted with items not yet started
+    Aborted {
+        /// Progress when the batch was aborted
+        progress: BatchProgress,
+    },
+}
+
+/// The outcome of a finished batch.
+#[derive(Debug)]
+pub struct BatchReport<R> {
+    /// Result of each item, in item order; `None` for items never run
+    pub results: Vec<Option<ProviderResult<R>>>,
+    /// Final progress
+    pub progress: BatchProgress,
+    /// Whether the batch was aborted before starting every item
+    pub aborted: bool,
+}
+
+/// Run `f` on every item, as configured by `options`.
+///
+/// Items start in order, on the current tokio runtime. A panicking item
+/// counts as failed.
+pub fn run_batch<I, T, R, F, Fut>(items: I, options: BatchOptions, f: F) -> BatchHandle<R>
+where
+    I: IntoIterator<Item = T>,
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> Fut + Send + 'static,
+    Fut: Future<Output = ProviderResult<R>> + Send + 'static,
+{
+    let items: Vec<T> = items.into_iter().collect();
+    let (events, stream) = StreamBuilder::new()
+        .buffer_size(options.buffer_size)
+        .build();
+    let shared = Arc::new(Shared {
+        progress: Mutex::new(BatchProgress {
+            total: items.len(),
+            remaining: items.len(),
+            ..BatchProgress::default()
+        }),
+        aborted: AtomicBool::new(false),
+    });
+    let task = spawn_named(
+        "rustratify batch",
+        drive(items, options, f, Arc::clone(&shared), events),
+    );
+    BatchHandle {
+        shared,
+        task: Some(task),
+        stream: Some(stream),
+    }
+}
+
+struct Shared {
+    progress: Mutex<BatchProgress>,
+    aborted: AtomicBool,
+}
+
+impl Shared {
+    fn progress(&self) -> MutexGuard<'_, BatchProgress> {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+async fn drive<T, R, F, Fut>(
+    items: Vec<T>,
+    options: BatchOptions,
+    f: F,
+    shared: Arc<Shared>,
+    events: EventSender<BatchEvent>,
+) -> Vec<Option<ProviderResult<R>>>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = ProviderResult<R>> + Send + 'static,
+{
+    let mut results: Vec<_> = items.iter().map(|_| None).collect();
+    let mut pending = items.into_iter().enumerate().peekable();
+    let mut running = JoinSet::new();
+    let mut indices = HashMap::new();
+    loop {
+        while running.len() < options.concurrency && pending.peek().is_some() {
+            if shared.aborted.load(Ordering::Acquire) {
+                let progress = *shared.progress();
+                tracing::warn!(
+                    failed = progress.failed,
+                    remaining = progress.remaining,
+                    "aborting batch"
+                );
+                events.offer(BatchEvent::Aborted { progress });
+                pending = Vec::new().into_iter().enumerate().peekable();
+                break;
+            }
+            let (index, item) = pending.next().expect("peeked");
+            let run = f(item);
+            let name = format!("rustratify batch item {index}");
+            let id = spawn_named_in(&mut running, &name, run).id();
+            indices.insert(id, index);
+        }
+        let Some(joined) = running.join_next_with_id().await else {
+            break;
+        };
+        let (index, result) = match joined {
+            Ok((id, result)) => (indices[&id], result),
+            Err(err) => (
+                indices[&err.id()],
+                Err(ProviderError::ExecutionFailed(format!(
+                    "batch item panicked: {err}"
+                ))),
+            ),
+        };
+        let event = {
+            let mut progress = shared.progress();
+            progress.remaining -= 1;
+            match &result {
+                Ok(_) => {
+                    progress.done += 1;
+                    BatchEvent::Succeeded {
+                        index,
+                        progress: *progress,
+                    }
+                }
+                Err(error) => {
+                    progress.failed += 1;
+                    BatchEvent::Failed {
+                        index,
+                        error: error.clone(),
+                        progress: *progress,
+                    }
+                }
+            }
+        };
+        results[index] = Some(result);
+        events.offer(event);
+
+        let failed = shared.progress().failed;
+        if options.abort_after.is_some_and(|max| failed >= max) {
+            shared.aborted.store(true, Ordering::Release);
+        }
+    }
+    results
+}
+
+/// A running batch from [`run_batch`].
+///
+/// Dropping the handle cancels the batch, including running items.
+pub struct BatchHandle<R> {
+    shared: Arc<Shared>,
+    task: Option<JoinHandle<Vec<Option<ProviderResult<R>>>>>,
+    stream: Option<EventStream<BatchEvent>>,
+}
+
+impl<R> BatchHandle<R> {
+    /// Current progress.
+    pub fn progress(&self) -> BatchProgress {
+        *self.shared.progress()
+    }
+
+    /// Whether the batch has been asked to stop starting items, by
+    /// [`abort`](Self::abort) or by reaching its failure threshold.
+    pub fn is_aborted(&self) -> bool {
+        self.shared.aborted.load(Ordering::Acquire)
+    }
+
+    /// Stop starting items. Running items are left to finish.
+    ///
+    /// Reported as [`BatchEvent::Aborted`] unless every item had already
+    /// started.
+    pub fn abort(&self) {
+        self.shared.aborted.store(true, Ordering::Release);
+    }
+
+    /// The stream of batch events. It ends when the batch finishes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called more than once.
+    pub fn events(&mut self) -> EventStream<BatchEvent> {
+        self.stream.take().expect("batch events already taken")
+    }
+
+    /// Wait for the batch to finish and collect the results.
+    pub async fn join(mut self) -> BatchReport<R> {
+        let task = self.task.take().expect("batch joined twice");
+        let results = match task.await {
+            Ok(results) => results,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        };
+        BatchReport {
+            aborted: results.iter().any(Option::is_none),
+            results,
+            progress: self.progress(),
+        }
+    }
+}
+
+impl<R> fmt::Debug for BatchHandle<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchHandle")
+            .field("progress", &self.progress())
+            .field("aborted", &self.is_aborted())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R> Drop for BatchHandle<R> {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_results_progress_and_events() {
+        let mut batch = run_batch(
+            0..10u32,
+            BatchOptions::new().concurrency(3),
+            |n| async move {
+                tokio::time::sleep(Duration::from_millis(u64::from(10 - n))).await;
+                if n % 4 == 0 {
+                    Err(ProviderError::ExecutionFailed(format!("item {n}")))
+                } else {
+                    Ok(n * 2)
+                }
+            },
+        );
+        let events = batch.events();
+        let report = batch.join().await;
+
+        assert!(!report.aborted);
+        assert_eq!(
+            report.progress,
+            BatchProgress {
+                total: 10,
+                done: 7,
+                failed: 3,
+                remaining: 0,
+            }
+        );
+        assert_eq!(report.results[3].as_ref().unwrap().as_ref().unwrap(), &6);
+        assert!(report.results[4].as_ref().unwrap().is_err());
+
+        let events: Vec<_> = events.collect().await;
+        assert_eq!(events.len(), 10);
+        assert!(events
+            .iter()
+            .all(|event| !matches!(event, BatchEvent::Aborted { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_abort_after_failures() {
+        let mut batch = run_batch(
+            0..100u32,
+            BatchOptions::new().concurrency(2).abort_after(2),
+            |n| async move {
+                if n >= 5 {
+                    Err(ProviderError::ExecutionFailed("disk full".into()))
+                } else {
+                    Ok(())
+                }
+            },
+        );
+        let events = batch.events();
+        let report = batch.join().await;
+
+        assert!(report.aborted);
+        assert!(report.progress.failed >= 2);
+        let started = report.results.iter().filter(|r| r.is_some()).count();
+        assert!(started < 10);
+        assert_eq!(report.progress.remaining, 100 - started);
+
+        let events: Vec<_> = events.collect().await;
+        // Items running at the abort finish after it
+        let aborted = events
+            .iter()
+            .filter(|event| matches!(event, BatchEvent::Aborted { .. }))
+            .count();
+        assert_eq!(aborted, 1);
+        assert_eq!(events.len(), started + 1);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_item_fails() {
+        let batch = run_batch(
+            [1, 0],
+            BatchOptions::new(),
+            |n: u32| async move { Ok(10 / n) },
+        );
+        let report = batch.join().await;
+        assert_eq!(report.progress.failed, 1);
+        assert!(matches!(
+            report.results[1],
+            Some(Err(ProviderError::ExecutionFailed(_)))
+        ));
+    }
+}
@@ -0,0 +1,89 @@
+//! `cargo rustratify` subcommands.
+//!
+//! ```text
+//! cargo rustratify check [--manifest-path <Cargo.toml>]
+//! cargo rustratify new <module>
+//! ```
+//!
+//! `check` validates SEA layering across the workspace and exits non-zero
+//! if any crate depends on a higher layer. `new` creates a workspace for a
+//! new SEA module in the current directory.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rustratify::ExitStatus;
+
+const USAGE: &str = "usage: cargo rustratify check [--manifest-path <Cargo.toml>]
+       cargo rustratify new <module>";
+
+fn main() -> ExitCode {
+    // Cargo passes the subcommand name as the first argument
+    let mut args = std::env::args().skip(1).peekable();
+    if args.peek().map(String::as_str) == Some("rustratify") {
+        args.next();
+    }
+
+    match args.next().as_deref() {
+        Some("check") => check(args),
+        Some("new") => match (args.next(), args.next()) {
+            (Some(name), None) => new(&name),
+            _ => usage(),
+        },
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            ExitStatus::SUCCESS.into()
+        }
+        _ => usage(),
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{USAGE}");
+    ExitStatus::USER.into()
+}
+
+fn new(name: &str) -> ExitCode {
+    match rustratify::scaffold::generate(name, ".") {
+        Ok(files) => {
+            for file in &files {
+                println!("created {}", file.display());
+            }
+            ExitStatus::SUCCESS.into()
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitStatus::USER.into()
+        }
+    }
+}
+
+fn check(mut args: impl Iterator<Item = String>) -> ExitCode {
+    let mut manifest = PathBuf::from("Cargo.toml");
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--manifest-path", Some(path)) => manifest = PathBuf::from(path),
+            _ => return usage(),
+        }
+    }
+
+    match rustratify::arch::validate(&manifest) {
+        Ok(report) => {
+            print!("{report}");
+            if report.is_ok() {
+                ExitStatus::SUCCESS.into()
+            } else {
+                eprintln!(
+                    "{} layer violation(s) in {}",
+                    report.violations.len(),
+                    manifest.display()
+                );
+                ExitCode::FAILURE
+            }
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitStatus::CONFIGURATION.into()
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Blocking facade for synchronous consumers.
+//!
+//! CLI tools and other non-async code can consume SEA modules through this
+//! module without writing their own `block_on` scaffolding. A
+//! [`BlockingRuntime`] owns (or borrows) the runtime that drives async work,
+//! and [`BlockingEventIter`] turns an [`EventStream`] into a plain
+//! [`Iterator`].
+//!
+//! With the `tokio` feature the runtime is a current-thread tokio runtime, so
+//! tokio-only stream operators such as `throttle` keep working. Without it, a
+//! minimal thread-parking executor is used.
+//!
+//! None of these types may be used from inside an async context: blocking a
+//! runtime worker thread panics with tokio and deadlocks elsewhere.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::blocking::BlockingRuntime;
+//! use rustratify::create_stream;
+//!
+//! let runtime = BlockingRuntime::new().unwrap();
+//! let (sender, stream) = create_stream::<u32>();
+//!
+//! runtime.block_on(async move {
+//!     sender.send(1).await.unwrap();
+//!     sender.send(2).await.unwrap();
+//! });
+//!
+//! let events: Vec<u32> = runtime.iter(stream).collect();
+//! assert_eq!(events, vec![1, 2]);
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+#[cfg(feature = "tokio")]
+use crate::error::RustratifyError;
+use crate::error::RustratifyResult;
+use crate::stream::{EventSender, EventStream, NextItem};
+
+/// A handle to the runtime that drives blocking calls.
+///
+/// Cloning is cheap; clones share the same runtime.
+#[derive(Clone)]
+pub struct BlockingRuntime {
+    inner: Arc<Inner>,
+}
+
+enum Inner {
+    #[cfg(feature = "tokio")]
+    Owned(tokio::runtime::Runtime),
+    #[cfg(feature = "tokio")]
+    Handle(tokio::runtime::Handle),
+    #[cfg(not(feature = "tokio"))]
+    Park,
+}
+
+impl BlockingRuntime {
+    /// Create a runtime owned by this handle.
+    ///
+    /// Fails if the underlying runtime cannot be started.
+    pub fn new() -> RustratifyResult<Self> {
+        #[cfg(feature = "tokio")]
+        let inner = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map(Inner::Owned)
+            .map_err(|e| RustratifyError::Other(format!("failed to start runtime: {e}")))?;
+        #[cfg(not(feature = "tokio"))]
+        let inner = Inner::Park;
+
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Drive blocking calls on an existing tokio runtime.
+    ///
+    /// Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub fn from_handle(handle: tokio::runtime::Handle) -> Self {
+        Self {
+            inner: Arc::new(Inner::Handle(handle)),
+        }
+    }
+
+    /// Run a future to completion on the current thread.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        match &*self.inner {
+            #[cfg(feature = "tokio")]
+            Inner::Owned(runtime) => runtime.block_on(future),
+            #[cfg(feature = "tokio")]
+            Inner::Handle(handle) => handle.block_on(future),
+            #[cfg(not(feature = "tokio"))]
+            Inner::Park => park::block_on(future),
+        }
+    }
+
+    /// Iterate over a stream's events, blocking for each one.
+    pub fn iter<T>(&self, stream: EventStream<T>) -> BlockingEventIter<T> {
+        BlockingEventIter {
+            runtime: self.clone(),
+            stream,
+        }
+    }
+
+    /// Wrap a sender so events can be sent without `.await`.
+    pub fn sender<T>(&self, sender: EventSender<T>) -> BlockingSender<T> {
+        BlockingSender {
+            runtime: self.clone(),
+            sender,
+        }
+    }
+
+    /// Start an operation that returns an identifier and an event stream.
+    ///
+    /// This is the blocking counterpart of the executor pattern
+    /// `let (run_id, stream) = executor.run(config).await?;`.
+    pub fn run<F, I, T, E>(&self, start: F) -> Result<(I, BlockingEventIter<T>), E>
+    where
+        F: Future<Output = Result<(I, EventStream<T>), E>>,
+    {
+        let (id, stream) = self.block_on(start)?;
+        Ok((id, self.iter(stream)))
+    }
+}
+
+impl fmt::Debug for BlockingRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match &*self.inner {
+            #[cfg(feature = "tokio")]
+            Inner::Owned(_) => "Owned",
+            #[cfg(feature = "tokio")]
+            Inner::Handle(_) => "Handle",
+            #[cfg(not(feature = "tokio"))]
+            Inner::Park => "Park",
+        };
+        f.debug_struct("BlockingRuntime")
+            .field("kind", &kind)
+            .finish()
+    }
+}
+
+/// An [`Iterator`] over the events of an [`EventStream`].
+///
+/// Each call to `next` blocks until the next event arrives or the stream ends.
+pub struct BlockingEventIter<T> {
+    runtime: BlockingRuntime,
+    stream: EventStream<T>,
+}
+
+impl<T> BlockingEventIter<T> {
+    /// Iterate over a stream using a new [`BlockingRuntime`].
+    pub fn new(stream: EventStream<T>) -> RustratifyResult<Self> {
+        Ok(BlockingRuntime::new()?.iter(stream))
+    }
+
+    /// Get the runtime driving this iterator.
+    pub fn runtime(&self) -> &BlockingRuntime {
+        &self.runtime
+    }
+
+    /// Recover the underlying stream.
+    pub fn into_inner(self) -> EventStream<T> {
+        self.stream
+    }
+}
+
+impl<T> Iterator for BlockingEventIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.runtime.block_on(NextItem(&mut self.stream))
+    }
+}
+
+impl<T> fmt::Debug for BlockingEventIter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingEventIter")
+            .field("runtime", &self.runtime)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An [`EventSender`] whose `send` blocks instead of returning a future.
+#[derive(Debug, Clone)]
+pub struct BlockingSender<T> {
+    runtime: BlockingRuntime,
+    sender: EventSender<T>,
+}
+
+impl<T> BlockingSender<T> {
+    /// Send an event, blocking while the channel is full.
+    ///
+    /// Returns `Err(event)` if the receiver was dropped.
+    pub fn send(&self, event: T) -> Result<(), T> {
+        self.runtime.block_on(self.sender.send(event))
+    }
+
+    /// Get the wrapped async sender.
+    pub fn inner(&self) -> &EventSender<T> {
+        &self.sender
+    }
+
+    /// Recover the wrapped async sender.
+    pub fn into_inner(self) -> EventSender<T> {
+        self.sender
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub use self::registry::BlockingStreamRegistry;
+
+#[cfg(feature = "tokio")]
+mod registry {
+    use std::fmt::Display;
+    use std::hash::Hash;
+
+    use super::{BlockingEventIter, BlockingRuntime, BlockingSender};
+    use crate::error::RegistryResult;
+    use crate::stream::StreamRegistry;
+
+    /// A [`StreamRegistry`] that hands out blocking senders and iterators.
+    ///
+    /// Requires the `tokio` feature.
+    pub struct BlockingStreamRegistry<K, T> {
+        runtime: BlockingRuntime,
+        registry: StreamRegistry<K, T>,
+    }
+
+    impl<K, T> BlockingStreamRegistry<K, T>
+    where
+        K: Eq + Hash + Clone + Display + Send + 'static,
+        T: Send + 'static,
+    {
+        /// Wrap a registry, driving it with `runtime`.
+        pub fn new(runtime: BlockingRuntime, registry: StreamRegistry<K, T>) -> Self {
+            Self { runtime, registry }
+        }
+
+        /// Open a stream for `key`. See [`StreamRegistry::open`].
+        pub fn open(&self, key: K) -> RegistryResult<BlockingSender<T>> {
+            let sender = self.registry.open(key)?;
+            Ok(self.runtime.sender(sender))
+        }
+
+        /// Take the consumer end for `key`. See [`StreamRegistry::attach`].
+        pub fn attach(&self, key: &K) -> Option<BlockingEventIter<T>> {
+            let stream = self.registry.attach(key)?;
+            Some(self.runtime.iter(stream))
+        }
+
+        /// Close the stream for `key`. See [`StreamRegistry::close`].
+        pub fn close(&self, key: &K) -> bool {
+            self.registry.close(key)
+        }
+
+        /// Get the wrapped async registry.
+        pub fn inner(&self) -> &StreamRegistry<K, T> {
+            &self.registry
+        }
+    }
+
+    impl<K, T> Clone for BlockingStreamRegistry<K, T> {
+        fn clone(&self) -> Self {
+            Self {
+                runtime: self.runtime.clone(),
+                registry: self.registry.clone(),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+mod park {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Poll `future` on the current thread, parking between wake-ups.
+    pub(super) fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{create_stream, StreamBuilder};
+
+    #[test]
+    fn test_iter_collects_events() {
+        let runtime = BlockingRuntime::new().unwrap();
+        let (sender, stream) = create_stream::<u32>();
+        let sender = runtime.sender(sender);
+        for i in 0..3 {
+            sender.send(i).unwrap();
+        }
+        drop(sender);
+
+        let events: Vec<_> = runtime.iter(stream).collect();
+        assert_eq!(events, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_iter_waits_for_producer_thread() {
+        let (sender, stream) = StreamBuilder::<u32>::new().buffer_size(1).build();
+        let producer = std::thread::spawn(move || {
+            let runtime = BlockingRuntime::new().unwrap();
+            let sender = runtime.sender(sender);
+            for i in 0..5 {
+                sender.send(i).unwrap();
+            }
+        });
+
+        let events: Vec<_> = BlockingEventIter::new(stream).unwrap().collect();
+        producer.join().unwrap();
+        assert_eq!(events, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_run() {
+        let runtime = BlockingRuntime::new().unwrap();
+        let result: Result<_, String> = runtime.run(async {
+            let (sender, stream) = create_stream::<&str>();
+            sender.send("started").await.unwrap();
+            Ok(("run-1", stream))
+        });
+
+        let (id, events) = result.unwrap();
+        assert_eq!(id, "run-1");
+        assert_eq!(events.collect::<Vec<_>>(), vec!["started"]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_blocking_stream_registry() {
+        use crate::stream::StreamRegistry;
+
+        let runtime = BlockingRuntime::new().unwrap();
+        let registry = BlockingStreamRegistry::new(runtime, StreamRegistry::<String, u32>::new());
+
+        let sender = registry.open("run-1".into()).unwrap();
+        sender.send(7).unwrap();
+        drop(sender);
+
+        let events: Vec<_> = registry.attach(&"run-1".into()).unwrap().collect();
+        assert_eq!(events, vec![7]);
+        assert!(registry.attach(&"run-1".into()).is_none());
+    }
+}
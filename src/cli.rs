@@ -0,0 +1,335 @@
+//! clap front-ends for SEA modules.
+//!
+//! [`CliApp`] builds a [`clap::Command`] with one subcommand per provider in
+//! a [`Registry`] and per registered operation, plus global `--config` and
+//! `--set` arguments. Parsing yields an [`Invocation`], which resolves the
+//! module config in layers (defaults, then the config file, then prefixed
+//! environment variables, then `--set` flags) and looks up the provider to
+//! run.
+//!
+//! Requires the `cli` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::cli::CliApp;
+//! use rustratify::{DefaultConfig, EnvConfig, Provider, Registry};
+//! # use std::any::Any;
+//! # #[derive(Debug)]
+//! # struct RustProvider;
+//! # impl Provider for RustProvider {
+//! #     fn name(&self) -> &str { "rust" }
+//! #     fn extensions(&self) -> &[&str] { &[".rs"] }
+//! #     fn as_any(&self) -> &dyn Any { self }
+//! # }
+//!
+//! let mut registry: Registry<dyn Provider> = Registry::new();
+//! registry.register(Box::new(RustProvider));
+//!
+//! let app = CliApp::new("lint").providers(&registry).operation("list", "List providers");
+//! let invocation = app
+//!     .try_parse_from(["lint", "--set", "timeout_ms=500", "rust", "src/main.rs"])
+//!     .unwrap();
+//! assert_eq!(invocation.command, "rust");
+//! assert_eq!(invocation.inputs, vec!["src/main.rs"]);
+//! assert_eq!(invocation.provider(&registry).unwrap().name(), "rust");
+//!
+//! let env = EnvConfig::from_vars("LINT", [("LINT_NAME", "ci")]);
+//! let config: DefaultConfig = invocation.resolve_config_with(&env).unwrap();
+//! assert_eq!(config.name, "ci");
+//! assert_eq!(config.timeout_ms, Some(500));
+//! ```
+
+use std::hash::BuildHasher;
+use std::path::PathBuf;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::de::DeserializeOwned;
+
+use crate::config::{load_config, EnvConfig, EnvReport, FromEnv};
+use crate::error::{ProviderError, ProviderResult};
+use crate::provider::Provider;
+use crate::registry::Registry;
+
+/// Prefix `--set` flags are mapped under before being applied like
+/// environment variables.
+const SET_PREFIX: &str = "SET";
+
+/// Builder for a SEA module's command line.
+#[derive(Debug, Clone)]
+pub struct CliApp {
+    command: Command,
+}
+
+impl CliApp {
+    /// Create a command line for the binary `name`, with the global
+    /// `--config <FILE>` and `--set <FIELD=VALUE>` arguments.
+    pub fn new(name: impl Into<String>) -> Self {
+        let command = Command::new(name.into())
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+            .arg(
+                Arg::new("config")
+                    .long("config")
+                    .short('c')
+                    .value_name("FILE")
+                    .value_parser(clap::value_parser!(PathBuf))
+                    .global(true)
+                    .help("Load configuration from FILE"),
+            )
+            .arg(
+                Arg::new("set")
+                    .long("set")
+                    .value_name("FIELD=VALUE")
+                    .value_parser(parse_override)
+                    .action(ArgAction::Append)
+                    .global(true)
+                    .help("Override a configuration field"),
+            );
+        Self { command }
+    }
+
+    /// Set the description shown in `--help`.
+    pub fn about(mut self, about: impl Into<String>) -> Self {
+        self.command = self.command.about(about.into());
+        self
+    }
+
+    /// Set the version shown by `--version`.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.command = self.command.version(version.into());
+        self
+    }
+
+    /// Add a subcommand per provider in `registry`, taking the inputs to
+    /// run it on.
+    pub fn providers<P, S>(mut self, registry: &Registry<P, S>) -> Self
+    where
+        P: Provider + ?Sized,
+        S: BuildHasher,
+    {
+        for provider in registry.iter() {
+            let about = match provider.extensions() {
+                [] => format!("Run the {} provider", provider.name()),
+                extensions => format!(
+                    "Run the {} provider ({})",
+                    provider.name(),
+                    extensions.join(", ")
+                ),
+            };
+            self = self.operation(provider.name(), about);
+        }
+        self
+    }
+
+    /// Add a subcommand for an API operation, taking the inputs to run it
+    /// on.
+    pub fn operation(mut self, name: impl Into<String>, about: impl Into<String>) -> Self {
+        let subcommand = Command::new(name.into()).about(about.into()).arg(
+            Arg::new("inputs")
+                .value_name("INPUT")
+                .num_args(0..)
+                .action(ArgAction::Append),
+        );
+        self.command = self.command.subcommand(subcommand);
+        self
+    }
+
+    /// The underlying command, e.g. to add arguments or generate
+    /// completions.
+    pub fn command(&self) -> Command {
+        self.command.clone()
+    }
+
+    /// Parse the process arguments, exiting with usage on error.
+    pub fn parse(&self) -> Invocation {
+        Invocation::from_matches(&self.command().get_matches())
+    }
+
+    /// Parse `args`, whose first item is the binary name.
+    pub fn try_parse_from<I, T>(&self, args: I) -> Result<Invocation, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        self.command()
+            .try_get_matches_from(args)
+            .map(|matches| Invocation::from_matches(&matches))
+    }
+}
+
+fn parse_override(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((field, value)) if !field.trim().is_empty() => {
+            Ok((field.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected FIELD=VALUE, got `{value}`")),
+    }
+}
+
+/// A parsed command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// The provider or operation to run
+    pub command: String,
+    /// Positional inputs to the command
+    pub inputs: Vec<String>,
+    /// The `--config` file
+    pub config_file: Option<PathBuf>,
+    /// `--set` overrides, in order
+    pub overrides: Vec<(String, String)>,
+}
+
+impl Invocation {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        let (command, sub) = matches.subcommand().expect("subcommand_required is set");
+        let overrides = sub
+            .get_many::<(String, String)>("set")
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        Self {
+            command: command.to_string(),
+            inputs: sub
+                .get_many::<String>("inputs")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect(),
+            config_file: sub.get_one::<PathBuf>("config").cloned(),
+            overrides,
+        }
+    }
+
+    /// The provider named by the subcommand, if it is one.
+    pub fn provider<'r, P, S>(&self, registry: &'r Registry<P, S>) -> Option<&'r P>
+    where
+        P: Provider + ?Sized,
+        S: BuildHasher,
+    {
+        registry.get(&self.command)
+    }
+
+    /// Resolve the config with environment variables starting with
+    /// `env_prefix`.
+    ///
+    /// See [`resolve_config_with`](Self::resolve_config_with).
+    pub fn resolve_config<C>(&self, env_prefix: &str) -> ProviderResult<C>
+    where
+        C: DeserializeOwned + FromEnv + Default,
+    {
+        self.resolve_config_with(&EnvConfig::new(env_prefix))
+    }
+
+    /// Resolve the config: the `--config` file if given, else the default,
+    /// then `env` overlaid, then the `--set` overrides.
+    ///
+    /// Fails with [`ProviderError::ConfigurationError`] if the file cannot
+    /// be loaded, a variable or override does not parse, or an override
+    /// names an unknown field. Unknown variables are only logged, since the
+    /// environment is shared with other programs.
+    pub fn resolve_config_with<C>(&self, env: &EnvConfig) -> ProviderResult<C>
+    where
+        C: DeserializeOwned + FromEnv + Default,
+    {
+        let mut config = match &self.config_file {
+            Some(path) => load_config(path).map_err(ProviderError::ConfigurationError)?,
+            None => C::default(),
+        };
+
+        let report = env.apply(&mut config);
+        for name in &report.unknown {
+            tracing::warn!(variable = %name, "ignoring unknown config variable");
+        }
+        let mut errors: Vec<String> = report.invalid.iter().map(|e| e.to_string()).collect();
+
+        let overrides = EnvConfig::from_vars(
+            SET_PREFIX,
+            self.overrides
+                .iter()
+                .map(|(field, value)| (format!("{SET_PREFIX}_{field}"), value.clone())),
+        );
+        let EnvReport {
+            unknown, invalid, ..
+        } = overrides.apply(&mut config);
+        errors.extend(
+            invalid
+                .iter()
+                .map(|e| format!("--set {}: {}", override_field(&e.name), e.message)),
+        );
+        errors.extend(
+            unknown
+                .iter()
+                .map(|name| format!("--set {}: unknown field", override_field(name))),
+        );
+
+        match errors.is_empty() {
+            true => Ok(config),
+            false => Err(ProviderError::ConfigurationError(errors.join("; "))),
+        }
+    }
+}
+
+fn override_field(name: &str) -> &str {
+    &name[SET_PREFIX.len() + 1..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DefaultConfig;
+
+    fn app() -> CliApp {
+        CliApp::new("tool").operation("run", "Run it")
+    }
+
+    #[test]
+    fn test_parse_global_args_after_subcommand() {
+        let invocation = app()
+            .try_parse_from([
+                "tool",
+                "run",
+                "a",
+                "--set",
+                "name=x",
+                "b",
+                "-c",
+                "tool.toml",
+            ])
+            .unwrap();
+        assert_eq!(invocation.command, "run");
+        assert_eq!(invocation.inputs, vec!["a", "b"]);
+        assert_eq!(invocation.config_file, Some(PathBuf::from("tool.toml")));
+        assert_eq!(invocation.overrides, vec![("name".into(), "x".into())]);
+
+        assert!(app()
+            .try_parse_from(["tool", "--set", "name", "run"])
+            .is_err());
+        assert!(app().try_parse_from(["tool", "missing"]).is_err());
+    }
+
+    #[test]
+    fn test_flags_override_env() {
+        let invocation = app()
+            .try_parse_from(["tool", "--set", "NAME=flag", "run"])
+            .unwrap();
+        let env = EnvConfig::from_vars("T", [("T_NAME", "env"), ("T_VERBOSE", "true")]);
+        let config: DefaultConfig = invocation.resolve_config_with(&env).unwrap();
+        assert_eq!(config.name, "flag");
+        assert!(config.verbose);
+
+        let invocation = app()
+            .try_parse_from(["tool", "--set", "nope=1", "--set", "verbose=maybe", "run"])
+            .unwrap();
+        let err = invocation
+            .resolve_config_with::<DefaultConfig>(&EnvConfig::from_vars(
+                "T",
+                Vec::<(String, String)>::new(),
+            ))
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("--set nope: unknown field"), "{message}");
+        assert!(message.contains("--set verbose:"), "{message}");
+    }
+}
@@ -2,8 +2,43 @@
 //!
 //! This module provides base traits for configuration types used across SEA layers.
 
+use alloc::string::String;
+use core::marker::PhantomData;
+use core::time::Duration;
+#[cfg(feature = "std")]
 use std::path::Path;
-use std::time::Duration;
+
+mod diff;
+mod env;
+#[cfg(feature = "serde")]
+mod file;
+#[cfg(feature = "serde")]
+mod interpolate;
+#[cfg(feature = "serde")]
+mod migrate;
+mod schema;
+mod secret;
+mod units;
+mod validate;
+
+pub use diff::{ConfigChange, ConfigDiff, DiffConfig, DiffFields};
+pub use env::{EnvConfig, EnvFields, EnvReport, EnvValue, EnvVarError, FromEnv};
+pub use schema::{ConfigSchema, SchemaProperty, SchemaType, SchemaValue, JSON_SCHEMA_DIALECT};
+pub use secret::{Secret, SecretValue, REDACTED};
+pub use units::{
+    format_duration, format_size, parse_duration, parse_size, ByteSize, HumanDuration,
+};
+pub use validate::{FieldCheck, IsEmpty, Severity, ValidationIssue, ValidationReport};
+
+#[cfg(feature = "serde")]
+pub use file::ConfigFormat;
+#[cfg(all(
+    feature = "serde",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use file::{load_config, save_config};
+#[cfg(feature = "serde")]
+pub use migrate::ConfigMigration;
 
 /// Base trait for configuration types.
 ///
@@ -37,6 +72,53 @@ use std::time::Duration;
 ///     }
 /// }
 /// ```
+///
+/// # Deriving
+///
+/// With the `derive` feature, `#[derive(Config)]` generates `Config`,
+/// [`MergeableConfig`], [`FromEnv`], [`DiffConfig`], [`SchemaValue`],
+/// `Default`, and a `{Name}Builder` implementing [`ConfigBuilder`]. Do not
+/// also derive `Default`. [`Config::schema`] is built from field types, doc
+/// comments, literal defaults, and checks, so field types must implement
+/// [`SchemaValue`].
+///
+/// Struct attribute:
+/// - `#[config(name = "...")]` - value returned by [`Config::name`]
+///
+/// Field attributes:
+/// - `default = expr` - default value (otherwise `Default::default()`)
+/// - `env = "VAR"` - read from `{PREFIX}_VAR` by [`EnvConfig`]
+/// - `required` - the builder's `build()` only compiles once the field is
+///   set (see [`Missing`]), and the schema marks it required
+/// - `validate(range(min = a, max = b))` - bounds checked by [`Config::validation`]
+/// - `validate(non_empty)` - reject empty strings and collections
+///
+/// Merging overrides a field when `other`'s value is `Some` (for `Option`
+/// fields) or differs from the default, so field types must be `Clone`,
+/// `PartialEq`, and (for diffing) `Debug`.
+///
+/// ```rust,ignore
+/// use rustratify::{Config, ConfigBuilder};
+///
+/// #[derive(Debug, Clone, Config)]
+/// #[config(name = "worker")]
+/// struct WorkerConfig {
+///     #[config(default = 5000, env = "TIMEOUT_MS", validate(range(min = 1)))]
+///     timeout_ms: u64,
+///     #[config(validate(non_empty))]
+///     queue: String,
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq, Config)]
+/// struct ClientConfig {
+///     #[config(required)]
+///     endpoint: String,
+/// }
+///
+/// // `ClientConfig::builder().build()` would not compile.
+/// let client = ClientConfig::builder().endpoint("https://example.com").build()?;
+/// let config = WorkerConfig::builder().queue("jobs").build()?;
+/// ```
 pub trait Config: Send + Sync {
     /// Returns the configuration name/identifier.
     fn name(&self) -> &str {
@@ -58,15 +140,42 @@ pub trait Config: Send + Sync {
         false
     }
 
+    /// Describes the configuration's fields for JSON Schema export.
+    ///
+    /// The default is an empty object schema. `#[derive(Config)]` fills it in
+    /// from the struct's fields.
+    fn schema() -> ConfigSchema
+    where
+        Self: Sized,
+    {
+        ConfigSchema::new()
+    }
+
+    /// Validates the configuration, collecting every issue found.
+    ///
+    /// Override this rather than [`validate`](Self::validate).
+    fn validation(&self) -> ValidationReport {
+        ValidationReport::new()
+    }
+
     /// Validates the configuration.
     ///
     /// Returns Ok(()) if valid, or an error message describing the issue.
+    /// This is a compatibility shim over [`validation`](Self::validation);
+    /// types that override only this method are still supported by callers
+    /// of `validate`, but not by callers of `validation`.
     fn validate(&self) -> Result<(), String> {
-        Ok(())
+        self.validation().into_legacy()
     }
 }
 
 /// Trait for configurations that support file-based loading.
+///
+/// With the `serde` feature, [`load_config`] and [`save_config`] implement
+/// both methods for any serde-compatible type; [`DefaultConfig`] uses them.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
 pub trait FileConfig: Config {
     /// Load configuration from a file path.
     fn from_file(path: &Path) -> Result<Self, String>
@@ -104,12 +213,50 @@ pub trait ConfigBuilder {
     fn build(self) -> Result<Self::Config, String>;
 }
 
+/// Typestate marker for a required builder field that has not been set.
+///
+/// Builders generated by `#[derive(Config)]` carry one type parameter per
+/// `#[config(required)]` field. It starts as `Missing<F>` and becomes
+/// [`Set<F>`] when the field's setter is called; [`ConfigBuilder::build`] is
+/// only implemented once every parameter is `Set`, so forgetting a required
+/// field is a compile error naming the field's marker `F`.
+///
+/// ```rust,ignore
+/// #[derive(Debug, Clone, PartialEq, rustratify::Config)]
+/// struct ClientConfig {
+///     #[config(required)]
+///     endpoint: String,
+/// }
+///
+/// // error[E0599]: no method named `build` found for `ClientConfigBuilder<..>`;
+/// // it is only implemented for `ClientConfigBuilder<Set<ClientConfig_endpoint>>`
+/// ClientConfig::builder().build();
+/// ```
+pub struct Missing<F>(PhantomData<F>);
+
+/// Typestate marker for a required builder field that has been set.
+///
+/// See [`Missing`].
+pub struct Set<F>(PhantomData<F>);
+
 /// A simple default configuration implementation.
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct DefaultConfig {
     /// Configuration name
     pub name: String,
     /// Timeout in milliseconds
+    ///
+    /// When loading, this also accepts durations such as `"30s"`, and files
+    /// may name it `timeout`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "timeout", deserialize_with = "units::optional_millis")
+    )]
     pub timeout_ms: Option<u64>,
     /// Verbose output flag
     pub verbose: bool,
@@ -174,6 +321,18 @@ impl Config for DefaultConfig {
     fn is_debug(&self) -> bool {
         self.debug
     }
+
+    fn schema() -> ConfigSchema {
+        ConfigSchema::new()
+            .title("default")
+            .property(SchemaProperty::of::<String>("name").description("Configuration name"))
+            .property(
+                SchemaProperty::of::<Option<HumanDuration>>("timeout_ms")
+                    .description("Timeout in milliseconds, or a duration such as \"30s\""),
+            )
+            .property(SchemaProperty::of::<bool>("verbose").description("Verbose output flag"))
+            .property(SchemaProperty::of::<bool>("debug").description("Debug mode flag"))
+    }
 }
 
 #[cfg(test)]
@@ -218,6 +377,34 @@ mod tests {
         }
     }
 
+    #[derive(Debug, Clone)]
+    struct ReportingConfig {
+        max_workers: u32,
+        queue: String,
+    }
+
+    impl Config for ReportingConfig {
+        fn validation(&self) -> ValidationReport {
+            let mut report = ValidationReport::new();
+            report.field("max_workers", &self.max_workers).range(1..);
+            report.field("queue", &self.queue).non_empty();
+            report
+        }
+    }
+
+    #[test]
+    fn test_validation_report() {
+        let config = ReportingConfig {
+            max_workers: 0,
+            queue: String::new(),
+        };
+        assert_eq!(config.validation().errors().count(), 2);
+        assert_eq!(
+            config.validate(),
+            Err("max_workers: must be at least 1; queue: must not be empty".to_string())
+        );
+    }
+
     #[test]
     fn test_custom_config_validation() {
         let valid = CustomConfig { max_workers: 4 };
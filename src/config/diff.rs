@@ -0,0 +1,258 @@
+//! Structured differences between two configurations.
+//!
+//! [`ConfigDiff::between`] lists the fields whose values changed, which is
+//! what a reload needs to decide whether to act and what an audit log should
+//! record. Config types opt in by implementing [`DiffConfig`]. Values are
+//! rendered with `Debug`, so [`Secret`](super::Secret) fields show up as
+//! changed without revealing either value.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::DefaultConfig;
+
+/// One changed field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigChange {
+    /// Dotted path to the field, e.g. `server.port`
+    pub path: String,
+    /// Previous value, rendered with `Debug`
+    pub old: String,
+    /// New value, rendered with `Debug`
+    pub new: String,
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.path, self.old, self.new)
+    }
+}
+
+/// Config types whose fields can be compared by [`ConfigDiff`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{ConfigDiff, DiffConfig, DiffFields};
+///
+/// #[derive(Debug, Clone, Default)]
+/// struct AppConfig {
+///     workers: u32,
+///     endpoint: Option<String>,
+/// }
+///
+/// impl DiffConfig for AppConfig {
+///     fn diff_fields(&self, new: &Self, fields: &mut DiffFields<'_>) {
+///         fields.field("workers", &self.workers, &new.workers);
+///         fields.field("endpoint", &self.endpoint, &new.endpoint);
+///     }
+/// }
+///
+/// let old = AppConfig::default();
+/// let new = AppConfig { workers: 8, ..old.clone() };
+/// let diff = ConfigDiff::between(&old, &new);
+/// assert_eq!(diff.to_string(), "workers: 0 -> 8");
+/// ```
+pub trait DiffConfig {
+    /// Compare each field of `self` with the same field of `new`.
+    fn diff_fields(&self, new: &Self, fields: &mut DiffFields<'_>);
+}
+
+/// Field comparer passed to [`DiffConfig::diff_fields`].
+pub struct DiffFields<'a> {
+    prefix: String,
+    changes: &'a mut Vec<ConfigChange>,
+}
+
+impl DiffFields<'_> {
+    /// Record a change if `old` and `new` differ.
+    pub fn field<T>(&mut self, name: &str, old: &T, new: &T) -> bool
+    where
+        T: PartialEq + fmt::Debug + ?Sized,
+    {
+        if old == new {
+            return false;
+        }
+        self.changes.push(ConfigChange {
+            path: self.path(name),
+            old: format!("{old:?}"),
+            new: format!("{new:?}"),
+        });
+        true
+    }
+
+    /// Compare a nested config, prefixing its paths with `name`.
+    pub fn nested<T: DiffConfig + ?Sized>(&mut self, name: &str, old: &T, new: &T) -> bool {
+        let before = self.changes.len();
+        let mut nested = DiffFields {
+            prefix: self.path(name),
+            changes: self.changes,
+        };
+        old.diff_fields(new, &mut nested);
+        self.changes.len() > before
+    }
+
+    fn path(&self, name: &str) -> String {
+        if self.prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{name}", self.prefix)
+        }
+    }
+}
+
+/// The fields that changed between two configurations, in field order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    changes: Vec<ConfigChange>,
+}
+
+impl ConfigDiff {
+    /// Compare `old` with `new`.
+    pub fn between<T: DiffConfig + ?Sized>(old: &T, new: &T) -> Self {
+        let mut changes = Vec::new();
+        old.diff_fields(
+            new,
+            &mut DiffFields {
+                prefix: String::new(),
+                changes: &mut changes,
+            },
+        );
+        Self { changes }
+    }
+
+    /// All changes, in field order.
+    pub fn changes(&self) -> &[ConfigChange] {
+        &self.changes
+    }
+
+    /// The change to `path`, if any.
+    pub fn get(&self, path: &str) -> Option<&ConfigChange> {
+        self.changes.iter().find(|c| c.path == path)
+    }
+
+    /// Whether `path` changed.
+    pub fn contains(&self, path: &str) -> bool {
+        self.get(path).is_some()
+    }
+
+    /// Number of changed fields.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Check if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+impl IntoIterator for ConfigDiff {
+    type Item = ConfigChange;
+    type IntoIter = alloc::vec::IntoIter<ConfigChange>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ConfigDiff {
+    type Item = &'a ConfigChange;
+    type IntoIter = core::slice::Iter<'a, ConfigChange>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
+
+impl DiffConfig for DefaultConfig {
+    fn diff_fields(&self, new: &Self, fields: &mut DiffFields<'_>) {
+        fields.field("name", &self.name, &new.name);
+        fields.field("timeout_ms", &self.timeout_ms, &new.timeout_ms);
+        fields.field("verbose", &self.verbose, &new.verbose);
+        fields.field("debug", &self.debug, &new.debug);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Secret, REDACTED};
+
+    #[test]
+    fn test_default_config_diff() {
+        let old = DefaultConfig::new().with_name("svc");
+        assert!(ConfigDiff::between(&old, &old.clone()).is_empty());
+
+        let new = old.clone().with_timeout_ms(500).verbose();
+        let diff = ConfigDiff::between(&old, &new);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff.get("timeout_ms").unwrap().new, "Some(500)");
+        assert!(diff.contains("verbose"));
+        assert!(!diff.contains("name"));
+        assert_eq!(
+            diff.to_string(),
+            "timeout_ms: None -> Some(500)\nverbose: false -> true"
+        );
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct DbConfig {
+        host: String,
+        password: Secret<String>,
+    }
+
+    impl DiffConfig for DbConfig {
+        fn diff_fields(&self, new: &Self, fields: &mut DiffFields<'_>) {
+            fields.field("host", &self.host, &new.host);
+            fields.field("password", &self.password, &new.password);
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct AppConfig {
+        db: DbConfig,
+        workers: u32,
+    }
+
+    impl DiffConfig for AppConfig {
+        fn diff_fields(&self, new: &Self, fields: &mut DiffFields<'_>) {
+            fields.nested("db", &self.db, &new.db);
+            fields.field("workers", &self.workers, &new.workers);
+        }
+    }
+
+    #[test]
+    fn test_nested_paths_and_redaction() {
+        let old = AppConfig::default();
+        let mut new = old.clone();
+        new.db.password = Secret::new("hunter2".to_string());
+        new.workers = 4;
+
+        let diff = ConfigDiff::between(&old, &new);
+        let paths: Vec<_> = diff.into_iter().map(|c| c.path).collect();
+        assert_eq!(paths, vec!["db.password", "workers"]);
+
+        let diff = ConfigDiff::between(&old, &new);
+        let change = diff.get("db.password").unwrap();
+        assert_eq!(
+            (change.old.as_str(), change.new.as_str()),
+            (REDACTED, REDACTED)
+        );
+        assert!(!diff.to_string().contains("hunter2"));
+    }
+}
@@ -0,0 +1,476 @@
+//! Environment variable configuration overlay.
+//!
+//! An [`EnvConfig`] captures every variable that starts with a prefix and maps
+//! the rest of the name onto a config field: with prefix `APP`,
+//! `APP_TIMEOUT_MS` sets `timeout_ms`. Config types opt in by implementing
+//! [`FromEnv`]. Applying the overlay returns an [`EnvReport`] listing the
+//! variables that were applied, not recognized, or failed to parse.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::ffi::OsString;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+use super::secret::REDACTED;
+use super::units::Millis;
+use super::DefaultConfig;
+
+/// A value that can be parsed from an environment variable.
+pub trait EnvValue: Sized {
+    /// Parse the raw variable value.
+    fn parse_env(value: &str) -> Result<Self, String>;
+
+    /// Whether the raw value must be kept out of reports.
+    fn is_secret() -> bool {
+        false
+    }
+}
+
+impl EnvValue for String {
+    fn parse_env(value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+}
+
+#[cfg(feature = "std")]
+impl EnvValue for PathBuf {
+    fn parse_env(value: &str) -> Result<Self, String> {
+        Ok(PathBuf::from(value))
+    }
+}
+
+impl EnvValue for bool {
+    /// Accepts `true`/`false`, `1`/`0`, `yes`/`no`, and `on`/`off`, ignoring case.
+    fn parse_env(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(true),
+            "false" | "0" | "no" | "off" => Ok(false),
+            _ => Err(format!("expected a boolean, got `{value}`")),
+        }
+    }
+}
+
+macro_rules! impl_env_value_from_str {
+    ($($ty:ty),*) => {
+        $(
+            impl EnvValue for $ty {
+                fn parse_env(value: &str) -> Result<Self, String> {
+                    value.trim().parse().map_err(|e| {
+                        format!("expected {}, got `{value}`: {e}", stringify!($ty))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_env_value_from_str!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64);
+
+/// A variable that matched the prefix but could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvVarError {
+    /// Full variable name, including the prefix
+    pub name: String,
+    /// Raw value, or `[REDACTED]` for [`Secret`](super::Secret) fields
+    pub value: String,
+    /// Why parsing failed
+    pub message: String,
+}
+
+impl fmt::Display for EnvVarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.message)
+    }
+}
+
+/// The outcome of applying an [`EnvConfig`] to a config value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvReport {
+    /// Variables that were applied
+    pub applied: Vec<String>,
+    /// Variables with the prefix that no field claimed
+    pub unknown: Vec<String>,
+    /// Variables that were claimed but failed to parse, and variables with
+    /// the prefix whose value is not valid UTF-8
+    pub invalid: Vec<EnvVarError>,
+}
+
+impl EnvReport {
+    /// Whether every prefixed variable was applied.
+    pub fn is_clean(&self) -> bool {
+        self.unknown.is_empty() && self.invalid.is_empty()
+    }
+}
+
+/// Config types that can be overlaid from environment variables.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{EnvConfig, EnvFields, FromEnv};
+///
+/// #[derive(Debug, Default)]
+/// struct AppConfig {
+///     workers: u32,
+///     endpoint: Option<String>,
+/// }
+///
+/// impl FromEnv for AppConfig {
+///     fn apply_env(&mut self, fields: &mut EnvFields<'_>) {
+///         fields.field("workers", &mut self.workers);
+///         fields.optional("endpoint", &mut self.endpoint);
+///     }
+/// }
+///
+/// let env = EnvConfig::from_vars("APP", [("APP_WORKERS", "8")]);
+/// let (config, report) = env.load::<AppConfig>();
+/// assert_eq!(config.workers, 8);
+/// assert!(report.is_clean());
+/// ```
+pub trait FromEnv {
+    /// Read fields from `fields`, overwriting those that are set.
+    fn apply_env(&mut self, fields: &mut EnvFields<'_>);
+}
+
+/// Field accessor passed to [`FromEnv::apply_env`].
+///
+/// Field names are matched case-insensitively against the variable name with
+/// the prefix removed.
+pub struct EnvFields<'a> {
+    env: &'a EnvConfig,
+    claimed: BTreeSet<String>,
+    report: EnvReport,
+}
+
+impl<'a> EnvFields<'a> {
+    /// Overwrite `slot` if the variable for `name` is set.
+    ///
+    /// Returns whether the field was set. A value that fails to parse leaves
+    /// `slot` unchanged and is recorded in [`EnvReport::invalid`].
+    pub fn field<T: EnvValue>(&mut self, name: &str, slot: &mut T) -> bool {
+        match self.parse(name) {
+            Some(value) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set `slot` to `Some(value)` if the variable for `name` is set.
+    pub fn optional<T: EnvValue>(&mut self, name: &str, slot: &mut Option<T>) -> bool {
+        match self.parse(name) {
+            Some(value) => {
+                *slot = Some(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the raw value of the variable for `name`, marking it as applied.
+    ///
+    /// Use this for fields that need custom parsing.
+    pub fn raw(&mut self, name: &str) -> Option<&str> {
+        let (var, value) = self.claim(name, false)?;
+        self.report.applied.push(var.clone());
+        Some(value)
+    }
+
+    fn parse<T: EnvValue>(&mut self, name: &str) -> Option<T> {
+        let (var, value) = self.claim(name, T::is_secret())?;
+        match T::parse_env(value) {
+            Ok(parsed) => {
+                self.report.applied.push(var.clone());
+                Some(parsed)
+            }
+            Err(message) => {
+                let value = if T::is_secret() {
+                    REDACTED.to_string()
+                } else {
+                    value.clone()
+                };
+                self.report.invalid.push(EnvVarError {
+                    name: var.clone(),
+                    value,
+                    message,
+                });
+                None
+            }
+        }
+    }
+
+    /// Claim the variable for `name`, recording it as invalid if its value
+    /// is not UTF-8.
+    fn claim(&mut self, name: &str, secret: bool) -> Option<&'a (String, String)> {
+        let env = self.env;
+        let key = name.to_ascii_lowercase();
+        if let Some((var, value)) = env.non_utf8.get(&key) {
+            let value = if secret { REDACTED } else { value };
+            self.report.invalid.push(non_utf8_error(var, value));
+            self.claimed.insert(key);
+            return None;
+        }
+        let entry = env.vars.get(&key)?;
+        self.claimed.insert(key);
+        Some(entry)
+    }
+}
+
+fn non_utf8_error(name: &str, value: &str) -> EnvVarError {
+    EnvVarError {
+        name: name.to_string(),
+        value: value.to_string(),
+        message: "value is not valid UTF-8".to_string(),
+    }
+}
+
+/// Configuration source backed by prefixed environment variables.
+#[derive(Debug, Clone)]
+pub struct EnvConfig {
+    prefix: String,
+    /// Field name -> (variable name, value)
+    vars: BTreeMap<String, (String, String)>,
+    /// Like `vars`, for values that are not UTF-8, converted lossily
+    non_utf8: BTreeMap<String, (String, String)>,
+}
+
+impl EnvConfig {
+    /// Capture the process environment variables starting with `prefix`.
+    ///
+    /// A trailing `_` on the prefix is optional: `APP` and `APP_` are the same.
+    /// Variables whose value is not valid UTF-8 are reported in
+    /// [`EnvReport::invalid`] when applied; variables whose name is not valid
+    /// UTF-8 are skipped.
+    #[cfg(feature = "std")]
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self::from_vars_os(prefix, std::env::vars_os())
+    }
+
+    #[cfg(feature = "std")]
+    fn from_vars_os(
+        prefix: impl Into<String>,
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Self {
+        let mut utf8 = Vec::new();
+        let mut non_utf8 = Vec::new();
+        for (name, value) in vars {
+            let Ok(name) = name.into_string() else {
+                continue;
+            };
+            match value.into_string() {
+                Ok(value) => utf8.push((name, value)),
+                Err(value) => non_utf8.push((name, value.to_string_lossy().into_owned())),
+            }
+        }
+        let mut env = Self::from_vars(prefix, utf8);
+        env.non_utf8 = env.capture(non_utf8);
+        env
+    }
+
+    /// Build from an explicit set of variables instead of the process
+    /// environment.
+    pub fn from_vars<I, K, V>(prefix: impl Into<String>, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let mut env = Self {
+            prefix: prefix.into().trim_end_matches('_').to_string(),
+            vars: BTreeMap::new(),
+            non_utf8: BTreeMap::new(),
+        };
+        env.vars = env.capture(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        env
+    }
+
+    /// Key the variables starting with the prefix by field name.
+    fn capture(
+        &self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> BTreeMap<String, (String, String)> {
+        let head = format!("{}_", self.prefix);
+        vars.into_iter()
+            .filter_map(|(name, value)| {
+                let field = name.strip_prefix(&head)?.to_ascii_lowercase();
+                (!field.is_empty()).then_some((field, (name, value)))
+            })
+            .collect()
+    }
+
+    /// The prefix, without a trailing `_`.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Number of captured variables.
+    pub fn len(&self) -> usize {
+        self.vars.len() + self.non_utf8.len()
+    }
+
+    /// Check if no variables were captured.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Overlay the captured variables onto `config`.
+    pub fn apply<C: FromEnv + ?Sized>(&self, config: &mut C) -> EnvReport {
+        let mut fields = EnvFields {
+            env: self,
+            claimed: BTreeSet::new(),
+            report: EnvReport::default(),
+        };
+        config.apply_env(&mut fields);
+
+        let EnvFields {
+            claimed,
+            mut report,
+            ..
+        } = fields;
+        report.unknown = self
+            .vars
+            .iter()
+            .filter(|(field, _)| !claimed.contains(*field))
+            .map(|(_, (name, _))| name.clone())
+            .collect();
+        report.invalid.extend(
+            self.non_utf8
+                .iter()
+                .filter(|(field, _)| !claimed.contains(*field))
+                .map(|(_, (name, value))| non_utf8_error(name, value)),
+        );
+        report
+    }
+
+    /// Return a copy of `base` with the captured variables applied.
+    pub fn overlay<C: FromEnv + Clone>(&self, base: &C) -> (C, EnvReport) {
+        let mut config = base.clone();
+        let report = self.apply(&mut config);
+        (config, report)
+    }
+
+    /// Build a config from its default with the captured variables applied.
+    pub fn load<C: FromEnv + Default>(&self) -> (C, EnvReport) {
+        let mut config = C::default();
+        let report = self.apply(&mut config);
+        (config, report)
+    }
+}
+
+impl FromEnv for DefaultConfig {
+    fn apply_env(&mut self, fields: &mut EnvFields<'_>) {
+        fields.field("name", &mut self.name);
+        // Accept `30s` as well as a bare millisecond count.
+        let mut timeout = None;
+        fields.optional("timeout_ms", &mut timeout);
+        if let Some(Millis(ms)) = timeout {
+            self.timeout_ms = Some(ms);
+        }
+        fields.field("verbose", &mut self.verbose);
+        fields.field("debug", &mut self.debug);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_default_config() {
+        let env = EnvConfig::from_vars(
+            "APP_",
+            [
+                ("APP_NAME", "svc"),
+                ("APP_TIMEOUT_MS", "1.5s"),
+                ("APP_VERBOSE", "yes"),
+                ("OTHER_DEBUG", "true"),
+            ],
+        );
+        assert_eq!(env.prefix(), "APP");
+        assert_eq!(env.len(), 3);
+
+        let base = DefaultConfig::new().with_name("base").debug();
+        let (config, report) = env.overlay(&base);
+        assert_eq!(config.name, "svc");
+        assert_eq!(config.timeout_ms, Some(1500));
+        assert!(config.verbose);
+        assert!(config.debug);
+        assert!(report.is_clean());
+        assert_eq!(report.applied.len(), 3);
+    }
+
+    #[test]
+    fn test_report_unknown_and_invalid() {
+        let env = EnvConfig::from_vars(
+            "APP",
+            [
+                ("APP_TIMEOUT_MS", "soon"),
+                ("APP_DEBUG", "maybe"),
+                ("APP_COLOUR", "blue"),
+            ],
+        );
+
+        let (config, report): (DefaultConfig, _) = env.load();
+        assert_eq!(config.timeout_ms, None);
+        assert!(!config.debug);
+        assert!(!report.is_clean());
+        assert_eq!(report.unknown, vec!["APP_COLOUR".to_string()]);
+
+        let invalid: Vec<_> = report.invalid.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(invalid, vec!["APP_TIMEOUT_MS", "APP_DEBUG"]);
+        assert!(report.invalid[0]
+            .to_string()
+            .starts_with("APP_TIMEOUT_MS: expected a number"));
+    }
+
+    #[cfg(all(unix, feature = "std"))]
+    #[test]
+    fn test_non_utf8_values_are_invalid() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let bad = || OsString::from_vec(vec![b'o', b'n', 0xff]);
+        let env = EnvConfig::from_vars_os(
+            "APP",
+            [
+                (OsString::from("APP_DEBUG"), bad()),
+                (OsString::from("APP_COLOUR"), bad()),
+                (OsString::from("APP_TIMEOUT_MS"), OsString::from("250")),
+                (OsString::from("PATHLIKE"), bad()),
+                (
+                    OsString::from_vec(b"APP_\xff".to_vec()),
+                    OsString::from("1"),
+                ),
+            ],
+        );
+        assert_eq!(env.len(), 3);
+
+        let (config, report): (DefaultConfig, _) = env.load();
+        assert!(!config.debug);
+        assert_eq!(report.applied, vec!["APP_TIMEOUT_MS".to_string()]);
+        assert!(report.unknown.is_empty());
+        let invalid: Vec<_> = report.invalid.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(invalid, vec!["APP_DEBUG", "APP_COLOUR"]);
+        assert_eq!(report.invalid[0].value, "on\u{fffd}");
+        assert_eq!(
+            report.invalid[0].to_string(),
+            "APP_DEBUG: value is not valid UTF-8"
+        );
+    }
+
+    #[test]
+    fn test_bool_coercion() {
+        for raw in ["1", "TRUE", "on"] {
+            assert_eq!(bool::parse_env(raw), Ok(true));
+        }
+        for raw in ["0", "False", "off"] {
+            assert_eq!(bool::parse_env(raw), Ok(false));
+        }
+        assert!(bool::parse_env("2").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! File-based configuration loading.
+//!
+//! [`load_config`] and [`save_config`] pick a [`ConfigFormat`] from the file
+//! extension. JSON is always available; TOML and YAML sit behind the `toml`
+//! and `yaml` features. Loading a file whose format is not enabled fails with
+//! a message naming the missing feature. String values may use `${...}` interpolation;
+//! see [`ConfigFormat::parse_with_env`].
+//!
+//! On `wasm32-unknown-unknown`, which has no filesystem, only the string
+//! parsing and rendering of [`ConfigFormat`] is available.
+
+use std::fmt;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::interpolate::interpolate;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use super::{DefaultConfig, FileConfig};
+
+/// A configuration file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigFormat {
+    /// TOML (`.toml`), requires the `toml` feature
+    Toml,
+    /// YAML (`.yaml`, `.yml`), requires the `yaml` feature
+    Yaml,
+    /// JSON (`.json`)
+    Json,
+}
+
+impl ConfigFormat {
+    /// Detect the format from a path's extension.
+    ///
+    /// Matching is case-insensitive. Returns `None` for unknown extensions.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Whether support for this format was compiled in.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Self::Toml => cfg!(feature = "toml"),
+            Self::Yaml => cfg!(feature = "yaml"),
+            Self::Json => true,
+        }
+    }
+
+    fn feature(&self) -> &'static str {
+        match self {
+            Self::Toml => "toml",
+            Self::Yaml => "yaml",
+            Self::Json => "serde",
+        }
+    }
+
+    fn disabled(&self) -> String {
+        format!("{self} support requires the `{}` feature", self.feature())
+    }
+
+    /// Parse a configuration value from a string in this format.
+    ///
+    /// `${...}` references are resolved against the process environment and
+    /// the document itself; see [`parse_with_env`](Self::parse_with_env).
+    pub fn parse<T: DeserializeOwned>(&self, input: &str) -> Result<T, String> {
+        self.parse_with_env(input, |name| std::env::var(name).ok())
+    }
+
+    /// Parse a configuration value, resolving `${...}` references with `env`.
+    ///
+    /// String values may contain `${NAME}` (environment variable, falling
+    /// back to the top-level key), `${other.key}` (another value by dotted
+    /// path), and `${NAME:-fallback}`. Write `$${` for a literal `${`. A
+    /// string that is a single reference keeps the referenced value's type.
+    /// Reference cycles and unresolved references are errors.
+    pub fn parse_with_env<T, F>(&self, input: &str, env: F) -> Result<T, String>
+    where
+        T: DeserializeOwned,
+        F: Fn(&str) -> Option<String>,
+    {
+        finish(self.parse_value(input)?, env)
+    }
+
+    /// Parse into an untyped value, without interpolation.
+    #[allow(unused_variables)]
+    pub(super) fn parse_value(&self, input: &str) -> Result<serde_json::Value, String> {
+        match self {
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::from_str(input).map_err(|e| e.to_string()),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::from_str(input).map_err(|e| e.to_string()),
+            Self::Json => serde_json::from_str(input).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.disabled()),
+        }
+    }
+
+    /// Render a configuration value as a string in this format.
+    #[allow(unused_variables)]
+    pub fn render<T: Serialize>(&self, value: &T) -> Result<String, String> {
+        match self {
+            #[cfg(feature = "toml")]
+            Self::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            #[cfg(feature = "yaml")]
+            Self::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+            Self::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.disabled()),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Toml => write!(f, "TOML"),
+            Self::Yaml => write!(f, "YAML"),
+            Self::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// Interpolate a parsed value and deserialize it.
+pub(super) fn finish<T, F>(value: serde_json::Value, env: F) -> Result<T, String>
+where
+    T: DeserializeOwned,
+    F: Fn(&str) -> Option<String>,
+{
+    let value = interpolate(value, &env)?;
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn format_of(path: &Path) -> Result<ConfigFormat, String> {
+    ConfigFormat::from_path(path)
+        .ok_or_else(|| format!("unsupported config file extension: {}", path.display()))
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+/// Read a config file, returning its detected format and contents.
+pub(super) fn read_config(path: &Path) -> Result<(ConfigFormat, String), String> {
+    let format = format_of(path)?;
+    let input =
+        fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    Ok((format, input))
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+/// Load a configuration value from a file.
+///
+/// The format is chosen from the file extension (`.toml`, `.yaml`/`.yml`,
+/// `.json`).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rustratify::{load_config, DefaultConfig};
+///
+/// let config: DefaultConfig = load_config("app.toml").unwrap();
+/// ```
+pub fn load_config<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, String> {
+    let path = path.as_ref();
+    let (format, input) = read_config(path)?;
+    format
+        .parse(&input)
+        .map_err(|e| format!("failed to parse {}: {e}", path.display()))
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+/// Save a configuration value to a file.
+///
+/// The format is chosen from the file extension, as for [`load_config`].
+pub fn save_config<T: Serialize>(value: &T, path: impl AsRef<Path>) -> Result<(), String> {
+    let path = path.as_ref();
+    let output = format_of(path)?.render(value)?;
+    fs::write(path, output).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl FileConfig for DefaultConfig {
+    fn from_file(path: &Path) -> Result<Self, String> {
+        load_config(path)
+    }
+
+    fn to_file(&self, path: &Path) -> Result<(), String> {
+        save_config(self, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rustratify-config-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.TOML")),
+            Some(ConfigFormat::Toml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.yml")),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.json")),
+            Some(ConfigFormat::Json)
+        );
+        assert_eq!(ConfigFormat::from_path(Path::new("a.ini")), None);
+        assert_eq!(ConfigFormat::from_path(Path::new("config")), None);
+    }
+
+    #[test]
+    fn test_unknown_extension() {
+        let err = load_config::<DefaultConfig>("settings.ini").unwrap_err();
+        assert!(err.contains("unsupported"));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let err = load_config::<DefaultConfig>(temp_path("missing.toml")).unwrap_err();
+        assert!(err.contains("failed to read"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_load_toml() {
+        let path = temp_path("load.toml");
+        fs::write(
+            &path,
+            "name = \"svc\"\ntimeout = \"1.5s\"\nverbose = true\n",
+        )
+        .unwrap();
+
+        let config = DefaultConfig::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.name, "svc");
+        assert_eq!(config.timeout_ms, Some(1500));
+        assert!(config.verbose);
+        assert!(!config.debug);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_load_yaml() {
+        let config: DefaultConfig = ConfigFormat::Yaml
+            .parse("name: svc\ndebug: true\n")
+            .unwrap();
+        assert_eq!(config.name, "svc");
+        assert!(config.debug);
+    }
+
+    #[test]
+    fn test_round_trip_json() {
+        let path = temp_path("round-trip.json");
+        let config = DefaultConfig::new().with_name("svc").with_timeout_ms(10);
+        config.to_file(&path).unwrap();
+
+        let loaded = DefaultConfig::from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.name, "svc");
+        assert_eq!(loaded.timeout_ms, Some(10));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_interpolation() {
+        let input = r#"
+            name = "${SERVICE:-svc}"
+            timeout = "${defaults.timeout}"
+
+            [defaults]
+            timeout = "2s"
+        "#;
+        let env = |name: &str| (name == "SERVICE").then(|| "billing".to_string());
+
+        let config: DefaultConfig = ConfigFormat::Toml.parse_with_env(input, env).unwrap();
+        assert_eq!(config.name, "billing");
+        assert_eq!(config.timeout_ms, Some(2000));
+
+        let err = ConfigFormat::Toml
+            .parse_with_env::<DefaultConfig, _>("name = \"${name}\"", |_| None)
+            .unwrap_err();
+        assert_eq!(err, "interpolation cycle: name -> name");
+    }
+
+    #[cfg(not(feature = "yaml"))]
+    #[test]
+    fn test_disabled_format() {
+        let err = ConfigFormat::Yaml
+            .parse::<DefaultConfig>("name: svc")
+            .unwrap_err();
+        assert!(err.contains("`yaml` feature"));
+    }
+}
@@ -0,0 +1,237 @@
+//! `${...}` interpolation in loaded configuration values.
+//!
+//! String values may reference environment variables and other keys:
+//!
+//! - `${NAME}` - the environment variable `NAME`, or else the top-level key
+//!   `name` as written
+//! - `${server.port}` - another key, by dotted path (array items by index)
+//! - `${NAME:-fallback}` - `fallback` if the reference cannot be resolved
+//! - `$${` - a literal `${`
+//!
+//! A string that is exactly one reference takes the referenced value with its
+//! type, so `port = "${defaults.port}"` stays a number. References inside
+//! longer strings are stringified. Reference cycles are reported as errors.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+/// Resolve every reference in `root`, looking up environment variables
+/// with `env`.
+pub(crate) fn interpolate(
+    root: Value,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<Value, String> {
+    let mut resolver = Resolver {
+        root: &root,
+        env,
+        resolved: HashMap::new(),
+        stack: Vec::new(),
+    };
+    resolver.resolve_value(root.clone(), "")
+}
+
+struct Resolver<'a> {
+    root: &'a Value,
+    env: &'a dyn Fn(&str) -> Option<String>,
+    /// Resolved values of referenced keys, by path.
+    resolved: HashMap<String, Value>,
+    /// Keys being resolved, for cycle detection.
+    stack: Vec<String>,
+}
+
+enum Piece<'s> {
+    Text(&'s str),
+    Ref(&'s str),
+}
+
+fn join(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{parent}.{key}")
+    }
+}
+
+fn lookup<'v>(root: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.').try_fold(root, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+fn split<'s>(input: &'s str, at: &str) -> Result<Vec<Piece<'s>>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find('$') {
+        let (text, tail) = rest.split_at(start);
+        if let Some(tail) = tail.strip_prefix("$${") {
+            pieces.push(Piece::Text(text));
+            pieces.push(Piece::Text("${"));
+            rest = tail;
+        } else if let Some(tail) = tail.strip_prefix("${") {
+            let end = tail
+                .find('}')
+                .ok_or_else(|| format!("unterminated `${{` in `{at}`"))?;
+            pieces.push(Piece::Text(text));
+            pieces.push(Piece::Ref(&tail[..end]));
+            rest = &tail[end + 1..];
+        } else {
+            pieces.push(Piece::Text(text));
+            pieces.push(Piece::Text("$"));
+            rest = &tail[1..];
+        }
+    }
+    pieces.push(Piece::Text(rest));
+    pieces.retain(|p| !matches!(p, Piece::Text("")));
+    Ok(pieces)
+}
+
+impl Resolver<'_> {
+    fn resolve_value(&mut self, value: Value, at: &str) -> Result<Value, String> {
+        match value {
+            Value::String(s) => self.resolve_str(&s, at),
+            Value::Array(items) => items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| self.resolve_value(item, &join(at, &i.to_string())))
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            Value::Object(map) => map
+                .into_iter()
+                .map(|(key, item)| {
+                    let item = self.resolve_value(item, &join(at, &key))?;
+                    Ok((key, item))
+                })
+                .collect::<Result<_, String>>()
+                .map(Value::Object),
+            other => Ok(other),
+        }
+    }
+
+    fn resolve_str(&mut self, input: &str, at: &str) -> Result<Value, String> {
+        let pieces = split(input, at)?;
+        if let [Piece::Ref(reference)] = pieces.as_slice() {
+            return self.resolve_ref(reference, at);
+        }
+        let mut out = String::new();
+        for piece in pieces {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Ref(reference) => match self.resolve_ref(reference, at)? {
+                    Value::String(s) => out.push_str(&s),
+                    Value::Null => {}
+                    other => out.push_str(&other.to_string()),
+                },
+            }
+        }
+        Ok(Value::String(out))
+    }
+
+    fn resolve_ref(&mut self, reference: &str, at: &str) -> Result<Value, String> {
+        let (name, fallback) = match reference.split_once(":-") {
+            Some((name, fallback)) => (name.trim(), Some(fallback)),
+            None => (reference.trim(), None),
+        };
+        if name.is_empty() {
+            return Err(format!("empty reference `${{{reference}}}` in `{at}`"));
+        }
+        let found = if name.contains('.') {
+            self.resolve_path(name)?
+        } else if let Some(value) = (self.env)(name) {
+            Some(Value::String(value))
+        } else {
+            self.resolve_path(name)?
+        };
+        match (found, fallback) {
+            (Some(value), _) => Ok(value),
+            (None, Some(fallback)) => Ok(Value::String(fallback.to_string())),
+            (None, None) if name.contains('.') => {
+                Err(format!("unknown key `{name}` referenced in `{at}`"))
+            }
+            (None, None) => Err(format!(
+                "environment variable `{name}` is not set (referenced in `{at}`)"
+            )),
+        }
+    }
+
+    fn resolve_path(&mut self, path: &str) -> Result<Option<Value>, String> {
+        if let Some(value) = self.resolved.get(path) {
+            return Ok(Some(value.clone()));
+        }
+        if let Some(pos) = self.stack.iter().position(|p| p == path) {
+            let mut cycle = self.stack[pos..].to_vec();
+            cycle.push(path.to_string());
+            return Err(format!("interpolation cycle: {}", cycle.join(" -> ")));
+        }
+        let Some(raw) = lookup(self.root, path) else {
+            return Ok(None);
+        };
+        self.stack.push(path.to_string());
+        let value = self.resolve_value(raw.clone(), path);
+        self.stack.pop();
+        let value = value?;
+        self.resolved.insert(path.to_string(), value.clone());
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/app".to_string()),
+            "PORT" => Some("9000".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_env_and_key_references() {
+        let config = json!({
+            "data_dir": "${HOME}/data",
+            "cache_dir": "${data_dir}/cache",
+            "defaults": { "port": 8080 },
+            "server": { "port": "${defaults.port}", "url": "http://localhost:${server.port}" },
+            "listen": "${PORT}",
+            "region": "${REGION:-eu-west-1}",
+            "price": "$5 and $${literal}",
+        });
+
+        let resolved = interpolate(config, &env).unwrap();
+        assert_eq!(resolved["data_dir"], "/home/app/data");
+        assert_eq!(resolved["cache_dir"], "/home/app/data/cache");
+        assert_eq!(resolved["server"]["port"], 8080);
+        assert_eq!(resolved["server"]["url"], "http://localhost:8080");
+        assert_eq!(resolved["listen"], "9000");
+        assert_eq!(resolved["region"], "eu-west-1");
+        assert_eq!(resolved["price"], "$5 and ${literal}");
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let config = json!({ "a": "${b.x}", "b": { "x": "${c}" }, "c": "${a}" });
+        let err = interpolate(config, &env).unwrap_err();
+        assert!(err.starts_with("interpolation cycle:"), "{err}");
+        assert!(err.contains("b.x -> c -> a -> b.x"), "{err}");
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = interpolate(json!({ "a": "${MISSING}" }), &env).unwrap_err();
+        assert_eq!(
+            err,
+            "environment variable `MISSING` is not set (referenced in `a`)"
+        );
+
+        let err = interpolate(json!({ "a": ["${b.c}"] }), &env).unwrap_err();
+        assert_eq!(err, "unknown key `b.c` referenced in `a.0`");
+
+        let err = interpolate(json!({ "a": "${oops" }), &env).unwrap_err();
+        assert_eq!(err, "unterminated `${` in `a`");
+    }
+}
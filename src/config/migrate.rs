@@ -0,0 +1,243 @@
+//! Versioned configuration files.
+//!
+//! A [`ConfigMigration`] knows the config version the binary understands and
+//! the steps that upgrade older files one version at a time. Loading through
+//! it reads the file's `version` field, applies each step in order, and
+//! refuses files written for a newer binary. Steps run on the parsed document
+//! before `${...}` interpolation and deserialization, so they can rename,
+//! move, and convert keys freely.
+
+use std::collections::BTreeMap;
+use std::fmt;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::file::finish;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use super::file::read_config;
+use super::ConfigFormat;
+
+type Step = Box<dyn Fn(&mut Value) -> Result<(), String> + Send + Sync>;
+
+/// Upgrades older configuration documents to the current version.
+///
+/// Files without a version field are treated as version 1.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::ConfigMigration;
+/// use serde_json::json;
+///
+/// let migration = ConfigMigration::new(2).step(1, |config| {
+///     // v2 renamed `timeout` to `timeout_ms`
+///     if let Some(timeout) = config.as_object_mut().and_then(|m| m.remove("timeout")) {
+///         config["timeout_ms"] = timeout;
+///     }
+///     Ok(())
+/// });
+///
+/// let upgraded = migration.migrate(json!({ "timeout": 500 })).unwrap();
+/// assert_eq!(upgraded, json!({ "timeout_ms": 500, "version": 2 }));
+/// ```
+pub struct ConfigMigration {
+    current: u32,
+    field: String,
+    steps: BTreeMap<u32, Step>,
+}
+
+impl ConfigMigration {
+    /// Create a migration to `current`, the version this binary writes.
+    pub fn new(current: u32) -> Self {
+        Self {
+            current,
+            field: "version".to_string(),
+            steps: BTreeMap::new(),
+        }
+    }
+
+    /// Read the version from a top-level key other than `version`.
+    pub fn version_field(mut self, name: impl Into<String>) -> Self {
+        self.field = name.into();
+        self
+    }
+
+    /// Register the step that upgrades a document from `from` to `from + 1`.
+    ///
+    /// Registering the same version twice replaces the earlier step.
+    pub fn step<F>(mut self, from: u32, step: F) -> Self
+    where
+        F: Fn(&mut Value) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.steps.insert(from, Box::new(step));
+        self
+    }
+
+    /// The version this binary understands.
+    pub fn current_version(&self) -> u32 {
+        self.current
+    }
+
+    /// The version recorded in `document`, or 1 if it has none.
+    pub fn version_of(&self, document: &Value) -> Result<u32, String> {
+        match document.get(&self.field) {
+            None | Some(Value::Null) => Ok(1),
+            Some(Value::Number(n)) => n
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| format!("invalid config {}: {n}", self.field)),
+            Some(Value::String(s)) => s
+                .trim()
+                .trim_start_matches('v')
+                .parse()
+                .map_err(|_| format!("invalid config {}: `{s}`", self.field)),
+            Some(other) => Err(format!("invalid config {}: {other}", self.field)),
+        }
+    }
+
+    /// Upgrade `document` to the current version.
+    ///
+    /// The version field is set to the current version afterwards. Fails if
+    /// the document is newer than the current version or a step is missing.
+    pub fn migrate(&self, mut document: Value) -> Result<Value, String> {
+        let found = self.version_of(&document)?;
+        if found > self.current {
+            return Err(format!(
+                "config version {found} is newer than the supported version {}",
+                self.current
+            ));
+        }
+        for version in found..self.current {
+            let step = self.steps.get(&version).ok_or_else(|| {
+                format!(
+                    "no migration from config version {version} to {}",
+                    version + 1
+                )
+            })?;
+            step(&mut document).map_err(|e| {
+                format!("migrating config version {version} to {}: {e}", version + 1)
+            })?;
+        }
+        if let Value::Object(map) = &mut document {
+            map.insert(self.field.clone(), Value::from(self.current));
+        }
+        Ok(document)
+    }
+
+    /// Parse, migrate, interpolate, and deserialize a configuration string.
+    pub fn parse<T: DeserializeOwned>(
+        &self,
+        format: ConfigFormat,
+        input: &str,
+    ) -> Result<T, String> {
+        let document = self.migrate(format.parse_value(input)?)?;
+        finish(document, |name| std::env::var(name).ok())
+    }
+
+    /// Load a configuration file, migrating it to the current version.
+    ///
+    /// Works like [`load_config`](super::load_config) with a migration step
+    /// before deserialization. The file itself is not rewritten.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn load<T: DeserializeOwned>(&self, path: impl AsRef<Path>) -> Result<T, String> {
+        let path = path.as_ref();
+        let (format, input) = read_config(path)?;
+        self.parse(format, &input)
+            .map_err(|e| format!("failed to load {}: {e}", path.display()))
+    }
+}
+
+impl fmt::Debug for ConfigMigration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigMigration")
+            .field("current", &self.current)
+            .field("field", &self.field)
+            .field("steps", &self.steps.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn migration() -> ConfigMigration {
+        ConfigMigration::new(3)
+            .step(1, |config| {
+                let workers = config["threads"].take();
+                config["workers"] = workers;
+                config.as_object_mut().unwrap().remove("threads");
+                Ok(())
+            })
+            .step(2, |config| {
+                let Some(workers) = config["workers"].as_u64() else {
+                    return Err("`workers` must be a number".to_string());
+                };
+                config["pool"] = json!({ "size": workers });
+                config.as_object_mut().unwrap().remove("workers");
+                Ok(())
+            })
+    }
+
+    #[test]
+    fn test_applies_steps_in_order() {
+        let migrated = migration().migrate(json!({ "threads": 4 })).unwrap();
+        assert_eq!(migrated, json!({ "pool": { "size": 4 }, "version": 3 }));
+
+        let current = json!({ "version": 3, "pool": { "size": 1 } });
+        assert_eq!(migration().migrate(current.clone()).unwrap(), current);
+
+        let from_v2 = json!({ "version": "v2", "workers": 2 });
+        assert_eq!(
+            migration().migrate(from_v2).unwrap()["pool"]["size"],
+            json!(2)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = migration().migrate(json!({ "version": 4 })).unwrap_err();
+        assert_eq!(
+            err,
+            "config version 4 is newer than the supported version 3"
+        );
+
+        let err = migration()
+            .migrate(json!({ "version": 2, "workers": "many" }))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "migrating config version 2 to 3: `workers` must be a number"
+        );
+
+        let err = ConfigMigration::new(2).migrate(json!({})).unwrap_err();
+        assert_eq!(err, "no migration from config version 1 to 2");
+
+        let err = migration()
+            .version_field("schema")
+            .migrate(json!({ "schema": true }))
+            .unwrap_err();
+        assert_eq!(err, "invalid config schema: true");
+    }
+
+    #[test]
+    fn test_parse_into_config() {
+        use crate::config::DefaultConfig;
+
+        let migration = ConfigMigration::new(2).step(1, |config| {
+            if let Some(label) = config.as_object_mut().unwrap().remove("label") {
+                config["name"] = label;
+            }
+            Ok(())
+        });
+        let config: DefaultConfig = migration
+            .parse(ConfigFormat::Json, r#"{ "label": "svc", "verbose": true }"#)
+            .unwrap();
+        assert_eq!(config.name, "svc");
+        assert!(config.verbose);
+    }
+}
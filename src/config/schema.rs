@@ -0,0 +1,528 @@
+//! JSON Schema export for configuration types.
+//!
+//! [`Config::schema`](super::Config::schema) describes a config's fields as a
+//! [`ConfigSchema`], which renders as a JSON Schema (draft 2020-12) document.
+//! Hosts can use it to validate user-provided files before loading them and
+//! to drive editor autocompletion. `#[derive(Config)]` generates the schema
+//! from field types, doc comments, defaults, and `validate(...)` checks.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+use super::{ByteSize, HumanDuration, Secret, SecretValue};
+
+/// The JSON Schema dialect emitted by [`ConfigSchema::to_json`].
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// The shape of a configuration value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaType {
+    /// Any value
+    Any,
+    /// `true` or `false`
+    Boolean,
+    /// A whole number
+    Integer,
+    /// Any number
+    Number,
+    /// A string
+    String,
+    /// A list of values of one type
+    Array(Box<SchemaType>),
+    /// A map from string keys to values of one type
+    Map(Box<SchemaType>),
+    /// A nested configuration
+    Object(Box<ConfigSchema>),
+    /// Any one of several types, e.g. `30000` or `"30s"`
+    OneOf(Vec<SchemaType>),
+}
+
+/// Types that can describe themselves in a [`ConfigSchema`].
+///
+/// Implemented for primitives, strings, paths, collections, `Option`,
+/// [`Secret`], [`HumanDuration`], and [`ByteSize`]. `#[derive(Config)]`
+/// implements it for the derived struct so configs can be nested.
+pub trait SchemaValue {
+    /// The schema type of this value.
+    fn schema_type() -> SchemaType;
+
+    /// Whether the value is a secret, emitted as `writeOnly`.
+    fn is_secret() -> bool {
+        false
+    }
+}
+
+macro_rules! impl_schema_value {
+    ($kind:ident: $($ty:ty),*) => {
+        $(
+            impl SchemaValue for $ty {
+                fn schema_type() -> SchemaType {
+                    SchemaType::$kind
+                }
+            }
+        )*
+    };
+}
+
+impl_schema_value!(Boolean: bool);
+impl_schema_value!(Integer: u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+impl_schema_value!(Number: f32, f64);
+impl_schema_value!(String: String);
+#[cfg(feature = "std")]
+impl_schema_value!(String: PathBuf);
+
+impl SchemaValue for HumanDuration {
+    fn schema_type() -> SchemaType {
+        SchemaType::OneOf(vec![SchemaType::Integer, SchemaType::String])
+    }
+}
+
+impl SchemaValue for ByteSize {
+    fn schema_type() -> SchemaType {
+        SchemaType::OneOf(vec![SchemaType::Integer, SchemaType::String])
+    }
+}
+
+impl<T: SchemaValue> SchemaValue for Option<T> {
+    fn schema_type() -> SchemaType {
+        T::schema_type()
+    }
+
+    fn is_secret() -> bool {
+        T::is_secret()
+    }
+}
+
+impl<T: SchemaValue + SecretValue> SchemaValue for Secret<T> {
+    fn schema_type() -> SchemaType {
+        T::schema_type()
+    }
+
+    fn is_secret() -> bool {
+        true
+    }
+}
+
+impl<T: SchemaValue> SchemaValue for Vec<T> {
+    fn schema_type() -> SchemaType {
+        SchemaType::Array(Box::new(T::schema_type()))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<V: SchemaValue, S> SchemaValue for HashMap<String, V, S> {
+    fn schema_type() -> SchemaType {
+        SchemaType::Map(Box::new(V::schema_type()))
+    }
+}
+
+impl<V: SchemaValue> SchemaValue for BTreeMap<String, V> {
+    fn schema_type() -> SchemaType {
+        SchemaType::Map(Box::new(V::schema_type()))
+    }
+}
+
+/// One field of a [`ConfigSchema`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaProperty {
+    /// Field name as written in config files
+    pub name: String,
+    /// Value type
+    pub kind: SchemaType,
+    /// Human-readable description
+    pub description: Option<String>,
+    /// Default value as JSON text, e.g. `5000` or `"jobs"`
+    pub default: Option<String>,
+    /// Whether files must set the field
+    pub required: bool,
+    /// Whether the value is a secret
+    pub secret: bool,
+    /// Smallest allowed number
+    pub minimum: Option<f64>,
+    /// Largest allowed number
+    pub maximum: Option<f64>,
+    /// Whether strings and lists must be non-empty
+    pub non_empty: bool,
+}
+
+impl SchemaProperty {
+    /// Create a property of the given type.
+    pub fn new(name: impl Into<String>, kind: SchemaType) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            description: None,
+            default: None,
+            required: false,
+            secret: false,
+            minimum: None,
+            maximum: None,
+            non_empty: false,
+        }
+    }
+
+    /// Create a property from a Rust field type.
+    ///
+    /// The property is not required; fields with defaults may be omitted.
+    pub fn of<T: SchemaValue>(name: impl Into<String>) -> Self {
+        Self {
+            secret: T::is_secret(),
+            ..Self::new(name, T::schema_type())
+        }
+    }
+
+    /// Set the description.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the default value, given as JSON text.
+    pub fn default_json(mut self, json: impl Into<String>) -> Self {
+        self.default = Some(json.into());
+        self
+    }
+
+    /// Mark the property as required.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Set the smallest allowed number.
+    pub fn minimum(mut self, min: f64) -> Self {
+        self.minimum = Some(min);
+        self
+    }
+
+    /// Set the largest allowed number.
+    pub fn maximum(mut self, max: f64) -> Self {
+        self.maximum = Some(max);
+        self
+    }
+
+    /// Require strings and lists to be non-empty.
+    pub fn non_empty(mut self) -> Self {
+        self.non_empty = true;
+        self
+    }
+}
+
+/// A description of a configuration type's fields.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{ConfigSchema, SchemaProperty};
+///
+/// let schema = ConfigSchema::new()
+///     .title("worker")
+///     .property(SchemaProperty::of::<u32>("workers").minimum(1.0).default_json("4"));
+///
+/// let json = schema.to_json();
+/// assert!(json.contains("\"minimum\": 1"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigSchema {
+    /// Schema title, usually the config name
+    pub title: Option<String>,
+    /// Human-readable description
+    pub description: Option<String>,
+    /// Fields, in declaration order
+    pub properties: Vec<SchemaProperty>,
+}
+
+impl ConfigSchema {
+    /// Create an empty schema.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the title.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the description.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Add a property.
+    pub fn property(mut self, property: SchemaProperty) -> Self {
+        self.properties.push(property);
+        self
+    }
+
+    /// Look up a property by name.
+    pub fn get(&self, name: &str) -> Option<&SchemaProperty> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Render as a pretty-printed JSON Schema document.
+    pub fn to_json(&self) -> String {
+        let mut fields = vec![("$schema".to_string(), Json::Str(JSON_SCHEMA_DIALECT.into()))];
+        fields.extend(self.json_fields());
+        let mut out = String::new();
+        Json::Obj(fields).write(&mut out, 0);
+        out
+    }
+
+    /// Render as a JSON value. Requires the `serde` feature.
+    #[cfg(feature = "serde")]
+    pub fn to_value(&self) -> serde_json::Value {
+        serde_json::from_str(&self.to_json()).expect("schema renders valid JSON")
+    }
+
+    fn json_fields(&self) -> Vec<(String, Json)> {
+        let mut fields = Vec::new();
+        if let Some(title) = &self.title {
+            fields.push(("title".into(), Json::Str(title.clone())));
+        }
+        if let Some(description) = &self.description {
+            fields.push(("description".into(), Json::Str(description.clone())));
+        }
+        fields.push(("type".into(), Json::Str("object".into())));
+        let properties = self
+            .properties
+            .iter()
+            .map(|p| (p.name.clone(), p.to_json()))
+            .collect();
+        fields.push(("properties".into(), Json::Obj(properties)));
+        let required: Vec<_> = self
+            .properties
+            .iter()
+            .filter(|p| p.required)
+            .map(|p| Json::Str(p.name.clone()))
+            .collect();
+        if !required.is_empty() {
+            fields.push(("required".into(), Json::Arr(required)));
+        }
+        fields
+    }
+}
+
+impl SchemaProperty {
+    fn to_json(&self) -> Json {
+        let mut fields = self.kind.json_fields();
+        if let Some(description) = &self.description {
+            fields.push(("description".into(), Json::Str(description.clone())));
+        }
+        if let Some(default) = &self.default {
+            fields.push(("default".into(), Json::Raw(default.clone())));
+        }
+        if let Some(min) = self.minimum {
+            fields.push(("minimum".into(), Json::Num(min)));
+        }
+        if let Some(max) = self.maximum {
+            fields.push(("maximum".into(), Json::Num(max)));
+        }
+        if self.non_empty {
+            let key = match self.kind {
+                SchemaType::Array(_) => "minItems",
+                SchemaType::Map(_) | SchemaType::Object(_) => "minProperties",
+                _ => "minLength",
+            };
+            fields.push((key.into(), Json::Num(1.0)));
+        }
+        if self.secret {
+            fields.push(("writeOnly".into(), Json::Raw("true".into())));
+        }
+        Json::Obj(fields)
+    }
+}
+
+impl SchemaType {
+    fn json_fields(&self) -> Vec<(String, Json)> {
+        let ty = |name: &str| vec![("type".to_string(), Json::Str(name.into()))];
+        match self {
+            Self::Any => Vec::new(),
+            Self::Boolean => ty("boolean"),
+            Self::Integer => ty("integer"),
+            Self::Number => ty("number"),
+            Self::String => ty("string"),
+            Self::Array(item) => {
+                let mut fields = ty("array");
+                fields.push(("items".into(), Json::Obj(item.json_fields())));
+                fields
+            }
+            Self::Map(value) => {
+                let mut fields = ty("object");
+                fields.push((
+                    "additionalProperties".into(),
+                    Json::Obj(value.json_fields()),
+                ));
+                fields
+            }
+            Self::Object(schema) => schema.json_fields(),
+            Self::OneOf(kinds) => {
+                let kinds = kinds.iter().map(|k| Json::Obj(k.json_fields())).collect();
+                vec![("oneOf".into(), Json::Arr(kinds))]
+            }
+        }
+    }
+}
+
+/// Minimal JSON tree for rendering schemas without requiring `serde`.
+enum Json {
+    Str(String),
+    Num(f64),
+    Raw(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Self::Str(s) => write_str(out, s),
+            Self::Num(n) if n.abs() < 1e15 && *n == *n as i64 as f64 => {
+                let _ = write!(out, "{}", *n as i64);
+            }
+            Self::Num(n) => {
+                let _ = write!(out, "{n}");
+            }
+            Self::Raw(raw) => out.push_str(raw),
+            Self::Arr(items) if items.is_empty() => out.push_str("[]"),
+            Self::Arr(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    pad(out, indent + 1);
+                    item.write(out, indent + 1);
+                }
+                out.push('\n');
+                pad(out, indent);
+                out.push(']');
+            }
+            Self::Obj(fields) if fields.is_empty() => out.push_str("{}"),
+            Self::Obj(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    pad(out, indent + 1);
+                    write_str(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                out.push('\n');
+                pad(out, indent);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn pad(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, DefaultConfig};
+
+    #[test]
+    fn test_property_types() {
+        assert_eq!(
+            SchemaProperty::of::<Option<u32>>("workers").kind,
+            SchemaType::Integer
+        );
+        assert!(SchemaProperty::of::<Secret<String>>("password").secret);
+        #[cfg(feature = "std")]
+        assert_eq!(
+            Vec::<PathBuf>::schema_type(),
+            SchemaType::Array(Box::new(SchemaType::String))
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let schema = ConfigSchema::new()
+            .title("db")
+            .property(
+                SchemaProperty::of::<String>("host")
+                    .required()
+                    .non_empty()
+                    .description("Host \"name\""),
+            )
+            .property(SchemaProperty::of::<Secret<String>>("password"))
+            .property(
+                SchemaProperty::of::<u16>("port")
+                    .default_json("5432")
+                    .minimum(1.0)
+                    .maximum(65535.0),
+            );
+
+        let expected = r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "db",
+  "type": "object",
+  "properties": {
+    "host": {
+      "type": "string",
+      "description": "Host \"name\"",
+      "minLength": 1
+    },
+    "password": {
+      "type": "string",
+      "writeOnly": true
+    },
+    "port": {
+      "type": "integer",
+      "default": 5432,
+      "minimum": 1,
+      "maximum": 65535
+    }
+  },
+  "required": [
+    "host"
+  ]
+}"#;
+        assert_eq!(schema.to_json(), expected);
+    }
+
+    #[test]
+    fn test_default_config_schema() {
+        let schema = DefaultConfig::schema();
+        assert_eq!(schema.title.as_deref(), Some("default"));
+        assert_eq!(schema.get("verbose").unwrap().kind, SchemaType::Boolean);
+        assert_eq!(
+            schema.get("timeout_ms").unwrap().kind,
+            HumanDuration::schema_type()
+        );
+
+        #[cfg(feature = "serde")]
+        assert_eq!(schema.to_value()["properties"]["name"]["type"], "string");
+    }
+}
@@ -0,0 +1,199 @@
+//! Secret configuration values.
+//!
+//! [`Secret`] wraps a credential so it cannot leak through logs: `Debug`,
+//! `Display`, and serialization all print `[REDACTED]`. Deserialization and
+//! [`EnvConfig`](super::EnvConfig) read the real value, so typing a field as
+//! `Secret<String>` is all it takes to mark it secret. With the `zeroize`
+//! feature, the value is wiped from memory on drop.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{EnvValue, IsEmpty};
+
+/// The text shown in place of a secret value.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Values that can be held in a [`Secret`].
+///
+/// `wipe` is called when the secret is dropped. The provided implementations
+/// overwrite the memory when the `zeroize` feature is enabled and do nothing
+/// otherwise. Implement it with an empty body for your own types if there is
+/// nothing to wipe.
+pub trait SecretValue {
+    /// Overwrite the value in place.
+    fn wipe(&mut self) {}
+}
+
+macro_rules! impl_secret_value {
+    ($($ty:ty),*) => {
+        $(
+            impl SecretValue for $ty {
+                fn wipe(&mut self) {
+                    #[cfg(feature = "zeroize")]
+                    zeroize::Zeroize::zeroize(self);
+                }
+            }
+        )*
+    };
+}
+
+impl_secret_value!(String, Vec<u8>);
+impl_secret_value!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128);
+
+impl<T: SecretValue> SecretValue for Option<T> {
+    fn wipe(&mut self) {
+        if let Some(value) = self {
+            value.wipe();
+        }
+    }
+}
+
+/// A value that is redacted in `Debug`, `Display`, and serialized output.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::Secret;
+///
+/// let password = Secret::new("hunter2".to_string());
+/// assert_eq!(format!("{password:?}"), "[REDACTED]");
+/// assert_eq!(password.expose_secret(), "hunter2");
+/// ```
+pub struct Secret<T: SecretValue>(T);
+
+impl<T: SecretValue> Secret<T> {
+    /// Wrap a value.
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Access the real value.
+    ///
+    /// Keep the returned reference away from logging and error messages.
+    pub fn expose_secret(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: SecretValue> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: SecretValue + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: SecretValue + Default> Default for Secret<T> {
+    fn default() -> Self {
+        Self(T::default())
+    }
+}
+
+impl<T: SecretValue + PartialEq> PartialEq for Secret<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: SecretValue> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl<T: SecretValue> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T: SecretValue> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T: SecretValue + EnvValue> EnvValue for Secret<T> {
+    fn parse_env(value: &str) -> Result<Self, String> {
+        T::parse_env(value)
+            .map(Self)
+            .map_err(|_| "invalid secret value".to_string())
+    }
+
+    fn is_secret() -> bool {
+        true
+    }
+}
+
+impl<T: SecretValue + IsEmpty> IsEmpty for Secret<T> {
+    fn is_empty_value(&self) -> bool {
+        self.0.is_empty_value()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: SecretValue> serde::Serialize for Secret<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: SecretValue + serde::Deserialize<'de>> serde::Deserialize<'de> for Secret<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{EnvConfig, EnvFields, FromEnv};
+
+    #[test]
+    fn test_redacted_formatting() {
+        let secret = Secret::new("s3cr3t".to_string());
+        assert_eq!(format!("{secret:?}"), REDACTED);
+        assert_eq!(secret.to_string(), REDACTED);
+        assert_eq!(secret.expose_secret(), "s3cr3t");
+    }
+
+    #[derive(Debug, Default)]
+    struct DbConfig {
+        password: Secret<String>,
+        pin: Option<Secret<u32>>,
+    }
+
+    impl FromEnv for DbConfig {
+        fn apply_env(&mut self, fields: &mut EnvFields<'_>) {
+            fields.field("password", &mut self.password);
+            fields.optional("pin", &mut self.pin);
+        }
+    }
+
+    #[test]
+    fn test_env_does_not_leak_invalid_values() {
+        let env = EnvConfig::from_vars("DB", [("DB_PASSWORD", "hunter2"), ("DB_PIN", "12ab")]);
+        let (config, report) = env.load::<DbConfig>();
+
+        assert_eq!(config.password.expose_secret(), "hunter2");
+        assert!(!format!("{config:?}").contains("hunter2"));
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].value, REDACTED);
+        assert!(!report.invalid[0].message.contains("12ab"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_redacts_on_write_only() {
+        let secret: Secret<String> = serde_json::from_str("\"token\"").unwrap();
+        assert_eq!(secret.expose_secret(), "token");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"[REDACTED]\"");
+    }
+}
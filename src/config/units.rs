@@ -0,0 +1,457 @@
+//! Human-friendly durations and sizes in configuration values.
+//!
+//! [`parse_duration`] accepts values like `30s`, `5m`, `1h30m`, or `250ms`;
+//! [`parse_size`] accepts values like `512`, `64KB`, or `1.5GiB`. The
+//! [`HumanDuration`] and [`ByteSize`] field types use them when loading from
+//! files (with `serde`) and environment variables.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+use core::str::FromStr;
+use core::time::Duration;
+
+use super::EnvValue;
+
+/// Split `input` into `(number, unit)` segments, e.g. `1h30m` into
+/// `[("1", "h"), ("30", "m")]`.
+fn segments(input: &str) -> Result<Vec<(&str, &str)>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("empty value".to_string());
+    }
+    let mut segments = Vec::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let num_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        if num_len == 0 {
+            return Err(format!("expected a number in `{input}`"));
+        }
+        let (number, tail) = rest.split_at(num_len);
+        let tail = tail.trim_start();
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.' || c.is_whitespace())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        segments.push((number, unit));
+        rest = tail.trim_start();
+    }
+    Ok(segments)
+}
+
+fn parse_number(number: &str, input: &str) -> Result<f64, String> {
+    number
+        .parse::<f64>()
+        .map_err(|_| format!("invalid number `{number}` in `{input}`"))
+}
+
+/// Parse a duration such as `30s`, `5m`, `1h30m`, `1.5s`, or `250ms`.
+///
+/// Units: `ns`, `us` (or `µs`), `ms`, `s`, `m` (or `min`), `h`, and `d`.
+/// Every number needs a unit, except a bare `0`.
+///
+/// ```rust
+/// use rustratify::parse_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+/// assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+/// assert!(parse_duration("30").is_err());
+/// ```
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    if input.trim() == "0" {
+        return Ok(Duration::ZERO);
+    }
+    let mut total = Duration::ZERO;
+    for (number, unit) in segments(input)? {
+        let nanos_per_unit: f64 = match unit.to_ascii_lowercase().as_str() {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" | "sec" | "secs" => 1e9,
+            "m" | "min" | "mins" => 60e9,
+            "h" | "hr" | "hrs" => 3600e9,
+            "d" | "day" | "days" => 86400e9,
+            "" => return Err(format!("missing unit after `{number}` in `{input}`")),
+            other => return Err(format!("unknown duration unit `{other}` in `{input}`")),
+        };
+        let nanos = parse_number(number, input)? * nanos_per_unit;
+        let segment = Duration::try_from_secs_f64(nanos / 1e9)
+            .map_err(|_| format!("duration `{input}` is out of range"))?;
+        total = total
+            .checked_add(segment)
+            .ok_or_else(|| format!("duration `{input}` is out of range"))?;
+    }
+    Ok(total)
+}
+
+/// Format a duration in the compact form accepted by [`parse_duration`].
+pub fn format_duration(duration: Duration) -> String {
+    if duration.is_zero() {
+        return "0s".to_string();
+    }
+    if !duration.subsec_nanos().is_multiple_of(1_000_000) {
+        return match duration.as_nanos() {
+            n if n.is_multiple_of(1000) => format!("{}us", n / 1000),
+            n => format!("{n}ns"),
+        };
+    }
+    let mut out = String::new();
+    let mut secs = duration.as_secs();
+    for (unit, size) in [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)] {
+        if secs >= size {
+            out.push_str(&format!("{}{unit}", secs / size));
+            secs %= size;
+        }
+    }
+    let millis = duration.subsec_millis();
+    if millis > 0 {
+        out.push_str(&format!("{millis}ms"));
+    }
+    out
+}
+
+/// Parse a size in bytes such as `512`, `512B`, `64KB`, or `1.5GiB`.
+///
+/// Units are binary multiples and case-insensitive: `K`, `KB`, and `KiB` all
+/// mean 1024 bytes. A bare number is a byte count.
+///
+/// ```rust
+/// use rustratify::parse_size;
+///
+/// assert_eq!(parse_size("512KB"), Ok(512 * 1024));
+/// assert_eq!(parse_size("1.5k"), Ok(1536));
+/// ```
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let segments = segments(input)?;
+    let [(number, unit)] = segments.as_slice() else {
+        return Err(format!("expected a single size in `{input}`"));
+    };
+    let exponent = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 1,
+        "m" | "mb" | "mib" => 2,
+        "g" | "gb" | "gib" => 3,
+        "t" | "tb" | "tib" => 4,
+        other => return Err(format!("unknown size unit `{other}` in `{input}`")),
+    };
+    if exponent == 0 {
+        return number
+            .parse()
+            .map_err(|_| format!("invalid byte count `{number}` in `{input}`"));
+    }
+    let bytes = parse_number(number, input)? * (1u64 << (10 * exponent)) as f64;
+    if bytes > u64::MAX as f64 || bytes != bytes as u64 as f64 {
+        return Err(format!("size `{input}` is not a whole number of bytes"));
+    }
+    Ok(bytes as u64)
+}
+
+/// Format a byte count using the largest unit that divides it exactly.
+pub fn format_size(bytes: u64) -> String {
+    for (unit, exponent) in [("TB", 4), ("GB", 3), ("MB", 2), ("KB", 1)] {
+        let size = 1u64 << (10 * exponent);
+        if bytes >= size && bytes.is_multiple_of(size) {
+            return format!("{}{unit}", bytes / size);
+        }
+    }
+    format!("{bytes}B")
+}
+
+/// Parse milliseconds from either a bare integer or a duration string.
+///
+/// Used for the legacy `timeout_ms` fields, where bare numbers already mean
+/// milliseconds.
+pub(crate) fn parse_millis(input: &str) -> Result<u64, String> {
+    if let Ok(ms) = input.trim().parse::<u64>() {
+        return Ok(ms);
+    }
+    let duration = parse_duration(input)?;
+    u64::try_from(duration.as_millis()).map_err(|_| format!("duration `{input}` is out of range"))
+}
+
+/// Milliseconds that parse from a bare integer or a duration string.
+pub(crate) struct Millis(pub(crate) u64);
+
+impl EnvValue for Millis {
+    fn parse_env(value: &str) -> Result<Self, String> {
+        parse_millis(value).map(Self)
+    }
+}
+
+/// A [`Duration`] that parses from strings like `30s` or `1h30m`.
+///
+/// In serialized configs it may also be an integer number of milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(pub Duration);
+
+impl HumanDuration {
+    /// The wrapped duration.
+    pub fn get(&self) -> Duration {
+        self.0
+    }
+}
+
+impl Deref for HumanDuration {
+    type Target = Duration;
+
+    fn deref(&self) -> &Duration {
+        &self.0
+    }
+}
+
+impl From<Duration> for HumanDuration {
+    fn from(duration: Duration) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        parse_duration(s).map(Self)
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_duration(self.0))
+    }
+}
+
+impl EnvValue for HumanDuration {
+    fn parse_env(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+impl EnvValue for Duration {
+    fn parse_env(value: &str) -> Result<Self, String> {
+        parse_duration(value)
+    }
+}
+
+/// A byte count that parses from strings like `512KB` or `1GiB`.
+///
+/// In serialized configs it may also be an integer number of bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// The size in bytes.
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(bytes: u64) -> Self {
+        Self(bytes)
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        parse_size(s).map(Self)
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_size(self.0))
+    }
+}
+
+impl EnvValue for ByteSize {
+    fn parse_env(value: &str) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use core::fmt;
+    use core::time::Duration;
+
+    use serde::de::{self, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{parse_duration, parse_millis, ByteSize, HumanDuration};
+
+    /// Accepts either an integer or a string parsed with `parse`.
+    struct IntOrStr<F> {
+        expecting: &'static str,
+        parse: F,
+    }
+
+    impl<'de, T, F> Visitor<'de> for IntOrStr<F>
+    where
+        F: FnOnce(&str) -> Result<T, String>,
+        T: From<u64>,
+    {
+        type Value = T;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.expecting)
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+            Ok(T::from(v))
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+            u64::try_from(v)
+                .map(T::from)
+                .map_err(|_| E::custom("value must not be negative"))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            (self.parse)(v).map_err(E::custom)
+        }
+    }
+
+    /// A duration read as milliseconds when given as an integer.
+    struct DurationField(Duration);
+
+    impl From<u64> for DurationField {
+        fn from(ms: u64) -> Self {
+            Self(Duration::from_millis(ms))
+        }
+    }
+
+    impl Serialize for HumanDuration {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for HumanDuration {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let DurationField(duration) = deserializer.deserialize_any(IntOrStr {
+                expecting: "a duration such as \"30s\" or milliseconds",
+                parse: |s: &str| parse_duration(s).map(DurationField),
+            })?;
+            Ok(Self(duration))
+        }
+    }
+
+    impl Serialize for ByteSize {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ByteSize {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(IntOrStr {
+                expecting: "a size such as \"512KB\" or a byte count",
+                parse: |s: &str| s.parse::<ByteSize>(),
+            })
+        }
+    }
+
+    /// `deserialize_with` for optional millisecond fields that also accept
+    /// duration strings.
+    pub(crate) fn optional_millis<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<u64>, D::Error> {
+        let ms: Option<MillisField> = Option::deserialize(deserializer)?;
+        Ok(ms.map(|m| m.0))
+    }
+
+    struct MillisField(u64);
+
+    impl From<u64> for MillisField {
+        fn from(ms: u64) -> Self {
+            Self(ms)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for MillisField {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(IntOrStr {
+                expecting: "milliseconds or a duration such as \"30s\"",
+                parse: |s: &str| parse_millis(s).map(MillisField),
+            })
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+pub(crate) use serde_impls::optional_millis;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h 30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2d"), Ok(Duration::from_secs(172_800)));
+        assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10").unwrap_err().contains("missing unit"));
+        assert!(parse_duration("10y")
+            .unwrap_err()
+            .contains("unknown duration unit"));
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("512B"), Ok(512));
+        assert_eq!(parse_size("512KB"), Ok(512 * 1024));
+        assert_eq!(parse_size("2 MiB"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_size("1g"), Ok(1 << 30));
+
+        assert!(parse_size("1.5B").is_err());
+        assert!(parse_size("12XB")
+            .unwrap_err()
+            .contains("unknown size unit"));
+        assert!(parse_size("1KB2").is_err());
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        for text in ["1h30m", "250ms", "2d1s", "0s", "15us"] {
+            let duration = parse_duration(text).unwrap();
+            assert_eq!(format_duration(duration), text);
+        }
+        assert_eq!(format_size(512 * 1024), "512KB");
+        assert_eq!(format_size(1500), "1500B");
+        assert_eq!(parse_millis("1500"), Ok(1500));
+        assert_eq!(parse_millis("2s"), Ok(2000));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let d: HumanDuration = serde_json::from_str("\"1m\"").unwrap();
+        assert_eq!(d.get(), Duration::from_secs(60));
+        let d: HumanDuration = serde_json::from_str("250").unwrap();
+        assert_eq!(d.get(), Duration::from_millis(250));
+        assert_eq!(serde_json::to_string(&d).unwrap(), "\"250ms\"");
+
+        let s: ByteSize = serde_json::from_str("\"4KB\"").unwrap();
+        assert_eq!(s.bytes(), 4096);
+        assert!(serde_json::from_str::<ByteSize>("-1").is_err());
+    }
+}
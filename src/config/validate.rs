@@ -0,0 +1,410 @@
+//! Configuration validation with accumulated, field-level issues.
+//!
+//! [`Config::validation`](super::Config::validation) returns a
+//! [`ValidationReport`] instead of stopping at the first problem. Checks are
+//! written with [`ValidationReport::field`]:
+//!
+//! ```rust
+//! use rustratify::ValidationReport;
+//!
+//! let workers = 0u32;
+//! let queue = "";
+//!
+//! let mut report = ValidationReport::new();
+//! report.field("workers", &workers).range(1..=64);
+//! report.field("queue", queue).non_empty();
+//!
+//! assert!(!report.is_valid());
+//! assert_eq!(report.errors().count(), 2);
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+/// How serious a validation issue is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Suspicious but usable
+    Warning,
+    /// The configuration must not be used
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single problem with one configuration field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// Dotted path to the field, e.g. `server.port`; empty for the whole config
+    pub path: String,
+    /// What is wrong
+    pub message: String,
+    /// How serious it is
+    pub severity: Severity,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Every issue found while validating a configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert the result of a legacy `validate() -> Result<(), String>`.
+    pub fn from_legacy(result: Result<(), String>) -> Self {
+        let mut report = Self::new();
+        if let Err(message) = result {
+            report.error("", message);
+        }
+        report
+    }
+
+    /// Record an issue.
+    pub fn push(&mut self, issue: ValidationIssue) {
+        self.issues.push(issue);
+    }
+
+    /// Record an error for `path`.
+    pub fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(ValidationIssue {
+            path: path.into(),
+            message: message.into(),
+            severity: Severity::Error,
+        });
+    }
+
+    /// Record a warning for `path`.
+    pub fn warning(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(ValidationIssue {
+            path: path.into(),
+            message: message.into(),
+            severity: Severity::Warning,
+        });
+    }
+
+    /// Start checking a field's value.
+    pub fn field<'r, T: ?Sized>(
+        &'r mut self,
+        path: impl Into<String>,
+        value: &'r T,
+    ) -> FieldCheck<'r, T> {
+        FieldCheck {
+            report: self,
+            path: path.into(),
+            value,
+            severity: Severity::Error,
+        }
+    }
+
+    /// Add the issues from a nested config's report under `prefix`.
+    pub fn nested(&mut self, prefix: &str, report: ValidationReport) {
+        for mut issue in report.issues {
+            issue.path = if issue.path.is_empty() {
+                prefix.to_string()
+            } else {
+                format!("{prefix}.{}", issue.path)
+            };
+            self.issues.push(issue);
+        }
+    }
+
+    /// Whether no errors were recorded. Warnings are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// All recorded issues, in order.
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.issues
+    }
+
+    /// Issues with [`Severity::Error`].
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.with_severity(Severity::Error)
+    }
+
+    /// Issues with [`Severity::Warning`].
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.with_severity(Severity::Warning)
+    }
+
+    fn with_severity(&self, severity: Severity) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(move |i| i.severity == severity)
+    }
+
+    /// Number of issues.
+    pub fn len(&self) -> usize {
+        self.issues.len()
+    }
+
+    /// Check if no issues were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// `Ok` if the report is valid, keeping any warnings; `Err` otherwise.
+    pub fn into_result(self) -> Result<Self, Self> {
+        if self.is_valid() {
+            Ok(self)
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Collapse into the legacy `Result<(), String>` form.
+    ///
+    /// Errors are joined with `; `. Warnings are dropped.
+    pub fn into_legacy(self) -> Result<(), String> {
+        if self.is_valid() {
+            return Ok(());
+        }
+        let messages: Vec<_> = self.errors().map(ToString::to_string).collect();
+        Err(messages.join("; "))
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}: {issue}", issue.severity)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for ValidationReport {}
+
+/// A chain of checks on one field, created by [`ValidationReport::field`].
+///
+/// Each failed check records one issue; later checks still run.
+pub struct FieldCheck<'r, T: ?Sized> {
+    report: &'r mut ValidationReport,
+    path: String,
+    value: &'r T,
+    severity: Severity,
+}
+
+impl<'r, T: ?Sized> FieldCheck<'r, T> {
+    /// Record failures of the following checks as warnings.
+    pub fn warn(mut self) -> Self {
+        self.severity = Severity::Warning;
+        self
+    }
+
+    /// Record `message` unless `ok` returns true for the value.
+    pub fn check(mut self, ok: impl FnOnce(&T) -> bool, message: impl Into<String>) -> Self {
+        if !ok(self.value) {
+            self.fail(message.into());
+        }
+        self
+    }
+
+    /// Require the value to lie within `range`.
+    pub fn range<R>(mut self, range: R) -> Self
+    where
+        R: RangeBounds<T>,
+        T: PartialOrd + fmt::Display,
+    {
+        let message = match range.start_bound() {
+            Bound::Included(min) if self.value < min => Some(format!("must be at least {min}")),
+            Bound::Excluded(min) if self.value <= min => {
+                Some(format!("must be greater than {min}"))
+            }
+            _ => None,
+        }
+        .or_else(|| match range.end_bound() {
+            Bound::Included(max) if self.value > max => Some(format!("must be at most {max}")),
+            Bound::Excluded(max) if self.value >= max => Some(format!("must be less than {max}")),
+            _ => None,
+        });
+        if let Some(message) = message {
+            self.fail(message);
+        }
+        self
+    }
+
+    /// Require the value to be non-empty.
+    pub fn non_empty(mut self) -> Self
+    where
+        T: IsEmpty,
+    {
+        if self.value.is_empty_value() {
+            self.fail("must not be empty".to_string());
+        }
+        self
+    }
+
+    /// Require the value to match `pattern`. Requires the `regex` feature.
+    #[cfg(feature = "regex")]
+    pub fn matches(mut self, pattern: &regex::Regex) -> Self
+    where
+        T: AsRef<str>,
+    {
+        if !pattern.is_match(self.value.as_ref()) {
+            self.fail(format!("must match `{}`", pattern.as_str()));
+        }
+        self
+    }
+
+    fn fail(&mut self, message: String) {
+        self.report.push(ValidationIssue {
+            path: self.path.clone(),
+            message,
+            severity: self.severity,
+        });
+    }
+}
+
+/// Values that can be empty, for [`FieldCheck::non_empty`].
+pub trait IsEmpty {
+    /// Whether the value is empty.
+    fn is_empty_value(&self) -> bool;
+}
+
+impl IsEmpty for str {
+    fn is_empty_value(&self) -> bool {
+        self.trim().is_empty()
+    }
+}
+
+impl IsEmpty for String {
+    fn is_empty_value(&self) -> bool {
+        self.as_str().is_empty_value()
+    }
+}
+
+#[cfg(feature = "std")]
+impl IsEmpty for Path {
+    fn is_empty_value(&self) -> bool {
+        self.as_os_str().is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+impl IsEmpty for PathBuf {
+    fn is_empty_value(&self) -> bool {
+        self.as_os_str().is_empty()
+    }
+}
+
+impl<T> IsEmpty for [T] {
+    fn is_empty_value(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<T> IsEmpty for Vec<T> {
+    fn is_empty_value(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V, S> IsEmpty for HashMap<K, V, S> {
+    fn is_empty_value(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+impl<K, V> IsEmpty for BTreeMap<K, V> {
+    fn is_empty_value(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_messages() {
+        let mut report = ValidationReport::new();
+        report.field("a", &0u32).range(1..);
+        report.field("b", &10u32).range(..10);
+        report.field("c", &5u32).range(1..=5);
+        report.field("d", &0.5f64).range(0.0..=1.0);
+
+        let messages: Vec<_> = report.issues().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            vec!["a: must be at least 1", "b: must be less than 10"]
+        );
+    }
+
+    #[test]
+    fn test_severity_and_legacy_shim() {
+        let mut report = ValidationReport::new();
+        report.field("name", "").non_empty();
+        report.field("workers", &200u32).warn().range(..=128);
+
+        assert!(!report.is_valid());
+        assert_eq!(report.errors().count(), 1);
+        assert_eq!(report.warnings().count(), 1);
+        assert_eq!(
+            report.clone().into_legacy(),
+            Err("name: must not be empty".into())
+        );
+
+        let warnings_only = ValidationReport::from_legacy(Ok(()));
+        assert!(warnings_only.into_result().is_ok());
+        assert!(ValidationReport::from_legacy(Err("bad".into()))
+            .into_result()
+            .is_err());
+    }
+
+    #[test]
+    fn test_nested_paths() {
+        let mut inner = ValidationReport::new();
+        inner.error("port", "must not be zero");
+        inner.error("", "unreachable host");
+
+        let mut report = ValidationReport::new();
+        report.nested("server", inner);
+        let paths: Vec<_> = report.issues().iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, vec!["server.port", "server"]);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_matches() {
+        let pattern = regex::Regex::new("^[a-z]+$").unwrap();
+        let mut report = ValidationReport::new();
+        report.field("queue", "jobs").matches(&pattern);
+        report.field("topic", "Jobs-1").matches(&pattern);
+        assert_eq!(report.len(), 1);
+        assert_eq!(report.issues()[0].path, "topic");
+    }
+}
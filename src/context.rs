@@ -0,0 +1,494 @@
+//! Per-operation context passed from the facade down to providers.
+//!
+//! A [`Context`] travels with one logical operation, such as a run, so that
+//! every provider call made on its behalf can be attributed to it. With the
+//! `otel` feature it also carries the OpenTelemetry context, so spans started
+//! by providers become children of the caller's span, including across
+//! process boundaries via [`inject`](Context::inject) and
+//! [`extract`](Context::extract).
+//!
+//! A correlation ID ties together everything done for one request across
+//! layers and processes, and a causation ID names the message or operation
+//! that directly caused this one. Both are stamped on event envelopes,
+//! audit records and telemetry spans. IDs from outside, such as an
+//! `X-Correlation-ID` header, are adopted with
+//! [`adopt_correlation_id`](Context::adopt_correlation_id).
+//!
+//! With the `std` feature a context can carry a deadline. It is an absolute
+//! point in time, so every stage of a pipeline that passes the context on
+//! hands the next one what is left of the budget rather than the full
+//! timeout again.
+//!
+//! With the `std` feature a context also carries the run's [`Warnings`]
+//! log, shared by every clone, so providers can report non-fatal problems
+//! with [`warn`](Context::warn) and the host sees them all at the end.
+
+use alloc::string::String;
+#[cfg(feature = "tokio")]
+use core::future::Future;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "std")]
+use crate::config::Config;
+#[cfg(feature = "std")]
+use crate::error::{ProviderError, ProviderResult};
+#[cfg(feature = "std")]
+use crate::output::{Warning, Warnings};
+
+/// The context of one logical operation.
+///
+/// Cloning is cheap. Builder methods return a new context, leaving the
+/// original unchanged for sibling operations.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::Context;
+///
+/// let cx = Context::new().with_run_id("run-42");
+/// assert_eq!(cx.run_id(), Some("run-42"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    run_id: Option<String>,
+    subject: Option<String>,
+    correlation_id: Option<String>,
+    causation_id: Option<String>,
+    #[cfg(feature = "std")]
+    deadline: Option<Deadline>,
+    #[cfg(feature = "std")]
+    warnings: Warnings,
+    #[cfg(feature = "otel")]
+    otel: opentelemetry::Context,
+}
+
+impl Context {
+    /// Create an empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the run this operation belongs to.
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// The run this operation belongs to, if any.
+    pub fn run_id(&self) -> Option<&str> {
+        self.run_id.as_deref()
+    }
+
+    /// Set who or what the operation acts for, e.g. a user or tenant ID.
+    ///
+    /// Feature flag rollouts use it to give the same subject the same
+    /// answer every time.
+    pub fn with_subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Who or what the operation acts for, if set.
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    /// Set the ID that correlates everything done for the same request.
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// The ID that correlates everything done for the same request, if any.
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Set the ID of the message or operation that caused this one.
+    pub fn with_causation_id(mut self, causation_id: impl Into<String>) -> Self {
+        self.causation_id = Some(causation_id.into());
+        self
+    }
+
+    /// The ID of the message or operation that caused this one, if any.
+    pub fn causation_id(&self) -> Option<&str> {
+        self.causation_id.as_deref()
+    }
+}
+
+/// The longest externally supplied correlation ID that is adopted.
+#[cfg(feature = "std")]
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+#[cfg(feature = "std")]
+impl Context {
+    /// Give the context a new random correlation ID unless it has one.
+    ///
+    /// Requires the `std` feature.
+    pub fn ensure_correlation_id(self) -> Self {
+        match self.correlation_id {
+            Some(_) => self,
+            None => self.with_correlation_id(generate_id()),
+        }
+    }
+
+    /// Use `supplied`, e.g. the value of an `X-Correlation-ID` header, as
+    /// the correlation ID, or a new random one if it is missing or unfit.
+    ///
+    /// An ID is fit when it has 1 to 128 visible ASCII characters, so
+    /// untrusted input cannot smuggle line breaks or unbounded data into
+    /// logs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::Context;
+    ///
+    /// let cx = Context::new().adopt_correlation_id(Some("req-7f3a"));
+    /// assert_eq!(cx.correlation_id(), Some("req-7f3a"));
+    ///
+    /// let cx = Context::new().adopt_correlation_id(Some("bad\nid"));
+    /// assert_ne!(cx.correlation_id(), Some("bad\nid"));
+    /// assert_eq!(cx.correlation_id().unwrap().len(), 32);
+    /// ```
+    ///
+    /// Requires the `std` feature.
+    pub fn adopt_correlation_id(self, supplied: Option<&str>) -> Self {
+        match supplied {
+            Some(id)
+                if (1..=MAX_CORRELATION_ID_LEN).contains(&id.len())
+                    && id.bytes().all(|b| b.is_ascii_graphic()) =>
+            {
+                self.with_correlation_id(id)
+            }
+            _ => self.with_correlation_id(generate_id()),
+        }
+    }
+
+    /// A context for an operation caused by `cause`, such as an event or
+    /// message ID, keeping the correlation ID and everything else.
+    ///
+    /// Requires the `std` feature.
+    pub fn caused_by(&self, cause: impl Into<String>) -> Self {
+        self.clone()
+            .ensure_correlation_id()
+            .with_causation_id(cause)
+    }
+
+    /// Record warnings in `warnings`, e.g. a log the host keeps per run,
+    /// instead of the context's own.
+    ///
+    /// Requires the `std` feature.
+    pub fn with_warnings(mut self, warnings: Warnings) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Report a non-fatal problem with the operation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{Context, Warning};
+    ///
+    /// let cx = Context::new();
+    /// cx.clone().with_run_id("run-42").warn("cache unavailable, recomputing");
+    /// cx.warn(Warning::new("`tabs` is deprecated").with_code("deprecated-option"));
+    /// assert_eq!(cx.warnings().len(), 2);
+    /// ```
+    ///
+    /// Requires the `std` feature.
+    pub fn warn(&self, warning: impl Into<Warning>) {
+        self.warnings.push(warning);
+    }
+
+    /// The warnings reported through this context and its clones.
+    ///
+    /// Requires the `std` feature.
+    pub fn warnings(&self) -> &Warnings {
+        &self.warnings
+    }
+}
+
+/// A random 128-bit ID as 32 lowercase hex digits.
+#[cfg(feature = "std")]
+fn generate_id() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let half = |salt: u64| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(count);
+        hasher.write_u64(salt);
+        hasher.finish()
+    };
+    format!("{:016x}{:016x}", half(0), half(1))
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+struct Deadline {
+    at: Instant,
+    /// The budget the deadline was set with, for error messages
+    budget: Duration,
+}
+
+#[cfg(feature = "std")]
+impl Deadline {
+    fn exceeded(&self) -> ProviderError {
+        ProviderError::Timeout(u64::try_from(self.budget.as_millis()).unwrap_or(u64::MAX))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Context {
+    /// Set the operation to end by `deadline`.
+    ///
+    /// A context never extends the deadline it already has: the earlier of
+    /// the two is kept, so a stage cannot give its callees more time than
+    /// it was given.
+    ///
+    /// Requires the `std` feature.
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        let budget = deadline.saturating_duration_since(Instant::now());
+        self.set_deadline(Deadline {
+            at: deadline,
+            budget,
+        })
+    }
+
+    fn set_deadline(mut self, deadline: Deadline) -> Self {
+        if self.deadline.is_none_or(|current| deadline.at < current.at) {
+            self.deadline = Some(deadline);
+        }
+        self
+    }
+
+    /// Set the operation to end within `timeout` from now, or by the
+    /// deadline it already has if that is sooner.
+    ///
+    /// Requires the `std` feature.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        match Instant::now().checked_add(timeout) {
+            Some(at) => self.set_deadline(Deadline {
+                at,
+                budget: timeout,
+            }),
+            None => self,
+        }
+    }
+
+    /// Set the deadline from [`Config::timeout`], if the config has one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{Config, Context};
+    /// use std::time::Duration;
+    ///
+    /// struct Run;
+    ///
+    /// impl Config for Run {
+    ///     fn timeout(&self) -> Option<Duration> { Some(Duration::from_secs(30)) }
+    /// }
+    ///
+    /// let cx = Context::new().with_config_timeout(&Run);
+    /// assert!(cx.remaining().unwrap() <= Duration::from_secs(30));
+    /// assert!(!cx.is_expired());
+    /// ```
+    ///
+    /// Requires the `std` feature.
+    pub fn with_config_timeout<C: Config + ?Sized>(self, config: &C) -> Self {
+        match config.timeout() {
+            Some(timeout) => self.with_timeout(timeout),
+            None => self,
+        }
+    }
+
+    /// The point in time the operation must end by, if any.
+    ///
+    /// Requires the `std` feature.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline.map(|deadline| deadline.at)
+    }
+
+    /// The time left until the deadline, zero once it has passed, or `None`
+    /// without a deadline.
+    ///
+    /// Pass this to a downstream service as its timeout.
+    ///
+    /// Requires the `std` feature.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Whether the deadline has passed.
+    ///
+    /// Requires the `std` feature.
+    pub fn is_expired(&self) -> bool {
+        self.remaining()
+            .is_some_and(|remaining| remaining.is_zero())
+    }
+
+    /// Fail with [`ProviderError::Timeout`] if the deadline has passed.
+    ///
+    /// Call before starting work on the operation's behalf.
+    ///
+    /// Requires the `std` feature.
+    pub fn check_deadline(&self) -> ProviderResult<()> {
+        match self.deadline {
+            Some(deadline) if self.is_expired() => Err(deadline.exceeded()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Context {
+    /// Drive `call` to completion within the deadline.
+    ///
+    /// Fails with [`ProviderError::Timeout`] without polling `call` if the
+    /// deadline has already passed, and cancels `call` by dropping it if
+    /// the deadline passes while it runs.
+    ///
+    /// Requires the `tokio` feature.
+    pub async fn within_deadline<T>(
+        &self,
+        call: impl Future<Output = ProviderResult<T>>,
+    ) -> ProviderResult<T> {
+        let Some(deadline) = self.deadline else {
+            return call.await;
+        };
+        self.check_deadline()?;
+        tokio::time::timeout_at(deadline.at.into(), call)
+            .await
+            .map_err(|_| deadline.exceeded())?
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Context {
+    /// A context carrying the current OpenTelemetry context.
+    ///
+    /// Requires the `otel` feature.
+    pub fn current() -> Self {
+        Self::new().with_otel(opentelemetry::Context::current())
+    }
+
+    /// Replace the OpenTelemetry context.
+    ///
+    /// Requires the `otel` feature.
+    pub fn with_otel(mut self, otel: opentelemetry::Context) -> Self {
+        self.otel = otel;
+        self
+    }
+
+    /// The OpenTelemetry context.
+    ///
+    /// Requires the `otel` feature.
+    pub fn otel(&self) -> &opentelemetry::Context {
+        &self.otel
+    }
+
+    /// Write the OpenTelemetry context into `carrier`, e.g. request headers,
+    /// using the global text map propagator.
+    ///
+    /// Requires the `otel` feature.
+    pub fn inject(&self, carrier: &mut dyn opentelemetry::propagation::Injector) {
+        opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&self.otel, carrier)
+        });
+    }
+
+    /// A context continuing the trace found in `carrier`, using the global
+    /// text map propagator.
+    ///
+    /// Requires the `otel` feature.
+    pub fn extract(carrier: &dyn opentelemetry::propagation::Extractor) -> Self {
+        let otel = opentelemetry::global::get_text_map_propagator(|propagator| {
+            propagator.extract(carrier)
+        });
+        Self::new().with_otel(otel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_deadline_only_shrinks() {
+        let cx = Context::new();
+        assert_eq!(cx.remaining(), None);
+        assert!(cx.check_deadline().is_ok());
+
+        let parent = cx.with_timeout(Duration::from_secs(60));
+        let child = parent.clone().with_timeout(Duration::from_secs(3600));
+        assert_eq!(child.deadline(), parent.deadline());
+        let tighter = child.with_timeout(Duration::from_secs(1));
+        assert!(tighter.deadline() < parent.deadline());
+        assert!(tighter.remaining().unwrap() <= Duration::from_secs(1));
+
+        let expired = Context::new().with_deadline(Instant::now());
+        assert!(expired.is_expired());
+        assert!(matches!(
+            expired.check_deadline(),
+            Err(ProviderError::Timeout(0))
+        ));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn test_within_deadline() {
+        let cx = Context::new().with_timeout(Duration::from_millis(50));
+        let value = cx.within_deadline(async { Ok(7) }).await.unwrap();
+        assert_eq!(value, 7);
+
+        let slow = cx.within_deadline(async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        });
+        assert!(matches!(slow.await, Err(ProviderError::Timeout(50))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_correlation_ids() {
+        let cx = Context::new().ensure_correlation_id();
+        let id = cx.correlation_id().unwrap().to_string();
+        assert_eq!(id.len(), 32);
+        assert_eq!(
+            cx.clone().ensure_correlation_id().correlation_id(),
+            Some(&*id)
+        );
+        assert_ne!(
+            Context::new().ensure_correlation_id().correlation_id(),
+            Some(&*id)
+        );
+
+        let child = cx.caused_by("event-3");
+        assert_eq!(child.correlation_id(), Some(&*id));
+        assert_eq!(child.causation_id(), Some("event-3"));
+        assert_eq!(cx.causation_id(), None);
+
+        let long = "x".repeat(MAX_CORRELATION_ID_LEN + 1);
+        for unfit in [None, Some(""), Some("a b"), Some(long.as_str())] {
+            let adopted = Context::new().adopt_correlation_id(unfit);
+            assert_eq!(adopted.correlation_id().unwrap().len(), 32);
+        }
+    }
+
+    #[test]
+    fn test_run_id() {
+        let parent = Context::new();
+        let child = parent.clone().with_run_id("r1");
+        assert_eq!(parent.run_id(), None);
+        assert_eq!(child.run_id(), Some("r1"));
+    }
+}
@@ -1,7 +1,28 @@
 //! Error types for Rustratify framework.
 
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use core::error::Error as StdError;
+use core::fmt;
+
 use thiserror::Error;
 
+mod fields;
+mod multi;
+mod trace;
+#[cfg(feature = "serde")]
+mod wire;
+
+pub use fields::{ErrorFields, FieldValue};
+pub use multi::MultiError;
+pub use trace::Traced;
+#[cfg(feature = "serde")]
+pub use wire::WireError;
+
+/// A boxed error from a domain crate, as carried by the `Custom` variants.
+pub type BoxError = Box<dyn StdError + Send + Sync + 'static>;
+
 /// Root error type for Rustratify operations.
 #[derive(Error, Debug)]
 pub enum RustratifyError {
@@ -20,6 +41,48 @@ pub enum RustratifyError {
     /// Generic error with message
     #[error("{0}")]
     Other(String),
+
+    /// An error from a domain crate, carried without stringifying it
+    #[error(transparent)]
+    Custom(BoxError),
+
+    /// Several failures from a fan-out operation
+    #[error(transparent)]
+    Multi(#[from] MultiError),
+
+    /// Another error with metadata attached by [`with_field`](Self::with_field)
+    #[error("{error}")]
+    WithFields {
+        /// The error the fields describe
+        error: Box<RustratifyError>,
+        /// The attached metadata
+        fields: ErrorFields,
+    },
+}
+
+impl RustratifyError {
+    /// Wrap a domain error.
+    pub fn custom<E: StdError + Send + Sync + 'static>(err: E) -> Self {
+        Self::Custom(Box::new(err))
+    }
+
+    /// The domain error, if this is a `Custom` error of type `E`.
+    ///
+    /// Looks through `Provider(ProviderError::Custom(..))` as well.
+    pub fn downcast_ref<E: StdError + 'static>(&self) -> Option<&E> {
+        match self {
+            Self::Custom(err) => err.downcast_ref(),
+            Self::Provider(err) => err.downcast_ref(),
+            Self::WithFields { error, .. } => error.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl From<BoxError> for RustratifyError {
+    fn from(err: BoxError) -> Self {
+        Self::Custom(err)
+    }
 }
 
 /// Errors that can occur in provider operations.
@@ -56,6 +119,59 @@ pub enum ProviderError {
     /// Provider was cancelled
     #[error("Operation was cancelled")]
     Cancelled,
+
+    /// Provider is not permitted to run with the permissions it requested
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// A run exceeded one of its resource limits and was terminated
+    #[error("Resource limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    /// An error from a domain crate, carried without stringifying it
+    ///
+    /// Held in an `Arc` so `ProviderError` stays `Clone`.
+    #[error(transparent)]
+    Custom(Arc<dyn StdError + Send + Sync + 'static>),
+
+    /// Another error with metadata attached by [`with_field`](Self::with_field)
+    #[error("{error}")]
+    WithFields {
+        /// The error the fields describe
+        error: Box<ProviderError>,
+        /// The attached metadata
+        fields: ErrorFields,
+    },
+}
+
+impl ProviderError {
+    /// Wrap a domain error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::ProviderError;
+    ///
+    /// #[derive(Debug, thiserror::Error)]
+    /// #[error("quota exceeded for {0}")]
+    /// struct QuotaError(String);
+    ///
+    /// let err = ProviderError::custom(QuotaError("acme".into()));
+    /// assert_eq!(err.to_string(), "quota exceeded for acme");
+    /// assert!(err.downcast_ref::<QuotaError>().is_some());
+    /// ```
+    pub fn custom<E: StdError + Send + Sync + 'static>(err: E) -> Self {
+        Self::Custom(Arc::new(err))
+    }
+
+    /// The domain error, if this is a `Custom` error of type `E`.
+    pub fn downcast_ref<E: StdError + 'static>(&self) -> Option<&E> {
+        match self {
+            Self::Custom(err) => err.downcast_ref(),
+            Self::WithFields { error, .. } => error.downcast_ref(),
+            _ => None,
+        }
+    }
 }
 
 /// Errors that can occur in registry operations.
@@ -76,14 +192,25 @@ pub enum RegistryError {
     /// Invalid provider name
     #[error("Invalid provider name: {0}")]
     InvalidName(String),
+
+    /// Several providers tied for the best match
+    #[error("Ambiguous provider match: {0}")]
+    AmbiguousMatch(String),
 }
 
+#[cfg(feature = "std")]
 impl From<std::io::Error> for ProviderError {
     fn from(err: std::io::Error) -> Self {
         ProviderError::IoError(err.to_string())
     }
 }
 
+impl From<BoxError> for ProviderError {
+    fn from(err: BoxError) -> Self {
+        ProviderError::Custom(Arc::from(err))
+    }
+}
+
 impl From<String> for ProviderError {
     fn from(msg: String) -> Self {
         ProviderError::ExecutionFailed(msg)
@@ -96,6 +223,346 @@ impl From<&str> for ProviderError {
     }
 }
 
+/// Broad classes of errors, for mapping to HTTP statuses and alerting rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ErrorCategory {
+    /// The caller asked for something invalid or unavailable
+    User,
+    /// A temporary failure; retrying may succeed
+    Transient,
+    /// A bug or unexpected failure inside the application
+    Internal,
+    /// The application or a module is misconfigured
+    Configuration,
+}
+
+impl ErrorCategory {
+    /// Whether retrying the operation may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::Transient)
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User => write!(f, "user"),
+            Self::Transient => write!(f, "transient"),
+            Self::Internal => write!(f, "internal"),
+            Self::Configuration => write!(f, "configuration"),
+        }
+    }
+}
+
+/// How serious an error is, for choosing a log level or alert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ErrorSeverity {
+    /// Expected in normal operation, e.g. a caller asking for something missing
+    Info,
+    /// Worth noticing, e.g. a transient failure that may recover
+    Warning,
+    /// Needs attention
+    Error,
+    /// Needs immediate attention
+    Critical,
+}
+
+impl ErrorSeverity {
+    /// The `tracing` level to log an error of this severity at.
+    pub fn level(&self) -> tracing::Level {
+        match self {
+            Self::Info => tracing::Level::INFO,
+            Self::Warning => tracing::Level::WARN,
+            Self::Error | Self::Critical => tracing::Level::ERROR,
+        }
+    }
+}
+
+impl fmt::Display for ErrorSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+            Self::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A process exit status for CLI hosts.
+///
+/// Each [`ErrorCategory`] maps to a fixed status taken from BSD `sysexits.h`,
+/// so every command of a tool exits with the same code for the same kind of
+/// failure. These values are stable:
+///
+/// | Status | Value | Category |
+/// |--------|-------|----------|
+/// | [`SUCCESS`](Self::SUCCESS) | 0 | — |
+/// | [`USER`](Self::USER) | 64 | [`ErrorCategory::User`] |
+/// | [`INTERNAL`](Self::INTERNAL) | 70 | [`ErrorCategory::Internal`] |
+/// | [`TRANSIENT`](Self::TRANSIENT) | 75 | [`ErrorCategory::Transient`] |
+/// | [`CONFIGURATION`](Self::CONFIGURATION) | 78 | [`ErrorCategory::Configuration`] |
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{ErrorCode, ExitStatus, ProviderError};
+///
+/// fn run() -> Result<(), ProviderError> {
+///     Err(ProviderError::Timeout(500))
+/// }
+///
+/// let status = ExitStatus::of(&run());
+/// assert_eq!(status, ExitStatus::TRANSIENT);
+/// assert_eq!(status.code(), 75);
+///
+/// // In `main`: `fn main() -> std::process::ExitCode { ExitStatus::of(&run()).into() }`
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExitStatus(u8);
+
+impl ExitStatus {
+    /// The command succeeded (0).
+    pub const SUCCESS: Self = Self(0);
+    /// The caller asked for something invalid or unavailable (64, `EX_USAGE`).
+    pub const USER: Self = Self(64);
+    /// An internal failure (70, `EX_SOFTWARE`).
+    pub const INTERNAL: Self = Self(70);
+    /// A temporary failure; the command may be retried (75, `EX_TEMPFAIL`).
+    pub const TRANSIENT: Self = Self(75);
+    /// Misconfiguration (78, `EX_CONFIG`).
+    pub const CONFIGURATION: Self = Self(78);
+
+    /// The status for errors of `category`.
+    pub const fn for_category(category: ErrorCategory) -> Self {
+        match category {
+            ErrorCategory::User => Self::USER,
+            ErrorCategory::Internal => Self::INTERNAL,
+            ErrorCategory::Transient => Self::TRANSIENT,
+            ErrorCategory::Configuration => Self::CONFIGURATION,
+        }
+    }
+
+    /// The status for the outcome of a command.
+    pub fn of<T, E: ErrorCode>(result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => Self::SUCCESS,
+            Err(err) => err.exit_code(),
+        }
+    }
+
+    /// The numeric exit code.
+    pub const fn code(&self) -> u8 {
+        self.0
+    }
+
+    /// Check if this is [`SUCCESS`](Self::SUCCESS).
+    pub const fn is_success(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ExitStatus> for std::process::ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        status.0.into()
+    }
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Stable, machine-readable identification of an error.
+///
+/// Codes have the form `RSTR-<area>-<number>` and never change meaning once
+/// released: `P` for provider, `R` for registry, `S` for stream, and `X` for
+/// other errors, including [`MultiError`].
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{ErrorCategory, ErrorCode, ProviderError};
+///
+/// let err = ProviderError::Timeout(500);
+/// assert_eq!(err.code(), "RSTR-P-007");
+/// assert_eq!(err.category(), ErrorCategory::Transient);
+/// ```
+pub trait ErrorCode {
+    /// The stable code for this error.
+    fn code(&self) -> &'static str;
+
+    /// The broad class this error belongs to.
+    fn category(&self) -> ErrorCategory;
+
+    /// How serious the error is. Defaults to a mapping from the category.
+    fn severity(&self) -> ErrorSeverity {
+        match self.category() {
+            ErrorCategory::User => ErrorSeverity::Info,
+            ErrorCategory::Transient => ErrorSeverity::Warning,
+            ErrorCategory::Internal | ErrorCategory::Configuration => ErrorSeverity::Error,
+        }
+    }
+
+    /// Safe, non-technical text to show end users.
+    ///
+    /// Never includes the error's payload, so internal details stay in logs.
+    /// Defaults to a message for the category.
+    fn user_message(&self) -> &'static str {
+        category_message(self.category())
+    }
+
+    /// The process exit status for this error, from its category.
+    fn exit_code(&self) -> ExitStatus {
+        ExitStatus::for_category(self.category())
+    }
+}
+
+fn category_message(category: ErrorCategory) -> &'static str {
+    match category {
+        ErrorCategory::User => "The request could not be completed.",
+        ErrorCategory::Transient => "A temporary problem occurred. Please try again.",
+        ErrorCategory::Internal => "Something went wrong. Please try again later.",
+        ErrorCategory::Configuration => "The service is not configured correctly.",
+    }
+}
+
+impl ErrorCode for ProviderError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "RSTR-P-001",
+            Self::NotSupported(_) => "RSTR-P-002",
+            Self::ExecutionFailed(_) => "RSTR-P-003",
+            Self::InitializationFailed(_) => "RSTR-P-004",
+            Self::ConfigurationError(_) => "RSTR-P-005",
+            Self::IoError(_) => "RSTR-P-006",
+            Self::Timeout(_) => "RSTR-P-007",
+            Self::Cancelled => "RSTR-P-008",
+            Self::Custom(_) => "RSTR-P-009",
+            Self::PermissionDenied(_) => "RSTR-P-010",
+            Self::LimitExceeded(_) => "RSTR-P-011",
+            Self::WithFields { error, .. } => error.code(),
+        }
+    }
+
+    fn category(&self) -> ErrorCategory {
+        match self {
+            Self::NotFound(_)
+            | Self::NotSupported(_)
+            | Self::Cancelled
+            | Self::PermissionDenied(_)
+            | Self::LimitExceeded(_) => ErrorCategory::User,
+            Self::IoError(_) | Self::Timeout(_) => ErrorCategory::Transient,
+            Self::ConfigurationError(_) => ErrorCategory::Configuration,
+            Self::ExecutionFailed(_) | Self::InitializationFailed(_) | Self::Custom(_) => {
+                ErrorCategory::Internal
+            }
+            Self::WithFields { error, .. } => error.category(),
+        }
+    }
+
+    fn user_message(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "The requested item could not be found.",
+            Self::NotSupported(_) => "This request is not supported.",
+            Self::InitializationFailed(_) => "The service is not available right now.",
+            Self::Timeout(_) => "The operation took too long. Please try again.",
+            Self::Cancelled => "The operation was cancelled.",
+            Self::PermissionDenied(_) => "This operation is not permitted.",
+            Self::LimitExceeded(_) => "The operation used more resources than it is allowed.",
+            Self::WithFields { error, .. } => error.user_message(),
+            _ => category_message(self.category()),
+        }
+    }
+}
+
+impl ErrorCode for RegistryError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::AlreadyRegistered(_) => "RSTR-R-001",
+            Self::NoMatchingProvider => "RSTR-R-002",
+            Self::Empty => "RSTR-R-003",
+            Self::InvalidName(_) => "RSTR-R-004",
+            Self::AmbiguousMatch(_) => "RSTR-R-005",
+        }
+    }
+
+    fn category(&self) -> ErrorCategory {
+        match self {
+            Self::NoMatchingProvider | Self::InvalidName(_) => ErrorCategory::User,
+            Self::AlreadyRegistered(_) | Self::Empty | Self::AmbiguousMatch(_) => {
+                ErrorCategory::Configuration
+            }
+        }
+    }
+
+    fn user_message(&self) -> &'static str {
+        match self {
+            Self::NoMatchingProvider => "No handler is available for this request.",
+            Self::InvalidName(_) => "The name is not valid.",
+            _ => category_message(self.category()),
+        }
+    }
+}
+
+impl ErrorCode for RustratifyError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::Provider(err) => err.code(),
+            Self::Registry(err) => err.code(),
+            Self::Stream(_) => "RSTR-S-001",
+            Self::Other(_) => "RSTR-X-001",
+            Self::Custom(_) => "RSTR-X-002",
+            Self::Multi(err) => err.code(),
+            Self::WithFields { error, .. } => error.code(),
+        }
+    }
+
+    fn category(&self) -> ErrorCategory {
+        match self {
+            Self::Provider(err) => err.category(),
+            Self::Registry(err) => err.category(),
+            Self::Multi(err) => err.category(),
+            Self::WithFields { error, .. } => error.category(),
+            Self::Stream(_) | Self::Other(_) | Self::Custom(_) => ErrorCategory::Internal,
+        }
+    }
+
+    fn severity(&self) -> ErrorSeverity {
+        match self {
+            Self::Provider(err) => err.severity(),
+            Self::Registry(err) => err.severity(),
+            Self::Multi(err) => err.severity(),
+            Self::WithFields { error, .. } => error.severity(),
+            Self::Stream(_) | Self::Other(_) | Self::Custom(_) => ErrorSeverity::Error,
+        }
+    }
+
+    fn user_message(&self) -> &'static str {
+        match self {
+            Self::Provider(err) => err.user_message(),
+            Self::Registry(err) => err.user_message(),
+            Self::Multi(err) => err.user_message(),
+            Self::WithFields { error, .. } => error.user_message(),
+            Self::Stream(_) | Self::Other(_) | Self::Custom(_) => {
+                category_message(ErrorCategory::Internal)
+            }
+        }
+    }
+}
+
 /// Result type alias for provider operations.
 pub type ProviderResult<T> = Result<T, ProviderError>;
 
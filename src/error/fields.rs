@@ -0,0 +1,300 @@
+//! Structured key-value metadata attached to errors.
+//!
+//! `with_field` wraps an error in a `WithFields` variant holding an
+//! [`ErrorFields`] map. The message, code, and category are those of the
+//! wrapped error, and fields added to a [`ProviderError`] survive conversion
+//! into [`RustratifyError`]. Match on [`root`](ProviderError::root) rather
+//! than the error itself when the variant matters.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+use super::{ProviderError, RustratifyError};
+
+/// A typed metadata value.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum FieldValue {
+    /// `true` or `false`
+    Bool(bool),
+    /// A signed integer
+    Int(i64),
+    /// An unsigned integer too large for `Int`
+    UInt(u64),
+    /// A floating-point number
+    Float(f64),
+    /// Text
+    Str(String),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(v) => write!(f, "{v}"),
+            Self::Int(v) => write!(f, "{v}"),
+            Self::UInt(v) => write!(f, "{v}"),
+            Self::Float(v) => write!(f, "{v}"),
+            Self::Str(v) => f.write_str(v),
+        }
+    }
+}
+
+macro_rules! impl_field_value_from {
+    ($variant:ident($target:ty): $($ty:ty),*) => {
+        $(
+            impl From<$ty> for FieldValue {
+                fn from(value: $ty) -> Self {
+                    Self::$variant(<$target>::from(value))
+                }
+            }
+        )*
+    };
+}
+
+impl_field_value_from!(Bool(bool): bool);
+impl_field_value_from!(Int(i64): i8, i16, i32, i64, u8, u16, u32);
+impl_field_value_from!(Float(f64): f32, f64);
+impl_field_value_from!(Str(String): String, &str);
+
+impl From<u64> for FieldValue {
+    fn from(value: u64) -> Self {
+        i64::try_from(value).map_or(Self::UInt(value), Self::Int)
+    }
+}
+
+impl From<usize> for FieldValue {
+    fn from(value: usize) -> Self {
+        Self::from(value as u64)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<&Path> for FieldValue {
+    fn from(value: &Path) -> Self {
+        Self::Str(value.display().to_string())
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<PathBuf> for FieldValue {
+    fn from(value: PathBuf) -> Self {
+        Self::from(value.as_path())
+    }
+}
+
+/// Metadata fields attached to an error, ordered by key.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct ErrorFields(BTreeMap<String, FieldValue>);
+
+impl ErrorFields {
+    /// Create an empty map.
+    pub const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// Set `key`, replacing any earlier value.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<FieldValue>) {
+        self.0.insert(key.into(), value.into());
+    }
+
+    /// The value of `key`, if set.
+    pub fn get(&self, key: &str) -> Option<&FieldValue> {
+        self.0.get(key)
+    }
+
+    /// All fields, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FieldValue)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Number of fields.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check if no fields are set.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(super) fn extend(&mut self, other: ErrorFields) {
+        self.0.extend(other.0);
+    }
+}
+
+impl fmt::Display for ErrorFields {
+    /// Renders as `key=value` pairs separated by spaces.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+static NO_FIELDS: ErrorFields = ErrorFields::new();
+
+impl ProviderError {
+    /// Attach a metadata field.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{ProviderError, RustratifyError};
+    ///
+    /// let err = ProviderError::IoError("denied".into())
+    ///     .with_field("path", "/etc/app.toml")
+    ///     .with_field("provider", "toml");
+    ///
+    /// let err = RustratifyError::from(err);
+    /// assert_eq!(err.fields().to_string(), "path=/etc/app.toml provider=toml");
+    /// ```
+    pub fn with_field(self, key: impl Into<String>, value: impl Into<FieldValue>) -> Self {
+        let mut fields = ErrorFields::new();
+        fields.insert(key, value);
+        self.with_fields(fields)
+    }
+
+    /// The attached metadata; empty if there is none.
+    pub fn fields(&self) -> &ErrorFields {
+        match self {
+            Self::WithFields { fields, .. } => fields,
+            _ => &NO_FIELDS,
+        }
+    }
+
+    /// Attach every field in `new`, replacing existing keys.
+    pub(crate) fn with_fields(self, new: ErrorFields) -> Self {
+        if new.is_empty() {
+            return self;
+        }
+        match self {
+            Self::WithFields { error, mut fields } => {
+                fields.extend(new);
+                Self::WithFields { error, fields }
+            }
+            error => Self::WithFields {
+                error: Box::new(error),
+                fields: new,
+            },
+        }
+    }
+
+    /// The error without its metadata.
+    pub fn root(&self) -> &ProviderError {
+        match self {
+            Self::WithFields { error, .. } => error.root(),
+            error => error,
+        }
+    }
+}
+
+impl RustratifyError {
+    /// Attach a metadata field.
+    ///
+    /// Fields on a `Provider` error are added to the provider error itself.
+    pub fn with_field(self, key: impl Into<String>, value: impl Into<FieldValue>) -> Self {
+        let mut fields = ErrorFields::new();
+        fields.insert(key, value);
+        self.with_fields(fields)
+    }
+
+    /// The attached metadata, including a provider error's; empty if there
+    /// is none.
+    pub fn fields(&self) -> &ErrorFields {
+        match self {
+            Self::WithFields { fields, .. } => fields,
+            Self::Provider(error) => error.fields(),
+            _ => &NO_FIELDS,
+        }
+    }
+
+    /// Attach every field in `new`, replacing existing keys.
+    pub(crate) fn with_fields(self, new: ErrorFields) -> Self {
+        if new.is_empty() {
+            return self;
+        }
+        match self {
+            Self::Provider(error) => Self::Provider(error.with_fields(new)),
+            Self::WithFields { error, mut fields } => {
+                fields.extend(new);
+                Self::WithFields { error, fields }
+            }
+            error => Self::WithFields {
+                error: Box::new(error),
+                fields: new,
+            },
+        }
+    }
+
+    /// The error without its metadata.
+    pub fn root(&self) -> &RustratifyError {
+        match self {
+            Self::WithFields { error, .. } => error.root(),
+            error => error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorCode, RegistryError};
+
+    #[test]
+    fn test_fields_keep_error_identity() {
+        let err = ProviderError::Timeout(30)
+            .with_field("provider", "http")
+            .with_field("attempt", 3u32)
+            .with_field("provider", "grpc");
+
+        assert_eq!(err.to_string(), "Operation timed out after 30ms");
+        assert_eq!(err.code(), "RSTR-P-007");
+        assert!(matches!(err.root(), ProviderError::Timeout(30)));
+        assert_eq!(err.fields().get("attempt"), Some(&FieldValue::Int(3)));
+        assert_eq!(err.fields().to_string(), "attempt=3 provider=grpc");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_path_field() {
+        assert_eq!(
+            FieldValue::from(Path::new("src/lib.rs")),
+            FieldValue::Str("src/lib.rs".into())
+        );
+    }
+
+    #[test]
+    fn test_fields_survive_conversion() {
+        let err: RustratifyError = ProviderError::NotFound("x".into())
+            .with_field("path", "src/lib.rs")
+            .into();
+        let err = err.with_field("run", 7u64);
+        assert_eq!(err.fields().len(), 2);
+        assert!(matches!(err.root(), RustratifyError::Provider(_)));
+
+        let err = RustratifyError::from(RegistryError::Empty).with_field("registry", "langs");
+        assert_eq!(err.to_string(), "Registry error: Registry is empty");
+        assert_eq!(err.code(), "RSTR-R-003");
+        assert!(matches!(
+            err.root(),
+            RustratifyError::Registry(RegistryError::Empty)
+        ));
+    }
+}
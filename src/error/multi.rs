@@ -0,0 +1,208 @@
+//! Aggregated errors from fan-out operations.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{ErrorCategory, ErrorCode, ErrorSeverity, ProviderError};
+
+/// Every failure from an operation that ran against several providers.
+///
+/// Each error is labelled, usually with the provider name, so callers see all
+/// failures instead of only the first one.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{MultiError, ProviderError};
+///
+/// let results = vec![
+///     ("rust", Ok(1)),
+///     ("python", Err(ProviderError::Timeout(100))),
+///     ("go", Err(ProviderError::Cancelled)),
+/// ];
+///
+/// let err = MultiError::collect(results).unwrap_err();
+/// assert_eq!(err.len(), 2);
+/// assert_eq!(err.labels().collect::<Vec<_>>(), vec!["python", "go"]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MultiError {
+    errors: Vec<(String, ProviderError)>,
+}
+
+impl MultiError {
+    /// Create an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a failure for `label`.
+    pub fn push(&mut self, label: impl Into<String>, error: ProviderError) {
+        self.errors.push((label.into(), error));
+    }
+
+    /// Gather labelled results, returning the successes in order or every
+    /// failure.
+    pub fn collect<L, T, I>(results: I) -> Result<Vec<T>, MultiError>
+    where
+        L: Into<String>,
+        I: IntoIterator<Item = (L, Result<T, ProviderError>)>,
+    {
+        let mut values = Vec::new();
+        let mut errors = Self::new();
+        for (label, result) in results {
+            match result {
+                Ok(value) => values.push(value),
+                Err(err) => errors.push(label, err),
+            }
+        }
+        errors.into_result().map(|()| values)
+    }
+
+    /// `Ok` if nothing failed, otherwise `Err(self)`.
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// The failures, in the order they were recorded.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ProviderError)> {
+        self.errors.iter().map(|(label, err)| (label.as_str(), err))
+    }
+
+    /// The labels of the failed items.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.errors.iter().map(|(label, _)| label.as_str())
+    }
+
+    /// The error recorded for `label`, if any.
+    pub fn get(&self, label: &str) -> Option<&ProviderError> {
+        self.iter().find(|(l, _)| *l == label).map(|(_, err)| err)
+    }
+
+    /// Number of failures.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Check if nothing failed.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Take the labelled errors.
+    pub fn into_inner(self) -> Vec<(String, ProviderError)> {
+        self.errors
+    }
+}
+
+impl fmt::Display for MultiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.errors.len() {
+            0 => return write!(f, "no errors"),
+            1 => write!(f, "1 operation failed: ")?,
+            n => write!(f, "{n} operations failed: ")?,
+        }
+        for (i, (label, err)) in self.errors.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{label}: {err}")?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for MultiError {}
+
+impl Extend<(String, ProviderError)> for MultiError {
+    fn extend<I: IntoIterator<Item = (String, ProviderError)>>(&mut self, iter: I) {
+        self.errors.extend(iter);
+    }
+}
+
+impl FromIterator<(String, ProviderError)> for MultiError {
+    fn from_iter<I: IntoIterator<Item = (String, ProviderError)>>(iter: I) -> Self {
+        Self {
+            errors: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for MultiError {
+    type Item = (String, ProviderError);
+    type IntoIter = alloc::vec::IntoIter<(String, ProviderError)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl ErrorCode for MultiError {
+    fn code(&self) -> &'static str {
+        "RSTR-X-003"
+    }
+
+    /// The shared category of every failure, or `Internal` if they differ.
+    fn category(&self) -> ErrorCategory {
+        let mut categories = self.errors.iter().map(|(_, err)| err.category());
+        match categories.next() {
+            Some(first) if categories.all(|c| c == first) => first,
+            _ => ErrorCategory::Internal,
+        }
+    }
+
+    /// The most serious severity among the failures.
+    fn severity(&self) -> ErrorSeverity {
+        self.errors
+            .iter()
+            .map(|(_, err)| err.severity())
+            .max()
+            .unwrap_or(ErrorSeverity::Info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_and_display() {
+        let ok = MultiError::collect([("a", Ok::<_, ProviderError>(1)), ("b", Ok(2))]);
+        assert_eq!(ok.unwrap(), vec![1, 2]);
+
+        let err = MultiError::collect([
+            ("a", Ok(1)),
+            ("b", Err(ProviderError::NotFound("x".into()))),
+            ("c", Err(ProviderError::Cancelled)),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "2 operations failed: b: Provider not found: x; c: Operation was cancelled"
+        );
+        assert!(matches!(err.get("c"), Some(ProviderError::Cancelled)));
+        assert!(err.get("a").is_none());
+    }
+
+    #[test]
+    fn test_category() {
+        let transient: MultiError = [
+            ("a".to_string(), ProviderError::Timeout(1)),
+            ("b".to_string(), ProviderError::IoError("reset".into())),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(transient.category(), ErrorCategory::Transient);
+
+        let mut mixed = transient.clone();
+        mixed.push("c", ProviderError::NotFound("x".into()));
+        assert_eq!(mixed.category(), ErrorCategory::Internal);
+        assert_eq!(mixed.severity(), ErrorSeverity::Warning);
+        assert_eq!(mixed.code(), "RSTR-X-003");
+    }
+}
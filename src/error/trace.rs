@@ -0,0 +1,189 @@
+//! Source location and backtrace capture for errors.
+//!
+//! The error enums are plain data so they stay cheap and `Clone`. To find out
+//! where a failure came from deep inside a provider stack, wrap it in a
+//! [`Traced`]: construction records the caller's source location, and with
+//! the `backtrace` feature also a [`Backtrace`](std::backtrace::Backtrace)
+//! (subject to `RUST_BACKTRACE`/`RUST_LIB_BACKTRACE`, as usual).
+
+use core::fmt;
+use core::ops::Deref;
+use core::panic::Location;
+
+#[cfg(feature = "backtrace")]
+use alloc::sync::Arc;
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+
+use super::{ErrorCategory, ErrorCode, ErrorSeverity, ProviderError, RegistryError};
+
+/// An error together with where it was created.
+///
+/// `From<E>` is `#[track_caller]`, so a `?` that converts into
+/// `Result<_, Traced<E>>` records the location of the `?` itself.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{ProviderError, Traced};
+///
+/// fn load() -> Result<(), Traced<ProviderError>> {
+///     Err(ProviderError::NotFound("rust".into()))?
+/// }
+///
+/// let err = load().unwrap_err();
+/// assert!(matches!(*err, ProviderError::NotFound(_)));
+/// assert!(err.location().file().ends_with(".rs"));
+/// ```
+#[derive(Clone)]
+pub struct Traced<E> {
+    error: E,
+    location: &'static Location<'static>,
+    #[cfg(feature = "backtrace")]
+    backtrace: Arc<Backtrace>,
+}
+
+impl<E> Traced<E> {
+    /// Wrap `error`, recording the caller's location.
+    #[track_caller]
+    pub fn new(error: E) -> Self {
+        Self {
+            error,
+            location: Location::caller(),
+            #[cfg(feature = "backtrace")]
+            backtrace: Arc::new(Backtrace::capture()),
+        }
+    }
+
+    /// Where the error was wrapped.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// The backtrace captured when the error was wrapped. Requires the
+    /// `backtrace` feature.
+    #[cfg(feature = "backtrace")]
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+
+    /// The wrapped error.
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Unwrap the error, dropping the trace.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+impl<E> Deref for Traced<E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        &self.error
+    }
+}
+
+impl<E> From<E> for Traced<E> {
+    #[track_caller]
+    fn from(error: E) -> Self {
+        Self::new(error)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Debug for Traced<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} at {}", self.error, self.location)?;
+        #[cfg(feature = "backtrace")]
+        if self.backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            write!(f, "\n\nStack backtrace:\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for Traced<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for Traced<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<E: ErrorCode> ErrorCode for Traced<E> {
+    fn code(&self) -> &'static str {
+        self.error.code()
+    }
+
+    fn category(&self) -> ErrorCategory {
+        self.error.category()
+    }
+
+    fn severity(&self) -> ErrorSeverity {
+        self.error.severity()
+    }
+
+    fn user_message(&self) -> &'static str {
+        self.error.user_message()
+    }
+}
+
+impl ProviderError {
+    /// Wrap this error in a [`Traced`], recording the caller's location.
+    #[track_caller]
+    pub fn traced(self) -> Traced<Self> {
+        Traced::new(self)
+    }
+}
+
+impl RegistryError {
+    /// Wrap this error in a [`Traced`], recording the caller's location.
+    #[track_caller]
+    pub fn traced(self) -> Traced<Self> {
+        Traced::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_points_at_caller() {
+        let line = line!() + 1;
+        let err = ProviderError::Timeout(5).traced();
+        assert_eq!(err.location().file(), file!());
+        assert_eq!(err.location().line(), line);
+        assert_eq!(err.code(), "RSTR-P-007");
+        assert_eq!(err.to_string(), "Operation timed out after 5ms");
+
+        let debug = format!("{err:?}");
+        assert!(debug.starts_with(&format!("Timeout(5) at {}:{line}:", file!())));
+    }
+
+    #[test]
+    fn test_question_mark_records_location() {
+        fn find() -> Result<(), Traced<RegistryError>> {
+            Err(RegistryError::Empty)?;
+            Ok(())
+        }
+
+        let err = find().unwrap_err();
+        assert_eq!(err.location().line(), line!() - 5);
+        assert!(matches!(err.into_inner(), RegistryError::Empty));
+    }
+
+    #[cfg(feature = "backtrace")]
+    #[test]
+    fn test_backtrace_is_captured() {
+        let err = RegistryError::Empty.traced();
+        // Capture depends on RUST_BACKTRACE; only check the accessor works.
+        let _ = err.backtrace().status();
+    }
+}
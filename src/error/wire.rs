@@ -0,0 +1,260 @@
+//! Serializable error form for sending errors between processes.
+//!
+//! The error enums hold non-serializable payloads (`Custom` errors), so they
+//! are not `Serialize` themselves. [`WireError`] carries an error's stable
+//! code, category, message, and payload instead. Converting back restores
+//! every known variant exactly; unknown codes and `Custom` errors come back as
+//! a `Custom` error wrapping the `WireError`, so the message is never lost.
+//! Metadata from [`with_field`](ProviderError::with_field) travels along and
+//! is re-attached on the way back.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ErrorCategory, ErrorCode, ErrorFields, ProviderError, RegistryError, RustratifyError};
+
+/// An error in a form that can be serialized and sent over the wire.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{ProviderError, WireError};
+///
+/// let wire = WireError::from(&ProviderError::NotFound("rust".into()));
+/// let json = serde_json::to_string(&wire).unwrap();
+///
+/// let received: WireError = serde_json::from_str(&json).unwrap();
+/// let err = ProviderError::from(received);
+/// assert!(matches!(err, ProviderError::NotFound(name) if name == "rust"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WireError {
+    /// Stable code from [`ErrorCode::code`], e.g. `RSTR-P-001`
+    pub code: String,
+    /// Category from [`ErrorCode::category`]
+    pub category: ErrorCategory,
+    /// Human-readable message, as from `Display`
+    pub message: String,
+    /// Variant payload, e.g. the provider name for `NotFound`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Metadata attached with `with_field`
+    #[serde(default, skip_serializing_if = "ErrorFields::is_empty")]
+    pub fields: ErrorFields,
+}
+
+impl WireError {
+    fn new(err: &impl ErrorCode, message: String, detail: Option<String>) -> Self {
+        Self {
+            code: err.code().to_string(),
+            category: err.category(),
+            message,
+            detail,
+            fields: ErrorFields::new(),
+        }
+    }
+
+    fn detail(&self) -> String {
+        self.detail.clone().unwrap_or_default()
+    }
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl From<&ProviderError> for WireError {
+    fn from(err: &ProviderError) -> Self {
+        let detail = match err {
+            ProviderError::NotFound(s)
+            | ProviderError::NotSupported(s)
+            | ProviderError::ExecutionFailed(s)
+            | ProviderError::InitializationFailed(s)
+            | ProviderError::ConfigurationError(s)
+            | ProviderError::IoError(s)
+            | ProviderError::PermissionDenied(s)
+            | ProviderError::LimitExceeded(s) => Some(s.clone()),
+            ProviderError::Timeout(ms) => Some(ms.to_string()),
+            ProviderError::Cancelled | ProviderError::Custom(_) => None,
+            ProviderError::WithFields { error, fields } => {
+                let mut wire = Self::from(&**error);
+                wire.fields = fields.clone();
+                return wire;
+            }
+        };
+        Self::new(err, err.to_string(), detail)
+    }
+}
+
+impl From<&RegistryError> for WireError {
+    fn from(err: &RegistryError) -> Self {
+        let detail = match err {
+            RegistryError::AlreadyRegistered(s)
+            | RegistryError::InvalidName(s)
+            | RegistryError::AmbiguousMatch(s) => Some(s.clone()),
+            RegistryError::NoMatchingProvider | RegistryError::Empty => None,
+        };
+        Self::new(err, err.to_string(), detail)
+    }
+}
+
+impl From<&RustratifyError> for WireError {
+    fn from(err: &RustratifyError) -> Self {
+        match err {
+            RustratifyError::Provider(inner) => inner.into(),
+            RustratifyError::Registry(inner) => inner.into(),
+            RustratifyError::Stream(s) | RustratifyError::Other(s) => {
+                Self::new(err, err.to_string(), Some(s.clone()))
+            }
+            RustratifyError::Custom(_) | RustratifyError::Multi(_) => {
+                Self::new(err, err.to_string(), None)
+            }
+            RustratifyError::WithFields { error, fields } => {
+                let mut wire = Self::from(&**error);
+                wire.fields.extend(fields.clone());
+                wire
+            }
+        }
+    }
+}
+
+impl From<WireError> for ProviderError {
+    fn from(mut wire: WireError) -> Self {
+        let fields = std::mem::take(&mut wire.fields);
+        let err = match wire.code.as_str() {
+            "RSTR-P-001" => Self::NotFound(wire.detail()),
+            "RSTR-P-002" => Self::NotSupported(wire.detail()),
+            "RSTR-P-003" => Self::ExecutionFailed(wire.detail()),
+            "RSTR-P-004" => Self::InitializationFailed(wire.detail()),
+            "RSTR-P-005" => Self::ConfigurationError(wire.detail()),
+            "RSTR-P-006" => Self::IoError(wire.detail()),
+            "RSTR-P-007" => match wire.detail().parse() {
+                Ok(ms) => Self::Timeout(ms),
+                Err(_) => Self::Custom(Arc::new(wire)),
+            },
+            "RSTR-P-008" => Self::Cancelled,
+            "RSTR-P-010" => Self::PermissionDenied(wire.detail()),
+            "RSTR-P-011" => Self::LimitExceeded(wire.detail()),
+            _ => Self::Custom(Arc::new(wire)),
+        };
+        err.with_fields(fields)
+    }
+}
+
+impl TryFrom<WireError> for RegistryError {
+    type Error = WireError;
+
+    /// Fails, returning the input, if the code is not a registry error code.
+    fn try_from(wire: WireError) -> Result<Self, WireError> {
+        match wire.code.as_str() {
+            "RSTR-R-001" => Ok(Self::AlreadyRegistered(wire.detail())),
+            "RSTR-R-002" => Ok(Self::NoMatchingProvider),
+            "RSTR-R-003" => Ok(Self::Empty),
+            "RSTR-R-004" => Ok(Self::InvalidName(wire.detail())),
+            "RSTR-R-005" => Ok(Self::AmbiguousMatch(wire.detail())),
+            _ => Err(wire),
+        }
+    }
+}
+
+impl From<WireError> for RustratifyError {
+    fn from(mut wire: WireError) -> Self {
+        if wire.code.starts_with("RSTR-P-") {
+            return Self::Provider(wire.into());
+        }
+        let fields = std::mem::take(&mut wire.fields);
+        let err = match wire.code.as_str() {
+            code if code.starts_with("RSTR-R-") => match RegistryError::try_from(wire) {
+                Ok(err) => Self::Registry(err),
+                Err(wire) => Self::Custom(Box::new(wire)),
+            },
+            "RSTR-S-001" => Self::Stream(wire.detail()),
+            "RSTR-X-001" => Self::Other(wire.detail()),
+            _ => Self::Custom(Box::new(wire)),
+        };
+        err.with_fields(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(err: RustratifyError) -> RustratifyError {
+        let json = serde_json::to_string(&WireError::from(&err)).unwrap();
+        serde_json::from_str::<WireError>(&json).unwrap().into()
+    }
+
+    #[test]
+    fn test_known_variants_round_trip() {
+        let errors: Vec<RustratifyError> = vec![
+            ProviderError::ExecutionFailed("boom".into()).into(),
+            ProviderError::Timeout(250).into(),
+            ProviderError::Cancelled.into(),
+            ProviderError::PermissionDenied("network crates.io".into()).into(),
+            ProviderError::LimitExceeded("wall-clock limit".into()).into(),
+            RegistryError::AlreadyRegistered("rust".into()).into(),
+            RegistryError::NoMatchingProvider.into(),
+            RegistryError::AmbiguousMatch("rust, rust2".into()).into(),
+            RustratifyError::Stream("closed".into()),
+            RustratifyError::Other("oops".into()),
+        ];
+        for err in errors {
+            let expected = format!("{err:?}");
+            assert_eq!(format!("{:?}", round_trip(err)), expected);
+        }
+    }
+
+    #[test]
+    fn test_custom_keeps_message_and_code() {
+        let err = ProviderError::custom(std::io::Error::other("disk full"));
+        let wire = WireError::from(&err);
+        assert_eq!(wire.code, "RSTR-P-009");
+        assert_eq!(wire.detail, None);
+
+        let back = ProviderError::from(wire.clone());
+        assert_eq!(back.to_string(), "disk full");
+        assert_eq!(back.downcast_ref::<WireError>(), Some(&wire));
+    }
+
+    #[test]
+    fn test_fields_round_trip() {
+        let err = RustratifyError::from(
+            ProviderError::IoError("denied".into()).with_field("path", "/srv/app.toml"),
+        )
+        .with_field("attempt", 2);
+
+        let wire = WireError::from(&err);
+        assert_eq!(
+            serde_json::to_value(&wire.fields).unwrap(),
+            serde_json::json!({ "attempt": 2, "path": "/srv/app.toml" })
+        );
+
+        let expected = format!("{err:?}");
+        assert_eq!(format!("{:?}", round_trip(err)), expected);
+
+        let err = RustratifyError::Stream("closed".into()).with_field("run", "r-1");
+        let back = round_trip(err);
+        assert!(matches!(back.root(), RustratifyError::Stream(_)));
+        assert_eq!(back.fields().get("run"), Some(&"r-1".into()));
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let wire = WireError::from(&RegistryError::Empty);
+        assert_eq!(
+            serde_json::to_value(&wire).unwrap(),
+            serde_json::json!({
+                "code": "RSTR-R-003",
+                "category": "configuration",
+                "message": "Registry is empty",
+            })
+        );
+    }
+}
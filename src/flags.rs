@@ -0,0 +1,309 @@
+//! Feature flags with percentage rollouts.
+//!
+//! A [`FeatureFlags`] store asks its [`FlagSource`]s, in order, for the
+//! [`FlagRule`] of a flag; the first source that knows the flag decides.
+//! Built-in sources are [`StaticFlags`] and [`EnvFlags`]; implement
+//! [`FlagSource`] to read flags from a remote service or database.
+//!
+//! Percentage rollouts hash the flag name with the [`Context`] subject, so a
+//! given subject gets the same answer on every call and raising the
+//! percentage only ever adds subjects.
+//!
+//! Registries can gate providers on flags at lookup time; see
+//! [`Registry::gate`](crate::Registry::gate).
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::flags::{FeatureFlags, FlagRule, StaticFlags};
+//! use rustratify::Context;
+//!
+//! let flags = FeatureFlags::new().with_source(
+//!     StaticFlags::new()
+//!         .flag("new-parser", FlagRule::On)
+//!         .flag("fast-path", FlagRule::Percentage(25)),
+//! );
+//!
+//! let cx = Context::new().with_subject("user-42");
+//! assert!(flags.is_enabled("new-parser", &cx));
+//! assert!(!flags.is_enabled("unknown", &cx));
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::context::Context;
+
+/// How a flag is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagRule {
+    /// Enabled for everyone
+    On,
+    /// Disabled for everyone
+    Off,
+    /// Enabled for this percentage of subjects, 0 to 100
+    Percentage(u8),
+}
+
+impl FlagRule {
+    /// Evaluate the rule for `flag` in `cx`.
+    ///
+    /// A partial rollout is off for contexts without a subject.
+    pub fn evaluate(&self, flag: &str, cx: &Context) -> bool {
+        match *self {
+            Self::On => true,
+            Self::Off => false,
+            Self::Percentage(p) if p >= 100 => true,
+            Self::Percentage(p) => match cx.subject() {
+                Some(subject) => bucket(flag, subject) < u64::from(p),
+                None => false,
+            },
+        }
+    }
+}
+
+/// Position of `subject` in the rollout of `flag`, from 0 to 99.
+fn bucket(flag: &str, subject: &str) -> u64 {
+    // FNV-1a: stable across processes and releases, unlike `DefaultHasher`.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in flag.bytes().chain([0]).chain(subject.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash % 100
+}
+
+impl FromStr for FlagRule {
+    type Err = String;
+
+    /// Parses `on`/`off` (also `true`/`false`, `yes`/`no`, `1`/`0`) and
+    /// percentages such as `25%`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        match s.to_ascii_lowercase().as_str() {
+            "on" | "true" | "yes" | "1" => return Ok(Self::On),
+            "off" | "false" | "no" | "0" => return Ok(Self::Off),
+            _ => {}
+        }
+        s.strip_suffix('%')
+            .and_then(|p| p.trim().parse::<u8>().ok())
+            .filter(|p| *p <= 100)
+            .map(Self::Percentage)
+            .ok_or_else(|| format!("invalid flag value `{s}`: expected on, off, or a percentage"))
+    }
+}
+
+impl fmt::Display for FlagRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::On => write!(f, "on"),
+            Self::Off => write!(f, "off"),
+            Self::Percentage(p) => write!(f, "{p}%"),
+        }
+    }
+}
+
+/// A source of flag rules.
+///
+/// Sources are consulted on every evaluation, so a dynamic source can
+/// change its answers at any time.
+pub trait FlagSource: Send + Sync {
+    /// Source name, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// The rule for `flag`, or `None` if this source does not define it.
+    fn rule(&self, flag: &str) -> Option<FlagRule>;
+}
+
+/// A fixed set of flags.
+#[derive(Debug, Clone, Default)]
+pub struct StaticFlags {
+    rules: HashMap<String, FlagRule>,
+}
+
+impl StaticFlags {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define `flag`.
+    pub fn flag(mut self, flag: impl Into<String>, rule: FlagRule) -> Self {
+        self.rules.insert(flag.into(), rule);
+        self
+    }
+}
+
+impl FlagSource for StaticFlags {
+    fn name(&self) -> &str {
+        "static"
+    }
+
+    fn rule(&self, flag: &str) -> Option<FlagRule> {
+        self.rules.get(flag).copied()
+    }
+}
+
+/// Flags from prefixed environment variables.
+///
+/// With prefix `APP_FLAG`, the flag `new-parser` is read from
+/// `APP_FLAG_NEW_PARSER`: the name is upper-cased and `-` and `.` become `_`.
+/// Values are parsed as [`FlagRule`]s; unparseable values are ignored with a
+/// warning.
+#[derive(Debug, Clone)]
+pub struct EnvFlags {
+    prefix: String,
+    rules: HashMap<String, FlagRule>,
+}
+
+impl EnvFlags {
+    /// Capture the process environment variables starting with `prefix`.
+    ///
+    /// A trailing `_` on the prefix is optional.
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self::from_vars(prefix, std::env::vars())
+    }
+
+    /// Capture the variables starting with `prefix` from `vars`.
+    pub fn from_vars<I, K, V>(prefix: impl Into<String>, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let prefix = prefix.into().trim_end_matches('_').to_string();
+        let head = format!("{prefix}_");
+        let rules = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let flag = name.as_ref().strip_prefix(&head)?;
+                match value.as_ref().parse() {
+                    Ok(rule) => Some((flag.to_string(), rule)),
+                    Err(err) => {
+                        tracing::warn!(variable = name.as_ref(), "{err}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { prefix, rules }
+    }
+
+    /// The prefix, without a trailing `_`.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+}
+
+impl FlagSource for EnvFlags {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    fn rule(&self, flag: &str) -> Option<FlagRule> {
+        let key: String = flag
+            .chars()
+            .map(|c| match c {
+                '-' | '.' => '_',
+                c => c.to_ascii_uppercase(),
+            })
+            .collect();
+        self.rules.get(&key).copied()
+    }
+}
+
+/// A layered store of feature flags.
+///
+/// Cloning is cheap; clones share the sources.
+#[derive(Clone, Default)]
+pub struct FeatureFlags {
+    sources: Arc<Vec<Arc<dyn FlagSource>>>,
+}
+
+impl FeatureFlags {
+    /// Create a store with no sources; every flag is off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source, consulted after those added before it.
+    pub fn with_source(mut self, source: impl FlagSource + 'static) -> Self {
+        Arc::make_mut(&mut self.sources).push(Arc::new(source));
+        self
+    }
+
+    /// The rule for `flag` from the first source that defines it.
+    pub fn rule(&self, flag: &str) -> Option<FlagRule> {
+        self.sources.iter().find_map(|source| source.rule(flag))
+    }
+
+    /// Whether `flag` is enabled in `cx`. Undefined flags are off.
+    pub fn is_enabled(&self, flag: &str, cx: &Context) -> bool {
+        self.rule(flag).is_some_and(|rule| rule.evaluate(flag, cx))
+    }
+}
+
+impl fmt::Debug for FeatureFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sources: Vec<&str> = self.sources.iter().map(|s| s.name()).collect();
+        f.debug_struct("FeatureFlags")
+            .field("sources", &sources)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!("ON".parse(), Ok(FlagRule::On));
+        assert_eq!("0".parse(), Ok(FlagRule::Off));
+        assert_eq!(" 25 %".parse(), Ok(FlagRule::Percentage(25)));
+        assert!("150%".parse::<FlagRule>().is_err());
+        assert!("maybe".parse::<FlagRule>().is_err());
+        assert_eq!(FlagRule::Percentage(5).to_string(), "5%");
+    }
+
+    #[test]
+    fn test_percentage_rollout_is_stable_and_monotonic() {
+        let subjects: Vec<Context> = (0..1000)
+            .map(|i| Context::new().with_subject(format!("user-{i}")))
+            .collect();
+        let enabled = |p| {
+            subjects
+                .iter()
+                .filter(|cx| FlagRule::Percentage(p).evaluate("beta", cx))
+                .count()
+        };
+        let quarter = enabled(25);
+        assert!((200..300).contains(&quarter), "{quarter}");
+        assert_eq!(enabled(25), quarter);
+        assert!(enabled(50) > quarter);
+        assert_eq!(enabled(100), 1000);
+        assert!(!FlagRule::Percentage(99).evaluate("beta", &Context::new()));
+    }
+
+    #[test]
+    fn test_sources_are_layered() {
+        let env = EnvFlags::from_vars(
+            "APP_FLAG_",
+            [
+                ("APP_FLAG_NEW_PARSER", "off"),
+                ("APP_FLAG_BAD", "sometimes"),
+            ],
+        );
+        let flags = FeatureFlags::new().with_source(env).with_source(
+            StaticFlags::new()
+                .flag("new-parser", FlagRule::On)
+                .flag("bad", FlagRule::On),
+        );
+        let cx = Context::new();
+        assert!(!flags.is_enabled("new-parser", &cx));
+        assert!(flags.is_enabled("bad", &cx));
+        assert_eq!(flags.rule("missing"), None);
+    }
+}
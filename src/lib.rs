@@ -45,23 +45,127 @@
 //! - Generic `Provider` trait for extension points
 //! - Type-safe `Registry` for provider management
 //! - Async stream utilities for event-driven APIs
+//! - Blocking facade for synchronous consumers
 //! - Error types following SEA conventions
+//!
+//! ## `no_std`
+//!
+//! With default features off, the crate builds under `no_std` with `alloc`
+//! and provides the L1 pieces only: errors, configuration traits, the
+//! [`Context`] and the [`Provider`] trait, without its path and permission
+//! APIs. The `std` feature, on by default and implied by every other
+//! feature, adds the rest.
+//!
+//! ## WebAssembly
+//!
+//! The crate builds for `wasm32-unknown-unknown`. With the `tokio` feature
+//! off, streams default to the runtime-agnostic
+//! [`StdBackend`](stream::StdBackend), which runs on the browser event loop.
+//! Helpers that need a filesystem (`load_config`, `save_config`,
+//! `ConfigMigration::load`, `stream::spill`, and
+//! `audit::JsonLinesSink::file`) are compiled out on that target.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "arch")]
+pub mod arch;
+#[cfg(feature = "std")]
+pub mod audit;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "tokio")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod blocking;
+#[cfg(feature = "cli")]
+pub mod cli;
 mod config;
+mod context;
 mod error;
+#[cfg(feature = "std")]
+pub mod flags;
+#[cfg(feature = "tokio")]
+pub mod limits;
+#[cfg(feature = "std")]
+pub mod log_bridge;
+pub mod matcher;
+#[cfg(feature = "std")]
+pub mod metrics;
+mod output;
+#[cfg(feature = "std")]
+pub mod permissions;
+#[cfg(feature = "abi_stable")]
+pub mod plugin;
+#[cfg(feature = "tokio")]
+pub mod pool;
 mod provider;
+#[cfg(feature = "std")]
+pub mod queue;
+#[cfg(feature = "std")]
 mod registry;
+#[cfg(feature = "arch")]
+pub mod scaffold;
+#[cfg(feature = "tokio")]
+pub mod scheduler;
+mod sealed;
+#[cfg(feature = "tokio")]
+pub mod shutdown;
+#[cfg(feature = "std")]
+pub mod state;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
 pub mod stream;
+#[cfg(feature = "tokio")]
+pub mod task;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod transport;
 
 pub mod prelude;
 
 // Re-export core types
-pub use config::{Config, ConfigBuilder, DefaultConfig, FileConfig, MergeableConfig};
+#[cfg(feature = "std")]
+pub use config::FileConfig;
+pub use config::{
+    format_duration, format_size, parse_duration, parse_size, ByteSize, Config, ConfigBuilder,
+    ConfigChange, ConfigDiff, ConfigSchema, DefaultConfig, DiffConfig, DiffFields, EnvConfig,
+    EnvFields, EnvReport, EnvValue, EnvVarError, FieldCheck, FromEnv, HumanDuration, IsEmpty,
+    MergeableConfig, Missing, SchemaProperty, SchemaType, SchemaValue, Secret, SecretValue, Set,
+    Severity, ValidationIssue, ValidationReport, JSON_SCHEMA_DIALECT, REDACTED,
+};
+#[cfg(all(
+    feature = "serde",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use config::{load_config, save_config};
+#[cfg(feature = "serde")]
+pub use config::{ConfigFormat, ConfigMigration};
+pub use context::Context;
+#[cfg(feature = "serde")]
+pub use error::WireError;
 pub use error::{
-    ProviderError, ProviderResult, RegistryError, RegistryResult, RustratifyError, RustratifyResult,
+    BoxError, ErrorCategory, ErrorCode, ErrorFields, ErrorSeverity, ExitStatus, FieldValue,
+    MultiError, ProviderError, ProviderResult, RegistryError, RegistryResult, RustratifyError,
+    RustratifyResult, Traced,
+};
+#[cfg(feature = "std")]
+pub use output::Warnings;
+pub use output::{ProviderOutput, Warning};
+pub use provider::{CloneableProvider, LifecycleFuture, Provider, ProviderExt};
+#[cfg(feature = "std")]
+pub use registry::{
+    BalanceStrategy, ModuleRegistry, ProviderInfo, QuarantinePolicy, Registry, RegistryBuilder,
+    RegistryManifest, RegistryView, SelectionPolicy, TieBreak, TypedRegistry,
 };
-pub use provider::{CloneableProvider, Provider, ProviderExt};
-pub use registry::{Registry, RegistryBuilder};
+#[cfg(feature = "derive")]
+pub use rustratify_derive::{sea_facade, spi, spi_trait, Config, Event, EventKind};
+#[cfg(feature = "std")]
 pub use stream::{create_stream, EventSender, EventStream, StreamBuilder};
 
 // Re-export async-trait for convenience
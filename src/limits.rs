@@ -0,0 +1,530 @@
+//! Per-run resource limits.
+//!
+//! A [`RunLimiter`] drives one run's future under [`RunLimits`]: a
+//! wall-clock budget, a memory budget and a cap on spawned subprocesses.
+//! When a limit is exceeded the run's future is dropped, which cancels it,
+//! and the run fails with [`ProviderError::LimitExceeded`].
+//!
+//! - Wall-clock time is measured from the first poll.
+//! - Memory is the net number of bytes allocated while the run's future is
+//!   being polled, and is only tracked when the binary installs
+//!   [`TrackingAllocator`] as its global allocator. Work moved to other
+//!   tasks or threads is not charged to the run.
+//! - Subprocesses count when started with [`spawn`] from inside the run.
+//!
+//! On Linux, the `cgroup` feature adds [`Cgroup`], which enforces the memory
+//! and process limits on the run's subprocesses in the kernel and kills them
+//! when the run is terminated.
+//!
+//! Across runs, a [`ConcurrencyLimiter`] caps how many runs each provider
+//! has in flight, with caps declared by providers in
+//! [`Provider::max_concurrency`](crate::Provider::max_concurrency) or
+//! configured per provider. A [`RunQueue`] instead admits runs to a fixed
+//! pool of workers, so callers can submit more runs than it can process:
+//! waiting runs start by priority, subject to per-submitter quotas.
+//!
+//! Requires the `tokio` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use rustratify::limits::{RunLimiter, RunLimits};
+//! use rustratify::ProviderError;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let limiter = RunLimiter::new(RunLimits::new().wall_clock(Duration::from_millis(10)));
+//! let result = limiter
+//!     .run(async {
+//!         tokio::time::sleep(Duration::from_secs(60)).await;
+//!         Ok(())
+//!     })
+//!     .await;
+//! assert!(matches!(result, Err(ProviderError::LimitExceeded(_))));
+//! # }
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicI64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::error::{ProviderError, ProviderResult};
+
+#[cfg(all(feature = "cgroup", target_os = "linux"))]
+mod cgroup;
+mod concurrency;
+mod run_queue;
+
+#[cfg(all(feature = "cgroup", target_os = "linux"))]
+pub use cgroup::Cgroup;
+pub use concurrency::{ConcurrencyLimiter, ConcurrencyPermit};
+pub use run_queue::{RunHandle, RunPermit, RunQueue};
+
+/// The limits of one run. Every limit is off unless set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunLimits {
+    wall_clock: Option<Duration>,
+    max_memory: Option<u64>,
+    max_subprocesses: Option<usize>,
+}
+
+impl RunLimits {
+    /// No limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail the run once it has taken `duration`.
+    pub fn wall_clock(mut self, duration: Duration) -> Self {
+        self.wall_clock = Some(duration);
+        self
+    }
+
+    /// Fail the run once it holds more than `bytes` of memory.
+    pub fn max_memory(mut self, bytes: u64) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Fail the run when it starts more than `count` subprocesses.
+    pub fn max_subprocesses(mut self, count: usize) -> Self {
+        self.max_subprocesses = Some(count);
+        self
+    }
+
+    /// The wall-clock limit.
+    pub fn wall_clock_limit(&self) -> Option<Duration> {
+        self.wall_clock
+    }
+
+    /// The memory limit, in bytes.
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.max_memory
+    }
+
+    /// The subprocess limit.
+    pub fn subprocess_limit(&self) -> Option<usize> {
+        self.max_subprocesses
+    }
+}
+
+const WITHIN_LIMITS: u8 = 0;
+const MEMORY: u8 = 1;
+const SUBPROCESSES: u8 = 2;
+
+/// Shared state of a run; read by the allocator, so it must not allocate.
+#[derive(Debug)]
+struct Usage {
+    max_memory: i64,
+    max_subprocesses: usize,
+    memory: AtomicI64,
+    peak_memory: AtomicI64,
+    subprocesses: AtomicUsize,
+    exceeded: AtomicU8,
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    cgroup: std::sync::OnceLock<Cgroup>,
+}
+
+impl Usage {
+    fn charge(&self, bytes: i64) {
+        let memory = self.memory.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.peak_memory.fetch_max(memory, Ordering::Relaxed);
+        if memory > self.max_memory {
+            self.exceed(MEMORY);
+        }
+    }
+
+    fn exceeded(&self) -> Option<ProviderError> {
+        let reason = match self.exceeded.load(Ordering::Relaxed) {
+            MEMORY => format!("memory limit of {} bytes", self.max_memory),
+            SUBPROCESSES => format!("subprocess limit of {}", self.max_subprocesses),
+            _ => return None,
+        };
+        Some(ProviderError::LimitExceeded(reason))
+    }
+
+    fn exceed(&self, limit: u8) {
+        let _ = self.exceeded.compare_exchange(
+            WITHIN_LIMITS,
+            limit,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+}
+
+thread_local! {
+    /// The run being polled on this thread.
+    static CURRENT: Cell<*const Usage> = const { Cell::new(std::ptr::null()) };
+}
+
+/// Puts back the previous [`CURRENT`] when dropped, so a run that panics
+/// while polled does not leave its `Usage` behind.
+struct Restore(*const Usage);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = CURRENT.try_with(|current| current.set(self.0));
+    }
+}
+
+/// Run `f` with the run being polled on this thread, if any.
+fn with_current<R>(f: impl FnOnce(&Usage) -> R) -> Option<R> {
+    let usage = CURRENT.try_with(Cell::get).ok()?;
+    // SAFETY: set only while `Limited::poll` holds the `Arc<Usage>`
+    (!usage.is_null()).then(|| f(unsafe { &*usage }))
+}
+
+/// Resource usage of a run so far.
+///
+/// Cloning is cheap; clones observe the same run.
+#[derive(Clone)]
+pub struct RunUsage(Arc<Usage>);
+
+impl RunUsage {
+    /// Net bytes allocated by the run; zero without [`TrackingAllocator`].
+    pub fn memory(&self) -> u64 {
+        self.0.memory.load(Ordering::Relaxed).max(0) as u64
+    }
+
+    /// Highest value [`memory`](Self::memory) has reached.
+    pub fn peak_memory(&self) -> u64 {
+        self.0.peak_memory.load(Ordering::Relaxed).max(0) as u64
+    }
+
+    /// Subprocesses started with [`spawn`].
+    pub fn subprocesses(&self) -> usize {
+        self.0.subprocesses.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for RunUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunUsage")
+            .field("memory", &self.memory())
+            .field("peak_memory", &self.peak_memory())
+            .field("subprocesses", &self.subprocesses())
+            .finish()
+    }
+}
+
+/// Runs one future under [`RunLimits`].
+#[derive(Debug)]
+pub struct RunLimiter {
+    limits: RunLimits,
+    usage: RunUsage,
+}
+
+impl RunLimiter {
+    /// A limiter for one run.
+    pub fn new(limits: RunLimits) -> Self {
+        let max_memory = limits
+            .max_memory
+            .map_or(i64::MAX, |bytes| i64::try_from(bytes).unwrap_or(i64::MAX));
+        Self {
+            limits,
+            usage: RunUsage(Arc::new(Usage {
+                max_memory,
+                max_subprocesses: limits.max_subprocesses.unwrap_or(usize::MAX),
+                memory: AtomicI64::new(0),
+                peak_memory: AtomicI64::new(0),
+                subprocesses: AtomicUsize::new(0),
+                exceeded: AtomicU8::new(WITHIN_LIMITS),
+                #[cfg(all(feature = "cgroup", target_os = "linux"))]
+                cgroup: std::sync::OnceLock::new(),
+            })),
+        }
+    }
+
+    /// Put subprocesses started with [`spawn`] in `cgroup`, applying the
+    /// memory and process limits to it.
+    ///
+    /// The cgroup is killed when the run exceeds a limit, and removed once
+    /// the limiter and its [`RunUsage`] handles drop.
+    ///
+    /// Requires the `cgroup` feature, on Linux.
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    pub fn with_cgroup(self, cgroup: Cgroup) -> ProviderResult<Self> {
+        if let Some(bytes) = self.limits.max_memory {
+            cgroup.set_memory_max(bytes)?;
+        }
+        if let Some(count) = self.limits.max_subprocesses {
+            cgroup.set_pids_max(count)?;
+        }
+        if self.usage.0.cgroup.set(cgroup).is_err() {
+            return Err(ProviderError::ConfigurationError(
+                "run already has a cgroup".to_string(),
+            ));
+        }
+        Ok(self)
+    }
+
+    /// The limits.
+    pub fn limits(&self) -> &RunLimits {
+        &self.limits
+    }
+
+    /// Usage so far.
+    pub fn usage(&self) -> &RunUsage {
+        &self.usage
+    }
+
+    /// Drive `run` to completion within the limits.
+    ///
+    /// Fails with [`ProviderError::LimitExceeded`], dropping `run`, as soon
+    /// as a limit is exceeded.
+    pub async fn run<F, T>(&self, run: F) -> ProviderResult<T>
+    where
+        F: Future<Output = ProviderResult<T>>,
+    {
+        let limited = Limited {
+            run: Box::pin(run),
+            usage: &self.usage.0,
+            deadline: self
+                .limits
+                .wall_clock
+                .map(|d| Box::pin(tokio::time::sleep(d))),
+        };
+        let result = limited.await;
+        if let Err(ProviderError::LimitExceeded(reason)) = &result {
+            tracing::warn!(%reason, "terminating run");
+            #[cfg(all(feature = "cgroup", target_os = "linux"))]
+            if let Some(cgroup) = self.usage.0.cgroup.get() {
+                if let Err(err) = cgroup.kill() {
+                    tracing::warn!(error = %err, "failed to kill run cgroup");
+                }
+            }
+        }
+        result
+    }
+}
+
+struct Limited<'a, T> {
+    run: Pin<Box<dyn Future<Output = ProviderResult<T>> + 'a>>,
+    usage: &'a Arc<Usage>,
+    deadline: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl<T> Future for Limited<'_, T> {
+    type Output = ProviderResult<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let usage = Arc::as_ptr(self.usage);
+        let restore = Restore(CURRENT.with(|current| current.replace(usage)));
+        let polled = self.run.as_mut().poll(cx);
+        drop(restore);
+
+        if let Some(err) = self.usage.exceeded() {
+            return Poll::Ready(Err(err));
+        }
+        if polled.is_ready() {
+            return polled;
+        }
+        if let Some(deadline) = &mut self.deadline {
+            if deadline.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Err(ProviderError::LimitExceeded(
+                    "wall-clock limit".to_string(),
+                )));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// Start `command`, counting it against the limits of the run being
+/// polled, if any.
+///
+/// If the run has a cgroup, the process is moved into it right after it
+/// starts.
+///
+/// Fails with [`ProviderError::LimitExceeded`] without starting the process
+/// if the run already started as many as it may, and with
+/// [`ProviderError::IoError`] if the process cannot be started.
+pub fn spawn(command: &mut Command) -> ProviderResult<Child> {
+    let denied = with_current(|usage| {
+        let started = usage.subprocesses.fetch_add(1, Ordering::Relaxed);
+        if started < usage.max_subprocesses {
+            return None;
+        }
+        usage.subprocesses.fetch_sub(1, Ordering::Relaxed);
+        usage.exceed(SUBPROCESSES);
+        Some(ProviderError::LimitExceeded(format!(
+            "subprocess limit of {}",
+            usage.max_subprocesses
+        )))
+    });
+    if let Some(err) = denied.flatten() {
+        return Err(err);
+    }
+    let child = command
+        .spawn()
+        .map_err(|e| ProviderError::IoError(format!("spawn {:?}: {e}", command.get_program())))?;
+    #[cfg(all(feature = "cgroup", target_os = "linux"))]
+    if let Some(Err(err)) = with_current(|usage| {
+        let cgroup = usage.cgroup.get()?;
+        Some(cgroup.add_process(child.id()))
+    })
+    .flatten()
+    {
+        let mut child = child;
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(err);
+    }
+    Ok(child)
+}
+
+/// A global allocator that charges allocations to the run being polled, so
+/// [`RunLimits::max_memory`] can be enforced.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rustratify::limits::TrackingAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: TrackingAllocator = TrackingAllocator::system();
+/// ```
+#[derive(Debug, Default)]
+pub struct TrackingAllocator<A = System> {
+    inner: A,
+}
+
+impl TrackingAllocator {
+    /// Track allocations made with the system allocator.
+    pub const fn system() -> Self {
+        Self { inner: System }
+    }
+}
+
+impl<A> TrackingAllocator<A> {
+    /// Track allocations made with `inner`.
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+fn charge(bytes: i64) {
+    with_current(|usage| usage.charge(bytes));
+}
+
+// SAFETY: every call is forwarded to `inner`; tracking only updates atomics
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwarded from the caller
+        let ptr = unsafe { self.inner.alloc(layout) };
+        if !ptr.is_null() {
+            charge(layout.size() as i64);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwarded from the caller
+        let ptr = unsafe { self.inner.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            charge(layout.size() as i64);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: forwarded from the caller
+        unsafe { self.inner.dealloc(ptr, layout) };
+        charge(-(layout.size() as i64));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: forwarded from the caller
+        let new_ptr = unsafe { self.inner.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            charge(new_size as i64 - layout.size() as i64);
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_within_limits() {
+        let limiter = RunLimiter::new(
+            RunLimits::new()
+                .wall_clock(Duration::from_secs(5))
+                .max_subprocesses(1),
+        );
+        let value = limiter.run(async { Ok(7) }).await.unwrap();
+        assert_eq!(value, 7);
+        assert_eq!(limiter.usage().subprocesses(), 0);
+    }
+
+    #[test]
+    fn test_panicking_run_restores_current() {
+        let limiter = RunLimiter::new(RunLimits::new());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            runtime.block_on(limiter.run(async { panic!("run failed") as ProviderResult<()> }))
+        }));
+        assert!(panicked.is_err());
+        assert!(with_current(|_| ()).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wall_clock() {
+        let limiter = RunLimiter::new(RunLimits::new().wall_clock(Duration::from_secs(1)));
+        let result = limiter
+            .run(async {
+                tokio::time::sleep(Duration::from_secs(2)).await;
+                Ok(())
+            })
+            .await;
+        assert!(matches!(result, Err(ProviderError::LimitExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn test_subprocess_limit_terminates_run() {
+        let limiter = RunLimiter::new(RunLimits::new().max_subprocesses(0));
+        let result = limiter
+            .run(async {
+                // The run carries on, but is terminated at the next yield
+                let _ = spawn(&mut Command::new("true"));
+                tokio::task::yield_now().await;
+                unreachable!("run should have been terminated");
+            })
+            .await;
+        let err: ProviderResult<()> = result;
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("subprocess limit of 0"));
+        assert_eq!(limiter.usage().subprocesses(), 0);
+    }
+
+    #[test]
+    fn test_memory_charge() {
+        let limiter = RunLimiter::new(RunLimits::new().max_memory(100));
+        let usage = &limiter.usage().0;
+        usage.charge(60);
+        usage.charge(-20);
+        assert!(usage.exceeded().is_none());
+        usage.charge(80);
+        assert_eq!(limiter.usage().peak_memory(), 120);
+        assert!(matches!(
+            usage.exceeded(),
+            Some(ProviderError::LimitExceeded(_))
+        ));
+    }
+}
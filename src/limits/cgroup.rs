@@ -0,0 +1,96 @@
+//! Linux cgroup v2 integration.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{ProviderError, ProviderResult};
+
+/// A cgroup v2 group holding one run's subprocesses.
+///
+/// The parent group must be delegated to this process, e.g. by systemd
+/// with `Delegate=yes`, and have the `memory` and `pids` controllers
+/// enabled for its children. The group is killed and removed on drop.
+///
+/// Requires the `cgroup` feature, on Linux.
+#[derive(Debug)]
+pub struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// Create the group `name` under `parent`, e.g.
+    /// `/sys/fs/cgroup/myapp.service/runs`.
+    pub fn create(parent: impl AsRef<Path>, name: &str) -> ProviderResult<Self> {
+        let path = parent.as_ref().join(name);
+        fs::create_dir(&path).map_err(|e| failed(&path, e))?;
+        Ok(Self { path })
+    }
+
+    /// Path of the group.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Limit the memory of the group's processes to `bytes`.
+    pub fn set_memory_max(&self, bytes: u64) -> ProviderResult<()> {
+        self.write("memory.max", &bytes.to_string())
+    }
+
+    /// Limit the group to `count` processes.
+    pub fn set_pids_max(&self, count: usize) -> ProviderResult<()> {
+        self.write("pids.max", &count.to_string())
+    }
+
+    /// Move the process `pid` into the group.
+    pub fn add_process(&self, pid: u32) -> ProviderResult<()> {
+        self.write("cgroup.procs", &pid.to_string())
+    }
+
+    /// Kill every process in the group.
+    pub fn kill(&self) -> ProviderResult<()> {
+        self.write("cgroup.kill", "1")
+    }
+
+    fn write(&self, file: &str, value: &str) -> ProviderResult<()> {
+        let path = self.path.join(file);
+        fs::write(&path, value).map_err(|e| failed(&path, e))
+    }
+}
+
+fn failed(path: &Path, err: io::Error) -> ProviderError {
+    ProviderError::IoError(format!("cgroup {}: {err}", path.display()))
+}
+
+impl Drop for Cgroup {
+    fn drop(&mut self) {
+        let _ = self.kill();
+        if let Err(err) = fs::remove_dir(&self.path) {
+            tracing::warn!(path = %self.path.display(), error = %err, "failed to remove cgroup");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writes_control_files() {
+        // A plain directory stands in for the cgroup filesystem
+        let parent = std::env::temp_dir().join(format!("rustratify-cgroup-{}", std::process::id()));
+        fs::create_dir_all(&parent).unwrap();
+
+        let cgroup = Cgroup::create(&parent, "run-1").unwrap();
+        cgroup.set_memory_max(1 << 20).unwrap();
+        cgroup.add_process(42).unwrap();
+        let read = |file| fs::read_to_string(parent.join("run-1").join(file)).unwrap();
+        assert_eq!(read("memory.max"), "1048576");
+        assert_eq!(read("cgroup.procs"), "42");
+        assert!(Cgroup::create(&parent, "run-1").is_err());
+
+        drop(cgroup);
+        assert_eq!(read("cgroup.kill"), "1");
+        fs::remove_dir_all(&parent).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! Per-provider caps on runs in flight.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::{ProviderError, ProviderResult};
+use crate::provider::Provider;
+
+/// Caps how many runs each provider has in flight, so a slow provider
+/// cannot take up every worker while runs for other providers wait.
+///
+/// A provider's cap is, in order of precedence, the one configured with
+/// [`limit`](Self::limit), the one it declares in
+/// [`Provider::max_concurrency`], or the [default](Self::default_limit).
+/// Providers without a cap run unbounded. Each capped provider gets its own
+/// semaphore on first use, sized by the cap at that time.
+///
+/// Clones share the semaphores, so one limiter can be handed to every
+/// worker.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::limits::ConcurrencyLimiter;
+/// use rustratify::{Provider, ProviderError};
+/// use std::any::Any;
+///
+/// #[derive(Debug)]
+/// struct Slow;
+///
+/// impl Provider for Slow {
+///     fn name(&self) -> &str { "slow" }
+///     fn max_concurrency(&self) -> Option<usize> { Some(1) }
+///     fn as_any(&self) -> &dyn Any { self }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let limiter = ConcurrencyLimiter::new();
+/// let permit = limiter.acquire(&Slow).await;
+/// assert_eq!(limiter.in_flight("slow"), 1);
+/// assert!(matches!(limiter.try_acquire(&Slow), Err(ProviderError::LimitExceeded(_))));
+///
+/// drop(permit);
+/// let value = limiter.run(&Slow, async { Ok(7) }).await.unwrap();
+/// assert_eq!(value, 7);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyLimiter {
+    configured: HashMap<String, usize>,
+    default: Option<usize>,
+    /// The slots of each capped provider, by name
+    slots: Arc<Mutex<HashMap<String, Slots>>>,
+}
+
+#[derive(Debug)]
+struct Slots {
+    max: usize,
+    semaphore: Arc<Semaphore>,
+}
+
+/// A slot taken from a [`ConcurrencyLimiter`], released on drop.
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl ConcurrencyLimiter {
+    /// A limiter that only applies the caps providers declare.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap the provider called `provider` at `max` runs in flight,
+    /// overriding the cap it declares.
+    pub fn limit(mut self, provider: impl Into<String>, max: usize) -> Self {
+        self.configured.insert(provider.into(), max);
+        self
+    }
+
+    /// Cap providers that neither declare nor are configured with a cap at
+    /// `max` runs in flight.
+    pub fn default_limit(mut self, max: usize) -> Self {
+        self.default = Some(max);
+        self
+    }
+
+    /// The cap that applies to `provider`, or `None` if it runs unbounded.
+    pub fn max_concurrency<P: Provider + ?Sized>(&self, provider: &P) -> Option<usize> {
+        self.configured
+            .get(provider.name())
+            .copied()
+            .or_else(|| provider.max_concurrency())
+            .or(self.default)
+    }
+
+    fn semaphore<P: Provider + ?Sized>(&self, provider: &P) -> Option<Arc<Semaphore>> {
+        let max = self.max_concurrency(provider)?.min(Semaphore::MAX_PERMITS);
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        let slots = slots
+            .entry(provider.name().to_string())
+            .or_insert_with(|| Slots {
+                max,
+                semaphore: Arc::new(Semaphore::new(max)),
+            });
+        Some(Arc::clone(&slots.semaphore))
+    }
+
+    /// Wait for a slot to run `provider`.
+    pub async fn acquire<P: Provider + ?Sized>(&self, provider: &P) -> ConcurrencyPermit {
+        let permit = match self.semaphore(provider) {
+            Some(semaphore) => Some(
+                semaphore
+                    .acquire_owned()
+                    .await
+                    .expect("limiter semaphores are never closed"),
+            ),
+            None => None,
+        };
+        ConcurrencyPermit { _permit: permit }
+    }
+
+    /// Take a slot to run `provider` without waiting.
+    ///
+    /// Fails with [`ProviderError::LimitExceeded`] if the provider is at its
+    /// cap.
+    pub fn try_acquire<P: Provider + ?Sized>(
+        &self,
+        provider: &P,
+    ) -> ProviderResult<ConcurrencyPermit> {
+        let permit = match self.semaphore(provider) {
+            Some(semaphore) => Some(semaphore.try_acquire_owned().map_err(|_| {
+                ProviderError::LimitExceeded(format!(
+                    "provider `{}` is at its concurrency limit",
+                    provider.name()
+                ))
+            })?),
+            None => None,
+        };
+        Ok(ConcurrencyPermit { _permit: permit })
+    }
+
+    /// Wait for a slot, then drive `run` to completion holding it.
+    pub async fn run<P, T>(
+        &self,
+        provider: &P,
+        run: impl Future<Output = ProviderResult<T>>,
+    ) -> ProviderResult<T>
+    where
+        P: Provider + ?Sized,
+    {
+        let _permit = self.acquire(provider).await;
+        run.await
+    }
+
+    /// Get the number of runs in flight for the provider called `name`.
+    ///
+    /// Always zero for providers without a cap, which are not tracked.
+    pub fn in_flight(&self, name: &str) -> usize {
+        let slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        slots.get(name).map_or(0, |slots| {
+            slots
+                .max
+                .saturating_sub(slots.semaphore.available_permits())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::any::Any;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[derive(Debug)]
+    struct Named(&'static str, Option<usize>);
+
+    impl Provider for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn max_concurrency(&self) -> Option<usize> {
+            self.1
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_configured_limit_overrides_declared() {
+        let limiter = ConcurrencyLimiter::new().limit("slow", 2);
+        let slow = Arc::new(Named("slow", Some(1)));
+        assert_eq!(limiter.max_concurrency(slow.as_ref()), Some(2));
+
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let tasks: Vec<_> = (0..5)
+            .map(|_| {
+                let (limiter, slow) = (limiter.clone(), Arc::clone(&slow));
+                let (running, peak) = (Arc::clone(&running), Arc::clone(&peak));
+                tokio::spawn(async move {
+                    limiter
+                        .run(slow.as_ref(), async {
+                            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                            peak.fetch_max(now, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                            running.fetch_sub(1, Ordering::SeqCst);
+                            Ok(())
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(limiter.in_flight("slow"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unbounded_and_default() {
+        let limiter = ConcurrencyLimiter::new();
+        let fast = Named("fast", None);
+        let _permits = (
+            limiter.acquire(&fast).await,
+            limiter.try_acquire(&fast).unwrap(),
+        );
+        assert_eq!(limiter.in_flight("fast"), 0);
+
+        let limiter = limiter.default_limit(1);
+        let _permit = limiter.try_acquire(&fast).unwrap();
+        assert_eq!(limiter.in_flight("fast"), 1);
+        assert!(limiter.try_acquire(&fast).is_err());
+    }
+}
@@ -0,0 +1,406 @@
+//! Admission of runs to a fixed pool of workers.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use crate::error::ProviderResult;
+use crate::metrics::{MetricValue, Metrics};
+
+/// Queues runs until one of a fixed number of workers is free.
+///
+/// Callers submit every run straight away instead of throttling
+/// themselves; each submission gets a [`RunHandle`] that resolves to a
+/// [`RunPermit`] once the run may start. Waiting runs start in order of
+/// priority, higher first, and in submission order within a priority.
+/// Submitter quotas cap how many runs each submitter has running at once,
+/// so one busy submitter cannot take every worker; its other runs wait
+/// while runs of other submitters go ahead.
+///
+/// Clones share the queue.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::limits::RunQueue;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let queue = RunQueue::new(1).submitter_quota(1);
+/// let first = queue.submit("alice", 0).admitted().await;
+///
+/// let later = queue.submit("alice", 0);
+/// let urgent = queue.submit("bob", 10);
+/// assert_eq!(urgent.position(), Some(0));
+/// assert_eq!(later.position(), Some(1));
+/// assert_eq!(queue.depth(), 2);
+///
+/// drop(first);
+/// let _permit = urgent.admitted().await;
+/// assert_eq!(later.position(), Some(0));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RunQueue {
+    state: Arc<Mutex<QueueState>>,
+}
+
+#[derive(Debug)]
+struct QueueState {
+    workers: usize,
+    default_quota: Option<usize>,
+    quotas: HashMap<String, usize>,
+    running: usize,
+    running_by: HashMap<String, usize>,
+    waiting: Vec<Waiting>,
+    next_ticket: u64,
+}
+
+#[derive(Debug)]
+struct Waiting {
+    ticket: u64,
+    priority: i32,
+    submitter: String,
+    admitted: bool,
+    waker: Option<Waker>,
+}
+
+impl Waiting {
+    /// Whether this run starts before `other`, quotas aside.
+    fn precedes(&self, other: &Waiting) -> bool {
+        (self.priority, std::cmp::Reverse(self.ticket))
+            > (other.priority, std::cmp::Reverse(other.ticket))
+    }
+}
+
+impl QueueState {
+    fn quota(&self, submitter: &str) -> Option<usize> {
+        self.quotas.get(submitter).copied().or(self.default_quota)
+    }
+
+    fn under_quota(&self, submitter: &str) -> bool {
+        let running = self.running_by.get(submitter).copied().unwrap_or(0);
+        self.quota(submitter).is_none_or(|quota| running < quota)
+    }
+
+    /// Start waiting runs while workers are free.
+    fn dispatch(&mut self) {
+        while self.running < self.workers {
+            let next = self
+                .waiting
+                .iter()
+                .enumerate()
+                .filter(|(_, run)| !run.admitted && self.under_quota(&run.submitter))
+                .reduce(|best, run| if run.1.precedes(best.1) { run } else { best })
+                .map(|(index, _)| index);
+            let Some(index) = next else {
+                return;
+            };
+            let run = &mut self.waiting[index];
+            run.admitted = true;
+            if let Some(waker) = run.waker.take() {
+                waker.wake();
+            }
+            let submitter = run.submitter.clone();
+            self.start(submitter);
+        }
+    }
+
+    fn start(&mut self, submitter: String) {
+        self.running += 1;
+        *self.running_by.entry(submitter).or_default() += 1;
+    }
+
+    fn finish(&mut self, submitter: &str) {
+        self.running -= 1;
+        if let Some(count) = self.running_by.get_mut(submitter) {
+            *count -= 1;
+            if *count == 0 {
+                self.running_by.remove(submitter);
+            }
+        }
+        self.dispatch();
+    }
+
+    fn index_of(&self, ticket: u64) -> Option<usize> {
+        self.waiting.iter().position(|run| run.ticket == ticket)
+    }
+}
+
+impl RunQueue {
+    /// Create a queue that runs at most `workers` runs at once. A pool of
+    /// zero workers is treated as one.
+    pub fn new(workers: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(QueueState {
+                workers: workers.max(1),
+                default_quota: None,
+                quotas: HashMap::new(),
+                running: 0,
+                running_by: HashMap::new(),
+                waiting: Vec::new(),
+                next_ticket: 0,
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Let each submitter have at most `max` runs running at once, unless
+    /// configured otherwise with [`quota`](Self::quota).
+    pub fn submitter_quota(self, max: usize) -> Self {
+        self.lock().default_quota = Some(max);
+        self
+    }
+
+    /// Let `submitter` have at most `max` runs running at once.
+    pub fn quota(self, submitter: impl Into<String>, max: usize) -> Self {
+        self.lock().quotas.insert(submitter.into(), max);
+        self
+    }
+
+    /// Queue a run for `submitter` at `priority`.
+    ///
+    /// Higher priorities start first. The run may start right away; await
+    /// [`RunHandle::admitted`] before doing its work. Dropping the handle
+    /// withdraws the run.
+    pub fn submit(&self, submitter: impl Into<String>, priority: i32) -> RunHandle {
+        let submitter = submitter.into();
+        let mut state = self.lock();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        state.waiting.push(Waiting {
+            ticket,
+            priority,
+            submitter: submitter.clone(),
+            admitted: false,
+            waker: None,
+        });
+        state.dispatch();
+        RunHandle {
+            queue: self.clone(),
+            ticket,
+            submitter,
+            claimed: false,
+        }
+    }
+
+    /// Queue a run, wait for its turn, then drive `run` to completion
+    /// holding a worker.
+    pub async fn run<T>(
+        &self,
+        submitter: impl Into<String>,
+        priority: i32,
+        run: impl Future<Output = ProviderResult<T>>,
+    ) -> ProviderResult<T> {
+        let _permit = self.submit(submitter, priority).admitted().await;
+        run.await
+    }
+
+    /// Number of runs waiting to start.
+    pub fn depth(&self) -> usize {
+        self.lock()
+            .waiting
+            .iter()
+            .filter(|run| !run.admitted)
+            .count()
+    }
+
+    /// Number of runs waiting to start for `submitter`.
+    pub fn depth_of(&self, submitter: &str) -> usize {
+        self.lock()
+            .waiting
+            .iter()
+            .filter(|run| !run.admitted && run.submitter == submitter)
+            .count()
+    }
+
+    /// Number of runs holding a worker.
+    pub fn running(&self) -> usize {
+        self.lock().running
+    }
+
+    /// Report the queue depth and running runs in `metrics` under the label
+    /// `queue="name"`.
+    pub fn observe(&self, metrics: &Metrics, name: impl Into<String>) {
+        let name = name.into();
+        let queue = self.clone();
+        metrics.register_collector(move |snapshot| {
+            let labels = [("queue", name.as_str())];
+            snapshot.push(
+                "rustratify_run_queue_depth",
+                &labels,
+                MetricValue::Gauge(queue.depth() as f64),
+            );
+            snapshot.push(
+                "rustratify_run_queue_running",
+                &labels,
+                MetricValue::Gauge(queue.running() as f64),
+            );
+        });
+    }
+}
+
+/// A run submitted to a [`RunQueue`].
+///
+/// Dropping the handle before the run starts withdraws it from the queue.
+#[derive(Debug)]
+pub struct RunHandle {
+    queue: RunQueue,
+    ticket: u64,
+    submitter: String,
+    claimed: bool,
+}
+
+impl RunHandle {
+    /// How many waiting runs start before this one, or `None` once it may
+    /// start.
+    ///
+    /// Counts runs of higher priority, and earlier runs of the same
+    /// priority, so it does not foresee runs held back by their
+    /// submitter's quota.
+    pub fn position(&self) -> Option<usize> {
+        let state = self.queue.lock();
+        let run = &state.waiting[state.index_of(self.ticket)?];
+        if run.admitted {
+            return None;
+        }
+        let ahead = state
+            .waiting
+            .iter()
+            .filter(|other| !other.admitted && other.precedes(run))
+            .count();
+        Some(ahead)
+    }
+
+    /// Wait until the run may start.
+    ///
+    /// The returned permit holds a worker until it is dropped.
+    pub async fn admitted(mut self) -> RunPermit {
+        std::future::poll_fn(|cx| self.poll_admitted(cx)).await;
+        self.claimed = true;
+        RunPermit {
+            queue: self.queue.clone(),
+            submitter: std::mem::take(&mut self.submitter),
+        }
+    }
+
+    fn poll_admitted(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.queue.lock();
+        let index = state
+            .index_of(self.ticket)
+            .expect("a run stays queued until its handle is dropped");
+        if state.waiting[index].admitted {
+            state.waiting.remove(index);
+            return Poll::Ready(());
+        }
+        state.waiting[index].waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for RunHandle {
+    fn drop(&mut self) {
+        if self.claimed {
+            return;
+        }
+        let mut state = self.queue.lock();
+        if let Some(index) = state.index_of(self.ticket) {
+            let run = state.waiting.remove(index);
+            if run.admitted {
+                // Started but never claimed: hand the worker on
+                state.finish(&run.submitter);
+            }
+        }
+    }
+}
+
+/// A worker held by a run from a [`RunQueue`], released on drop.
+#[derive(Debug)]
+pub struct RunPermit {
+    queue: RunQueue,
+    submitter: String,
+}
+
+impl Drop for RunPermit {
+    fn drop(&mut self) {
+        self.queue.lock().finish(&self.submitter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_priority_then_submission_order() {
+        let queue = RunQueue::new(1);
+        let first = queue.submit("a", 0).admitted().await;
+
+        let low = queue.submit("a", 0);
+        let high = queue.submit("b", 5);
+        let low_later = queue.submit("c", 0);
+        assert_eq!(queue.depth(), 3);
+        assert_eq!(high.position(), Some(0));
+        assert_eq!(low.position(), Some(1));
+        assert_eq!(low_later.position(), Some(2));
+
+        drop(first);
+        assert_eq!(high.position(), None);
+        let permit = high.admitted().await;
+        assert_eq!(queue.running(), 1);
+        drop(permit);
+        assert_eq!(low.position(), None);
+        assert_eq!(low_later.position(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_submitter_quota_lets_others_ahead() {
+        let queue = RunQueue::new(2).submitter_quota(1).quota("batch", 1);
+        let busy = queue.submit("batch", 0).admitted().await;
+        let more = queue.submit("batch", 10);
+        let other = queue.submit("ui", 0);
+
+        // `more` has the higher priority but its submitter is at quota
+        assert_eq!(other.position(), None);
+        assert_eq!(more.position(), Some(0));
+        assert_eq!(queue.depth_of("batch"), 1);
+        let _other = other.admitted().await;
+
+        drop(busy);
+        let _more = tokio::time::timeout(Duration::from_secs(1), more.admitted())
+            .await
+            .unwrap();
+        assert_eq!(queue.running(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_handles_release_their_turn() {
+        let queue = RunQueue::new(1);
+        let metrics = Metrics::new();
+        queue.observe(&metrics, "runs");
+
+        let first = queue.submit("a", 0);
+        let second = queue.submit("a", 0);
+        assert_eq!(
+            metrics
+                .snapshot()
+                .get("rustratify_run_queue_depth", &[("queue", "runs")]),
+            Some(&MetricValue::Gauge(1.0))
+        );
+
+        // Admitted but never claimed
+        drop(first);
+        assert_eq!(second.position(), None);
+        drop(second);
+        assert_eq!(queue.running(), 0);
+
+        let value = queue.run("a", 0, async { Ok(7) }).await.unwrap();
+        assert_eq!(value, 7);
+        assert_eq!(queue.depth(), 0);
+    }
+}
@@ -0,0 +1,197 @@
+//! Uniform `tracing` output for runs and their events.
+//!
+//! Apps built from several SEA modules get one log shape when every module
+//! runs its work inside a [`run_span`] and logs its events through this
+//! module:
+//!
+//! - [`run_span`] and [`in_run`] wrap a run in an `info` span named `run`
+//!   with `run_id`, `correlation_id`, `provider`, and `config` fields, so
+//!   every record emitted during the run carries them.
+//! - [`log_event`] and [`log_events`] turn stream events into log records
+//!   at the [level](level_of) the event reports, with its
+//!   [kind](EventKind::kind) as a field.
+//!
+//! Records go wherever the application's `tracing` subscriber sends them.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::log_bridge::{in_run, log_events};
+//! use rustratify::stream::{create_stream, EventKind};
+//! use rustratify::Context;
+//! use futures::StreamExt;
+//!
+//! #[derive(Debug)]
+//! enum Build {
+//!     Compiled(String),
+//!     Done,
+//! }
+//!
+//! impl EventKind for Build {
+//!     fn is_terminal(&self) -> bool {
+//!         matches!(self, Self::Done)
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let cx = Context::new().with_run_id("build-7");
+//! let events = in_run(&cx, "cargo", "release", async {
+//!     let (sender, stream) = create_stream();
+//!     sender.send(Build::Compiled("core".into())).await.unwrap();
+//!     sender.send(Build::Done).await.unwrap();
+//!     drop(sender);
+//!     // Logged at debug, then info, inside the run span
+//!     log_events(stream).collect::<Vec<_>>().await
+//! })
+//! .await;
+//! assert_eq!(events.len(), 2);
+//! # }
+//! ```
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+
+use futures_core::Stream;
+use tracing::field::Empty;
+use tracing::{Instrument, Span};
+
+use crate::context::Context;
+use crate::stream::{EventKind, EventStream, Level};
+
+/// Create the span for one run of `provider` with the configuration named
+/// `config`, e.g. [`Config::name`](crate::Config::name).
+///
+/// The run and correlation IDs are taken from `cx` and left empty if it
+/// has none.
+pub fn run_span(cx: &Context, provider: &str, config: &str) -> Span {
+    let span = tracing::info_span!(
+        "run",
+        run_id = Empty,
+        correlation_id = Empty,
+        provider,
+        config
+    );
+    if let Some(run_id) = cx.run_id() {
+        span.record("run_id", run_id);
+    }
+    if let Some(correlation_id) = cx.correlation_id() {
+        span.record("correlation_id", correlation_id);
+    }
+    span
+}
+
+/// Drive `run` inside the [`run_span`] for `cx`, `provider`, and `config`.
+pub async fn in_run<F: Future>(cx: &Context, provider: &str, config: &str, run: F) -> F::Output {
+    run.instrument(run_span(cx, provider, config)).await
+}
+
+/// The level an event is logged at.
+///
+/// The event's own [level](EventKind::level) if it has one; otherwise
+/// [`Level::Error`] for errors, [`Level::Info`] for other terminal events,
+/// and [`Level::Debug`] for the rest.
+pub fn level_of<T: EventKind + ?Sized>(event: &T) -> Level {
+    match event.level() {
+        Some(level) => level,
+        None if event.is_error() => Level::Error,
+        None if event.is_terminal() => Level::Info,
+        None => Level::Debug,
+    }
+}
+
+/// Log `event` at its [level](level_of).
+pub fn log_event<T: EventKind + Debug + ?Sized>(event: &T) {
+    let kind = event.kind();
+    match level_of(event) {
+        Level::Trace => tracing::trace!(kind, ?event, "event"),
+        Level::Debug => tracing::debug!(kind, ?event, "event"),
+        Level::Info => tracing::info!(kind, ?event, "event"),
+        Level::Warn => tracing::warn!(kind, ?event, "event"),
+        Level::Error => tracing::error!(kind, ?event, "event"),
+    }
+}
+
+/// Log every event of `stream` with [`log_event`] as it passes through.
+///
+/// Events are logged in the span of the task reading the stream.
+pub fn log_events<T>(stream: EventStream<T>) -> EventStream<T>
+where
+    T: EventKind + Debug + Send + 'static,
+{
+    Box::pin(LogEvents { inner: stream })
+}
+
+struct LogEvents<T> {
+    inner: EventStream<T>,
+}
+
+impl<T: EventKind + Debug> Stream for LogEvents<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<T>> {
+        let polled = self.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(event)) = &polled {
+            log_event(event);
+        }
+        polled
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[derive(Debug, PartialEq)]
+    enum TestEvent {
+        Progress,
+        Warned,
+        Failed,
+        Done,
+    }
+
+    impl EventKind for TestEvent {
+        fn is_terminal(&self) -> bool {
+            matches!(self, Self::Failed | Self::Done)
+        }
+
+        fn is_error(&self) -> bool {
+            matches!(self, Self::Failed)
+        }
+
+        fn level(&self) -> Option<Level> {
+            matches!(self, Self::Warned).then_some(Level::Warn)
+        }
+    }
+
+    #[test]
+    fn test_level_of() {
+        assert_eq!(level_of(&TestEvent::Progress), Level::Debug);
+        assert_eq!(level_of(&TestEvent::Warned), Level::Warn);
+        assert_eq!(level_of(&TestEvent::Failed), Level::Error);
+        assert_eq!(level_of(&TestEvent::Done), Level::Info);
+    }
+
+    #[tokio::test]
+    async fn test_log_events_passes_events_through() {
+        let events = vec![TestEvent::Progress, TestEvent::Warned, TestEvent::Done];
+        let cx = Context::new().with_run_id("run-1");
+        let logged: Vec<_> = in_run(&cx, "test", "default", async {
+            log_events(futures::stream::iter(events).boxed())
+                .collect()
+                .await
+        })
+        .await;
+        assert_eq!(
+            logged,
+            [TestEvent::Progress, TestEvent::Warned, TestEvent::Done]
+        );
+    }
+}
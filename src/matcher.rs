@@ -0,0 +1,193 @@
+//! Pattern matching for provider selection.
+//!
+//! A provider that routes by more than file extensions returns a
+//! [`Matcher`] from [`Provider::matcher`](crate::Provider::matcher), and the
+//! default [`Provider::supports`](crate::Provider::supports) accepts every
+//! key it matches. [`GlobMatcher`] (feature `glob`) matches paths against
+//! patterns like `**/*.test.ts`, and [`RegexMatcher`] (feature `regex`)
+//! matches keys against patterns like `^api/v\d+/`.
+//!
+//! # Example
+//!
+//! ```rust
+//! # #[cfg(feature = "glob")]
+//! # {
+//! use rustratify::matcher::{GlobMatcher, Matcher};
+//! use rustratify::{Provider, Registry};
+//! use std::any::Any;
+//!
+//! #[derive(Debug)]
+//! struct Jest {
+//!     matcher: GlobMatcher,
+//! }
+//!
+//! impl Provider for Jest {
+//!     fn name(&self) -> &str { "jest" }
+//!     fn matcher(&self) -> Option<&dyn Matcher> { Some(&self.matcher) }
+//!     fn as_any(&self) -> &dyn Any { self }
+//! }
+//!
+//! let jest = Jest { matcher: GlobMatcher::new(["**/*.test.ts"]).unwrap() };
+//! let mut registry: Registry<dyn Provider> = Registry::new();
+//! registry.register(Box::new(jest));
+//!
+//! assert!(registry.find("src/app/button.test.ts").is_some());
+//! assert!(registry.find("src/app/button.ts").is_none());
+//! # }
+//! ```
+
+use core::fmt;
+
+#[cfg(any(feature = "glob", feature = "regex"))]
+use crate::error::{ProviderError, ProviderResult};
+
+/// Decides which keys a provider supports.
+///
+/// Implemented for closures taking the key.
+pub trait Matcher: Send + Sync {
+    /// Check if `key` matches.
+    fn is_match(&self, key: &str) -> bool;
+}
+
+impl fmt::Debug for dyn Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Matcher")
+    }
+}
+
+/// Matches paths against glob patterns. Requires the `glob` feature.
+///
+/// `*` and `?` stay within one path component, `**` spans any number of
+/// them, and `{a,b}` and `[a-z]` work as in a shell. A key matches if it
+/// matches any of the patterns.
+#[cfg(feature = "glob")]
+#[derive(Debug, Clone)]
+pub struct GlobMatcher {
+    patterns: Vec<String>,
+    set: globset::GlobSet,
+}
+
+#[cfg(feature = "glob")]
+impl GlobMatcher {
+    /// Compile `patterns`.
+    ///
+    /// Fails with [`ProviderError::ConfigurationError`] naming the first
+    /// invalid pattern.
+    pub fn new<I, S>(patterns: I) -> ProviderResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let patterns: Vec<String> = patterns.into_iter().map(Into::into).collect();
+        let mut set = globset::GlobSetBuilder::new();
+        for pattern in &patterns {
+            let glob = globset::GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|err| ProviderError::ConfigurationError(err.to_string()))?;
+            set.add(glob);
+        }
+        let set = set
+            .build()
+            .map_err(|err| ProviderError::ConfigurationError(err.to_string()))?;
+        Ok(Self { patterns, set })
+    }
+
+    /// The patterns, as given.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+}
+
+#[cfg(feature = "glob")]
+impl Matcher for GlobMatcher {
+    fn is_match(&self, key: &str) -> bool {
+        self.set.is_match(key)
+    }
+}
+
+/// Matches keys against regular expressions. Requires the `regex` feature.
+///
+/// Patterns are unanchored, so `^api/v\d+/` needs its `^` to match only at
+/// the start. A key matches if it matches any of the patterns.
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct RegexMatcher {
+    set: regex::RegexSet,
+}
+
+#[cfg(feature = "regex")]
+impl RegexMatcher {
+    /// Compile `patterns`.
+    ///
+    /// Fails with [`ProviderError::ConfigurationError`] naming the first
+    /// invalid pattern.
+    pub fn new<I, S>(patterns: I) -> ProviderResult<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let set = regex::RegexSet::new(patterns)
+            .map_err(|err| ProviderError::ConfigurationError(err.to_string()))?;
+        Ok(Self { set })
+    }
+
+    /// The patterns, as given.
+    pub fn patterns(&self) -> &[String] {
+        self.set.patterns()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Matcher for RegexMatcher {
+    fn is_match(&self, key: &str) -> bool {
+        self.set.is_match(key)
+    }
+}
+
+impl<F> Matcher for F
+where
+    F: Fn(&str) -> bool + Send + Sync,
+{
+    fn is_match(&self, key: &str) -> bool {
+        self(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "glob")]
+    #[test]
+    fn test_glob_matcher() {
+        let matcher = GlobMatcher::new(["**/*.test.ts", "docs/*.{md,mdx}"]).unwrap();
+        assert!(matcher.is_match("button.test.ts"));
+        assert!(matcher.is_match("src/ui/button.test.ts"));
+        assert!(matcher.is_match("docs/intro.mdx"));
+        assert!(!matcher.is_match("docs/guide/intro.md"));
+        assert!(!matcher.is_match("src/ui/button.ts"));
+        assert_eq!(matcher.patterns().len(), 2);
+
+        let err = GlobMatcher::new(["src/[a-"]).unwrap_err();
+        assert!(err.to_string().contains("src/[a-"));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_matcher() {
+        let matcher = RegexMatcher::new([r"^api/v\d+/", r"\.graphql$"]).unwrap();
+        assert!(matcher.is_match("api/v2/users"));
+        assert!(matcher.is_match("schema.graphql"));
+        assert!(!matcher.is_match("web/api/v2/users"));
+        let err = RegexMatcher::new(["ok", "(oops"]).unwrap_err();
+        assert!(err.to_string().contains("(oops"));
+    }
+
+    #[test]
+    fn test_closure_matcher() {
+        let matcher = |key: &str| key.starts_with("s3://");
+        assert!(matcher.is_match("s3://bucket"));
+        assert!(!matcher.is_match("file:///tmp"));
+    }
+}
@@ -0,0 +1,631 @@
+//! Counters, gauges, and histograms with pluggable exporters.
+//!
+//! A [`Metrics`] handle owns a set of named, labelled metrics. Hand it to the
+//! parts of an application that should be observed:
+//!
+//! - [`Registry::with_metrics`](crate::Registry::with_metrics) counts lookups
+//!   and tracks the number of providers
+//! - [`StreamBuilder::metrics`](crate::StreamBuilder::metrics) reports a
+//!   stream's event counters and consumer lag
+//! - [`Histogram::start_timer`] times provider calls in wrappers and hosts
+//!
+//! A [`MetricsExporter`] receives [`MetricsSnapshot`]s and sends them to a
+//! monitoring system. With the `prometheus` feature, [`PrometheusExporter`]
+//! renders the Prometheus text format.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::metrics::{MetricValue, Metrics};
+//!
+//! let metrics = Metrics::new();
+//! metrics.describe("jobs_total", "Jobs processed");
+//!
+//! let done = metrics.counter("jobs_total", &[("result", "ok")]);
+//! done.inc();
+//! done.inc_by(2);
+//!
+//! let snapshot = metrics.snapshot();
+//! assert_eq!(
+//!     snapshot.get("jobs_total", &[("result", "ok")]),
+//!     Some(&MetricValue::Counter(3))
+//! );
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::ProviderResult;
+use crate::stream::StreamStats;
+
+#[cfg(feature = "prometheus")]
+mod prometheus;
+
+#[cfg(feature = "prometheus")]
+pub use prometheus::PrometheusExporter;
+
+/// Default histogram bucket bounds, in seconds.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+type Labels = Vec<(String, String)>;
+type Collector = Box<dyn Fn(&mut MetricsSnapshot) + Send + Sync>;
+
+/// A shared set of metrics.
+///
+/// Cloning the handle is cheap; all clones record into the same metrics.
+/// Asking for an existing name and label set returns the existing metric.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    metrics: Mutex<BTreeMap<(String, Labels), Entry>>,
+    help: Mutex<BTreeMap<String, String>>,
+    collectors: Mutex<Vec<Collector>>,
+}
+
+#[derive(Clone)]
+enum Entry {
+    Counter(Counter),
+    Gauge(Gauge),
+    Histogram(Histogram),
+}
+
+impl Metrics {
+    /// Create an empty set of metrics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the help text reported for `name`.
+    pub fn describe(&self, name: impl Into<String>, help: impl Into<String>) {
+        lock(&self.inner.help).insert(name.into(), help.into());
+    }
+
+    /// The counter called `name` with `labels`.
+    ///
+    /// If `name` is already used by a gauge or histogram, a warning is logged
+    /// and the returned counter is not reported. The same holds for the other
+    /// kinds.
+    pub fn counter(&self, name: &str, labels: &[(&str, &str)]) -> Counter {
+        match self.entry(name, labels, || Entry::Counter(Counter::default())) {
+            Entry::Counter(counter) => counter,
+            _ => {
+                conflict(name);
+                Counter::default()
+            }
+        }
+    }
+
+    /// The gauge called `name` with `labels`.
+    pub fn gauge(&self, name: &str, labels: &[(&str, &str)]) -> Gauge {
+        match self.entry(name, labels, || Entry::Gauge(Gauge::default())) {
+            Entry::Gauge(gauge) => gauge,
+            _ => {
+                conflict(name);
+                Gauge::default()
+            }
+        }
+    }
+
+    /// The histogram called `name` with `labels`, using [`DEFAULT_BUCKETS`].
+    pub fn histogram(&self, name: &str, labels: &[(&str, &str)]) -> Histogram {
+        self.histogram_with_buckets(name, labels, DEFAULT_BUCKETS)
+    }
+
+    /// The histogram called `name` with `labels`.
+    ///
+    /// `buckets` are the upper bounds; they only apply when the histogram is
+    /// first created.
+    pub fn histogram_with_buckets(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+        buckets: &[f64],
+    ) -> Histogram {
+        let create = || Entry::Histogram(Histogram::new(buckets));
+        match self.entry(name, labels, create) {
+            Entry::Histogram(histogram) => histogram,
+            _ => {
+                conflict(name);
+                Histogram::new(buckets)
+            }
+        }
+    }
+
+    fn entry(&self, name: &str, labels: &[(&str, &str)], create: impl FnOnce() -> Entry) -> Entry {
+        let mut labels: Labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        labels.sort();
+        let key = (name.to_string(), labels);
+        let mut metrics = lock(&self.inner.metrics);
+        if let Some(entry) = metrics.get(&key) {
+            return entry.clone();
+        }
+        let entry = create();
+        if metrics
+            .iter()
+            .any(|((n, _), e)| n == name && e.kind() != entry.kind())
+        {
+            conflict(name);
+            return entry;
+        }
+        metrics.insert(key, entry.clone());
+        entry
+    }
+
+    /// Add a callback that contributes metrics to every snapshot.
+    ///
+    /// Use this for values that are cheaper to read on demand than to
+    /// record, such as queue depths.
+    pub fn register_collector<F>(&self, collector: F)
+    where
+        F: Fn(&mut MetricsSnapshot) + Send + Sync + 'static,
+    {
+        lock(&self.inner.collectors).push(Box::new(collector));
+    }
+
+    /// Report a stream's counters under the label `stream="name"`.
+    ///
+    /// The stream's stats are read on every snapshot, including after the
+    /// stream has finished.
+    pub fn observe_stream(&self, name: impl Into<String>, stats: StreamStats) {
+        let name = name.into();
+        self.register_collector(move |snapshot| {
+            let labels = [("stream", name.as_str())];
+            let s = stats.snapshot();
+            snapshot.push(
+                "rustratify_stream_sent_total",
+                &labels,
+                MetricValue::Counter(s.sent),
+            );
+            snapshot.push(
+                "rustratify_stream_received_total",
+                &labels,
+                MetricValue::Counter(s.received),
+            );
+            snapshot.push(
+                "rustratify_stream_dropped_total",
+                &labels,
+                MetricValue::Counter(s.dropped),
+            );
+            snapshot.push(
+                "rustratify_stream_buffered",
+                &labels,
+                MetricValue::Gauge(s.buffered as f64),
+            );
+            snapshot.push(
+                "rustratify_stream_consumer_lag_seconds",
+                &labels,
+                MetricValue::Gauge(s.consumer_lag.as_secs_f64()),
+            );
+        });
+    }
+
+    /// The current value of every metric, ordered by name and labels.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut snapshot = MetricsSnapshot::default();
+        for ((name, labels), entry) in lock(&self.inner.metrics).iter() {
+            let value = match entry {
+                Entry::Counter(c) => MetricValue::Counter(c.get()),
+                Entry::Gauge(g) => MetricValue::Gauge(g.get()),
+                Entry::Histogram(h) => MetricValue::Histogram(h.snapshot()),
+            };
+            snapshot.metrics.push(Metric {
+                name: name.clone(),
+                help: None,
+                labels: labels.clone(),
+                value,
+            });
+        }
+        for collector in lock(&self.inner.collectors).iter() {
+            collector(&mut snapshot);
+        }
+        let help = lock(&self.inner.help);
+        for metric in &mut snapshot.metrics {
+            if metric.help.is_none() {
+                metric.help = help.get(&metric.name).cloned();
+            }
+        }
+        snapshot
+            .metrics
+            .sort_by(|a, b| (&a.name, &a.labels).cmp(&(&b.name, &b.labels)));
+        snapshot
+    }
+
+    /// Send a snapshot to `exporter`.
+    pub fn export(&self, exporter: &dyn MetricsExporter) -> ProviderResult<()> {
+        exporter.export(&self.snapshot())
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("metrics", &lock(&self.inner.metrics).len())
+            .field("collectors", &lock(&self.inner.collectors).len())
+            .finish()
+    }
+}
+
+impl Entry {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Counter(_) => "counter",
+            Self::Gauge(_) => "gauge",
+            Self::Histogram(_) => "histogram",
+        }
+    }
+}
+
+/// A name can only have one kind; the new metric is returned but not
+/// recorded, so callers keep working.
+fn conflict(name: &str) {
+    tracing::warn!(metric = name, "metric already registered with another type");
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A monotonically increasing count.
+#[derive(Debug, Clone, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    /// Add one.
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    /// Add `n`.
+    pub fn inc_by(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// The current count.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A value that can go up and down.
+#[derive(Debug, Clone, Default)]
+pub struct Gauge(Arc<AtomicU64>);
+
+impl Gauge {
+    /// Set the value.
+    pub fn set(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Add `delta`, which may be negative.
+    pub fn add(&self, delta: f64) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + delta).to_bits())
+            });
+    }
+
+    /// Add one.
+    pub fn inc(&self) {
+        self.add(1.0);
+    }
+
+    /// Subtract one.
+    pub fn dec(&self) {
+        self.add(-1.0);
+    }
+
+    /// The current value.
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// A distribution of observed values, counted into buckets.
+#[derive(Debug, Clone)]
+pub struct Histogram(Arc<HistogramInner>);
+
+#[derive(Debug)]
+struct HistogramInner {
+    bounds: Vec<f64>,
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum: Gauge,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        let mut bounds = bounds.to_vec();
+        bounds.retain(|b| b.is_finite());
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        Self(Arc::new(HistogramInner {
+            buckets: bounds.iter().map(|_| AtomicU64::new(0)).collect(),
+            bounds,
+            count: AtomicU64::new(0),
+            sum: Gauge::default(),
+        }))
+    }
+
+    /// Record a value.
+    pub fn observe(&self, value: f64) {
+        let inner = &self.0;
+        if let Some(i) = inner.bounds.iter().position(|b| value <= *b) {
+            inner.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        inner.count.fetch_add(1, Ordering::Relaxed);
+        inner.sum.add(value);
+    }
+
+    /// Record a duration in seconds.
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration.as_secs_f64());
+    }
+
+    /// Start timing; the elapsed time is recorded when the timer is dropped.
+    pub fn start_timer(&self) -> HistogramTimer {
+        HistogramTimer {
+            histogram: self.clone(),
+            start: Instant::now(),
+        }
+    }
+
+    /// The current distribution.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let inner = &self.0;
+        let mut cumulative = 0;
+        let buckets = inner
+            .bounds
+            .iter()
+            .zip(&inner.buckets)
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+        HistogramSnapshot {
+            buckets,
+            count: inner.count.load(Ordering::Relaxed),
+            sum: inner.sum.get(),
+        }
+    }
+}
+
+/// Records the time since [`Histogram::start_timer`] when dropped.
+#[derive(Debug)]
+#[must_use = "the timer records when dropped"]
+pub struct HistogramTimer {
+    histogram: Histogram,
+    start: Instant,
+}
+
+impl HistogramTimer {
+    /// Stop the timer now, returning the recorded duration.
+    pub fn stop(self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for HistogramTimer {
+    fn drop(&mut self) {
+        self.histogram.observe_duration(self.start.elapsed());
+    }
+}
+
+/// A point-in-time copy of a [`Histogram`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistogramSnapshot {
+    /// Upper bounds with the cumulative count of values at or below each
+    pub buckets: Vec<(f64, u64)>,
+    /// Total number of observations
+    pub count: u64,
+    /// Sum of all observed values
+    pub sum: f64,
+}
+
+/// The value of a metric in a snapshot.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum MetricValue {
+    /// A [`Counter`] value
+    Counter(u64),
+    /// A [`Gauge`] value
+    Gauge(f64),
+    /// A [`Histogram`] distribution
+    Histogram(HistogramSnapshot),
+}
+
+/// One metric in a snapshot.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metric {
+    /// Metric name, e.g. `rustratify_registry_lookups_total`
+    pub name: String,
+    /// Help text from [`Metrics::describe`]
+    pub help: Option<String>,
+    /// Label pairs, sorted by key
+    pub labels: Vec<(String, String)>,
+    /// The value
+    pub value: MetricValue,
+}
+
+/// The state of a [`Metrics`] set at one point in time.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetricsSnapshot {
+    /// All metrics, ordered by name and labels
+    pub metrics: Vec<Metric>,
+}
+
+impl MetricsSnapshot {
+    /// Add a metric; used by collectors.
+    pub fn push(&mut self, name: impl Into<String>, labels: &[(&str, &str)], value: MetricValue) {
+        let mut labels: Labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        labels.sort();
+        self.metrics.push(Metric {
+            name: name.into(),
+            help: None,
+            labels,
+            value,
+        });
+    }
+
+    /// The value of the metric called `name` with exactly `labels`.
+    pub fn get(&self, name: &str, labels: &[(&str, &str)]) -> Option<&MetricValue> {
+        self.metrics
+            .iter()
+            .find(|m| {
+                m.name == name
+                    && m.labels.len() == labels.len()
+                    && labels
+                        .iter()
+                        .all(|(k, v)| m.labels.iter().any(|(mk, mv)| mk == k && mv == v))
+            })
+            .map(|m| &m.value)
+    }
+
+    /// Number of metrics.
+    pub fn len(&self) -> usize {
+        self.metrics.len()
+    }
+
+    /// Check if there are no metrics.
+    pub fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+    }
+}
+
+/// Sends metric snapshots to a monitoring system.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::metrics::{Metrics, MetricsExporter, MetricsSnapshot};
+/// use rustratify::ProviderResult;
+///
+/// struct LogExporter;
+///
+/// impl MetricsExporter for LogExporter {
+///     fn name(&self) -> &str {
+///         "log"
+///     }
+///
+///     fn export(&self, snapshot: &MetricsSnapshot) -> ProviderResult<()> {
+///         for metric in &snapshot.metrics {
+///             println!("{} {:?}", metric.name, metric.value);
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let metrics = Metrics::new();
+/// metrics.counter("requests_total", &[]).inc();
+/// metrics.export(&LogExporter).unwrap();
+/// ```
+pub trait MetricsExporter: Send + Sync {
+    /// Exporter name, e.g. `prometheus`.
+    fn name(&self) -> &str;
+
+    /// Send `snapshot`.
+    fn export(&self, snapshot: &MetricsSnapshot) -> ProviderResult<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_name_and_labels_share_metric() {
+        let metrics = Metrics::new();
+        metrics.counter("hits", &[("a", "1"), ("b", "2")]).inc();
+        metrics.counter("hits", &[("b", "2"), ("a", "1")]).inc();
+        metrics.counter("hits", &[("a", "2")]).inc();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(
+            snapshot.get("hits", &[("b", "2"), ("a", "1")]),
+            Some(&MetricValue::Counter(2))
+        );
+    }
+
+    #[test]
+    fn test_gauge_and_histogram() {
+        let metrics = Metrics::new();
+        let gauge = metrics.gauge("depth", &[]);
+        gauge.set(3.0);
+        gauge.dec();
+        assert_eq!(gauge.get(), 2.0);
+
+        let histogram = metrics.histogram_with_buckets("latency", &[], &[1.0, 0.1]);
+        histogram.observe(0.05);
+        histogram.observe(0.5);
+        histogram.observe(7.0);
+        assert_eq!(
+            histogram.snapshot(),
+            HistogramSnapshot {
+                buckets: vec![(0.1, 1), (1.0, 2)],
+                count: 3,
+                sum: 7.55,
+            }
+        );
+
+        drop(histogram.start_timer());
+        assert_eq!(histogram.snapshot().count, 4);
+    }
+
+    #[test]
+    fn test_conflicting_kind_returns_detached_metric() {
+        let metrics = Metrics::new();
+        metrics.counter("x", &[]).inc();
+        let gauge = metrics.gauge("x", &[]);
+        gauge.set(5.0);
+        assert_eq!(
+            metrics.snapshot().get("x", &[]),
+            Some(&MetricValue::Counter(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_observe_stream() {
+        let metrics = Metrics::new();
+        metrics.describe("rustratify_stream_sent_total", "Events sent");
+        let (sender, _stream) = crate::StreamBuilder::<u32>::new()
+            .metrics(&metrics, "jobs")
+            .build();
+        sender.send(1).await.unwrap();
+
+        let snapshot = metrics.snapshot();
+        let sent = snapshot
+            .metrics
+            .iter()
+            .find(|m| m.name == "rustratify_stream_sent_total")
+            .unwrap();
+        assert_eq!(sent.value, MetricValue::Counter(1));
+        assert_eq!(sent.help.as_deref(), Some("Events sent"));
+        assert_eq!(
+            snapshot.get("rustratify_stream_buffered", &[("stream", "jobs")]),
+            Some(&MetricValue::Gauge(1.0))
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Prometheus text exposition format.
+
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use super::{lock, MetricValue, MetricsExporter, MetricsSnapshot};
+use crate::error::ProviderResult;
+
+/// Renders snapshots in the Prometheus text format.
+///
+/// Prometheus scrapes rather than receives, so [`export`](MetricsExporter::export)
+/// keeps the rendered text for an HTTP handler to serve with
+/// [`text`](Self::text). [`render`](Self::render) formats a snapshot directly.
+///
+/// Requires the `prometheus` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::metrics::{Metrics, PrometheusExporter};
+///
+/// let metrics = Metrics::new();
+/// metrics.describe("jobs_total", "Jobs processed");
+/// metrics.counter("jobs_total", &[("result", "ok")]).inc();
+///
+/// let text = PrometheusExporter::render(&metrics.snapshot());
+/// let expected = concat!(
+///     "# HELP jobs_total Jobs processed\n",
+///     "# TYPE jobs_total counter\n",
+///     "jobs_total{result=\"ok\"} 1\n",
+/// );
+/// assert_eq!(text, expected);
+/// ```
+#[derive(Debug, Default)]
+pub struct PrometheusExporter {
+    text: Mutex<String>,
+}
+
+impl PrometheusExporter {
+    /// Create an exporter with no rendered text.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The text from the last export.
+    pub fn text(&self) -> String {
+        lock(&self.text).clone()
+    }
+
+    /// Format `snapshot`.
+    pub fn render(snapshot: &MetricsSnapshot) -> String {
+        let mut out = String::new();
+        let mut previous: Option<&str> = None;
+        for metric in &snapshot.metrics {
+            let name = metric.name.as_str();
+            if previous != Some(name) {
+                if let Some(help) = &metric.help {
+                    let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+                    let _ = writeln!(out, "# HELP {name} {help}");
+                }
+                let kind = match metric.value {
+                    MetricValue::Counter(_) => "counter",
+                    MetricValue::Gauge(_) => "gauge",
+                    MetricValue::Histogram(_) => "histogram",
+                };
+                let _ = writeln!(out, "# TYPE {name} {kind}");
+                previous = Some(name);
+            }
+
+            let labels = &metric.labels;
+            match &metric.value {
+                MetricValue::Counter(v) => sample(&mut out, name, labels, None, &v.to_string()),
+                MetricValue::Gauge(v) => sample(&mut out, name, labels, None, &number(*v)),
+                MetricValue::Histogram(h) => {
+                    let bucket = format!("{name}_bucket");
+                    for (bound, count) in &h.buckets {
+                        let le = number(*bound);
+                        sample(&mut out, &bucket, labels, Some(&le), &count.to_string());
+                    }
+                    sample(
+                        &mut out,
+                        &bucket,
+                        labels,
+                        Some("+Inf"),
+                        &h.count.to_string(),
+                    );
+                    sample(
+                        &mut out,
+                        &format!("{name}_sum"),
+                        labels,
+                        None,
+                        &number(h.sum),
+                    );
+                    let count = h.count.to_string();
+                    sample(&mut out, &format!("{name}_count"), labels, None, &count);
+                }
+            }
+        }
+        out
+    }
+}
+
+impl MetricsExporter for PrometheusExporter {
+    fn name(&self) -> &str {
+        "prometheus"
+    }
+
+    fn export(&self, snapshot: &MetricsSnapshot) -> ProviderResult<()> {
+        *lock(&self.text) = Self::render(snapshot);
+        Ok(())
+    }
+}
+
+fn sample(
+    out: &mut String,
+    name: &str,
+    labels: &[(String, String)],
+    le: Option<&str>,
+    value: &str,
+) {
+    out.push_str(name);
+    let le = le.map(|le| ("le", le));
+    let pairs = labels
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .chain(le);
+    for (i, (key, value)) in pairs.enumerate() {
+        out.push(if i == 0 { '{' } else { ',' });
+        let value = value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        let _ = write!(out, "{key}=\"{value}\"");
+    }
+    if !labels.is_empty() || le.is_some() {
+        out.push('}');
+    }
+    let _ = writeln!(out, " {value}");
+}
+
+fn number(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::Metrics;
+
+    #[test]
+    fn test_render_histogram_and_escaping() {
+        let metrics = Metrics::new();
+        let latency = metrics.histogram_with_buckets("latency_seconds", &[], &[0.1, 1.0]);
+        latency.observe(0.05);
+        latency.observe(2.0);
+        metrics
+            .gauge("temperature", &[("room", "a \"b\"")])
+            .set(f64::INFINITY);
+
+        assert_eq!(
+            PrometheusExporter::render(&metrics.snapshot()),
+            "# TYPE latency_seconds histogram\n\
+             latency_seconds_bucket{le=\"0.1\"} 1\n\
+             latency_seconds_bucket{le=\"1\"} 1\n\
+             latency_seconds_bucket{le=\"+Inf\"} 2\n\
+             latency_seconds_sum 2.05\n\
+             latency_seconds_count 2\n\
+             # TYPE temperature gauge\n\
+             temperature{room=\"a \\\"b\\\"\"} +Inf\n"
+        );
+    }
+
+    #[test]
+    fn test_export_keeps_text() {
+        let metrics = Metrics::new();
+        metrics.counter("a", &[]).inc();
+        let exporter = PrometheusExporter::new();
+        assert_eq!(exporter.text(), "");
+        metrics.export(&exporter).unwrap();
+        assert_eq!(exporter.text(), "# TYPE a counter\na 1\n");
+    }
+}
@@ -0,0 +1,282 @@
+//! Provider results with call metadata.
+//!
+//! A [`ProviderOutput`] carries the value a provider returned together with
+//! which provider produced it, how long the call took, any warnings it
+//! raised, and whether the value came from a cache, so hosts get that
+//! bookkeeping with the result instead of threading it alongside.
+//! [`Instrumented::call_output`](crate::stats::Instrumented::call_output)
+//! fills it in for calls made through an instrumented provider.
+//!
+//! A [`Warning`] is a problem that did not stop the provider from producing
+//! a result, such as a deprecated option or a fallback taken. Providers
+//! raise them with [`Context::warn`](crate::Context::warn) (feature `std`);
+//! the context's [`Warnings`] log aggregates them for the whole run, and
+//! envelope senders built [`with_context`](crate::stream::EnvelopeSender::with_context)
+//! stamp each one on the next event sent.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+
+/// A non-fatal problem reported alongside a result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warning {
+    /// What went wrong
+    pub message: String,
+    /// Machine-readable code, e.g. `deprecated-option`
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub code: Option<String>,
+    /// Name of the provider that raised the warning
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub provider: Option<String>,
+}
+
+impl Warning {
+    /// Create a warning with `message`.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            code: None,
+            provider: None,
+        }
+    }
+
+    /// Set the machine-readable code.
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Set the provider that raised the warning.
+    pub fn with_provider(mut self, provider: impl Into<String>) -> Self {
+        self.provider = Some(provider.into());
+        self
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(provider) = &self.provider {
+            write!(f, "{provider}: ")?;
+        }
+        if let Some(code) = &self.code {
+            write!(f, "[{code}] ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl From<&str> for Warning {
+    fn from(message: &str) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<String> for Warning {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+/// The warnings raised during one run, in the order raised.
+///
+/// Clones share the log. Requires the `std` feature.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct Warnings {
+    log: Arc<Mutex<WarningLog>>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+struct WarningLog {
+    raised: Vec<Warning>,
+    /// How many of `raised` were already handed out by `take_new`
+    taken: usize,
+}
+
+#[cfg(feature = "std")]
+impl Warnings {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, WarningLog> {
+        self.log.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record `warning`.
+    pub fn push(&self, warning: impl Into<Warning>) {
+        self.lock().raised.push(warning.into());
+    }
+
+    /// Every warning raised so far.
+    pub fn all(&self) -> Vec<Warning> {
+        self.lock().raised.clone()
+    }
+
+    /// The warnings raised since the previous call, across all clones.
+    ///
+    /// The log keeps them; only the position is advanced, so each warning
+    /// is handed out once.
+    pub fn take_new(&self) -> Vec<Warning> {
+        let (new, position) = self.peek_new();
+        self.mark_taken(position);
+        new
+    }
+
+    /// The warnings [`take_new`](Self::take_new) would return, without
+    /// handing them out, and the position to pass to
+    /// [`mark_taken`](Self::mark_taken) once they have been delivered.
+    pub(crate) fn peek_new(&self) -> (Vec<Warning>, usize) {
+        let log = self.lock();
+        (log.raised[log.taken..].to_vec(), log.raised.len())
+    }
+
+    /// Hand out the warnings before `position`.
+    pub(crate) fn mark_taken(&self, position: usize) {
+        let mut log = self.lock();
+        log.taken = log.taken.max(position);
+    }
+
+    /// Get the number of warnings raised.
+    pub fn len(&self) -> usize {
+        self.lock().raised.len()
+    }
+
+    /// Check if no warnings were raised.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The value of a provider call, with metadata about the call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProviderOutput<T> {
+    /// The value the provider returned
+    pub value: T,
+    /// Name of the provider that produced the value
+    pub provider: String,
+    /// How long the call took
+    pub duration: Duration,
+    /// Non-fatal problems the provider reported
+    pub warnings: Vec<Warning>,
+    /// Whether the value was served from a cache rather than computed
+    pub cache_hit: bool,
+}
+
+impl<T> ProviderOutput<T> {
+    /// Wrap `value`, produced by the provider called `provider`.
+    pub fn new(provider: impl Into<String>, value: T) -> Self {
+        Self {
+            value,
+            provider: provider.into(),
+            duration: Duration::ZERO,
+            warnings: Vec::new(),
+            cache_hit: false,
+        }
+    }
+
+    /// Set how long the call took.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Add a warning.
+    pub fn with_warning(mut self, warning: impl Into<Warning>) -> Self {
+        self.warnings.push(warning.into());
+        self
+    }
+
+    /// Add several warnings, e.g. the new ones of a run's [`Warnings`].
+    pub fn with_warnings(mut self, warnings: impl IntoIterator<Item = Warning>) -> Self {
+        self.warnings.extend(warnings);
+        self
+    }
+
+    /// Mark the value as served from a cache.
+    pub fn cached(mut self) -> Self {
+        self.cache_hit = true;
+        self
+    }
+
+    /// Check if the provider reported any warnings.
+    pub fn has_warnings(&self) -> bool {
+        !self.warnings.is_empty()
+    }
+
+    /// Transform the value, keeping the metadata.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ProviderOutput<U> {
+        ProviderOutput {
+            value: f(self.value),
+            provider: self.provider,
+            duration: self.duration,
+            warnings: self.warnings,
+            cache_hit: self.cache_hit,
+        }
+    }
+
+    /// Discard the metadata.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_metadata() {
+        let output = ProviderOutput::new("rust", 2)
+            .with_duration(Duration::from_millis(5))
+            .with_warning("deprecated syntax")
+            .cached();
+        assert!(output.has_warnings() && output.cache_hit);
+
+        let output = output.map(|n| n * 10);
+        assert_eq!(output.value, 20);
+        assert_eq!(output.provider, "rust");
+        assert_eq!(output.duration, Duration::from_millis(5));
+        assert_eq!(output.warnings, [Warning::new("deprecated syntax")]);
+        assert_eq!(output.into_value(), 20);
+    }
+
+    #[test]
+    fn test_warning_display() {
+        let warning = Warning::new("`tabs` is deprecated")
+            .with_code("deprecated-option")
+            .with_provider("fmt");
+        assert_eq!(
+            warning.to_string(),
+            "fmt: [deprecated-option] `tabs` is deprecated"
+        );
+        assert_eq!(Warning::from("plain").to_string(), "plain");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_warnings_take_new() {
+        let warnings = Warnings::new();
+        let shared = warnings.clone();
+        warnings.push("first");
+        assert_eq!(shared.take_new(), [Warning::new("first")]);
+        shared.push("second");
+        assert_eq!(warnings.take_new(), [Warning::new("second")]);
+        assert!(warnings.take_new().is_empty());
+        assert_eq!(warnings.all().len(), 2);
+    }
+}
@@ -0,0 +1,385 @@
+//! Capability-based permissions for providers.
+//!
+//! A provider declares the [`Permissions`] it needs (filesystem paths,
+//! network hosts, environment variables) through
+//! [`Provider::permissions`](crate::Provider::permissions), or, for plugins,
+//! in its manifest. Before dispatching to a provider, the host asks a
+//! [`PermissionChecker`] whether the provider may run with what it
+//! requested; [`PermissionPolicy`] is a checker that grants permissions per
+//! provider. Registries enforce a checker with
+//! [`Registry::with_permissions`](crate::Registry::with_permissions).
+//!
+//! Permissions are declarative: they decide what the host agrees to run,
+//! not what a provider's code is able to do. Run code that is actually
+//! hostile in an OS-level sandbox as well.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::permissions::{PermissionChecker, PermissionPolicy, Permissions};
+//! use rustratify::Context;
+//!
+//! let policy = PermissionPolicy::new().grant(
+//!     "formatter",
+//!     Permissions::none().with_write("/srv/workspace").with_env("FMT_*"),
+//! );
+//!
+//! let requested = Permissions::none()
+//!     .with_read("/srv/workspace/src")
+//!     .with_env("FMT_STYLE");
+//! assert!(policy.check("formatter", &requested, &Context::new()).is_ok());
+//!
+//! let requested = requested.with_network("crates.io");
+//! assert!(policy.check("formatter", &requested, &Context::new()).is_err());
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::context::Context;
+use crate::error::{ProviderError, ProviderResult};
+
+/// What a provider may access.
+///
+/// Used both for what a provider requests and what a host grants. A grant
+/// covers a request when every requested entry is covered:
+///
+/// - a path is covered by a granted path that is it or one of its
+///   ancestors; write access implies read access. Paths are compared after
+///   resolving `.` and `..` lexically, without following symlinks, and a
+///   path whose `..` climbs above its start is never covered
+/// - a host is covered by the same host, `*`, or a `*.domain` pattern
+///   matching a subdomain
+/// - an environment variable is covered by the same name, `*`, or a
+///   `PREFIX*` pattern
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Permissions {
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    read: Vec<PathBuf>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    write: Vec<PathBuf>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    network: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    env: Vec<String>,
+}
+
+impl Permissions {
+    /// No access at all.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Add read access to `path` and everything below it.
+    pub fn with_read(mut self, path: impl Into<PathBuf>) -> Self {
+        self.read.push(path.into());
+        self
+    }
+
+    /// Add read and write access to `path` and everything below it.
+    pub fn with_write(mut self, path: impl Into<PathBuf>) -> Self {
+        self.write.push(path.into());
+        self
+    }
+
+    /// Add network access to `host`.
+    pub fn with_network(mut self, host: impl Into<String>) -> Self {
+        self.network.push(host.into());
+        self
+    }
+
+    /// Add access to the environment variable `name`.
+    pub fn with_env(mut self, name: impl Into<String>) -> Self {
+        self.env.push(name.into());
+        self
+    }
+
+    /// Paths with read access.
+    pub fn read_paths(&self) -> &[PathBuf] {
+        &self.read
+    }
+
+    /// Paths with write access.
+    pub fn write_paths(&self) -> &[PathBuf] {
+        &self.write
+    }
+
+    /// Network hosts.
+    pub fn hosts(&self) -> &[String] {
+        &self.network
+    }
+
+    /// Environment variables.
+    pub fn env_vars(&self) -> &[String] {
+        &self.env
+    }
+
+    /// Whether no access is listed.
+    pub fn is_empty(&self) -> bool {
+        self.read.is_empty()
+            && self.write.is_empty()
+            && self.network.is_empty()
+            && self.env.is_empty()
+    }
+
+    /// Whether these permissions cover everything in `requested`.
+    pub fn covers(&self, requested: &Permissions) -> bool {
+        self.missing(requested).is_empty()
+    }
+
+    /// The part of `requested` these permissions do not cover.
+    pub fn missing(&self, requested: &Permissions) -> Permissions {
+        let within = |path: &Path, granted: &Path| match (normalize(path), normalize(granted)) {
+            (Some(path), Some(granted)) => path.starts_with(granted),
+            _ => false,
+        };
+        let readable = |path: &PathBuf| {
+            self.read
+                .iter()
+                .chain(&self.write)
+                .any(|granted| within(path, granted))
+        };
+        let writable = |path: &PathBuf| self.write.iter().any(|granted| within(path, granted));
+        Permissions {
+            read: requested
+                .read
+                .iter()
+                .filter(|p| !readable(p))
+                .cloned()
+                .collect(),
+            write: requested
+                .write
+                .iter()
+                .filter(|p| !writable(p))
+                .cloned()
+                .collect(),
+            network: requested
+                .network
+                .iter()
+                .filter(|host| !self.network.iter().any(|g| host_matches(g, host)))
+                .cloned()
+                .collect(),
+            env: requested
+                .env
+                .iter()
+                .filter(|name| !self.env.iter().any(|g| env_matches(g, name)))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// `path` with `.` and `..` resolved lexically, or `None` if a `..` climbs
+/// above its start.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !matches!(normal.components().next_back(), Some(Component::Normal(_))) {
+                    return None;
+                }
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+    Some(normal)
+}
+
+fn host_matches(granted: &str, host: &str) -> bool {
+    if granted == "*" {
+        return true;
+    }
+    match granted.strip_prefix('*') {
+        Some(suffix) => {
+            host.len() > suffix.len()
+                && host
+                    .to_ascii_lowercase()
+                    .ends_with(&suffix.to_ascii_lowercase())
+        }
+        None => granted.eq_ignore_ascii_case(host),
+    }
+}
+
+fn env_matches(granted: &str, name: &str) -> bool {
+    match granted.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => granted == name,
+    }
+}
+
+impl fmt::Display for Permissions {
+    /// Lists the entries, e.g. `read /srv, network crates.io`, or `nothing`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("nothing");
+        }
+        let entries = self
+            .read
+            .iter()
+            .map(|p| format!("read {}", p.display()))
+            .chain(self.write.iter().map(|p| format!("write {}", p.display())))
+            .chain(self.network.iter().map(|h| format!("network {h}")))
+            .chain(self.env.iter().map(|v| format!("env {v}")));
+        for (i, entry) in entries.enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(&entry)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decides whether a provider may run with the permissions it requests.
+///
+/// Implemented for closures taking the provider name, the requested
+/// permissions and the operation's context.
+pub trait PermissionChecker: Send + Sync {
+    /// Allow or deny `provider` running with `requested` in `cx`.
+    ///
+    /// Denials should be [`ProviderError::PermissionDenied`].
+    fn check(&self, provider: &str, requested: &Permissions, cx: &Context) -> ProviderResult<()>;
+}
+
+impl<F> PermissionChecker for F
+where
+    F: Fn(&str, &Permissions, &Context) -> ProviderResult<()> + Send + Sync,
+{
+    fn check(&self, provider: &str, requested: &Permissions, cx: &Context) -> ProviderResult<()> {
+        self(provider, requested, cx)
+    }
+}
+
+impl fmt::Debug for dyn PermissionChecker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PermissionChecker")
+    }
+}
+
+/// A [`PermissionChecker`] with a fixed grant per provider.
+///
+/// Providers without their own grant get the default grant, which is
+/// [`Permissions::none`] unless set with
+/// [`default_grant`](Self::default_grant).
+#[derive(Debug, Clone, Default)]
+pub struct PermissionPolicy {
+    default: Permissions,
+    grants: HashMap<String, Permissions>,
+}
+
+impl PermissionPolicy {
+    /// A policy granting nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant `permissions` to the provider called `provider`, replacing the
+    /// default grant for it.
+    pub fn grant(mut self, provider: impl Into<String>, permissions: Permissions) -> Self {
+        self.grants.insert(provider.into(), permissions);
+        self
+    }
+
+    /// Grant `permissions` to providers without their own grant.
+    pub fn default_grant(mut self, permissions: Permissions) -> Self {
+        self.default = permissions;
+        self
+    }
+
+    /// What the provider called `provider` is granted.
+    pub fn granted(&self, provider: &str) -> &Permissions {
+        self.grants.get(provider).unwrap_or(&self.default)
+    }
+}
+
+impl PermissionChecker for PermissionPolicy {
+    fn check(&self, provider: &str, requested: &Permissions, _cx: &Context) -> ProviderResult<()> {
+        let missing = self.granted(provider).missing(requested);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(ProviderError::PermissionDenied(format!(
+            "{provider} is not granted {missing}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing() {
+        let granted = Permissions::none()
+            .with_read("/data")
+            .with_write("/tmp/out")
+            .with_network("*.example.com")
+            .with_network("localhost")
+            .with_env("APP_*");
+        let requested = Permissions::none()
+            .with_read("/data/a.txt")
+            .with_read("/tmp/out/log")
+            .with_read("/etc")
+            .with_write("/data/a.txt")
+            .with_network("api.example.com")
+            .with_network("example.com")
+            .with_network("LOCALHOST")
+            .with_env("APP_TOKEN")
+            .with_env("HOME");
+
+        let missing = granted.missing(&requested);
+        assert_eq!(
+            missing,
+            Permissions::none()
+                .with_read("/etc")
+                .with_write("/data/a.txt")
+                .with_network("example.com")
+                .with_env("HOME")
+        );
+        assert_eq!(
+            missing.to_string(),
+            "read /etc, write /data/a.txt, network example.com, env HOME"
+        );
+        assert!(granted.covers(&Permissions::none()));
+        assert!(Permissions::none()
+            .with_network("*")
+            .covers(&Permissions::none().with_network("anything")));
+    }
+
+    #[test]
+    fn test_missing_resolves_parent_dirs() {
+        let granted = Permissions::none().with_read("/srv/data");
+        let escape = Permissions::none().with_read("/srv/data/../../etc/shadow");
+        assert_eq!(granted.missing(&escape), escape);
+        assert!(!granted.covers(&Permissions::none().with_read("/srv/data/..")));
+        assert!(!granted.covers(&Permissions::none().with_read("/srv/../../srv/data")));
+        assert!(granted.covers(&Permissions::none().with_read("/srv/data/./a/../b")));
+        assert!(!Permissions::none()
+            .with_read("data")
+            .covers(&Permissions::none().with_read("data/../../data")));
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = PermissionPolicy::new()
+            .default_grant(Permissions::none().with_env("*"))
+            .grant("net", Permissions::none().with_network("*"));
+        let cx = Context::new();
+        let env = Permissions::none().with_env("HOME");
+        let net = Permissions::none().with_network("crates.io");
+
+        assert!(policy.check("other", &env, &cx).is_ok());
+        assert!(policy.check("net", &net, &cx).is_ok());
+        assert!(matches!(
+            policy.check("net", &env, &cx),
+            Err(ProviderError::PermissionDenied(_))
+        ));
+        assert!(policy.check("other", &net, &cx).is_err());
+    }
+}
@@ -0,0 +1,567 @@
+//! Dynamically loaded provider plugins with a stable ABI.
+//!
+//! Rust trait objects have no stable layout, so a `Box<dyn Provider>` built
+//! by a plugin compiled with a different compiler cannot be used by the
+//! host. Plugins are built on [`abi_stable`](https://docs.rs/abi_stable)
+//! instead: each exports a versioned [`PluginRoot`] module with
+//! [`export_plugin!`](crate::export_plugin), providers cross the boundary
+//! as [`StableProvider`] trait objects, and the host wraps each one in a
+//! [`PluginProvider`] that implements [`Provider`]. `abi_stable` checks the
+//! layout of every type in the root module when the library is loaded, so
+//! a plugin built against an incompatible version of this crate is
+//! rejected rather than misread.
+//!
+//! Requires the `abi_stable` feature. Plugin crates also depend on
+//! `abi_stable` directly, since the code [`export_plugin!`](crate::export_plugin)
+//! generates refers to it.
+//!
+//! # Example
+//!
+//! In the plugin crate (`crate-type = ["cdylib"]`, depending on
+//! `abi_stable = "0.11"`):
+//!
+//! ```rust
+//! use std::any::Any;
+//!
+//! use rustratify::{export_plugin, Provider};
+//!
+//! #[derive(Debug)]
+//! struct RustProvider;
+//!
+//! impl Provider for RustProvider {
+//!     fn name(&self) -> &str {
+//!         "rust"
+//!     }
+//!
+//!     fn extensions(&self) -> &[&str] {
+//!         &[".rs"]
+//!     }
+//!
+//!     fn as_any(&self) -> &dyn Any {
+//!         self
+//!     }
+//! }
+//!
+//! export_plugin! {
+//!     name: "lang-rust",
+//!     version: env!("CARGO_PKG_VERSION"),
+//!     providers: [RustProvider],
+//! }
+//! ```
+//!
+//! In the host:
+//!
+//! ```rust,no_run
+//! use rustratify::plugin::Plugin;
+//! use rustratify::{Provider, Registry};
+//!
+//! let plugin = unsafe { Plugin::load("target/release/liblang_rust.so") }.unwrap();
+//! let mut registry: Registry<dyn Provider> = Registry::new();
+//! for provider in plugin.providers() {
+//!     registry.register(Box::new(provider));
+//! }
+//! ```
+//!
+//! # Packaging
+//!
+//! A plugin ships as its library plus a [`ProviderManifest`] declaring its
+//! name, version, capabilities, configuration schema and checksum. A
+//! [`PluginCatalog`] scans a directory of manifests and loads only plugins
+//! whose library matches its checksum:
+//!
+//! ```rust,no_run
+//! use rustratify::plugin::PluginCatalog;
+//! use rustratify::{Provider, Registry};
+//!
+//! let catalog = PluginCatalog::scan("/usr/lib/myapp/plugins").unwrap();
+//! let mut registry: Registry<dyn Provider> = Registry::new();
+//! let plugins = unsafe {
+//!     catalog.register_matching(&mut registry, |m| m.has_capability("lint"))
+//! }
+//! .unwrap();
+//! ```
+
+// `#[sabi_trait]` implements traits inside a generated `const` item
+#![allow(non_local_definitions)]
+
+use std::any::Any;
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+#[doc(hidden)]
+pub use abi_stable;
+use abi_stable::library::{lib_header_from_path, RootModule};
+use abi_stable::sabi_trait::TD_Opaque;
+use abi_stable::sabi_types::VersionStrings;
+use abi_stable::std_types::{RBox, ROption, RResult, RStr, RString, RVec};
+use abi_stable::{declare_root_module_statics, package_version_strings, sabi_trait, StableAbi};
+
+use crate::error::{ProviderError, ProviderResult};
+use crate::permissions::Permissions;
+use crate::provider::Provider;
+
+mod manifest;
+
+pub use manifest::{CatalogEntry, PluginCatalog, ProviderManifest};
+
+/// Version of the plugin ABI. Plugins built against a different version are
+/// rejected at load time.
+pub const ABI_VERSION: u32 = 2;
+
+/// A provider as exported by a plugin.
+///
+/// Created from a [`Provider`] with [`export_provider`], which catches
+/// panics inside its methods on the plugin side of the boundary.
+#[sabi_trait]
+pub trait StableProvider: Send + Sync + fmt::Debug {
+    /// See [`Provider::name`].
+    fn name(&self) -> RStr<'_>;
+
+    /// See [`Provider::extensions`].
+    fn extensions(&self) -> RVec<RStr<'_>>;
+
+    /// See [`Provider::mime_types`].
+    fn mime_types(&self) -> RVec<RStr<'_>>;
+
+    /// See [`Provider::schemes`].
+    fn schemes(&self) -> RVec<RStr<'_>>;
+
+    /// See [`Provider::supports`].
+    fn supports(&self, key: RStr<'_>) -> bool;
+
+    /// See [`Provider::priority`].
+    fn priority(&self) -> i32;
+
+    /// See [`Provider::max_concurrency`].
+    fn max_concurrency(&self) -> ROption<usize>;
+
+    /// See [`Provider::health`]; errors cross as their message.
+    #[sabi(last_prefix_field)]
+    fn health(&self) -> RResult<(), RString>;
+}
+
+/// An owned [`StableProvider`] trait object.
+pub type StableProviderBox = StableProvider_TO<'static, RBox<()>>;
+
+/// Run `f` on `provider`, returning `default` if it panics.
+fn guard<'a, P: Provider, R>(provider: &'a P, default: R, f: impl FnOnce(&'a P) -> R) -> R {
+    catch_unwind(AssertUnwindSafe(|| f(provider))).unwrap_or(default)
+}
+
+/// A [`Provider`] behind the [`StableProvider`] interface.
+struct Exported<P>(P);
+
+impl<P: fmt::Debug> fmt::Debug for Exported<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+fn strs<'a>(strs: &[&'a str]) -> RVec<RStr<'a>> {
+    strs.iter().map(|s| RStr::from_str(s)).collect()
+}
+
+impl<P: Provider> StableProvider for Exported<P> {
+    fn name(&self) -> RStr<'_> {
+        guard(&self.0, RStr::from_str(""), |p| RStr::from_str(p.name()))
+    }
+
+    fn extensions(&self) -> RVec<RStr<'_>> {
+        guard(&self.0, RVec::new(), |p| strs(p.extensions()))
+    }
+
+    fn mime_types(&self) -> RVec<RStr<'_>> {
+        guard(&self.0, RVec::new(), |p| strs(p.mime_types()))
+    }
+
+    fn schemes(&self) -> RVec<RStr<'_>> {
+        guard(&self.0, RVec::new(), |p| strs(p.schemes()))
+    }
+
+    fn supports(&self, key: RStr<'_>) -> bool {
+        guard(&self.0, false, |p| p.supports(key.as_str()))
+    }
+
+    fn priority(&self) -> i32 {
+        guard(&self.0, 0, |p| p.priority())
+    }
+
+    fn max_concurrency(&self) -> ROption<usize> {
+        guard(&self.0, ROption::RNone, |p| p.max_concurrency().into())
+    }
+
+    fn health(&self) -> RResult<(), RString> {
+        let result = guard(&self.0, Err("provider panicked".to_string()), |p| {
+            p.health().map_err(|e| e.to_string())
+        });
+        result.map_err(RString::from).into()
+    }
+}
+
+/// Export `provider` across the plugin boundary.
+pub fn export_provider<P: Provider + 'static>(provider: P) -> StableProviderBox {
+    StableProvider_TO::from_value(Exported(provider), TD_Opaque)
+}
+
+/// The root module a plugin exports.
+///
+/// Fields can be added after `providers` in later versions without
+/// breaking plugins built against this one.
+#[repr(C)]
+#[derive(StableAbi)]
+#[sabi(kind(Prefix(prefix_ref = PluginRootRef)))]
+#[sabi(missing_field(panic))]
+pub struct PluginRoot {
+    /// [`ABI_VERSION`] the plugin was built against
+    pub abi_version: u32,
+    /// Plugin name
+    pub name: RStr<'static>,
+    /// Plugin version
+    pub version: RStr<'static>,
+    /// Create every provider the plugin exports
+    #[sabi(last_prefix_field)]
+    pub providers: extern "C" fn() -> RVec<StableProviderBox>,
+}
+
+impl RootModule for PluginRootRef {
+    declare_root_module_statics! {PluginRootRef}
+
+    const BASE_NAME: &'static str = "rustratify_plugin";
+    const NAME: &'static str = "rustratify_plugin";
+    const VERSION_STRINGS: VersionStrings = package_version_strings!();
+}
+
+/// Export a plugin root with the given providers.
+///
+/// Each provider expression is evaluated every time the host asks for the
+/// plugin's providers. Use it once, in a `cdylib` crate that depends on
+/// `abi_stable`.
+///
+/// Requires the `abi_stable` feature.
+#[macro_export]
+macro_rules! export_plugin {
+    (
+        name: $name:expr,
+        version: $version:expr,
+        providers: [$($provider:expr),* $(,)?] $(,)?
+    ) => {
+        #[$crate::plugin::abi_stable::export_root_module]
+        pub fn rustratify_plugin_root() -> $crate::plugin::PluginRootRef {
+            use $crate::plugin::abi_stable::std_types::{RStr, RVec};
+
+            extern "C" fn providers() -> RVec<$crate::plugin::StableProviderBox> {
+                RVec::from(vec![$($crate::plugin::export_provider($provider)),*])
+            }
+
+            $crate::plugin::abi_stable::prefix_type::PrefixTypeTrait::leak_into_prefix(
+                $crate::plugin::PluginRoot {
+                    abi_version: $crate::plugin::ABI_VERSION,
+                    name: RStr::from_str($name),
+                    version: RStr::from_str($version),
+                    providers,
+                },
+            )
+        }
+    };
+}
+
+/// A loaded plugin.
+///
+/// Plugin libraries stay loaded for the rest of the process, so the plugin
+/// can be dropped once its providers are registered.
+pub struct Plugin {
+    name: String,
+    version: String,
+    root: PluginRootRef,
+    permissions: Permissions,
+}
+
+impl Plugin {
+    /// Load the plugin library at `path`.
+    ///
+    /// Fails with [`ProviderError::InitializationFailed`] if the library
+    /// cannot be loaded, does not export a root module, was built against
+    /// an incompatible version of this crate or its types, or against
+    /// another [`ABI_VERSION`].
+    ///
+    /// # Safety
+    ///
+    /// Loading runs the library's initialization code, and the library must
+    /// have been built with [`export_plugin!`](crate::export_plugin).
+    pub unsafe fn load(path: impl AsRef<Path>) -> ProviderResult<Self> {
+        let path = path.as_ref();
+        let failed = |e: abi_stable::library::LibraryError| {
+            ProviderError::InitializationFailed(format!("plugin {}: {e}", path.display()))
+        };
+        let root = lib_header_from_path(path)
+            .and_then(|header| header.init_root_module::<PluginRootRef>())
+            .map_err(failed)?;
+        Self::from_root(root)
+    }
+
+    /// Use a plugin root linked into this binary, e.g. the function
+    /// generated by [`export_plugin!`](crate::export_plugin) in a crate
+    /// that is also built as an `rlib`.
+    pub fn from_root(root: PluginRootRef) -> ProviderResult<Self> {
+        if root.abi_version() != ABI_VERSION {
+            return Err(ProviderError::InitializationFailed(format!(
+                "plugin ABI version {} is not supported (expected {ABI_VERSION})",
+                root.abi_version()
+            )));
+        }
+        Ok(Self {
+            name: root.name().to_string(),
+            version: root.version().to_string(),
+            root,
+            permissions: Permissions::none(),
+        })
+    }
+
+    /// Plugin name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Plugin version.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Declare what the plugin's providers need to access, usually from its
+    /// [`ProviderManifest`]; the ABI does not carry permissions.
+    pub fn with_permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// What the plugin's providers need to access.
+    pub fn permissions(&self) -> &Permissions {
+        &self.permissions
+    }
+
+    /// Create every provider the plugin exports.
+    pub fn providers(&self) -> Vec<PluginProvider> {
+        (self.root.providers())()
+            .into_iter()
+            .map(|inner| PluginProvider::new(inner, self))
+            .collect()
+    }
+}
+
+impl fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Plugin")
+            .field("name", &self.name)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+/// A provider exported by a [`Plugin`].
+pub struct PluginProvider {
+    name: String,
+    plugin: String,
+    extensions: Vec<&'static str>,
+    mime_types: Vec<&'static str>,
+    schemes: Vec<&'static str>,
+    priority: i32,
+    max_concurrency: Option<usize>,
+    permissions: Permissions,
+    inner: StableProviderBox,
+}
+
+impl PluginProvider {
+    fn new(inner: StableProviderBox, plugin: &Plugin) -> Self {
+        // SAFETY: the strings borrow from the provider behind `inner` or
+        // from statics of the plugin, whose library is never unloaded.
+        // `inner` is owned by `self` and only used through `&self`, so the
+        // provider lives, unchanged, for as long as the strings are handed
+        // out by `&self` methods.
+        let leak = |strs: RVec<RStr<'_>>| -> Vec<&'static str> {
+            strs.iter()
+                .map(|s| unsafe { &*(s.as_str() as *const str) })
+                .collect()
+        };
+        Self {
+            name: inner.name().to_string(),
+            plugin: plugin.name.clone(),
+            extensions: leak(inner.extensions()),
+            mime_types: leak(inner.mime_types()),
+            schemes: leak(inner.schemes()),
+            priority: inner.priority(),
+            max_concurrency: inner.max_concurrency().into(),
+            permissions: plugin.permissions.clone(),
+            inner,
+        }
+    }
+
+    /// Name of the plugin that exported the provider.
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+}
+
+impl fmt::Debug for PluginProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PluginProvider")
+            .field("name", &self.name)
+            .field("plugin", &self.plugin)
+            .field("extensions", &self.extensions)
+            .finish()
+    }
+}
+
+impl Provider for PluginProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &self.mime_types
+    }
+
+    fn schemes(&self) -> &[&str] {
+        &self.schemes
+    }
+
+    fn supports(&self, key: &str) -> bool {
+        self.inner.supports(RStr::from_str(key))
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn max_concurrency(&self) -> Option<usize> {
+        self.max_concurrency
+    }
+
+    fn permissions(&self) -> Permissions {
+        self.permissions.clone()
+    }
+
+    fn health(&self) -> ProviderResult<()> {
+        self.inner
+            .health()
+            .into_result()
+            .map_err(|e| ProviderError::ExecutionFailed(e.into()))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi_stable::prefix_type::PrefixTypeTrait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug)]
+    struct Lang {
+        name: String,
+        extensions: Vec<&'static str>,
+    }
+
+    impl Lang {
+        fn new(name: &str, extensions: &[&'static str]) -> Self {
+            Self {
+                name: name.to_string(),
+                extensions: extensions.to_vec(),
+            }
+        }
+    }
+
+    impl Drop for Lang {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl Provider for Lang {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &self.extensions
+        }
+
+        fn supports(&self, key: &str) -> bool {
+            assert_ne!(key, "panic", "provider panicked");
+            self.extensions.iter().any(|ext| key.ends_with(ext))
+        }
+
+        fn priority(&self) -> i32 {
+            self.extensions.len() as i32
+        }
+
+        fn max_concurrency(&self) -> Option<usize> {
+            Some(4)
+        }
+
+        fn health(&self) -> ProviderResult<()> {
+            match self.name.as_str() {
+                "web" => Err(ProviderError::ExecutionFailed("offline".into())),
+                _ => Ok(()),
+            }
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    crate::export_plugin! {
+        name: "langs",
+        version: "1.2.0",
+        providers: [Lang::new("rust", &[".rs"]), Lang::new("web", &[".js", ".ts"])],
+    }
+
+    #[test]
+    fn test_exported_providers_work_through_the_abi() {
+        let plugin = Plugin::from_root(rustratify_plugin_root()).unwrap();
+        assert_eq!((plugin.name(), plugin.version()), ("langs", "1.2.0"));
+
+        let providers = plugin.providers();
+        drop(plugin);
+        let web = &providers[1];
+        assert_eq!(web.name(), "web");
+        assert_eq!(web.plugin(), "langs");
+        assert_eq!(web.extensions(), &[".js", ".ts"]);
+        assert_eq!(web.priority(), 2);
+        assert!(web.supports("app.ts"));
+        assert!(!web.supports("main.rs"));
+        assert!(!web.supports("panic"));
+        assert_eq!(web.max_concurrency(), Some(4));
+        assert!(web.health().unwrap_err().to_string().contains("offline"));
+        assert!(providers[0].health().is_ok());
+
+        let before = DROPPED.load(Ordering::SeqCst);
+        drop(providers);
+        assert_eq!(DROPPED.load(Ordering::SeqCst), before + 2);
+    }
+
+    #[test]
+    fn test_rejects_other_abi_versions() {
+        extern "C" fn providers() -> RVec<StableProviderBox> {
+            unreachable!()
+        }
+        let old = PluginRoot {
+            abi_version: 0,
+            name: RStr::from_str("old"),
+            version: RStr::from_str("0.1.0"),
+            providers,
+        };
+        let err = Plugin::from_root(old.leak_into_prefix()).unwrap_err();
+        assert!(err.to_string().contains("ABI version 0"), "{err}");
+    }
+}
@@ -0,0 +1,330 @@
+//! Plugin manifests and directory catalogs.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{Plugin, ABI_VERSION};
+use crate::config::{load_config, ConfigFormat};
+use crate::error::{ProviderError, ProviderResult};
+use crate::permissions::Permissions;
+use crate::provider::Provider;
+use crate::registry::Registry;
+
+/// Packaging metadata shipped next to a plugin library.
+///
+/// # Example
+///
+/// ```toml
+/// name = "lang-rust"
+/// version = "1.2.0"
+/// capabilities = ["format", "lint"]
+/// entry_point = "liblang_rust.so"
+/// checksum = "sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+///
+/// [permissions]
+/// read = ["/srv/workspace"]
+/// env = ["RUSTFMT_*"]
+///
+/// [config_schema]
+/// type = "object"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderManifest {
+    /// Plugin name; must match the name the library exports
+    pub name: String,
+    /// Plugin version, `MAJOR.MINOR.PATCH`; must match the library
+    pub version: String,
+    /// Human-readable description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// What the plugin provides, e.g. API operations, for hosts to select on
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// What the plugin's providers need to access
+    #[serde(default, skip_serializing_if = "Permissions::is_empty")]
+    pub permissions: Permissions,
+    /// JSON Schema of the plugin's configuration, as from
+    /// [`ConfigSchema::to_value`](crate::ConfigSchema::to_value)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_schema: Option<serde_json::Value>,
+    /// Library path, relative to the manifest
+    pub entry_point: PathBuf,
+    /// `sha256:<hex>` digest of the library
+    pub checksum: String,
+    /// [`ABI_VERSION`] the library was built against
+    #[serde(default = "default_abi_version")]
+    pub abi_version: u32,
+}
+
+fn default_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+impl ProviderManifest {
+    /// Load and validate a manifest; the format follows the file extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let manifest: Self = load_config(path)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Check that the fields are well-formed.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("manifest name is empty".to_string());
+        }
+        let semver = self.version.split('.').collect::<Vec<_>>();
+        if semver.len() != 3 || semver.iter().any(|part| part.parse::<u64>().is_err()) {
+            return Err(format!(
+                "manifest `{}` version `{}` is not MAJOR.MINOR.PATCH",
+                self.name, self.version
+            ));
+        }
+        let digest = self.checksum.strip_prefix("sha256:").unwrap_or_default();
+        if digest.len() != 64 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!(
+                "manifest `{}` checksum must be `sha256:` followed by 64 hex digits",
+                self.name
+            ));
+        }
+        if self.entry_point.as_os_str().is_empty() || self.entry_point.is_absolute() {
+            return Err(format!(
+                "manifest `{}` entry point must be a relative path",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// The `sha256:<hex>` checksum of `bytes`.
+    pub fn checksum_of(bytes: &[u8]) -> String {
+        let mut checksum = String::from("sha256:");
+        for byte in Sha256::digest(bytes) {
+            let _ = write!(checksum, "{byte:02x}");
+        }
+        checksum
+    }
+
+    /// Whether the plugin declares `capability`.
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Verify the library next to a manifest in `dir`, returning its path.
+    ///
+    /// Fails with [`ProviderError::InitializationFailed`] if the library is
+    /// missing, its checksum differs, or it targets another ABI version.
+    pub fn verify(&self, dir: &Path) -> ProviderResult<PathBuf> {
+        let failed = |message: String| {
+            ProviderError::InitializationFailed(format!("plugin `{}`: {message}", self.name))
+        };
+        if self.abi_version != ABI_VERSION {
+            return Err(failed(format!(
+                "ABI version {} is not supported (expected {ABI_VERSION})",
+                self.abi_version
+            )));
+        }
+        let path = dir.join(&self.entry_point);
+        let bytes = fs::read(&path).map_err(|e| failed(format!("{}: {e}", path.display())))?;
+        let actual = Self::checksum_of(&bytes);
+        if !actual.eq_ignore_ascii_case(&self.checksum) {
+            return Err(failed(format!(
+                "checksum mismatch for {}: expected {}, found {actual}",
+                path.display(),
+                self.checksum
+            )));
+        }
+        Ok(path)
+    }
+}
+
+/// A manifest found by [`PluginCatalog::scan`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    /// The manifest
+    pub manifest: ProviderManifest,
+    /// The manifest file
+    pub path: PathBuf,
+}
+
+impl CatalogEntry {
+    fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+}
+
+/// The plugin manifests in a directory.
+///
+/// Every file in the directory with a config extension (`.toml`, `.yaml`,
+/// `.yml`, `.json`) whose format is enabled is read as a
+/// [`ProviderManifest`]. Files that fail to load are recorded in
+/// [`skipped`](Self::skipped) rather than failing the scan.
+#[derive(Debug, Clone, Default)]
+pub struct PluginCatalog {
+    entries: Vec<CatalogEntry>,
+    skipped: Vec<(PathBuf, String)>,
+}
+
+impl PluginCatalog {
+    /// Read the manifests in `dir`, ordered by file name.
+    pub fn scan(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let read_err = |e: std::io::Error| format!("{}: {e}", dir.display());
+        let mut paths = fs::read_dir(dir)
+            .map_err(read_err)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_err)?;
+        paths.sort();
+
+        let mut catalog = Self::default();
+        for path in paths {
+            if !path.is_file() || !ConfigFormat::from_path(&path).is_some_and(|f| f.is_enabled()) {
+                continue;
+            }
+            match ProviderManifest::load(&path) {
+                Ok(manifest) if catalog.get(&manifest.name).is_some() => {
+                    let message = format!("duplicate plugin `{}`", manifest.name);
+                    catalog.skipped.push((path, message));
+                }
+                Ok(manifest) => catalog.entries.push(CatalogEntry { manifest, path }),
+                Err(message) => catalog.skipped.push((path, message)),
+            }
+        }
+        for (path, message) in &catalog.skipped {
+            tracing::warn!(path = %path.display(), %message, "skipping plugin manifest");
+        }
+        Ok(catalog)
+    }
+
+    /// The manifests that loaded.
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    /// Files that were not valid manifests, with the reason.
+    pub fn skipped(&self) -> &[(PathBuf, String)] {
+        &self.skipped
+    }
+
+    /// The entry for the plugin called `name`.
+    pub fn get(&self, name: &str) -> Option<&CatalogEntry> {
+        self.entries.iter().find(|e| e.manifest.name == name)
+    }
+
+    /// Verify and load the plugin called `name`.
+    ///
+    /// Fails if the plugin is not in the catalog, fails
+    /// [`verify`](ProviderManifest::verify), or exports a different name or
+    /// version than its manifest declares.
+    ///
+    /// # Safety
+    ///
+    /// See [`Plugin::load`].
+    pub unsafe fn load(&self, name: &str) -> ProviderResult<Plugin> {
+        let entry = self
+            .get(name)
+            .ok_or_else(|| ProviderError::NotFound(name.to_string()))?;
+        let manifest = &entry.manifest;
+        let library = manifest.verify(entry.dir())?;
+        // SAFETY: forwarded to the caller
+        let plugin =
+            unsafe { Plugin::load(library) }?.with_permissions(manifest.permissions.clone());
+        if plugin.name() != manifest.name || plugin.version() != manifest.version {
+            return Err(ProviderError::InitializationFailed(format!(
+                "plugin `{}` {} exports `{}` {}",
+                manifest.name,
+                manifest.version,
+                plugin.name(),
+                plugin.version()
+            )));
+        }
+        Ok(plugin)
+    }
+
+    /// Load every plugin whose manifest matches `filter` and register its
+    /// providers, returning the loaded plugins.
+    ///
+    /// Stops at the first plugin that fails to load; providers of plugins
+    /// loaded before it stay registered.
+    ///
+    /// # Safety
+    ///
+    /// See [`Plugin::load`].
+    pub unsafe fn register_matching<F>(
+        &self,
+        registry: &mut Registry<dyn Provider>,
+        filter: F,
+    ) -> ProviderResult<Vec<Plugin>>
+    where
+        F: Fn(&ProviderManifest) -> bool,
+    {
+        let mut loaded = Vec::new();
+        for entry in self.entries.iter().filter(|e| filter(&e.manifest)) {
+            // SAFETY: forwarded to the caller
+            let plugin = unsafe { self.load(&entry.manifest.name) }?;
+            for provider in plugin.providers() {
+                registry.register(Box::new(provider));
+            }
+            loaded.push(plugin);
+        }
+        Ok(loaded)
+    }
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+    use super::*;
+
+    fn manifest(name: &str, entry: &str, checksum: &str) -> String {
+        format!(
+            "name = \"{name}\"\nversion = \"1.0.0\"\ncapabilities = [\"lint\"]\n\
+             entry_point = \"{entry}\"\nchecksum = \"{checksum}\"\n"
+        )
+    }
+
+    #[test]
+    fn test_scan_and_verify() {
+        let dir = std::env::temp_dir().join(format!("rustratify-catalog-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("liblint.so"), b"not really a library").unwrap();
+        let checksum = ProviderManifest::checksum_of(b"not really a library");
+        fs::write(
+            dir.join("a.toml"),
+            manifest("lint", "liblint.so", &checksum),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("b.toml"),
+            manifest("lint", "liblint.so", &checksum),
+        )
+        .unwrap();
+        fs::write(dir.join("c.toml"), manifest("bad", "libbad.so", "md5:00")).unwrap();
+        let other = ProviderManifest::checksum_of(b"tampered");
+        fs::write(
+            dir.join("d.toml"),
+            manifest("tampered", "liblint.so", &other),
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let catalog = PluginCatalog::scan(&dir).unwrap();
+        let names: Vec<_> = catalog.entries().iter().map(|e| &e.manifest.name).collect();
+        assert_eq!(names, ["lint", "tampered"]);
+        assert_eq!(catalog.skipped().len(), 2);
+        assert!(catalog.get("lint").unwrap().manifest.has_capability("lint"));
+
+        let lint = &catalog.get("lint").unwrap().manifest;
+        assert_eq!(lint.verify(&dir).unwrap(), dir.join("liblint.so"));
+        let err = catalog.get("tampered").unwrap().manifest.verify(&dir);
+        assert!(err.unwrap_err().to_string().contains("checksum mismatch"));
+        // SAFETY: verification fails before anything is loaded
+        assert!(unsafe { catalog.load("tampered") }.is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,498 @@
+//! Async pools of expensive resources.
+//!
+//! A [`Pool`] keeps up to a maximum number of resources, such as connections
+//! or subprocesses, created on demand by a [`PoolManager`]. Checked-out
+//! resources return to the pool when their [`Pooled`] guard drops. Idle
+//! resources are discarded after an idle timeout, and each one is validated
+//! by the manager before it is handed out again.
+//!
+//! [`PooledProvider`] puts a pool of provider instances in a registry, for
+//! providers that hold per-instance state and cannot be shared across calls.
+//!
+//! Requires the `tokio` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::pool::{Pool, PoolManager};
+//! use rustratify::{async_trait, ProviderResult};
+//!
+//! struct Connections;
+//!
+//! #[async_trait]
+//! impl PoolManager for Connections {
+//!     type Resource = String;
+//!
+//!     async fn create(&self) -> ProviderResult<String> {
+//!         Ok("connection".to_string())
+//!     }
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() -> ProviderResult<()> {
+//! let pool = Pool::builder(Connections).max_size(4).build();
+//! {
+//!     let conn = pool.get().await?;
+//!     assert_eq!(conn.as_str(), "connection");
+//!     assert_eq!(pool.status().in_use, 1);
+//! }
+//! assert_eq!(pool.status().idle, 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::error::{ProviderError, ProviderResult};
+use crate::provider::Provider;
+
+/// Creates and validates the resources of a [`Pool`].
+#[async_trait]
+pub trait PoolManager: Send + Sync + 'static {
+    /// The pooled resource.
+    type Resource: Send + 'static;
+
+    /// Create a new resource.
+    async fn create(&self) -> ProviderResult<Self::Resource>;
+
+    /// Check an idle resource before handing it out; `false` discards it.
+    /// Default accepts every resource.
+    async fn validate(&self, _resource: &mut Self::Resource) -> bool {
+        true
+    }
+}
+
+/// Counts of a pool's resources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStatus {
+    /// Most resources the pool will hold
+    pub max_size: usize,
+    /// Resources waiting in the pool
+    pub idle: usize,
+    /// Resources currently checked out
+    pub in_use: usize,
+}
+
+struct Idle<T> {
+    resource: T,
+    since: Instant,
+}
+
+struct Inner<M: PoolManager> {
+    manager: M,
+    idle: Mutex<Vec<Idle<M::Resource>>>,
+    permits: Arc<Semaphore>,
+    max_size: usize,
+    idle_timeout: Option<Duration>,
+    checkout_timeout: Option<Duration>,
+}
+
+impl<M: PoolManager> Inner<M> {
+    fn idle(&self) -> MutexGuard<'_, Vec<Idle<M::Resource>>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A pool of resources created by `M`.
+///
+/// Cloning is cheap; clones share the pool.
+pub struct Pool<M: PoolManager> {
+    inner: Arc<Inner<M>>,
+}
+
+impl<M: PoolManager> Pool<M> {
+    /// Create a pool with the default settings: at most 10 resources, no
+    /// idle timeout, and no checkout timeout.
+    pub fn new(manager: M) -> Self {
+        Self::builder(manager).build()
+    }
+
+    /// Start building a pool.
+    pub fn builder(manager: M) -> PoolBuilder<M> {
+        PoolBuilder {
+            manager,
+            max_size: 10,
+            idle_timeout: None,
+            checkout_timeout: None,
+        }
+    }
+
+    /// Check out a resource, waiting for one to be returned if the pool is
+    /// at its maximum size.
+    ///
+    /// Reuses the most recently returned idle resource that passes
+    /// validation, and creates a new one otherwise. Fails if the checkout
+    /// timeout passes or the manager cannot create a resource.
+    pub async fn get(&self) -> ProviderResult<Pooled<M>> {
+        let acquire = Arc::clone(&self.inner.permits).acquire_owned();
+        let permit = match self.inner.checkout_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire).await.map_err(|_| {
+                ProviderError::ExecutionFailed(format!("pool checkout timed out after {timeout:?}"))
+            })?,
+            None => acquire.await,
+        }
+        .expect("pool semaphore is never closed");
+
+        loop {
+            let idle = {
+                let mut idle = self.inner.idle();
+                if let Some(timeout) = self.inner.idle_timeout {
+                    idle.retain(|entry| entry.since.elapsed() < timeout);
+                }
+                idle.pop()
+            };
+            let Some(mut idle) = idle else { break };
+            if self.inner.manager.validate(&mut idle.resource).await {
+                return Ok(self.guard(idle.resource, permit));
+            }
+        }
+        let resource = self.inner.manager.create().await?;
+        Ok(self.guard(resource, permit))
+    }
+
+    fn guard(&self, resource: M::Resource, permit: OwnedSemaphorePermit) -> Pooled<M> {
+        Pooled {
+            resource: Some(resource),
+            pool: Arc::clone(&self.inner),
+            _permit: permit,
+        }
+    }
+
+    /// Current resource counts.
+    pub fn status(&self) -> PoolStatus {
+        let max_size = self.inner.max_size;
+        PoolStatus {
+            max_size,
+            idle: self.inner.idle().len(),
+            in_use: max_size - self.inner.permits.available_permits(),
+        }
+    }
+
+    /// The manager.
+    pub fn manager(&self) -> &M {
+        &self.inner.manager
+    }
+}
+
+impl<M: PoolManager> Clone for Pool<M> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<M: PoolManager> fmt::Debug for Pool<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pool")
+            .field("status", &self.status())
+            .field("idle_timeout", &self.inner.idle_timeout)
+            .field("checkout_timeout", &self.inner.checkout_timeout)
+            .finish()
+    }
+}
+
+/// Builder for [`Pool`].
+pub struct PoolBuilder<M: PoolManager> {
+    manager: M,
+    max_size: usize,
+    idle_timeout: Option<Duration>,
+    checkout_timeout: Option<Duration>,
+}
+
+impl<M: PoolManager> PoolBuilder<M> {
+    /// Set the most resources the pool holds, checked out or idle.
+    ///
+    /// # Panics
+    ///
+    /// [`build`](Self::build) panics if `size` is zero.
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Discard resources that have been idle for longer than `timeout`.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail checkouts that wait longer than `timeout` for a free slot.
+    pub fn checkout_timeout(mut self, timeout: Duration) -> Self {
+        self.checkout_timeout = Some(timeout);
+        self
+    }
+
+    /// Build the pool. No resources are created until the first checkout.
+    pub fn build(self) -> Pool<M> {
+        assert!(self.max_size > 0, "pool max size must be at least 1");
+        Pool {
+            inner: Arc::new(Inner {
+                manager: self.manager,
+                idle: Mutex::new(Vec::new()),
+                permits: Arc::new(Semaphore::new(self.max_size)),
+                max_size: self.max_size,
+                idle_timeout: self.idle_timeout,
+                checkout_timeout: self.checkout_timeout,
+            }),
+        }
+    }
+}
+
+/// A checked-out resource, returned to its pool on drop.
+pub struct Pooled<M: PoolManager> {
+    resource: Option<M::Resource>,
+    pool: Arc<Inner<M>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<M: PoolManager> Pooled<M> {
+    /// Drop the resource instead of returning it, e.g. after it failed.
+    pub fn discard(mut self) {
+        self.resource = None;
+    }
+}
+
+impl<M: PoolManager> Deref for Pooled<M> {
+    type Target = M::Resource;
+
+    fn deref(&self) -> &M::Resource {
+        self.resource.as_ref().expect("resource present until drop")
+    }
+}
+
+impl<M: PoolManager> DerefMut for Pooled<M> {
+    fn deref_mut(&mut self) -> &mut M::Resource {
+        self.resource.as_mut().expect("resource present until drop")
+    }
+}
+
+impl<M: PoolManager> Drop for Pooled<M> {
+    fn drop(&mut self) {
+        if let Some(resource) = self.resource.take() {
+            self.pool.idle().push(Idle {
+                resource,
+                since: Instant::now(),
+            });
+        }
+    }
+}
+
+impl<M: PoolManager> fmt::Debug for Pooled<M>
+where
+    M::Resource: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Pooled").field(&self.resource).finish()
+    }
+}
+
+/// A provider backed by a pool of instances, one checked out per call.
+///
+/// The registry sees a single provider with the name, extensions, and
+/// priority given here; callers downcast to `PooledProvider<M>` and use
+/// [`call`](Self::call) or [`checkout`](Self::checkout) to reach an
+/// instance.
+pub struct PooledProvider<M: PoolManager> {
+    name: String,
+    extensions: &'static [&'static str],
+    priority: i32,
+    pool: Pool<M>,
+}
+
+impl<M> PooledProvider<M>
+where
+    M: PoolManager,
+    M::Resource: Provider,
+{
+    /// Create a provider named `name` over `pool`.
+    pub fn new(name: impl Into<String>, pool: Pool<M>) -> Self {
+        Self {
+            name: name.into(),
+            extensions: &[],
+            priority: 0,
+            pool,
+        }
+    }
+
+    /// Set the extensions the provider reports to the registry.
+    pub fn with_extensions(mut self, extensions: &'static [&'static str]) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Set the priority the provider reports to the registry.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Check out an instance.
+    pub async fn checkout(&self) -> ProviderResult<Pooled<M>> {
+        self.pool.get().await
+    }
+
+    /// Run `f` with a checked-out instance.
+    ///
+    /// The instance goes back to the pool afterwards, unless `f` failed, in
+    /// which case it is discarded in case the failure left it unusable.
+    pub async fn call<R, F>(&self, f: F) -> ProviderResult<R>
+    where
+        F: for<'a> FnOnce(
+            &'a mut M::Resource,
+        ) -> Pin<Box<dyn Future<Output = ProviderResult<R>> + Send + 'a>>,
+    {
+        let mut instance = self.checkout().await?;
+        let result = f(&mut instance).await;
+        if result.is_err() {
+            instance.discard();
+        }
+        result
+    }
+
+    /// The pool.
+    pub fn pool(&self) -> &Pool<M> {
+        &self.pool
+    }
+}
+
+impl<M: PoolManager> fmt::Debug for PooledProvider<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledProvider")
+            .field("name", &self.name)
+            .field("pool", &self.pool)
+            .finish()
+    }
+}
+
+impl<M> Provider for PooledProvider<M>
+where
+    M: PoolManager,
+    M::Resource: Provider,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug)]
+    struct Worker {
+        id: usize,
+        healthy: bool,
+    }
+
+    impl Provider for Worker {
+        fn name(&self) -> &str {
+            "worker"
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[derive(Default)]
+    struct Workers {
+        created: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl PoolManager for Workers {
+        type Resource = Worker;
+
+        async fn create(&self) -> ProviderResult<Worker> {
+            let id = self.created.fetch_add(1, Ordering::SeqCst);
+            Ok(Worker { id, healthy: true })
+        }
+
+        async fn validate(&self, worker: &mut Worker) -> bool {
+            worker.healthy
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reuse_and_validation() {
+        let pool = Pool::new(Workers::default());
+        let first = pool.get().await.unwrap();
+        let mut second = pool.get().await.unwrap();
+        assert_eq!((first.id, second.id), (0, 1));
+        second.healthy = false;
+        drop(first);
+        drop(second);
+        assert_eq!(pool.status().idle, 2);
+
+        // The unhealthy worker is discarded and the healthy one reused
+        let worker = pool.get().await.unwrap();
+        assert_eq!(worker.id, 0);
+        assert_eq!(pool.manager().created.load(Ordering::SeqCst), 2);
+        worker.discard();
+        assert_eq!(
+            pool.status(),
+            PoolStatus {
+                max_size: 10,
+                idle: 0,
+                in_use: 0
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_size_and_timeouts() {
+        let pool = Pool::builder(Workers::default())
+            .max_size(1)
+            .idle_timeout(Duration::from_secs(60))
+            .checkout_timeout(Duration::from_secs(1))
+            .build();
+        let held = pool.get().await.unwrap();
+        assert!(pool.get().await.is_err());
+        drop(held);
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert_eq!(pool.get().await.unwrap().id, 1);
+    }
+
+    #[tokio::test]
+    async fn test_pooled_provider_discards_failed_instances() {
+        let provider = PooledProvider::new("workers", Pool::new(Workers::default()))
+            .with_extensions(&[".job"]);
+        assert!(provider.supports("nightly.job"));
+
+        let id = provider
+            .call(|worker| Box::pin(async move { Ok(worker.id) }))
+            .await
+            .unwrap();
+        assert_eq!(id, 0);
+        let failed = provider
+            .call(|_| Box::pin(async { Err::<(), _>(ProviderError::ExecutionFailed("x".into())) }))
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(provider.pool().status().idle, 0);
+    }
+}
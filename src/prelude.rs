@@ -10,21 +10,40 @@
 //! ```
 
 // Configuration
-pub use crate::config::{Config, ConfigBuilder, DefaultConfig, FileConfig, MergeableConfig};
+#[cfg(feature = "std")]
+pub use crate::config::FileConfig;
+pub use crate::config::{
+    Config, ConfigBuilder, DefaultConfig, EnvConfig, FromEnv, MergeableConfig, ValidationReport,
+};
+#[cfg(feature = "derive")]
+pub use rustratify_derive::Config;
+
+// Context
+pub use crate::context::Context;
 
 // Core traits
+pub use crate::output::{ProviderOutput, Warning};
 pub use crate::provider::{CloneableProvider, Provider, ProviderExt};
 
 // Registry
+#[cfg(feature = "std")]
 pub use crate::registry::{Registry, RegistryBuilder};
 
 // Streams
-pub use crate::stream::{create_stream, EventSender, EventStream, EventStreamExt, StreamBuilder};
+#[cfg(feature = "std")]
+pub use crate::stream::{
+    create_stream, merge_streams, Envelope, EnvelopeSender, EventKind, EventSender, EventStream,
+    EventStreamExt, Multiplexer, StreamBuilder, TryEventStreamExt,
+};
+
+#[cfg(feature = "tokio")]
+pub use crate::stream::StreamRegistry;
 
 // Errors
 pub use crate::error::{
-    ProviderError, ProviderResult, RegistryError, RegistryResult, RustratifyError,
-    RustratifyResult,
+    BoxError, ErrorCategory, ErrorCode, ErrorFields, ErrorSeverity, ExitStatus, FieldValue,
+    MultiError, ProviderError, ProviderResult, RegistryError, RegistryResult, RustratifyError,
+    RustratifyResult, Traced,
 };
 
 // Re-export async_trait for convenience
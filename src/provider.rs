@@ -3,10 +3,24 @@
 //! The `Provider` trait defines the contract for extension points in a Rustratify module.
 //! Providers are registered in a `Registry` and selected based on their capabilities.
 
-use std::any::Any;
-use std::fmt::Debug;
+use alloc::boxed::Box;
+use core::any::Any;
+use core::fmt::Debug;
+use core::future::{self, Future};
+use core::pin::Pin;
+#[cfg(feature = "std")]
 use std::path::Path;
 
+use crate::error::ProviderResult;
+use crate::matcher::Matcher;
+#[cfg(feature = "std")]
+use crate::permissions::Permissions;
+#[cfg(feature = "std")]
+use crate::stats::ProviderStats;
+
+/// Future returned by the [`Provider`] lifecycle hooks.
+pub type LifecycleFuture<'a> = Pin<Box<dyn Future<Output = ProviderResult<()>> + Send + 'a>>;
+
 /// Base trait for all SEA providers.
 ///
 /// Providers are extension points that implement specific functionality.
@@ -55,11 +69,42 @@ pub trait Provider: Send + Sync + Debug {
         &[]
     }
 
+    /// Returns the MIME types this provider handles, such as
+    /// `"application/json"`, or `"image/*"` for every subtype.
+    ///
+    /// Used by `Registry::find_by_mime` for routing by content type.
+    fn mime_types(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Returns the URI schemes this provider handles, such as `"s3"` or
+    /// `"https"`, without the trailing `:`.
+    ///
+    /// Used by `Registry::find_by_scheme` for routing by URI.
+    fn schemes(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Returns the matcher for the keys this provider handles beyond its
+    /// extensions, such as a `GlobMatcher` for `**/*.test.ts`. See
+    /// [`matcher`](crate::matcher).
+    ///
+    /// Consulted by the default [`supports`](Self::supports).
+    fn matcher(&self) -> Option<&dyn Matcher> {
+        None
+    }
+
     /// Check if this provider supports the given key.
     ///
     /// The key can be a file path, language name, framework name, etc.
     /// depending on the domain.
+    ///
+    /// By default, a key is supported if the [`matcher`](Self::matcher)
+    /// matches it or it ends with one of the [`extensions`](Self::extensions).
     fn supports(&self, key: &str) -> bool {
+        if self.matcher().is_some_and(|matcher| matcher.is_match(key)) {
+            return true;
+        }
         // Default: check if key ends with any supported extension
         let extensions = self.extensions();
         if extensions.is_empty() {
@@ -71,6 +116,9 @@ pub trait Provider: Send + Sync + Debug {
     /// Check if this provider supports the given path.
     ///
     /// Override this for path-based provider selection (e.g., config file detection).
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
     fn supports_path(&self, path: &Path) -> bool {
         path.to_str().map(|s| self.supports(s)).unwrap_or(false)
     }
@@ -82,6 +130,69 @@ pub trait Provider: Send + Sync + Debug {
         0
     }
 
+    /// Returns how many runs of this provider may be in flight at once, or
+    /// `None` for no limit.
+    ///
+    /// Enforced by `limits::ConcurrencyLimiter`, which can also override
+    /// it. Defaults to no limit.
+    fn max_concurrency(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns what this provider needs to access when it runs.
+    ///
+    /// Hosts check the request against their policy before dispatching; see
+    /// [`permissions`](crate::permissions). Defaults to nothing.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    fn permissions(&self) -> Permissions {
+        Permissions::none()
+    }
+
+    /// Start the provider: open connections, spawn background work.
+    ///
+    /// Called by `Registry::start_all` before the provider serves requests.
+    /// Defaults to doing nothing.
+    fn start(&self) -> LifecycleFuture<'_> {
+        Box::pin(future::ready(Ok(())))
+    }
+
+    /// Prepare the provider for its first request: load models, prime
+    /// caches.
+    ///
+    /// Called by `Registry::start_all` after [`start`](Self::start); the
+    /// provider counts as ready once it succeeds. Defaults to doing nothing.
+    fn warm_up(&self) -> LifecycleFuture<'_> {
+        Box::pin(future::ready(Ok(())))
+    }
+
+    /// Stop the provider, releasing what [`start`](Self::start) acquired.
+    ///
+    /// Called by `Registry::stop_all`. Defaults to doing nothing.
+    fn stop(&self) -> LifecycleFuture<'_> {
+        Box::pin(future::ready(Ok(())))
+    }
+
+    /// Check whether the provider can serve requests.
+    ///
+    /// Should be cheap, since hosts may call it on every health probe.
+    /// Defaults to healthy.
+    fn health(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+
+    /// Returns the call statistics of this provider, if it records them.
+    ///
+    /// [`Instrumented`](crate::stats::Instrumented) providers report theirs;
+    /// see [`stats`](crate::stats). Defaults to `None`.
+    ///
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    fn stats(&self) -> Option<&dyn ProviderStats> {
+        None
+    }
+
     /// Downcast to concrete type for advanced usage.
     fn as_any(&self) -> &dyn Any;
 }
@@ -136,6 +247,34 @@ where
     }
 }
 
+/// Box a provider as a `dyn Provider`, as for
+/// [`RegistryBuilder::with_default`](crate::RegistryBuilder::with_default).
+///
+/// To build registries of another SPI trait the same way, add the matching
+/// impl in the crate declaring the trait:
+///
+/// ```rust
+/// # use rustratify::Provider;
+/// pub trait Formatter: Provider {}
+///
+/// impl<T: Formatter + 'static> From<Box<T>> for Box<dyn Formatter> {
+///     fn from(provider: Box<T>) -> Self {
+///         provider
+///     }
+/// }
+/// ```
+impl<T: Provider + 'static> From<Box<T>> for Box<dyn Provider> {
+    fn from(provider: Box<T>) -> Self {
+        provider
+    }
+}
+
+impl<T: Provider + Clone + 'static> From<Box<T>> for Box<dyn CloneableProvider> {
+    fn from(provider: Box<T>) -> Self {
+        provider
+    }
+}
+
 /// Extension trait for provider type checking.
 pub trait ProviderExt: Provider {
     /// Check if this provider is of type T.
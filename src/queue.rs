@@ -0,0 +1,391 @@
+//! Job queues with leases, acknowledgement, and retry.
+//!
+//! A [`JobQueue`] hands out jobs under a time-limited lease. A worker that
+//! finishes a job [`ack`](JobQueue::ack)s it; one that fails hands it back
+//! with [`retry`](JobQueue::retry), usually with a delay from [`Backoff`]. A
+//! lease that expires without either makes the job available again, so a
+//! crashed worker's jobs are picked up by another: delivery is at least once.
+//!
+//! [`MemoryQueue`] is the built-in queue. On its own it lives and dies with
+//! the process; opened on a [`JobStore`] it writes every change through to
+//! the store and reloads pending jobs on restart.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use rustratify::queue::{Backoff, JobQueue, MemoryQueue};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rustratify::ProviderError> {
+//! let queue = MemoryQueue::new();
+//! queue.enqueue("reindex", b"repo-1".to_vec()).await?;
+//!
+//! let job = queue.lease(Duration::from_secs(30)).await?.unwrap();
+//! assert_eq!(job.kind, "reindex");
+//! assert_eq!(job.attempts, 1);
+//!
+//! // The work failed; try again later
+//! let delay = Backoff::default().delay(job.attempts);
+//! queue.retry(job.id, delay).await?;
+//! assert!(queue.lease(Duration::from_secs(30)).await?.is_none());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+
+use crate::error::{ProviderError, ProviderResult};
+
+/// Identifies a job within its queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct JobId(pub u64);
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "job-{}", self.0)
+    }
+}
+
+/// A job and its delivery state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueuedJob {
+    /// Queue-assigned ID
+    pub id: JobId,
+    /// What to do, e.g. a provider or operation name
+    pub kind: String,
+    /// Opaque input for the worker
+    pub payload: Vec<u8>,
+    /// Number of times the job has been leased, including the current lease
+    pub attempts: u32,
+    /// The job is not handed out before this time
+    pub available_at: SystemTime,
+}
+
+/// Exponential backoff between retries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    /// Delay after the first attempt
+    pub initial: Duration,
+    /// Upper bound on the delay
+    pub max: Duration,
+    /// Factor applied per further attempt
+    pub multiplier: f64,
+}
+
+impl Backoff {
+    /// Delay before retrying a job that has made `attempts` attempts.
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = self.initial.as_secs_f64() * self.multiplier.powi(exponent);
+        if secs.is_finite() && secs < self.max.as_secs_f64() {
+            Duration::from_secs_f64(secs.max(0.0))
+        } else {
+            self.max
+        }
+    }
+}
+
+impl Default for Backoff {
+    /// One second, doubling up to five minutes.
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(300),
+            multiplier: 2.0,
+        }
+    }
+}
+
+/// A queue of jobs delivered under leases.
+#[async_trait]
+pub trait JobQueue: Send + Sync {
+    /// Queue name, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// Add a job, available immediately.
+    async fn enqueue(&self, kind: &str, payload: Vec<u8>) -> ProviderResult<JobId>;
+
+    /// Take the oldest available job for `ttl`, or `None` if there is none.
+    ///
+    /// The job's attempt count is incremented.
+    async fn lease(&self, ttl: Duration) -> ProviderResult<Option<QueuedJob>>;
+
+    /// Remove a finished job.
+    async fn ack(&self, id: JobId) -> ProviderResult<()>;
+
+    /// Release a leased job, available again after `delay`.
+    async fn retry(&self, id: JobId, delay: Duration) -> ProviderResult<()>;
+
+    /// Number of jobs not yet acknowledged, leased or not.
+    async fn pending(&self) -> ProviderResult<usize>;
+}
+
+/// Durable storage behind a [`MemoryQueue`], e.g. a database table.
+///
+/// The queue calls the store after every change, so the store always holds
+/// the pending jobs. Leases are not stored: after a restart every pending
+/// job is available once its `available_at` has passed.
+#[async_trait]
+pub trait JobStore: Send + Sync {
+    /// Store name, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// All stored jobs.
+    async fn load(&self) -> ProviderResult<Vec<QueuedJob>>;
+
+    /// Insert or replace `job`.
+    async fn save(&self, job: &QueuedJob) -> ProviderResult<()>;
+
+    /// Delete the job with `id`.
+    async fn remove(&self, id: JobId) -> ProviderResult<()>;
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_id: u64,
+    jobs: BTreeMap<JobId, Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    job: QueuedJob,
+    leased_until: Option<SystemTime>,
+}
+
+/// The built-in [`JobQueue`], optionally backed by a [`JobStore`].
+///
+/// Cloning is cheap; clones share the queue.
+#[derive(Clone, Default)]
+pub struct MemoryQueue {
+    state: Arc<Mutex<State>>,
+    store: Option<Arc<dyn JobStore>>,
+}
+
+impl MemoryQueue {
+    /// Create an empty queue that is not persisted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a queue backed by `store`, starting with the jobs it holds.
+    pub async fn open(store: impl JobStore + 'static) -> ProviderResult<Self> {
+        let jobs = store.load().await?;
+        let next_id = jobs.iter().map(|job| job.id.0 + 1).max().unwrap_or(0);
+        let jobs = jobs
+            .into_iter()
+            .map(|job| {
+                let entry = Entry {
+                    job,
+                    leased_until: None,
+                };
+                (entry.job.id, entry)
+            })
+            .collect();
+        Ok(Self {
+            state: Arc::new(Mutex::new(State { next_id, jobs })),
+            store: Some(Arc::new(store)),
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn save(&self, job: &QueuedJob) -> ProviderResult<()> {
+        match &self.store {
+            Some(store) => store.save(job).await,
+            None => Ok(()),
+        }
+    }
+
+    fn leased(&self, id: JobId) -> ProviderResult<()> {
+        let now = SystemTime::now();
+        match self.state().jobs.get(&id) {
+            Some(entry) if entry.leased_until.is_some_and(|until| until > now) => Ok(()),
+            Some(_) => Err(ProviderError::ExecutionFailed(format!(
+                "{id} is not leased"
+            ))),
+            None => Err(ProviderError::NotFound(id.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl JobQueue for MemoryQueue {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    async fn enqueue(&self, kind: &str, payload: Vec<u8>) -> ProviderResult<JobId> {
+        let job = {
+            let mut state = self.state();
+            let id = JobId(state.next_id);
+            state.next_id += 1;
+            QueuedJob {
+                id,
+                kind: kind.to_string(),
+                payload,
+                attempts: 0,
+                available_at: SystemTime::now(),
+            }
+        };
+        self.save(&job).await?;
+        let id = job.id;
+        self.state().jobs.insert(
+            id,
+            Entry {
+                job,
+                leased_until: None,
+            },
+        );
+        Ok(id)
+    }
+
+    async fn lease(&self, ttl: Duration) -> ProviderResult<Option<QueuedJob>> {
+        let now = SystemTime::now();
+        let job = {
+            let mut state = self.state();
+            let entry = state.jobs.values_mut().find(|entry| {
+                entry.job.available_at <= now && entry.leased_until.is_none_or(|until| until <= now)
+            });
+            let Some(entry) = entry else {
+                return Ok(None);
+            };
+            entry.job.attempts += 1;
+            entry.leased_until = Some(now + ttl);
+            entry.job.clone()
+        };
+        self.save(&job).await?;
+        Ok(Some(job))
+    }
+
+    async fn ack(&self, id: JobId) -> ProviderResult<()> {
+        self.leased(id)?;
+        if let Some(store) = &self.store {
+            store.remove(id).await?;
+        }
+        self.state().jobs.remove(&id);
+        Ok(())
+    }
+
+    async fn retry(&self, id: JobId, delay: Duration) -> ProviderResult<()> {
+        self.leased(id)?;
+        let job = {
+            let mut state = self.state();
+            let Some(entry) = state.jobs.get_mut(&id) else {
+                return Err(ProviderError::NotFound(id.to_string()));
+            };
+            entry.job.available_at = SystemTime::now() + delay;
+            entry.leased_until = None;
+            entry.job.clone()
+        };
+        self.save(&job).await
+    }
+
+    async fn pending(&self) -> ProviderResult<usize> {
+        Ok(self.state().jobs.len())
+    }
+}
+
+impl fmt::Debug for MemoryQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryQueue")
+            .field("jobs", &self.state().jobs.len())
+            .field("store", &self.store.as_ref().map(|s| s.name()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedStore(Arc<Mutex<BTreeMap<JobId, QueuedJob>>>);
+
+    #[async_trait]
+    impl JobStore for SharedStore {
+        fn name(&self) -> &str {
+            "shared"
+        }
+
+        async fn load(&self) -> ProviderResult<Vec<QueuedJob>> {
+            Ok(self.0.lock().unwrap().values().cloned().collect())
+        }
+
+        async fn save(&self, job: &QueuedJob) -> ProviderResult<()> {
+            self.0.lock().unwrap().insert(job.id, job.clone());
+            Ok(())
+        }
+
+        async fn remove(&self, id: JobId) -> ProviderResult<()> {
+            self.0.lock().unwrap().remove(&id);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff::default();
+        assert_eq!(backoff.delay(1), Duration::from_secs(1));
+        assert_eq!(backoff.delay(4), Duration::from_secs(8));
+        assert_eq!(backoff.delay(100), Duration::from_secs(300));
+    }
+
+    #[tokio::test]
+    async fn test_lease_ack_and_expiry() {
+        let queue = MemoryQueue::new();
+        let first = queue.enqueue("a", vec![1]).await.unwrap();
+        let second = queue.enqueue("b", vec![2]).await.unwrap();
+
+        // An expired lease makes the job available again
+        let job = queue.lease(Duration::ZERO).await.unwrap().unwrap();
+        assert_eq!(job.id, first);
+        assert!(queue.ack(first).await.is_err());
+        let job = queue.lease(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!((job.id, job.attempts), (first, 2));
+
+        queue.ack(first).await.unwrap();
+        assert_eq!(queue.pending().await.unwrap(), 1);
+        let job = queue.lease(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!(job.id, second);
+        queue.retry(second, Duration::ZERO).await.unwrap();
+        assert_eq!(
+            queue.lease(Duration::ZERO).await.unwrap().unwrap().attempts,
+            2
+        );
+        assert!(matches!(
+            queue.ack(JobId(9)).await,
+            Err(ProviderError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_store_survives_restart() {
+        let store = SharedStore::default();
+        let queue = MemoryQueue::open(store.clone()).await.unwrap();
+        queue.enqueue("a", vec![]).await.unwrap();
+        let b = queue.enqueue("b", vec![]).await.unwrap();
+        let leased = queue.lease(Duration::from_secs(60)).await.unwrap().unwrap();
+        queue.ack(leased.id).await.unwrap();
+        queue.lease(Duration::from_secs(60)).await.unwrap().unwrap();
+        drop(queue);
+
+        let queue = MemoryQueue::open(store).await.unwrap();
+        let job = queue.lease(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!((job.id, job.attempts), (b, 2));
+        assert_eq!(queue.enqueue("c", vec![]).await.unwrap(), JobId(2));
+    }
+}
@@ -3,11 +3,37 @@
 //! The `Registry` is a type-safe container for providers that supports
 //! registration, lookup by name, and automatic selection based on capabilities.
 
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::convert::Infallible;
+use std::hash::{BuildHasher, Hasher};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-use crate::error::{RegistryError, RegistryResult};
+use crate::audit::AuditLog;
+use crate::context::Context;
+use crate::error::{MultiError, ProviderError, ProviderResult, RegistryError, RegistryResult};
+use crate::flags::FeatureFlags;
+use crate::metrics::{Counter, Gauge, Metrics};
+use crate::permissions::PermissionChecker;
 use crate::provider::{CloneableProvider, Provider};
+use crate::stats::StatsReport;
+use cache::{Lookup, LookupCache};
+use quarantine::Quarantine;
+
+mod cache;
+mod module;
+mod quarantine;
+mod selection;
+mod typed;
+mod view;
+
+pub use module::ModuleRegistry;
+pub use quarantine::QuarantinePolicy;
+pub use selection::{SelectionPolicy, TieBreak};
+pub use typed::TypedRegistry;
+pub use view::RegistryView;
 
 /// A registry for managing providers.
 ///
@@ -37,17 +63,174 @@ use crate::provider::{CloneableProvider, Provider};
 /// assert!(registry.get("my-provider").is_some());
 /// ```
 #[derive(Debug)]
-pub struct Registry<P: ?Sized> {
-    providers: HashMap<String, Box<P>>,
-    ordered: Vec<String>,
+pub struct Registry<P: ?Sized, S = RandomState> {
+    /// Providers in registration order, scanned by the `find` methods
+    /// without hashing
+    entries: Vec<Entry<P>>,
+    /// Position of each provider in `entries`; shares the name allocations
+    index: HashMap<Arc<str>, usize, S>,
+    /// Sorted `(lowercase extension, position)` pairs
+    extensions: Vec<(Box<str>, usize)>,
+    metrics: Option<RegistryMetrics>,
+    audit: Option<AuditLog>,
+    flags: Option<FeatureFlags>,
+    gates: HashMap<String, String>,
+    permissions: Option<Arc<dyn PermissionChecker>>,
+    /// Whether the `find` methods skip providers that are not ready
+    require_ready: bool,
+    /// Whether `find_best` skips providers that report themselves unhealthy
+    route_by_health: bool,
+    /// Providers the `find` methods skip for failing too often
+    quarantine: Option<Quarantine>,
+    /// Next round-robin turn for `find_balanced`, keyed by the name of the
+    /// first provider in each tied group so it stays bounded by the providers
+    turns: Mutex<HashMap<Box<str>, usize>>,
+    /// Tie-breaking for `find_best`; `None` picks the last registered
+    selection: Option<Arc<dyn SelectionPolicy>>,
+    /// Memoized `find` and `find_best` results
+    cache: Option<LookupCache>,
+}
+
+/// How [`Registry::find_balanced`] picks among equally good providers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceStrategy {
+    /// Take turns in registration order, separately for each group of
+    /// tied providers
+    RoundRobin,
+    /// Pick the provider with the fewest calls in its
+    /// [`stats`](Provider::stats); providers without stats count as idle
+    LeastLoaded,
+    /// Pick one at random
+    Random,
+}
+
+#[derive(Debug)]
+struct Entry<P: ?Sized> {
+    name: Arc<str>,
+    provider: Box<P>,
+    /// Set once the provider has warmed up
+    ready: AtomicBool,
+}
+
+impl<P: ?Sized> Entry<P> {
+    fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Release);
+    }
+}
+
+/// The highest priority among `providers` and how many share it.
+fn top_priority<'a, P: Provider + ?Sized + 'a>(
+    providers: impl Iterator<Item = &'a P>,
+) -> (i32, usize) {
+    providers.fold((i32::MIN, 0), |(top, count), p| match p.priority() {
+        priority if priority > top => (priority, 1),
+        priority if priority == top => (top, count + 1),
+        _ => (top, count),
+    })
+}
+
+#[derive(Debug)]
+struct RegistryMetrics {
+    hits: Counter,
+    misses: Counter,
+    providers: Gauge,
 }
 
 impl<P: Provider + ?Sized> Registry<P> {
     /// Create a new empty registry.
     pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<P: Provider + ?Sized, S: BuildHasher> Registry<P, S> {
+    /// Create a new empty registry that hashes names with `hasher`.
+    ///
+    /// The default SipHash resists collision attacks on names from
+    /// untrusted input; a faster hasher such as FxHash cuts lookup cost in
+    /// registries with many providers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustc_hash::FxBuildHasher;
+    /// use rustratify::{Provider, Registry};
+    ///
+    /// let registry: Registry<dyn Provider, FxBuildHasher> = Registry::with_hasher(FxBuildHasher);
+    /// assert!(registry.is_empty());
+    /// ```
+    pub fn with_hasher(hasher: S) -> Self {
         Self {
-            providers: HashMap::new(),
-            ordered: Vec::new(),
+            entries: Vec::new(),
+            index: HashMap::with_hasher(hasher),
+            extensions: Vec::new(),
+            metrics: None,
+            audit: None,
+            flags: None,
+            gates: HashMap::new(),
+            permissions: None,
+            require_ready: false,
+            route_by_health: false,
+            quarantine: None,
+            turns: Mutex::default(),
+            selection: None,
+            cache: None,
+        }
+    }
+
+    /// Append an audit record to `log` whenever a provider is registered or
+    /// removed.
+    ///
+    /// Failing to write a record does not fail the registry operation; it is
+    /// logged as a warning.
+    pub fn with_audit(mut self, log: &AuditLog) -> Self {
+        self.audit = Some(log.clone());
+        self
+    }
+
+    fn audit(&self, record: impl FnOnce(&AuditLog) -> Result<(), MultiError>) {
+        if let Some(log) = &self.audit {
+            if let Err(err) = record(log) {
+                tracing::warn!(error = %err, "failed to write registry audit record");
+            }
+        }
+    }
+
+    /// Record lookups and the provider count in `metrics`.
+    ///
+    /// Reports `rustratify_registry_lookups_total` with a `result` label of
+    /// `hit` or `miss` for [`get`](Self::get) and the `find` methods except
+    /// [`find_all`](Self::find_all) and [`find_all_iter`](Self::find_all_iter),
+    /// and the `rustratify_registry_providers` gauge. Both carry a
+    /// `registry="name"` label.
+    pub fn with_metrics(mut self, metrics: &Metrics, name: &str) -> Self {
+        let lookups = "rustratify_registry_lookups_total";
+        self.metrics = Some(RegistryMetrics {
+            hits: metrics.counter(lookups, &[("registry", name), ("result", "hit")]),
+            misses: metrics.counter(lookups, &[("registry", name), ("result", "miss")]),
+            providers: metrics.gauge("rustratify_registry_providers", &[("registry", name)]),
+        });
+        self.record_len();
+        self
+    }
+
+    fn record_lookup<'a>(&self, found: Option<&'a P>) -> Option<&'a P> {
+        if let Some(metrics) = &self.metrics {
+            match found {
+                Some(_) => metrics.hits.inc(),
+                None => metrics.misses.inc(),
+            }
+        }
+        found
+    }
+
+    fn record_len(&self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.providers.set(self.entries.len() as f64);
         }
     }
 
@@ -56,32 +239,131 @@ impl<P: Provider + ?Sized> Registry<P> {
     /// The provider is registered under its name. If a provider with the same
     /// name already exists, it will be replaced.
     pub fn register(&mut self, provider: Box<P>) {
-        let name = provider.name().to_string();
-        if !self.providers.contains_key(&name) {
-            self.ordered.push(name.clone());
+        self.audit(|log| log.provider_registered(provider.name()));
+        match self.index.get(provider.name()) {
+            Some(&position) => {
+                self.entries[position].provider = provider;
+                self.entries[position].set_ready(false);
+                self.extensions.retain(|&(_, at)| at != position);
+                self.add_extensions(position);
+                self.invalidate();
+            }
+            None => self.insert(provider),
+        }
+    }
+
+    fn insert(&mut self, provider: Box<P>) {
+        let name: Arc<str> = Arc::from(provider.name());
+        self.index.insert(Arc::clone(&name), self.entries.len());
+        self.entries.push(Entry {
+            name,
+            provider,
+            ready: AtomicBool::new(false),
+        });
+        self.add_extensions(self.entries.len() - 1);
+        self.invalidate();
+        self.record_len();
+    }
+
+    fn add_extensions(&mut self, position: usize) {
+        for ext in self.entries[position].provider.extensions() {
+            let key = (ext.to_ascii_lowercase().into_boxed_str(), position);
+            let at = self.extensions.partition_point(|entry| *entry < key);
+            self.extensions.insert(at, key);
+        }
+    }
+
+    /// Drop the extensions of the provider at `removed` and shift the
+    /// positions after it, keeping the table sorted.
+    fn remove_extensions(&mut self, removed: usize) {
+        self.extensions.retain_mut(|(_, position)| {
+            if *position == removed {
+                return false;
+            }
+            if *position > removed {
+                *position -= 1;
+            }
+            true
+        });
+    }
+
+    fn invalidate(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
         }
-        self.providers.insert(name, provider);
+    }
+
+    /// Run `lookup` through the cache, if there is one.
+    fn cached<'a, E>(
+        &'a self,
+        lookup: Lookup,
+        key: &str,
+        compute: impl FnOnce() -> Result<Option<&'a P>, E>,
+    ) -> Result<Option<&'a P>, E> {
+        // Quarantine decisions change without the registry knowing
+        let Some(cache) = self.cache.as_ref().filter(|_| self.quarantine.is_none()) else {
+            return compute();
+        };
+        let name = cache.resolve(lookup, key, || Ok(compute()?.map(|p| Arc::from(p.name()))))?;
+        Ok(name
+            .and_then(|name| self.position(&name))
+            .map(|position| self.entries[position].provider.as_ref()))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    fn scan(&self) -> impl Iterator<Item = &P> {
+        self.entries.iter().map(|entry| entry.provider.as_ref())
+    }
+
+    /// The entries the `find` methods consider, in registration order.
+    fn serving(&self) -> impl Iterator<Item = &Entry<P>> {
+        self.entries.iter().filter(|entry| self.serves(entry))
+    }
+
+    /// Whether the `find` methods may return `entry`.
+    fn serves(&self, entry: &Entry<P>) -> bool {
+        (!self.require_ready || entry.is_ready())
+            && self
+                .quarantine
+                .as_ref()
+                .is_none_or(|q| q.admits(&entry.name, entry.provider.as_ref()))
+    }
+
+    fn scan_serving(&self) -> impl Iterator<Item = &P> {
+        self.serving().map(|entry| entry.provider.as_ref())
     }
 
     /// Register a provider, returning an error if already registered.
     pub fn register_unique(&mut self, provider: Box<P>) -> RegistryResult<()> {
-        let name = provider.name().to_string();
-        if self.providers.contains_key(&name) {
-            return Err(RegistryError::AlreadyRegistered(name));
+        if self.index.contains_key(provider.name()) {
+            return Err(RegistryError::AlreadyRegistered(
+                provider.name().to_string(),
+            ));
         }
-        self.ordered.push(name.clone());
-        self.providers.insert(name, provider);
+        self.audit(|log| log.provider_registered(provider.name()));
+        self.insert(provider);
         Ok(())
     }
 
     /// Get a provider by name.
     pub fn get(&self, name: &str) -> Option<&P> {
-        self.providers.get(name).map(|p| p.as_ref())
+        let found = self
+            .position(name)
+            .map(|i| self.entries[i].provider.as_ref());
+        self.record_lookup(found)
     }
 
     /// Get a mutable provider by name.
+    ///
+    /// [`find_by_extension`](Self::find_by_extension) keeps using the
+    /// extensions the provider had when it was registered.
     pub fn get_mut(&mut self, name: &str) -> Option<&mut P> {
-        self.providers.get_mut(name).map(|p| p.as_mut())
+        let position = self.position(name)?;
+        self.invalidate();
+        Some(self.entries[position].provider.as_mut())
     }
 
     /// Find a provider that supports the given key.
@@ -89,103 +371,882 @@ impl<P: Provider + ?Sized> Registry<P> {
     /// Returns the first provider that returns `true` for `supports(key)`.
     /// Providers are checked in registration order.
     pub fn find(&self, key: &str) -> Option<&P> {
-        self.ordered
-            .iter()
-            .filter_map(|name| self.providers.get(name))
-            .find(|p| p.supports(key))
-            .map(|p| p.as_ref())
+        // The extension table cannot answer this: the registry cannot tell
+        // whether a provider overrides `supports` or has a matcher, and the
+        // default `supports` matches any suffix of the key case-sensitively,
+        // where the table holds whole extensions folded to lowercase.
+        let found = self.cached(Lookup::Find, key, || {
+            Ok::<_, Infallible>(self.scan_serving().find(|p| p.supports(key)))
+        });
+        self.record_lookup(found.unwrap_or_else(|never| match never {}))
     }
 
     /// Find a provider that supports the given path.
     ///
     /// Returns the first provider that returns `true` for `supports_path(path)`.
     pub fn find_by_path(&self, path: &Path) -> Option<&P> {
-        self.ordered
-            .iter()
-            .filter_map(|name| self.providers.get(name))
-            .find(|p| p.supports_path(path))
-            .map(|p| p.as_ref())
+        let found = self.scan_serving().find(|p| p.supports_path(path));
+        self.record_lookup(found)
     }
 
     /// Find the best provider for the given key, considering priority.
     ///
     /// Returns the provider with the highest priority among those that support the key.
+    ///
+    /// Ties between providers of equal priority are broken by the
+    /// [selection policy](Self::with_selection_policy); a policy that
+    /// refuses to pick returns `None`. With
+    /// [`route_by_health`](Self::route_by_health), unhealthy providers are
+    /// passed over for the next best.
     pub fn find_best(&self, key: &str) -> Option<&P> {
-        self.ordered
-            .iter()
-            .filter_map(|name| self.providers.get(name))
-            .filter(|p| p.supports(key))
-            .max_by_key(|p| p.priority())
-            .map(|p| p.as_ref())
+        self.try_find_best(key).ok()
+    }
+
+    /// Find the best provider for the given key, reporting why none was
+    /// returned.
+    ///
+    /// Fails with [`RegistryError::NoMatchingProvider`] if no provider
+    /// supports the key, or the error of a selection policy that refuses to
+    /// break a tie, such as [`RegistryError::AmbiguousMatch`].
+    pub fn try_find_best(&self, key: &str) -> RegistryResult<&P> {
+        let found = if self.route_by_health {
+            // Health changes without the registry knowing, so skip the cache
+            self.select_best(
+                key,
+                self.scan_serving()
+                    .filter(|p| p.supports(key) && self.is_healthy(*p)),
+            )
+        } else {
+            let found = self.cached(Lookup::FindBest, key, || {
+                match self.select_best(key, self.scan_serving().filter(|p| p.supports(key))) {
+                    Ok(best) => Ok(Some(best)),
+                    Err(RegistryError::NoMatchingProvider) => Ok(None),
+                    Err(err) => Err(err),
+                }
+            });
+            found.and_then(|best| best.ok_or(RegistryError::NoMatchingProvider))
+        };
+        self.record_lookup(found.as_ref().ok().copied());
+        found
+    }
+
+    fn select_best<'a>(
+        &self,
+        key: &str,
+        candidates: impl Iterator<Item = &'a P>,
+    ) -> RegistryResult<&'a P>
+    where
+        P: 'a,
+    {
+        // Filter in one pass: health checks and quarantine probes may change
+        // their answer between passes. Providers are only collected once
+        // two of them tie for the top priority.
+        let mut best: Option<&P> = None;
+        let mut tied: Vec<&P> = Vec::new();
+        for p in candidates {
+            match best.map(|b| (b, b.priority())) {
+                Some((_, top)) if p.priority() < top => {}
+                Some((b, top)) if p.priority() == top => {
+                    if tied.is_empty() {
+                        tied.push(b);
+                    }
+                    tied.push(p);
+                }
+                _ => {
+                    best = Some(p);
+                    tied.clear();
+                }
+            }
+        }
+        if tied.is_empty() {
+            return best.ok_or(RegistryError::NoMatchingProvider);
+        }
+        let names: Vec<&str> = tied.iter().map(|p| p.name()).collect();
+        let pick = match &self.selection {
+            Some(policy) => policy.select(key, &names)?,
+            None => TieBreak::LastRegistered.select(key, &names)?,
+        };
+        tied.get(pick)
+            .copied()
+            .ok_or_else(|| RegistryError::AmbiguousMatch(names.join(", ")))
+    }
+
+    /// Find a provider for the given key, spreading lookups over every
+    /// provider that shares the highest priority.
+    ///
+    /// Use this where several equivalent providers should share traffic;
+    /// [`find_best`](Self::find_best) always returns the same one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{BalanceStrategy, Provider, Registry};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug)]
+    /// struct Replica(&'static str);
+    ///
+    /// impl Provider for Replica {
+    ///     fn name(&self) -> &str { self.0 }
+    ///     fn extensions(&self) -> &[&str] { &["rs"] }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// let mut registry: Registry<dyn Provider> = Registry::new();
+    /// registry.register(Box::new(Replica("a")));
+    /// registry.register(Box::new(Replica("b")));
+    ///
+    /// let picks: Vec<_> = (0..3)
+    ///     .map(|_| registry.find_balanced("rs", BalanceStrategy::RoundRobin).unwrap().name())
+    ///     .collect();
+    /// assert_eq!(picks, ["a", "b", "a"]);
+    /// ```
+    pub fn find_balanced(&self, key: &str, strategy: BalanceStrategy) -> Option<&P> {
+        let candidates: Vec<&P> = self.scan_serving().filter(|p| p.supports(key)).collect();
+        let (top, count) = top_priority(candidates.iter().copied());
+        let mut candidates = candidates
+            .into_iter()
+            .filter(|p| p.priority() == top)
+            .peekable();
+        let found = match (strategy, count) {
+            (_, 0) => None,
+            (BalanceStrategy::RoundRobin, _) => {
+                let first = candidates.peek().map_or("", |p| p.name());
+                let turn = self.next_turn(first);
+                candidates.nth(turn % count)
+            }
+            (BalanceStrategy::LeastLoaded, _) => {
+                candidates.min_by_key(|p| p.stats().map_or(0, |stats| stats.calls()))
+            }
+            (BalanceStrategy::Random, _) => {
+                let random = RandomState::new().build_hasher().finish() as usize;
+                candidates.nth(random % count)
+            }
+        };
+        self.record_lookup(found)
+    }
+
+    fn next_turn(&self, first: &str) -> usize {
+        let mut turns = self.turns.lock().unwrap_or_else(|e| e.into_inner());
+        match turns.get_mut(first) {
+            Some(turn) => {
+                *turn = turn.wrapping_add(1);
+                *turn
+            }
+            None => {
+                turns.insert(first.into(), 0);
+                0
+            }
+        }
     }
 
     /// Find all providers that support the given key.
     pub fn find_all(&self, key: &str) -> Vec<&P> {
-        self.ordered
+        self.scan_serving().filter(|p| p.supports(key)).collect()
+    }
+
+    /// Iterate over the providers that support the given key, in
+    /// registration order, without collecting them.
+    pub fn find_all_iter<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a P> + 'a {
+        self.scan_serving().filter(move |p| p.supports(key))
+    }
+
+    /// Find the first registered provider that declares `extension` in
+    /// [`Provider::extensions`], ignoring ASCII case.
+    ///
+    /// Unlike [`find`](Self::find), this compares against the declared
+    /// extensions exactly as written (so `".RS"` finds a provider declaring
+    /// `".rs"` but not one declaring `"rs"`) and never calls
+    /// [`Provider::supports`]. It uses a table built at registration, so it
+    /// neither scans every provider nor allocates.
+    pub fn find_by_extension(&self, extension: &str) -> Option<&P> {
+        let query = extension.bytes().map(|b| b.to_ascii_lowercase());
+        let start = self
+            .extensions
+            .partition_point(|(ext, _)| ext.bytes().lt(query.clone()));
+        let found = self.extensions[start..]
             .iter()
-            .filter_map(|name| self.providers.get(name))
-            .filter(|p| p.supports(key))
-            .map(|p| p.as_ref())
-            .collect()
+            .take_while(|(ext, _)| ext.bytes().eq(query.clone()))
+            .map(|&(_, position)| &self.entries[position])
+            .find(|entry| self.serves(entry))
+            .map(|entry| entry.provider.as_ref());
+        self.record_lookup(found)
+    }
+
+    /// Find the first registered provider that declares the MIME type
+    /// `mime` in [`Provider::mime_types`], ignoring ASCII case and
+    /// parameters.
+    ///
+    /// `"text/html; charset=utf-8"` finds a provider declaring
+    /// `"text/html"`. A declared `"image/*"` matches every image type and
+    /// `"*/*"` matches anything; an exact declaration wins over `type/*`,
+    /// which wins over `*/*`, whatever the registration order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{Provider, Registry};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug)]
+    /// struct Handler(&'static str, &'static [&'static str]);
+    ///
+    /// impl Provider for Handler {
+    ///     fn name(&self) -> &str { self.0 }
+    ///     fn mime_types(&self) -> &[&str] { self.1 }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// let mut registry: Registry<dyn Provider> = Registry::new();
+    /// registry.register(Box::new(Handler("images", &["image/*"])));
+    /// registry.register(Box::new(Handler("png", &["image/png"])));
+    ///
+    /// assert_eq!(registry.find_by_mime("image/PNG").unwrap().name(), "png");
+    /// assert_eq!(registry.find_by_mime("image/gif").unwrap().name(), "images");
+    /// assert!(registry.find_by_mime("text/plain").is_none());
+    /// ```
+    pub fn find_by_mime(&self, mime: &str) -> Option<&P> {
+        let essence = mime.split(';').next().unwrap_or_default().trim();
+        if essence.is_empty() {
+            return self.record_lookup(None);
+        }
+        let kind = essence.split_once('/').map_or(essence, |(kind, _)| kind);
+        let specificity = |declared: &&str| match declared.strip_suffix("/*") {
+            _ if declared.eq_ignore_ascii_case(essence) => Some(2),
+            Some("*") => Some(0),
+            Some(declared) if declared.eq_ignore_ascii_case(kind) => Some(1),
+            _ => None,
+        };
+        let found = self
+            .scan_serving()
+            .filter_map(|p| Some((p.mime_types().iter().filter_map(specificity).max()?, p)))
+            .fold(None, |best, (rank, p)| match best {
+                Some((best_rank, _)) if best_rank >= rank => best,
+                _ => Some((rank, p)),
+            })
+            .map(|(_, p)| p);
+        self.record_lookup(found)
+    }
+
+    /// Find the first registered provider that declares the scheme of `uri`
+    /// in [`Provider::schemes`], ignoring ASCII case.
+    ///
+    /// Takes a bare scheme such as `"s3"` or a whole URI such as
+    /// `"s3://bucket/key"`.
+    pub fn find_by_scheme(&self, uri: &str) -> Option<&P> {
+        let scheme = uri.split_once(':').map_or(uri, |(scheme, _)| scheme);
+        let found = self.scan_serving().find(|p| {
+            p.schemes()
+                .iter()
+                .any(|declared| declared.eq_ignore_ascii_case(scheme))
+        });
+        self.record_lookup(found)
+    }
+
+    /// Evaluate gates set with [`gate`](Self::gate) against `flags`.
+    pub fn with_flags(mut self, flags: &FeatureFlags) -> Self {
+        self.flags = Some(flags.clone());
+        self
+    }
+
+    /// Make the provider called `provider` available to the `_enabled`
+    /// lookups only when `flag` is enabled.
+    ///
+    /// The gate applies whether or not the provider is registered yet. Without
+    /// [`with_flags`](Self::with_flags), gated providers are never enabled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::flags::{FeatureFlags, FlagRule, StaticFlags};
+    /// use rustratify::{Context, Provider, Registry};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug)]
+    /// struct NewParser;
+    ///
+    /// impl Provider for NewParser {
+    ///     fn name(&self) -> &str { "new-parser" }
+    ///     fn extensions(&self) -> &[&str] { &["rs"] }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// let flags = FeatureFlags::new()
+    ///     .with_source(StaticFlags::new().flag("parser-v2", FlagRule::Percentage(0)));
+    /// let mut registry: Registry<dyn Provider> = Registry::new().with_flags(&flags);
+    /// registry.register(Box::new(NewParser));
+    /// registry.gate("new-parser", "parser-v2");
+    ///
+    /// let cx = Context::new().with_subject("user-1");
+    /// assert!(registry.find_enabled("rs", &cx).is_none());
+    /// assert!(registry.find("rs").is_some());
+    /// ```
+    pub fn gate(&mut self, provider: impl Into<String>, flag: impl Into<String>) {
+        self.gates.insert(provider.into(), flag.into());
+    }
+
+    /// Whether the provider called `name` passes its gate in `cx`.
+    /// Ungated providers are always enabled.
+    pub fn is_enabled(&self, name: &str, cx: &Context) -> bool {
+        match self.gates.get(name) {
+            None => true,
+            Some(flag) => self
+                .flags
+                .as_ref()
+                .is_some_and(|flags| flags.is_enabled(flag, cx)),
+        }
+    }
+
+    /// Like [`get`](Self::get), skipping providers whose gate is off in `cx`.
+    pub fn get_enabled(&self, name: &str, cx: &Context) -> Option<&P> {
+        let found = self
+            .position(name)
+            .filter(|_| self.is_enabled(name, cx))
+            .map(|i| self.entries[i].provider.as_ref());
+        self.record_lookup(found)
+    }
+
+    /// Like [`find`](Self::find), skipping providers whose gate is off in
+    /// `cx`.
+    pub fn find_enabled(&self, key: &str, cx: &Context) -> Option<&P> {
+        let found = self
+            .serving()
+            .filter(|entry| self.is_enabled(&entry.name, cx))
+            .map(|entry| entry.provider.as_ref())
+            .find(|p| p.supports(key));
+        self.record_lookup(found)
+    }
+
+    /// Like [`find_best`](Self::find_best), skipping providers whose gate is
+    /// off in `cx`.
+    pub fn find_best_enabled(&self, key: &str, cx: &Context) -> Option<&P> {
+        let found = self.select_best(
+            key,
+            self.serving()
+                .filter(|entry| self.is_enabled(&entry.name, cx))
+                .map(|entry| entry.provider.as_ref())
+                .filter(|p| p.supports(key))
+                .filter(|p| !self.route_by_health || self.is_healthy(*p)),
+        );
+        self.record_lookup(found.ok())
+    }
+
+    /// Break ties in [`find_best`](Self::find_best) with `policy`.
+    ///
+    /// Without a policy the provider registered last wins, as with
+    /// [`TieBreak::LastRegistered`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{Provider, Registry, RegistryError, TieBreak};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug)]
+    /// struct Lang(&'static str);
+    ///
+    /// impl Provider for Lang {
+    ///     fn name(&self) -> &str { self.0 }
+    ///     fn extensions(&self) -> &[&str] { &[".rs"] }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// let mut registry: Registry<dyn Provider> =
+    ///     Registry::new().with_selection_policy(TieBreak::Reject);
+    /// registry.register(Box::new(Lang("rust")));
+    /// registry.register(Box::new(Lang("rust-analyzer")));
+    ///
+    /// assert!(registry.find_best("main.rs").is_none());
+    /// assert!(matches!(
+    ///     registry.try_find_best("main.rs"),
+    ///     Err(RegistryError::AmbiguousMatch(names)) if names == "rust, rust-analyzer"
+    /// ));
+    /// ```
+    pub fn with_selection_policy(mut self, policy: impl SelectionPolicy + 'static) -> Self {
+        self.selection = Some(Arc::new(policy));
+        self.invalidate();
+        self
+    }
+
+    /// Remember the results of [`find`](Self::find) and
+    /// [`find_best`](Self::find_best) for up to `capacity` keys, evicting
+    /// the least recently used.
+    ///
+    /// Hosts that resolve the same handful of keys over and over skip the
+    /// scan over every provider's [`supports`](Provider::supports). Keys
+    /// without a match are remembered too. The cache is cleared whenever
+    /// providers are registered, removed, borrowed through
+    /// [`get_mut`](Self::get_mut) or change readiness, so `supports` must
+    /// answer the same for a key as long as the provider is unchanged.
+    pub fn with_lookup_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(LookupCache::new(capacity));
+        self
+    }
+
+    /// Check providers against `checker` in [`authorize`](Self::authorize)
+    /// and [`get_authorized`](Self::get_authorized).
+    pub fn with_permissions(mut self, checker: impl PermissionChecker + 'static) -> Self {
+        self.permissions = Some(Arc::new(checker));
+        self
+    }
+
+    /// Check that `provider` may run with the permissions it requests in
+    /// `cx`; call before dispatching to it.
+    ///
+    /// Without [`with_permissions`](Self::with_permissions) every provider
+    /// is allowed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::permissions::{PermissionPolicy, Permissions};
+    /// use rustratify::{Context, Provider, Registry};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug)]
+    /// struct Fetcher;
+    ///
+    /// impl Provider for Fetcher {
+    ///     fn name(&self) -> &str { "fetcher" }
+    ///     fn permissions(&self) -> Permissions {
+    ///         Permissions::none().with_network("crates.io")
+    ///     }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// let mut registry: Registry<dyn Provider> =
+    ///     Registry::new().with_permissions(PermissionPolicy::new());
+    /// registry.register(Box::new(Fetcher));
+    ///
+    /// assert!(registry.get_authorized("fetcher", &Context::new()).is_err());
+    /// ```
+    pub fn authorize(&self, provider: &P, cx: &Context) -> ProviderResult<()> {
+        let Some(checker) = &self.permissions else {
+            return Ok(());
+        };
+        let result = checker.check(provider.name(), &provider.permissions(), cx);
+        if let Err(err) = &result {
+            tracing::warn!(provider = provider.name(), error = %err, "provider not authorized");
+        }
+        result
+    }
+
+    /// Like [`get_enabled`](Self::get_enabled), also checking the provider
+    /// with [`authorize`](Self::authorize).
+    ///
+    /// Fails with [`ProviderError::NotFound`] if no enabled provider has the
+    /// name.
+    pub fn get_authorized(&self, name: &str, cx: &Context) -> ProviderResult<&P> {
+        let provider = self
+            .get_enabled(name, cx)
+            .ok_or_else(|| ProviderError::NotFound(name.to_string()))?;
+        self.authorize(provider, cx)?;
+        Ok(provider)
     }
 
     /// Check if a provider with the given name is registered.
     pub fn contains(&self, name: &str) -> bool {
-        self.providers.contains_key(name)
+        self.index.contains_key(name)
     }
 
     /// Remove a provider by name.
     pub fn remove(&mut self, name: &str) -> Option<Box<P>> {
-        self.ordered.retain(|n| n != name);
-        self.providers.remove(name)
+        let removed = self.index.remove(name).map(|removed| {
+            for position in self.index.values_mut() {
+                if *position > removed {
+                    *position -= 1;
+                }
+            }
+            self.remove_extensions(removed);
+            self.entries.remove(removed).provider
+        });
+        if removed.is_some() {
+            self.invalidate();
+            if let Some(quarantine) = &self.quarantine {
+                quarantine.forget(name);
+            }
+            self.turns
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(name);
+            self.audit(|log| log.provider_removed(name));
+        }
+        self.record_len();
+        removed
     }
 
     /// Get the names of all registered providers.
     pub fn names(&self) -> Vec<&str> {
-        self.ordered.iter().map(|s| s.as_str()).collect()
+        self.entries.iter().map(|entry| &*entry.name).collect()
     }
 
     /// Get all registered providers.
     pub fn providers(&self) -> Vec<&P> {
-        self.ordered
-            .iter()
-            .filter_map(|name| self.providers.get(name))
-            .map(|p| p.as_ref())
-            .collect()
+        self.scan().collect()
     }
 
     /// Get the number of registered providers.
     pub fn len(&self) -> usize {
-        self.providers.len()
+        self.entries.len()
     }
 
     /// Check if the registry is empty.
     pub fn is_empty(&self) -> bool {
-        self.providers.is_empty()
+        self.entries.is_empty()
+    }
+
+    /// Clear all providers from the registry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+        self.extensions.clear();
+        if let Some(quarantine) = &self.quarantine {
+            quarantine.clear();
+        }
+        self.turns
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self.invalidate();
+        self.record_len();
+    }
+
+    /// Iterate over all providers.
+    pub fn iter(&self) -> impl Iterator<Item = &P> {
+        self.scan()
+    }
+
+    /// Borrow the registry as a [`RegistryView`], which can look providers
+    /// up but not change the registry.
+    pub fn view(&self) -> RegistryView<'_, P, S> {
+        RegistryView::from(self)
+    }
+
+    /// Describe the registered providers, in registration order.
+    pub fn manifest(&self) -> RegistryManifest {
+        RegistryManifest {
+            providers: self.iter().map(ProviderInfo::of).collect(),
+        }
+    }
+
+    /// Collect the [statistics](Provider::stats) of every provider that
+    /// records them.
+    pub fn stats_report(&self) -> StatsReport {
+        StatsReport {
+            providers: self
+                .entries
+                .iter()
+                .filter_map(|entry| {
+                    let stats = entry.provider.stats()?;
+                    Some((entry.name.to_string(), stats.snapshot()))
+                })
+                .collect(),
+        }
+    }
+
+    /// [Start](Provider::start) and [warm up](Provider::warm_up) every
+    /// provider, in registration order.
+    ///
+    /// Each provider is marked ready once its warm-up succeeds. A failing
+    /// provider does not keep the others from starting; every failure is
+    /// returned, labelled with the provider name.
+    pub async fn start_all(&self) -> Result<(), MultiError> {
+        let mut errors = MultiError::new();
+        for entry in &self.entries {
+            if let Err(err) = entry.provider.start().await {
+                errors.push(&*entry.name, err);
+            } else if let Err(err) = self.warm_up_entry(entry).await {
+                errors.push(&*entry.name, err);
+            }
+        }
+        errors.into_result()
+    }
+
+    /// [Warm up](Provider::warm_up) the provider called `name` and mark it
+    /// ready, for providers registered after [`start_all`](Self::start_all).
+    pub async fn warm_up(&self, name: &str) -> ProviderResult<()> {
+        let position = self
+            .position(name)
+            .ok_or_else(|| ProviderError::NotFound(name.to_string()))?;
+        self.warm_up_entry(&self.entries[position]).await
+    }
+
+    async fn warm_up_entry(&self, entry: &Entry<P>) -> ProviderResult<()> {
+        entry.provider.warm_up().await?;
+        entry.set_ready(true);
+        self.invalidate();
+        Ok(())
+    }
+
+    /// Whether the provider called `name` has warmed up.
+    pub fn is_ready(&self, name: &str) -> bool {
+        self.position(name)
+            .is_some_and(|position| self.entries[position].is_ready())
     }
 
-    /// Clear all providers from the registry.
-    pub fn clear(&mut self) {
-        self.providers.clear();
-        self.ordered.clear();
+    /// Make the `find` methods skip providers that are not
+    /// [ready](Self::is_ready), so no request waits on a cold provider.
+    ///
+    /// [`get`](Self::get) still returns any provider by name.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{Provider, Registry};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug)]
+    /// struct Model;
+    ///
+    /// impl Provider for Model {
+    ///     fn name(&self) -> &str { "model" }
+    ///     fn extensions(&self) -> &[&str] { &["txt"] }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// let mut registry: Registry<dyn Provider> = Registry::new().require_ready();
+    /// registry.register(Box::new(Model));
+    /// assert!(registry.find("a.txt").is_none());
+    ///
+    /// registry.start_all().await.unwrap();
+    /// assert!(registry.find("a.txt").is_some());
+    /// # }
+    /// ```
+    pub fn require_ready(mut self) -> Self {
+        self.require_ready = true;
+        self.invalidate();
+        self
+    }
+
+    /// Make [`find_best`](Self::find_best) and
+    /// [`find_best_enabled`](Self::find_best_enabled) pass over providers
+    /// whose [health](Provider::health) check fails, falling back to the
+    /// next best match.
+    ///
+    /// A provider going through an outage then degrades to its fallback
+    /// instead of failing every request routed to it. Health is checked on
+    /// every lookup, so these lookups bypass the
+    /// [lookup cache](Self::with_lookup_cache). If no matching provider is
+    /// healthy, there is no match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{Provider, ProviderError, ProviderResult, Registry};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug)]
+    /// struct Remote;
+    ///
+    /// impl Provider for Remote {
+    ///     fn name(&self) -> &str { "remote" }
+    ///     fn extensions(&self) -> &[&str] { &["rs"] }
+    ///     fn priority(&self) -> i32 { 10 }
+    ///     fn health(&self) -> ProviderResult<()> {
+    ///         Err(ProviderError::ExecutionFailed("connection refused".into()))
+    ///     }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// #[derive(Debug)]
+    /// struct Local;
+    ///
+    /// impl Provider for Local {
+    ///     fn name(&self) -> &str { "local" }
+    ///     fn extensions(&self) -> &[&str] { &["rs"] }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// let mut registry: Registry<dyn Provider> = Registry::new().route_by_health();
+    /// registry.register(Box::new(Remote));
+    /// registry.register(Box::new(Local));
+    /// assert_eq!(registry.find_best("main.rs").unwrap().name(), "local");
+    /// ```
+    pub fn route_by_health(mut self) -> Self {
+        self.route_by_health = true;
+        self
+    }
+
+    /// Skip providers that fail too often in the `find` methods, as set by
+    /// `policy`.
+    ///
+    /// Quarantine decisions change over time, so the `find` methods bypass
+    /// the [lookup cache](Self::with_lookup_cache).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use rustratify::stats::{CallStats, Instrumented};
+    /// use rustratify::{Provider, ProviderError, QuarantinePolicy, Registry};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug)]
+    /// struct Lang(&'static str, i32);
+    ///
+    /// impl Provider for Lang {
+    ///     fn name(&self) -> &str { self.0 }
+    ///     fn extensions(&self) -> &[&str] { &["rs"] }
+    ///     fn priority(&self) -> i32 { self.1 }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// let policy = QuarantinePolicy::new(0.5)
+    ///     .min_calls(4)
+    ///     .cool_down(Duration::from_secs(30));
+    /// let mut registry: Registry<dyn Provider> = Registry::new().with_quarantine(policy);
+    /// let stats = CallStats::new();
+    /// registry.register(Box::new(Instrumented::with_stats(Lang("remote", 10), stats.clone())));
+    /// registry.register(Box::new(Lang("local", 0)));
+    ///
+    /// let outage = ProviderError::ExecutionFailed("connection refused".into());
+    /// for _ in 0..4 {
+    ///     stats.record(Duration::from_millis(5), Some(&outage));
+    /// }
+    /// assert_eq!(registry.find_best("main.rs").unwrap().name(), "local");
+    /// assert!(registry.is_quarantined("remote"));
+    /// ```
+    pub fn with_quarantine(mut self, policy: QuarantinePolicy) -> Self {
+        self.quarantine = Some(Quarantine::new(policy));
+        self.invalidate();
+        self
+    }
+
+    /// Whether the provider called `name` is quarantined.
+    pub fn is_quarantined(&self, name: &str) -> bool {
+        self.quarantine
+            .as_ref()
+            .is_some_and(|q| q.is_quarantined(name))
+    }
+
+    /// Re-admit the provider called `name` from quarantine now, forgetting
+    /// its failures so far. Returns whether it was quarantined.
+    pub fn release(&self, name: &str) -> bool {
+        let (Some(quarantine), Some(position)) = (&self.quarantine, self.position(name)) else {
+            return false;
+        };
+        let was_quarantined = quarantine.is_quarantined(name);
+        let entry = &self.entries[position];
+        quarantine.release(&entry.name, entry.provider.as_ref());
+        was_quarantined
+    }
+
+    fn is_healthy(&self, provider: &P) -> bool {
+        match provider.health() {
+            Ok(()) => true,
+            Err(err) => {
+                tracing::debug!(provider = provider.name(), error = %err, "skipping unhealthy provider");
+                false
+            }
+        }
+    }
+
+    /// [Stop](Provider::stop) every provider, in reverse registration order.
+    ///
+    /// Failures are collected as in [`start_all`](Self::start_all).
+    pub async fn stop_all(&self) -> Result<(), MultiError> {
+        let mut errors = MultiError::new();
+        for entry in self.entries.iter().rev() {
+            entry.set_ready(false);
+            self.invalidate();
+            if let Err(err) = entry.provider.stop().await {
+                errors.push(&*entry.name, err);
+            }
+        }
+        errors.into_result()
+    }
+
+    /// Check the [health](Provider::health) of every provider, returning
+    /// every unhealthy one.
+    pub fn health(&self) -> Result<(), MultiError> {
+        let mut errors = MultiError::new();
+        for entry in &self.entries {
+            if let Err(err) = entry.provider.health() {
+                errors.push(&*entry.name, err);
+            }
+        }
+        errors.into_result()
+    }
+}
+
+/// A snapshot of what a [`Registry`] contains, for listing over an API or
+/// persisting.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{Provider, Registry};
+/// use std::any::Any;
+///
+/// #[derive(Debug)]
+/// struct Rust;
+///
+/// impl Provider for Rust {
+///     fn name(&self) -> &str { "rust" }
+///     fn extensions(&self) -> &[&str] { &["rs"] }
+///     fn as_any(&self) -> &dyn Any { self }
+/// }
+///
+/// let mut registry: Registry<dyn Provider> = Registry::new();
+/// registry.register(Box::new(Rust));
+///
+/// let manifest = registry.manifest();
+/// assert_eq!(manifest.providers[0].name, "rust");
+/// assert_eq!(manifest.providers[0].extensions, vec!["rs"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegistryManifest {
+    /// The providers, in registration order
+    pub providers: Vec<ProviderInfo>,
+}
+
+impl RegistryManifest {
+    /// The entry for the provider called `name`.
+    pub fn get(&self, name: &str) -> Option<&ProviderInfo> {
+        self.providers.iter().find(|p| p.name == name)
     }
+}
 
-    /// Iterate over all providers.
-    pub fn iter(&self) -> impl Iterator<Item = &P> {
-        self.ordered
-            .iter()
-            .filter_map(move |name| self.providers.get(name))
-            .map(|p| p.as_ref())
+/// The capabilities of one registered provider.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProviderInfo {
+    /// The provider name
+    pub name: String,
+    /// Supported extensions or keys
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub extensions: Vec<String>,
+    /// Selection priority
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub priority: i32,
+}
+
+impl ProviderInfo {
+    /// Describe `provider`.
+    pub fn of<P: Provider + ?Sized>(provider: &P) -> Self {
+        Self {
+            name: provider.name().to_string(),
+            extensions: provider
+                .extensions()
+                .iter()
+                .map(|e| e.to_string())
+                .collect(),
+            priority: provider.priority(),
+        }
     }
 }
 
-impl<P: Provider + ?Sized> Default for Registry<P> {
+impl<P: Provider + ?Sized, S: BuildHasher + Default> Default for Registry<P, S> {
     fn default() -> Self {
-        Self::new()
+        Self::with_hasher(S::default())
     }
 }
 
-impl Registry<dyn CloneableProvider> {
+impl<S: BuildHasher + Clone> Registry<dyn CloneableProvider, S> {
     /// Clone the registry and all its providers.
     ///
     /// This method is only available for registries containing `CloneableProvider` trait objects.
@@ -216,20 +1277,19 @@ impl Registry<dyn CloneableProvider> {
     /// assert_eq!(cloned.len(), 1);
     /// assert!(cloned.contains("test"));
     /// ```
+    #[allow(clippy::should_implement_trait)]
     pub fn clone(&self) -> Self {
-        let mut new_registry = Registry::new();
-        for name in &self.ordered {
-            if let Some(provider) = self.providers.get(name) {
-                new_registry.register(provider.clone_box());
-            }
+        let mut new_registry = Registry::with_hasher(self.index.hasher().clone());
+        for provider in self.iter() {
+            new_registry.register(provider.clone_box());
         }
         new_registry
     }
 }
 
 /// Builder for creating registries with fluent API.
-pub struct RegistryBuilder<P: ?Sized> {
-    registry: Registry<P>,
+pub struct RegistryBuilder<P: ?Sized, S = RandomState> {
+    registry: Registry<P, S>,
 }
 
 impl<P: Provider + ?Sized> RegistryBuilder<P> {
@@ -239,6 +1299,16 @@ impl<P: Provider + ?Sized> RegistryBuilder<P> {
             registry: Registry::new(),
         }
     }
+}
+
+impl<P: Provider + ?Sized, S: BuildHasher> RegistryBuilder<P, S> {
+    /// Create a builder for a registry that hashes names with `hasher`.
+    /// See [`Registry::with_hasher`].
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            registry: Registry::with_hasher(hasher),
+        }
+    }
 
     /// Add a provider to the registry.
     pub fn with(mut self, provider: Box<P>) -> Self {
@@ -246,15 +1316,139 @@ impl<P: Provider + ?Sized> RegistryBuilder<P> {
         self
     }
 
+    /// Add a provider to the registry if `condition` holds.
+    pub fn with_if(self, condition: bool, provider: Box<P>) -> Self {
+        if condition {
+            self.with(provider)
+        } else {
+            self
+        }
+    }
+
+    /// Add every provider of `providers` to the registry, in order.
+    pub fn with_all(mut self, providers: impl IntoIterator<Item = Box<P>>) -> Self {
+        for provider in providers {
+            self.registry.register(provider);
+        }
+        self
+    }
+
+    /// Add the [default](Default) `T` to the registry.
+    ///
+    /// Available for registries of `dyn Provider` and
+    /// `dyn CloneableProvider`, and of other SPI traits whose trait objects
+    /// implement `From<Box<T>>`, as `Box<dyn Provider>` does.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustratify::{Provider, RegistryBuilder};
+    /// use std::any::Any;
+    ///
+    /// #[derive(Debug, Default)]
+    /// struct Rust;
+    ///
+    /// impl Provider for Rust {
+    ///     fn name(&self) -> &str { "rust" }
+    ///     fn as_any(&self) -> &dyn Any { self }
+    /// }
+    ///
+    /// let registry = RegistryBuilder::<dyn Provider>::new()
+    ///     .with_default::<Rust>()
+    ///     .build();
+    /// assert!(registry.contains("rust"));
+    /// ```
+    pub fn with_default<T>(self) -> Self
+    where
+        T: Default,
+        Box<T>: Into<Box<P>>,
+    {
+        self.with(Box::new(T::default()).into())
+    }
+
+    /// Add a provider to the registry, failing if one with the same name
+    /// is already added. See [`Registry::register_unique`].
+    pub fn try_with(mut self, provider: Box<P>) -> RegistryResult<Self> {
+        self.registry.register_unique(provider)?;
+        Ok(self)
+    }
+
+    /// Record registry metrics. See [`Registry::with_metrics`].
+    pub fn metrics(mut self, metrics: &Metrics, name: &str) -> Self {
+        self.registry = self.registry.with_metrics(metrics, name);
+        self
+    }
+
+    /// Audit registrations and removals. See [`Registry::with_audit`].
+    pub fn audit(mut self, log: &AuditLog) -> Self {
+        self.registry = self.registry.with_audit(log);
+        self
+    }
+
+    /// Evaluate provider gates against `flags`. See [`Registry::with_flags`].
+    pub fn flags(mut self, flags: &FeatureFlags) -> Self {
+        self.registry = self.registry.with_flags(flags);
+        self
+    }
+
+    /// Check providers against `checker` before dispatch. See
+    /// [`Registry::with_permissions`].
+    pub fn permissions(mut self, checker: impl PermissionChecker + 'static) -> Self {
+        self.registry = self.registry.with_permissions(checker);
+        self
+    }
+
+    /// Break ties in `find_best` with `policy`. See
+    /// [`Registry::with_selection_policy`].
+    pub fn selection_policy(mut self, policy: impl SelectionPolicy + 'static) -> Self {
+        self.registry = self.registry.with_selection_policy(policy);
+        self
+    }
+
+    /// Cache lookup results. See [`Registry::with_lookup_cache`].
+    pub fn lookup_cache(mut self, capacity: usize) -> Self {
+        self.registry = self.registry.with_lookup_cache(capacity);
+        self
+    }
+
+    /// Skip providers that are not ready in the `find` methods. See
+    /// [`Registry::require_ready`].
+    pub fn require_ready(mut self) -> Self {
+        self.registry = self.registry.require_ready();
+        self
+    }
+
+    /// Quarantine providers that fail too often. See
+    /// [`Registry::with_quarantine`].
+    pub fn quarantine(mut self, policy: QuarantinePolicy) -> Self {
+        self.registry = self.registry.with_quarantine(policy);
+        self
+    }
+
+    /// Pass over unhealthy providers in `find_best`. See
+    /// [`Registry::route_by_health`].
+    pub fn route_by_health(mut self) -> Self {
+        self.registry = self.registry.route_by_health();
+        self
+    }
+
+    /// Add a provider available only when `flag` is enabled. See
+    /// [`Registry::gate`].
+    pub fn gated(mut self, provider: Box<P>, flag: impl Into<String>) -> Self {
+        self.registry.gate(provider.name().to_string(), flag);
+        self.registry.register(provider);
+        self
+    }
+
     /// Build the registry.
-    pub fn build(self) -> Registry<P> {
+    pub fn build(self) -> Registry<P, S> {
         self.registry
     }
 }
 
-impl<P: Provider + ?Sized> Default for RegistryBuilder<P> {
+impl<P: Provider + ?Sized, S: BuildHasher + Default> Default for RegistryBuilder<P, S> {
     fn default() -> Self {
-        Self::new()
+        Self::with_hasher(S::default())
     }
 }
 
@@ -344,6 +1538,144 @@ mod tests {
         assert_eq!(provider.unwrap().name(), "high");
     }
 
+    #[test]
+    fn test_registry_selection_policy() {
+        let tied = |policy: TieBreak| {
+            RegistryBuilder::<dyn Provider>::new()
+                .selection_policy(policy)
+                .with(Box::new(
+                    TestProvider::new("a", vec![".test"]).with_priority(5),
+                ))
+                .with(Box::new(
+                    TestProvider::new("b", vec![".test"]).with_priority(5),
+                ))
+                .with(Box::new(
+                    TestProvider::new("c", vec![".test"]).with_priority(1),
+                ))
+                .build()
+        };
+
+        let last = tied(TieBreak::LastRegistered);
+        assert_eq!(last.find_best("x.test").unwrap().name(), "b");
+        let first = tied(TieBreak::FirstRegistered);
+        assert_eq!(first.find_best("x.test").unwrap().name(), "a");
+
+        let reject = tied(TieBreak::Reject);
+        assert!(reject.find_best("x.test").is_none());
+        assert!(matches!(
+            reject.try_find_best("x.test"),
+            Err(RegistryError::AmbiguousMatch(names)) if names == "a, b"
+        ));
+        assert!(matches!(
+            reject.try_find_best("x.none"),
+            Err(RegistryError::NoMatchingProvider)
+        ));
+
+        let mut by_name =
+            Registry::<dyn Provider>::new().with_selection_policy(|_: &str, tied: &[&str]| {
+                tied.iter()
+                    .position(|name| *name == "b")
+                    .ok_or(RegistryError::NoMatchingProvider)
+            });
+        by_name.register(Box::new(TestProvider::new("b", vec![".test"])));
+        by_name.register(Box::new(TestProvider::new("a", vec![".test"])));
+        assert_eq!(by_name.try_find_best("x.test").unwrap().name(), "b");
+    }
+
+    #[test]
+    fn test_registry_lookup_cache() {
+        use std::sync::atomic::AtomicUsize;
+
+        #[derive(Debug)]
+        struct Counting(&'static str, Arc<AtomicUsize>);
+
+        impl Provider for Counting {
+            fn name(&self) -> &str {
+                self.0
+            }
+
+            fn supports(&self, key: &str) -> bool {
+                self.1.fetch_add(1, Ordering::Relaxed);
+                key.ends_with(self.0)
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        let scans = Arc::new(AtomicUsize::new(0));
+        let mut registry = RegistryBuilder::<dyn Provider>::new()
+            .lookup_cache(8)
+            .with(Box::new(Counting("rs", Arc::clone(&scans))))
+            .build();
+
+        let lookups = |registry: &Registry<dyn Provider>| {
+            assert_eq!(registry.find("main.rs").unwrap().name(), "rs");
+            assert_eq!(registry.find_best("main.rs").unwrap().name(), "rs");
+            assert!(registry.find("main.py").is_none());
+            scans.load(Ordering::Relaxed)
+        };
+        let first = lookups(&registry);
+        assert_eq!(lookups(&registry), first);
+
+        registry.register(Box::new(Counting("py", Arc::clone(&scans))));
+        assert_eq!(registry.find("main.py").unwrap().name(), "py");
+        registry.remove("rs");
+        assert!(registry.find("main.rs").is_none());
+        assert!(registry.try_find_best("main.rs").is_err());
+    }
+
+    #[test]
+    fn test_registry_find_by_mime_and_scheme() {
+        #[derive(Debug)]
+        struct Handler(
+            &'static str,
+            &'static [&'static str],
+            &'static [&'static str],
+        );
+
+        impl Provider for Handler {
+            fn name(&self) -> &str {
+                self.0
+            }
+
+            fn mime_types(&self) -> &[&str] {
+                self.1
+            }
+
+            fn schemes(&self) -> &[&str] {
+                self.2
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        let registry = RegistryBuilder::<dyn Provider>::new()
+            .with(Box::new(Handler("fallback", &["*/*"], &[])))
+            .with(Box::new(Handler("text", &["text/*"], &["file"])))
+            .with(Box::new(Handler(
+                "json",
+                &["application/json"],
+                &["s3", "S3A"],
+            )))
+            .build();
+
+        let mime = |mime| registry.find_by_mime(mime).map(|p| p.name());
+        assert_eq!(mime("application/json; charset=utf-8"), Some("json"));
+        assert_eq!(mime("Text/CSV"), Some("text"));
+        assert_eq!(mime("image/png"), Some("fallback"));
+        assert_eq!(mime(""), None);
+
+        let scheme = |uri| registry.find_by_scheme(uri).map(|p| p.name());
+        assert_eq!(scheme("s3"), Some("json"));
+        assert_eq!(scheme("s3a://bucket/key"), Some("json"));
+        assert_eq!(scheme("FILE:///tmp/a"), Some("text"));
+        assert_eq!(scheme("https://example.com"), None);
+    }
+
     #[test]
     fn test_registry_names() {
         let mut registry: Registry<dyn Provider> = Registry::new();
@@ -364,6 +1696,51 @@ mod tests {
         assert_eq!(registry.len(), 2);
     }
 
+    #[test]
+    fn test_registry_builder_helpers() {
+        #[derive(Debug, Clone, Default)]
+        struct Fallback;
+
+        impl Provider for Fallback {
+            fn name(&self) -> &str {
+                "fallback"
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        let builder = RegistryBuilder::<dyn Provider>::new()
+            .with_if(true, Box::new(TestProvider::new("on", vec![])))
+            .with_if(false, Box::new(TestProvider::new("off", vec![])))
+            .with_all(
+                ["a", "b"]
+                    .map(|name| Box::new(TestProvider::new(name, vec![])) as Box<dyn Provider>),
+            )
+            .with_default::<Fallback>()
+            .try_with(Box::new(TestProvider::new("c", vec![])))
+            .unwrap();
+        assert!(matches!(
+            builder.try_with(Box::new(TestProvider::new("a", vec![]))),
+            Err(RegistryError::AlreadyRegistered(name)) if name == "a"
+        ));
+
+        let registry = RegistryBuilder::<dyn Provider>::new()
+            .with_all(vec![
+                Box::new(TestProvider::new("a", vec![])) as Box<dyn Provider>,
+                Box::new(Fallback),
+            ])
+            .with_if(true, Box::new(TestProvider::new("on", vec![])))
+            .build();
+        assert_eq!(registry.names(), ["a", "fallback", "on"]);
+
+        let cloneable = RegistryBuilder::<dyn CloneableProvider>::new()
+            .with_default::<Fallback>()
+            .build();
+        assert_eq!(cloneable.clone().names(), ["fallback"]);
+    }
+
     #[test]
     fn test_registry_clone() {
         let mut registry: Registry<dyn CloneableProvider> = Registry::new();
@@ -405,4 +1782,457 @@ mod tests {
         let names: Vec<&str> = cloned.names();
         assert_eq!(names, vec!["rust", "python", "javascript"]);
     }
+
+    #[test]
+    fn test_registry_manifest() {
+        let mut registry: Registry<dyn Provider> = Registry::new();
+        registry.register(Box::new(
+            TestProvider::new("rust", vec![".rs"]).with_priority(10),
+        ));
+        registry.register(Box::new(TestProvider::new("python", vec![".py", ".pyw"])));
+
+        let manifest = registry.manifest();
+        assert_eq!(manifest.providers.len(), 2);
+        assert_eq!(manifest.providers[1].name, "python");
+        assert_eq!(manifest.get("rust").unwrap().priority, 10);
+        assert!(manifest.get("go").is_none());
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&manifest).unwrap();
+            assert_eq!(
+                json["providers"][0],
+                serde_json::json!({ "name": "rust", "extensions": [".rs"], "priority": 10 })
+            );
+            let back: RegistryManifest = serde_json::from_value(json).unwrap();
+            assert_eq!(back, manifest);
+        }
+    }
+
+    #[test]
+    fn test_registry_metrics() {
+        use crate::metrics::MetricValue;
+
+        let metrics = Metrics::new();
+        let mut registry = RegistryBuilder::<dyn Provider>::new()
+            .metrics(&metrics, "langs")
+            .with(Box::new(TestProvider::new("rust", vec![".rs"])))
+            .build();
+        registry.register(Box::new(TestProvider::new("python", vec![".py"])));
+
+        assert!(registry.find(".rs").is_some());
+        assert!(registry.get("rust").is_some());
+        assert!(registry.find_best(".go").is_none());
+        registry.remove("python");
+
+        let snapshot = metrics.snapshot();
+        let lookups = |result| {
+            snapshot
+                .get(
+                    "rustratify_registry_lookups_total",
+                    &[("registry", "langs"), ("result", result)],
+                )
+                .cloned()
+        };
+        assert_eq!(lookups("hit"), Some(MetricValue::Counter(2)));
+        assert_eq!(lookups("miss"), Some(MetricValue::Counter(1)));
+        assert_eq!(
+            snapshot.get("rustratify_registry_providers", &[("registry", "langs")]),
+            Some(&MetricValue::Gauge(1.0))
+        );
+    }
+
+    #[test]
+    fn test_registry_audit() {
+        use crate::audit::{AuditAction, MemorySink};
+
+        let sink = MemorySink::new();
+        let log = AuditLog::new().with_sink(sink.clone());
+        let mut registry = RegistryBuilder::<dyn Provider>::new()
+            .audit(&log)
+            .with(Box::new(TestProvider::new("rust", vec![".rs"])))
+            .build();
+        registry.remove("rust");
+        registry.remove("rust");
+
+        let actions: Vec<_> = sink.records().into_iter().map(|r| r.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                AuditAction::ProviderRegistered,
+                AuditAction::ProviderRemoved
+            ]
+        );
+    }
+
+    #[test]
+    fn test_registry_permissions() {
+        use crate::permissions::Permissions;
+
+        let cx = Context::new();
+        let mut open: Registry<dyn Provider> = Registry::new();
+        open.register(Box::new(TestProvider::new("rust", vec![".rs"])));
+        assert!(open.get_authorized("rust", &cx).is_ok());
+
+        let registry = RegistryBuilder::<dyn Provider>::new()
+            .permissions(|name: &str, _: &Permissions, _: &Context| match name {
+                "rust" => Ok(()),
+                _ => Err(ProviderError::PermissionDenied(name.to_string())),
+            })
+            .with(Box::new(TestProvider::new("rust", vec![".rs"])))
+            .with(Box::new(TestProvider::new("python", vec![".py"])))
+            .build();
+        assert!(registry.get_authorized("rust", &cx).is_ok());
+        assert!(matches!(
+            registry.get_authorized("python", &cx),
+            Err(ProviderError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            registry.get_authorized("go", &cx),
+            Err(ProviderError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_registry_find_by_extension() {
+        let mut registry: Registry<dyn Provider> = Registry::new();
+        registry.register(Box::new(TestProvider::new("rust", vec![".rs"])));
+        registry.register(Box::new(TestProvider::new("markdown", vec![".md", ".MDX"])));
+        registry.register(Box::new(TestProvider::new("rust2", vec![".rs"])));
+
+        assert_eq!(registry.find_by_extension(".RS").unwrap().name(), "rust");
+        assert_eq!(
+            registry.find_by_extension(".mdx").unwrap().name(),
+            "markdown"
+        );
+        assert!(registry.find_by_extension(".m").is_none());
+        assert!(registry.find_by_extension("rs").is_none());
+
+        registry.remove("rust");
+        assert_eq!(registry.find_by_extension(".rs").unwrap().name(), "rust2");
+        assert_eq!(registry.get("rust2").unwrap().name(), "rust2");
+        let names: Vec<_> = registry.find_all_iter("a.rs").map(|p| p.name()).collect();
+        assert_eq!(names, vec!["rust2"]);
+
+        registry.register(Box::new(TestProvider::new("markdown", vec![".txt"])));
+        assert!(registry.find_by_extension(".md").is_none());
+        assert_eq!(
+            registry.find_by_extension(".TXT").unwrap().name(),
+            "markdown"
+        );
+        registry.remove("markdown");
+        assert!(registry.find_by_extension(".txt").is_none());
+        assert_eq!(registry.find_by_extension(".rs").unwrap().name(), "rust2");
+    }
+
+    #[test]
+    fn test_registry_custom_hasher() {
+        use rustc_hash::FxBuildHasher;
+
+        let mut registry = RegistryBuilder::<dyn CloneableProvider, _>::with_hasher(FxBuildHasher)
+            .with(Box::new(TestProvider::new("rust", vec![".rs"])))
+            .with(Box::new(TestProvider::new("python", vec![".py"])))
+            .build();
+        registry.register(Box::new(
+            TestProvider::new("rust", vec![".rs"]).with_priority(5),
+        ));
+        assert_eq!(registry.names(), vec!["rust", "python"]);
+        assert_eq!(registry.get("rust").unwrap().priority(), 5);
+        // Names are stored once, shared by the map and the ordering
+        assert_eq!(Arc::strong_count(&registry.entries[0].name), 2);
+
+        let cloned: Registry<dyn CloneableProvider, FxBuildHasher> = registry.clone();
+        assert_eq!(cloned.find(".py").unwrap().name(), "python");
+        registry.remove("rust");
+        assert_eq!(registry.names(), vec!["python"]);
+    }
+
+    #[test]
+    fn test_registry_flag_gates() {
+        use crate::flags::{FlagRule, StaticFlags};
+
+        let flags = FeatureFlags::new().with_source(
+            StaticFlags::new()
+                .flag("rust-v2", FlagRule::On)
+                .flag("python-v2", FlagRule::Off),
+        );
+        let registry = RegistryBuilder::<dyn Provider>::new()
+            .flags(&flags)
+            .with(Box::new(TestProvider::new("rust", vec![".rs"])))
+            .gated(
+                Box::new(TestProvider::new("rust2", vec![".rs"]).with_priority(10)),
+                "rust-v2",
+            )
+            .gated(Box::new(TestProvider::new("py2", vec![".py"])), "python-v2")
+            .build();
+
+        let cx = Context::new();
+        assert_eq!(
+            registry.find_best_enabled(".rs", &cx).unwrap().name(),
+            "rust2"
+        );
+        assert!(registry.find_enabled(".py", &cx).is_none());
+        assert!(registry.get_enabled("py2", &cx).is_none());
+        assert!(registry.get("py2").is_some());
+        assert!(registry.is_enabled("rust", &cx));
+
+        let mut no_flags = Registry::<dyn Provider>::new();
+        no_flags.gate("rust", "rust-v2");
+        assert!(!no_flags.is_enabled("rust", &cx));
+    }
+
+    #[test]
+    fn test_registry_find_balanced() {
+        use crate::stats::Instrumented;
+
+        let mut registry: Registry<dyn Provider> = Registry::new();
+        registry.register(Box::new(
+            TestProvider::new("low", vec![".rs"]).with_priority(-1),
+        ));
+        registry.register(Box::new(Instrumented::new(TestProvider::new(
+            "a",
+            vec![".rs"],
+        ))));
+        let busy = Instrumented::new(TestProvider::new("b", vec![".rs", ".py"]));
+        busy.call(|_| Ok(())).unwrap();
+        registry.register(Box::new(busy));
+        registry.register(Box::new(TestProvider::new("c", vec![".rs"])));
+
+        let round_robin = |key| {
+            registry
+                .find_balanced(key, BalanceStrategy::RoundRobin)
+                .unwrap()
+                .name()
+        };
+        let picks: Vec<_> = (0..4).map(|_| round_robin(".rs")).collect();
+        assert_eq!(picks, ["a", "b", "c", "a"]);
+        assert_eq!(round_robin(".py"), "b");
+        assert_eq!(round_robin(".rs"), "b");
+
+        // Turns are kept per group of providers, not per key looked up
+        for i in 0..100 {
+            let key = format!("src/file{i}.rs");
+            assert!(registry
+                .find_balanced(&key, BalanceStrategy::RoundRobin)
+                .is_some());
+        }
+        assert_eq!(registry.turns.lock().unwrap().len(), 2);
+
+        let least = registry.find_balanced(".rs", BalanceStrategy::LeastLoaded);
+        assert_eq!(least.unwrap().name(), "a");
+        for _ in 0..10 {
+            let random = registry.find_balanced(".rs", BalanceStrategy::Random);
+            assert_ne!(random.unwrap().name(), "low");
+        }
+        assert!(registry
+            .find_balanced(".go", BalanceStrategy::Random)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_registry_readiness_gating() {
+        #[derive(Debug)]
+        struct Cold;
+
+        impl Provider for Cold {
+            fn name(&self) -> &str {
+                "cold"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &[".rs"]
+            }
+
+            fn priority(&self) -> i32 {
+                10
+            }
+
+            fn warm_up(&self) -> crate::provider::LifecycleFuture<'_> {
+                Box::pin(async { Err(ProviderError::InitializationFailed("no model".into())) })
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        let mut registry = RegistryBuilder::<dyn Provider>::new()
+            .require_ready()
+            .with(Box::new(Cold))
+            .with(Box::new(TestProvider::new("rust", vec![".rs"])))
+            .build();
+        assert!(registry.find(".rs").is_none());
+        assert!(registry.find_by_extension(".rs").is_none());
+
+        let err = registry.start_all().await.unwrap_err();
+        assert_eq!(err.labels().collect::<Vec<_>>(), vec!["cold"]);
+        assert!(!registry.is_ready("cold"));
+        assert!(registry.is_ready("rust"));
+        assert_eq!(registry.find_best(".rs").unwrap().name(), "rust");
+        assert_eq!(registry.find_by_extension(".RS").unwrap().name(), "rust");
+        assert_eq!(registry.find_all(".rs").len(), 1);
+        assert!(registry.get("cold").is_some());
+
+        registry.register(Box::new(TestProvider::new("rust", vec![".rs"])));
+        assert!(registry.find(".rs").is_none());
+        registry.warm_up("rust").await.unwrap();
+        assert!(registry.find(".rs").is_some());
+        assert!(registry.warm_up("go").await.is_err());
+
+        registry.stop_all().await.unwrap();
+        assert!(!registry.is_ready("rust"));
+    }
+
+    #[test]
+    fn test_registry_route_by_health() {
+        #[derive(Debug)]
+        struct Remote(Arc<AtomicBool>);
+
+        impl Provider for Remote {
+            fn name(&self) -> &str {
+                "remote"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &[".rs"]
+            }
+
+            fn priority(&self) -> i32 {
+                10
+            }
+
+            fn health(&self) -> ProviderResult<()> {
+                if self.0.load(Ordering::SeqCst) {
+                    Ok(())
+                } else {
+                    Err(ProviderError::ExecutionFailed("connection refused".into()))
+                }
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        let up = Arc::new(AtomicBool::new(true));
+        let registry = RegistryBuilder::<dyn Provider>::new()
+            .route_by_health()
+            .lookup_cache(8)
+            .with(Box::new(Remote(Arc::clone(&up))))
+            .with(Box::new(TestProvider::new("local", vec![".rs"])))
+            .build();
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "remote");
+
+        up.store(false, Ordering::SeqCst);
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "local");
+        let cx = Context::new();
+        assert_eq!(
+            registry.find_best_enabled("a.rs", &cx).unwrap().name(),
+            "local"
+        );
+        assert!(matches!(
+            registry.try_find_best("a.go"),
+            Err(RegistryError::NoMatchingProvider)
+        ));
+
+        up.store(true, Ordering::SeqCst);
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "remote");
+    }
+
+    #[test]
+    fn test_registry_find_best_checks_health_once() {
+        use std::sync::atomic::AtomicUsize;
+
+        /// Healthy on every other probe
+        #[derive(Debug)]
+        struct Flaky(AtomicUsize);
+
+        impl Provider for Flaky {
+            fn name(&self) -> &str {
+                "flaky"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &[".rs"]
+            }
+
+            fn health(&self) -> ProviderResult<()> {
+                match self.0.fetch_add(1, Ordering::SeqCst) % 2 {
+                    0 => Ok(()),
+                    _ => Err(ProviderError::ExecutionFailed("flapping".into())),
+                }
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        let registry = RegistryBuilder::<dyn Provider>::new()
+            .route_by_health()
+            .with(Box::new(Flaky(AtomicUsize::new(0))))
+            .build();
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "flaky");
+        let probes = &registry
+            .get("flaky")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Flaky>()
+            .unwrap()
+            .0;
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_registry_quarantine() {
+        use crate::stats::{CallStats, Instrumented};
+        use std::time::Duration;
+
+        let stats = CallStats::new();
+        let mut registry = RegistryBuilder::<dyn Provider>::new()
+            .quarantine(
+                QuarantinePolicy::new(0.5)
+                    .min_calls(2)
+                    .cool_down(Duration::from_millis(20)),
+            )
+            .with(Box::new(Instrumented::with_stats(
+                TestProvider::new("flaky", vec![".rs"]).with_priority(10),
+                stats.clone(),
+            )))
+            .with(Box::new(TestProvider::new("steady", vec![".rs"])))
+            .build();
+        let failure = ProviderError::ExecutionFailed("down".into());
+        let record = |n, error: Option<&ProviderError>| {
+            for _ in 0..n {
+                stats.record(Duration::ZERO, error);
+            }
+        };
+
+        record(1, Some(&failure));
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "flaky");
+        record(2, Some(&failure));
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "steady");
+        assert!(registry.is_quarantined("flaky"));
+        assert_eq!(registry.find("a.rs").unwrap().name(), "steady");
+        assert_eq!(registry.find_by_extension(".rs").unwrap().name(), "steady");
+
+        // Re-admitted after the cool-down, judged only on calls since
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "flaky");
+        assert!(!registry.is_quarantined("flaky"));
+        record(4, None);
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "flaky");
+
+        record(10, Some(&failure));
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "steady");
+        assert!(registry.release("flaky"));
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "flaky");
+        assert!(!registry.release("steady"));
+
+        // Clearing forgets quarantine, so a re-registered provider starts afresh
+        record(10, Some(&failure));
+        assert_eq!(registry.find_best("a.rs").unwrap().name(), "steady");
+        registry.clear();
+        assert!(!registry.is_quarantined("flaky"));
+    }
 }
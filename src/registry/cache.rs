@@ -0,0 +1,166 @@
+//! Memoized lookup results for [`Registry`](super::Registry).
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// The lookup a cached result answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Lookup {
+    Find,
+    FindBest,
+}
+
+/// A bounded least-recently-used map from lookup keys to the name of the
+/// provider they resolved to, or `None` for keys without a match.
+#[derive(Debug)]
+pub(super) struct LookupCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Resolved name and last use, by key, for each [`Lookup`]
+    resolved: [HashMap<Box<str>, Resolved>; 2],
+    /// Keys by the use they were last queued at, oldest first; a hit only
+    /// bumps `Resolved::used`, and eviction requeues keys used since
+    queue: BTreeMap<u64, (Lookup, Box<str>)>,
+    clock: u64,
+    /// Bumped on every invalidation, so results computed against an older
+    /// registry state are not stored
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct Resolved {
+    name: Option<Arc<str>>,
+    used: u64,
+}
+
+impl LookupCache {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::default(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get the cached result of `lookup` for `key`, or run `compute` and
+    /// cache its result. Errors are not cached.
+    pub(super) fn resolve<E>(
+        &self,
+        lookup: Lookup,
+        key: &str,
+        compute: impl FnOnce() -> Result<Option<Arc<str>>, E>,
+    ) -> Result<Option<Arc<str>>, E> {
+        if self.capacity == 0 {
+            return compute();
+        }
+        let generation = {
+            let mut inner = self.lock();
+            inner.clock += 1;
+            let now = inner.clock;
+            if let Some(hit) = inner.resolved[lookup as usize].get_mut(key) {
+                hit.used = now;
+                return Ok(hit.name.clone());
+            }
+            inner.generation
+        };
+        let name = compute()?;
+        let mut inner = self.lock();
+        if inner.generation == generation {
+            inner.insert(lookup, key, name.clone(), self.capacity);
+        }
+        Ok(name)
+    }
+
+    /// Forget every cached result.
+    pub(super) fn clear(&self) {
+        let mut inner = self.lock();
+        inner.resolved.iter_mut().for_each(HashMap::clear);
+        inner.queue.clear();
+        inner.generation += 1;
+    }
+
+    /// Get the number of cached results.
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.lock().resolved.iter().map(HashMap::len).sum()
+    }
+}
+
+impl Inner {
+    fn insert(&mut self, lookup: Lookup, key: &str, name: Option<Arc<str>>, capacity: usize) {
+        if self.resolved[lookup as usize].contains_key(key) {
+            return;
+        }
+        while self.queue.len() >= capacity {
+            self.evict();
+        }
+        self.clock += 1;
+        let used = self.clock;
+        self.resolved[lookup as usize].insert(key.into(), Resolved { name, used });
+        self.queue.insert(used, (lookup, key.into()));
+    }
+
+    /// Remove the least recently used result.
+    fn evict(&mut self) {
+        while let Some((queued, (lookup, key))) = self.queue.pop_first() {
+            let Some(used) = self.resolved[lookup as usize].get(&key).map(|r| r.used) else {
+                continue;
+            };
+            if used == queued {
+                self.resolved[lookup as usize].remove(&key);
+                return;
+            }
+            self.queue.insert(used, (lookup, key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+
+    fn resolve(cache: &LookupCache, key: &str, name: &str) -> Option<Arc<str>> {
+        cache
+            .resolve(Lookup::Find, key, || Ok::<_, Infallible>(Some(name.into())))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = LookupCache::new(2);
+        resolve(&cache, "a.rs", "rust");
+        resolve(&cache, "b.py", "python");
+        // A hit on a.rs makes b.py the oldest
+        assert_eq!(resolve(&cache, "a.rs", "other").as_deref(), Some("rust"));
+        resolve(&cache, "c.go", "go");
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(resolve(&cache, "a.rs", "other").as_deref(), Some("rust"));
+        assert_eq!(resolve(&cache, "b.py", "other").as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn test_clear_discards_results_in_flight() {
+        let cache = LookupCache::new(4);
+        let name = cache
+            .resolve(Lookup::FindBest, "a.rs", || {
+                cache.clear();
+                Ok::<_, Infallible>(Some("stale".into()))
+            })
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("stale"));
+        assert_eq!(cache.len(), 0);
+
+        let err = cache.resolve(Lookup::Find, "a.rs", || Err("ambiguous"));
+        assert_eq!(err, Err("ambiguous"));
+        assert_eq!(cache.len(), 0);
+    }
+}
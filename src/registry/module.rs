@@ -0,0 +1,369 @@
+//! One handle over the registries of a module's SPI extension points.
+
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+use super::Registry;
+use crate::error::MultiError;
+use crate::provider::Provider;
+
+type LifecycleResult<'a> = Pin<Box<dyn Future<Output = Result<(), MultiError>> + Send + 'a>>;
+
+/// The registries of a module with several SPI extension points, under one
+/// handle with a shared lifecycle.
+///
+/// Holds one [`Registry`] per SPI trait, each under a label used in error
+/// reports. [`start_all`](Self::start_all), [`stop_all`](Self::stop_all) and
+/// [`health`](Self::health) run across every provider of every registry, and
+/// failures are labelled `label/provider`.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{ModuleRegistry, Provider, Registry};
+/// use std::any::Any;
+///
+/// trait Formatter: Provider {}
+/// trait Reporter: Provider {}
+///
+/// #[derive(Debug)]
+/// struct Plain;
+///
+/// impl Provider for Plain {
+///     fn name(&self) -> &str { "plain" }
+///     fn as_any(&self) -> &dyn Any { self }
+/// }
+///
+/// impl Formatter for Plain {}
+/// impl Reporter for Plain {}
+///
+/// let mut formatters: Registry<dyn Formatter> = Registry::new();
+/// formatters.register(Box::new(Plain));
+///
+/// let module = ModuleRegistry::new()
+///     .with("formatters", formatters)
+///     .with("reporters", Registry::<dyn Reporter>::new());
+///
+/// assert!(module.get::<dyn Formatter>().unwrap().contains("plain"));
+/// assert!(module.health().is_ok());
+/// ```
+#[derive(Default)]
+pub struct ModuleRegistry {
+    registries: Vec<Slot>,
+}
+
+struct Slot {
+    label: String,
+    contract: TypeId,
+    registry: Box<dyn AnyRegistry>,
+}
+
+/// The lifecycle of a `Registry<P>`, with `P` erased.
+trait AnyRegistry: Send + Sync {
+    fn start_all(&self) -> LifecycleResult<'_>;
+    fn stop_all(&self) -> LifecycleResult<'_>;
+    fn health(&self) -> Result<(), MultiError>;
+    fn len(&self) -> usize;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<P: Provider + ?Sized + 'static> AnyRegistry for Registry<P> {
+    fn start_all(&self) -> LifecycleResult<'_> {
+        Box::pin(Registry::start_all(self))
+    }
+
+    fn stop_all(&self) -> LifecycleResult<'_> {
+        Box::pin(Registry::stop_all(self))
+    }
+
+    fn health(&self) -> Result<(), MultiError> {
+        Registry::health(self)
+    }
+
+    fn len(&self) -> usize {
+        Registry::len(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl ModuleRegistry {
+    /// Create a module registry without any registries.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the registry for the SPI trait `P`, labelled `label`.
+    ///
+    /// Replaces a registry already held for `P`.
+    pub fn with<P: Provider + ?Sized + 'static>(
+        mut self,
+        label: impl Into<String>,
+        registry: Registry<P>,
+    ) -> Self {
+        self.insert(label, registry);
+        self
+    }
+
+    /// Add the registry for the SPI trait `P`, labelled `label`, returning
+    /// the registry it replaces.
+    ///
+    /// The replaced registry keeps its position in the lifecycle order.
+    pub fn insert<P: Provider + ?Sized + 'static>(
+        &mut self,
+        label: impl Into<String>,
+        registry: Registry<P>,
+    ) -> Option<Registry<P>> {
+        let slot = Slot {
+            label: label.into(),
+            contract: TypeId::of::<P>(),
+            registry: Box::new(registry),
+        };
+        match self.position::<P>() {
+            Some(i) => {
+                let old = std::mem::replace(&mut self.registries[i], slot);
+                old.registry.into_any().downcast().ok().map(|r| *r)
+            }
+            None => {
+                self.registries.push(slot);
+                None
+            }
+        }
+    }
+
+    fn position<P: ?Sized + 'static>(&self) -> Option<usize> {
+        let contract = TypeId::of::<P>();
+        self.registries.iter().position(|s| s.contract == contract)
+    }
+
+    /// Get the registry for the SPI trait `P`.
+    pub fn get<P: Provider + ?Sized + 'static>(&self) -> Option<&Registry<P>> {
+        let i = self.position::<P>()?;
+        self.registries[i].registry.as_any().downcast_ref()
+    }
+
+    /// Get the registry for the SPI trait `P` mutably, to register or remove
+    /// providers.
+    pub fn get_mut<P: Provider + ?Sized + 'static>(&mut self) -> Option<&mut Registry<P>> {
+        let i = self.position::<P>()?;
+        self.registries[i].registry.as_any_mut().downcast_mut()
+    }
+
+    /// The registry labels, in insertion order.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.registries.iter().map(|s| s.label.as_str())
+    }
+
+    /// Get the number of registries.
+    pub fn len(&self) -> usize {
+        self.registries.len()
+    }
+
+    /// Check if the module has no registries.
+    pub fn is_empty(&self) -> bool {
+        self.registries.is_empty()
+    }
+
+    /// Get the number of providers across all registries.
+    pub fn provider_count(&self) -> usize {
+        self.registries.iter().map(|s| s.registry.len()).sum()
+    }
+
+    /// [Start](Registry::start_all) the providers of every registry, in
+    /// insertion order.
+    ///
+    /// A failing provider does not keep the others from starting.
+    pub async fn start_all(&self) -> Result<(), MultiError> {
+        let mut errors = MultiError::new();
+        for slot in &self.registries {
+            if let Err(err) = slot.registry.start_all().await {
+                slot.relabel(err, &mut errors);
+            }
+        }
+        errors.into_result()
+    }
+
+    /// [Stop](Registry::stop_all) the providers of every registry, in
+    /// reverse insertion order.
+    pub async fn stop_all(&self) -> Result<(), MultiError> {
+        let mut errors = MultiError::new();
+        for slot in self.registries.iter().rev() {
+            if let Err(err) = slot.registry.stop_all().await {
+                slot.relabel(err, &mut errors);
+            }
+        }
+        errors.into_result()
+    }
+
+    /// Check the [health](Registry::health) of every registry, returning
+    /// every unhealthy provider.
+    pub fn health(&self) -> Result<(), MultiError> {
+        let mut errors = MultiError::new();
+        for slot in &self.registries {
+            if let Err(err) = slot.registry.health() {
+                slot.relabel(err, &mut errors);
+            }
+        }
+        errors.into_result()
+    }
+}
+
+impl Slot {
+    fn relabel(&self, err: MultiError, into: &mut MultiError) {
+        for (provider, error) in err {
+            into.push(format!("{}/{provider}", self.label), error);
+        }
+    }
+}
+
+impl fmt::Debug for ModuleRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.registries.iter().map(|s| (&s.label, s.registry.len())))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProviderError;
+    use crate::provider::LifecycleFuture;
+    use std::sync::{Arc, Mutex};
+
+    trait Formatter: Provider {}
+    trait Reporter: Provider {}
+
+    #[derive(Debug)]
+    struct Tracked {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+        healthy: bool,
+    }
+
+    impl Tracked {
+        fn new(name: &'static str, log: &Arc<Mutex<Vec<String>>>) -> Box<Self> {
+            Box::new(Self {
+                name,
+                log: Arc::clone(log),
+                healthy: true,
+            })
+        }
+    }
+
+    impl Provider for Tracked {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn start(&self) -> LifecycleFuture<'_> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("start {}", self.name));
+            Box::pin(async move {
+                match self.healthy {
+                    true => Ok(()),
+                    false => Err(ProviderError::InitializationFailed(self.name.into())),
+                }
+            })
+        }
+
+        fn stop(&self) -> LifecycleFuture<'_> {
+            self.log.lock().unwrap().push(format!("stop {}", self.name));
+            Box::pin(async { Ok(()) })
+        }
+
+        fn health(&self) -> crate::error::ProviderResult<()> {
+            match self.healthy {
+                true => Ok(()),
+                false => Err(ProviderError::ExecutionFailed(self.name.into())),
+            }
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl Formatter for Tracked {}
+    impl Reporter for Tracked {}
+
+    #[tokio::test]
+    async fn test_lifecycle_across_registries() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut formatters: Registry<dyn Formatter> = Registry::new();
+        formatters.register(Tracked::new("plain", &log));
+        formatters.register(Tracked::new("color", &log));
+        let mut reporters: Registry<dyn Reporter> = Registry::new();
+        reporters.register(Tracked::new("json", &log));
+
+        let module = ModuleRegistry::new()
+            .with("formatters", formatters)
+            .with("reporters", reporters);
+        assert_eq!(module.provider_count(), 3);
+
+        module.start_all().await.unwrap();
+        module.stop_all().await.unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "start plain",
+                "start color",
+                "start json",
+                "stop json",
+                "stop color",
+                "stop plain"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_labelled() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut sick = Tracked::new("sick", &log);
+        sick.healthy = false;
+        let mut module = ModuleRegistry::new()
+            .with("formatters", Registry::<dyn Formatter>::new())
+            .with("reporters", Registry::<dyn Reporter>::new());
+        module.get_mut::<dyn Reporter>().unwrap().register(sick);
+        module
+            .get_mut::<dyn Formatter>()
+            .unwrap()
+            .register(Tracked::new("plain", &log));
+
+        let err = module.start_all().await.unwrap_err();
+        assert_eq!(err.labels().collect::<Vec<_>>(), vec!["reporters/sick"]);
+        assert_eq!(log.lock().unwrap().len(), 2);
+        let err = module.health().unwrap_err();
+        assert!(err.get("reporters/sick").is_some());
+    }
+
+    #[test]
+    fn test_insert_replaces_by_contract() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut formatters: Registry<dyn Formatter> = Registry::new();
+        formatters.register(Tracked::new("plain", &log));
+
+        let mut module = ModuleRegistry::new().with("formatters", formatters);
+        let old = module.insert("fmt", Registry::<dyn Formatter>::new());
+        assert!(old.unwrap().contains("plain"));
+        assert_eq!(module.labels().collect::<Vec<_>>(), vec!["fmt"]);
+        assert!(module.get::<dyn Reporter>().is_none());
+        assert!(module.get::<dyn Formatter>().unwrap().is_empty());
+    }
+}
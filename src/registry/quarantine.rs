@@ -0,0 +1,151 @@
+//! Excluding failing providers from selection for a while.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use crate::provider::Provider;
+
+/// When [`Registry`](super::Registry) quarantines a provider, set with
+/// [`Registry::with_quarantine`](super::Registry::with_quarantine).
+///
+/// A provider whose error rate, from its [`stats`](Provider::stats), goes
+/// over the threshold is skipped by the `find` methods for the cool-down
+/// period. The next lookup after that probes its
+/// [health](Provider::health): a healthy provider is re-admitted, an
+/// unhealthy one serves another cool-down. The error rate only counts calls
+/// made since the provider was last admitted, and providers without stats
+/// are never quarantined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuarantinePolicy {
+    max_error_rate: f64,
+    min_calls: u64,
+    cool_down: Duration,
+}
+
+impl QuarantinePolicy {
+    /// Quarantine providers failing more than `max_error_rate` of their
+    /// calls, between 0.0 and 1.0, for 30 seconds at a time.
+    pub fn new(max_error_rate: f64) -> Self {
+        Self {
+            max_error_rate,
+            min_calls: 10,
+            cool_down: Duration::from_secs(30),
+        }
+    }
+
+    /// Judge the error rate only after `calls` calls. Defaults to 10.
+    pub fn min_calls(mut self, calls: u64) -> Self {
+        self.min_calls = calls;
+        self
+    }
+
+    /// Keep quarantined providers out for `cool_down` before probing them.
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+}
+
+/// Quarantine state of every provider of a registry.
+#[derive(Debug)]
+pub(super) struct Quarantine {
+    policy: QuarantinePolicy,
+    providers: Mutex<HashMap<Arc<str>, Standing>>,
+}
+
+#[derive(Debug, Default)]
+struct Standing {
+    /// Calls and errors when the provider was last admitted
+    baseline: (u64, u64),
+    /// End of the current cool-down
+    until: Option<Instant>,
+}
+
+impl Quarantine {
+    pub(super) fn new(policy: QuarantinePolicy) -> Self {
+        Self {
+            policy,
+            providers: Mutex::default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Arc<str>, Standing>> {
+        self.providers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether `provider` may be selected, quarantining or re-admitting it
+    /// as its stats and health require.
+    pub(super) fn admits<P: Provider + ?Sized>(&self, name: &Arc<str>, provider: &P) -> bool {
+        let Some(stats) = provider.stats() else {
+            return true;
+        };
+        let (calls, errors) = (stats.calls(), stats.errors());
+        let now = Instant::now();
+        let mut providers = self.lock();
+        let standing = providers.entry(Arc::clone(name)).or_default();
+        if let Some(until) = standing.until {
+            if now < until {
+                return false;
+            }
+            return match provider.health() {
+                Ok(()) => {
+                    tracing::info!(provider = %name, "re-admitting provider from quarantine");
+                    *standing = Standing {
+                        baseline: (calls, errors),
+                        until: None,
+                    };
+                    true
+                }
+                Err(err) => {
+                    tracing::warn!(provider = %name, error = %err, "provider failed quarantine probe");
+                    standing.until = Some(now + self.policy.cool_down);
+                    false
+                }
+            };
+        }
+
+        let calls = calls.saturating_sub(standing.baseline.0);
+        let errors = errors.saturating_sub(standing.baseline.1);
+        if calls < self.policy.min_calls.max(1)
+            || errors as f64 / calls as f64 <= self.policy.max_error_rate
+        {
+            return true;
+        }
+        tracing::warn!(provider = %name, calls, errors, "quarantining provider");
+        standing.until = Some(now + self.policy.cool_down);
+        false
+    }
+
+    /// Whether the provider called `name` is serving a cool-down.
+    pub(super) fn is_quarantined(&self, name: &str) -> bool {
+        self.lock()
+            .get(name)
+            .and_then(|standing| standing.until)
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Re-admit `provider` now, forgetting its errors so far.
+    pub(super) fn release<P: Provider + ?Sized>(&self, name: &Arc<str>, provider: &P) {
+        let baseline = provider
+            .stats()
+            .map_or((0, 0), |stats| (stats.calls(), stats.errors()));
+        self.lock().insert(
+            Arc::clone(name),
+            Standing {
+                baseline,
+                until: None,
+            },
+        );
+    }
+
+    /// Forget the provider called `name`, e.g. once it is removed.
+    pub(super) fn forget(&self, name: &str) {
+        self.lock().remove(name);
+    }
+
+    /// Forget every provider, e.g. once the registry is cleared.
+    pub(super) fn clear(&self) {
+        self.lock().clear();
+    }
+}
@@ -0,0 +1,56 @@
+//! Tie-breaking between providers of equal priority.
+
+use std::fmt;
+
+use crate::error::{RegistryError, RegistryResult};
+
+/// Decides which provider [`Registry::find_best`](super::Registry::find_best)
+/// returns when several share the highest priority.
+///
+/// Implemented for closures taking the lookup key and the tied provider
+/// names, and by the built-in [`TieBreak`] policies.
+pub trait SelectionPolicy: Send + Sync {
+    /// Pick one of `tied`, the names of two or more providers supporting
+    /// `key` at the highest priority, in registration order.
+    ///
+    /// Returns an index into `tied`; refusing to pick should be
+    /// [`RegistryError::AmbiguousMatch`].
+    fn select(&self, key: &str, tied: &[&str]) -> RegistryResult<usize>;
+}
+
+impl<F> SelectionPolicy for F
+where
+    F: Fn(&str, &[&str]) -> RegistryResult<usize> + Send + Sync,
+{
+    fn select(&self, key: &str, tied: &[&str]) -> RegistryResult<usize> {
+        self(key, tied)
+    }
+}
+
+impl fmt::Debug for dyn SelectionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SelectionPolicy")
+    }
+}
+
+/// The built-in [`SelectionPolicy`] implementations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Pick the provider registered first
+    FirstRegistered,
+    /// Pick the provider registered last; the default
+    #[default]
+    LastRegistered,
+    /// Refuse to pick, failing with [`RegistryError::AmbiguousMatch`]
+    Reject,
+}
+
+impl SelectionPolicy for TieBreak {
+    fn select(&self, _key: &str, tied: &[&str]) -> RegistryResult<usize> {
+        match self {
+            TieBreak::FirstRegistered => Ok(0),
+            TieBreak::LastRegistered => Ok(tied.len() - 1),
+            TieBreak::Reject => Err(RegistryError::AmbiguousMatch(tied.join(", "))),
+        }
+    }
+}
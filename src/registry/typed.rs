@@ -0,0 +1,211 @@
+//! A registry holding one provider per SPI contract.
+
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::provider::Provider;
+
+/// A registry that stores exactly one provider per SPI trait or type.
+///
+/// Where [`Registry`](super::Registry) keys many providers of one contract by
+/// name, a `TypedRegistry` keys providers by the contract itself: inserting a
+/// `Box<dyn FormatterProvider>` replaces the previous formatter, and
+/// `get::<dyn FormatterProvider>()` returns it. Use it for modules where one
+/// implementation per contract is the norm.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{Provider, TypedRegistry};
+/// use std::any::Any;
+///
+/// trait FormatterProvider: Provider {
+///     fn format(&self, input: &str) -> String;
+/// }
+///
+/// #[derive(Debug)]
+/// struct Upper;
+///
+/// impl Provider for Upper {
+///     fn name(&self) -> &str { "upper" }
+///     fn as_any(&self) -> &dyn Any { self }
+/// }
+///
+/// impl FormatterProvider for Upper {
+///     fn format(&self, input: &str) -> String { input.to_uppercase() }
+/// }
+///
+/// let mut registry = TypedRegistry::new();
+/// registry.insert::<dyn FormatterProvider>(Box::new(Upper));
+///
+/// let formatter = registry.get::<dyn FormatterProvider>().unwrap();
+/// assert_eq!(formatter.format("sea"), "SEA");
+/// ```
+#[derive(Default)]
+pub struct TypedRegistry {
+    entries: HashMap<TypeId, Slot>,
+}
+
+struct Slot {
+    /// A `Box<P>` for the contract `P` the slot is keyed by
+    provider: Box<dyn Any + Send + Sync>,
+    contract: &'static str,
+    name: fn(&(dyn Any + Send + Sync)) -> &str,
+}
+
+impl TypedRegistry {
+    /// Create a new empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `provider` as the implementation of `P`.
+    ///
+    /// Returns the provider it replaces, if any.
+    pub fn insert<P: Provider + ?Sized + 'static>(&mut self, provider: Box<P>) -> Option<Box<P>> {
+        let slot = Slot {
+            provider: Box::new(provider),
+            contract: type_name::<P>(),
+            name: |provider| downcast::<P>(provider).name(),
+        };
+        self.entries
+            .insert(TypeId::of::<P>(), slot)
+            .map(|old| unbox::<P>(old.provider))
+    }
+
+    /// Get the implementation of `P`.
+    pub fn get<P: Provider + ?Sized + 'static>(&self) -> Option<&P> {
+        self.entries
+            .get(&TypeId::of::<P>())
+            .map(|slot| downcast::<P>(slot.provider.as_ref()))
+    }
+
+    /// Get the implementation of `P` mutably.
+    pub fn get_mut<P: Provider + ?Sized + 'static>(&mut self) -> Option<&mut P> {
+        self.entries.get_mut(&TypeId::of::<P>()).map(|slot| {
+            slot.provider
+                .downcast_mut::<Box<P>>()
+                .expect("slot holds its contract type")
+                .as_mut()
+        })
+    }
+
+    /// Check if an implementation of `P` is stored.
+    pub fn contains<P: Provider + ?Sized + 'static>(&self) -> bool {
+        self.entries.contains_key(&TypeId::of::<P>())
+    }
+
+    /// Remove the implementation of `P`.
+    pub fn remove<P: Provider + ?Sized + 'static>(&mut self) -> Option<Box<P>> {
+        self.entries
+            .remove(&TypeId::of::<P>())
+            .map(|slot| unbox::<P>(slot.provider))
+    }
+
+    /// Get the number of stored providers.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the registry is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Clear all providers from the registry.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn downcast<P: ?Sized + 'static>(provider: &(dyn Any + Send + Sync)) -> &P {
+    provider
+        .downcast_ref::<Box<P>>()
+        .expect("slot holds its contract type")
+}
+
+fn unbox<P: ?Sized + 'static>(provider: Box<dyn Any + Send + Sync>) -> Box<P> {
+    *provider
+        .downcast::<Box<P>>()
+        .unwrap_or_else(|_| unreachable!("slot holds its contract type"))
+}
+
+impl fmt::Debug for TypedRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.entries
+                    .values()
+                    .map(|slot| (slot.contract, (slot.name)(slot.provider.as_ref()))),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    trait Formatter: Provider {
+        fn format(&self, input: &str) -> String;
+    }
+
+    trait Reporter: Provider {}
+
+    #[derive(Debug)]
+    struct Upper(&'static str);
+
+    impl Provider for Upper {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    impl Formatter for Upper {
+        fn format(&self, input: &str) -> String {
+            input.to_uppercase()
+        }
+    }
+
+    impl Reporter for Upper {}
+
+    #[test]
+    fn test_one_provider_per_contract() {
+        let mut registry = TypedRegistry::new();
+        assert!(registry
+            .insert::<dyn Formatter>(Box::new(Upper("first")))
+            .is_none());
+        registry.insert::<dyn Reporter>(Box::new(Upper("reporter")));
+
+        let replaced = registry.insert::<dyn Formatter>(Box::new(Upper("second")));
+        assert_eq!(replaced.unwrap().name(), "first");
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get::<dyn Formatter>().unwrap().name(), "second");
+        assert_eq!(registry.get::<dyn Formatter>().unwrap().format("a"), "A");
+        assert_eq!(registry.get::<dyn Reporter>().unwrap().name(), "reporter");
+        assert!(registry.get::<Upper>().is_none());
+    }
+
+    #[test]
+    fn test_concrete_types_and_removal() {
+        let mut registry = TypedRegistry::new();
+        registry.insert(Box::new(Upper("concrete")));
+        assert!(registry.contains::<Upper>());
+        assert!(!registry.contains::<dyn Provider>());
+
+        registry.get_mut::<Upper>().unwrap().0 = "renamed";
+        assert_eq!(
+            format!("{registry:?}"),
+            format!(r#"{{"{}": "renamed"}}"#, type_name::<Upper>())
+        );
+
+        assert_eq!(registry.remove::<Upper>().unwrap().name(), "renamed");
+        assert!(registry.remove::<Upper>().is_none());
+        assert!(registry.is_empty());
+    }
+}
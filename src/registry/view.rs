@@ -0,0 +1,263 @@
+//! Read-only access to a registry.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::path::Path;
+
+use super::{BalanceStrategy, Registry, RegistryManifest};
+use crate::context::Context;
+use crate::error::{MultiError, ProviderResult, RegistryResult};
+use crate::provider::Provider;
+use crate::stats::StatsReport;
+
+/// A borrowed, lookup-only view of a [`Registry`], from
+/// [`Registry::view`].
+///
+/// A view offers the lookup methods of the registry but none that change
+/// it: providers cannot be registered or removed, and neither the
+/// lifecycle methods such as [`start_all`](Registry::start_all) nor
+/// [`release`](Registry::release), which take `&self` on the registry, are
+/// reachable. Hand a view to API implementations that should find
+/// providers but not manage them. Views are `Copy`.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{Provider, Registry, RegistryView};
+/// use std::any::Any;
+///
+/// #[derive(Debug)]
+/// struct Rust;
+///
+/// impl Provider for Rust {
+///     fn name(&self) -> &str { "rust" }
+///     fn extensions(&self) -> &[&str] { &[".rs"] }
+///     fn as_any(&self) -> &dyn Any { self }
+/// }
+///
+/// fn language_of(registry: RegistryView<'_, dyn Provider>, path: &str) -> Option<String> {
+///     registry.find(path).map(|p| p.name().to_string())
+/// }
+///
+/// let mut registry: Registry<dyn Provider> = Registry::new();
+/// registry.register(Box::new(Rust));
+///
+/// assert_eq!(language_of(registry.view(), "main.rs").as_deref(), Some("rust"));
+/// ```
+pub struct RegistryView<'a, P: ?Sized, S = RandomState> {
+    registry: &'a Registry<P, S>,
+}
+
+impl<P: ?Sized, S> Clone for RegistryView<'_, P, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: ?Sized, S> Copy for RegistryView<'_, P, S> {}
+
+impl<P: ?Sized, S> fmt::Debug for RegistryView<'_, P, S>
+where
+    Registry<P, S>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegistryView")
+            .field("registry", self.registry)
+            .finish()
+    }
+}
+
+impl<'a, P: ?Sized, S> From<&'a Registry<P, S>> for RegistryView<'a, P, S> {
+    fn from(registry: &'a Registry<P, S>) -> Self {
+        Self { registry }
+    }
+}
+
+impl<'a, P: Provider + ?Sized, S: BuildHasher> RegistryView<'a, P, S> {
+    /// See [`Registry::get`].
+    pub fn get(&self, name: &str) -> Option<&'a P> {
+        self.registry.get(name)
+    }
+
+    /// See [`Registry::find`].
+    pub fn find(&self, key: &str) -> Option<&'a P> {
+        self.registry.find(key)
+    }
+
+    /// See [`Registry::find_by_path`].
+    pub fn find_by_path(&self, path: &Path) -> Option<&'a P> {
+        self.registry.find_by_path(path)
+    }
+
+    /// See [`Registry::find_best`].
+    pub fn find_best(&self, key: &str) -> Option<&'a P> {
+        self.registry.find_best(key)
+    }
+
+    /// See [`Registry::try_find_best`].
+    pub fn try_find_best(&self, key: &str) -> RegistryResult<&'a P> {
+        self.registry.try_find_best(key)
+    }
+
+    /// See [`Registry::find_balanced`].
+    pub fn find_balanced(&self, key: &str, strategy: BalanceStrategy) -> Option<&'a P> {
+        self.registry.find_balanced(key, strategy)
+    }
+
+    /// See [`Registry::find_all`].
+    pub fn find_all(&self, key: &str) -> Vec<&'a P> {
+        self.registry.find_all(key)
+    }
+
+    /// See [`Registry::find_all_iter`].
+    pub fn find_all_iter(&self, key: &'a str) -> impl Iterator<Item = &'a P> + 'a {
+        self.registry.find_all_iter(key)
+    }
+
+    /// See [`Registry::find_by_extension`].
+    pub fn find_by_extension(&self, extension: &str) -> Option<&'a P> {
+        self.registry.find_by_extension(extension)
+    }
+
+    /// See [`Registry::find_by_mime`].
+    pub fn find_by_mime(&self, mime: &str) -> Option<&'a P> {
+        self.registry.find_by_mime(mime)
+    }
+
+    /// See [`Registry::find_by_scheme`].
+    pub fn find_by_scheme(&self, uri: &str) -> Option<&'a P> {
+        self.registry.find_by_scheme(uri)
+    }
+
+    /// See [`Registry::is_enabled`].
+    pub fn is_enabled(&self, name: &str, cx: &Context) -> bool {
+        self.registry.is_enabled(name, cx)
+    }
+
+    /// See [`Registry::get_enabled`].
+    pub fn get_enabled(&self, name: &str, cx: &Context) -> Option<&'a P> {
+        self.registry.get_enabled(name, cx)
+    }
+
+    /// See [`Registry::find_enabled`].
+    pub fn find_enabled(&self, key: &str, cx: &Context) -> Option<&'a P> {
+        self.registry.find_enabled(key, cx)
+    }
+
+    /// See [`Registry::find_best_enabled`].
+    pub fn find_best_enabled(&self, key: &str, cx: &Context) -> Option<&'a P> {
+        self.registry.find_best_enabled(key, cx)
+    }
+
+    /// See [`Registry::authorize`].
+    pub fn authorize(&self, provider: &P, cx: &Context) -> ProviderResult<()> {
+        self.registry.authorize(provider, cx)
+    }
+
+    /// See [`Registry::get_authorized`].
+    pub fn get_authorized(&self, name: &str, cx: &Context) -> ProviderResult<&'a P> {
+        self.registry.get_authorized(name, cx)
+    }
+
+    /// See [`Registry::contains`].
+    pub fn contains(&self, name: &str) -> bool {
+        self.registry.contains(name)
+    }
+
+    /// See [`Registry::names`].
+    pub fn names(&self) -> Vec<&'a str> {
+        self.registry.names()
+    }
+
+    /// See [`Registry::providers`].
+    pub fn providers(&self) -> Vec<&'a P> {
+        self.registry.providers()
+    }
+
+    /// See [`Registry::len`].
+    pub fn len(&self) -> usize {
+        self.registry.len()
+    }
+
+    /// See [`Registry::is_empty`].
+    pub fn is_empty(&self) -> bool {
+        self.registry.is_empty()
+    }
+
+    /// See [`Registry::iter`].
+    pub fn iter(&self) -> impl Iterator<Item = &'a P> + 'a {
+        self.registry.iter()
+    }
+
+    /// See [`Registry::manifest`].
+    pub fn manifest(&self) -> RegistryManifest {
+        self.registry.manifest()
+    }
+
+    /// See [`Registry::stats_report`].
+    pub fn stats_report(&self) -> StatsReport {
+        self.registry.stats_report()
+    }
+
+    /// See [`Registry::is_ready`].
+    pub fn is_ready(&self, name: &str) -> bool {
+        self.registry.is_ready(name)
+    }
+
+    /// See [`Registry::is_quarantined`].
+    pub fn is_quarantined(&self, name: &str) -> bool {
+        self.registry.is_quarantined(name)
+    }
+
+    /// See [`Registry::health`].
+    pub fn health(&self) -> Result<(), MultiError> {
+        self.registry.health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::any::Any;
+
+    #[derive(Debug)]
+    struct Lang(&'static str, &'static [&'static str]);
+
+    impl Provider for Lang {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn extensions(&self) -> &[&str] {
+            self.1
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn test_view_looks_up_providers() {
+        let mut registry: Registry<dyn Provider> = Registry::new();
+        registry.register(Box::new(Lang("rust", &[".rs"])));
+        registry.register(Box::new(Lang("python", &[".py"])));
+
+        let view = registry.view();
+        let copy = view;
+        assert_eq!(view.len(), 2);
+        assert_eq!(copy.names(), ["rust", "python"]);
+        assert_eq!(view.find("main.py").unwrap().name(), "python");
+        assert_eq!(view.find_by_extension(".RS").unwrap().name(), "rust");
+        assert!(view.get("go").is_none());
+        assert_eq!(view.manifest(), registry.manifest());
+
+        // Providers found through the view outlive it
+        let rust = {
+            let view = RegistryView::from(&registry);
+            view.get("rust").unwrap()
+        };
+        assert_eq!(rust.name(), "rust");
+    }
+}
@@ -0,0 +1,405 @@
+//! Generator for new SEA modules.
+//!
+//! [`generate`] writes a workspace with the five SEA crates for a module
+//! named e.g. `file-processor`:
+//!
+//! ```text
+//! file-processor/
+//! ├── Cargo.toml               workspace
+//! ├── file-processor-common/   L1: error, config, DTOs
+//! ├── file-processor-spi/      L2: provider trait
+//! ├── file-processor-api/      L3: service trait
+//! ├── file-processor-core/     L4: registry-backed service, example provider
+//! └── file-processor/          L5: facade
+//! ```
+//!
+//! The generated workspace builds as is and passes
+//! [`arch::validate`](crate::arch::validate). The same generator runs as
+//! `cargo rustratify new <module>`.
+//!
+//! Requires the `arch` feature.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const WORKSPACE: &str = r#"[workspace]
+resolver = "2"
+members = [
+    "{{name}}-common",
+    "{{name}}-spi",
+    "{{name}}-api",
+    "{{name}}-core",
+    "{{name}}",
+]
+
+[workspace.dependencies]
+rustratify = "{{version}}"
+tokio = { version = "1", features = ["macros", "rt-multi-thread"] }
+{{name}}-common = { path = "{{name}}-common" }
+{{name}}-spi = { path = "{{name}}-spi" }
+{{name}}-api = { path = "{{name}}-api" }
+{{name}}-core = { path = "{{name}}-core" }
+"#;
+
+const COMMON_MANIFEST: &str = r#"[package]
+name = "{{name}}-common"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+rustratify.workspace = true
+"#;
+
+const COMMON: &str = r#"//! L1 Common: errors, configuration, and DTOs for {{name}}.
+
+use std::fmt;
+
+use rustratify::{Config, ProviderError};
+
+/// Errors returned by {{name}}.
+#[derive(Debug)]
+pub enum {{Type}}Error {
+    /// No provider handles the input
+    NoProvider(String),
+    /// A provider failed
+    Provider(ProviderError),
+}
+
+impl fmt::Display for {{Type}}Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoProvider(input) => write!(f, "no provider for: {input}"),
+            Self::Provider(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for {{Type}}Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Provider(err) => Some(err),
+            Self::NoProvider(_) => None,
+        }
+    }
+}
+
+impl From<ProviderError> for {{Type}}Error {
+    fn from(err: ProviderError) -> Self {
+        Self::Provider(err)
+    }
+}
+
+/// Result type for {{name}} operations.
+pub type {{Type}}Result<T> = Result<T, {{Type}}Error>;
+
+/// Configuration for {{name}}.
+#[derive(Debug, Clone, Default)]
+pub struct {{Type}}Config {
+    /// Log each processed input
+    pub verbose: bool,
+}
+
+impl Config for {{Type}}Config {
+    fn name(&self) -> &str {
+        "{{name}}"
+    }
+
+    fn is_verbose(&self) -> bool {
+        self.verbose
+    }
+}
+
+/// The result of processing one input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct {{Type}}Output {
+    /// The provider that handled the input
+    pub provider: String,
+    /// The processed value
+    pub value: String,
+}
+"#;
+
+const SPI_MANIFEST: &str = r#"[package]
+name = "{{name}}-spi"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+rustratify.workspace = true
+{{name}}-common.workspace = true
+"#;
+
+const SPI: &str = r#"//! L2 SPI: the extension point for {{name}} providers.
+
+use rustratify::{async_trait, Provider};
+use {{crate}}_common::{{{Type}}Output, {{Type}}Result};
+
+/// A provider that processes inputs it [`supports`](Provider::supports).
+#[async_trait]
+pub trait {{Type}}Provider: Provider {
+    /// Process `input`.
+    async fn process(&self, input: &str) -> {{Type}}Result<{{Type}}Output>;
+}
+"#;
+
+const API_MANIFEST: &str = r#"[package]
+name = "{{name}}-api"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+rustratify.workspace = true
+{{name}}-common.workspace = true
+"#;
+
+const API: &str = r#"//! L3 API: the contract {{name}} offers its consumers.
+
+use rustratify::async_trait;
+use {{crate}}_common::{{{Type}}Config, {{Type}}Output, {{Type}}Result};
+
+/// The {{name}} service.
+#[async_trait]
+pub trait {{Type}}Service: Send + Sync {
+    /// Process `input` with the provider that supports it.
+    async fn process(&self, input: &str, config: &{{Type}}Config) -> {{Type}}Result<{{Type}}Output>;
+}
+"#;
+
+const CORE_MANIFEST: &str = r#"[package]
+name = "{{name}}-core"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+rustratify.workspace = true
+{{name}}-common.workspace = true
+{{name}}-spi.workspace = true
+{{name}}-api.workspace = true
+"#;
+
+const CORE: &str = r#"//! L4 Core: the {{name}} implementation.
+
+use std::any::Any;
+
+use rustratify::{async_trait, Config, Provider, Registry};
+use {{crate}}_api::{{Type}}Service;
+use {{crate}}_common::{{{Type}}Config, {{Type}}Error, {{Type}}Output, {{Type}}Result};
+use {{crate}}_spi::{{Type}}Provider;
+
+/// Registry of {{name}} providers.
+pub type {{Type}}Registry = Registry<dyn {{Type}}Provider>;
+
+/// Example provider that upper-cases `.txt` inputs. Replace with real ones.
+#[derive(Debug, Default)]
+pub struct UppercaseProvider;
+
+impl Provider for UppercaseProvider {
+    fn name(&self) -> &str {
+        "uppercase"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[".txt"]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[async_trait]
+impl {{Type}}Provider for UppercaseProvider {
+    async fn process(&self, input: &str) -> {{Type}}Result<{{Type}}Output> {
+        Ok({{Type}}Output {
+            provider: self.name().to_string(),
+            value: input.to_uppercase(),
+        })
+    }
+}
+
+/// A registry with the built-in providers.
+pub fn default_registry() -> {{Type}}Registry {
+    let mut registry = {{Type}}Registry::new();
+    registry.register(Box::new(UppercaseProvider));
+    registry
+}
+
+/// [`{{Type}}Service`] that dispatches to registered providers.
+pub struct Default{{Type}}Service {
+    registry: {{Type}}Registry,
+}
+
+impl Default{{Type}}Service {
+    /// Create a service over `registry`.
+    pub fn new(registry: {{Type}}Registry) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl {{Type}}Service for Default{{Type}}Service {
+    async fn process(&self, input: &str, config: &{{Type}}Config) -> {{Type}}Result<{{Type}}Output> {
+        let provider = self
+            .registry
+            .find(input)
+            .ok_or_else(|| {{Type}}Error::NoProvider(input.to_string()))?;
+        let output = provider.process(input).await?;
+        if config.is_verbose() {
+            println!("{} processed {input}", output.provider);
+        }
+        Ok(output)
+    }
+}
+"#;
+
+const FACADE_MANIFEST: &str = r#"[package]
+name = "{{name}}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+{{name}}-common.workspace = true
+{{name}}-spi.workspace = true
+{{name}}-api.workspace = true
+{{name}}-core.workspace = true
+
+[dev-dependencies]
+tokio.workspace = true
+"#;
+
+const FACADE: &str = r#"//! {{name}}: the public entry point.
+//!
+//! ```rust
+//! use {{crate}}::{{{Type}}Config, {{Type}}Service};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = {{crate}}::create();
+//! let output = service.process("notes.txt", &{{Type}}Config::default()).await.unwrap();
+//! assert_eq!(output.value, "NOTES.TXT");
+//! # }
+//! ```
+
+pub use {{crate}}_api::{{Type}}Service;
+pub use {{crate}}_common::{{{Type}}Config, {{Type}}Error, {{Type}}Output, {{Type}}Result};
+pub use {{crate}}_core::{{Type}}Registry;
+pub use {{crate}}_spi::{{Type}}Provider;
+
+use {{crate}}_core::{default_registry, Default{{Type}}Service};
+
+/// Create the service with the built-in providers.
+pub fn create() -> impl {{Type}}Service {
+    Default{{Type}}Service::new(default_registry())
+}
+
+/// Create the service with a custom registry.
+pub fn create_with_registry(registry: {{Type}}Registry) -> impl {{Type}}Service {
+    Default{{Type}}Service::new(registry)
+}
+"#;
+
+/// The files of a module named `name`, as paths relative to the module
+/// directory and their contents.
+///
+/// Fails if `name` is not a valid crate name: lowercase ASCII letters,
+/// digits, `-`, and `_`, starting with a letter.
+pub fn files(name: &str) -> Result<Vec<(PathBuf, String)>, String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!(
+            "invalid module name `{name}`: use lowercase letters, digits, `-`, and `_`, \
+             starting with a letter"
+        ));
+    }
+    let type_name: String = name
+        .split(['-', '_'])
+        .filter(|part| !part.is_empty())
+        .map(|part| part[..1].to_ascii_uppercase() + &part[1..])
+        .collect();
+    let render = |template: &str| {
+        template
+            .replace("{{name}}", name)
+            .replace("{{crate}}", &name.replace('-', "_"))
+            .replace("{{Type}}", &type_name)
+            .replace("{{version}}", env!("CARGO_PKG_VERSION"))
+    };
+
+    let mut files = vec![(PathBuf::from("Cargo.toml"), render(WORKSPACE))];
+    let crates = [
+        ("-common", COMMON_MANIFEST, COMMON),
+        ("-spi", SPI_MANIFEST, SPI),
+        ("-api", API_MANIFEST, API),
+        ("-core", CORE_MANIFEST, CORE),
+        ("", FACADE_MANIFEST, FACADE),
+    ];
+    for (suffix, manifest, lib) in crates {
+        let dir = PathBuf::from(format!("{name}{suffix}"));
+        files.push((dir.join("Cargo.toml"), render(manifest)));
+        files.push((dir.join("src").join("lib.rs"), render(lib)));
+    }
+    Ok(files)
+}
+
+/// Write a module named `name` into a new directory `parent/name`,
+/// returning the paths written.
+///
+/// Fails if the directory already exists.
+pub fn generate(name: &str, parent: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+    let files = files(name)?;
+    let root = parent.as_ref().join(name);
+    if root.exists() {
+        return Err(format!("{} already exists", root.display()));
+    }
+    let mut written = Vec::new();
+    for (relative, contents) in files {
+        let path = root.join(relative);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+        }
+        fs::write(&path, contents)
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_are_named_after_the_module() {
+        let generated = files("file-processor").unwrap();
+        assert_eq!(generated.len(), 11);
+        let (path, core) = &generated[8];
+        assert_eq!(path, Path::new("file-processor-core/src/lib.rs"));
+        assert!(core.contains("pub struct DefaultFileProcessorService"));
+        assert!(core.contains("use file_processor_spi::FileProcessorProvider;"));
+        assert!(generated.iter().all(|(_, text)| !text.contains("{{")));
+
+        assert!(files("File").is_err());
+        assert!(files("9lives").is_err());
+    }
+
+    #[test]
+    fn test_generated_layout_passes_layer_check() {
+        let parent =
+            std::env::temp_dir().join(format!("rustratify-scaffold-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&parent);
+        let written = generate("billing", &parent).unwrap();
+        assert_eq!(written.len(), 11);
+        assert!(generate("billing", &parent)
+            .unwrap_err()
+            .contains("already exists"));
+
+        let report = crate::arch::validate(parent.join("billing/Cargo.toml")).unwrap();
+        assert!(report.is_ok(), "{report}");
+        let layers: Vec<_> = report.crates.iter().map(|c| c.layer).collect();
+        assert!(layers.iter().all(Option::is_some));
+        fs::remove_dir_all(&parent).unwrap();
+    }
+}
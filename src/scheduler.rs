@@ -0,0 +1,701 @@
+//! Periodic jobs on intervals or cron expressions.
+//!
+//! A [`Scheduler`] owns a set of [`Job`]s, each an async closure run on a
+//! [`Schedule`]. Provider work is scheduled by calling the provider from the
+//! closure. [`OverlapPolicy`] decides what happens when a run is still in
+//! flight at the next tick, and optional jitter spreads out jobs that would
+//! otherwise fire together.
+//!
+//! Every run, skip, and failure is reported as a [`JobEvent`] on the stream
+//! returned by [`Scheduler::events`], and recorded in an [`EventStore`]
+//! under the job name if one is set with [`Scheduler::store`].
+//!
+//! For the simple "run this later" case, a started scheduler also runs
+//! one-off jobs with [`SchedulerHandle::schedule_at`] and
+//! [`SchedulerHandle::schedule_after`], which can be cancelled until they
+//! start.
+//!
+//! Requires the `tokio` feature; cron expressions also require `cron`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use rustratify::scheduler::{Job, JobOutcome, Schedule, Scheduler};
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let mut scheduler = Scheduler::new();
+//! scheduler.add(Job::new("refresh", Schedule::every(Duration::from_millis(10)), || async {
+//!     // e.g. registry.get("cache").unwrap().refresh().await
+//!     Ok(())
+//! }));
+//! let mut events = scheduler.events();
+//! let handle = scheduler.start();
+//!
+//! let event = events.next().await.unwrap();
+//! assert_eq!(event.job, "refresh");
+//! assert!(matches!(event.outcome, JobOutcome::Succeeded));
+//! handle.shutdown();
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use tokio::task::JoinHandle;
+
+use crate::error::{ProviderError, ProviderResult};
+use crate::stream::{EventSender, EventStore, EventStream, StreamBuilder};
+use crate::task::spawn_named;
+
+type JobFuture = Pin<Box<dyn Future<Output = ProviderResult<()>> + Send>>;
+type JobFn = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+/// When a job runs.
+#[derive(Clone)]
+pub enum Schedule {
+    /// Every interval, the first run one interval after start
+    Every(Duration),
+    /// On each time matched by a cron expression, in UTC
+    ///
+    /// Requires the `cron` feature.
+    #[cfg(feature = "cron")]
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    /// Run every `interval`.
+    pub fn every(interval: Duration) -> Self {
+        Self::Every(interval)
+    }
+
+    /// Run on a cron expression with a leading seconds field, e.g.
+    /// `"0 */5 * * * *"` for every five minutes.
+    ///
+    /// Requires the `cron` feature.
+    #[cfg(feature = "cron")]
+    pub fn cron(expression: &str) -> Result<Self, String> {
+        expression
+            .parse::<cron::Schedule>()
+            .map(|schedule| Self::Cron(Box::new(schedule)))
+            .map_err(|err| format!("invalid cron expression `{expression}`: {err}"))
+    }
+
+    /// Time from now until the next run, or `None` if there is none.
+    pub fn next_delay(&self) -> Option<Duration> {
+        match self {
+            Self::Every(interval) => Some(*interval),
+            #[cfg(feature = "cron")]
+            Self::Cron(schedule) => {
+                let now = chrono::Utc::now();
+                let next = schedule.after(&now).next()?;
+                Some((next - now).to_std().unwrap_or_default())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Every(interval) => f.debug_tuple("Every").field(interval).finish(),
+            #[cfg(feature = "cron")]
+            Self::Cron(schedule) => f.debug_tuple("Cron").field(&schedule.to_string()).finish(),
+        }
+    }
+}
+
+/// What to do when a tick arrives while the previous run is still going.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Skip the tick and report [`JobOutcome::Skipped`]
+    #[default]
+    Skip,
+    /// Run one tick at a time, waiting out the schedule after each run ends
+    Queue,
+    /// Start another run alongside the running one
+    Allow,
+}
+
+/// A named async closure and its schedule.
+pub struct Job {
+    name: String,
+    schedule: Schedule,
+    overlap: OverlapPolicy,
+    jitter: Duration,
+    run: JobFn,
+}
+
+impl Job {
+    /// Create a job that calls `f` on `schedule`.
+    pub fn new<F, Fut>(name: impl Into<String>, schedule: Schedule, f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ProviderResult<()>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            schedule,
+            overlap: OverlapPolicy::default(),
+            jitter: Duration::ZERO,
+            run: Arc::new(move || Box::pin(f())),
+        }
+    }
+
+    /// Set the overlap policy. Defaults to [`OverlapPolicy::Skip`].
+    pub fn overlap(mut self, policy: OverlapPolicy) -> Self {
+        self.overlap = policy;
+        self
+    }
+
+    /// Delay each run by a random amount up to `jitter`.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// The job name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The job schedule.
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+}
+
+impl fmt::Debug for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Job")
+            .field("name", &self.name)
+            .field("schedule", &self.schedule)
+            .field("overlap", &self.overlap)
+            .field("jitter", &self.jitter)
+            .finish()
+    }
+}
+
+/// How one tick of a job ended.
+#[derive(Debug, Clone)]
+pub enum JobOutcome {
+    /// The job returned `Ok`
+    Succeeded,
+    /// The job returned an error
+    Failed(ProviderError),
+    /// The tick was skipped because the previous run was still going
+    Skipped,
+    /// A one-off run was cancelled before it started
+    Cancelled,
+}
+
+/// One tick of a job, reported on the scheduler's event stream.
+#[derive(Debug, Clone)]
+pub struct JobEvent {
+    /// Job name
+    pub job: String,
+    /// Tick number for this job, starting at 1
+    pub tick: u64,
+    /// When the tick fired
+    pub started_at: SystemTime,
+    /// How long the run took; zero for skipped and cancelled ticks
+    pub duration: Duration,
+    /// How the tick ended
+    pub outcome: JobOutcome,
+}
+
+/// Runs jobs on their schedules.
+///
+/// Add jobs, take the [`events`](Self::events) stream if wanted, then
+/// [`start`](Self::start). Events are dropped rather than delaying jobs when
+/// the stream is full or nobody reads it.
+pub struct Scheduler {
+    jobs: Vec<Job>,
+    reporter: Reporter,
+    stream: Option<EventStream<JobEvent>>,
+}
+
+impl Scheduler {
+    /// Create a scheduler with no jobs and a 64-event stream buffer.
+    pub fn new() -> Self {
+        Self::with_buffer(64)
+    }
+
+    /// Create a scheduler whose event stream buffers `size` events.
+    pub fn with_buffer(size: usize) -> Self {
+        let (events, stream) = StreamBuilder::new().buffer_size(size).build();
+        Self {
+            jobs: Vec::new(),
+            reporter: Reporter {
+                events,
+                store: None,
+            },
+            stream: Some(stream),
+        }
+    }
+
+    /// Add a job.
+    pub fn add(&mut self, job: Job) -> &mut Self {
+        self.jobs.push(job);
+        self
+    }
+
+    /// The number of jobs.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Whether there are no jobs.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Record every job event in `store`, under the job name as run ID.
+    ///
+    /// Events are appended as they are reported; failures to append are
+    /// logged and do not affect the jobs.
+    pub fn store(&mut self, store: Arc<dyn EventStore<JobEvent>>) -> &mut Self {
+        self.reporter.store = Some(store);
+        self
+    }
+
+    /// The stream of job events.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called more than once.
+    pub fn events(&mut self) -> EventStream<JobEvent> {
+        self.stream.take().expect("scheduler events already taken")
+    }
+
+    /// Start every job on the current tokio runtime.
+    ///
+    /// Jobs stop when the returned handle is shut down or dropped; runs
+    /// already in flight are left to finish.
+    pub fn start(self) -> SchedulerHandle {
+        let tasks = self
+            .jobs
+            .into_iter()
+            .map(|job| {
+                spawn_named(
+                    &format!("rustratify job {}", job.name),
+                    drive(job, self.reporter.clone()),
+                )
+            })
+            .collect();
+        SchedulerHandle {
+            tasks,
+            reporter: self.reporter,
+            one_offs: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("jobs", &self.jobs)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Stops a started [`Scheduler`] when shut down or dropped.
+///
+/// Dropping the handle also cancels one-off runs that have not started.
+pub struct SchedulerHandle {
+    tasks: Vec<JoinHandle<()>>,
+    reporter: Reporter,
+    one_offs: Mutex<Vec<ScheduledRun>>,
+}
+
+impl SchedulerHandle {
+    /// Stop scheduling new runs.
+    pub fn shutdown(self) {}
+
+    /// Run `f` once at `when`, reported under `name` with tick 1.
+    ///
+    /// A time in the past runs it right away.
+    pub fn schedule_at<F, Fut>(
+        &self,
+        name: impl Into<String>,
+        when: SystemTime,
+        f: F,
+    ) -> ScheduledRun
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ProviderResult<()>> + Send + 'static,
+    {
+        let delay = when
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO);
+        self.schedule_after(name, delay, f)
+    }
+
+    /// Run `f` once after `delay`, reported under `name` with tick 1.
+    pub fn schedule_after<F, Fut>(
+        &self,
+        name: impl Into<String>,
+        delay: Duration,
+        f: F,
+    ) -> ScheduledRun
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ProviderResult<()>> + Send + 'static,
+    {
+        let name = name.into();
+        let state = Arc::new(AtomicU8::new(PENDING));
+        let reporter = self.reporter.clone();
+        let run_state = Arc::clone(&state);
+        let run_name = name.clone();
+        let task = spawn_named(&format!("rustratify job {name} once"), async move {
+            tokio::time::sleep(delay).await;
+            if run_state
+                .compare_exchange(PENDING, STARTED, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                let run: JobFuture = Box::pin(f());
+                reporter.report(timed(run_name, 1, run).await).await;
+            }
+        });
+        let scheduled = ScheduledRun {
+            name,
+            state,
+            task: Arc::new(task),
+            reporter: self.reporter.clone(),
+        };
+        let mut one_offs = self.one_offs.lock().unwrap_or_else(|e| e.into_inner());
+        one_offs.retain(|run| !run.task.is_finished());
+        one_offs.push(scheduled.clone());
+        scheduled
+    }
+}
+
+impl fmt::Debug for SchedulerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchedulerHandle")
+            .field("tasks", &self.tasks)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for SchedulerHandle {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        let one_offs = self.one_offs.get_mut().unwrap_or_else(|e| e.into_inner());
+        for run in one_offs.drain(..) {
+            run.cancel();
+        }
+    }
+}
+
+const PENDING: u8 = 0;
+const STARTED: u8 = 1;
+const CANCELLED: u8 = 2;
+
+/// A one-off run from [`SchedulerHandle::schedule_at`] or
+/// [`SchedulerHandle::schedule_after`].
+///
+/// Dropping it leaves the run scheduled.
+#[derive(Clone)]
+pub struct ScheduledRun {
+    name: String,
+    state: Arc<AtomicU8>,
+    task: Arc<JoinHandle<()>>,
+    reporter: Reporter,
+}
+
+impl ScheduledRun {
+    /// The job name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the run has started.
+    pub fn is_started(&self) -> bool {
+        self.state.load(Ordering::Acquire) == STARTED
+    }
+
+    /// Whether the run was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.load(Ordering::Acquire) == CANCELLED
+    }
+
+    /// Cancel the run if it has not started, reporting
+    /// [`JobOutcome::Cancelled`]. Returns whether it was cancelled.
+    ///
+    /// A run that has started is left to finish.
+    pub fn cancel(&self) -> bool {
+        if self
+            .state
+            .compare_exchange(PENDING, CANCELLED, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return false;
+        }
+        self.task.abort();
+        let event = JobEvent {
+            job: self.name.clone(),
+            tick: 1,
+            started_at: SystemTime::now(),
+            duration: Duration::ZERO,
+            outcome: JobOutcome::Cancelled,
+        };
+        // May be called outside the runtime, e.g. when the handle is dropped
+        if tokio::runtime::Handle::try_current().is_ok() {
+            let reporter = self.reporter.clone();
+            let name = format!("rustratify job {} cancelled", self.name);
+            spawn_named(&name, async move { reporter.report(event).await });
+        } else {
+            self.reporter.events.offer(event);
+        }
+        true
+    }
+}
+
+impl fmt::Debug for ScheduledRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScheduledRun")
+            .field("name", &self.name)
+            .field("started", &self.is_started())
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Where job events go: the event stream, and the store if one is set.
+#[derive(Clone)]
+struct Reporter {
+    events: EventSender<JobEvent>,
+    store: Option<Arc<dyn EventStore<JobEvent>>>,
+}
+
+impl Reporter {
+    async fn report(&self, event: JobEvent) {
+        if let Some(store) = &self.store {
+            if let Err(err) = store.append(&event.job, event.clone()).await {
+                tracing::warn!(job = %event.job, tick = event.tick, "failed to store job event: {err}");
+            }
+        }
+        self.events.offer(event);
+    }
+}
+
+async fn drive(job: Job, events: Reporter) {
+    let running = Arc::new(AtomicBool::new(false));
+    let ticks = Arc::new(AtomicU64::new(0));
+    let mut rng = jitter_seed(&job.name);
+    while let Some(delay) = job.schedule.next_delay() {
+        tokio::time::sleep(delay + jitter(&mut rng, job.jitter)).await;
+        let tick = ticks.fetch_add(1, Ordering::Relaxed) + 1;
+        match job.overlap {
+            OverlapPolicy::Queue => {
+                events
+                    .report(timed(job.name.clone(), tick, (job.run)()).await)
+                    .await;
+            }
+            OverlapPolicy::Skip if running.swap(true, Ordering::AcqRel) => {
+                events
+                    .report(JobEvent {
+                        job: job.name.clone(),
+                        tick,
+                        started_at: SystemTime::now(),
+                        duration: Duration::ZERO,
+                        outcome: JobOutcome::Skipped,
+                    })
+                    .await;
+            }
+            policy => {
+                let name = job.name.clone();
+                let run = (job.run)();
+                let events = events.clone();
+                let running = Arc::clone(&running);
+                spawn_named(&format!("rustratify job {name} tick {tick}"), async move {
+                    events.report(timed(name, tick, run).await).await;
+                    if policy == OverlapPolicy::Skip {
+                        running.store(false, Ordering::Release);
+                    }
+                });
+            }
+        }
+    }
+}
+
+async fn timed(job: String, tick: u64, run: JobFuture) -> JobEvent {
+    let started_at = SystemTime::now();
+    let start = tokio::time::Instant::now();
+    let outcome = match run.await {
+        Ok(()) => JobOutcome::Succeeded,
+        Err(err) => {
+            tracing::warn!(job = %job, tick, "scheduled job failed: {err}");
+            JobOutcome::Failed(err)
+        }
+    };
+    JobEvent {
+        job,
+        tick,
+        started_at,
+        duration: start.elapsed(),
+        outcome,
+    }
+}
+
+fn jitter_seed(name: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write(name.as_bytes());
+    hasher.finish() | 1
+}
+
+/// A random duration up to `max`, from an xorshift generator.
+fn jitter(state: &mut u64, max: Duration) -> Duration {
+    if max.is_zero() {
+        return Duration::ZERO;
+    }
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    let nanos = max.as_nanos().min(u128::from(u64::MAX)) as u64;
+    Duration::from_nanos(*state % (nanos + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_interval_job_reports_outcomes() {
+        let mut scheduler = Scheduler::new();
+        let calls = Arc::new(AtomicU64::new(0));
+        let counter = Arc::clone(&calls);
+        scheduler.add(Job::new(
+            "flaky",
+            Schedule::every(Duration::from_secs(1)),
+            move || {
+                let n = counter.fetch_add(1, Ordering::Relaxed);
+                async move {
+                    if n == 1 {
+                        Err(ProviderError::ExecutionFailed("boom".into()))
+                    } else {
+                        Ok(())
+                    }
+                }
+            },
+        ));
+        let mut events = scheduler.events();
+        let handle = scheduler.start();
+
+        let first = events.next().await.unwrap();
+        assert_eq!((first.job.as_str(), first.tick), ("flaky", 1));
+        assert!(matches!(first.outcome, JobOutcome::Succeeded));
+        let second = events.next().await.unwrap();
+        assert!(matches!(second.outcome, JobOutcome::Failed(_)));
+        handle.shutdown();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_skip_policy_skips_overlapping_ticks() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add(
+            Job::new("slow", Schedule::every(Duration::from_secs(1)), || async {
+                tokio::time::sleep(Duration::from_millis(2500)).await;
+                Ok(())
+            })
+            .overlap(OverlapPolicy::Skip),
+        );
+        let mut events = scheduler.events();
+        let _handle = scheduler.start();
+
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            let event = events.next().await.unwrap();
+            seen.push((event.tick, matches!(event.outcome, JobOutcome::Skipped)));
+        }
+        assert_eq!(seen, vec![(2, true), (3, true), (1, false)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_one_off_runs_are_stored_and_cancellable() {
+        let store = crate::stream::MemoryEventStore::new();
+        let mut scheduler = Scheduler::new();
+        scheduler.store(Arc::new(store.clone()));
+        let mut events = scheduler.events();
+        let handle = scheduler.start();
+
+        let later = handle.schedule_after("report", Duration::from_secs(5), || async { Ok(()) });
+        let never = handle.schedule_after("cleanup", Duration::from_secs(1), || async {
+            panic!("cancelled run started")
+        });
+        assert!(never.cancel());
+        assert!(!never.cancel());
+        assert!(never.is_cancelled());
+
+        let event = events.next().await.unwrap();
+        assert_eq!(event.job, "cleanup");
+        assert!(matches!(event.outcome, JobOutcome::Cancelled));
+        let event = events.next().await.unwrap();
+        assert_eq!((event.job.as_str(), event.tick), ("report", 1));
+        assert!(matches!(event.outcome, JobOutcome::Succeeded));
+        assert!(later.is_started());
+        assert!(!later.cancel());
+
+        let stored = store.read("report").await.unwrap();
+        assert!(matches!(
+            stored[..],
+            [JobEvent {
+                outcome: JobOutcome::Succeeded,
+                ..
+            }]
+        ));
+        assert_eq!(store.read("cleanup").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_dropping_handle_cancels_pending_one_offs() {
+        let mut scheduler = Scheduler::new();
+        let mut events = scheduler.events();
+        let handle = scheduler.start();
+        let run = handle.schedule_at(
+            "nightly",
+            SystemTime::now() + Duration::from_secs(3600),
+            || async { Ok(()) },
+        );
+        drop(handle);
+
+        assert!(run.is_cancelled());
+        let event = events.next().await.unwrap();
+        assert!(matches!(event.outcome, JobOutcome::Cancelled));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bound() {
+        let mut state = jitter_seed("job");
+        let max = Duration::from_millis(50);
+        for _ in 0..1000 {
+            assert!(jitter(&mut state, max) <= max);
+        }
+        assert_eq!(jitter(&mut state, Duration::ZERO), Duration::ZERO);
+    }
+
+    #[cfg(feature = "cron")]
+    #[test]
+    fn test_cron_schedule() {
+        let schedule = Schedule::cron("*/5 * * * * *").unwrap();
+        assert!(schedule.next_delay().unwrap() <= Duration::from_secs(5));
+        assert!(Schedule::cron("not cron").is_err());
+    }
+}
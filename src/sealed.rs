@@ -0,0 +1,149 @@
+//! Sealing SPI traits against implementations outside their crate.
+
+/// Declare a trait that other crates can call but not implement.
+///
+/// Generates a private module holding a `Sealed` trait, makes it a
+/// supertrait of the declared trait, and implements it for the listed
+/// types, which are then the only types outside the module that can
+/// implement the trait. Use it for traits that cross a SEA layer boundary
+/// for calling, such as internal SPIs behind an L3 API, but whose
+/// implementations must stay in the module.
+///
+/// The first line names the private module and lists the implementing
+/// types; more can be added in the same module with
+/// `impl <module>::Sealed for Type {}`. The trait can have supertraits but
+/// no generics or `where` clause.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::{sealed_spi, Provider};
+/// use std::any::Any;
+///
+/// #[derive(Debug)]
+/// pub struct Cargo;
+///
+/// impl Provider for Cargo {
+///     fn name(&self) -> &str { "cargo" }
+///     fn as_any(&self) -> &dyn Any { self }
+/// }
+///
+/// sealed_spi! {
+///     mod sealed for Cargo;
+///
+///     /// Builds a project.
+///     pub trait Builder: Provider {
+///         fn build(&self) -> String;
+///     }
+/// }
+///
+/// impl Builder for Cargo {
+///     fn build(&self) -> String { "cargo build".into() }
+/// }
+///
+/// let builder: &dyn Builder = &Cargo;
+/// assert_eq!(builder.build(), "cargo build");
+/// ```
+///
+/// Implementing the trait for any other type fails to compile:
+///
+/// ```rust,compile_fail
+/// # use rustratify::sealed_spi;
+/// sealed_spi! {
+///     mod sealed for ();
+///
+///     pub trait Builder {
+///         fn build(&self) -> String;
+///     }
+/// }
+///
+/// struct Make;
+///
+/// impl Builder for Make {
+///     fn build(&self) -> String { "make".into() }
+/// }
+/// ```
+#[macro_export]
+macro_rules! sealed_spi {
+    (@bounds [$($head:tt)*] $seal:ident [] { $($body:tt)* }) => {
+        $($head)*: $seal::Sealed { $($body)* }
+    };
+    (@bounds $head:tt $seal:ident [] : $($rest:tt)*) => {
+        $crate::sealed_spi!(@more $head $seal [] $($rest)*);
+    };
+    (@more [$($head:tt)*] $seal:ident [$($bound:tt)+] { $($body:tt)* }) => {
+        $($head)*: $($bound)+ + $seal::Sealed { $($body)* }
+    };
+    (@more $head:tt $seal:ident [$($bound:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::sealed_spi!(@more $head $seal [$($bound)* $next] $($rest)*);
+    };
+    (
+        mod $seal:ident for $($ty:ty),+ $(,)?;
+        $(#[$meta:meta])*
+        $vis:vis trait $name:ident $($rest:tt)*
+    ) => {
+        mod $seal {
+            pub trait Sealed {}
+        }
+
+        $(impl $seal::Sealed for $ty {})+
+
+        $crate::sealed_spi!(@bounds [$(#[$meta])* $vis trait $name] $seal [] $($rest)*);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::provider::Provider;
+    use std::any::Any;
+
+    #[derive(Debug)]
+    struct Cargo;
+
+    impl Provider for Cargo {
+        fn name(&self) -> &str {
+            "cargo"
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    crate::sealed_spi! {
+        mod sealed for Cargo;
+
+        /// Builds a project.
+        pub trait Builder: Provider + Send {
+            fn build(&self) -> String;
+        }
+    }
+
+    crate::sealed_spi! {
+        mod unbounded_seal for u8;
+
+        trait Unbounded {
+            fn value(&self) -> u8;
+        }
+    }
+
+    impl Builder for Cargo {
+        fn build(&self) -> String {
+            "cargo build".into()
+        }
+    }
+
+    impl Unbounded for u8 {
+        fn value(&self) -> u8 {
+            *self
+        }
+    }
+
+    #[test]
+    fn test_sealed_spi() {
+        let builder: Box<dyn Builder> = Box::new(Cargo);
+        assert_eq!(builder.name(), "cargo");
+        assert_eq!(builder.build(), "cargo build");
+        assert_eq!(7u8.value(), 7);
+    }
+}
@@ -0,0 +1,211 @@
+//! Graceful shutdown coordination.
+//!
+//! A [`Shutdown`] is triggered once, by [`trigger`](Shutdown::trigger) or,
+//! with the `signals` feature, by SIGINT/SIGTERM (ctrl-c on Windows). Tasks
+//! hold a clone and [`wait`](Shutdown::wait) for it or read the stream from
+//! [`subscribe`](Shutdown::subscribe), then wind down.
+//!
+//! Requires the `tokio` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::shutdown::{Shutdown, ShutdownReason};
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let shutdown = Shutdown::new();
+//! let worker = tokio::spawn({
+//!     let shutdown = shutdown.clone();
+//!     async move { shutdown.wait().await }
+//! });
+//!
+//! shutdown.trigger(ShutdownReason::Requested);
+//! assert_eq!(worker.await.unwrap(), ShutdownReason::Requested);
+//! # }
+//! ```
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::watch;
+
+use crate::stream::{EventSender, EventStream, StreamBuilder};
+
+/// Why shutdown was triggered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShutdownReason {
+    /// The application asked for it
+    Requested,
+    /// SIGINT or ctrl-c
+    Interrupt,
+    /// SIGTERM
+    Terminate,
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Requested => "requested",
+            Self::Interrupt => "interrupt",
+            Self::Terminate => "terminate",
+        })
+    }
+}
+
+/// A one-shot shutdown signal shared by every part of the application.
+///
+/// Cloning is cheap; clones observe the same trigger.
+#[derive(Clone)]
+pub struct Shutdown {
+    reason: Arc<watch::Sender<Option<ShutdownReason>>>,
+    subscribers: Arc<Mutex<Vec<EventSender<ShutdownReason>>>>,
+}
+
+impl Shutdown {
+    /// Create a coordinator that has not been triggered.
+    pub fn new() -> Self {
+        Self {
+            reason: Arc::new(watch::Sender::new(None)),
+            subscribers: Arc::default(),
+        }
+    }
+
+    /// Trigger shutdown. Returns `false` if it was already triggered, in
+    /// which case the first reason stands.
+    pub fn trigger(&self, reason: ShutdownReason) -> bool {
+        let first = self.reason.send_if_modified(|current| {
+            if current.is_some() {
+                return false;
+            }
+            *current = Some(reason);
+            true
+        });
+        if first {
+            tracing::info!(%reason, "shutdown triggered");
+            let subscribers =
+                std::mem::take(&mut *self.subscribers.lock().unwrap_or_else(|e| e.into_inner()));
+            for subscriber in subscribers {
+                subscriber.offer(reason);
+            }
+        }
+        first
+    }
+
+    /// The reason, if shutdown has been triggered.
+    pub fn reason(&self) -> Option<ShutdownReason> {
+        *self.reason.borrow()
+    }
+
+    /// Whether shutdown has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// Wait until shutdown is triggered.
+    pub async fn wait(&self) -> ShutdownReason {
+        let mut receiver = self.reason.subscribe();
+        let reason = receiver
+            .wait_for(Option::is_some)
+            .await
+            .expect("sender is owned by self");
+        reason.expect("waited for a reason")
+    }
+
+    /// A stream that yields the reason once shutdown is triggered, then
+    /// ends.
+    pub fn subscribe(&self) -> EventStream<ShutdownReason> {
+        let (sender, stream) = StreamBuilder::new().buffer_size(1).build();
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        // Checked under the lock so a concurrent trigger cannot be missed
+        match self.reason() {
+            Some(reason) => {
+                sender.offer(reason);
+            }
+            None => subscribers.push(sender),
+        }
+        stream
+    }
+}
+
+#[cfg(feature = "signals")]
+impl Shutdown {
+    /// Create a coordinator triggered by SIGINT and SIGTERM, or ctrl-c on
+    /// Windows.
+    ///
+    /// Requires the `signals` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a tokio runtime or if the signal handlers
+    /// cannot be installed.
+    pub fn listen_for_signals() -> Self {
+        let shutdown = Self::new();
+        shutdown.spawn_signal_listeners();
+        shutdown
+    }
+
+    #[cfg(unix)]
+    fn spawn_signal_listeners(&self) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        for (kind, reason) in [
+            (SignalKind::interrupt(), ShutdownReason::Interrupt),
+            (SignalKind::terminate(), ShutdownReason::Terminate),
+        ] {
+            let mut signal = signal(kind).expect("failed to install signal handler");
+            let shutdown = self.clone();
+            crate::task::spawn_named("rustratify shutdown signal", async move {
+                if signal.recv().await.is_some() {
+                    shutdown.trigger(reason);
+                }
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn spawn_signal_listeners(&self) {
+        let shutdown = self.clone();
+        crate::task::spawn_named("rustratify shutdown signal", async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                shutdown.trigger(ShutdownReason::Interrupt);
+            }
+        });
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Shutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shutdown")
+            .field("reason", &self.reason())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_first_trigger_wins() {
+        let shutdown = Shutdown::new();
+        let mut early = shutdown.subscribe();
+        assert!(!shutdown.is_triggered());
+
+        assert!(shutdown.trigger(ShutdownReason::Terminate));
+        assert!(!shutdown.clone().trigger(ShutdownReason::Requested));
+        assert_eq!(shutdown.wait().await, ShutdownReason::Terminate);
+
+        assert_eq!(early.next().await, Some(ShutdownReason::Terminate));
+        assert_eq!(early.next().await, None);
+        let late: Vec<_> = shutdown.subscribe().collect().await;
+        assert_eq!(late, vec![ShutdownReason::Terminate]);
+    }
+}
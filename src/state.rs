@@ -0,0 +1,390 @@
+//! Finite state machines for lifecycles.
+//!
+//! A [`StateMachine`] moves between states of type `S` when fired with
+//! events of type `E`, following the transitions declared on its
+//! [`StateMachineBuilder`]. A transition may carry a guard that can reject
+//! it at fire time. Every transition taken is published to the streams
+//! returned by [`StateMachine::subscribe`].
+//!
+//! [`RunState`] is the lifecycle of a run; domain modules with lifecycles of
+//! their own (jobs, sessions, connections) declare theirs the same way.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::state::{RunEvent, RunState};
+//!
+//! let mut run = RunState::machine();
+//! run.fire(RunEvent::Start).unwrap();
+//! run.fire(RunEvent::Complete).unwrap();
+//! assert_eq!(run.state(), &RunState::Succeeded);
+//! assert!(run.fire(RunEvent::Start).is_err());
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::stream::{EventSender, EventStream, StreamBuilder};
+
+type Guard<S, E> = Arc<dyn Fn(&S, &E) -> bool + Send + Sync>;
+
+struct Transition<S, E> {
+    from: S,
+    event: E,
+    to: S,
+    guard: Option<Guard<S, E>>,
+}
+
+/// A transition that was taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateTransition<S, E> {
+    /// State before the transition
+    pub from: S,
+    /// Event that caused it
+    pub event: E,
+    /// State after the transition
+    pub to: S,
+}
+
+/// Why an event was not accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TransitionError<S: fmt::Debug, E: fmt::Debug> {
+    /// No transition is declared for the event in the current state
+    #[error("no transition from {state:?} on {event:?}")]
+    Undefined {
+        /// The current state
+        state: S,
+        /// The event fired
+        event: E,
+    },
+    /// Every matching transition was rejected by its guard
+    #[error("transition from {state:?} on {event:?} rejected by guard")]
+    Rejected {
+        /// The current state
+        state: S,
+        /// The event fired
+        event: E,
+    },
+}
+
+/// Builder for [`StateMachine`].
+pub struct StateMachineBuilder<S, E> {
+    initial: S,
+    transitions: Vec<Transition<S, E>>,
+}
+
+impl<S, E> StateMachineBuilder<S, E>
+where
+    S: Clone + PartialEq + fmt::Debug + Send + 'static,
+    E: Clone + PartialEq + fmt::Debug + Send + 'static,
+{
+    /// Declare that `event` moves the machine from `from` to `to`.
+    pub fn transition(mut self, from: S, event: E, to: S) -> Self {
+        self.transitions.push(Transition {
+            from,
+            event,
+            to,
+            guard: None,
+        });
+        self
+    }
+
+    /// Declare a transition that is only taken when `guard` returns `true`
+    /// for the current state and event.
+    ///
+    /// Transitions are tried in declaration order, so a guarded transition
+    /// can be followed by a fallback for the same state and event.
+    pub fn guarded<G>(mut self, from: S, event: E, to: S, guard: G) -> Self
+    where
+        G: Fn(&S, &E) -> bool + Send + Sync + 'static,
+    {
+        self.transitions.push(Transition {
+            from,
+            event,
+            to,
+            guard: Some(Arc::new(guard)),
+        });
+        self
+    }
+
+    /// Build the machine in its initial state.
+    pub fn build(self) -> StateMachine<S, E> {
+        StateMachine {
+            state: self.initial,
+            transitions: Arc::new(self.transitions),
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+/// A state machine with declared transitions.
+///
+/// Cloning gives an independent machine in the same state that shares the
+/// transition table but not the subscribers.
+pub struct StateMachine<S, E> {
+    state: S,
+    transitions: Arc<Vec<Transition<S, E>>>,
+    subscribers: Vec<EventSender<StateTransition<S, E>>>,
+}
+
+impl<S, E> StateMachine<S, E>
+where
+    S: Clone + PartialEq + fmt::Debug + Send + 'static,
+    E: Clone + PartialEq + fmt::Debug + Send + 'static,
+{
+    /// Start declaring a machine that begins in `initial`.
+    pub fn builder(initial: S) -> StateMachineBuilder<S, E> {
+        StateMachineBuilder {
+            initial,
+            transitions: Vec::new(),
+        }
+    }
+
+    /// The current state.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Apply `event`, returning the new state.
+    ///
+    /// The first declared transition from the current state on `event`
+    /// whose guard passes is taken and published to subscribers. On error
+    /// the state is unchanged.
+    pub fn fire(&mut self, event: E) -> Result<&S, TransitionError<S, E>> {
+        let mut rejected = false;
+        let to = self
+            .candidates(&event)
+            .find(|t| match &t.guard {
+                Some(guard) if !guard(&self.state, &event) => {
+                    rejected = true;
+                    false
+                }
+                _ => true,
+            })
+            .map(|t| t.to.clone());
+        let Some(to) = to else {
+            let (state, event) = (self.state.clone(), event);
+            return Err(if rejected {
+                TransitionError::Rejected { state, event }
+            } else {
+                TransitionError::Undefined { state, event }
+            });
+        };
+
+        let from = std::mem::replace(&mut self.state, to);
+        let transition = StateTransition {
+            from,
+            event,
+            to: self.state.clone(),
+        };
+        self.subscribers.retain(|s| !s.is_closed());
+        for subscriber in &self.subscribers {
+            subscriber.offer(transition.clone());
+        }
+        Ok(&self.state)
+    }
+
+    /// Whether `event` would be accepted in the current state.
+    pub fn can_fire(&self, event: &E) -> bool {
+        self.candidates(event).any(|t| {
+            t.guard
+                .as_ref()
+                .is_none_or(|guard| guard(&self.state, event))
+        })
+    }
+
+    /// The events with a declared transition from the current state,
+    /// guarded or not.
+    pub fn events(&self) -> Vec<E> {
+        let mut events: Vec<E> = Vec::new();
+        for t in self.transitions.iter().filter(|t| t.from == self.state) {
+            if !events.contains(&t.event) {
+                events.push(t.event.clone());
+            }
+        }
+        events
+    }
+
+    /// A stream of the transitions taken from now on.
+    ///
+    /// The stream buffers 64 transitions; further ones are dropped until the
+    /// subscriber catches up.
+    pub fn subscribe(&mut self) -> EventStream<StateTransition<S, E>> {
+        let (sender, stream) = StreamBuilder::new().buffer_size(64).build();
+        self.subscribers.push(sender);
+        stream
+    }
+
+    fn candidates<'a>(&'a self, event: &'a E) -> impl Iterator<Item = &'a Transition<S, E>> {
+        self.transitions
+            .iter()
+            .filter(move |t| t.from == self.state && t.event == *event)
+    }
+}
+
+impl<S: Clone, E> Clone for StateMachine<S, E> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            transitions: Arc::clone(&self.transitions),
+            subscribers: Vec::new(),
+        }
+    }
+}
+
+impl<S: fmt::Debug, E> fmt::Debug for StateMachine<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateMachine")
+            .field("state", &self.state)
+            .field("transitions", &self.transitions.len())
+            .finish()
+    }
+}
+
+/// The lifecycle of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum RunState {
+    /// Submitted but not started
+    Pending,
+    /// In progress
+    Running,
+    /// Suspended; may resume
+    Paused,
+    /// Finished successfully
+    Succeeded,
+    /// Finished with an error
+    Failed,
+    /// Stopped on request
+    Cancelled,
+}
+
+/// Events that move a run between [`RunState`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum RunEvent {
+    /// Pending to running
+    Start,
+    /// Running to paused
+    Pause,
+    /// Paused to running
+    Resume,
+    /// Running to succeeded
+    Complete,
+    /// Running or paused to failed
+    Fail,
+    /// Any unfinished state to cancelled
+    Cancel,
+}
+
+impl RunState {
+    /// A machine in [`Pending`](Self::Pending) with the run transitions.
+    pub fn machine() -> StateMachine<RunState, RunEvent> {
+        use RunEvent::*;
+        use RunState::*;
+        StateMachine::builder(Pending)
+            .transition(Pending, Start, Running)
+            .transition(Pending, Cancel, Cancelled)
+            .transition(Running, Pause, Paused)
+            .transition(Running, Complete, Succeeded)
+            .transition(Running, Fail, Failed)
+            .transition(Running, Cancel, Cancelled)
+            .transition(Paused, Resume, Running)
+            .transition(Paused, Fail, Failed)
+            .transition(Paused, Cancel, Cancelled)
+            .build()
+    }
+
+    /// Whether the run has finished and will not change state again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+    }
+}
+
+impl fmt::Display for RunState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Paused => "paused",
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_run_lifecycle() {
+        let mut run = RunState::machine();
+        assert_eq!(run.events(), vec![RunEvent::Start, RunEvent::Cancel]);
+        run.fire(RunEvent::Start).unwrap();
+        run.fire(RunEvent::Pause).unwrap();
+        assert_eq!(
+            run.fire(RunEvent::Complete),
+            Err(TransitionError::Undefined {
+                state: RunState::Paused,
+                event: RunEvent::Complete,
+            })
+        );
+        run.fire(RunEvent::Cancel).unwrap();
+        assert!(run.state().is_terminal());
+        assert!(run.events().is_empty());
+    }
+
+    #[test]
+    fn test_guards_fall_through_in_order() {
+        let mut door = StateMachine::builder(0u8)
+            .guarded(0, "knock", 1, |_, _| false)
+            .guarded(0, "push", 2, |_, _| false)
+            .transition(0, "knock", 3)
+            .build();
+        assert!(!door.can_fire(&"push"));
+        assert!(matches!(
+            door.fire("push"),
+            Err(TransitionError::Rejected { state: 0, .. })
+        ));
+        assert_eq!(door.fire("knock"), Ok(&3));
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_see_transitions() {
+        let mut run = RunState::machine();
+        let mut transitions = run.subscribe();
+        run.fire(RunEvent::Start).unwrap();
+        run.fire(RunEvent::Fail).unwrap();
+        drop(run);
+
+        let taken: Vec<_> = transitions.by_ref().collect().await;
+        assert_eq!(
+            taken,
+            vec![
+                StateTransition {
+                    from: RunState::Pending,
+                    event: RunEvent::Start,
+                    to: RunState::Running,
+                },
+                StateTransition {
+                    from: RunState::Running,
+                    event: RunEvent::Fail,
+                    to: RunState::Failed,
+                },
+            ]
+        );
+    }
+}
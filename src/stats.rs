@@ -0,0 +1,435 @@
+//! Call statistics for providers.
+//!
+//! A provider reports how it is doing through [`ProviderStats`]: how often
+//! it was called, how often it failed, how long calls took on average, and
+//! the last error. [`CallStats`] is the standard implementation, and
+//! [`Instrumented`] wraps a provider so every call made through it is
+//! recorded. Providers expose their stats with
+//! [`Provider::stats`](crate::Provider::stats), and
+//! [`Registry::stats_report`](crate::Registry::stats_report) collects them
+//! into a [`StatsReport`] for dashboards (serializable with the `serde`
+//! feature).
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::stats::Instrumented;
+//! use rustratify::{Provider, ProviderError, Registry};
+//! use std::any::Any;
+//!
+//! #[derive(Debug)]
+//! struct Rust;
+//!
+//! impl Provider for Rust {
+//!     fn name(&self) -> &str { "rust" }
+//!     fn as_any(&self) -> &dyn Any { self }
+//! }
+//!
+//! let provider = Instrumented::new(Rust);
+//! provider.call(|_| Ok(())).unwrap();
+//! let _ = provider.call(|_| Err::<(), _>(ProviderError::Timeout(50)));
+//!
+//! let mut registry: Registry<dyn Provider> = Registry::new();
+//! registry.register(Box::new(provider));
+//!
+//! let report = registry.stats_report();
+//! let rust = report.get("rust").unwrap();
+//! assert_eq!((rust.calls, rust.errors), (2, 1));
+//! assert_eq!(rust.last_error.as_deref(), Some("Operation timed out after 50ms"));
+//! ```
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::{ProviderError, ProviderResult};
+use crate::matcher::Matcher;
+use crate::output::ProviderOutput;
+use crate::permissions::Permissions;
+use crate::provider::{LifecycleFuture, Provider};
+
+/// Call statistics of one provider.
+pub trait ProviderStats: Send + Sync {
+    /// Get the number of calls made.
+    fn calls(&self) -> u64;
+
+    /// Get the number of calls that failed.
+    fn errors(&self) -> u64;
+
+    /// Get the mean call latency, zero before the first call.
+    fn mean_latency(&self) -> Duration;
+
+    /// Get the message of the most recent error.
+    fn last_error(&self) -> Option<String>;
+
+    /// Take a snapshot of all statistics.
+    fn snapshot(&self) -> ProviderStatsSnapshot {
+        ProviderStatsSnapshot {
+            calls: self.calls(),
+            errors: self.errors(),
+            mean_latency: self.mean_latency(),
+            last_error: self.last_error(),
+        }
+    }
+}
+
+/// A point-in-time copy of [`ProviderStats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProviderStatsSnapshot {
+    /// Calls made
+    pub calls: u64,
+    /// Calls that failed
+    pub errors: u64,
+    /// Mean call latency
+    pub mean_latency: Duration,
+    /// Message of the most recent error
+    pub last_error: Option<String>,
+}
+
+impl ProviderStatsSnapshot {
+    /// Get the fraction of calls that failed, between 0.0 and 1.0.
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.calls as f64
+    }
+}
+
+/// The statistics of every provider in a registry that reports them, by
+/// provider name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatsReport {
+    /// Statistics by provider name
+    pub providers: BTreeMap<String, ProviderStatsSnapshot>,
+}
+
+impl StatsReport {
+    /// The statistics of the provider called `name`.
+    pub fn get(&self, name: &str) -> Option<&ProviderStatsSnapshot> {
+        self.providers.get(name)
+    }
+
+    /// Get the number of calls across all providers.
+    pub fn total_calls(&self) -> u64 {
+        self.providers.values().map(|s| s.calls).sum()
+    }
+
+    /// Get the number of failed calls across all providers.
+    pub fn total_errors(&self) -> u64 {
+        self.providers.values().map(|s| s.errors).sum()
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    latency_nanos: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+/// Shared handle to the call counters of a provider.
+///
+/// Cloning the handle is cheap; all clones record into the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct CallStats {
+    counters: Arc<Counters>,
+}
+
+impl CallStats {
+    /// Create empty counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one call that took `latency`, failing with `error` if given.
+    pub fn record(&self, latency: Duration, error: Option<&ProviderError>) {
+        let c = &self.counters;
+        c.calls.fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX);
+        c.latency_nanos.fetch_add(nanos, Ordering::Relaxed);
+        if let Some(err) = error {
+            c.errors.fetch_add(1, Ordering::Relaxed);
+            *c.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(err.to_string());
+        }
+    }
+
+    /// Run `call`, recording its latency and outcome.
+    pub fn track<T>(&self, call: impl FnOnce() -> ProviderResult<T>) -> ProviderResult<T> {
+        let start = Instant::now();
+        let result = call();
+        self.record(start.elapsed(), result.as_ref().err());
+        result
+    }
+
+    /// Await `call`, recording its latency and outcome.
+    pub async fn track_async<T>(
+        &self,
+        call: impl Future<Output = ProviderResult<T>>,
+    ) -> ProviderResult<T> {
+        let start = Instant::now();
+        let result = call.await;
+        self.record(start.elapsed(), result.as_ref().err());
+        result
+    }
+}
+
+impl ProviderStats for CallStats {
+    fn calls(&self) -> u64 {
+        self.counters.calls.load(Ordering::Relaxed)
+    }
+
+    fn errors(&self) -> u64 {
+        self.counters.errors.load(Ordering::Relaxed)
+    }
+
+    fn mean_latency(&self) -> Duration {
+        match self.calls() {
+            0 => Duration::ZERO,
+            calls => {
+                Duration::from_nanos(self.counters.latency_nanos.load(Ordering::Relaxed) / calls)
+            }
+        }
+    }
+
+    fn last_error(&self) -> Option<String> {
+        self.counters
+            .last_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// A provider whose calls are recorded in [`CallStats`].
+///
+/// Make calls through [`call`](Self::call) or
+/// [`call_async`](Self::call_async). The wrapper forwards every [`Provider`]
+/// method to the inner provider, including [`as_any`](Provider::as_any), so
+/// downcasting sees the inner type, and reports its stats through
+/// [`Provider::stats`].
+pub struct Instrumented<P> {
+    provider: P,
+    stats: CallStats,
+}
+
+impl<P> Instrumented<P> {
+    /// Wrap `provider` with empty counters.
+    pub fn new(provider: P) -> Self {
+        Self::with_stats(provider, CallStats::new())
+    }
+
+    /// Wrap `provider`, recording into `stats`.
+    pub fn with_stats(provider: P, stats: CallStats) -> Self {
+        Self { provider, stats }
+    }
+
+    /// The counters calls are recorded in.
+    pub fn call_stats(&self) -> &CallStats {
+        &self.stats
+    }
+
+    /// Call the provider, recording latency and outcome.
+    pub fn call<T>(&self, call: impl FnOnce(&P) -> ProviderResult<T>) -> ProviderResult<T> {
+        self.stats.track(|| call(&self.provider))
+    }
+
+    /// Call the provider asynchronously, recording latency and outcome.
+    pub async fn call_async<'a, T, F, Fut>(&'a self, call: F) -> ProviderResult<T>
+    where
+        F: FnOnce(&'a P) -> Fut,
+        Fut: Future<Output = ProviderResult<T>>,
+    {
+        self.stats.track_async(call(&self.provider)).await
+    }
+
+    /// The wrapped provider.
+    pub fn get_ref(&self) -> &P {
+        &self.provider
+    }
+
+    /// Unwrap the provider.
+    pub fn into_inner(self) -> P {
+        self.provider
+    }
+}
+
+impl<P: Provider> Instrumented<P> {
+    /// Call the provider like [`call`](Self::call), returning the value
+    /// with the provider name and call duration.
+    pub fn call_output<T>(
+        &self,
+        call: impl FnOnce(&P) -> ProviderResult<T>,
+    ) -> ProviderResult<ProviderOutput<T>> {
+        let start = Instant::now();
+        let value = self.call(call)?;
+        Ok(ProviderOutput::new(self.provider.name(), value).with_duration(start.elapsed()))
+    }
+
+    /// Call the provider like [`call_async`](Self::call_async), returning
+    /// the value with the provider name and call duration.
+    pub async fn call_output_async<'a, T, F, Fut>(
+        &'a self,
+        call: F,
+    ) -> ProviderResult<ProviderOutput<T>>
+    where
+        F: FnOnce(&'a P) -> Fut,
+        Fut: Future<Output = ProviderResult<T>>,
+    {
+        let start = Instant::now();
+        let value = self.call_async(call).await?;
+        Ok(ProviderOutput::new(self.provider.name(), value).with_duration(start.elapsed()))
+    }
+}
+
+impl<P: fmt::Debug> fmt::Debug for Instrumented<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Instrumented")
+            .field("provider", &self.provider)
+            .field("stats", &self.stats.snapshot())
+            .finish()
+    }
+}
+
+impl<P: Provider> Provider for Instrumented<P> {
+    fn name(&self) -> &str {
+        self.provider.name()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.provider.extensions()
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        self.provider.mime_types()
+    }
+
+    fn schemes(&self) -> &[&str] {
+        self.provider.schemes()
+    }
+
+    fn matcher(&self) -> Option<&dyn Matcher> {
+        self.provider.matcher()
+    }
+
+    fn supports(&self, key: &str) -> bool {
+        self.provider.supports(key)
+    }
+
+    fn supports_path(&self, path: &Path) -> bool {
+        self.provider.supports_path(path)
+    }
+
+    fn priority(&self) -> i32 {
+        self.provider.priority()
+    }
+
+    fn max_concurrency(&self) -> Option<usize> {
+        self.provider.max_concurrency()
+    }
+
+    fn permissions(&self) -> Permissions {
+        self.provider.permissions()
+    }
+
+    fn start(&self) -> LifecycleFuture<'_> {
+        self.provider.start()
+    }
+
+    fn warm_up(&self) -> LifecycleFuture<'_> {
+        self.provider.warm_up()
+    }
+
+    fn stop(&self) -> LifecycleFuture<'_> {
+        self.provider.stop()
+    }
+
+    fn health(&self) -> ProviderResult<()> {
+        self.provider.health()
+    }
+
+    fn stats(&self) -> Option<&dyn ProviderStats> {
+        Some(&self.stats)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self.provider.as_any()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_stats() {
+        let stats = CallStats::new();
+        assert_eq!(stats.snapshot(), ProviderStatsSnapshot::default());
+
+        stats.record(Duration::from_millis(10), None);
+        stats.record(
+            Duration::from_millis(30),
+            Some(&ProviderError::NotFound("x".into())),
+        );
+        let snapshot = stats.clone().snapshot();
+        assert_eq!(snapshot.calls, 2);
+        assert_eq!(snapshot.errors, 1);
+        assert_eq!(snapshot.mean_latency, Duration::from_millis(20));
+        assert_eq!(
+            snapshot.last_error.as_deref(),
+            Some("Provider not found: x")
+        );
+        assert_eq!(snapshot.error_rate(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_track_async() {
+        let stats = CallStats::new();
+        let value = stats.track_async(async { Ok(7) }).await.unwrap();
+        assert_eq!(value, 7);
+        let err = stats
+            .track_async(async { Err::<(), _>(ProviderError::Cancelled) })
+            .await;
+        assert!(err.is_err());
+        assert_eq!((stats.calls(), stats.errors()), (2, 1));
+    }
+
+    #[test]
+    fn test_call_output() {
+        #[derive(Debug)]
+        struct Rust;
+
+        impl Provider for Rust {
+            fn name(&self) -> &str {
+                "rust"
+            }
+
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+        }
+
+        let provider = Instrumented::new(Rust);
+        let output = provider.call_output(|_| Ok(3)).unwrap();
+        assert_eq!((output.provider.as_str(), output.value), ("rust", 3));
+        assert!(!output.cache_hit && output.warnings.is_empty());
+        assert!(provider
+            .call_output(|_| Err::<(), _>(ProviderError::Cancelled))
+            .is_err());
+        assert_eq!(
+            (
+                provider.call_stats().calls(),
+                provider.call_stats().errors()
+            ),
+            (2, 1)
+        );
+    }
+}
@@ -3,11 +3,91 @@
 //! This module provides utilities for creating and working with async streams,
 //! which are the preferred way to handle events in Rustratify modules.
 
+use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+#[cfg(feature = "tokio")]
+use std::time::Duration;
 
 use futures_core::Stream;
+#[cfg(feature = "tokio")]
 use tokio::sync::mpsc;
-use tokio_stream::wrappers::ReceiverStream;
+
+use crate::metrics::Metrics;
+use close::{CloseCallback, OnClose};
+use completion::{FinishState, Finishing};
+
+pub mod backend;
+#[cfg(feature = "tokio")]
+mod broadcast;
+mod checkpoint;
+mod close;
+mod completion;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub mod compress;
+mod dead_letter;
+mod dedup;
+mod envelope;
+#[cfg(feature = "tokio")]
+mod keyed;
+mod level;
+mod merge;
+mod pause;
+#[cfg(feature = "tokio")]
+mod rate;
+#[cfg(feature = "json")]
+pub mod serde;
+#[cfg(feature = "sink")]
+mod sink;
+#[cfg(all(
+    feature = "spill",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub mod spill;
+#[cfg(feature = "sse")]
+pub mod sse;
+mod stats;
+mod store;
+mod tee;
+#[cfg(feature = "tokio")]
+pub mod testing;
+mod try_stream;
+#[cfg(feature = "tokio")]
+mod watchdog;
+
+#[cfg(any(feature = "async-std", feature = "smol"))]
+pub use backend::AsyncChannelBackend;
+#[cfg(feature = "tokio")]
+pub use backend::TokioBackend;
+pub use backend::{ChannelBackend, DefaultBackend, ReserveError, StdBackend, TrySendError};
+#[cfg(feature = "tokio")]
+pub use broadcast::{BroadcastItem, BroadcastStream, Broadcaster, SubscriberLag};
+pub use checkpoint::{resume_from, Checkpoint};
+pub use completion::{Event, EventKind, UntilTerminal};
+pub use dead_letter::{DeadLetter, DeadLetterReason, DeadLetterSink};
+pub use dedup::Dedup;
+pub use envelope::{Envelope, EnvelopeSender};
+#[cfg(feature = "tokio")]
+pub use keyed::StreamRegistry;
+pub use level::{Level, MinLevel};
+pub use merge::{merge_streams, Merge, Multiplexer};
+pub use pause::{PausableStream, PauseHandle, PausePolicy};
+#[cfg(feature = "tokio")]
+pub use rate::{Debounce, Sample, Throttle};
+#[cfg(feature = "sink")]
+pub use sink::{EventSink, SendError};
+pub use stats::{StreamStats, StreamStatsSnapshot};
+#[cfg(all(
+    feature = "serde",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use store::JsonLinesEventStore;
+pub use store::{EventStore, MemoryEventStore};
+pub use tee::{tee, Tee, TeePolicy};
+pub use try_stream::TryEventStreamExt;
+#[cfg(feature = "tokio")]
+pub use watchdog::{Watchdog, WatchdogItem};
 
 /// Type alias for a boxed async stream of events.
 ///
@@ -16,25 +96,115 @@ pub type EventStream<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
 
 /// A sender for events in an async stream.
 ///
-/// This wraps a tokio mpsc sender and provides convenience methods
-/// for sending events.
+/// This wraps the sender half of a [`ChannelBackend`] channel (a tokio mpsc
+/// sender by default) and provides convenience methods for sending events.
 #[derive(Debug)]
 pub struct EventSender<T> {
-    tx: mpsc::Sender<T>,
+    tx: Tx<T>,
+    stats: Option<StreamStats>,
+    dead_letter: Option<DeadLetterSink<T>>,
+    /// Shared by clones and, for streams from [`StreamBuilder`], the stream
+    finish: Arc<FinishState>,
+}
+
+enum Tx<T> {
+    #[cfg(feature = "tokio")]
+    Tokio(mpsc::Sender<T>),
+    Backend(Box<dyn backend::BackendSender<T>>),
+}
+
+impl<T> std::fmt::Debug for Tx<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => f.debug_tuple("Tokio").field(tx).finish(),
+            Tx::Backend(_) => f.write_str("Backend"),
+        }
+    }
+}
+
+/// A slot reserved by [`EventSender::reserve`] for one event.
+///
+/// Dropping the permit unused gives the slot back to the channel.
+pub struct SendPermit<'a, T> {
+    slot: Option<Slot<'a, T>>,
+    sender: &'a EventSender<T>,
+}
+
+enum Slot<'a, T> {
+    #[cfg(feature = "tokio")]
+    Tokio(mpsc::Permit<'a, T>),
+    Backend(&'a dyn backend::BackendSender<T>),
+}
+
+impl<T> SendPermit<'_, T> {
+    /// Send `event` into the reserved slot.
+    pub fn send(mut self, event: T) {
+        match self.slot.take() {
+            #[cfg(feature = "tokio")]
+            Some(Slot::Tokio(permit)) => permit.send(event),
+            Some(Slot::Backend(tx)) => tx.send_reserved(event),
+            None => unreachable!("permit already used"),
+        }
+        self.sender.record(true);
+    }
+}
+
+impl<T> Drop for SendPermit<'_, T> {
+    fn drop(&mut self) {
+        if let Some(Slot::Backend(tx)) = self.slot.take() {
+            tx.release(1);
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for SendPermit<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SendPermit").finish_non_exhaustive()
+    }
 }
 
 impl<T> EventSender<T> {
-    /// Create a new event sender from an mpsc sender.
+    /// Create a new event sender from a tokio mpsc sender.
+    #[cfg(feature = "tokio")]
     pub fn new(tx: mpsc::Sender<T>) -> Self {
-        Self { tx }
+        Self::with_tx(Tx::Tokio(tx))
+    }
+
+    /// Create a new event sender from a custom backend sender.
+    pub fn from_backend(tx: Box<dyn backend::BackendSender<T>>) -> Self {
+        Self::with_tx(Tx::Backend(tx))
+    }
+
+    fn with_tx(tx: Tx<T>) -> Self {
+        Self {
+            tx,
+            stats: None,
+            dead_letter: None,
+            finish: Arc::default(),
+        }
     }
 
     /// Send an event.
     ///
     /// Returns `Ok(())` if the event was sent, or `Err(event)` if the
-    /// receiver was dropped.
+    /// receiver was dropped or the stream was [finished](Self::finish).
     pub async fn send(&self, event: T) -> Result<(), T> {
-        self.tx.send(event).await.map_err(|e| e.0)
+        if self.is_finished() {
+            self.record(false);
+            return Err(event);
+        }
+        self.deliver(event).await
+    }
+
+    async fn deliver(&self, event: T) -> Result<(), T> {
+        let result = match &self.tx {
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => tx.send(event).await.map_err(|e| e.0),
+            Tx::Backend(tx) => tx.send(event).await,
+        };
+        self.record(result.is_ok());
+        result
     }
 
     /// Try to send an event without waiting.
@@ -42,27 +212,228 @@ impl<T> EventSender<T> {
     /// Returns `Ok(())` if the event was sent, or `Err(event)` if the
     /// channel is full or closed.
     pub fn try_send(&self, event: T) -> Result<(), T> {
-        self.tx.try_send(event).map_err(|e| match e {
-            mpsc::error::TrySendError::Full(v) => v,
-            mpsc::error::TrySendError::Closed(v) => v,
-        })
+        let result = self.try_send_raw(event).map_err(TrySendError::into_inner);
+        self.record(result.is_ok());
+        result
+    }
+
+    fn try_send_raw(&self, event: T) -> Result<(), TrySendError<T>> {
+        if self.is_finished() {
+            return Err(TrySendError::Closed(event));
+        }
+        match &self.tx {
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => tx.try_send(event).map_err(|e| match e {
+                mpsc::error::TrySendError::Full(v) => TrySendError::Full(v),
+                mpsc::error::TrySendError::Closed(v) => TrySendError::Closed(v),
+            }),
+            Tx::Backend(tx) => tx.try_send(event),
+        }
     }
 
-    /// Check if the receiver has been dropped.
+    /// Send an event without waiting, dropping it if it cannot be delivered.
+    ///
+    /// Undeliverable events are routed to the dead-letter sink configured with
+    /// [`StreamBuilder::dead_letter`], if any. Returns whether the event was
+    /// delivered.
+    pub fn offer(&self, event: T) -> bool {
+        let (event, reason) = match self.try_send_raw(event) {
+            Ok(()) => {
+                self.record(true);
+                return true;
+            }
+            Err(TrySendError::Full(v)) => (v, DeadLetterReason::Full),
+            Err(TrySendError::Closed(v)) => (v, DeadLetterReason::Closed),
+        };
+        self.record(false);
+        if let Some(sink) = &self.dead_letter {
+            sink.send(event, reason);
+        }
+        false
+    }
+
+    /// Wait until the receiver has been dropped.
+    ///
+    /// Race it against expensive work to stop producing as soon as nobody
+    /// is listening, rather than finding out on the next failed send.
+    /// Returns at once if the stream was already [finished](Self::finish).
+    pub async fn closed(&self) {
+        if self.is_finished() {
+            return;
+        }
+        match &self.tx {
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => tx.closed().await,
+            Tx::Backend(tx) => std::future::poll_fn(|cx| tx.poll_closed(cx)).await,
+        }
+    }
+
+    /// Get the stats handle, if the stream was built with
+    /// [`StreamBuilder::with_stats`].
+    pub fn stats(&self) -> Option<StreamStats> {
+        self.stats.clone()
+    }
+
+    fn record(&self, delivered: bool) {
+        if let Some(stats) = &self.stats {
+            if delivered {
+                stats.record_sent();
+            } else {
+                stats.record_dropped();
+            }
+        }
+    }
+
+    /// Check if the receiver has been dropped or the stream was
+    /// [finished](Self::finish).
     pub fn is_closed(&self) -> bool {
-        self.tx.is_closed()
+        if self.is_finished() {
+            return true;
+        }
+        match &self.tx {
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => tx.is_closed(),
+            Tx::Backend(tx) => tx.is_closed(),
+        }
     }
 
     /// Get the capacity of the underlying channel.
     pub fn capacity(&self) -> usize {
-        self.tx.capacity()
+        match &self.tx {
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => tx.capacity(),
+            Tx::Backend(tx) => tx.capacity(),
+        }
+    }
+
+    /// Wait for room in the channel and reserve it for one event.
+    ///
+    /// Sending through the permit never waits, so a producer can reserve
+    /// before doing the work of building an event. Dropping the permit
+    /// unused frees the slot. The tokio and std backends support
+    /// reservations; backends that do not, such as flume, crossbeam and
+    /// async-channel, fail with [`ReserveError::Unsupported`].
+    pub async fn reserve(&self) -> Result<SendPermit<'_, T>, ReserveError> {
+        match &self.tx {
+            _ if self.is_finished() => Err(ReserveError::Closed),
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => {
+                let permit = tx.reserve().await.map_err(|_| ReserveError::Closed)?;
+                Ok(SendPermit {
+                    slot: Some(Slot::Tokio(permit)),
+                    sender: self,
+                })
+            }
+            Tx::Backend(tx) => {
+                std::future::poll_fn(|cx| tx.poll_reserve(cx, 1)).await?;
+                Ok(SendPermit {
+                    slot: Some(Slot::Backend(tx.as_ref())),
+                    sender: self,
+                })
+            }
+        }
+    }
+
+    /// Wait for room for `n` events and reserve it all at once.
+    ///
+    /// Fails with [`ReserveError::TooMany`] if the channel can never hold
+    /// `n` events. Otherwise behaves like [`reserve`](Self::reserve).
+    pub async fn reserve_many(&self, n: usize) -> Result<Vec<SendPermit<'_, T>>, ReserveError> {
+        match &self.tx {
+            _ if self.is_finished() => Err(ReserveError::Closed),
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) if n > tx.max_capacity() => Err(ReserveError::TooMany(n)),
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => {
+                let permits = tx.reserve_many(n).await.map_err(|_| ReserveError::Closed)?;
+                Ok(permits
+                    .map(|permit| SendPermit {
+                        slot: Some(Slot::Tokio(permit)),
+                        sender: self,
+                    })
+                    .collect())
+            }
+            Tx::Backend(tx) => {
+                std::future::poll_fn(|cx| tx.poll_reserve(cx, n)).await?;
+                Ok((0..n)
+                    .map(|_| SendPermit {
+                        slot: Some(Slot::Backend(tx.as_ref())),
+                        sender: self,
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Send every event of `events` in order, waiting for room whenever
+    /// the channel is full.
+    ///
+    /// Returns the number of events sent, or `Err(event)` with the first
+    /// event that could not be sent because the receiver was dropped; the
+    /// events after it are not consumed.
+    pub async fn send_all<I>(&self, events: I) -> Result<usize, T>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut sent = 0;
+        for event in events {
+            match self.try_send_raw(event) {
+                Ok(()) => self.record(true),
+                Err(TrySendError::Full(event)) => self.send(event).await?,
+                Err(TrySendError::Closed(event)) => {
+                    self.record(false);
+                    return Err(event);
+                }
+            }
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Send a terminal event, close the stream to every clone of this
+    /// sender, and drop this sender.
+    ///
+    /// Sends through any clone after this fail as if the receiver had been
+    /// dropped, so the terminal event is the last one sent. A stream built
+    /// with [`StreamBuilder`] or [`create_stream`] ends once the terminal
+    /// event has been read, even while clones are still alive; a send that
+    /// was already waiting for room may then be lost. Fails with
+    /// `Err(event)` if a clone finished the stream first or the receiver was
+    /// dropped.
+    pub async fn finish(self, event: T) -> Result<(), T>
+    where
+        T: EventKind,
+    {
+        debug_assert!(
+            event.is_terminal(),
+            "finish() called with a non-terminal event"
+        );
+        if !self.finish.start() {
+            self.record(false);
+            return Err(event);
+        }
+        self.deliver(event).await?;
+        self.finish.delivered();
+        Ok(())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finish.is_finished()
     }
 }
 
 impl<T> Clone for EventSender<T> {
     fn clone(&self) -> Self {
+        let tx = match &self.tx {
+            #[cfg(feature = "tokio")]
+            Tx::Tokio(tx) => Tx::Tokio(tx.clone()),
+            Tx::Backend(tx) => Tx::Backend(tx.clone_sender()),
+        };
         Self {
-            tx: self.tx.clone(),
+            tx,
+            stats: self.stats.clone(),
+            dead_letter: self.dead_letter.clone(),
+            finish: Arc::clone(&self.finish),
         }
     }
 }
@@ -92,35 +463,155 @@ impl<T> Clone for EventSender<T> {
 /// sender.send(MyEvent::Complete).await.unwrap();
 /// # }
 /// ```
-pub struct StreamBuilder<T> {
+pub struct StreamBuilder<T, B = DefaultBackend> {
+    backend: B,
     buffer_size: usize,
+    with_stats: bool,
+    metrics: Option<(Metrics, String)>,
+    dead_letter: Option<DeadLetterSink<T>>,
+    on_close: Option<CloseCallback>,
     _marker: std::marker::PhantomData<T>,
 }
 
 impl<T: Send + 'static> StreamBuilder<T> {
     /// Create a new stream builder with default settings.
     pub fn new() -> Self {
+        Self::with_backend(DefaultBackend::default())
+    }
+}
+
+#[cfg(feature = "async-std")]
+impl<T: Send + 'static> StreamBuilder<T, AsyncChannelBackend> {
+    /// Create a stream builder for the async-std runtime.
+    ///
+    /// Requires the `async-std` feature.
+    pub fn async_std() -> Self {
+        Self::with_backend(AsyncChannelBackend)
+    }
+}
+
+#[cfg(feature = "smol")]
+impl<T: Send + 'static> StreamBuilder<T, AsyncChannelBackend> {
+    /// Create a stream builder for the smol runtime.
+    ///
+    /// Requires the `smol` feature.
+    pub fn smol() -> Self {
+        Self::with_backend(AsyncChannelBackend)
+    }
+}
+
+impl<T: Send + 'static, B: ChannelBackend> StreamBuilder<T, B> {
+    fn with_backend(backend: B) -> Self {
         Self {
+            backend,
             buffer_size: 100,
+            with_stats: false,
+            metrics: None,
+            dead_letter: None,
+            on_close: None,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Use a different channel backend.
+    ///
+    /// Default is [`DefaultBackend`]. See the [`backend`] module. Only the
+    /// tokio and std backends support [`EventSender::reserve`]; on the
+    /// others it fails with [`ReserveError::Unsupported`].
+    pub fn backend<B2: ChannelBackend>(self, backend: B2) -> StreamBuilder<T, B2> {
+        StreamBuilder {
+            backend,
+            buffer_size: self.buffer_size,
+            with_stats: self.with_stats,
+            metrics: self.metrics,
+            dead_letter: self.dead_letter,
+            on_close: self.on_close,
             _marker: std::marker::PhantomData,
         }
     }
 
+    /// Get the name of the channel backend.
+    pub fn backend_name(&self) -> &str {
+        self.backend.name()
+    }
+
     /// Set the buffer size for the underlying channel.
     ///
-    /// Default is 100.
+    /// Default is 100. A size of zero is treated as one.
     pub fn buffer_size(mut self, size: usize) -> Self {
         self.buffer_size = size;
         self
     }
 
+    /// Track sent, received, and dropped events.
+    ///
+    /// The counters are available through [`EventSender::stats`].
+    pub fn with_stats(mut self) -> Self {
+        self.with_stats = true;
+        self
+    }
+
+    /// Report the stream's stats in `metrics` under the label `stream="name"`.
+    ///
+    /// Implies [`with_stats`](Self::with_stats). See
+    /// [`Metrics::observe_stream`].
+    pub fn metrics(mut self, metrics: &Metrics, name: impl Into<String>) -> Self {
+        self.with_stats = true;
+        self.metrics = Some((metrics.clone(), name.into()));
+        self
+    }
+
+    /// Route events dropped by [`EventSender::offer`] to a dead-letter sink.
+    ///
+    /// Streams built with [`enveloped`](Self::enveloped) route events dropped
+    /// by [`EnvelopeSender::offer`] instead, without their envelopes.
+    pub fn dead_letter(mut self, sink: DeadLetterSink<T>) -> Self {
+        self.dead_letter = Some(sink);
+        self
+    }
+
+    /// Run `callback` once the receiving stream is dropped.
+    ///
+    /// See also [`EventSender::closed`] to wait for the same moment from
+    /// the producer side.
+    pub fn on_close(mut self, callback: impl FnOnce() + Send + 'static) -> Self {
+        self.on_close = Some(Box::new(callback));
+        self
+    }
+
     /// Build the stream and sender.
     ///
     /// Returns a tuple of (sender, stream).
-    pub fn build(self) -> (EventSender<T>, EventStream<T>) {
-        let (tx, rx) = mpsc::channel(self.buffer_size);
-        let sender = EventSender::new(tx);
-        let stream: EventStream<T> = Box::pin(ReceiverStream::new(rx));
+    pub fn build(mut self) -> (EventSender<T>, EventStream<T>) {
+        let (mut sender, stream) = self.channel();
+        sender.dead_letter = self.dead_letter;
+        (sender, stream)
+    }
+
+    /// Build a stream whose events are wrapped in [`Envelope`]s.
+    ///
+    /// The returned sender stamps each event with a sequence number and
+    /// timestamp at send time; use [`EnvelopeSender::with_run_id`] and
+    /// [`EnvelopeSender::with_correlation_id`] to add identifiers.
+    pub fn enveloped(mut self) -> (EnvelopeSender<T>, EventStream<Envelope<T>>) {
+        let (sender, stream) = self.channel();
+        (EnvelopeSender::new(sender, self.dead_letter), stream)
+    }
+
+    fn channel<U: Send + 'static>(&mut self) -> (EventSender<U>, EventStream<U>) {
+        let (mut sender, mut stream) = self.backend.channel::<U>(self.buffer_size);
+        stream = Box::pin(Finishing::new(stream, Arc::clone(&sender.finish)));
+        if self.with_stats {
+            let stats = StreamStats::new(self.buffer_size);
+            stream = Box::pin(stats::Counted::new(stream, stats.clone()));
+            if let Some((metrics, name)) = &self.metrics {
+                metrics.observe_stream(name.clone(), stats.clone());
+            }
+            sender.stats = Some(stats);
+        }
+        if let Some(callback) = self.on_close.take() {
+            stream = Box::pin(OnClose::new(stream, callback));
+        }
         (sender, stream)
     }
 }
@@ -156,10 +647,134 @@ pub fn create_stream_with_buffer<T: Send + 'static>(
     StreamBuilder::<T>::new().buffer_size(buffer_size).build()
 }
 
+/// Minimal `next()` future so the library does not depend on `futures-util`.
+pub(crate) struct NextItem<'a, T>(pub(crate) &'a mut EventStream<T>);
+
+impl<T> Future for NextItem<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.0.as_mut().poll_next(cx)
+    }
+}
+
 /// Extension trait for working with event streams.
 pub trait EventStreamExt<T> {
     /// Convert into a boxed stream.
     fn boxed(self) -> EventStream<T>;
+
+    /// Limit the stream to at most `per_second` items per second.
+    ///
+    /// Requires the `tokio` feature.
+    ///
+    /// Items are delayed rather than dropped, so the producer is slowed down
+    /// through backpressure. A rate of zero is treated as one.
+    #[cfg(feature = "tokio")]
+    fn throttle(self, per_second: u32) -> EventStream<T>
+    where
+        Self: Sized,
+        T: Send + 'static,
+    {
+        Box::pin(Throttle::new(self.boxed(), per_second))
+    }
+
+    /// Emit an item only after the stream has been quiet for `quiet`.
+    ///
+    /// Items superseded within the quiet period are dropped. Requires the
+    /// `tokio` feature.
+    #[cfg(feature = "tokio")]
+    fn debounce(self, quiet: Duration) -> EventStream<T>
+    where
+        Self: Sized,
+        T: Send + 'static,
+    {
+        Box::pin(Debounce::new(self.boxed(), quiet))
+    }
+
+    /// End the stream after the first terminal event.
+    ///
+    /// The terminal event itself is yielded. See [`EventKind`].
+    fn until_terminal(self) -> EventStream<T>
+    where
+        Self: Sized,
+        T: EventKind + Send + 'static,
+    {
+        Box::pin(UntilTerminal::new(self.boxed()))
+    }
+
+    /// Drop events below `min`.
+    ///
+    /// Events without a [level](EventKind::level) and terminal events
+    /// always pass. Apply it to each consumer's stream to give consumers
+    /// different levels.
+    fn min_level(self, min: Level) -> EventStream<T>
+    where
+        Self: Sized,
+        T: EventKind + Send + 'static,
+    {
+        Box::pin(MinLevel::new(self.boxed(), min))
+    }
+
+    /// Collapse runs of consecutive equal events into their first event.
+    fn dedup_events(self) -> EventStream<T>
+    where
+        Self: Sized,
+        T: Clone + PartialEq + Send + 'static,
+    {
+        Box::pin(Dedup::new(self.boxed(), T::clone))
+    }
+
+    /// Collapse runs of consecutive events with equal keys into their first
+    /// event.
+    ///
+    /// Use it when events carry data that should not count, such as a
+    /// timestamp on otherwise repeated progress.
+    fn dedup_by_key<K, F>(self, key: F) -> EventStream<T>
+    where
+        Self: Sized,
+        T: Send + 'static,
+        K: PartialEq + Send + 'static,
+        F: FnMut(&T) -> K + Send + 'static,
+    {
+        Box::pin(Dedup::new(self.boxed(), key))
+    }
+
+    /// Make the stream pausable.
+    ///
+    /// Returns the wrapped stream and a handle to pause and resume it. The
+    /// policy decides what happens to events that arrive while paused.
+    fn pausable(self, policy: PausePolicy) -> (PausableStream<T>, PauseHandle)
+    where
+        Self: Sized,
+        T: Send + 'static,
+    {
+        PausableStream::new(self.boxed(), policy)
+    }
+
+    /// Emit the latest item once every `period`, dropping the rest.
+    ///
+    /// Requires the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    fn sample(self, period: Duration) -> EventStream<T>
+    where
+        Self: Sized,
+        T: Send + 'static,
+    {
+        Box::pin(Sample::new(self.boxed(), period))
+    }
+
+    /// Report a stall whenever no event arrives within `heartbeat`.
+    ///
+    /// Requires the `tokio` feature. Use [`Watchdog`] to also cancel runs
+    /// that stay stalled.
+    #[cfg(feature = "tokio")]
+    fn watchdog(self, heartbeat: Duration) -> EventStream<WatchdogItem<T>>
+    where
+        Self: Sized,
+        T: Send + 'static,
+    {
+        Watchdog::new(heartbeat).watch(self.boxed())
+    }
 }
 
 impl<S, T> EventStreamExt<T> for S
@@ -175,6 +790,7 @@ where
 mod tests {
     use super::*;
     use futures::StreamExt;
+    use std::time::Duration;
 
     #[derive(Debug, Clone, PartialEq)]
     enum TestEvent {
@@ -183,9 +799,15 @@ mod tests {
         Complete,
     }
 
+    impl EventKind for TestEvent {
+        fn is_terminal(&self) -> bool {
+            matches!(self, TestEvent::Complete)
+        }
+    }
+
     #[tokio::test]
     async fn test_stream_builder() {
-        let (sender, mut stream) = StreamBuilder::<TestEvent>::new()
+        let (sender, stream) = StreamBuilder::<TestEvent>::new()
             .buffer_size(10)
             .build();
 
@@ -207,7 +829,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_create_stream() {
-        let (sender, mut stream) = create_stream::<String>();
+        let (sender, stream) = create_stream::<String>();
 
         sender.send("Hello".to_string()).await.unwrap();
         sender.send("World".to_string()).await.unwrap();
@@ -219,7 +841,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_sender_clone() {
-        let (sender, mut stream) = create_stream::<u32>();
+        let (sender, stream) = create_stream::<u32>();
 
         let sender2 = sender.clone();
         sender.send(1).await.unwrap();
@@ -241,4 +863,87 @@ mod tests {
         // (depends on timing, so just test it doesn't panic)
         let _ = sender.try_send(2);
     }
+
+    #[tokio::test]
+    async fn test_reserve_and_send_all() {
+        let (sender, stream) = create_stream_with_buffer::<u32>(2);
+
+        let permit = sender.reserve().await.unwrap();
+        permit.send(1);
+        let permits = sender.reserve_many(1).await.unwrap();
+        for permit in permits {
+            permit.send(2);
+        }
+        assert_eq!(
+            sender.reserve_many(3).await.unwrap_err(),
+            ReserveError::TooMany(3)
+        );
+
+        let consumer = tokio::spawn(stream.collect::<Vec<_>>());
+        assert_eq!(sender.send_all(3..6).await, Ok(3));
+        drop(sender);
+
+        assert_eq!(consumer.await.unwrap(), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn test_on_close() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (sender, stream) = StreamBuilder::<u32>::new()
+            .on_close(move || tx.send(()).unwrap())
+            .build();
+
+        sender.send(1).await.unwrap();
+        assert!(rx.try_recv().is_err());
+        drop(stream);
+        rx.try_recv().unwrap();
+        assert!(sender.is_closed());
+        sender.closed().await;
+    }
+
+    #[tokio::test]
+    async fn test_finish_closes_clones() {
+        let (sender, stream) = create_stream::<TestEvent>();
+        let clone = sender.clone();
+        clone.send(TestEvent::Start).await.unwrap();
+        sender.finish(TestEvent::Complete).await.unwrap();
+
+        assert!(clone.is_closed());
+        clone.closed().await;
+        assert!(clone.send(TestEvent::Progress(1)).await.is_err());
+        assert_eq!(
+            clone.try_send(TestEvent::Progress(2)),
+            Err(TestEvent::Progress(2))
+        );
+        assert!(clone.clone().finish(TestEvent::Complete).await.is_err());
+
+        // The stream ends while the clone is still alive
+        let events: Vec<_> = stream.collect().await;
+        assert_eq!(events, vec![TestEvent::Start, TestEvent::Complete]);
+        drop(clone);
+    }
+
+    #[tokio::test]
+    async fn test_finish_wakes_waiting_stream() {
+        let (sender, mut stream) = create_stream::<TestEvent>();
+        let clone = sender.clone();
+
+        let consumer = tokio::spawn(async move {
+            let mut events = Vec::new();
+            while let Some(event) = stream.next().await {
+                events.push(event);
+            }
+            events
+        });
+        tokio::task::yield_now().await;
+        clone.send(TestEvent::Progress(1)).await.unwrap();
+        sender.finish(TestEvent::Complete).await.unwrap();
+
+        let events = tokio::time::timeout(Duration::from_secs(5), consumer)
+            .await
+            .expect("stream did not end after finish")
+            .unwrap();
+        assert_eq!(events, vec![TestEvent::Progress(1), TestEvent::Complete]);
+        assert!(clone.is_closed());
+    }
 }
@@ -0,0 +1,998 @@
+//! Pluggable channel backends for [`StreamBuilder`](super::StreamBuilder).
+//!
+//! With the default `tokio` feature, [`TokioBackend`] (a tokio mpsc channel)
+//! is the default. Without it, the default is [`StdBackend`], which only uses
+//! `std` synchronization and works on any executor, including the browser
+//! event loop on `wasm32-unknown-unknown`. Other backends are
+//! available behind features: [`FlumeBackend`] (`flume`),
+//! [`CrossbeamBackend`] (`crossbeam`), and [`AsyncChannelBackend`]
+//! (`async-std` or `smol`). Implement [`ChannelBackend`] to plug in your own.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use super::{EventSender, EventStream};
+
+/// Error returned when a non-blocking send fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel buffer is full
+    Full(T),
+    /// The receiver has been dropped
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    /// Return the event that could not be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(v) | Self::Closed(v) => v,
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => write!(f, "channel full"),
+            Self::Closed(_) => write!(f, "channel closed"),
+        }
+    }
+}
+
+/// Error returned when reserving room in a channel fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReserveError {
+    /// The receiver has been dropped
+    Closed,
+    /// The channel can never hold this many events at once
+    TooMany(usize),
+    /// The backend does not support reservations
+    Unsupported,
+}
+
+impl fmt::Display for ReserveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => write!(f, "channel closed"),
+            Self::TooMany(n) => write!(f, "cannot reserve {n} slots, more than the channel holds"),
+            Self::Unsupported => write!(f, "channel backend does not support reservations"),
+        }
+    }
+}
+
+/// Future returned by [`BackendSender::send`].
+pub type SendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<(), T>> + Send + 'a>>;
+
+/// Sending half of a channel created by a [`ChannelBackend`].
+pub trait BackendSender<T>: Send + Sync {
+    /// Send an event, waiting for buffer space.
+    ///
+    /// Resolves to `Err(event)` if the receiver was dropped.
+    fn send(&self, event: T) -> SendFuture<'_, T>;
+
+    /// Send an event without waiting.
+    fn try_send(&self, event: T) -> Result<(), TrySendError<T>>;
+
+    /// Check if the receiver has been dropped.
+    fn is_closed(&self) -> bool;
+
+    /// Poll for the receiver being dropped.
+    ///
+    /// Returns `Poll::Pending` until then, and arranges for the task in `cx`
+    /// to be woken when the receiver is dropped.
+    fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()>;
+
+    /// Get the remaining buffer capacity.
+    fn capacity(&self) -> usize;
+
+    /// Poll for room for `n` events and reserve it.
+    ///
+    /// Once this returns `Poll::Ready(Ok(()))`, the next `n` calls to
+    /// [`send_reserved`](Self::send_reserved) do not need to wait, and
+    /// slots that go unused are given back through
+    /// [`release`](Self::release). The default does not support
+    /// reservations and fails with [`ReserveError::Unsupported`].
+    fn poll_reserve(&self, cx: &mut Context<'_>, n: usize) -> Poll<Result<(), ReserveError>> {
+        let _ = (cx, n);
+        Poll::Ready(Err(ReserveError::Unsupported))
+    }
+
+    /// Send an event into a slot reserved by
+    /// [`poll_reserve`](Self::poll_reserve).
+    ///
+    /// The event is dropped if the receiver is gone.
+    fn send_reserved(&self, event: T) {
+        let _ = self.try_send(event);
+    }
+
+    /// Give back `n` reserved slots that will not be used.
+    fn release(&self, n: usize) {
+        let _ = n;
+    }
+
+    /// Clone this sender into a new boxed sender.
+    fn clone_sender(&self) -> Box<dyn BackendSender<T>>;
+}
+
+/// A channel implementation used by [`StreamBuilder`](super::StreamBuilder).
+pub trait ChannelBackend {
+    /// Returns the backend name, for diagnostics and benchmarks.
+    fn name(&self) -> &str;
+
+    /// Create a bounded channel.
+    fn channel<T: Send + 'static>(&self, buffer_size: usize) -> (EventSender<T>, EventStream<T>);
+}
+
+/// The backend used by [`StreamBuilder::new`](super::StreamBuilder::new).
+#[cfg(feature = "tokio")]
+pub type DefaultBackend = TokioBackend;
+
+/// The backend used by [`StreamBuilder::new`](super::StreamBuilder::new).
+#[cfg(not(feature = "tokio"))]
+pub type DefaultBackend = StdBackend;
+
+#[cfg(feature = "tokio")]
+pub use self::tokio_backend::TokioBackend;
+
+#[cfg(feature = "tokio")]
+mod tokio_backend {
+    use super::*;
+
+    use tokio::sync::mpsc;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    /// Backend using a tokio mpsc channel. Requires the `tokio` feature.
+    ///
+    /// Supports [`EventSender::reserve`].
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct TokioBackend;
+
+    impl ChannelBackend for TokioBackend {
+        fn name(&self) -> &str {
+            "tokio"
+        }
+
+        fn channel<T: Send + 'static>(
+            &self,
+            buffer_size: usize,
+        ) -> (EventSender<T>, EventStream<T>) {
+            let (tx, rx) = mpsc::channel(buffer_size.max(1));
+            (EventSender::new(tx), Box::pin(ReceiverStream::new(rx)))
+        }
+    }
+}
+
+/// Runtime-agnostic backend built on `std` synchronization primitives.
+///
+/// Works with any executor, including `futures::executor::block_on` and
+/// `wasm-bindgen-futures`, so it is the backend to use in WASM builds.
+/// Supports [`EventSender::reserve`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdBackend;
+
+impl ChannelBackend for StdBackend {
+    fn name(&self) -> &str {
+        "std"
+    }
+
+    fn channel<T: Send + 'static>(&self, buffer_size: usize) -> (EventSender<T>, EventStream<T>) {
+        let chan = Arc::new(Mutex::new(StdChannel {
+            queue: VecDeque::new(),
+            capacity: buffer_size.max(1),
+            reserved: 0,
+            senders: 1,
+            receiver_alive: true,
+            receiver_waker: None,
+            sender_wakers: Vec::new(),
+        }));
+        let sender = StdSender {
+            chan: Arc::clone(&chan),
+        };
+        (
+            EventSender::from_backend(Box::new(sender)),
+            Box::pin(StdReceiver { chan }),
+        )
+    }
+}
+
+struct StdChannel<T> {
+    queue: VecDeque<T>,
+    capacity: usize,
+    /// Slots held by unused send permits
+    reserved: usize,
+    senders: usize,
+    receiver_alive: bool,
+    receiver_waker: Option<Waker>,
+    sender_wakers: Vec<Waker>,
+}
+
+impl<T> StdChannel<T> {
+    fn push(&mut self, event: T, waker: Option<&Waker>) -> Result<(), TrySendError<T>> {
+        if !self.receiver_alive {
+            return Err(TrySendError::Closed(event));
+        }
+        if self.queue.len() + self.reserved >= self.capacity {
+            if let Some(waker) = waker {
+                self.sender_wakers.push(waker.clone());
+            }
+            return Err(TrySendError::Full(event));
+        }
+        self.queue.push_back(event);
+        if let Some(waker) = self.receiver_waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    fn reserve(&mut self, n: usize, waker: &Waker) -> Poll<Result<(), ReserveError>> {
+        if !self.receiver_alive {
+            return Poll::Ready(Err(ReserveError::Closed));
+        }
+        if n > self.capacity {
+            return Poll::Ready(Err(ReserveError::TooMany(n)));
+        }
+        if self.queue.len() + self.reserved + n > self.capacity {
+            self.sender_wakers.push(waker.clone());
+            return Poll::Pending;
+        }
+        self.reserved += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+struct StdSender<T> {
+    chan: Arc<Mutex<StdChannel<T>>>,
+}
+
+impl<T: Send + 'static> BackendSender<T> for StdSender<T> {
+    fn send(&self, event: T) -> SendFuture<'_, T> {
+        let mut slot = Some(event);
+        Box::pin(std::future::poll_fn(move |cx| {
+            let event = slot.take().expect("polled after completion");
+            let mut chan = self.chan.lock().unwrap();
+            match chan.push(event, Some(cx.waker())) {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(TrySendError::Closed(v)) => Poll::Ready(Err(v)),
+                Err(TrySendError::Full(v)) => {
+                    slot = Some(v);
+                    Poll::Pending
+                }
+            }
+        }))
+    }
+
+    fn try_send(&self, event: T) -> Result<(), TrySendError<T>> {
+        self.chan.lock().unwrap().push(event, None)
+    }
+
+    fn is_closed(&self) -> bool {
+        !self.chan.lock().unwrap().receiver_alive
+    }
+
+    fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut chan = self.chan.lock().unwrap();
+        if !chan.receiver_alive {
+            return Poll::Ready(());
+        }
+        chan.sender_wakers.push(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn capacity(&self) -> usize {
+        let chan = self.chan.lock().unwrap();
+        chan.capacity
+            .saturating_sub(chan.queue.len() + chan.reserved)
+    }
+
+    fn poll_reserve(&self, cx: &mut Context<'_>, n: usize) -> Poll<Result<(), ReserveError>> {
+        self.chan.lock().unwrap().reserve(n, cx.waker())
+    }
+
+    fn send_reserved(&self, event: T) {
+        let mut chan = self.chan.lock().unwrap();
+        chan.reserved -= 1;
+        let _ = chan.push(event, None);
+    }
+
+    fn release(&self, n: usize) {
+        let mut chan = self.chan.lock().unwrap();
+        chan.reserved -= n;
+        for waker in chan.sender_wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn clone_sender(&self) -> Box<dyn BackendSender<T>> {
+        self.chan.lock().unwrap().senders += 1;
+        Box::new(StdSender {
+            chan: Arc::clone(&self.chan),
+        })
+    }
+}
+
+impl<T> Drop for StdSender<T> {
+    fn drop(&mut self) {
+        let mut chan = self.chan.lock().unwrap();
+        chan.senders -= 1;
+        if chan.senders == 0 {
+            if let Some(waker) = chan.receiver_waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+struct StdReceiver<T> {
+    chan: Arc<Mutex<StdChannel<T>>>,
+}
+
+impl<T> Stream for StdReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut chan = self.chan.lock().unwrap();
+        if let Some(event) = chan.queue.pop_front() {
+            for waker in chan.sender_wakers.drain(..) {
+                waker.wake();
+            }
+            return Poll::Ready(Some(event));
+        }
+        if chan.senders == 0 {
+            return Poll::Ready(None);
+        }
+        chan.receiver_waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for StdReceiver<T> {
+    fn drop(&mut self) {
+        let mut chan = self.chan.lock().unwrap();
+        chan.receiver_alive = false;
+        chan.queue.clear();
+        for waker in chan.sender_wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Senders waiting in [`BackendSender::poll_closed`] on a channel that
+/// cannot notify them itself.
+#[cfg(any(feature = "async-std", feature = "smol", feature = "flume"))]
+#[derive(Default)]
+struct CloseWakers(Mutex<Vec<Waker>>);
+
+#[cfg(any(feature = "async-std", feature = "smol", feature = "flume"))]
+impl CloseWakers {
+    fn poll(&self, cx: &mut Context<'_>, is_closed: impl Fn() -> bool) -> Poll<()> {
+        if is_closed() {
+            return Poll::Ready(());
+        }
+        self.0.lock().unwrap().push(cx.waker().clone());
+        // Check again so a drop between the check and the registration is not missed.
+        if is_closed() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Receiving stream that wakes its [`CloseWakers`] once dropped.
+#[cfg(any(feature = "async-std", feature = "smol", feature = "flume"))]
+struct WakeOnClose<T> {
+    inner: Option<EventStream<T>>,
+    wakers: Arc<CloseWakers>,
+}
+
+#[cfg(any(feature = "async-std", feature = "smol", feature = "flume"))]
+impl<T> Stream for WakeOnClose<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.as_mut().poll_next(cx),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner
+            .as_ref()
+            .map_or((0, Some(0)), |inner| inner.size_hint())
+    }
+}
+
+#[cfg(any(feature = "async-std", feature = "smol", feature = "flume"))]
+impl<T> Drop for WakeOnClose<T> {
+    fn drop(&mut self) {
+        // Close the channel before waking, so woken senders see it closed.
+        drop(self.inner.take());
+        for waker in self.wakers.0.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(any(feature = "async-std", feature = "smol"))]
+pub use self::async_channel_backend::AsyncChannelBackend;
+
+#[cfg(any(feature = "async-std", feature = "smol"))]
+mod async_channel_backend {
+    use super::*;
+
+    /// Backend using an [async-channel](https://docs.rs/async-channel) channel,
+    /// the channel used by async-std and smol. Requires the `async-std` or
+    /// `smol` feature.
+    ///
+    /// Does not support reservations: [`EventSender::reserve`] fails with
+    /// [`ReserveError::Unsupported`].
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct AsyncChannelBackend;
+
+    impl ChannelBackend for AsyncChannelBackend {
+        fn name(&self) -> &str {
+            "async-channel"
+        }
+
+        fn channel<T: Send + 'static>(
+            &self,
+            buffer_size: usize,
+        ) -> (EventSender<T>, EventStream<T>) {
+            let (tx, rx) = async_channel::bounded(buffer_size.max(1));
+            let wakers = Arc::new(CloseWakers::default());
+            let sender = AsyncChannelSender {
+                tx,
+                wakers: Arc::clone(&wakers),
+            };
+            let stream = WakeOnClose {
+                inner: Some(Box::pin(rx)),
+                wakers,
+            };
+            (
+                EventSender::from_backend(Box::new(sender)),
+                Box::pin(stream),
+            )
+        }
+    }
+
+    struct AsyncChannelSender<T> {
+        tx: async_channel::Sender<T>,
+        wakers: Arc<CloseWakers>,
+    }
+
+    impl<T: Send + 'static> BackendSender<T> for AsyncChannelSender<T> {
+        fn send(&self, event: T) -> SendFuture<'_, T> {
+            Box::pin(async move { self.tx.send(event).await.map_err(|e| e.0) })
+        }
+
+        fn try_send(&self, event: T) -> Result<(), TrySendError<T>> {
+            self.tx.try_send(event).map_err(|e| match e {
+                async_channel::TrySendError::Full(v) => TrySendError::Full(v),
+                async_channel::TrySendError::Closed(v) => TrySendError::Closed(v),
+            })
+        }
+
+        fn is_closed(&self) -> bool {
+            self.tx.is_closed()
+        }
+
+        fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+            self.wakers.poll(cx, || self.tx.is_closed())
+        }
+
+        fn capacity(&self) -> usize {
+            self.tx
+                .capacity()
+                .map_or(usize::MAX, |cap| cap.saturating_sub(self.tx.len()))
+        }
+
+        fn clone_sender(&self) -> Box<dyn BackendSender<T>> {
+            Box::new(AsyncChannelSender {
+                tx: self.tx.clone(),
+                wakers: Arc::clone(&self.wakers),
+            })
+        }
+    }
+}
+
+#[cfg(feature = "flume")]
+pub use self::flume_backend::FlumeBackend;
+
+#[cfg(feature = "flume")]
+mod flume_backend {
+    use super::*;
+
+    /// Backend using a [flume](https://docs.rs/flume) channel.
+    ///
+    /// Does not support reservations: [`EventSender::reserve`] fails with
+    /// [`ReserveError::Unsupported`].
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct FlumeBackend;
+
+    impl ChannelBackend for FlumeBackend {
+        fn name(&self) -> &str {
+            "flume"
+        }
+
+        fn channel<T: Send + 'static>(
+            &self,
+            buffer_size: usize,
+        ) -> (EventSender<T>, EventStream<T>) {
+            let (tx, rx) = flume::bounded(buffer_size.max(1));
+            let wakers = Arc::new(CloseWakers::default());
+            let sender = FlumeSender {
+                tx,
+                wakers: Arc::clone(&wakers),
+            };
+            let stream = WakeOnClose {
+                inner: Some(Box::pin(rx.into_stream())),
+                wakers,
+            };
+            (
+                EventSender::from_backend(Box::new(sender)),
+                Box::pin(stream),
+            )
+        }
+    }
+
+    struct FlumeSender<T> {
+        tx: flume::Sender<T>,
+        wakers: Arc<CloseWakers>,
+    }
+
+    impl<T: Send + 'static> BackendSender<T> for FlumeSender<T> {
+        fn send(&self, event: T) -> SendFuture<'_, T> {
+            Box::pin(async move { self.tx.send_async(event).await.map_err(|e| e.0) })
+        }
+
+        fn try_send(&self, event: T) -> Result<(), TrySendError<T>> {
+            self.tx.try_send(event).map_err(|e| match e {
+                flume::TrySendError::Full(v) => TrySendError::Full(v),
+                flume::TrySendError::Disconnected(v) => TrySendError::Closed(v),
+            })
+        }
+
+        fn is_closed(&self) -> bool {
+            self.tx.is_disconnected()
+        }
+
+        fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+            self.wakers.poll(cx, || self.tx.is_disconnected())
+        }
+
+        fn capacity(&self) -> usize {
+            self.tx
+                .capacity()
+                .map_or(usize::MAX, |cap| cap.saturating_sub(self.tx.len()))
+        }
+
+        fn clone_sender(&self) -> Box<dyn BackendSender<T>> {
+            Box::new(FlumeSender {
+                tx: self.tx.clone(),
+                wakers: Arc::clone(&self.wakers),
+            })
+        }
+    }
+}
+
+#[cfg(feature = "crossbeam")]
+pub use self::crossbeam_backend::CrossbeamBackend;
+
+#[cfg(feature = "crossbeam")]
+mod crossbeam_backend {
+    use super::*;
+
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+
+    use crossbeam_channel::{Receiver, Sender, TryRecvError};
+    use futures_core::Stream;
+
+    /// Backend using a [crossbeam](https://docs.rs/crossbeam-channel) channel.
+    ///
+    /// Crossbeam channels are not async-aware; this backend adds waker
+    /// bookkeeping so they can drive an [`EventStream`]. Reservations are
+    /// not supported: [`EventSender::reserve`] fails with
+    /// [`ReserveError::Unsupported`].
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct CrossbeamBackend;
+
+    impl ChannelBackend for CrossbeamBackend {
+        fn name(&self) -> &str {
+            "crossbeam"
+        }
+
+        fn channel<T: Send + 'static>(
+            &self,
+            buffer_size: usize,
+        ) -> (EventSender<T>, EventStream<T>) {
+            let (tx, rx) = crossbeam_channel::bounded(buffer_size.max(1));
+            let shared = Arc::new(Shared::default());
+            let sender = CrossbeamSender {
+                tx,
+                shared: Arc::clone(&shared),
+            };
+            let stream = CrossbeamStream { rx, shared };
+            (
+                EventSender::from_backend(Box::new(sender)),
+                Box::pin(stream),
+            )
+        }
+    }
+
+    #[derive(Default)]
+    struct Shared {
+        receiver_closed: AtomicBool,
+        receiver_waker: Mutex<Option<Waker>>,
+        sender_wakers: Mutex<Vec<Waker>>,
+    }
+
+    impl Shared {
+        fn wake_receiver(&self) {
+            if let Some(waker) = self.receiver_waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+
+        fn wake_senders(&self) {
+            for waker in self.sender_wakers.lock().unwrap().drain(..) {
+                waker.wake();
+            }
+        }
+    }
+
+    struct CrossbeamSender<T> {
+        tx: Sender<T>,
+        shared: Arc<Shared>,
+    }
+
+    impl<T> CrossbeamSender<T> {
+        fn poll_send(&self, slot: &mut Option<T>, cx: &mut Context<'_>) -> Poll<Result<(), T>> {
+            let event = slot.take().expect("polled after completion");
+            match self.try_send_raw(event) {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(TrySendError::Closed(v)) => Poll::Ready(Err(v)),
+                Err(TrySendError::Full(v)) => {
+                    self.shared
+                        .sender_wakers
+                        .lock()
+                        .unwrap()
+                        .push(cx.waker().clone());
+                    // Retry once after registering so a concurrent receive is not missed.
+                    match self.try_send_raw(v) {
+                        Ok(()) => Poll::Ready(Ok(())),
+                        Err(TrySendError::Closed(v)) => Poll::Ready(Err(v)),
+                        Err(TrySendError::Full(v)) => {
+                            *slot = Some(v);
+                            Poll::Pending
+                        }
+                    }
+                }
+            }
+        }
+
+        fn try_send_raw(&self, event: T) -> Result<(), TrySendError<T>> {
+            if self.shared.receiver_closed.load(Ordering::SeqCst) {
+                return Err(TrySendError::Closed(event));
+            }
+            match self.tx.try_send(event) {
+                Ok(()) => {
+                    self.shared.wake_receiver();
+                    Ok(())
+                }
+                Err(crossbeam_channel::TrySendError::Full(v)) => Err(TrySendError::Full(v)),
+                Err(crossbeam_channel::TrySendError::Disconnected(v)) => {
+                    Err(TrySendError::Closed(v))
+                }
+            }
+        }
+    }
+
+    impl<T: Send + 'static> BackendSender<T> for CrossbeamSender<T> {
+        fn send(&self, event: T) -> SendFuture<'_, T> {
+            let mut slot = Some(event);
+            Box::pin(std::future::poll_fn(move |cx| {
+                self.poll_send(&mut slot, cx)
+            }))
+        }
+
+        fn try_send(&self, event: T) -> Result<(), TrySendError<T>> {
+            self.try_send_raw(event)
+        }
+
+        fn is_closed(&self) -> bool {
+            self.shared.receiver_closed.load(Ordering::SeqCst)
+        }
+
+        fn poll_closed(&self, cx: &mut Context<'_>) -> Poll<()> {
+            if self.is_closed() {
+                return Poll::Ready(());
+            }
+            self.shared
+                .sender_wakers
+                .lock()
+                .unwrap()
+                .push(cx.waker().clone());
+            // Check again so a drop between the check and the registration is not missed.
+            if self.is_closed() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn capacity(&self) -> usize {
+            self.tx
+                .capacity()
+                .map_or(usize::MAX, |cap| cap.saturating_sub(self.tx.len()))
+        }
+
+        fn clone_sender(&self) -> Box<dyn BackendSender<T>> {
+            Box::new(CrossbeamSender {
+                tx: self.tx.clone(),
+                shared: Arc::clone(&self.shared),
+            })
+        }
+    }
+
+    impl<T> Drop for CrossbeamSender<T> {
+        fn drop(&mut self) {
+            // The receiver may be waiting to observe disconnection.
+            self.shared.wake_receiver();
+        }
+    }
+
+    struct CrossbeamStream<T> {
+        rx: Receiver<T>,
+        shared: Arc<Shared>,
+    }
+
+    impl<T> Stream for CrossbeamStream<T> {
+        type Item = T;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+            for attempt in 0..2 {
+                match self.rx.try_recv() {
+                    Ok(event) => {
+                        self.shared.wake_senders();
+                        return Poll::Ready(Some(event));
+                    }
+                    Err(TryRecvError::Disconnected) => return Poll::Ready(None),
+                    Err(TryRecvError::Empty) if attempt == 0 => {
+                        *self.shared.receiver_waker.lock().unwrap() = Some(cx.waker().clone());
+                    }
+                    Err(TryRecvError::Empty) => {}
+                }
+            }
+            Poll::Pending
+        }
+    }
+
+    impl<T> Drop for CrossbeamStream<T> {
+        fn drop(&mut self) {
+            self.shared.receiver_closed.store(true, Ordering::SeqCst);
+            self.shared.wake_senders();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::StreamBuilder;
+    use futures::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Wake;
+
+    async fn exercise<B: ChannelBackend>(backend: B) {
+        let (sender, stream) = StreamBuilder::<u32>::new()
+            .buffer_size(2)
+            .backend(backend)
+            .build();
+
+        let producer = tokio::spawn(async move {
+            for i in 0..10 {
+                sender.send(i).await.unwrap();
+            }
+        });
+
+        let events: Vec<_> = stream.collect().await;
+        producer.await.unwrap();
+        assert_eq!(events, (0..10).collect::<Vec<_>>());
+    }
+
+    async fn exercise_zero_buffer<B: ChannelBackend>(backend: B) {
+        let (sender, mut stream) = StreamBuilder::<u32>::new()
+            .buffer_size(0)
+            .backend(backend)
+            .build();
+
+        // Treated as a buffer of one, not a rendezvous channel
+        assert!(sender.try_send(1).is_ok());
+        assert_eq!(sender.try_send(2), Err(2));
+        assert_eq!(stream.next().await, Some(1));
+    }
+
+    async fn exercise_try_send<B: ChannelBackend>(backend: B) {
+        let (sender, stream) = StreamBuilder::<u32>::new()
+            .buffer_size(1)
+            .backend(backend)
+            .build();
+
+        assert!(sender.try_send(1).is_ok());
+        assert_eq!(sender.try_send(2), Err(2));
+        drop(stream);
+        assert!(sender.is_closed());
+        assert!(sender.send(3).await.is_err());
+    }
+
+    async fn exercise_reserve<B: ChannelBackend>(backend: B) {
+        let (sender, mut stream) = StreamBuilder::<u32>::new()
+            .buffer_size(2)
+            .backend(backend)
+            .build();
+
+        let permit = sender.reserve().await.unwrap();
+        assert!(sender.try_send(1).is_ok());
+        // The permit holds the last slot
+        assert_eq!(sender.try_send(2), Err(2));
+        permit.send(3);
+        assert_eq!(stream.next().await, Some(1));
+        assert_eq!(stream.next().await, Some(3));
+
+        // Unused permits give their slots back
+        drop(sender.reserve_many(2).await.unwrap());
+        assert!(sender.try_send(4).is_ok());
+        assert!(sender.try_send(5).is_ok());
+        assert_eq!(
+            sender.reserve_many(3).await.unwrap_err(),
+            ReserveError::TooMany(3)
+        );
+
+        drop(stream);
+        assert_eq!(sender.reserve().await.unwrap_err(), ReserveError::Closed);
+    }
+
+    #[derive(Default)]
+    struct CountWakes(AtomicUsize);
+
+    impl Wake for CountWakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    async fn exercise_closed<B: ChannelBackend + Clone>(backend: B) {
+        // Waiting must not spin: the waker only fires once the receiver drops
+        let (sender, stream) = StreamBuilder::<u32>::new().backend(backend.clone()).build();
+        let wakes = Arc::new(CountWakes::default());
+        let waker = Waker::from(Arc::clone(&wakes));
+        let mut cx = Context::from_waker(&waker);
+        let mut closed = std::pin::pin!(sender.closed());
+        assert!(closed.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+        drop(stream);
+        assert!(wakes.0.load(Ordering::SeqCst) > 0);
+        assert!(closed.as_mut().poll(&mut cx).is_ready());
+
+        let (sender, stream) = StreamBuilder::<u32>::new().backend(backend).build();
+
+        let waiter = tokio::spawn(async move { sender.closed().await });
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+        drop(stream);
+        waiter.await.unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_tokio_backend() {
+        assert_eq!(TokioBackend.name(), "tokio");
+        exercise(TokioBackend).await;
+        exercise_try_send(TokioBackend).await;
+        exercise_zero_buffer(TokioBackend).await;
+        exercise_closed(TokioBackend).await;
+        exercise_reserve(TokioBackend).await;
+    }
+
+    #[tokio::test]
+    async fn test_std_backend() {
+        exercise(StdBackend).await;
+        exercise_try_send(StdBackend).await;
+        exercise_zero_buffer(StdBackend).await;
+        exercise_closed(StdBackend).await;
+        exercise_reserve(StdBackend).await;
+    }
+
+    #[test]
+    fn test_std_backend_without_tokio_runtime() {
+        let (sender, stream) = StreamBuilder::<u32>::new()
+            .backend(StdBackend)
+            .buffer_size(4)
+            .build();
+        futures::executor::block_on(async {
+            sender.send(1).await.unwrap();
+            sender.send(2).await.unwrap();
+        });
+        drop(sender);
+
+        let events: Vec<_> = futures::executor::block_on_stream(stream).collect();
+        assert_eq!(events, vec![1, 2]);
+    }
+
+    #[cfg(any(feature = "async-std", feature = "smol"))]
+    #[tokio::test]
+    async fn test_async_channel_backend() {
+        exercise(AsyncChannelBackend).await;
+        exercise_try_send(AsyncChannelBackend).await;
+        exercise_zero_buffer(AsyncChannelBackend).await;
+        exercise_closed(AsyncChannelBackend).await;
+
+        let (sender, _stream) = StreamBuilder::<u32>::new()
+            .backend(AsyncChannelBackend)
+            .build();
+        assert_eq!(
+            sender.reserve().await.unwrap_err(),
+            ReserveError::Unsupported
+        );
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn test_smol_builder() {
+        let builder = StreamBuilder::<u32, AsyncChannelBackend>::smol();
+        assert_eq!(builder.backend_name(), "async-channel");
+    }
+
+    #[cfg(feature = "flume")]
+    #[tokio::test]
+    async fn test_flume_backend() {
+        exercise(FlumeBackend).await;
+        exercise_try_send(FlumeBackend).await;
+        exercise_zero_buffer(FlumeBackend).await;
+        exercise_closed(FlumeBackend).await;
+
+        let (sender, _stream) = StreamBuilder::<u32>::new().backend(FlumeBackend).build();
+        assert_eq!(
+            sender.reserve().await.unwrap_err(),
+            ReserveError::Unsupported
+        );
+    }
+
+    #[cfg(feature = "crossbeam")]
+    #[tokio::test]
+    async fn test_crossbeam_backend() {
+        exercise(CrossbeamBackend).await;
+        exercise_try_send(CrossbeamBackend).await;
+        exercise_zero_buffer(CrossbeamBackend).await;
+        exercise_closed(CrossbeamBackend).await;
+
+        let (sender, _stream) = StreamBuilder::<u32>::new()
+            .backend(CrossbeamBackend)
+            .build();
+        assert_eq!(
+            sender.reserve().await.unwrap_err(),
+            ReserveError::Unsupported
+        );
+    }
+
+    #[cfg(feature = "flume")]
+    #[test]
+    fn test_flume_backend_without_tokio_runtime() {
+        let (sender, mut stream) = StreamBuilder::<u32>::new().backend(FlumeBackend).build();
+        sender.try_send(5).unwrap();
+        drop(sender);
+
+        let events: Vec<_> = futures::executor::block_on_stream(&mut stream).collect();
+        assert_eq!(events, vec![5]);
+    }
+}
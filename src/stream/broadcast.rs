@@ -0,0 +1,359 @@
+//! Broadcasting one event stream to many subscribers.
+//!
+//! A [`Broadcaster`] delivers every event to each live subscriber through a
+//! bounded ring buffer. A subscriber that falls more than the buffer
+//! behind misses the oldest events; its [`BroadcastStream`] reports the gap
+//! as a [`BroadcastItem::Lagged`] item and counts it, and
+//! [`Broadcaster::lag`] collects the counts of every subscriber for
+//! dashboards. Broadcasting [`Envelope`]s that are also kept in an
+//! [`EventStore`] lets a subscriber repair gaps with
+//! [`BroadcastStream::catch_up_via`]. Requires the `tokio` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::sync::Arc;
+//!
+//! use rustratify::stream::{Broadcaster, Envelope, EventStore, MemoryEventStore};
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rustratify::ProviderError> {
+//! let store = Arc::new(MemoryEventStore::new());
+//! let broadcaster = Broadcaster::new(2);
+//! let subscriber = broadcaster.subscribe();
+//!
+//! for (sequence, step) in ["fetch", "build", "test"].into_iter().enumerate() {
+//!     let envelope = Envelope::new(sequence as u64, step).with_run_id("run-1");
+//!     store.append("run-1", envelope.clone()).await?;
+//!     broadcaster.send(envelope);
+//! }
+//! drop(broadcaster);
+//!
+//! // "fetch" fell out of the buffer and is read back from the store
+//! let steps: Vec<_> = subscriber
+//!     .catch_up_via(store, "run-1")
+//!     .map(|envelope| envelope.unwrap().event)
+//!     .collect()
+//!     .await;
+//! assert_eq!(steps, ["fetch", "build", "test"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::sync::broadcast::{self, error::RecvError, Receiver};
+
+use super::{Checkpoint, Envelope, EventStore, EventStream};
+use crate::error::ProviderResult;
+
+/// An item of a [`BroadcastStream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BroadcastItem<T> {
+    /// The next event
+    Event(T),
+    /// The subscriber fell behind and this many events were skipped
+    Lagged(u64),
+}
+
+/// Lag counters of one subscriber.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubscriberLag {
+    /// Identifier of the subscriber, unique per broadcaster
+    pub id: u64,
+    /// Events the subscriber missed in total
+    pub missed: u64,
+    /// How many times the subscriber fell behind
+    pub gaps: u64,
+}
+
+#[derive(Debug)]
+struct LagCounter {
+    id: u64,
+    missed: AtomicU64,
+    gaps: AtomicU64,
+}
+
+impl LagCounter {
+    fn snapshot(&self) -> SubscriberLag {
+        SubscriberLag {
+            id: self.id,
+            missed: self.missed.load(Ordering::Relaxed),
+            gaps: self.gaps.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Sends every event to all of its subscribers.
+///
+/// Sending never waits: each subscriber buffers up to `capacity` events,
+/// and a subscriber further behind loses the oldest ones. Cloning is cheap;
+/// clones send to the same subscribers.
+#[derive(Debug, Clone)]
+pub struct Broadcaster<T> {
+    tx: broadcast::Sender<T>,
+    subscribers: Arc<Mutex<Vec<Weak<LagCounter>>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl<T: Clone + Send + 'static> Broadcaster<T> {
+    /// Create a broadcaster whose subscribers each buffer up to `capacity`
+    /// events. A capacity of zero is treated as one.
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+        Self {
+            tx,
+            subscribers: Arc::default(),
+            next_id: Arc::default(),
+        }
+    }
+
+    /// Send `event` to every subscriber, returning how many there are.
+    pub fn send(&self, event: T) -> usize {
+        self.tx.send(event).unwrap_or(0)
+    }
+
+    /// Subscribe to the events sent from now on.
+    pub fn subscribe(&self) -> BroadcastStream<T> {
+        let lag = Arc::new(LagCounter {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            missed: AtomicU64::new(0),
+            gaps: AtomicU64::new(0),
+        });
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.retain(|counter| counter.strong_count() > 0);
+        subscribers.push(Arc::downgrade(&lag));
+        BroadcastStream {
+            recv: Some(recv(self.tx.subscribe())),
+            lag,
+        }
+    }
+
+    /// Number of live subscribers.
+    pub fn subscriber_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    /// Lag counters of every live subscriber, in subscription order.
+    pub fn lag(&self) -> Vec<SubscriberLag> {
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.retain(|counter| counter.strong_count() > 0);
+        subscribers
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|counter| counter.snapshot())
+            .collect()
+    }
+}
+
+type Recv<T> = Pin<Box<dyn Future<Output = (Result<T, RecvError>, Receiver<T>)> + Send>>;
+
+fn recv<T: Clone + Send + 'static>(mut rx: Receiver<T>) -> Recv<T> {
+    Box::pin(async move {
+        let result = rx.recv().await;
+        (result, rx)
+    })
+}
+
+/// Stream of one subscriber, returned by [`Broadcaster::subscribe`].
+///
+/// Ends once every clone of the broadcaster has been dropped and the
+/// buffered events have been read.
+pub struct BroadcastStream<T> {
+    recv: Option<Recv<T>>,
+    lag: Arc<LagCounter>,
+}
+
+impl<T> BroadcastStream<T> {
+    /// Lag counters of this subscriber.
+    pub fn lag(&self) -> SubscriberLag {
+        self.lag.snapshot()
+    }
+}
+
+impl<T: Clone + Send + 'static> Stream for BroadcastStream<T> {
+    type Item = BroadcastItem<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(pending) = self.recv.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let (result, rx) = std::task::ready!(pending.as_mut().poll(cx));
+        let item = match result {
+            Ok(event) => BroadcastItem::Event(event),
+            Err(RecvError::Lagged(missed)) => {
+                self.lag.missed.fetch_add(missed, Ordering::Relaxed);
+                self.lag.gaps.fetch_add(1, Ordering::Relaxed);
+                BroadcastItem::Lagged(missed)
+            }
+            Err(RecvError::Closed) => {
+                self.recv = None;
+                return Poll::Ready(None);
+            }
+        };
+        self.recv = Some(recv(rx));
+        Poll::Ready(Some(item))
+    }
+}
+
+impl<T: Clone + Send + 'static> BroadcastStream<Envelope<T>> {
+    /// Fill gaps from `store`, where the broadcast envelopes of `run_id`
+    /// are kept.
+    ///
+    /// Whenever the subscriber falls behind, the envelopes after the last
+    /// one delivered are read back from the store and delivered first;
+    /// envelopes seen already are skipped. Envelopes must be stored before
+    /// they are broadcast. A failed read is yielded as an error and the
+    /// stream carries on live.
+    pub fn catch_up_via<S>(
+        self,
+        store: Arc<S>,
+        run_id: impl Into<String>,
+    ) -> EventStream<ProviderResult<Envelope<T>>>
+    where
+        S: EventStore<Envelope<T>> + ?Sized + 'static,
+    {
+        Box::pin(CatchUp {
+            live: self,
+            store,
+            run_id: run_id.into(),
+            checkpoint: Checkpoint::new(),
+            reading: None,
+            replay: VecDeque::new(),
+        })
+    }
+}
+
+type Read<T> = Pin<Box<dyn Future<Output = ProviderResult<Vec<Envelope<T>>>> + Send>>;
+
+struct CatchUp<T, S: ?Sized> {
+    live: BroadcastStream<Envelope<T>>,
+    store: Arc<S>,
+    run_id: String,
+    checkpoint: Checkpoint,
+    reading: Option<Read<T>>,
+    replay: VecDeque<Envelope<T>>,
+}
+
+// The pending read is boxed, so nothing here is pinned in place.
+impl<T, S: ?Sized> Unpin for CatchUp<T, S> {}
+
+impl<T, S> Stream for CatchUp<T, S>
+where
+    T: Clone + Send + 'static,
+    S: EventStore<Envelope<T>> + ?Sized + 'static,
+{
+    type Item = ProviderResult<Envelope<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            while let Some(envelope) = this.replay.pop_front() {
+                if this.checkpoint.is_new(&envelope) {
+                    this.checkpoint.record(&envelope);
+                    return Poll::Ready(Some(Ok(envelope)));
+                }
+            }
+            if let Some(reading) = this.reading.as_mut() {
+                let result = std::task::ready!(reading.as_mut().poll(cx));
+                this.reading = None;
+                match result {
+                    Ok(mut envelopes) => {
+                        envelopes.sort_by_key(|envelope| envelope.sequence);
+                        this.replay = envelopes.into();
+                        continue;
+                    }
+                    Err(e) => return Poll::Ready(Some(Err(e))),
+                }
+            }
+            match std::task::ready!(Pin::new(&mut this.live).poll_next(cx)) {
+                Some(BroadcastItem::Event(envelope)) => {
+                    if this.checkpoint.is_new(&envelope) {
+                        this.checkpoint.record(&envelope);
+                        return Poll::Ready(Some(Ok(envelope)));
+                    }
+                }
+                Some(BroadcastItem::Lagged(_)) => {
+                    let store = Arc::clone(&this.store);
+                    let run_id = this.run_id.clone();
+                    let checkpoint = this.checkpoint;
+                    this.reading = Some(Box::pin(async move {
+                        let mut envelopes = store.read(&run_id).await?;
+                        envelopes.retain(|envelope| checkpoint.is_new(envelope));
+                        Ok(envelopes)
+                    }));
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::MemoryEventStore;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_broadcast_reports_lag() {
+        let broadcaster = Broadcaster::new(2);
+        let mut fast = broadcaster.subscribe();
+        let slow = broadcaster.subscribe();
+        assert_eq!(broadcaster.subscriber_count(), 2);
+
+        for i in 0..2 {
+            broadcaster.send(i);
+            assert_eq!(fast.next().await, Some(BroadcastItem::Event(i)));
+        }
+        for i in 2..5 {
+            broadcaster.send(i);
+        }
+
+        let items: Vec<_> = slow.take(3).collect().await;
+        assert_eq!(
+            items,
+            [
+                BroadcastItem::Lagged(3),
+                BroadcastItem::Event(3),
+                BroadcastItem::Event(4)
+            ]
+        );
+        assert_eq!(fast.next().await, Some(BroadcastItem::Lagged(1)));
+        assert_eq!(fast.lag().missed, 1);
+
+        let lag = broadcaster.lag();
+        assert_eq!(lag.len(), 1);
+        assert_eq!(lag[0].id, 0);
+        assert_eq!(lag[0].gaps, 1);
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_skips_duplicates() {
+        let store = Arc::new(MemoryEventStore::new());
+        let broadcaster = Broadcaster::new(1);
+        let subscriber = broadcaster.subscribe();
+
+        for sequence in 0..4u64 {
+            let envelope = Envelope::new(sequence, sequence * 10);
+            store.append("run", envelope.clone()).await.unwrap();
+            broadcaster.send(envelope);
+        }
+        drop(broadcaster);
+
+        let events: Vec<_> = subscriber
+            .catch_up_via(store, "run")
+            .map(|envelope| envelope.unwrap().event)
+            .collect()
+            .await;
+        assert_eq!(events, [0, 10, 20, 30]);
+    }
+}
@@ -0,0 +1,159 @@
+//! Resuming enveloped streams after a reconnect.
+//!
+//! A consumer of an enveloped stream records the sequence number of each
+//! envelope it has finished processing in a [`Checkpoint`] and keeps the
+//! checkpoint somewhere durable. When the producer sends with
+//! [`EnvelopeSender::send_durable`](super::EnvelopeSender::send_durable),
+//! every envelope is also kept in an [`EventStore`], so after a reconnect
+//! [`resume_from`] replays exactly the envelopes the consumer had not
+//! processed yet, and [`Checkpoint::is_new`] skips the ones the live stream
+//! delivers again.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::stream::{resume_from, Checkpoint, MemoryEventStore, StreamBuilder};
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rustratify::ProviderError> {
+//! let store = MemoryEventStore::new();
+//! let (sender, mut stream) = StreamBuilder::<&str>::new().enveloped();
+//! let sender = sender.with_run_id("run-1");
+//! for step in ["fetch", "build", "test"] {
+//!     sender.send_durable(&store, step).await?;
+//! }
+//!
+//! // Process one envelope, then lose the connection
+//! let mut checkpoint = Checkpoint::new();
+//! checkpoint.record(&stream.next().await.unwrap());
+//! drop(stream);
+//!
+//! let rest: Vec<_> = resume_from(&store, "run-1", checkpoint)
+//!     .await?
+//!     .map(|envelope| envelope.event)
+//!     .collect()
+//!     .await;
+//! assert_eq!(rest, ["build", "test"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Envelope, EventStore, EventStream};
+use crate::error::ProviderResult;
+
+/// The position of a consumer in an enveloped stream: the sequence number
+/// of the last envelope it processed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint {
+    last: Option<u64>,
+}
+
+impl Checkpoint {
+    /// A checkpoint before the first envelope.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A checkpoint after the envelope numbered `sequence`.
+    pub fn at(sequence: u64) -> Self {
+        Self {
+            last: Some(sequence),
+        }
+    }
+
+    /// The sequence number of the last envelope processed, if any.
+    pub fn last(&self) -> Option<u64> {
+        self.last
+    }
+
+    /// The sequence number of the first envelope not yet processed.
+    pub fn next_sequence(&self) -> u64 {
+        self.last.map_or(0, |last| last.saturating_add(1))
+    }
+
+    /// Mark the envelope numbered `sequence`, and every one before it, as
+    /// processed. Never moves the checkpoint back.
+    pub fn commit(&mut self, sequence: u64) {
+        self.last = Some(self.last.map_or(sequence, |last| last.max(sequence)));
+    }
+
+    /// Mark `envelope`, and every one before it, as processed.
+    pub fn record<T>(&mut self, envelope: &Envelope<T>) {
+        self.commit(envelope.sequence);
+    }
+
+    /// Check if `envelope` comes after the checkpoint.
+    pub fn is_new<T>(&self, envelope: &Envelope<T>) -> bool {
+        envelope.sequence >= self.next_sequence()
+    }
+}
+
+/// Replay the envelopes of `run_id` kept in `store` that come after
+/// `checkpoint`, in sequence order.
+pub async fn resume_from<T, S>(
+    store: &S,
+    run_id: &str,
+    checkpoint: Checkpoint,
+) -> ProviderResult<EventStream<Envelope<T>>>
+where
+    T: Send + 'static,
+    S: EventStore<Envelope<T>> + ?Sized,
+{
+    // Stores make no promise about the order they read back in
+    let mut envelopes = store.read(run_id).await?;
+    envelopes.retain(|envelope| checkpoint.is_new(envelope));
+    envelopes.sort_by_key(|envelope| envelope.sequence);
+    Ok(Box::pin(super::store::Replay::new(envelopes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{MemoryEventStore, StreamBuilder};
+    use futures::StreamExt;
+
+    #[test]
+    fn test_checkpoint_only_moves_forward() {
+        let mut checkpoint = Checkpoint::new();
+        assert_eq!((checkpoint.last(), checkpoint.next_sequence()), (None, 0));
+        checkpoint.commit(4);
+        checkpoint.commit(2);
+        assert_eq!(checkpoint, Checkpoint::at(4));
+        assert!(!checkpoint.is_new(&Envelope::new(4, ())));
+        assert!(checkpoint.is_new(&Envelope::new(5, ())));
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_processed_and_live_duplicates() {
+        let store = MemoryEventStore::new();
+        let (sender, mut live) = StreamBuilder::<u32>::new().buffer_size(8).enveloped();
+        let sender = sender.with_run_id("run-1");
+        for n in 0..4 {
+            sender.send_durable(&store, n).await.unwrap();
+        }
+
+        let mut checkpoint = Checkpoint::new();
+        checkpoint.record(&live.next().await.unwrap());
+        checkpoint.record(&live.next().await.unwrap());
+
+        let replayed: Vec<_> = resume_from(&store, "run-1", checkpoint)
+            .await
+            .unwrap()
+            .map(|envelope| envelope.event)
+            .collect()
+            .await;
+        assert_eq!(replayed, [2, 3]);
+
+        checkpoint.commit(3);
+        sender.send_durable(&store, 4).await.unwrap();
+        drop(sender);
+        let fresh: Vec<_> = live
+            .filter(|envelope| std::future::ready(checkpoint.is_new(envelope)))
+            .map(|envelope| envelope.event)
+            .collect()
+            .await;
+        assert_eq!(fresh, [4]);
+    }
+}
@@ -0,0 +1,49 @@
+//! Notifying producers when the receiver goes away.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use super::EventStream;
+
+/// Callback run once the receiving stream is dropped.
+pub(crate) type CloseCallback = Box<dyn FnOnce() + Send>;
+
+/// Stream wrapper that runs a [`CloseCallback`] when dropped.
+pub(crate) struct OnClose<T> {
+    inner: EventStream<T>,
+    // Declared after `inner` so the receiver is closed before the callback runs.
+    _guard: CloseGuard,
+}
+
+impl<T> OnClose<T> {
+    pub(crate) fn new(inner: EventStream<T>, callback: CloseCallback) -> Self {
+        Self {
+            inner,
+            _guard: CloseGuard(Some(callback)),
+        }
+    }
+}
+
+impl<T> Stream for OnClose<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+struct CloseGuard(Option<CloseCallback>);
+
+impl Drop for CloseGuard {
+    fn drop(&mut self) {
+        if let Some(callback) = self.0.take() {
+            callback();
+        }
+    }
+}
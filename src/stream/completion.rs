@@ -0,0 +1,289 @@
+//! Terminal-event protocol for event streams.
+//!
+//! SEA event streams signal completion in-band: the producer sends a final
+//! event for which [`EventKind::is_terminal`] returns `true`, and consumers
+//! stop reading once they see it. [`EventSender::finish`](super::EventSender::finish)
+//! and [`EventStreamExt::until_terminal`](super::EventStreamExt::until_terminal)
+//! implement the two halves of that contract.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::SystemTime;
+
+use futures_core::Stream;
+
+use super::{EventStream, Level};
+
+/// Classifies events so generic code can detect completion and failure.
+///
+/// With the `derive` feature, `#[derive(EventKind)]` implements it for an
+/// enum from `#[event(terminal)]`, `#[event(error)]`,
+/// `#[event(level = "...")]` and `#[event(kind = "...")]` variant
+/// attributes and an `#[event(timestamp)]` field.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::EventKind;
+///
+/// enum JobEvent {
+///     Progress(u32),
+///     Completed,
+///     Failed(String),
+/// }
+///
+/// impl EventKind for JobEvent {
+///     fn is_terminal(&self) -> bool {
+///         matches!(self, JobEvent::Completed | JobEvent::Failed(_))
+///     }
+///
+///     fn is_error(&self) -> bool {
+///         matches!(self, JobEvent::Failed(_))
+///     }
+/// }
+/// ```
+pub trait EventKind {
+    /// Returns whether this is the last event of the stream.
+    fn is_terminal(&self) -> bool;
+
+    /// Returns whether this event reports a failure.
+    fn is_error(&self) -> bool {
+        false
+    }
+
+    /// Returns how verbose this event is, for
+    /// [`min_level`](super::EventStreamExt::min_level) filtering.
+    ///
+    /// Events without a level are never filtered out.
+    fn level(&self) -> Option<Level> {
+        None
+    }
+
+    /// Returns a short name for the kind of event, e.g. for logs or SSE
+    /// event names.
+    ///
+    /// Defaults to the type name.
+    fn kind(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Returns when the event happened, if it records that itself.
+    fn timestamp(&self) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// A domain event for SEA event streams.
+///
+/// An alias for [`EventKind`], implemented for every type that implements
+/// it, so either name can bound generic code. With the `derive` feature,
+/// `#[derive(Event)]` implements `EventKind` from the same attributes as
+/// `#[derive(EventKind)]`; derive one or the other.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::{Event, EventKind};
+///
+/// struct Done;
+///
+/// impl EventKind for Done {
+///     fn is_terminal(&self) -> bool {
+///         true
+///     }
+/// }
+///
+/// fn last<E: Event>(events: &[E]) -> bool {
+///     events.last().is_some_and(|e| e.is_terminal())
+/// }
+///
+/// assert!(last(&[Done]));
+/// ```
+pub trait Event: EventKind {}
+
+impl<T: EventKind + ?Sized> Event for T {}
+
+/// Stream returned by [`EventStreamExt::until_terminal`](super::EventStreamExt::until_terminal).
+///
+/// Yields items up to and including the first terminal event, then ends and
+/// drops the source so that remaining senders observe a closed channel.
+pub struct UntilTerminal<T> {
+    inner: Option<EventStream<T>>,
+}
+
+impl<T> UntilTerminal<T> {
+    pub(crate) fn new(inner: EventStream<T>) -> Self {
+        Self { inner: Some(inner) }
+    }
+}
+
+impl<T: EventKind> Stream for UntilTerminal<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        match inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                if item.is_terminal() {
+                    self.inner = None;
+                }
+                Poll::Ready(Some(item))
+            }
+            Poll::Ready(None) => {
+                self.inner = None;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Finish state shared by an [`EventSender`](super::EventSender), its clones,
+/// and the stream they feed.
+#[derive(Debug, Default)]
+pub(crate) struct FinishState {
+    /// Set once a sender starts finishing; later sends fail.
+    finished: AtomicBool,
+    /// Set once the terminal event is in the channel.
+    delivered: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl FinishState {
+    /// Mark the stream finished. Returns `false` if it already was.
+    pub(crate) fn start(&self) -> bool {
+        !self.finished.swap(true, Ordering::AcqRel)
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    /// Record that the terminal event was sent and wake the stream.
+    pub(crate) fn delivered(&self) {
+        self.delivered.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Stream wrapper that ends once a sender has
+/// [finished](super::EventSender::finish) the stream and its terminal event
+/// has been read.
+///
+/// The source is dropped when it ends, so that remaining senders observe a
+/// closed channel.
+pub(crate) struct Finishing<T> {
+    inner: Option<EventStream<T>>,
+    state: Arc<FinishState>,
+}
+
+impl<T> Finishing<T> {
+    pub(crate) fn new(inner: EventStream<T>, state: Arc<FinishState>) -> Self {
+        Self {
+            inner: Some(inner),
+            state,
+        }
+    }
+}
+
+impl<T> Stream for Finishing<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        // Read before polling: the terminal event was queued before the flag
+        // was set, so once it is set an empty channel means it has been read.
+        let delivered = self.state.delivered.load(Ordering::Acquire);
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        match inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(item)) => Poll::Ready(Some(item)),
+            Poll::Pending if !delivered => {
+                *self.state.waker.lock().unwrap() = Some(cx.waker().clone());
+                if self.state.delivered.load(Ordering::Acquire) {
+                    cx.waker().wake_by_ref();
+                }
+                Poll::Pending
+            }
+            Poll::Ready(None) | Poll::Pending => {
+                self.inner = None;
+                Poll::Ready(None)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            Some(inner) => (0, inner.size_hint().1),
+            None => (0, Some(0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{create_stream, EventStreamExt};
+    use futures::StreamExt;
+
+    #[derive(Debug, PartialEq)]
+    enum TestEvent {
+        Progress(u32),
+        Done,
+        Failed,
+    }
+
+    impl EventKind for TestEvent {
+        fn is_terminal(&self) -> bool {
+            matches!(self, TestEvent::Done | TestEvent::Failed)
+        }
+
+        fn is_error(&self) -> bool {
+            matches!(self, TestEvent::Failed)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_until_terminal_stops_after_terminal_event() {
+        let (sender, stream) = create_stream::<TestEvent>();
+        let extra = sender.clone();
+
+        sender.send(TestEvent::Progress(1)).await.unwrap();
+        sender.finish(TestEvent::Done).await.unwrap();
+
+        let mut stream = stream.until_terminal();
+        assert_eq!(stream.next().await, Some(TestEvent::Progress(1)));
+        assert_eq!(stream.next().await, Some(TestEvent::Done));
+        assert_eq!(stream.next().await, None);
+
+        // The receiver is gone, so remaining senders see a closed channel.
+        assert!(extra.is_closed());
+        assert!(extra.send(TestEvent::Progress(2)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_until_terminal_ends_with_source() {
+        let (sender, stream) = create_stream::<TestEvent>();
+        sender.send(TestEvent::Progress(1)).await.unwrap();
+        drop(sender);
+
+        let events: Vec<_> = stream.until_terminal().collect().await;
+        assert_eq!(events, vec![TestEvent::Progress(1)]);
+    }
+
+    #[test]
+    fn test_event_kind_classification() {
+        assert!(!TestEvent::Progress(0).is_terminal());
+        assert!(TestEvent::Done.is_terminal());
+        assert!(!TestEvent::Done.is_error());
+        assert!(TestEvent::Failed.is_error());
+    }
+}
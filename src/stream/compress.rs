@@ -0,0 +1,424 @@
+//! Compressed framing for serialized event streams.
+//!
+//! Large runs ship megabytes of JSON across remote adapters. This module
+//! packs serialized bytes into self-describing frames: a one-byte codec
+//! tag, the compressed length as a big-endian `u32`, then the compressed
+//! data. Decoders detect the codec from the tag, so a reader does not need
+//! to know how the writer was configured. Frames may come from untrusted
+//! peers, so decoders enforce [`FrameLimits`] on both the compressed and
+//! the decompressed size.
+//!
+//! Requires the `gzip` or `zstd` feature, which enable the matching
+//! [`Compression`] variant.
+//!
+//! # Example
+//!
+//! ```rust
+//! # #[cfg(all(feature = "gzip", feature = "json"))]
+//! # async fn example() {
+//! use rustratify::stream::compress::{compress_stream, decompress_stream, Compression};
+//! use rustratify::stream::create_stream;
+//! use rustratify::stream::serde::to_json_lines;
+//! use rustratify::stream::TryEventStreamExt;
+//!
+//! let (sender, stream) = create_stream::<u32>();
+//! sender.send(1).await.unwrap();
+//! sender.send(2).await.unwrap();
+//! drop(sender);
+//!
+//! let frames = compress_stream(to_json_lines(stream), Compression::Gzip)
+//!     .collect_ok()
+//!     .await
+//!     .unwrap();
+//! let reader = std::io::Cursor::new(frames.concat());
+//! let lines = decompress_stream(reader).collect_ok().await.unwrap();
+//! assert_eq!(lines.concat(), b"1\n2\n");
+//! # }
+//! ```
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use super::EventStream;
+use crate::error::RustratifyError;
+
+/// Bytes before the compressed data in every frame.
+const HEADER_LEN: usize = 5;
+
+/// Uncompressed bytes [`compress_stream`] collects into one frame at most.
+const MAX_BATCH: usize = 64 * 1024;
+
+/// Size limits enforced when reading frames.
+///
+/// The length in a frame header is trusted only up to `max_frame_len`, and
+/// decompression stops with an error past `max_decoded_len`, so a corrupt or
+/// malicious frame cannot exhaust memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLimits {
+    /// Largest compressed frame body accepted, in bytes (default 16 MiB)
+    pub max_frame_len: usize,
+    /// Largest decompressed frame accepted, in bytes (default 64 MiB)
+    pub max_decoded_len: usize,
+}
+
+impl FrameLimits {
+    /// Create limits for the compressed and decompressed size of a frame.
+    pub fn new(max_frame_len: usize, max_decoded_len: usize) -> Self {
+        Self {
+            max_frame_len,
+            max_decoded_len,
+        }
+    }
+}
+
+impl Default for FrameLimits {
+    fn default() -> Self {
+        Self::new(16 * 1024 * 1024, 64 * 1024 * 1024)
+    }
+}
+
+/// A compression codec for frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// gzip, at the default level. Requires the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Zstandard, at the default level. Requires the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    fn tag(self) -> u8 {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => 1,
+            #[cfg(feature = "zstd")]
+            Self::Zstd => 2,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self, RustratifyError> {
+        match tag {
+            #[cfg(feature = "gzip")]
+            1 => Ok(Self::Gzip),
+            #[cfg(feature = "zstd")]
+            2 => Ok(Self::Zstd),
+            other => Err(RustratifyError::Stream(format!(
+                "unsupported compression tag {other}"
+            ))),
+        }
+    }
+
+    fn encode(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                use std::io::Write;
+
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+        }
+    }
+
+    fn decode(self, data: &[u8], max_len: usize) -> std::io::Result<Vec<u8>> {
+        use std::io::Read;
+
+        // Read one byte past the limit to tell a full frame from an oversized one
+        let limit = (max_len as u64).saturating_add(1);
+        let mut out = Vec::new();
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => {
+                flate2::read::GzDecoder::new(data)
+                    .take(limit)
+                    .read_to_end(&mut out)?;
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                zstd::stream::read::Decoder::new(data)?
+                    .take(limit)
+                    .read_to_end(&mut out)?;
+            }
+        }
+        if out.len() > max_len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("decompressed frame exceeds {max_len} bytes"),
+            ));
+        }
+        Ok(out)
+    }
+}
+
+/// Compress `data` into a single frame.
+pub fn compress(data: &[u8], compression: Compression) -> Result<Vec<u8>, RustratifyError> {
+    let body = compression
+        .encode(data)
+        .map_err(|e| RustratifyError::Stream(format!("compression failed: {e}")))?;
+    let len = u32::try_from(body.len())
+        .map_err(|_| RustratifyError::Stream("compressed frame too large".to_string()))?;
+    let mut frame = Vec::with_capacity(HEADER_LEN + body.len());
+    frame.push(compression.tag());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(&body);
+    Ok(frame)
+}
+
+/// Decompress a single frame produced by [`compress`], within the default
+/// [`FrameLimits`].
+///
+/// Fails if `frame` is not exactly one complete frame.
+pub fn decompress(frame: &[u8]) -> Result<Vec<u8>, RustratifyError> {
+    decompress_with_limits(frame, FrameLimits::default())
+}
+
+/// Decompress a single frame produced by [`compress`], within `limits`.
+pub fn decompress_with_limits(
+    frame: &[u8],
+    limits: FrameLimits,
+) -> Result<Vec<u8>, RustratifyError> {
+    match frame_len(frame, limits)? {
+        Some(len) if len == frame.len() => decode_frame(frame, limits),
+        _ => Err(RustratifyError::Stream(
+            "payload is not a single compressed frame".to_string(),
+        )),
+    }
+}
+
+/// Total length of the frame at the start of `buf`, once its header is in.
+fn frame_len(buf: &[u8], limits: FrameLimits) -> Result<Option<usize>, RustratifyError> {
+    let Some(header) = buf.get(..HEADER_LEN) else {
+        return Ok(None);
+    };
+    Compression::from_tag(header[0])?;
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if len > limits.max_frame_len {
+        return Err(RustratifyError::Stream(format!(
+            "compressed frame of {len} bytes exceeds the limit of {}",
+            limits.max_frame_len
+        )));
+    }
+    Ok(Some(HEADER_LEN + len))
+}
+
+fn decode_frame(frame: &[u8], limits: FrameLimits) -> Result<Vec<u8>, RustratifyError> {
+    Compression::from_tag(frame[0])?
+        .decode(&frame[HEADER_LEN..], limits.max_decoded_len)
+        .map_err(|e| RustratifyError::Stream(format!("decompression failed: {e}")))
+}
+
+/// Compress a stream of serialized bytes, such as the output of
+/// [`to_json_lines`](super::serde::to_json_lines), into frames.
+///
+/// Chunks that are ready together are compressed together, up to 64 KiB,
+/// so bursts compress well without delaying a quiet stream. A batch that
+/// fails to compress is yielded as an error in place of its frame.
+pub fn compress_stream(
+    stream: EventStream<Bytes>,
+    compression: Compression,
+) -> EventStream<Result<Bytes, RustratifyError>> {
+    Box::pin(CompressFrames {
+        inner: stream,
+        compression,
+        batch: Vec::new(),
+        done: false,
+    })
+}
+
+struct CompressFrames {
+    inner: EventStream<Bytes>,
+    compression: Compression,
+    batch: Vec<u8>,
+    done: bool,
+}
+
+impl Stream for CompressFrames {
+    type Item = Result<Bytes, RustratifyError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        while !this.done && this.batch.len() < MAX_BATCH {
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(chunk)) => this.batch.extend_from_slice(&chunk),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+        }
+        if this.batch.is_empty() {
+            return if this.done {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            };
+        }
+        let batch = std::mem::take(&mut this.batch);
+        Poll::Ready(Some(compress(&batch, this.compression).map(Bytes::from)))
+    }
+}
+
+/// Read frames from `reader` and yield their decompressed contents, within
+/// the default [`FrameLimits`].
+///
+/// Each item is the data of one frame. I/O errors, unknown codecs, corrupt
+/// or oversized frames and a truncated last frame are yielded as errors.
+pub fn decompress_stream<R>(reader: R) -> EventStream<Result<Bytes, RustratifyError>>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    decompress_stream_with_limits(reader, FrameLimits::default())
+}
+
+/// Read frames from `reader` and yield their decompressed contents, within
+/// `limits`.
+///
+/// A frame whose header exceeds `limits` ends the stream with an error,
+/// since the stream cannot be resynchronized after it.
+pub fn decompress_stream_with_limits<R>(
+    reader: R,
+    limits: FrameLimits,
+) -> EventStream<Result<Bytes, RustratifyError>>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    Box::pin(DecompressFrames {
+        reader,
+        limits,
+        buf: Vec::new(),
+        done: false,
+    })
+}
+
+struct DecompressFrames<R> {
+    reader: R,
+    limits: FrameLimits,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> Stream for DecompressFrames<R> {
+    type Item = Result<Bytes, RustratifyError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            match frame_len(&this.buf, this.limits) {
+                Ok(Some(len)) if this.buf.len() >= len => {
+                    let frame: Vec<u8> = this.buf.drain(..len).collect();
+                    let data = decode_frame(&frame, this.limits);
+                    return Poll::Ready(Some(data.map(Bytes::from)));
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // The stream cannot be resynchronized after a bad header.
+                    this.buf.clear();
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+            if this.done {
+                if this.buf.is_empty() {
+                    return Poll::Ready(None);
+                }
+                this.buf.clear();
+                return Poll::Ready(Some(Err(RustratifyError::Stream(
+                    "truncated compressed frame".to_string(),
+                ))));
+            }
+
+            let mut chunk = [0u8; 8 * 1024];
+            let mut read = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.reader).poll_read(cx, &mut read) {
+                Poll::Ready(Ok(())) if read.filled().is_empty() => this.done = true,
+                Poll::Ready(Ok(())) => this.buf.extend_from_slice(read.filled()),
+                Poll::Ready(Err(e)) => {
+                    return Poll::Ready(Some(Err(RustratifyError::Stream(e.to_string()))))
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::TryEventStreamExt;
+    use futures::StreamExt;
+
+    fn codecs() -> Vec<Compression> {
+        vec![
+            #[cfg(feature = "gzip")]
+            Compression::Gzip,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+        ]
+    }
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let data = b"{\"progress\":50}\n".repeat(100);
+        for codec in codecs() {
+            let frame = compress(&data, codec).unwrap();
+            assert!(frame.len() < data.len());
+            assert_eq!(decompress(&frame).unwrap(), data);
+            assert!(decompress(&frame[..frame.len() - 1]).is_err());
+        }
+        assert!(decompress(&[9, 0, 0, 0, 0]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compress_stream_roundtrip() {
+        for codec in codecs() {
+            let chunks = (0..1000).map(|i| Bytes::from(format!("{i}\n")));
+            let frames = compress_stream(futures::stream::iter(chunks).boxed(), codec)
+                .collect_ok()
+                .await
+                .unwrap();
+            let bytes = frames.concat();
+
+            let reader = std::io::Cursor::new(bytes.clone());
+            let decoded = decompress_stream(reader).collect_ok().await.unwrap();
+            let expected: String = (0..1000).map(|i| format!("{i}\n")).collect();
+            assert_eq!(decoded.concat(), expected.as_bytes());
+
+            let truncated = std::io::Cursor::new(bytes[..bytes.len() - 1].to_vec());
+            assert!(decompress_stream(truncated).collect_ok().await.is_err());
+        }
+    }
+    #[tokio::test]
+    async fn test_limits_reject_oversized_frames() {
+        let data = vec![0u8; 4096];
+        for codec in codecs() {
+            let frame = compress(&data, codec).unwrap();
+            let body = frame.len() - HEADER_LEN;
+            assert!(decompress_with_limits(&frame, FrameLimits::new(body, 4096)).is_ok());
+            // Decompresses far beyond its compressed size
+            let err = decompress_with_limits(&frame, FrameLimits::new(body, 4095)).unwrap_err();
+            assert!(err.to_string().contains("exceeds 4095 bytes"), "{err}");
+            assert!(decompress_with_limits(&frame, FrameLimits::new(body - 1, 4096)).is_err());
+
+            // A header claiming a huge frame is rejected before it is buffered
+            let mut header = vec![frame[0]];
+            header.extend_from_slice(&u32::MAX.to_be_bytes());
+            let reader = std::io::Cursor::new(header);
+            let results: Vec<_> = decompress_stream(reader).collect().await;
+            assert_eq!(results.len(), 1);
+            assert!(results[0]
+                .as_ref()
+                .unwrap_err()
+                .to_string()
+                .contains("exceeds the limit"));
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! Dead-letter handling for events dropped by lossy delivery.
+//!
+//! A [`DeadLetterSink`] receives every event that a drop-based policy
+//! discards, together with the reason, and keeps per-reason counts. Attach one
+//! with [`StreamBuilder::dead_letter`](super::StreamBuilder::dead_letter) for
+//! [`EventSender::offer`](super::EventSender::offer) and
+//! [`EnvelopeSender::offer`](super::EnvelopeSender::offer), or with
+//! [`PausableStream::dead_letter`](super::PausableStream::dead_letter) for
+//! events discarded while paused.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::{EventStream, StreamBuilder};
+
+/// Why an event was dead-lettered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DeadLetterReason {
+    /// The channel buffer was full
+    Full,
+    /// The receiver had been dropped
+    Closed,
+    /// The stream was paused with a dropping policy
+    Paused,
+    /// A newer event replaced this one before it was delivered
+    Superseded,
+    /// Another clone of an [`EnvelopeSender`](super::EnvelopeSender) was
+    /// sending, and the event could not wait for its turn
+    Busy,
+}
+
+impl fmt::Display for DeadLetterReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "buffer full"),
+            Self::Closed => write!(f, "receiver closed"),
+            Self::Paused => write!(f, "stream paused"),
+            Self::Superseded => write!(f, "superseded"),
+            Self::Busy => write!(f, "sender busy"),
+        }
+    }
+}
+
+/// An event that could not be delivered.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeadLetter<T> {
+    /// The undelivered event
+    pub event: T,
+    /// Why it was not delivered
+    pub reason: DeadLetterReason,
+}
+
+#[derive(Default)]
+struct Counts {
+    full: AtomicU64,
+    closed: AtomicU64,
+    paused: AtomicU64,
+    superseded: AtomicU64,
+    busy: AtomicU64,
+    lost: AtomicU64,
+}
+
+type Handler<T> = Box<dyn Fn(DeadLetter<T>) -> Result<(), DeadLetter<T>> + Send + Sync>;
+
+/// Destination for dead-lettered events.
+///
+/// Cloning the sink is cheap; clones share the handler and counts.
+pub struct DeadLetterSink<T> {
+    handler: Arc<Handler<T>>,
+    counts: Arc<Counts>,
+}
+
+impl<T: Send + 'static> DeadLetterSink<T> {
+    /// Create a sink that calls `f` for every dead letter.
+    pub fn from_fn<F>(f: F) -> Self
+    where
+        F: Fn(DeadLetter<T>) + Send + Sync + 'static,
+    {
+        Self::with_handler(Box::new(move |letter| {
+            f(letter);
+            Ok(())
+        }))
+    }
+
+    /// Create a sink that forwards dead letters to a secondary stream.
+    ///
+    /// Forwarding never waits: if the secondary stream's buffer is full, the
+    /// dead letter is discarded and counted in [`lost`](Self::lost).
+    pub fn channel(buffer_size: usize) -> (Self, EventStream<DeadLetter<T>>) {
+        let (tx, stream) = StreamBuilder::new().buffer_size(buffer_size).build();
+        let sink = Self::with_handler(Box::new(move |letter| tx.try_send(letter)));
+        (sink, stream)
+    }
+
+    /// Create a sink that only counts dead letters.
+    pub fn counting() -> Self {
+        Self::from_fn(|_| {})
+    }
+
+    fn with_handler(handler: Handler<T>) -> Self {
+        Self {
+            handler: Arc::new(handler),
+            counts: Arc::new(Counts::default()),
+        }
+    }
+}
+
+impl<T> DeadLetterSink<T> {
+    /// Route an undelivered event to the sink.
+    pub fn send(&self, event: T, reason: DeadLetterReason) {
+        let counter = match reason {
+            DeadLetterReason::Full => &self.counts.full,
+            DeadLetterReason::Closed => &self.counts.closed,
+            DeadLetterReason::Paused => &self.counts.paused,
+            DeadLetterReason::Superseded => &self.counts.superseded,
+            DeadLetterReason::Busy => &self.counts.busy,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if (self.handler)(DeadLetter { event, reason }).is_err() {
+            self.counts.lost.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Get the number of dead letters for a reason.
+    pub fn count(&self, reason: DeadLetterReason) -> u64 {
+        match reason {
+            DeadLetterReason::Full => self.counts.full.load(Ordering::Relaxed),
+            DeadLetterReason::Closed => self.counts.closed.load(Ordering::Relaxed),
+            DeadLetterReason::Paused => self.counts.paused.load(Ordering::Relaxed),
+            DeadLetterReason::Superseded => self.counts.superseded.load(Ordering::Relaxed),
+            DeadLetterReason::Busy => self.counts.busy.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get the total number of dead letters.
+    pub fn total(&self) -> u64 {
+        [
+            DeadLetterReason::Full,
+            DeadLetterReason::Closed,
+            DeadLetterReason::Paused,
+            DeadLetterReason::Superseded,
+            DeadLetterReason::Busy,
+        ]
+        .into_iter()
+        .map(|reason| self.count(reason))
+        .sum()
+    }
+
+    /// Get the number of dead letters the handler itself could not accept.
+    pub fn lost(&self) -> u64 {
+        self.counts.lost.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Clone for DeadLetterSink<T> {
+    fn clone(&self) -> Self {
+        Self {
+            handler: Arc::clone(&self.handler),
+            counts: Arc::clone(&self.counts),
+        }
+    }
+}
+
+impl<T> fmt::Debug for DeadLetterSink<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeadLetterSink")
+            .field("total", &self.total())
+            .field("lost", &self.lost())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{create_stream, EventStreamExt, PausePolicy, StreamBuilder};
+    use futures::StreamExt;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_offer_routes_overflow_to_callback() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_clone = Arc::clone(&seen);
+        let sink = DeadLetterSink::from_fn(move |letter: DeadLetter<u32>| {
+            seen_clone.lock().unwrap().push(letter);
+        });
+
+        let (sender, stream) = StreamBuilder::<u32>::new()
+            .buffer_size(1)
+            .dead_letter(sink.clone())
+            .build();
+
+        assert!(sender.offer(1));
+        assert!(!sender.offer(2));
+        drop(stream);
+        assert!(!sender.offer(3));
+
+        assert_eq!(sink.count(DeadLetterReason::Full), 1);
+        assert_eq!(sink.count(DeadLetterReason::Closed), 1);
+        assert_eq!(sink.total(), 2);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                DeadLetter {
+                    event: 2,
+                    reason: DeadLetterReason::Full
+                },
+                DeadLetter {
+                    event: 3,
+                    reason: DeadLetterReason::Closed
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_channel_sink_and_paused_stream() {
+        let (sink, dead) = DeadLetterSink::<u32>::channel(8);
+        let (sender, stream) = create_stream::<u32>();
+        let (stream, handle) = stream.pausable(PausePolicy::KeepLatest);
+        let mut stream = stream.dead_letter(sink.clone());
+
+        handle.pause();
+        for i in 1..=3 {
+            sender.send(i).await.unwrap();
+        }
+        let next = tokio::time::timeout(Duration::from_millis(20), stream.next()).await;
+        assert!(next.is_err());
+        handle.resume();
+        assert_eq!(stream.next().await, Some(3));
+
+        drop(sink);
+        drop(stream);
+        let letters: Vec<_> = dead.take(2).collect().await;
+        assert_eq!(letters[0].event, 1);
+        assert_eq!(letters[1].reason, DeadLetterReason::Superseded);
+    }
+
+    #[test]
+    fn test_full_channel_sink_counts_lost() {
+        let (sink, _dead) = DeadLetterSink::<u32>::channel(1);
+        sink.send(1, DeadLetterReason::Full);
+        sink.send(2, DeadLetterReason::Full);
+        assert_eq!(sink.total(), 2);
+        assert_eq!(sink.lost(), 1);
+    }
+}
@@ -0,0 +1,106 @@
+//! Collapsing of repeated events.
+//!
+//! Chatty providers often report the same progress or status many times in
+//! a row. [`EventStreamExt::dedup_events`](super::EventStreamExt::dedup_events)
+//! and [`EventStreamExt::dedup_by_key`](super::EventStreamExt::dedup_by_key)
+//! pass only the first of each run of consecutive equal events.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use super::EventStream;
+
+/// Stream returned by [`EventStreamExt::dedup_events`](super::EventStreamExt::dedup_events)
+/// and [`EventStreamExt::dedup_by_key`](super::EventStreamExt::dedup_by_key).
+///
+/// Drops an event when its key equals the key of the event before it.
+/// Events that differ only from an earlier, non-adjacent event still pass.
+pub struct Dedup<T, K> {
+    inner: EventStream<T>,
+    key: Box<dyn FnMut(&T) -> K + Send>,
+    last: Option<K>,
+}
+
+impl<T, K> Dedup<T, K> {
+    pub(crate) fn new(inner: EventStream<T>, key: impl FnMut(&T) -> K + Send + 'static) -> Self {
+        Self {
+            inner,
+            key: Box::new(key),
+            last: None,
+        }
+    }
+}
+
+// The last key is never pinned.
+impl<T, K> Unpin for Dedup<T, K> {}
+
+impl<T, K: PartialEq> Stream for Dedup<T, K> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = &mut *self;
+        loop {
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    let key = (this.key)(&item);
+                    if this.last.as_ref() != Some(&key) {
+                        this.last = Some(key);
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::EventStreamExt;
+    use futures::StreamExt;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Event {
+        Status(&'static str),
+        Progress(u32, &'static str),
+    }
+
+    #[tokio::test]
+    async fn test_dedup_events() {
+        let events = futures::stream::iter([
+            Event::Status("running"),
+            Event::Status("running"),
+            Event::Status("waiting"),
+            Event::Status("running"),
+            Event::Status("running"),
+        ]);
+        let kept: Vec<_> = events.dedup_events().collect().await;
+        assert_eq!(
+            kept,
+            [
+                Event::Status("running"),
+                Event::Status("waiting"),
+                Event::Status("running"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dedup_by_key() {
+        let events = futures::stream::iter([
+            Event::Progress(10, "a"),
+            Event::Progress(10, "b"),
+            Event::Progress(20, "c"),
+        ]);
+        let kept: Vec<_> = events
+            .dedup_by_key(|e| match e {
+                Event::Progress(percent, _) => Some(*percent),
+                Event::Status(_) => None,
+            })
+            .collect()
+            .await;
+        assert_eq!(kept, [Event::Progress(10, "a"), Event::Progress(20, "c")]);
+    }
+}
@@ -0,0 +1,670 @@
+//! Event envelopes carrying ordering and correlation metadata.
+//!
+//! An [`EnvelopeSender`] stamps every event with a sequence number that is
+//! shared by all of its clones, plus a timestamp and optional run,
+//! correlation and causation identifiers, which can be taken from a
+//! [`Context`]. Consumers can use these to order and group
+//! events coming from several producers over the same stream.
+
+use std::collections::BTreeSet;
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+use std::time::SystemTime;
+
+use super::{
+    DeadLetterReason, DeadLetterSink, EventKind, EventSender, EventStore, Level, TrySendError,
+};
+use crate::context::Context;
+use crate::error::{ProviderError, ProviderResult};
+use crate::output::{Warning, Warnings};
+
+/// An event wrapped with metadata stamped at send time.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Envelope<T> {
+    /// Monotonic sequence number, unique per stream
+    pub sequence: u64,
+    /// Wall-clock time at which the event was sent
+    pub timestamp: SystemTime,
+    /// Identifier of the run that produced the event
+    pub run_id: Option<String>,
+    /// Identifier used to correlate related events
+    pub correlation_id: Option<String>,
+    /// Identifier of the message or operation that caused the event
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub causation_id: Option<String>,
+    /// Warnings raised since the previous event
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub warnings: Vec<Warning>,
+    /// The wrapped event
+    pub event: T,
+}
+
+impl<T> Envelope<T> {
+    /// Wrap an event with the given sequence number and the current time.
+    pub fn new(sequence: u64, event: T) -> Self {
+        Self {
+            sequence,
+            timestamp: SystemTime::now(),
+            run_id: None,
+            correlation_id: None,
+            causation_id: None,
+            warnings: Vec::new(),
+            event,
+        }
+    }
+
+    /// Set the run identifier.
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Set the correlation identifier.
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Set the causation identifier.
+    pub fn with_causation_id(mut self, causation_id: impl Into<String>) -> Self {
+        self.causation_id = Some(causation_id.into());
+        self
+    }
+
+    /// Attach a warning.
+    pub fn with_warning(mut self, warning: impl Into<Warning>) -> Self {
+        self.warnings.push(warning.into());
+        self
+    }
+
+    /// Transform the wrapped event, keeping the metadata.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Envelope<U> {
+        Envelope {
+            sequence: self.sequence,
+            timestamp: self.timestamp,
+            run_id: self.run_id,
+            correlation_id: self.correlation_id,
+            causation_id: self.causation_id,
+            warnings: self.warnings,
+            event: f(self.event),
+        }
+    }
+
+    /// Discard the metadata and return the wrapped event.
+    pub fn into_inner(self) -> T {
+        self.event
+    }
+}
+
+impl<T: EventKind> EventKind for Envelope<T> {
+    fn is_terminal(&self) -> bool {
+        self.event.is_terminal()
+    }
+
+    fn is_error(&self) -> bool {
+        self.event.is_error()
+    }
+
+    fn level(&self) -> Option<Level> {
+        self.event.level()
+    }
+
+    fn kind(&self) -> &str {
+        self.event.kind()
+    }
+
+    fn timestamp(&self) -> Option<SystemTime> {
+        Some(self.timestamp)
+    }
+}
+
+/// A sender that wraps events in [`Envelope`]s.
+///
+/// Created by [`StreamBuilder::enveloped`](super::StreamBuilder::enveloped).
+/// Clones share the sequence counter, so sequence numbers stay unique and
+/// increasing across every producer of the stream. Clones also take turns
+/// to send, each stamping its envelope only once it is its turn, so
+/// envelopes arrive in sequence order.
+#[derive(Debug)]
+pub struct EnvelopeSender<T> {
+    inner: EventSender<Envelope<T>>,
+    sequence: Arc<Sequencer>,
+    run_id: Option<String>,
+    correlation_id: Option<String>,
+    causation_id: Option<String>,
+    warnings: Option<Warnings>,
+    dead_letter: Option<DeadLetterSink<T>>,
+}
+
+impl<T> EnvelopeSender<T> {
+    pub(crate) fn new(
+        inner: EventSender<Envelope<T>>,
+        dead_letter: Option<DeadLetterSink<T>>,
+    ) -> Self {
+        Self {
+            inner,
+            sequence: Arc::default(),
+            run_id: None,
+            correlation_id: None,
+            causation_id: None,
+            warnings: None,
+            dead_letter,
+        }
+    }
+
+    /// Stamp every event sent through this sender with a run identifier.
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Stamp every event sent through this sender with a correlation identifier.
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Stamp every event sent through this sender with a causation identifier.
+    pub fn with_causation_id(mut self, causation_id: impl Into<String>) -> Self {
+        self.causation_id = Some(causation_id.into());
+        self
+    }
+
+    /// Stamp every event sent through this sender with the run,
+    /// correlation and causation identifiers `cx` has, and attach each
+    /// warning reported through `cx` to the next event sent.
+    pub fn with_context(mut self, cx: &Context) -> Self {
+        self.warnings = Some(cx.warnings().clone());
+        if let Some(run_id) = cx.run_id() {
+            self.run_id = Some(run_id.to_string());
+        }
+        if let Some(correlation_id) = cx.correlation_id() {
+            self.correlation_id = Some(correlation_id.to_string());
+        }
+        if let Some(causation_id) = cx.causation_id() {
+            self.causation_id = Some(causation_id.to_string());
+        }
+        self
+    }
+
+    /// Get the run identifier stamped on events, if any.
+    pub fn run_id(&self) -> Option<&str> {
+        self.run_id.as_deref()
+    }
+
+    /// Get the correlation identifier stamped on events, if any.
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    /// Get the causation identifier stamped on events, if any.
+    pub fn causation_id(&self) -> Option<&str> {
+        self.causation_id.as_deref()
+    }
+
+    /// Wrap and send an event.
+    ///
+    /// Returns `Err(event)` if the receiver was dropped.
+    pub async fn send(&self, event: T) -> Result<(), T> {
+        let turn = self.sequence.turn().await;
+        let (envelope, seen) = self.wrap(turn.sequence(), event, None);
+        self.inner
+            .send(envelope)
+            .await
+            .map_err(Envelope::into_inner)?;
+        self.commit(turn, seen);
+        Ok(())
+    }
+
+    /// Wrap and send an event with an explicit correlation identifier.
+    pub async fn send_correlated(
+        &self,
+        event: T,
+        correlation_id: impl Into<String>,
+    ) -> Result<(), T> {
+        let turn = self.sequence.turn().await;
+        let (envelope, seen) = self.wrap(turn.sequence(), event, Some(correlation_id.into()));
+        self.inner
+            .send(envelope)
+            .await
+            .map_err(Envelope::into_inner)?;
+        self.commit(turn, seen);
+        Ok(())
+    }
+
+    /// Wrap `event`, keep the envelope in `store` under the sender's run
+    /// identifier, then send it.
+    ///
+    /// Once stored, the event counts as delivered: if the receiver was
+    /// dropped, a consumer that reconnects replays it with
+    /// [`resume_from`](super::resume_from). Fails with
+    /// [`ProviderError::ConfigurationError`] if the sender has no run
+    /// identifier, or with the store's error, in which case nothing is sent.
+    pub async fn send_durable<S>(&self, store: &S, event: T) -> ProviderResult<()>
+    where
+        T: Clone + Send + 'static,
+        S: EventStore<Envelope<T>> + ?Sized,
+    {
+        let Some(run_id) = &self.run_id else {
+            return Err(ProviderError::ConfigurationError(
+                "durable sends need a run identifier".into(),
+            ));
+        };
+        let turn = self.sequence.turn().await;
+        let (envelope, seen) = self.wrap(turn.sequence(), event, None);
+        store.append(run_id, envelope.clone()).await?;
+        self.commit(turn, seen);
+        let _ = self.inner.send(envelope).await;
+        Ok(())
+    }
+
+    /// Try to wrap and send an event without waiting.
+    ///
+    /// Returns `Err(event)` if the channel is full or closed, or if a
+    /// clone of this sender is sending: envelopes go out in sequence order,
+    /// so the event cannot overtake the clone's even when the channel has
+    /// room. Use [`send`](Self::send) to wait for the turn instead.
+    pub fn try_send(&self, event: T) -> Result<(), T> {
+        let Some(turn) = self.sequence.try_turn() else {
+            return Err(event);
+        };
+        let (envelope, seen) = self.wrap(turn.sequence(), event, None);
+        self.inner
+            .try_send(envelope)
+            .map_err(Envelope::into_inner)?;
+        self.commit(turn, seen);
+        Ok(())
+    }
+
+    /// Wrap and send an event without waiting, dropping it if it cannot be
+    /// delivered.
+    ///
+    /// Undeliverable events are routed unwrapped to the dead-letter sink
+    /// configured with
+    /// [`StreamBuilder::dead_letter`](super::StreamBuilder::dead_letter), if
+    /// any. An event offered while a clone of this sender is sending cannot
+    /// overtake the clone's envelope, so it is dropped with
+    /// [`DeadLetterReason::Busy`] even if the channel has room. Returns
+    /// whether the event was delivered.
+    pub fn offer(&self, event: T) -> bool {
+        let result = match self.sequence.try_turn() {
+            _ if self.inner.is_closed() => Err((event, DeadLetterReason::Closed)),
+            None => Err((event, DeadLetterReason::Busy)),
+            Some(turn) => {
+                let (envelope, seen) = self.wrap(turn.sequence(), event, None);
+                match self.inner.try_send_raw(envelope) {
+                    Ok(()) => {
+                        self.commit(turn, seen);
+                        Ok(())
+                    }
+                    Err(TrySendError::Full(e)) => Err((e.into_inner(), DeadLetterReason::Full)),
+                    Err(TrySendError::Closed(e)) => Err((e.into_inner(), DeadLetterReason::Closed)),
+                }
+            }
+        };
+        self.inner.record(result.is_ok());
+        let Err((event, reason)) = result else {
+            return true;
+        };
+        if let Some(sink) = &self.dead_letter {
+            sink.send(event, reason);
+        }
+        false
+    }
+
+    /// Check if the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    /// Stamp `event`, returning the envelope and the position in the
+    /// warning log to hand out up to once it is delivered.
+    fn wrap(
+        &self,
+        sequence: u64,
+        event: T,
+        correlation_id: Option<String>,
+    ) -> (Envelope<T>, usize) {
+        // Only peek: a send that fails leaves the warnings for the next one
+        let (warnings, seen) = self
+            .warnings
+            .as_ref()
+            .map(Warnings::peek_new)
+            .unwrap_or_default();
+        let envelope = Envelope {
+            sequence,
+            timestamp: SystemTime::now(),
+            run_id: self.run_id.clone(),
+            correlation_id: correlation_id.or_else(|| self.correlation_id.clone()),
+            causation_id: self.causation_id.clone(),
+            warnings,
+            event,
+        };
+        (envelope, seen)
+    }
+
+    /// Use up the turn's sequence number and the warnings its envelope
+    /// carried.
+    fn commit(&self, mut turn: Turn<'_>, seen: usize) {
+        turn.commit();
+        if let Some(warnings) = &self.warnings {
+            warnings.mark_taken(seen);
+        }
+    }
+}
+
+impl<T> Clone for EnvelopeSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            sequence: Arc::clone(&self.sequence),
+            run_id: self.run_id.clone(),
+            correlation_id: self.correlation_id.clone(),
+            causation_id: self.causation_id.clone(),
+            warnings: self.warnings.clone(),
+            dead_letter: self.dead_letter.clone(),
+        }
+    }
+}
+
+/// The sequence counter shared by the clones of a sender, handed out in
+/// turns so that each clone stamps and sends as one step.
+#[derive(Debug, Default)]
+struct Sequencer {
+    state: Mutex<Turns>,
+}
+
+#[derive(Debug, Default)]
+struct Turns {
+    /// The next sequence number to stamp
+    sequence: u64,
+    /// The next ticket to hand out
+    next_ticket: u64,
+    /// The ticket whose turn it is
+    serving: u64,
+    /// Tickets given up before their turn came
+    abandoned: BTreeSet<u64>,
+    waiting: Vec<Waker>,
+}
+
+impl Sequencer {
+    fn lock(&self) -> MutexGuard<'_, Turns> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait for a turn, in the order turns were asked for.
+    async fn turn(&self) -> Turn<'_> {
+        let ticket = {
+            let mut turns = self.lock();
+            turns.next_ticket += 1;
+            turns.next_ticket - 1
+        };
+        // Created before waiting, so a send dropped while waiting gives up
+        // its ticket
+        let turn = Turn {
+            sequencer: self,
+            ticket,
+        };
+        poll_fn(|cx| {
+            let mut turns = self.lock();
+            if turns.serving == ticket {
+                return Poll::Ready(());
+            }
+            turns.waiting.push(cx.waker().clone());
+            Poll::Pending
+        })
+        .await;
+        turn
+    }
+
+    /// Take a turn if nobody else has one.
+    fn try_turn(&self) -> Option<Turn<'_>> {
+        let mut turns = self.lock();
+        if turns.next_ticket != turns.serving {
+            return None;
+        }
+        turns.next_ticket += 1;
+        Some(Turn {
+            sequencer: self,
+            ticket: turns.serving,
+        })
+    }
+}
+
+/// The right to stamp and send; the next turn starts when it drops.
+struct Turn<'a> {
+    sequencer: &'a Sequencer,
+    ticket: u64,
+}
+
+impl Turn<'_> {
+    /// The sequence number to stamp.
+    fn sequence(&self) -> u64 {
+        self.sequencer.lock().sequence
+    }
+
+    /// Use up the sequence number, once its envelope is sent or stored.
+    fn commit(&mut self) {
+        self.sequencer.lock().sequence += 1;
+    }
+}
+
+impl Drop for Turn<'_> {
+    fn drop(&mut self) {
+        let mut turns = self.sequencer.lock();
+        let turns = &mut *turns;
+        if turns.serving != self.ticket {
+            turns.abandoned.insert(self.ticket);
+            return;
+        }
+        turns.serving += 1;
+        while turns.abandoned.remove(&turns.serving) {
+            turns.serving += 1;
+        }
+        for waker in std::mem::take(&mut turns.waiting) {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::stream::StreamBuilder;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_enveloped_stream_stamps_metadata() {
+        let (sender, stream) = StreamBuilder::<&'static str>::new().enveloped();
+        let sender = sender.with_run_id("run-1");
+
+        sender.send("start").await.unwrap();
+        sender.send_correlated("step", "req-7").await.unwrap();
+        drop(sender);
+
+        let envelopes: Vec<_> = stream.collect().await;
+        assert_eq!(envelopes.len(), 2);
+        assert_eq!(envelopes[0].sequence, 0);
+        assert_eq!(envelopes[0].run_id.as_deref(), Some("run-1"));
+        assert_eq!(envelopes[0].correlation_id, None);
+        assert_eq!(envelopes[1].sequence, 1);
+        assert_eq!(envelopes[1].correlation_id.as_deref(), Some("req-7"));
+        assert!(envelopes[0].timestamp <= envelopes[1].timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_context_ids() {
+        let cx = Context::new()
+            .with_run_id("run-1")
+            .with_correlation_id("req-7")
+            .with_causation_id("cmd-2");
+        let (sender, stream) = StreamBuilder::<u8>::new().enveloped();
+        sender.with_context(&cx).send(1).await.unwrap();
+
+        let envelopes: Vec<_> = stream.collect().await;
+        assert_eq!(envelopes[0].run_id.as_deref(), Some("run-1"));
+        assert_eq!(envelopes[0].correlation_id.as_deref(), Some("req-7"));
+        assert_eq!(envelopes[0].causation_id.as_deref(), Some("cmd-2"));
+    }
+
+    #[tokio::test]
+    async fn test_context_warnings() {
+        let cx = Context::new();
+        let (sender, stream) = StreamBuilder::<u8>::new().enveloped();
+        let sender = sender.with_context(&cx);
+        sender.send(1).await.unwrap();
+        cx.warn("slow disk");
+        cx.warn("retrying");
+        sender.send(2).await.unwrap();
+        sender.send(3).await.unwrap();
+        drop(sender);
+
+        let envelopes: Vec<_> = stream.collect().await;
+        let counts: Vec<_> = envelopes.iter().map(|e| e.warnings.len()).collect();
+        assert_eq!(counts, [0, 2, 0]);
+        assert_eq!(envelopes[1].warnings[1].message, "retrying");
+        assert_eq!(cx.warnings().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_send_keeps_warnings() {
+        let cx = Context::new();
+        let (sender, mut stream) = StreamBuilder::<u8>::new().buffer_size(1).enveloped();
+        let sender = sender.with_context(&cx);
+        sender.send(1).await.unwrap();
+        cx.warn("slow disk");
+        // The channel is full, so the warning stays for the next event
+        assert_eq!(sender.try_send(2), Err(2));
+        assert!(!sender.offer(3));
+
+        assert!(stream.next().await.unwrap().warnings.is_empty());
+        sender.send(4).await.unwrap();
+        let envelope = stream.next().await.unwrap();
+        assert_eq!(envelope.event, 4);
+        assert_eq!(envelope.warnings.len(), 1);
+        assert_eq!(envelope.warnings[0].message, "slow disk");
+    }
+
+    #[tokio::test]
+    async fn test_cloned_senders_share_sequence() {
+        let (sender, stream) = StreamBuilder::<u32>::new().enveloped();
+        let a = sender.clone().with_correlation_id("a");
+        let b = sender.with_correlation_id("b");
+
+        a.send(1).await.unwrap();
+        b.send(2).await.unwrap();
+        a.send(3).await.unwrap();
+        drop((a, b));
+
+        let envelopes: Vec<_> = stream.collect().await;
+        let sequences: Vec<_> = envelopes.iter().map(|e| e.sequence).collect();
+        assert_eq!(sequences, vec![0, 1, 2]);
+        assert_eq!(envelopes[1].correlation_id.as_deref(), Some("b"));
+        assert_eq!(envelopes[2].clone().into_inner(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_cloned_senders_deliver_in_sequence_order() {
+        let (sender, mut stream) = StreamBuilder::<u32>::new().buffer_size(1).enveloped();
+        let producers: Vec<_> = (0..2)
+            .map(|_| {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    for i in 0..200 {
+                        sender.send(i).await.unwrap();
+                        if i % 7 == 0 {
+                            tokio::task::yield_now().await;
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+
+        let mut expected = 0;
+        while let Some(envelope) = stream.next().await {
+            assert_eq!(envelope.sequence, expected);
+            expected += 1;
+        }
+        for producer in producers {
+            producer.await.unwrap();
+        }
+        assert_eq!(expected, 400);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_send_gives_up_its_turn() {
+        let (sender, stream) = StreamBuilder::<u32>::new().buffer_size(1).enveloped();
+        sender.send(0).await.unwrap();
+        // The channel is full, so this send waits with the turn and is
+        // dropped without using up a sequence number
+        let blocked = sender.clone();
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(10), blocked.send(1))
+                .await
+                .is_err()
+        );
+        assert!(sender.try_send(2).is_err());
+        drop(blocked);
+
+        let mut stream = stream;
+        assert_eq!(stream.next().await.unwrap().sequence, 0);
+        sender.send(3).await.unwrap();
+        let next = stream.next().await.unwrap();
+        assert_eq!((next.sequence, next.event), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_offer_dead_letters_unwrapped_events() {
+        use crate::stream::{DeadLetterReason, DeadLetterSink};
+
+        let (sink, letters) = DeadLetterSink::channel(4);
+        let (sender, mut stream) = StreamBuilder::<&'static str>::new()
+            .buffer_size(1)
+            .dead_letter(sink.clone())
+            .enveloped();
+
+        assert!(sender.clone().offer("first"));
+        assert!(!sender.offer("second"));
+        assert_eq!(stream.next().await.unwrap().event, "first");
+        drop(stream);
+        assert!(!sender.offer("third"));
+        drop(sink);
+
+        let letters: Vec<_> = letters.take(2).collect().await;
+        assert_eq!(letters[0].event, "second");
+        assert_eq!(letters[0].reason, DeadLetterReason::Full);
+        assert_eq!(letters[1].event, "third");
+        assert_eq!(letters[1].reason, DeadLetterReason::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_offer_while_clone_sends_is_busy() {
+        use crate::stream::{DeadLetterReason, DeadLetterSink};
+
+        let sink = DeadLetterSink::counting();
+        let (sender, mut stream) = StreamBuilder::<u32>::new()
+            .buffer_size(1)
+            .dead_letter(sink.clone())
+            .enveloped();
+        sender.send(0).await.unwrap();
+        // Waits for room while holding the turn
+        let blocked = sender.clone();
+        let waiting = tokio::spawn(async move { blocked.send(1).await });
+        tokio::task::yield_now().await;
+
+        assert!(!sender.offer(2));
+        assert_eq!(sink.count(DeadLetterReason::Busy), 1);
+        assert_eq!(sink.count(DeadLetterReason::Full), 0);
+
+        assert_eq!(stream.next().await.unwrap().event, 0);
+        waiting.await.unwrap().unwrap();
+        assert_eq!(stream.next().await.unwrap().event, 1);
+        assert!(sender.offer(3));
+    }
+}
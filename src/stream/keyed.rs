@@ -0,0 +1,348 @@
+//! Keyed registry of event streams.
+//!
+//! Hosts that run several operations at once (one stream per run) use a
+//! [`StreamRegistry`] to hand the producer and consumer ends of each run's
+//! stream to whoever asks for them by key.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use super::{EventSender, EventStream};
+use crate::error::{RegistryError, RegistryResult};
+
+struct Entry<T> {
+    /// Distinguishes this stream from later ones opened under the same key.
+    id: u64,
+    tx: mpsc::WeakSender<T>,
+    /// The consumer end, until someone attaches to it.
+    stream: Option<ReceiverStream<T>>,
+}
+
+type Entries<K, T> = Mutex<HashMap<K, Entry<T>>>;
+
+/// A registry of event streams keyed by run identifier.
+///
+/// Each key owns one channel. [`open`](Self::open) creates it and returns the
+/// sender, [`attach`](Self::attach) hands out the stream once, and the entry is
+/// removed automatically when the attached stream is dropped. An unattached
+/// stream whose senders have all been dropped is forgotten once it buffers
+/// no events, so no method reports it any more. A key whose attached stream
+/// has lost its senders can be [opened](Self::open) again while the old
+/// stream drains. Cloning the registry is cheap; clones share the same
+/// entries.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::StreamRegistry;
+/// use futures::StreamExt;
+///
+/// # async fn example() {
+/// let registry = StreamRegistry::<String, u32>::new();
+///
+/// let sender = registry.open("run-1".to_string()).unwrap();
+/// let mut stream = registry.attach(&"run-1".to_string()).unwrap();
+///
+/// sender.send(42).await.unwrap();
+/// assert_eq!(stream.next().await, Some(42));
+/// # }
+/// ```
+pub struct StreamRegistry<K, T> {
+    entries: Arc<Entries<K, T>>,
+    buffer_size: usize,
+}
+
+impl<K, T> StreamRegistry<K, T>
+where
+    K: Eq + Hash + Clone + Display + Send + 'static,
+    T: Send + 'static,
+{
+    /// Create an empty registry with the default buffer size.
+    pub fn new() -> Self {
+        Self::with_buffer_size(100)
+    }
+
+    /// Create an empty registry whose channels use `buffer_size`.
+    pub fn with_buffer_size(buffer_size: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            buffer_size,
+        }
+    }
+
+    /// Open a stream for `key` and return its sender.
+    ///
+    /// Returns an error if the key has a stream with live senders, or an
+    /// unattached one that still buffers events.
+    pub fn open(&self, key: K) -> RegistryResult<EventSender<T>> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        self.prune();
+        let mut entries = self.entries.lock().unwrap();
+        let taken = entries
+            .get(&key)
+            .is_some_and(|entry| entry.stream.is_some() || entry.tx.upgrade().is_some());
+        if taken {
+            return Err(RegistryError::AlreadyRegistered(key.to_string()));
+        }
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel(self.buffer_size);
+        entries.insert(
+            key,
+            Entry {
+                id,
+                tx: tx.downgrade(),
+                stream: Some(ReceiverStream::new(rx)),
+            },
+        );
+        Ok(EventSender::new(tx))
+    }
+
+    /// Get another sender for an open stream.
+    ///
+    /// Returns `None` if the key is unknown or every sender has been dropped.
+    pub fn sender(&self, key: &K) -> Option<EventSender<T>> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .and_then(|entry| entry.tx.upgrade())
+            .map(EventSender::new)
+    }
+
+    /// Take the stream for `key`.
+    ///
+    /// Each stream can be attached once; returns `None` if the key is unknown
+    /// or the stream was already attached. A stream whose senders have all
+    /// been dropped can still be attached to read what it buffers.
+    pub fn attach(&self, key: &K) -> Option<EventStream<T>> {
+        let stream = {
+            let mut entries = self.entries.lock().unwrap();
+            entries.get_mut(key).and_then(|entry| {
+                let inner = entry.stream.take()?;
+                Some(AttachedStream {
+                    inner,
+                    key: Some(key.clone()),
+                    id: entry.id,
+                    entries: Arc::downgrade(&self.entries),
+                })
+            })
+        };
+        self.prune();
+        stream.map(|stream| Box::pin(stream) as EventStream<T>)
+    }
+
+    /// Check if the stream for `key` has been attached.
+    pub fn is_attached(&self, key: &K) -> bool {
+        let entries = self.entries.lock().unwrap();
+        entries.get(key).is_some_and(|entry| entry.stream.is_none())
+    }
+
+    /// Remove the entry for `key`.
+    ///
+    /// An unattached stream is dropped, closing the channel. An attached
+    /// stream keeps delivering until its senders are dropped.
+    pub fn close(&self, key: &K) -> bool {
+        let removed = self.entries.lock().unwrap().remove(key);
+        removed.is_some()
+    }
+
+    /// Check if a stream is open for `key`.
+    pub fn contains(&self, key: &K) -> bool {
+        self.prune();
+        self.entries.lock().unwrap().contains_key(key)
+    }
+
+    /// Get the keys of all open streams.
+    pub fn keys(&self) -> Vec<K> {
+        self.prune();
+        self.entries.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Get the number of open streams.
+    pub fn len(&self) -> usize {
+        self.prune();
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Check if the registry is empty.
+    pub fn is_empty(&self) -> bool {
+        self.prune();
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    /// Forget unattached streams with no senders and no buffered events.
+    ///
+    /// Attached streams remove their entry themselves when dropped.
+    fn prune(&self) {
+        self.entries.lock().unwrap().retain(|_, entry| {
+            entry.tx.upgrade().is_some()
+                || entry
+                    .stream
+                    .as_ref()
+                    .is_none_or(|stream| !stream.as_ref().is_empty())
+        });
+    }
+}
+
+impl<K, T> Default for StreamRegistry<K, T>
+where
+    K: Eq + Hash + Clone + Display + Send + 'static,
+    T: Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, T> Clone for StreamRegistry<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: Arc::clone(&self.entries),
+            buffer_size: self.buffer_size,
+        }
+    }
+}
+
+/// Consumer end that removes its registry entry when dropped.
+struct AttachedStream<K: Eq + Hash, T> {
+    inner: ReceiverStream<T>,
+    key: Option<K>,
+    id: u64,
+    entries: Weak<Entries<K, T>>,
+}
+
+// `K` is only ever moved, never pinned.
+impl<K: Eq + Hash, T> Unpin for AttachedStream<K, T> {}
+
+impl<K: Eq + Hash, T> Stream for AttachedStream<K, T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+impl<K: Eq + Hash, T> Drop for AttachedStream<K, T> {
+    fn drop(&mut self) {
+        let (Some(key), Some(entries)) = (self.key.take(), self.entries.upgrade()) else {
+            return;
+        };
+        // The entry may belong to a later stream opened under the same key.
+        let mut entries = entries.lock().unwrap();
+        if entries.get(&key).is_some_and(|e| e.id == self.id) {
+            entries.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_open_attach_and_send() {
+        let registry = StreamRegistry::<&'static str, u32>::new();
+        let sender = registry.open("run-1").unwrap();
+        assert!(registry.contains(&"run-1"));
+        assert!(!registry.is_attached(&"run-1"));
+
+        let mut stream = registry.attach(&"run-1").unwrap();
+        assert!(registry.attach(&"run-1").is_none());
+        assert!(registry.is_attached(&"run-1"));
+
+        registry.sender(&"run-1").unwrap().send(1).await.unwrap();
+        sender.send(2).await.unwrap();
+        assert_eq!(stream.next().await, Some(1));
+        assert_eq!(stream.next().await, Some(2));
+    }
+
+    #[test]
+    fn test_open_twice_fails() {
+        let registry = StreamRegistry::<String, u32>::new();
+        let _sender = registry.open("run".to_string()).unwrap();
+        let err = registry.open("run".to_string()).unwrap_err();
+        assert!(matches!(err, RegistryError::AlreadyRegistered(key) if key == "run"));
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_when_both_ends_drop() {
+        let registry = StreamRegistry::<u32, u32>::new();
+        let sender = registry.open(7).unwrap();
+        let stream = registry.attach(&7).unwrap();
+
+        drop(sender);
+        assert!(registry.contains(&7));
+        assert!(registry.sender(&7).is_none());
+
+        drop(stream);
+        assert!(!registry.contains(&7));
+        assert!(registry.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_unattached_closes_channel() {
+        let registry = StreamRegistry::<u32, u32>::new();
+        let sender = registry.open(1).unwrap();
+
+        assert!(registry.close(&1));
+        assert!(!registry.close(&1));
+        assert!(sender.is_closed());
+    }
+
+    #[tokio::test]
+    async fn test_old_stream_keeps_reopened_entry() {
+        let registry = StreamRegistry::<u32, u32>::new();
+        let sender = registry.open(3).unwrap();
+        let old = registry.attach(&3).unwrap();
+        drop(sender);
+
+        // The old run's senders are gone, so the key can be opened again
+        let sender = registry.open(3).unwrap();
+        drop(old);
+        assert!(registry.contains(&3));
+        assert!(registry.sender(&3).is_some());
+
+        let mut stream = registry.attach(&3).unwrap();
+        sender.send(9).await.unwrap();
+        assert_eq!(stream.next().await, Some(9));
+    }
+
+    #[tokio::test]
+    async fn test_prunes_streams_without_senders() {
+        let registry = StreamRegistry::<u32, u32>::new();
+        let _live = registry.open(1).unwrap();
+        let dead = registry.open(2).unwrap();
+        dead.send(5).await.unwrap();
+        drop(dead);
+
+        // Buffered events can still be read by attaching
+        assert_eq!(registry.len(), 2);
+        assert!(registry.contains(&2));
+        assert!(registry.open(2).is_err());
+        let mut stream = registry.attach(&2).unwrap();
+        assert_eq!(stream.next().await, Some(5));
+        assert_eq!(stream.next().await, None);
+
+        drop(registry.open(3).unwrap());
+        assert!(!registry.contains(&3));
+        let mut keys = registry.keys();
+        keys.sort();
+        assert_eq!(keys, [1, 2]);
+
+        // The drained stream's entry goes with it
+        drop(stream);
+        assert_eq!(registry.keys(), [1]);
+        assert_eq!(registry.len(), 1);
+        assert!(!registry.is_empty());
+    }
+}
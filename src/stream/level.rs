@@ -0,0 +1,133 @@
+//! Verbosity levels for events.
+//!
+//! Events that report their [`Level`] through
+//! [`EventKind::level`](super::EventKind::level) can be filtered per
+//! consumer with
+//! [`EventStreamExt::min_level`](super::EventStreamExt::min_level), so a
+//! UI can show progress while a log collector keeps every trace event of
+//! the same chatty provider.
+
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use super::{EventKind, EventStream};
+
+/// How verbose an event is, from most to least.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Level {
+    /// Step-by-step detail
+    Trace,
+    /// Detail useful when diagnosing a problem
+    Debug,
+    /// Normal progress
+    Info,
+    /// Something unexpected that did not stop the run
+    Warn,
+    /// A failure
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trace => write!(f, "trace"),
+            Self::Debug => write!(f, "debug"),
+            Self::Info => write!(f, "info"),
+            Self::Warn => write!(f, "warn"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Stream returned by [`EventStreamExt::min_level`](super::EventStreamExt::min_level).
+///
+/// Drops events below the minimum level. Events without a level and
+/// terminal events always pass, so completion is still seen.
+pub struct MinLevel<T> {
+    inner: EventStream<T>,
+    min: Level,
+}
+
+impl<T> MinLevel<T> {
+    pub(crate) fn new(inner: EventStream<T>, min: Level) -> Self {
+        Self { inner, min }
+    }
+}
+
+impl<T: EventKind> Stream for MinLevel<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    let keep = item.is_terminal() || item.level().is_none_or(|l| l >= self.min);
+                    if keep {
+                        return Poll::Ready(Some(item));
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::EventStreamExt;
+    use futures::StreamExt;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Log(Level),
+        Artifact,
+        Done,
+    }
+
+    impl EventKind for Event {
+        fn is_terminal(&self) -> bool {
+            matches!(self, Event::Done)
+        }
+
+        fn level(&self) -> Option<Level> {
+            match self {
+                Event::Log(level) => Some(*level),
+                Event::Artifact => None,
+                Event::Done => Some(Level::Debug),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_min_level() {
+        let events = futures::stream::iter([
+            Event::Log(Level::Trace),
+            Event::Log(Level::Info),
+            Event::Artifact,
+            Event::Log(Level::Debug),
+            Event::Log(Level::Error),
+            Event::Done,
+        ]);
+        let kept: Vec<_> = events.min_level(Level::Info).collect().await;
+        assert_eq!(
+            kept,
+            [
+                Event::Log(Level::Info),
+                Event::Artifact,
+                Event::Log(Level::Error),
+                Event::Done
+            ]
+        );
+        assert!(Level::Warn > Level::Info);
+        assert_eq!(Level::Warn.to_string(), "warn");
+    }
+}
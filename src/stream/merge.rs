@@ -0,0 +1,230 @@
+//! Stream merging and multiplexing.
+//!
+//! Merged streams are polled round-robin so that a busy source cannot starve
+//! its siblings.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use super::EventStream;
+
+/// Stream returned by [`merge_streams`].
+pub struct Merge<T> {
+    streams: Vec<EventStream<T>>,
+    next: usize,
+}
+
+impl<T> Stream for Merge<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = &mut *self;
+        let mut checked = 0;
+
+        while checked < this.streams.len() {
+            if this.next >= this.streams.len() {
+                this.next = 0;
+            }
+            let index = this.next;
+
+            match this.streams[index].as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.next = index + 1;
+                    return Poll::Ready(Some(item));
+                }
+                Poll::Ready(None) => {
+                    // Keep `next` pointing at the stream that slid into this slot.
+                    drop(this.streams.remove(index));
+                }
+                Poll::Pending => {
+                    this.next = index + 1;
+                    checked += 1;
+                }
+            }
+        }
+
+        if this.streams.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Merge several event streams into one.
+///
+/// Items are interleaved as they become available; sources are polled
+/// round-robin. The merged stream ends once every source has ended.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::{create_stream, merge_streams};
+/// use futures::StreamExt;
+///
+/// # async fn example() {
+/// let (a_tx, a) = create_stream::<u32>();
+/// let (b_tx, b) = create_stream::<u32>();
+/// a_tx.send(1).await.unwrap();
+/// b_tx.send(2).await.unwrap();
+/// drop((a_tx, b_tx));
+///
+/// let merged: Vec<u32> = merge_streams(vec![a, b]).collect().await;
+/// assert_eq!(merged, vec![1, 2]);
+/// # }
+/// ```
+pub fn merge_streams<T: Send + 'static>(streams: Vec<EventStream<T>>) -> EventStream<T> {
+    Box::pin(Merge { streams, next: 0 })
+}
+
+/// Tags each item of a stream with the key of its source.
+struct Tagged<K, T> {
+    key: K,
+    inner: EventStream<T>,
+}
+
+impl<K: Unpin, T> Unpin for Tagged<K, T> {}
+
+impl<K: Clone + Unpin, T> Stream for Tagged<K, T> {
+    type Item = (K, T);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<(K, T)>> {
+        let this = &mut *self;
+        match this.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(item)) => Poll::Ready(Some((this.key.clone(), item))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Combines keyed event streams into a single stream of `(key, item)` pairs.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::{create_stream, Multiplexer};
+/// use futures::StreamExt;
+///
+/// # async fn example() {
+/// let (rust_tx, rust) = create_stream::<u32>();
+/// let (python_tx, python) = create_stream::<u32>();
+///
+/// let mut stream = Multiplexer::new()
+///     .add("rust", rust)
+///     .add("python", python)
+///     .build();
+///
+/// python_tx.send(7).await.unwrap();
+/// assert_eq!(stream.next().await, Some(("python", 7)));
+/// # drop((rust_tx, python_tx));
+/// # }
+/// ```
+pub struct Multiplexer<K, T> {
+    sources: Vec<EventStream<(K, T)>>,
+}
+
+impl<K, T> Multiplexer<K, T>
+where
+    K: Clone + Send + Unpin + 'static,
+    T: Send + 'static,
+{
+    /// Create an empty multiplexer.
+    pub fn new() -> Self {
+        Self {
+            sources: Vec::new(),
+        }
+    }
+
+    /// Add a source stream tagged with `key`.
+    pub fn add(mut self, key: K, stream: EventStream<T>) -> Self {
+        self.push(key, stream);
+        self
+    }
+
+    /// Add a source stream tagged with `key` without consuming the multiplexer.
+    pub fn push(&mut self, key: K, stream: EventStream<T>) {
+        self.sources.push(Box::pin(Tagged { key, inner: stream }));
+    }
+
+    /// Get the number of sources added so far.
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    /// Check if no sources have been added.
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Build the multiplexed stream.
+    pub fn build(self) -> EventStream<(K, T)> {
+        merge_streams(self.sources)
+    }
+}
+
+impl<K, T> Default for Multiplexer<K, T>
+where
+    K: Clone + Send + Unpin + 'static,
+    T: Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::create_stream;
+    use futures::StreamExt;
+
+    fn iter<T: Send + 'static>(items: Vec<T>) -> EventStream<T> {
+        Box::pin(futures::stream::iter(items))
+    }
+
+    #[tokio::test]
+    async fn test_merge_streams_collects_all() {
+        let (a_tx, a) = create_stream::<u32>();
+        let (b_tx, b) = create_stream::<u32>();
+
+        a_tx.send(1).await.unwrap();
+        a_tx.send(2).await.unwrap();
+        b_tx.send(10).await.unwrap();
+        drop(a_tx);
+        drop(b_tx);
+
+        let mut events: Vec<_> = merge_streams(vec![a, b]).collect().await;
+        events.sort();
+        assert_eq!(events, vec![1, 2, 10]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_streams_is_fair() {
+        let busy = iter(vec!["a"; 4]);
+        let quiet = iter(vec!["b"; 2]);
+
+        let events: Vec<_> = merge_streams(vec![busy, quiet]).collect().await;
+        assert_eq!(events, vec!["a", "b", "a", "b", "a", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_empty() {
+        let events: Vec<u32> = merge_streams::<u32>(Vec::new()).collect().await;
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_multiplexer_tags_items() {
+        let a = iter(vec![1, 2]);
+        let b = iter(vec![3]);
+
+        let mux = Multiplexer::new().add("a", a).add("b", b);
+        assert_eq!(mux.len(), 2);
+
+        let events: Vec<_> = mux.build().collect().await;
+        assert_eq!(events, vec![("a", 1), ("b", 3), ("a", 2)]);
+    }
+}
@@ -0,0 +1,254 @@
+//! Pause and resume for event streams.
+//!
+//! [`EventStreamExt::pausable`](super::EventStreamExt::pausable) wraps a stream
+//! in a [`PausableStream`] and returns a [`PauseHandle`] that can suspend and
+//! resume delivery from anywhere, e.g. a UI control.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use super::{DeadLetterReason, DeadLetterSink, EventStream};
+
+/// What happens to events that arrive while a stream is paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PausePolicy {
+    /// Stop reading the source. Events queue up in the channel buffer and
+    /// producers are slowed by backpressure once it fills.
+    #[default]
+    Buffer,
+    /// Keep reading the source and discard events.
+    Drop,
+    /// Keep reading the source and deliver only the most recent event on resume.
+    KeepLatest,
+}
+
+#[derive(Debug, Default)]
+struct PauseState {
+    paused: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+/// Control handle for a [`PausableStream`].
+///
+/// Cloning the handle is cheap; all clones control the same stream.
+#[derive(Debug, Clone)]
+pub struct PauseHandle {
+    state: Arc<PauseState>,
+}
+
+impl PauseHandle {
+    /// Suspend delivery of events.
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resume delivery of events.
+    pub fn resume(&self) {
+        self.state.paused.store(false, Ordering::SeqCst);
+        if let Some(waker) = self.state.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    /// Toggle between paused and running. Returns the new paused state.
+    pub fn toggle(&self) -> bool {
+        if self.is_paused() {
+            self.resume();
+            false
+        } else {
+            self.pause();
+            true
+        }
+    }
+
+    /// Check if the stream is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::SeqCst)
+    }
+}
+
+/// Stream returned by [`EventStreamExt::pausable`](super::EventStreamExt::pausable).
+pub struct PausableStream<T> {
+    inner: EventStream<T>,
+    policy: PausePolicy,
+    state: Arc<PauseState>,
+    latest: Option<T>,
+    dead_letter: Option<DeadLetterSink<T>>,
+    done: bool,
+}
+
+// `T` is only ever moved, never pinned.
+impl<T> Unpin for PausableStream<T> {}
+
+impl<T> PausableStream<T> {
+    pub(crate) fn new(inner: EventStream<T>, policy: PausePolicy) -> (Self, PauseHandle) {
+        let state = Arc::new(PauseState::default());
+        let stream = Self {
+            inner,
+            policy,
+            state: Arc::clone(&state),
+            latest: None,
+            dead_letter: None,
+            done: false,
+        };
+        (stream, PauseHandle { state })
+    }
+
+    /// Route events discarded while paused to a dead-letter sink.
+    pub fn dead_letter(mut self, sink: DeadLetterSink<T>) -> Self {
+        self.dead_letter = Some(sink);
+        self
+    }
+
+    /// Get a control handle for this stream.
+    pub fn handle(&self) -> PauseHandle {
+        PauseHandle {
+            state: Arc::clone(&self.state),
+        }
+    }
+
+    fn poll_paused(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        *self.state.waker.lock().unwrap() = Some(cx.waker().clone());
+
+        // Re-check after registering so a concurrent resume is not missed.
+        if !self.state.paused.load(Ordering::SeqCst) {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        if self.policy == PausePolicy::Buffer {
+            return Poll::Pending;
+        }
+
+        while !self.done {
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    let discarded = if self.policy == PausePolicy::KeepLatest {
+                        self.latest
+                            .replace(item)
+                            .map(|old| (old, DeadLetterReason::Superseded))
+                    } else {
+                        Some((item, DeadLetterReason::Paused))
+                    };
+                    if let (Some((event, reason)), Some(sink)) = (discarded, &self.dead_letter) {
+                        sink.send(event, reason);
+                    }
+                }
+                Poll::Ready(None) => {
+                    self.done = true;
+                    if self.latest.is_none() {
+                        return Poll::Ready(None);
+                    }
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<T> Stream for PausableStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if self.state.paused.load(Ordering::SeqCst) {
+            return self.poll_paused(cx);
+        }
+
+        if let Some(item) = self.latest.take() {
+            return Poll::Ready(Some(item));
+        }
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        match self.inner.as_mut().poll_next(cx) {
+            Poll::Ready(None) => {
+                self.done = true;
+                Poll::Ready(None)
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{create_stream, EventStreamExt};
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pause_buffers_until_resume() {
+        let (sender, stream) = create_stream::<u32>();
+        let (mut stream, handle) = stream.pausable(PausePolicy::Buffer);
+
+        handle.pause();
+        sender.send(1).await.unwrap();
+        sender.send(2).await.unwrap();
+
+        let next = tokio::time::timeout(Duration::from_millis(20), stream.next()).await;
+        assert!(next.is_err(), "paused stream should not yield");
+
+        handle.resume();
+        assert_eq!(stream.next().await, Some(1));
+        assert_eq!(stream.next().await, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_pause_drop_policy_discards() {
+        let (sender, stream) = create_stream::<u32>();
+        let (mut stream, handle) = stream.pausable(PausePolicy::Drop);
+
+        handle.pause();
+        sender.send(1).await.unwrap();
+        sender.send(2).await.unwrap();
+        let next = tokio::time::timeout(Duration::from_millis(20), stream.next()).await;
+        assert!(next.is_err());
+
+        handle.resume();
+        sender.send(3).await.unwrap();
+        drop(sender);
+
+        let events: Vec<_> = stream.collect().await;
+        assert_eq!(events, vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_pause_keep_latest_policy() {
+        let (sender, stream) = create_stream::<u32>();
+        let (mut stream, handle) = stream.pausable(PausePolicy::KeepLatest);
+
+        handle.pause();
+        for i in 1..=3 {
+            sender.send(i).await.unwrap();
+        }
+        drop(sender);
+        let next = tokio::time::timeout(Duration::from_millis(20), stream.next()).await;
+        assert!(next.is_err());
+
+        assert!(!handle.toggle());
+        let events: Vec<_> = stream.collect().await;
+        assert_eq!(events, vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_resume_wakes_waiting_consumer() {
+        let (sender, stream) = create_stream::<u32>();
+        let (mut stream, handle) = stream.pausable(PausePolicy::Buffer);
+        handle.pause();
+        sender.send(7).await.unwrap();
+
+        let consumer = tokio::spawn(async move { stream.next().await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        handle.resume();
+
+        assert_eq!(consumer.await.unwrap(), Some(7));
+    }
+}
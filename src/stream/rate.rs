@@ -0,0 +1,231 @@
+//! Rate-shaping operators for event streams.
+//!
+//! These operators keep high-frequency producers (progress ticks, log lines)
+//! from flooding UI and reporting consumers. They are exposed as methods on
+//! [`EventStreamExt`](super::EventStreamExt).
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+use tokio::time::{Instant, Interval, MissedTickBehavior, Sleep};
+
+use super::EventStream;
+
+/// Stream returned by [`EventStreamExt::throttle`](super::EventStreamExt::throttle).
+///
+/// Spaces items at least `interval` apart. Items are delayed, never dropped.
+pub struct Throttle<T> {
+    inner: EventStream<T>,
+    interval: Duration,
+    delay: Pin<Box<Sleep>>,
+    armed: bool,
+}
+
+impl<T> Throttle<T> {
+    pub(crate) fn new(inner: EventStream<T>, per_second: u32) -> Self {
+        let interval = Duration::from_secs(1) / per_second.max(1);
+        Self {
+            inner,
+            interval,
+            delay: Box::pin(tokio::time::sleep(Duration::ZERO)),
+            armed: false,
+        }
+    }
+}
+
+impl<T> Stream for Throttle<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if self.armed {
+            if self.delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.armed = false;
+        }
+
+        match self.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                let deadline = Instant::now() + self.interval;
+                self.delay.as_mut().reset(deadline);
+                self.armed = true;
+                Poll::Ready(Some(item))
+            }
+            other => other,
+        }
+    }
+}
+
+/// Stream returned by [`EventStreamExt::debounce`](super::EventStreamExt::debounce).
+///
+/// Emits the most recent item once the source has been quiet for the
+/// configured period. Intermediate items are dropped. A pending item is
+/// flushed when the source ends.
+pub struct Debounce<T> {
+    inner: EventStream<T>,
+    quiet: Duration,
+    pending: Option<T>,
+    delay: Pin<Box<Sleep>>,
+    done: bool,
+}
+
+impl<T> Debounce<T> {
+    pub(crate) fn new(inner: EventStream<T>, quiet: Duration) -> Self {
+        Self {
+            inner,
+            quiet,
+            pending: None,
+            delay: Box::pin(tokio::time::sleep(quiet)),
+            done: false,
+        }
+    }
+}
+
+// `T` is only ever moved, never pinned.
+impl<T> Unpin for Debounce<T> {}
+
+impl<T> Stream for Debounce<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        while !self.done {
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    self.pending = Some(item);
+                    let deadline = Instant::now() + self.quiet;
+                    self.delay.as_mut().reset(deadline);
+                }
+                Poll::Ready(None) => {
+                    self.done = true;
+                    return Poll::Ready(self.pending.take());
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        if self.pending.is_some() && self.delay.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(self.pending.take());
+        }
+
+        Poll::Pending
+    }
+}
+
+/// Stream returned by [`EventStreamExt::sample`](super::EventStreamExt::sample).
+///
+/// Emits the latest item seen during each period. Periods with no new items
+/// emit nothing. The latest unsent item is flushed when the source ends.
+pub struct Sample<T> {
+    inner: EventStream<T>,
+    ticker: Interval,
+    latest: Option<T>,
+    done: bool,
+}
+
+impl<T> Sample<T> {
+    pub(crate) fn new(inner: EventStream<T>, period: Duration) -> Self {
+        let mut ticker = tokio::time::interval_at(Instant::now() + period, period);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        Self {
+            inner,
+            ticker,
+            latest: None,
+            done: false,
+        }
+    }
+}
+
+// `T` is only ever moved, never pinned.
+impl<T> Unpin for Sample<T> {}
+
+impl<T> Stream for Sample<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        while !self.done {
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => self.latest = Some(item),
+                Poll::Ready(None) => self.done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if self.done {
+            return Poll::Ready(self.latest.take());
+        }
+
+        while self.ticker.poll_tick(cx).is_ready() {
+            if let Some(item) = self.latest.take() {
+                return Poll::Ready(Some(item));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{create_stream, EventStreamExt};
+    use futures::StreamExt;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttle_spaces_items() {
+        let (sender, stream) = create_stream::<u32>();
+        for i in 0..3 {
+            sender.send(i).await.unwrap();
+        }
+        drop(sender);
+
+        let start = Instant::now();
+        let events: Vec<_> = stream.throttle(10).collect().await;
+
+        assert_eq!(events, vec![0, 1, 2]);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_debounce_emits_last_after_quiet_period() {
+        let (sender, stream) = create_stream::<u32>();
+        let mut stream = stream.debounce(Duration::from_millis(50));
+
+        tokio::spawn(async move {
+            for i in 0..5 {
+                sender.send(i).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            sender.send(99).await.unwrap();
+        });
+
+        assert_eq!(stream.next().await, Some(4));
+        assert_eq!(stream.next().await, Some(99));
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sample_emits_latest_per_period() {
+        let (sender, stream) = create_stream::<u32>();
+        let stream = stream.sample(Duration::from_millis(100));
+
+        tokio::spawn(async move {
+            for i in 0..10 {
+                sender.send(i).await.unwrap();
+                tokio::time::sleep(Duration::from_millis(25)).await;
+            }
+        });
+
+        let events: Vec<_> = stream.collect().await;
+        assert!(events.len() < 10);
+        assert_eq!(events.last(), Some(&9));
+    }
+}
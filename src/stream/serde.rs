@@ -0,0 +1,181 @@
+//! JSON Lines encoding of event streams.
+//!
+//! One JSON document per line is easy to pipe between processes, append to a
+//! log file, and replay later with [`from_json_lines`]. Requires the `json`
+//! feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::stream::create_stream;
+//! use rustratify::stream::serde::{from_json_lines, to_json_lines};
+//! use rustratify::stream::TryEventStreamExt;
+//! use futures::StreamExt;
+//!
+//! # async fn example() {
+//! let (sender, stream) = create_stream::<u32>();
+//! sender.send(1).await.unwrap();
+//! sender.send(2).await.unwrap();
+//! drop(sender);
+//!
+//! let lines: Vec<_> = to_json_lines(stream).collect().await;
+//! let bytes: Vec<u8> = lines.concat();
+//! assert_eq!(bytes, b"1\n2\n");
+//!
+//! let reader = std::io::Cursor::new(bytes);
+//! let decoded = from_json_lines::<u32, _>(reader).collect_ok().await;
+//! assert_eq!(decoded.unwrap(), vec![1, 2]);
+//! # }
+//! ```
+
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use ::serde::de::DeserializeOwned;
+use ::serde::Serialize;
+use bytes::Bytes;
+use futures_core::Stream;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio_stream::wrappers::LinesStream;
+
+use super::EventStream;
+use crate::error::RustratifyError;
+
+/// Encode each event as one line of JSON.
+///
+/// Every item is a complete line including the trailing newline. Events that
+/// fail to serialize are skipped and logged.
+pub fn to_json_lines<T>(stream: EventStream<T>) -> EventStream<Bytes>
+where
+    T: Serialize + Send + 'static,
+{
+    Box::pin(ToJsonLines { inner: stream })
+}
+
+struct ToJsonLines<T> {
+    inner: EventStream<T>,
+}
+
+impl<T: Serialize> Stream for ToJsonLines<T> {
+    type Item = Bytes;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        loop {
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(event)) => match serde_json::to_vec(&event) {
+                    Ok(mut line) => {
+                        line.push(b'\n');
+                        return Poll::Ready(Some(Bytes::from(line)));
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, "skipping event that failed to serialize");
+                    }
+                },
+                other => return other.map(|_| None),
+            }
+        }
+    }
+}
+
+/// Decode events from a reader containing one JSON document per line.
+///
+/// Blank lines are ignored. I/O and parse failures are yielded as errors;
+/// use [`TryEventStreamExt`](super::TryEventStreamExt) to stop at the first
+/// one.
+pub fn from_json_lines<T, R>(reader: R) -> EventStream<Result<T, RustratifyError>>
+where
+    T: DeserializeOwned + Send + 'static,
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    Box::pin(FromJsonLines {
+        lines: LinesStream::new(BufReader::new(reader).lines()),
+        _marker: PhantomData,
+    })
+}
+
+struct FromJsonLines<R, T> {
+    lines: LinesStream<BufReader<R>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> Stream for FromJsonLines<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    type Item = Result<T, RustratifyError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let line = match Pin::new(&mut self.lines).poll_next(cx) {
+                Poll::Ready(Some(Ok(line))) => line,
+                Poll::Ready(Some(Err(e))) => {
+                    return Poll::Ready(Some(Err(RustratifyError::Stream(e.to_string()))))
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line)
+                .map_err(|e| RustratifyError::Stream(format!("invalid JSON line: {}", e)));
+            return Poll::Ready(Some(event));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{create_stream, TryEventStreamExt};
+    use ::serde::Deserialize;
+    use futures::StreamExt;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Event {
+        Started { run: String },
+        Progress(u32),
+        Done,
+    }
+
+    #[tokio::test]
+    async fn test_json_lines_roundtrip() {
+        let events = vec![
+            Event::Started {
+                run: "r1".to_string(),
+            },
+            Event::Progress(50),
+            Event::Done,
+        ];
+
+        let (sender, stream) = create_stream::<Event>();
+        for event in events.clone() {
+            sender.send(event).await.unwrap();
+        }
+        drop(sender);
+
+        let lines: Vec<Bytes> = to_json_lines(stream).collect().await;
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.ends_with(b"\n")));
+
+        let encoded: Vec<u8> = lines.concat();
+        let decoded = from_json_lines::<Event, _>(std::io::Cursor::new(encoded))
+            .collect_ok()
+            .await
+            .unwrap();
+        assert_eq!(decoded, events);
+    }
+
+    #[tokio::test]
+    async fn test_from_json_lines_reports_invalid_lines() {
+        let input: &'static [u8] = b"1\n\nnot json\n3\n";
+        let items: Vec<_> = from_json_lines::<u32, _>(input).collect().await;
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap(), &1);
+        assert!(items[1].is_err());
+        assert_eq!(items[2].as_ref().unwrap(), &3);
+    }
+}
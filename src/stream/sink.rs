@@ -0,0 +1,146 @@
+//! [`Sink`] adapter for [`EventSender`].
+//!
+//! Requires the `sink` feature.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_sink::Sink;
+
+use super::EventSender;
+
+/// Error returned by [`EventSink`] when the receiver has been dropped.
+///
+/// Holds the event that could not be sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "channel closed")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for SendError<T> {}
+
+type PendingSend<T> = Pin<Box<dyn Future<Output = Result<(), T>> + Send>>;
+
+/// A [`Sink`] that sends into an [`EventSender`].
+///
+/// Created by [`EventSender::into_sink`]. At most one event is in flight;
+/// [`poll_ready`](Sink::poll_ready) waits for it to be accepted, so a slow
+/// consumer applies backpressure as with [`EventSender::send`]. Closing
+/// the sink drops the sender.
+///
+/// # Example
+///
+/// ```rust
+/// use futures::StreamExt;
+/// use rustratify::stream::{create_stream, SendError};
+///
+/// # async fn example() {
+/// let (sender, stream) = create_stream::<u32>();
+/// let source = futures::stream::iter([1, 2, 3]).map(Ok::<_, SendError<u32>>);
+/// source.forward(sender.into_sink()).await.unwrap();
+/// let events: Vec<_> = stream.collect().await;
+/// assert_eq!(events, [1, 2, 3]);
+/// # }
+/// ```
+pub struct EventSink<T> {
+    sender: Option<Arc<EventSender<T>>>,
+    pending: Option<PendingSend<T>>,
+}
+
+impl<T> EventSink<T> {
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), SendError<T>>> {
+        let Some(pending) = self.pending.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+        let result = std::task::ready!(pending.as_mut().poll(cx));
+        self.pending = None;
+        Poll::Ready(result.map_err(SendError))
+    }
+}
+
+// The in-flight send is boxed, so nothing here is pinned in place.
+impl<T> Unpin for EventSink<T> {}
+
+impl<T> fmt::Debug for EventSink<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSink")
+            .field("closed", &self.sender.is_none())
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> Sink<T> for EventSink<T> {
+    type Error = SendError<T>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let Some(sender) = &this.sender else {
+            return Err(SendError(item));
+        };
+        let sender = Arc::clone(sender);
+        this.pending = Some(Box::pin(async move { sender.send(item).await }));
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let result = std::task::ready!(this.poll_pending(cx));
+        this.sender = None;
+        Poll::Ready(result)
+    }
+}
+
+impl<T> EventSender<T> {
+    /// Turn this sender into a [`Sink`].
+    ///
+    /// Requires the `sink` feature. See [`EventSink`].
+    pub fn into_sink(self) -> EventSink<T> {
+        EventSink {
+            sender: Some(Arc::new(self)),
+            pending: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::create_stream_with_buffer;
+    use futures::{SinkExt, StreamExt};
+
+    #[tokio::test]
+    async fn test_forward_into_sink() {
+        let (sender, stream) = create_stream_with_buffer::<u32>(1);
+        let consumer = tokio::spawn(stream.collect::<Vec<_>>());
+
+        let source = futures::stream::iter(0..5).map(Ok);
+        source.forward(sender.into_sink()).await.unwrap();
+
+        assert_eq!(consumer.await.unwrap(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_sink_after_receiver_dropped() {
+        let (sender, stream) = create_stream_with_buffer::<u32>(1);
+        drop(stream);
+
+        let mut sink = sender.into_sink();
+        assert_eq!(sink.send(7).await, Err(SendError(7)));
+    }
+}
@@ -0,0 +1,418 @@
+//! Event streams that spill to disk instead of blocking producers.
+//!
+//! A spilling stream keeps up to a configured number of events in memory.
+//! Further events are appended to a temporary file as JSON lines and read back
+//! in order once the consumer has caught up, so a slow consumer neither
+//! blocks producers nor exhausts memory. A spilled event that cannot be read
+//! back is yielded as an error in its place. Requires the `spill` feature and
+//! a filesystem, so it is not available on `wasm32-unknown-unknown`.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::EventStream;
+
+/// Configuration for a spilling stream.
+#[derive(Debug, Clone)]
+pub struct SpillConfig {
+    /// Number of events kept in memory before spilling to disk
+    pub memory_threshold: usize,
+    /// Directory for spill files (defaults to the system temp directory)
+    pub dir: PathBuf,
+}
+
+impl SpillConfig {
+    /// Create a configuration with the given in-memory threshold.
+    pub fn new(memory_threshold: usize) -> Self {
+        Self {
+            memory_threshold,
+            dir: std::env::temp_dir(),
+        }
+    }
+
+    /// Set the directory for spill files.
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+}
+
+/// Create a spilling stream.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::spill::{spill_channel, SpillConfig};
+/// use rustratify::stream::TryEventStreamExt;
+///
+/// # async fn example() {
+/// let (sender, stream) = spill_channel::<u32>(SpillConfig::new(2));
+/// for i in 0..5 {
+///     sender.send(i).unwrap(); // never waits; 2..5 go to disk
+/// }
+/// drop(sender);
+///
+/// let events = stream.collect_ok().await.unwrap();
+/// assert_eq!(events, vec![0, 1, 2, 3, 4]);
+/// # }
+/// ```
+pub fn spill_channel<T>(config: SpillConfig) -> (SpillSender<T>, EventStream<io::Result<T>>)
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        memory: VecDeque::new(),
+        file: None,
+        config,
+        senders: 1,
+        receiver_alive: true,
+        waker: None,
+    }));
+    let sender = SpillSender {
+        shared: Arc::clone(&shared),
+    };
+    (sender, Box::pin(SpillStream { shared }))
+}
+
+struct SpillFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    reader: BufReader<File>,
+    pending: usize,
+}
+
+impl SpillFile {
+    fn create(dir: &std::path::Path) -> io::Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!(
+            "rustratify-spill-{}-{}.jsonl",
+            std::process::id(),
+            id
+        ));
+        let writer = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)?;
+        let reader = File::open(&path)?;
+        Ok(Self {
+            path,
+            writer: BufWriter::new(writer),
+            reader: BufReader::new(reader),
+            pending: 0,
+        })
+    }
+
+    fn write<T: Serialize>(&mut self, event: &T) -> io::Result<()> {
+        // Serialize first so a failure leaves no partial record behind
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    fn read<T: DeserializeOwned>(&mut self) -> io::Result<T> {
+        self.pending -= 1;
+        self.writer.flush()?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+struct Shared<T> {
+    memory: VecDeque<T>,
+    file: Option<SpillFile>,
+    config: SpillConfig,
+    senders: usize,
+    receiver_alive: bool,
+    waker: Option<Waker>,
+}
+
+impl<T: Serialize + DeserializeOwned> Shared<T> {
+    /// Queue `event`, returning it if it can be neither spilled nor kept in
+    /// memory without overtaking events already on disk.
+    fn push(&mut self, event: T) -> Result<(), T> {
+        let spilling = self.file.as_ref().is_some_and(|f| f.pending > 0);
+        if spilling || self.memory.len() >= self.config.memory_threshold {
+            match self.spill(&event) {
+                Ok(()) => return Ok(()),
+                // Memory is read before the file, so keeping the event there
+                // would deliver it ahead of the spilled ones
+                Err(e) if spilling => {
+                    tracing::warn!(error = %e, "failed to spill event to disk, rejecting it");
+                    return Err(event);
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to spill event to disk, keeping it in memory");
+                }
+            }
+        }
+        self.memory.push_back(event);
+        Ok(())
+    }
+
+    fn spill(&mut self, event: &T) -> io::Result<()> {
+        if self.file.is_none() {
+            self.file = Some(SpillFile::create(&self.config.dir)?);
+        }
+        self.file
+            .as_mut()
+            .expect("spill file just created")
+            .write(event)
+    }
+
+    fn pop(&mut self) -> Option<io::Result<T>> {
+        if let Some(event) = self.memory.pop_front() {
+            return Some(Ok(event));
+        }
+        let file = self.file.as_mut().filter(|f| f.pending > 0)?;
+        let event = file.read();
+        if file.pending == 0 {
+            // Start a fresh file next time rather than growing this one forever.
+            self.file = None;
+        }
+        Some(event)
+    }
+
+    fn spilled(&self) -> usize {
+        self.file.as_ref().map_or(0, |f| f.pending)
+    }
+}
+
+/// Sending half of a spilling stream.
+///
+/// Sending never waits. Clones feed the same stream, which ends once every
+/// sender has been dropped.
+pub struct SpillSender<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T: Serialize + DeserializeOwned> SpillSender<T> {
+    /// Send an event, spilling it to disk if the memory buffer is full.
+    ///
+    /// Returns `Err(event)` if the receiver was dropped, or if the event
+    /// could not be written to disk while earlier events are still there:
+    /// keeping it in memory would deliver it out of order. If nothing is on
+    /// disk, an event that cannot be spilled is kept in memory instead.
+    pub fn send(&self, event: T) -> Result<(), T> {
+        let mut shared = self.shared.lock().unwrap();
+        if !shared.receiver_alive {
+            return Err(event);
+        }
+        shared.push(event)?;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Get the number of events held in memory.
+    pub fn in_memory(&self) -> usize {
+        self.shared.lock().unwrap().memory.len()
+    }
+
+    /// Get the number of events currently spilled to disk.
+    pub fn spilled(&self) -> usize {
+        self.shared.lock().unwrap().spilled()
+    }
+
+    /// Check if the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        !self.shared.lock().unwrap().receiver_alive
+    }
+}
+
+impl<T> Clone for SpillSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().unwrap().senders += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for SpillSender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.senders -= 1;
+        if shared.senders == 0 {
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+struct SpillStream<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T: Serialize + DeserializeOwned> Stream for SpillStream<T> {
+    type Item = io::Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(event) = shared.pop() {
+            return Poll::Ready(Some(event));
+        }
+        if shared.senders == 0 {
+            return Poll::Ready(None);
+        }
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for SpillStream<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.receiver_alive = false;
+        shared.memory.clear();
+        shared.file = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::TryEventStreamExt;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_spills_beyond_threshold_and_preserves_order() {
+        let (sender, mut stream) = spill_channel::<String>(SpillConfig::new(3));
+
+        for i in 0..10 {
+            sender.send(format!("event-{}", i)).unwrap();
+        }
+        assert_eq!(sender.in_memory(), 3);
+        assert_eq!(sender.spilled(), 7);
+
+        // Consume part of the backlog, then keep producing.
+        for i in 0..5 {
+            let event = stream.next().await.unwrap().unwrap();
+            assert_eq!(event, format!("event-{}", i));
+        }
+        sender.send("event-10".to_string()).unwrap();
+        drop(sender);
+
+        let rest = stream.collect_ok().await.unwrap();
+        let expected: Vec<_> = (5..=10).map(|i| format!("event-{}", i)).collect();
+        assert_eq!(rest, expected);
+    }
+
+    #[tokio::test]
+    async fn test_spill_file_removed_after_drain() {
+        let dir =
+            std::env::temp_dir().join(format!("rustratify-spill-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let (sender, mut stream) = spill_channel::<u32>(SpillConfig::new(1).with_dir(&dir));
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        assert_eq!(stream.next().await.unwrap().unwrap(), 2);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        drop(stream);
+        assert_eq!(sender.send(3), Err(3));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_consumer_woken_by_send() {
+        let (sender, mut stream) = spill_channel::<u32>(SpillConfig::new(8));
+        let consumer = tokio::spawn(async move { stream.next().await });
+        tokio::task::yield_now().await;
+
+        sender.clone().send(42).unwrap();
+        assert_eq!(consumer.await.unwrap().unwrap().unwrap(), 42);
+    }
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    enum Event {
+        Ok(u32),
+        #[serde(skip_serializing)]
+        Unspillable,
+        #[serde(skip_deserializing)]
+        Unreadable,
+        Batch(Vec<Event>),
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unspillable_event_behind_spilled_ones() {
+        let (sender, stream) = spill_channel::<Event>(SpillConfig::new(1));
+        sender.send(Event::Unspillable).unwrap();
+        // Nothing is on disk yet, so this one is kept in memory
+        sender.send(Event::Unspillable).unwrap();
+        sender.send(Event::Ok(1)).unwrap();
+        assert_eq!(sender.spilled(), 1);
+
+        assert_eq!(sender.send(Event::Unspillable), Err(Event::Unspillable));
+        sender.send(Event::Ok(2)).unwrap();
+        drop(sender);
+
+        let events = stream.collect_ok().await.unwrap();
+        assert_eq!(
+            events,
+            [
+                Event::Unspillable,
+                Event::Unspillable,
+                Event::Ok(1),
+                Event::Ok(2)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_spill_leaves_no_partial_record() {
+        let (sender, stream) = spill_channel::<Event>(SpillConfig::new(0));
+        sender.send(Event::Ok(1)).unwrap();
+        // Fails after the first element has been serialized
+        let batch = Event::Batch(vec![Event::Ok(5), Event::Unspillable]);
+        assert!(sender.send(batch).is_err());
+        sender.send(Event::Ok(2)).unwrap();
+        drop(sender);
+
+        let events = stream.collect_ok().await.unwrap();
+        assert_eq!(events, [Event::Ok(1), Event::Ok(2)]);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_spilled_event_is_an_error() {
+        let (sender, mut stream) = spill_channel::<Event>(SpillConfig::new(0));
+        sender.send(Event::Ok(1)).unwrap();
+        sender.send(Event::Unreadable).unwrap();
+        sender.send(Event::Ok(2)).unwrap();
+        drop(sender);
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), Event::Ok(1));
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(stream.next().await.unwrap().unwrap(), Event::Ok(2));
+        assert!(stream.next().await.is_none());
+    }
+}
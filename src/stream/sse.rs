@@ -0,0 +1,309 @@
+//! Server-Sent Events adapter.
+//!
+//! Converts an [`EventStream`] of serializable events into a stream of SSE
+//! frames that can be handed directly to an HTTP response body. Requires the
+//! `sse` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::stream::create_stream;
+//! use rustratify::stream::sse::{to_sse, SseConfig};
+//!
+//! # async fn example() {
+//! let (sender, stream) = create_stream::<u32>();
+//! let body = to_sse(stream, SseConfig::new().event_name("progress"));
+//! sender.send(50).await.unwrap();
+//! # drop(body);
+//! # }
+//! ```
+
+use std::fmt::Write;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_core::Stream;
+use serde::Serialize;
+use tokio::time::{Instant, Sleep};
+
+use super::EventStream;
+use crate::error::RustratifyError;
+
+/// Default interval between keep-alive comments.
+pub const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+type EventNameFn<T> = Box<dyn Fn(&T) -> Option<String> + Send + Sync>;
+
+/// Options for [`to_sse`].
+pub struct SseConfig<T> {
+    event_name: Option<EventNameFn<T>>,
+    ids: bool,
+    keep_alive: Option<Duration>,
+    retry: Option<Duration>,
+}
+
+impl<T> SseConfig<T> {
+    /// Create a configuration with ids enabled and the default keep-alive.
+    pub fn new() -> Self {
+        Self {
+            event_name: None,
+            ids: true,
+            keep_alive: Some(DEFAULT_KEEP_ALIVE),
+            retry: None,
+        }
+    }
+
+    /// Use the same `event:` name for every frame.
+    pub fn event_name(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.event_name = Some(Box::new(move |_| Some(name.clone())));
+        self
+    }
+
+    /// Derive the `event:` name from each event.
+    pub fn event_name_with<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> Option<String> + Send + Sync + 'static,
+    {
+        self.event_name = Some(Box::new(f));
+        self
+    }
+
+    /// Enable or disable sequential `id:` fields.
+    pub fn ids(mut self, enabled: bool) -> Self {
+        self.ids = enabled;
+        self
+    }
+
+    /// Set the keep-alive interval, or `None` to disable keep-alives.
+    pub fn keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.keep_alive = interval;
+        self
+    }
+
+    /// Ask the client to wait `delay` before reconnecting.
+    pub fn retry(mut self, delay: Duration) -> Self {
+        self.retry = Some(delay);
+        self
+    }
+}
+
+impl<T> Default for SseConfig<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single SSE frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseFrame {
+    /// Value of the `event:` field
+    pub event: Option<String>,
+    /// Value of the `id:` field
+    pub id: Option<String>,
+    /// Payload, split into one `data:` field per line
+    pub data: String,
+    /// Value of the `retry:` field
+    pub retry: Option<Duration>,
+}
+
+impl SseFrame {
+    /// Create a frame carrying `data`.
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Encode the frame in SSE wire format.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut out = String::new();
+        if let Some(event) = &self.event {
+            let _ = writeln!(out, "event: {}", event);
+        }
+        if let Some(id) = &self.id {
+            let _ = writeln!(out, "id: {}", id);
+        }
+        if let Some(retry) = self.retry {
+            let _ = writeln!(out, "retry: {}", retry.as_millis());
+        }
+        for line in self.data.lines() {
+            let _ = writeln!(out, "data: {}", line);
+        }
+        out.push('\n');
+        Bytes::from(out)
+    }
+
+    /// Encode a keep-alive comment.
+    pub fn keep_alive() -> Bytes {
+        Bytes::from_static(b": keep-alive\n\n")
+    }
+}
+
+/// Convert an event stream into SSE frames.
+///
+/// Each event is serialized as JSON into the `data:` field. Serialization
+/// failures are yielded as errors so the HTTP layer can decide whether to
+/// abort the response.
+pub fn to_sse<T>(
+    stream: EventStream<T>,
+    config: SseConfig<T>,
+) -> EventStream<Result<Bytes, RustratifyError>>
+where
+    T: Serialize + Send + 'static,
+{
+    let keep_alive = config
+        .keep_alive
+        .map(|interval| Box::pin(tokio::time::sleep(interval)));
+    Box::pin(SseStream {
+        inner: Some(stream),
+        config,
+        next_id: 0,
+        keep_alive,
+        retry_sent: false,
+    })
+}
+
+struct SseStream<T> {
+    inner: Option<EventStream<T>>,
+    config: SseConfig<T>,
+    next_id: u64,
+    keep_alive: Option<Pin<Box<Sleep>>>,
+    retry_sent: bool,
+}
+
+impl<T: Serialize> SseStream<T> {
+    fn encode(&mut self, event: &T) -> Result<Bytes, RustratifyError> {
+        let data =
+            serde_json::to_string(event).map_err(|e| RustratifyError::Stream(e.to_string()))?;
+        let mut frame = SseFrame::new(data);
+        frame.event = self.config.event_name.as_ref().and_then(|f| f(event));
+        if self.config.ids {
+            frame.id = Some(self.next_id.to_string());
+            self.next_id += 1;
+        }
+        Ok(frame.to_bytes())
+    }
+
+    fn reset_keep_alive(&mut self) {
+        if let (Some(sleep), Some(interval)) = (self.keep_alive.as_mut(), self.config.keep_alive) {
+            sleep.as_mut().reset(Instant::now() + interval);
+        }
+    }
+}
+
+impl<T: Serialize> Stream for SseStream<T> {
+    type Item = Result<Bytes, RustratifyError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if !this.retry_sent {
+            this.retry_sent = true;
+            if let Some(retry) = this.config.retry {
+                let frame = SseFrame {
+                    retry: Some(retry),
+                    ..SseFrame::default()
+                };
+                return Poll::Ready(Some(Ok(frame.to_bytes())));
+            }
+        }
+
+        let Some(inner) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        match inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                this.reset_keep_alive();
+                return Poll::Ready(Some(this.encode(&event)));
+            }
+            Poll::Ready(None) => {
+                this.inner = None;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => {}
+        }
+
+        if let Some(sleep) = this.keep_alive.as_mut() {
+            if sleep.as_mut().poll(cx).is_ready() {
+                this.reset_keep_alive();
+                return Poll::Ready(Some(Ok(SseFrame::keep_alive())));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::create_stream;
+    use futures::StreamExt;
+    use serde::Serialize;
+
+    #[derive(Debug, Serialize)]
+    struct Progress {
+        percent: u32,
+    }
+
+    #[test]
+    fn test_frame_encoding() {
+        let frame = SseFrame {
+            event: Some("update".to_string()),
+            id: Some("3".to_string()),
+            data: "line one\nline two".to_string(),
+            retry: None,
+        };
+        assert_eq!(
+            frame.to_bytes(),
+            "event: update\nid: 3\ndata: line one\ndata: line two\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_to_sse_frames() {
+        let (sender, stream) = create_stream::<Progress>();
+        sender.send(Progress { percent: 10 }).await.unwrap();
+        sender.send(Progress { percent: 20 }).await.unwrap();
+        drop(sender);
+
+        let config = SseConfig::new()
+            .event_name("progress")
+            .retry(Duration::from_secs(3));
+        let frames: Vec<_> = to_sse(stream, config)
+            .map(|frame| frame.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0], "retry: 3000\n\n");
+        assert_eq!(
+            frames[1],
+            "event: progress\nid: 0\ndata: {\"percent\":10}\n\n"
+        );
+        assert_eq!(
+            frames[2],
+            "event: progress\nid: 1\ndata: {\"percent\":20}\n\n"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_to_sse_keep_alive() {
+        let (sender, stream) = create_stream::<u32>();
+        let config = SseConfig::new()
+            .ids(false)
+            .keep_alive(Some(Duration::from_secs(5)));
+        let mut frames = to_sse(stream, config);
+
+        assert_eq!(frames.next().await.unwrap().unwrap(), ": keep-alive\n\n");
+
+        sender.send(1).await.unwrap();
+        assert_eq!(frames.next().await.unwrap().unwrap(), "data: 1\n\n");
+    }
+}
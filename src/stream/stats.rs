@@ -0,0 +1,235 @@
+//! Counters for monitoring event stream health.
+//!
+//! Enable tracking with [`StreamBuilder::with_stats`](super::StreamBuilder::with_stats)
+//! and read the counters through the [`StreamStats`] handle returned by
+//! [`EventSender::stats`](super::EventSender::stats). A growing buffer or
+//! consumer lag points at a slow consumer before backpressure stalls the
+//! producers.
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+
+use super::EventStream;
+
+#[derive(Debug)]
+struct Counters {
+    origin: Instant,
+    capacity: usize,
+    sent: AtomicU64,
+    received: AtomicU64,
+    dropped: AtomicU64,
+    /// Nanoseconds since `origin` at which the consumer last took an item.
+    last_received: AtomicU64,
+}
+
+/// Shared handle to the counters of an instrumented stream.
+///
+/// Cloning the handle is cheap; all clones observe the same counters.
+#[derive(Debug, Clone)]
+pub struct StreamStats {
+    counters: Arc<Counters>,
+}
+
+/// A point-in-time copy of [`StreamStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StreamStatsSnapshot {
+    /// Events accepted by the channel
+    pub sent: u64,
+    /// Events taken by the consumer
+    pub received: u64,
+    /// Events that could not be delivered
+    pub dropped: u64,
+    /// Events waiting in the buffer
+    pub buffered: u64,
+    /// Buffer capacity of the channel
+    pub capacity: usize,
+    /// How long the consumer has been behind
+    pub consumer_lag: Duration,
+}
+
+impl StreamStats {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            counters: Arc::new(Counters {
+                origin: Instant::now(),
+                capacity,
+                sent: AtomicU64::new(0),
+                received: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+                last_received: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub(crate) fn record_sent(&self) {
+        self.counters.sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_dropped(&self) {
+        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_received(&self) {
+        let now = self.counters.origin.elapsed().as_nanos() as u64;
+        self.counters.last_received.store(now, Ordering::Relaxed);
+        self.counters.received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Get the number of events accepted by the channel.
+    pub fn sent(&self) -> u64 {
+        self.counters.sent.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of events taken by the consumer.
+    pub fn received(&self) -> u64 {
+        self.counters.received.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of events that could not be delivered.
+    ///
+    /// Counts `try_send` calls rejected because the buffer was full, and sends
+    /// attempted after the consumer went away.
+    pub fn dropped(&self) -> u64 {
+        self.counters.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of events waiting in the buffer.
+    pub fn buffered(&self) -> u64 {
+        self.sent().saturating_sub(self.received())
+    }
+
+    /// Get the buffer capacity of the channel.
+    pub fn capacity(&self) -> usize {
+        self.counters.capacity
+    }
+
+    /// Get the fraction of the buffer in use, between 0.0 and 1.0.
+    pub fn utilization(&self) -> f64 {
+        if self.counters.capacity == 0 {
+            return 0.0;
+        }
+        (self.buffered() as f64 / self.counters.capacity as f64).min(1.0)
+    }
+
+    /// Get how long the consumer has been behind.
+    ///
+    /// This is the time since the consumer last took an item while events are
+    /// waiting in the buffer, and zero when the buffer is empty.
+    pub fn consumer_lag(&self) -> Duration {
+        if self.buffered() == 0 {
+            return Duration::ZERO;
+        }
+        let last = Duration::from_nanos(self.counters.last_received.load(Ordering::Relaxed));
+        self.counters.origin.elapsed().saturating_sub(last)
+    }
+
+    /// Take a snapshot of all counters.
+    pub fn snapshot(&self) -> StreamStatsSnapshot {
+        StreamStatsSnapshot {
+            sent: self.sent(),
+            received: self.received(),
+            dropped: self.dropped(),
+            buffered: self.buffered(),
+            capacity: self.capacity(),
+            consumer_lag: self.consumer_lag(),
+        }
+    }
+}
+
+/// Counts items as the consumer takes them from the stream.
+pub(crate) struct Counted<T> {
+    inner: EventStream<T>,
+    stats: StreamStats,
+}
+
+impl<T> Counted<T> {
+    pub(crate) fn new(inner: EventStream<T>, stats: StreamStats) -> Self {
+        Self { inner, stats }
+    }
+}
+
+impl<T> Stream for Counted<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let poll = self.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(_)) = &poll {
+            self.stats.record_received();
+        }
+        poll
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stream::StreamBuilder;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_stats_track_sent_and_received() {
+        let (sender, mut stream) = StreamBuilder::<u32>::new()
+            .buffer_size(4)
+            .with_stats()
+            .build();
+        let stats = sender.stats().expect("stats enabled");
+
+        sender.send(1).await.unwrap();
+        sender.send(2).await.unwrap();
+        assert_eq!(stats.sent(), 2);
+        assert_eq!(stats.buffered(), 2);
+        assert_eq!(stats.utilization(), 0.5);
+
+        assert_eq!(stream.next().await, Some(1));
+        assert_eq!(stats.received(), 1);
+        assert_eq!(stats.buffered(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_dropped_events() {
+        let (sender, stream) = StreamBuilder::<u32>::new()
+            .buffer_size(1)
+            .with_stats()
+            .build();
+        let stats = sender.stats().unwrap();
+
+        assert!(sender.try_send(1).is_ok());
+        assert!(sender.try_send(2).is_err());
+        drop(stream);
+        assert!(sender.send(3).await.is_err());
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.sent, 1);
+        assert_eq!(snapshot.dropped, 2);
+    }
+
+    #[tokio::test]
+    async fn test_consumer_lag() {
+        let (sender, mut stream) = StreamBuilder::<u32>::new().with_stats().build();
+        let stats = sender.stats().unwrap();
+        assert_eq!(stats.consumer_lag(), Duration::ZERO);
+
+        sender.send(1).await.unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(stats.consumer_lag() >= Duration::from_millis(5));
+
+        stream.next().await;
+        assert_eq!(stats.consumer_lag(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_stats_disabled_by_default() {
+        let (sender, _stream) = StreamBuilder::<u32>::new().build();
+        assert!(sender.stats().is_none());
+    }
+}
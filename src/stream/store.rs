@@ -0,0 +1,390 @@
+//! Persistent event histories of runs.
+//!
+//! An [`EventStore`] keeps the events of each run in the order they were
+//! appended, so a finished run can be shown again later, e.g. a past build
+//! log in a UI. Each event gets an offset, counting from zero per run, and
+//! [`stream_from`](EventStore::stream_from) replays a history from any
+//! offset. [`MemoryEventStore`] keeps histories for the life of the
+//! process; [`JsonLinesEventStore`] (feature `serde`) appends them to a file
+//! as JSON lines.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::stream::{EventStore, MemoryEventStore};
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), rustratify::ProviderError> {
+//! let store = MemoryEventStore::new();
+//! for line in ["compiling", "linking", "done"] {
+//!     store.append("build-7", line.to_string()).await?;
+//! }
+//!
+//! let tail: Vec<String> = store.stream_from("build-7", 1).await?.collect().await;
+//! assert_eq!(tail, ["linking", "done"]);
+//! assert_eq!(store.runs().await?, ["build-7"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+
+use super::EventStream;
+use crate::error::ProviderResult;
+
+/// Storage for the event histories of runs.
+#[async_trait]
+pub trait EventStore<T: Send + 'static>: Send + Sync {
+    /// Store name, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// Add `event` to the end of the history of `run_id`, returning its
+    /// offset.
+    async fn append(&self, run_id: &str, event: T) -> ProviderResult<u64>;
+
+    /// The events of `run_id` from `offset` on, in the order appended.
+    ///
+    /// Empty for unknown runs and offsets past the end.
+    async fn read_from(&self, run_id: &str, offset: u64) -> ProviderResult<Vec<T>>;
+
+    /// The IDs of every run with a history.
+    async fn runs(&self) -> ProviderResult<Vec<String>>;
+
+    /// The whole history of `run_id`.
+    async fn read(&self, run_id: &str) -> ProviderResult<Vec<T>> {
+        self.read_from(run_id, 0).await
+    }
+
+    /// Replay the history of `run_id` from `offset` on as a stream.
+    ///
+    /// The stream holds the events stored when it was created and ends
+    /// after them.
+    async fn stream_from(&self, run_id: &str, offset: u64) -> ProviderResult<EventStream<T>> {
+        let events = self.read_from(run_id, offset).await?;
+        Ok(Box::pin(Replay::new(events)))
+    }
+}
+
+/// A stream over events already read.
+pub(super) struct Replay<T>(std::vec::IntoIter<T>);
+
+impl<T> Replay<T> {
+    pub(super) fn new(events: Vec<T>) -> Self {
+        Self(events.into_iter())
+    }
+}
+
+impl<T> Stream for Replay<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Poll::Ready(self.0.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+// Only the iterator is pinned, and it is never pinned structurally.
+impl<T> Unpin for Replay<T> {}
+
+/// An [`EventStore`] in memory.
+///
+/// Cloning is cheap; clones share the histories.
+pub struct MemoryEventStore<T> {
+    runs: Arc<Mutex<BTreeMap<String, Vec<T>>>>,
+}
+
+impl<T> MemoryEventStore<T> {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self {
+            runs: Arc::default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, Vec<T>>> {
+        self.runs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Forget the history of `run_id`, returning whether it had one.
+    pub fn remove(&self, run_id: &str) -> bool {
+        self.lock().remove(run_id).is_some()
+    }
+}
+
+impl<T> Default for MemoryEventStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for MemoryEventStore<T> {
+    fn clone(&self) -> Self {
+        Self {
+            runs: Arc::clone(&self.runs),
+        }
+    }
+}
+
+impl<T> fmt::Debug for MemoryEventStore<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryEventStore")
+            .field("runs", &self.lock().len())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<T: Clone + Send + 'static> EventStore<T> for MemoryEventStore<T> {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    async fn append(&self, run_id: &str, event: T) -> ProviderResult<u64> {
+        let mut runs = self.lock();
+        let events = runs.entry(run_id.to_string()).or_default();
+        events.push(event);
+        Ok(events.len() as u64 - 1)
+    }
+
+    async fn read_from(&self, run_id: &str, offset: u64) -> ProviderResult<Vec<T>> {
+        let runs = self.lock();
+        let events = runs.get(run_id).map_or(&[][..], Vec::as_slice);
+        let start = usize::try_from(offset).unwrap_or(usize::MAX);
+        Ok(events.get(start..).unwrap_or_default().to_vec())
+    }
+
+    async fn runs(&self) -> ProviderResult<Vec<String>> {
+        Ok(self.lock().keys().cloned().collect())
+    }
+}
+
+#[cfg(all(
+    feature = "serde",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub use json::JsonLinesEventStore;
+
+#[cfg(all(
+    feature = "serde",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+mod json {
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use std::fs::{File, OpenOptions};
+    use std::io::{BufRead, BufReader, Write};
+    use std::marker::PhantomData;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Mutex, MutexGuard};
+
+    use async_trait::async_trait;
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+
+    use super::EventStore;
+    use crate::error::{ProviderError, ProviderResult};
+
+    /// An [`EventStore`] appending to a file of JSON lines.
+    ///
+    /// Every run shares the file; each line holds one event with its run ID
+    /// and offset. Opening a file that already has histories continues
+    /// them. Requires the `serde` feature; not available on
+    /// `wasm32-unknown-unknown`.
+    pub struct JsonLinesEventStore<T> {
+        name: String,
+        path: PathBuf,
+        state: Mutex<State>,
+        _events: PhantomData<fn(T) -> T>,
+    }
+
+    struct State {
+        file: File,
+        /// The offset of the next event, by run ID
+        next: BTreeMap<String, u64>,
+    }
+
+    #[derive(Serialize)]
+    struct Line<'a, T> {
+        run_id: &'a str,
+        offset: u64,
+        event: &'a T,
+    }
+
+    /// A line without its event, to skip other runs cheaply.
+    #[derive(Deserialize)]
+    struct Header {
+        run_id: String,
+        offset: u64,
+    }
+
+    #[derive(Deserialize)]
+    struct Event<T> {
+        event: T,
+    }
+
+    fn corrupt(path: &Path, line: usize, err: serde_json::Error) -> ProviderError {
+        ProviderError::ExecutionFailed(format!("{}:{line}: {err}", path.display()))
+    }
+
+    impl<T> JsonLinesEventStore<T> {
+        /// Open the file at `path`, creating it if needed.
+        pub fn open(path: impl AsRef<Path>) -> ProviderResult<Self> {
+            let path = path.as_ref().to_path_buf();
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            let mut next = BTreeMap::new();
+            for (index, line) in BufReader::new(File::open(&path)?).lines().enumerate() {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+                let header: Header =
+                    serde_json::from_str(&line).map_err(|e| corrupt(&path, index + 1, e))?;
+                next.insert(header.run_id, header.offset + 1);
+            }
+            Ok(Self {
+                name: path.display().to_string(),
+                path,
+                state: Mutex::new(State { file, next }),
+                _events: PhantomData,
+            })
+        }
+
+        /// The file histories are stored in.
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        fn state(&self) -> MutexGuard<'_, State> {
+            self.state.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    impl<T> fmt::Debug for JsonLinesEventStore<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("JsonLinesEventStore")
+                .field("path", &self.path)
+                .finish()
+        }
+    }
+
+    #[async_trait]
+    impl<T> EventStore<T> for JsonLinesEventStore<T>
+    where
+        T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        async fn append(&self, run_id: &str, event: T) -> ProviderResult<u64> {
+            let mut state = self.state();
+            let offset = state.next.get(run_id).copied().unwrap_or(0);
+            let mut line = serde_json::to_vec(&Line {
+                run_id,
+                offset,
+                event: &event,
+            })
+            .map_err(|e| ProviderError::ExecutionFailed(e.to_string()))?;
+            line.push(b'\n');
+            state.file.write_all(&line)?;
+            state.next.insert(run_id.to_string(), offset + 1);
+            Ok(offset)
+        }
+
+        async fn read_from(&self, run_id: &str, offset: u64) -> ProviderResult<Vec<T>> {
+            // Held so a concurrent append cannot leave a partial last line
+            let state = self.state();
+            if state.next.get(run_id).is_none_or(|&next| next <= offset) {
+                return Ok(Vec::new());
+            }
+            let mut events = Vec::new();
+            for (index, line) in BufReader::new(File::open(&self.path)?).lines().enumerate() {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+                let corrupt = |e| corrupt(&self.path, index + 1, e);
+                let header: Header = serde_json::from_str(&line).map_err(corrupt)?;
+                if header.run_id == run_id && header.offset >= offset {
+                    let Event { event } = serde_json::from_str(&line).map_err(corrupt)?;
+                    events.push(event);
+                }
+            }
+            Ok(events)
+        }
+
+        async fn runs(&self) -> ProviderResult<Vec<String>> {
+            Ok(self.state().next.keys().cloned().collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryEventStore::new();
+        assert_eq!(store.append("run-1", 10).await.unwrap(), 0);
+        assert_eq!(store.append("run-2", 20).await.unwrap(), 0);
+        assert_eq!(store.clone().append("run-1", 11).await.unwrap(), 1);
+
+        assert_eq!(store.read("run-1").await.unwrap(), [10, 11]);
+        assert_eq!(store.read_from("run-1", 1).await.unwrap(), [11]);
+        assert!(store.read_from("run-1", 5).await.unwrap().is_empty());
+        assert!(store.read("run-3").await.unwrap().is_empty());
+        assert_eq!(store.runs().await.unwrap(), ["run-1", "run-2"]);
+
+        let replay: Vec<_> = store.stream_from("run-2", 0).await.unwrap().collect().await;
+        assert_eq!(replay, [20]);
+        assert!(store.remove("run-2"));
+        assert_eq!(store.runs().await.unwrap(), ["run-1"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn test_json_lines_store_reopens() {
+        let path =
+            std::env::temp_dir().join(format!("rustratify-events-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let store = JsonLinesEventStore::<String>::open(&path).unwrap();
+        store.append("build-1", "compiling".into()).await.unwrap();
+        store.append("build-2", "testing".into()).await.unwrap();
+        store.append("build-1", "done".into()).await.unwrap();
+        drop(store);
+
+        let store = JsonLinesEventStore::<String>::open(&path).unwrap();
+        assert_eq!(store.append("build-1", "again".into()).await.unwrap(), 2);
+        assert_eq!(
+            store.read("build-1").await.unwrap(),
+            ["compiling", "done", "again"]
+        );
+        let tail: Vec<_> = store
+            .stream_from("build-1", 1)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(tail, ["done", "again"]);
+        assert_eq!(store.runs().await.unwrap(), ["build-1", "build-2"]);
+
+        std::fs::write(&path, "not json\n").unwrap();
+        let err = JsonLinesEventStore::<String>::open(&path).unwrap_err();
+        assert!(err.to_string().contains(":1:"), "{err}");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
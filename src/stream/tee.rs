@@ -0,0 +1,350 @@
+//! Splitting one event stream into several.
+//!
+//! [`tee`] and [`Tee`] fan a single stream out to independent branches,
+//! e.g. one run's events feeding both UI rendering and a persistence task.
+//! Each branch buffers events its consumer has not read yet; a
+//! [`TeePolicy`] per branch decides what happens when that buffer fills.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use futures_core::Stream;
+
+use super::EventStream;
+
+/// What a [`Tee`] branch does when its buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TeePolicy {
+    /// Stop reading the source until the branch catches up, so the slowest
+    /// waiting branch sets the pace for all of them.
+    #[default]
+    Wait,
+    /// Discard the oldest buffered event to make room.
+    DropOldest,
+    /// Discard the incoming event.
+    DropNewest,
+}
+
+/// Builder for splitting a stream into branches with their own policies.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::{create_stream, Tee, TeePolicy};
+/// use futures::StreamExt;
+///
+/// # async fn example() {
+/// let (sender, stream) = create_stream::<u32>();
+/// let mut branches = Tee::new(stream)
+///     .buffer_size(16)
+///     .branch(TeePolicy::DropOldest) // UI: only recent events matter
+///     .branch(TeePolicy::Wait) // persistence: keep everything
+///     .build();
+/// let store = branches.pop().unwrap();
+/// let ui = branches.pop().unwrap();
+/// # drop((sender, store, ui));
+/// # }
+/// ```
+pub struct Tee<T> {
+    source: EventStream<T>,
+    buffer_size: usize,
+    policies: Vec<TeePolicy>,
+}
+
+impl<T: Clone + Send + 'static> Tee<T> {
+    /// Start splitting `source`.
+    pub fn new(source: EventStream<T>) -> Self {
+        Self {
+            source,
+            buffer_size: 100,
+            policies: Vec::new(),
+        }
+    }
+
+    /// Set how many unread events each branch buffers.
+    ///
+    /// Default is 100. A size of zero is treated as one.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size.max(1);
+        self
+    }
+
+    /// Add a branch with the given policy.
+    pub fn branch(mut self, policy: TeePolicy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    /// Build the branches, in the order they were added.
+    ///
+    /// Every branch sees every event read from the source while it is
+    /// alive, except those its policy discards. Dropping a branch does not
+    /// affect the others.
+    pub fn build(self) -> Vec<EventStream<T>> {
+        let count = self.policies.len();
+        let shared = Arc::new(Shared {
+            state: Mutex::new(TeeState {
+                source: self.source,
+                branches: self
+                    .policies
+                    .into_iter()
+                    .map(|policy| {
+                        Some(BranchState {
+                            policy,
+                            queue: VecDeque::new(),
+                        })
+                    })
+                    .collect(),
+                capacity: self.buffer_size,
+                done: false,
+            }),
+            wakers: Arc::new(TeeWaker {
+                wakers: Mutex::new(vec![None; count]),
+            }),
+        });
+        (0..count)
+            .map(|index| {
+                Box::pin(TeeBranch {
+                    shared: Arc::clone(&shared),
+                    index,
+                }) as EventStream<T>
+            })
+            .collect()
+    }
+}
+
+/// Split a stream into `n` branches that each see every event.
+///
+/// Branches buffer up to 100 unread events and use [`TeePolicy::Wait`].
+/// Use [`Tee`] to choose the buffer size and per-branch policies.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::{create_stream, tee};
+/// use futures::StreamExt;
+///
+/// # async fn example() {
+/// let (sender, stream) = create_stream::<u32>();
+/// sender.send(1).await.unwrap();
+/// drop(sender);
+///
+/// let branches = tee(stream, 2);
+/// for branch in branches {
+///     assert_eq!(branch.collect::<Vec<_>>().await, vec![1]);
+/// }
+/// # }
+/// ```
+pub fn tee<T: Clone + Send + 'static>(source: EventStream<T>, n: usize) -> Vec<EventStream<T>> {
+    (0..n)
+        .fold(Tee::new(source), |tee, _| tee.branch(TeePolicy::Wait))
+        .build()
+}
+
+struct Shared<T> {
+    state: Mutex<TeeState<T>>,
+    wakers: Arc<TeeWaker>,
+}
+
+struct TeeState<T> {
+    source: EventStream<T>,
+    /// `None` once the branch has been dropped.
+    branches: Vec<Option<BranchState<T>>>,
+    capacity: usize,
+    done: bool,
+}
+
+struct BranchState<T> {
+    policy: TeePolicy,
+    queue: VecDeque<T>,
+}
+
+impl<T: Clone> TeeState<T> {
+    fn blocked(&self) -> bool {
+        self.branches
+            .iter()
+            .flatten()
+            .any(|branch| branch.policy == TeePolicy::Wait && branch.queue.len() >= self.capacity)
+    }
+
+    fn distribute(&mut self, item: T) {
+        let capacity = self.capacity;
+        let mut alive: Vec<_> = self.branches.iter_mut().flatten().collect();
+        if let Some(last) = alive.pop() {
+            for branch in alive {
+                branch.push(item.clone(), capacity);
+            }
+            last.push(item, capacity);
+        }
+    }
+}
+
+impl<T> BranchState<T> {
+    fn push(&mut self, item: T, capacity: usize) {
+        if self.queue.len() >= capacity {
+            match self.policy {
+                TeePolicy::DropNewest => return,
+                TeePolicy::DropOldest => {
+                    self.queue.pop_front();
+                }
+                TeePolicy::Wait => {}
+            }
+        }
+        self.queue.push_back(item);
+    }
+}
+
+/// Wakes every branch waiting on the source, so the source can be polled
+/// by whichever branch consumer runs next.
+struct TeeWaker {
+    wakers: Mutex<Vec<Option<Waker>>>,
+}
+
+impl TeeWaker {
+    fn register(&self, index: usize, waker: &Waker) {
+        self.wakers.lock().unwrap()[index] = Some(waker.clone());
+    }
+
+    fn wake_all(&self) {
+        let wakers: Vec<_> = self
+            .wakers
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .flat_map(Option::take)
+            .collect();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl Wake for TeeWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_all();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wake_all();
+    }
+}
+
+struct TeeBranch<T> {
+    shared: Arc<Shared<T>>,
+    index: usize,
+}
+
+impl<T: Clone> Stream for TeeBranch<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        let wakers = &this.shared.wakers;
+        let mut state = this.shared.state.lock().unwrap();
+        loop {
+            let capacity = state.capacity;
+            let branch = state.branches[this.index]
+                .as_mut()
+                .expect("tee branch polled after drop");
+            if let Some(item) = branch.queue.pop_front() {
+                if branch.policy == TeePolicy::Wait && branch.queue.len() + 1 == capacity {
+                    // This branch may have been holding the others back.
+                    wakers.wake_all();
+                }
+                return Poll::Ready(Some(item));
+            }
+            if state.done {
+                return Poll::Ready(None);
+            }
+
+            wakers.register(this.index, cx.waker());
+            if state.blocked() {
+                return Poll::Pending;
+            }
+            let waker = Waker::from(Arc::clone(wakers));
+            match state
+                .source
+                .as_mut()
+                .poll_next(&mut Context::from_waker(&waker))
+            {
+                Poll::Ready(Some(item)) => {
+                    state.distribute(item);
+                    wakers.wake_all();
+                }
+                Poll::Ready(None) => {
+                    state.done = true;
+                    wakers.wake_all();
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> Drop for TeeBranch<T> {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().branches[self.index] = None;
+        // A dropped branch may have been blocking the source.
+        self.shared.wakers.wake_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::create_stream_with_buffer;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_tee_every_branch_sees_every_event() {
+        let (sender, stream) = create_stream_with_buffer::<u32>(2);
+        let producer = tokio::spawn(async move {
+            for i in 0..20 {
+                sender.send(i).await.unwrap();
+            }
+        });
+
+        let consumers: Vec<_> = tee(stream, 3)
+            .into_iter()
+            .map(|branch| tokio::spawn(branch.collect::<Vec<_>>()))
+            .collect();
+        producer.await.unwrap();
+        for consumer in consumers {
+            assert_eq!(consumer.await.unwrap(), (0..20).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tee_policies() {
+        let source = futures::stream::iter(0..5).boxed();
+        let mut branches = Tee::new(source)
+            .buffer_size(2)
+            .branch(TeePolicy::Wait)
+            .branch(TeePolicy::DropOldest)
+            .branch(TeePolicy::DropNewest)
+            .build()
+            .into_iter();
+        let wait = branches.next().unwrap();
+        let drop_oldest = branches.next().unwrap();
+        let drop_newest = branches.next().unwrap();
+
+        // Reading one branch to the end overfills the unread ones.
+        assert_eq!(wait.collect::<Vec<_>>().await, [0, 1, 2, 3, 4]);
+        assert_eq!(drop_oldest.collect::<Vec<_>>().await, [3, 4]);
+        assert_eq!(drop_newest.collect::<Vec<_>>().await, [0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_tee_dropped_branch_does_not_block() {
+        let source = futures::stream::iter(0..10).boxed();
+        let mut branches = Tee::new(source)
+            .buffer_size(1)
+            .branch(TeePolicy::Wait)
+            .branch(TeePolicy::Wait)
+            .build();
+        drop(branches.pop());
+
+        let events: Vec<_> = branches.pop().unwrap().collect().await;
+        assert_eq!(events, (0..10).collect::<Vec<_>>());
+    }
+}
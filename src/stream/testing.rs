@@ -0,0 +1,258 @@
+//! Recording and playback of event streams for tests.
+//!
+//! [`record`] captures every item of a stream along with its arrival offset.
+//! [`playback`] turns a [`Recording`] back into an [`EventStream`], so a
+//! sequence observed in production or in a bug report can be replayed
+//! deterministically. With the `serde` feature enabled, recordings can be
+//! serialized whenever the event type can.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+use tokio::time::{Instant, Sleep};
+
+use super::{EventStream, NextItem};
+
+/// A single recorded event and when it arrived.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedEvent<T> {
+    /// Time elapsed between the start of the recording and this event
+    pub offset: Duration,
+    /// The recorded event
+    pub event: T,
+}
+
+/// An ordered sequence of recorded events.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recording<T> {
+    /// Recorded events, in arrival order
+    pub events: Vec<RecordedEvent<T>>,
+}
+
+impl<T> Recording<T> {
+    /// Create an empty recording.
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    /// Append an event at the given offset.
+    pub fn push(&mut self, offset: Duration, event: T) {
+        self.events.push(RecordedEvent { offset, event });
+    }
+
+    /// Get the number of recorded events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Check if the recording is empty.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Get the total duration of the recording.
+    pub fn duration(&self) -> Duration {
+        self.events.last().map(|e| e.offset).unwrap_or_default()
+    }
+
+    /// Iterate over the recorded events, discarding timing.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events.iter().map(|e| &e.event)
+    }
+
+    /// Consume the recording, returning the events without timing.
+    pub fn into_events(self) -> Vec<T> {
+        self.events.into_iter().map(|e| e.event).collect()
+    }
+}
+
+impl<T> Default for Recording<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<T> for Recording<T> {
+    /// Build a recording with every event at offset zero.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            events: iter
+                .into_iter()
+                .map(|event| RecordedEvent {
+                    offset: Duration::ZERO,
+                    event,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Record a stream until it ends.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::create_stream;
+/// use rustratify::stream::testing::record;
+///
+/// # async fn example() {
+/// let (sender, stream) = create_stream::<u32>();
+/// sender.send(1).await.unwrap();
+/// drop(sender);
+///
+/// let recording = record(stream).await;
+/// assert_eq!(recording.into_events(), vec![1]);
+/// # }
+/// ```
+pub async fn record<T>(mut stream: EventStream<T>) -> Recording<T> {
+    let start = Instant::now();
+    let mut recording = Recording::new();
+    while let Some(event) = NextItem(&mut stream).await {
+        recording.push(start.elapsed(), event);
+    }
+    recording
+}
+
+/// Timing used when playing back a recording.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    /// Emit every event as fast as the consumer polls
+    Immediate,
+    /// Reproduce the recorded offsets
+    Original,
+    /// Reproduce the recorded offsets multiplied by a factor
+    Scaled(f64),
+    /// Emit events a fixed interval apart, ignoring recorded offsets
+    Fixed(Duration),
+}
+
+/// Play back a recording as an event stream.
+pub fn playback<T: Send + 'static>(recording: Recording<T>, pacing: Pacing) -> EventStream<T> {
+    Box::pin(Playback {
+        events: recording.events.into(),
+        pacing,
+        index: 0,
+        start: None,
+        delay: Box::pin(tokio::time::sleep(Duration::ZERO)),
+        armed: false,
+    })
+}
+
+struct Playback<T> {
+    events: VecDeque<RecordedEvent<T>>,
+    pacing: Pacing,
+    index: u32,
+    start: Option<Instant>,
+    delay: Pin<Box<Sleep>>,
+    armed: bool,
+}
+
+// `T` is only ever moved, never pinned.
+impl<T> Unpin for Playback<T> {}
+
+impl<T> Playback<T> {
+    fn scheduled_offset(&self, recorded: Duration) -> Duration {
+        match self.pacing {
+            Pacing::Immediate => Duration::ZERO,
+            Pacing::Original => recorded,
+            Pacing::Scaled(factor) => recorded.mul_f64(factor.max(0.0)),
+            Pacing::Fixed(interval) => interval * self.index,
+        }
+    }
+}
+
+impl<T> Stream for Playback<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let Some(offset) = self.events.front().map(|e| e.offset) else {
+            return Poll::Ready(None);
+        };
+
+        if self.pacing != Pacing::Immediate {
+            if !self.armed {
+                let start = *self.start.get_or_insert_with(Instant::now);
+                let deadline = start + self.scheduled_offset(offset);
+                self.delay.as_mut().reset(deadline);
+                self.armed = true;
+            }
+            if self.delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.armed = false;
+        }
+
+        self.index += 1;
+        Poll::Ready(self.events.pop_front().map(|e| e.event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::create_stream;
+    use futures::StreamExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_record_captures_offsets() {
+        let (sender, stream) = create_stream::<&'static str>();
+
+        tokio::spawn(async move {
+            sender.send("start").await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            sender.send("done").await.unwrap();
+        });
+
+        let recording = record(stream).await;
+        assert_eq!(recording.len(), 2);
+        assert_eq!(recording.events[0].offset, Duration::ZERO);
+        assert!(recording.events[1].offset >= Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_playback_original_pacing() {
+        let mut recording = Recording::new();
+        recording.push(Duration::ZERO, 1);
+        recording.push(Duration::from_millis(500), 2);
+
+        let start = Instant::now();
+        let events: Vec<_> = playback(recording, Pacing::Original).collect().await;
+
+        assert_eq!(events, vec![1, 2]);
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_playback_fixed_and_immediate() {
+        let recording: Recording<u32> = (0..3).collect();
+
+        let start = Instant::now();
+        let events: Vec<_> = playback(recording.clone(), Pacing::Fixed(Duration::from_secs(1)))
+            .collect()
+            .await;
+        assert_eq!(events, vec![0, 1, 2]);
+        assert!(start.elapsed() >= Duration::from_secs(2));
+
+        let start = Instant::now();
+        let events: Vec<_> = playback(recording, Pacing::Immediate).collect().await;
+        assert_eq!(events, vec![0, 1, 2]);
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_recording_serde_roundtrip() {
+        let mut recording = Recording::new();
+        recording.push(Duration::from_millis(5), "event".to_string());
+
+        let json = serde_json::to_string(&recording).unwrap();
+        let restored: Recording<String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, recording);
+    }
+}
@@ -0,0 +1,189 @@
+//! Support for streams of `Result` items.
+//!
+//! Operations that can fail part-way through push their failures through the
+//! same channel as their output: the stream item type is `Result<T, E>`, and
+//! [`TryEventStreamExt`] provides the consumer-side helpers.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures_core::Stream;
+
+use super::{EventSender, EventStream, NextItem};
+
+impl<T, E> EventSender<Result<T, E>> {
+    /// Send a successful item.
+    ///
+    /// Returns `Err(value)` if the receiver was dropped.
+    pub async fn send_ok(&self, value: T) -> Result<(), T> {
+        match self.send(Ok(value)).await {
+            Ok(()) => Ok(()),
+            Err(Ok(value)) => Err(value),
+            Err(Err(_)) => unreachable!("send returned a different item"),
+        }
+    }
+
+    /// Send an error.
+    ///
+    /// Returns `Err(error)` if the receiver was dropped.
+    pub async fn send_err(&self, error: E) -> Result<(), E> {
+        match self.send(Err(error)).await {
+            Ok(()) => Ok(()),
+            Err(Err(error)) => Err(error),
+            Err(Ok(_)) => unreachable!("send returned a different item"),
+        }
+    }
+}
+
+/// Extension trait for streams of `Result` items.
+///
+/// # Example
+///
+/// ```rust
+/// use rustratify::stream::{create_stream, TryEventStreamExt};
+///
+/// # async fn example() {
+/// let (sender, stream) = create_stream::<Result<u32, String>>();
+/// sender.send_ok(1).await.unwrap();
+/// sender.send_err("disk full".to_string()).await.unwrap();
+/// drop(sender);
+///
+/// assert_eq!(stream.collect_ok().await, Err("disk full".to_string()));
+/// # }
+/// ```
+#[async_trait]
+pub trait TryEventStreamExt<T, E> {
+    /// Collect all successful items, stopping at the first error.
+    async fn collect_ok(self) -> Result<Vec<T>, E>;
+
+    /// Drain the stream and return the first error, if any.
+    ///
+    /// Successful items are discarded.
+    async fn first_error(self) -> Option<E>;
+
+    /// End the stream after the first error.
+    ///
+    /// The error item itself is yielded.
+    fn abort_on_error(self) -> EventStream<Result<T, E>>;
+}
+
+#[async_trait]
+impl<S, T, E> TryEventStreamExt<T, E> for S
+where
+    S: Stream<Item = Result<T, E>> + Send + 'static,
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    async fn collect_ok(self) -> Result<Vec<T>, E> {
+        let mut stream: EventStream<Result<T, E>> = Box::pin(self);
+        let mut items = Vec::new();
+        while let Some(item) = NextItem(&mut stream).await {
+            items.push(item?);
+        }
+        Ok(items)
+    }
+
+    async fn first_error(self) -> Option<E> {
+        let mut stream: EventStream<Result<T, E>> = Box::pin(self);
+        while let Some(item) = NextItem(&mut stream).await {
+            if let Err(error) = item {
+                return Some(error);
+            }
+        }
+        None
+    }
+
+    fn abort_on_error(self) -> EventStream<Result<T, E>> {
+        Box::pin(AbortOnError {
+            inner: Some(Box::pin(self)),
+        })
+    }
+}
+
+/// Stream returned by [`TryEventStreamExt::abort_on_error`].
+struct AbortOnError<T, E> {
+    inner: Option<EventStream<Result<T, E>>>,
+}
+
+impl<T, E> Stream for AbortOnError<T, E> {
+    type Item = Result<T, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+
+        match inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Err(error))) => {
+                self.inner = None;
+                Poll::Ready(Some(Err(error)))
+            }
+            Poll::Ready(None) => {
+                self.inner = None;
+                Poll::Ready(None)
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::create_stream;
+    use futures::StreamExt;
+
+    async fn failing_stream() -> EventStream<Result<u32, String>> {
+        let (sender, stream) = create_stream::<Result<u32, String>>();
+        sender.send_ok(1).await.unwrap();
+        sender.send_ok(2).await.unwrap();
+        sender.send_err("boom".to_string()).await.unwrap();
+        sender.send_ok(3).await.unwrap();
+        stream
+    }
+
+    #[tokio::test]
+    async fn test_collect_ok() {
+        let (sender, stream) = create_stream::<Result<u32, String>>();
+        sender.send_ok(1).await.unwrap();
+        sender.send_ok(2).await.unwrap();
+        drop(sender);
+        assert_eq!(stream.collect_ok().await, Ok(vec![1, 2]));
+
+        assert_eq!(
+            failing_stream().await.collect_ok().await,
+            Err("boom".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_first_error() {
+        assert_eq!(
+            failing_stream().await.first_error().await,
+            Some("boom".to_string())
+        );
+
+        let (sender, stream) = create_stream::<Result<u32, String>>();
+        sender.send_ok(1).await.unwrap();
+        drop(sender);
+        assert_eq!(stream.first_error().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_abort_on_error() {
+        let items: Vec<_> = failing_stream().await.abort_on_error().collect().await;
+        assert_eq!(items, vec![Ok(1), Ok(2), Err("boom".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_send_err_after_close() {
+        let (sender, stream) = create_stream::<Result<u32, String>>();
+        drop(stream);
+        assert_eq!(
+            sender.send_err("lost".to_string()).await,
+            Err("lost".to_string())
+        );
+        assert_eq!(sender.send_ok(5).await, Err(5));
+    }
+}
@@ -0,0 +1,251 @@
+//! Heartbeat watchdog for event streams.
+//!
+//! A provider that hangs without failing leaves its run's stream silent,
+//! and a consumer waiting on the next event cannot tell a slow run from a
+//! stuck one. A [`Watchdog`] reports a stall whenever no event arrives
+//! within its heartbeat interval, and can cancel the run by dropping the
+//! source after repeated stalls; the producer then sees its stream closed.
+//! Requires the `tokio` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use rustratify::stream::{create_stream, Watchdog, WatchdogItem};
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main(flavor = "current_thread", start_paused = true)]
+//! # async fn main() {
+//! let (sender, stream) = create_stream::<u32>();
+//! let mut watched = Watchdog::new(Duration::from_secs(30))
+//!     .cancel_after(2)
+//!     .watch(stream);
+//!
+//! sender.send(1).await.unwrap();
+//! assert_eq!(watched.next().await, Some(WatchdogItem::Event(1)));
+//! // The provider hangs
+//! assert!(matches!(
+//!     watched.next().await,
+//!     Some(WatchdogItem::Stalled { cancelled: false, .. })
+//! ));
+//! assert!(matches!(
+//!     watched.next().await,
+//!     Some(WatchdogItem::Stalled { cancelled: true, .. })
+//! ));
+//! assert_eq!(watched.next().await, None);
+//! assert!(sender.is_closed());
+//! # }
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+use tokio::time::{Instant, Sleep};
+
+use super::EventStream;
+
+/// An item of a stream watched by a [`Watchdog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchdogItem<T> {
+    /// The next event
+    Event(T),
+    /// No event arrived within the heartbeat interval
+    Stalled {
+        /// Time since the last event, or since the stream was first polled
+        silent_for: Duration,
+        /// Whether the watchdog cancelled the run, ending the stream
+        cancelled: bool,
+    },
+}
+
+/// Reports runs whose streams go silent.
+///
+/// A stall is reported once per heartbeat interval for as long as the
+/// stream stays silent; any event resets the count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchdog {
+    heartbeat: Duration,
+    cancel_after: Option<u32>,
+}
+
+impl Watchdog {
+    /// Create a watchdog that expects an event at least every `heartbeat`.
+    pub fn new(heartbeat: Duration) -> Self {
+        Self {
+            heartbeat,
+            cancel_after: None,
+        }
+    }
+
+    /// Cancel the run after `stalls` stalls in a row.
+    ///
+    /// The source stream is dropped and the watched stream ends after the
+    /// last stall is reported. By default the watchdog only reports. Zero
+    /// is treated as one.
+    pub fn cancel_after(mut self, stalls: u32) -> Self {
+        self.cancel_after = Some(stalls.max(1));
+        self
+    }
+
+    /// Watch `stream`.
+    ///
+    /// The heartbeat starts when the watched stream is first polled, not
+    /// when it is created.
+    pub fn watch<T: Send + 'static>(self, stream: EventStream<T>) -> EventStream<WatchdogItem<T>> {
+        Box::pin(Watched::new(stream, self))
+    }
+}
+
+/// Stream returned by [`Watchdog::watch`].
+struct Watched<T> {
+    inner: Option<EventStream<T>>,
+    watchdog: Watchdog,
+    /// Started on the first poll
+    delay: Option<Pin<Box<Sleep>>>,
+    last_event: Option<Instant>,
+    stalls: u32,
+}
+
+impl<T> Watched<T> {
+    fn new(inner: EventStream<T>, watchdog: Watchdog) -> Self {
+        Self {
+            inner: Some(inner),
+            watchdog,
+            delay: None,
+            last_event: None,
+            stalls: 0,
+        }
+    }
+}
+
+impl<T> Stream for Watched<T> {
+    type Item = WatchdogItem<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let Some(inner) = this.inner.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let heartbeat = this.watchdog.heartbeat;
+        let last_event = *this.last_event.get_or_insert_with(Instant::now);
+        let delay = this
+            .delay
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(last_event + heartbeat)));
+        match inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                let now = Instant::now();
+                this.last_event = Some(now);
+                this.stalls = 0;
+                delay.as_mut().reset(now + heartbeat);
+                return Poll::Ready(Some(WatchdogItem::Event(event)));
+            }
+            Poll::Ready(None) => {
+                this.inner = None;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => {}
+        }
+
+        if delay.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        let now = Instant::now();
+        this.stalls += 1;
+        delay.as_mut().reset(now + heartbeat);
+        let silent_for = now - last_event;
+        let cancelled = this
+            .watchdog
+            .cancel_after
+            .is_some_and(|stalls| this.stalls >= stalls);
+        if cancelled {
+            // Dropping the source closes the run's channel
+            this.inner = None;
+            tracing::warn!(?silent_for, "cancelling stalled run");
+        } else {
+            tracing::warn!(?silent_for, "run stalled");
+        }
+        Poll::Ready(Some(WatchdogItem::Stalled {
+            silent_for,
+            cancelled,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{create_stream, EventStreamExt};
+    use futures::StreamExt;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stalls_reported_until_events_resume() {
+        let (sender, stream) = create_stream::<u32>();
+        let mut watched = stream.watchdog(Duration::from_secs(1));
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(2500)).await;
+            sender.send(7).await.unwrap();
+        });
+
+        let stalls: Vec<_> = (&mut watched).take(2).collect().await;
+        assert_eq!(
+            stalls,
+            [1, 2].map(|secs| WatchdogItem::Stalled {
+                silent_for: Duration::from_secs(secs),
+                cancelled: false,
+            })
+        );
+        assert_eq!(watched.next().await, Some(WatchdogItem::Event(7)));
+        assert_eq!(watched.next().await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_drops_source() {
+        let (sender, stream) = create_stream::<u32>();
+        let mut watched = Watchdog::new(Duration::from_secs(1))
+            .cancel_after(1)
+            .watch(stream);
+
+        let stall = watched.next().await.unwrap();
+        assert!(matches!(
+            stall,
+            WatchdogItem::Stalled {
+                cancelled: true,
+                ..
+            }
+        ));
+        assert_eq!(watched.next().await, None);
+        assert!(sender.send(1).await.is_err());
+    }
+
+    #[test]
+    fn test_heartbeat_starts_on_first_poll() {
+        let (sender, stream) = create_stream::<u32>();
+        // Watching needs no runtime; the timer is created when polled
+        let mut watched = Watchdog::new(Duration::from_secs(1))
+            .cancel_after(1)
+            .watch(stream);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // A consumer that starts late gets a full heartbeat
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            assert_eq!(
+                watched.next().await,
+                Some(WatchdogItem::Stalled {
+                    silent_for: Duration::from_secs(1),
+                    cancelled: true,
+                })
+            );
+        });
+        assert!(sender.is_closed());
+    }
+}
@@ -0,0 +1,126 @@
+//! Named tokio tasks, for telling runs apart in tokio-console.
+//!
+//! Every task this crate spawns, such as scheduler jobs, batch items and
+//! transport consumers, goes through [`spawn_named`], or [`spawn_named_in`]
+//! for tasks in a [`JoinSet`], and [`spawn_run`] spawns a run named after
+//! its run ID and provider. With the `task-names` feature, in a build with
+//! `RUSTFLAGS="--cfg tokio_unstable"` as tokio-console requires, the names
+//! are attached with [`tokio::task::Builder`]. Otherwise tasks are spawned
+//! unnamed, as with [`tokio::spawn`].
+//!
+//! Requires the `tokio` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::task::spawn_run;
+//! use rustratify::Context;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let cx = Context::new().with_run_id("build-7");
+//! // Shows up in tokio-console as "run build-7 cargo"
+//! let task = spawn_run(&cx, "cargo", "release", async { 42 });
+//! assert_eq!(task.await.unwrap(), 42);
+//! # }
+//! ```
+
+use std::future::Future;
+
+use tokio::task::{AbortHandle, JoinHandle, JoinSet};
+use tracing::Instrument;
+
+use crate::context::Context;
+use crate::log_bridge::run_span;
+
+/// Spawn `future` on the current runtime as a task called `name`.
+///
+/// # Panics
+///
+/// Panics outside a tokio runtime, like [`tokio::spawn`].
+#[track_caller]
+pub fn spawn_named<F>(name: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(feature = "task-names", tokio_unstable))]
+    {
+        tokio::task::Builder::new()
+            .name(name)
+            .spawn(future)
+            .expect("failed to spawn task")
+    }
+    #[cfg(not(all(feature = "task-names", tokio_unstable)))]
+    {
+        let _ = name;
+        tokio::spawn(future)
+    }
+}
+
+/// Spawn `future` on `set` as a task called `name`.
+///
+/// # Panics
+///
+/// Panics outside a tokio runtime, like [`JoinSet::spawn`].
+#[track_caller]
+pub fn spawn_named_in<T, F>(set: &mut JoinSet<T>, name: &str, future: F) -> AbortHandle
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    #[cfg(all(feature = "task-names", tokio_unstable))]
+    {
+        set.build_task()
+            .name(name)
+            .spawn(future)
+            .expect("failed to spawn task")
+    }
+    #[cfg(not(all(feature = "task-names", tokio_unstable)))]
+    {
+        let _ = name;
+        set.spawn(future)
+    }
+}
+
+/// The task name [`spawn_run`] gives a run of `provider`: `run`, the run
+/// ID if `cx` has one, and the provider name.
+pub fn run_task_name(cx: &Context, provider: &str) -> String {
+    match cx.run_id() {
+        Some(run_id) => format!("run {run_id} {provider}"),
+        None => format!("run {provider}"),
+    }
+}
+
+/// Spawn a run of `provider` with the configuration named `config` as a
+/// [named](run_task_name) task, inside the
+/// [run span](crate::log_bridge::run_span) for `cx`.
+#[track_caller]
+pub fn spawn_run<F>(cx: &Context, provider: &str, config: &str, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let span = run_span(cx, provider, config);
+    spawn_named(&run_task_name(cx, provider), future.instrument(span))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spawn_run() {
+        let cx = Context::new().with_run_id("run-1");
+        assert_eq!(run_task_name(&cx, "rust"), "run run-1 rust");
+        assert_eq!(run_task_name(&Context::new(), "rust"), "run rust");
+
+        let task = spawn_run(&cx, "rust", "default", async { 2 });
+        assert_eq!(task.await.unwrap(), 2);
+        assert_eq!(spawn_named("named", async { 1 }).await.unwrap(), 1);
+
+        let mut set = JoinSet::new();
+        spawn_named_in(&mut set, "in a set", async { 3 });
+        assert_eq!(set.join_next().await.unwrap().unwrap(), 3);
+    }
+}
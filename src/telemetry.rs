@@ -0,0 +1,285 @@
+//! OpenTelemetry spans and metrics for provider calls, runs, and streams.
+//!
+//! Spans and instruments come from the global tracer and meter providers
+//! under the `rustratify` instrumentation scope, so they go wherever the
+//! application's OpenTelemetry pipeline sends them. Until a pipeline is
+//! installed everything here is a no-op apart from context propagation.
+//!
+//! Each helper takes the caller's [`Context`], starts a child span, and hands
+//! the operation a [`Context`] carrying that span, so nested calls line up in
+//! the trace.
+//!
+//! Requires the `otel` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::{telemetry, Context, ProviderError};
+//!
+//! # async fn example() -> Result<(), ProviderError> {
+//! let cx = Context::current().with_run_id("run-1");
+//! let output = telemetry::provider_call(&cx, "rust", "analyze", |cx| async move {
+//!     // `cx` carries the "rust analyze" span; pass it to nested calls
+//!     Ok::<_, ProviderError>(cx.run_id().map(str::len))
+//! })
+//! .await?;
+//! assert_eq!(output, Some(5));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+use std::time::Instant;
+
+use futures_core::Stream;
+use opentelemetry::context::FutureExt;
+use opentelemetry::global::{self, BoxedSpan};
+use opentelemetry::trace::{Span, SpanKind, Status, TraceContextExt, Tracer};
+use opentelemetry::KeyValue;
+
+use crate::context::Context;
+use crate::stream::EventStream;
+
+/// Instrumentation scope name for all spans and instruments.
+pub const SCOPE: &str = "rustratify";
+
+/// Attribute holding the run ID from [`Context::run_id`].
+pub const RUN_ID: &str = "rustratify.run_id";
+/// Attribute holding the correlation ID from [`Context::correlation_id`].
+pub const CORRELATION_ID: &str = "rustratify.correlation_id";
+/// Attribute holding the causation ID from [`Context::causation_id`].
+pub const CAUSATION_ID: &str = "rustratify.causation_id";
+/// Attribute holding the provider name.
+pub const PROVIDER: &str = "rustratify.provider";
+/// Attribute holding the provider operation.
+pub const OPERATION: &str = "rustratify.operation";
+/// Attribute holding the stream name.
+pub const STREAM: &str = "rustratify.stream";
+/// Attribute holding `ok` or `error`.
+pub const OUTCOME: &str = "rustratify.outcome";
+
+/// Run `f` as a call to `operation` on `provider`, in a span named
+/// `"{provider} {operation}"`.
+///
+/// Records the `rustratify.provider.calls` counter and the
+/// `rustratify.provider.duration` histogram (seconds). An `Err` marks the span
+/// as failed with the error's message.
+pub async fn provider_call<F, Fut, T, E>(
+    cx: &Context,
+    provider: &str,
+    operation: &str,
+    f: F,
+) -> Result<T, E>
+where
+    F: FnOnce(Context) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: fmt::Display,
+{
+    let attributes = vec![
+        KeyValue::new(PROVIDER, provider.to_string()),
+        KeyValue::new(OPERATION, operation.to_string()),
+    ];
+    let name = format!("{provider} {operation}");
+    let metrics = ("rustratify.provider.calls", "rustratify.provider.duration");
+    instrument(cx, name, metrics, attributes, f).await
+}
+
+/// Run `f` as one executor run, in a span named `rustratify.run`.
+///
+/// Records the `rustratify.run.count` counter and the
+/// `rustratify.run.duration` histogram (seconds).
+pub async fn run<F, Fut, T, E>(cx: &Context, f: F) -> Result<T, E>
+where
+    F: FnOnce(Context) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: fmt::Display,
+{
+    let metrics = ("rustratify.run.count", "rustratify.run.duration");
+    instrument(cx, "rustratify.run".to_string(), metrics, vec![], f).await
+}
+
+async fn instrument<F, Fut, T, E>(
+    cx: &Context,
+    name: String,
+    (counter, histogram): (&'static str, &'static str),
+    mut attributes: Vec<KeyValue>,
+    f: F,
+) -> Result<T, E>
+where
+    F: FnOnce(Context) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: fmt::Display,
+{
+    push_ids(cx, &mut attributes);
+    let tracer = global::tracer(SCOPE);
+    let span = tracer
+        .span_builder(name)
+        .with_kind(SpanKind::Internal)
+        .with_attributes(attributes.clone())
+        .start_with_context(&tracer, cx.otel());
+    let otel = cx.otel().with_span(span);
+    let child = cx.clone().with_otel(otel.clone());
+
+    let start = Instant::now();
+    let result = f(child).with_context(otel.clone()).await;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let span = otel.span();
+    let outcome = match &result {
+        Ok(_) => "ok",
+        Err(err) => {
+            span.set_status(Status::error(err.to_string()));
+            "error"
+        }
+    };
+    span.end();
+
+    attributes.push(KeyValue::new(OUTCOME, outcome));
+    let meter = global::meter(SCOPE);
+    meter.u64_counter(counter).build().add(1, &attributes);
+    meter
+        .f64_histogram(histogram)
+        .with_unit("s")
+        .build()
+        .record(elapsed, &attributes);
+    result
+}
+
+/// Add the run, correlation and causation IDs of `cx` to `attributes`.
+fn push_ids(cx: &Context, attributes: &mut Vec<KeyValue>) {
+    let ids = [
+        (RUN_ID, cx.run_id()),
+        (CORRELATION_ID, cx.correlation_id()),
+        (CAUSATION_ID, cx.causation_id()),
+    ];
+    for (key, id) in ids {
+        if let Some(id) = id {
+            attributes.push(KeyValue::new(key, id.to_string()));
+        }
+    }
+}
+
+/// Trace the lifetime of `stream` in a span named `rustratify.stream`.
+///
+/// The span starts now and ends when the stream finishes or is dropped. It
+/// records the number of events taken as `rustratify.stream.events`, and
+/// `rustratify.stream.cancelled` if the stream was dropped before it ended.
+pub fn instrument_stream<T: Send + 'static>(
+    cx: &Context,
+    name: &str,
+    stream: EventStream<T>,
+) -> EventStream<T> {
+    let mut attributes = vec![KeyValue::new(STREAM, name.to_string())];
+    push_ids(cx, &mut attributes);
+    let tracer = global::tracer(SCOPE);
+    let span = tracer
+        .span_builder("rustratify.stream")
+        .with_kind(SpanKind::Internal)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, cx.otel());
+    Box::pin(TracedStream {
+        inner: stream,
+        span: Some(span),
+        events: 0,
+    })
+}
+
+struct TracedStream<T> {
+    inner: EventStream<T>,
+    span: Option<BoxedSpan>,
+    events: u64,
+}
+
+impl<T> TracedStream<T> {
+    fn finish(&mut self, cancelled: bool) {
+        if let Some(mut span) = self.span.take() {
+            span.set_attribute(KeyValue::new(
+                "rustratify.stream.events",
+                self.events as i64,
+            ));
+            if cancelled {
+                span.set_attribute(KeyValue::new("rustratify.stream.cancelled", true));
+            }
+            span.end();
+        }
+    }
+}
+
+impl<T> Stream for TracedStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Option<T>> {
+        let poll = self.inner.as_mut().poll_next(cx);
+        match &poll {
+            Poll::Ready(Some(_)) => self.events += 1,
+            Poll::Ready(None) => self.finish(false),
+            Poll::Pending => {}
+        }
+        poll
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> Drop for TracedStream<T> {
+    fn drop(&mut self) {
+        self.finish(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StreamBuilder;
+    use futures::StreamExt;
+    use opentelemetry::trace::{SpanContext, SpanId, TraceFlags, TraceId, TraceState};
+
+    fn remote_parent() -> Context {
+        let parent = SpanContext::new(
+            TraceId::from(7),
+            SpanId::from(9),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        );
+        Context::new()
+            .with_run_id("r1")
+            .with_otel(opentelemetry::Context::new().with_remote_span_context(parent))
+    }
+
+    #[tokio::test]
+    async fn test_provider_call_propagates_context() {
+        let cx = remote_parent();
+        let trace_id = super::provider_call(&cx, "rust", "analyze", |child| async move {
+            assert_eq!(child.run_id(), Some("r1"));
+            let nested = super::provider_call(&child, "inner", "parse", |cx| async move {
+                Ok::<_, String>(cx.otel().span().span_context().trace_id())
+            });
+            nested.await
+        })
+        .await
+        .unwrap();
+        assert_eq!(trace_id, TraceId::from(7));
+
+        let err = super::run(&cx, |_| async { Err::<(), _>("boom") }).await;
+        assert_eq!(err, Err("boom"));
+    }
+
+    #[tokio::test]
+    async fn test_instrument_stream_passes_events_through() {
+        let (sender, stream) = StreamBuilder::<u32>::new().build();
+        let mut stream = instrument_stream(&remote_parent(), "events", stream);
+        sender.send(1).await.unwrap();
+        sender.send(2).await.unwrap();
+        drop(sender);
+
+        assert_eq!(stream.next().await, Some(1));
+        assert_eq!(stream.next().await, Some(2));
+        assert_eq!(stream.next().await, None);
+    }
+}
@@ -0,0 +1,355 @@
+//! Helpers for testing code built on providers, registries, and streams.
+//!
+//! - [`MockProvider`]: a provider with configurable metadata and canned
+//!   results that records every call
+//! - [`TestRegistry`]: assertions on a [`Registry`]
+//! - [`collect_events_with_timeout`]: drain a stream, failing the test if it
+//!   does not end in time
+//! - [`spi_contract_tests!`](crate::spi_contract_tests) and the `check_*`
+//!   functions: conformance checks for any [`Provider`] implementation
+//! - [`strategy`], with the `proptest` feature: property-test strategies for
+//!   core types
+//!
+//! Stream recording and playback live in
+//! [`stream::testing`](crate::stream::testing).
+//!
+//! Requires the `testing` feature; enable it for dev-dependencies only.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::testing::{MockProvider, TestRegistry};
+//! use rustratify::{Provider, Registry};
+//!
+//! let rust = MockProvider::new("rust")
+//!     .with_extensions(&[".rs"])
+//!     .returning("fn main() {}".to_string());
+//! let calls = rust.clone();
+//!
+//! let mut registry: Registry<dyn Provider> = Registry::new();
+//! registry.register(Box::new(rust));
+//! registry.assert_registered("rust");
+//! registry.assert_find_resolves_to("main.rs", "rust");
+//!
+//! // Code under test reaches the mock through an SPI trait forwarding to `call`
+//! assert_eq!(calls.call("main.rs").unwrap(), "fn main() {}");
+//! assert_eq!(calls.calls(), vec!["main.rs"]);
+//! ```
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::error::{ProviderError, ProviderResult};
+use crate::provider::Provider;
+use crate::registry::Registry;
+
+mod contract;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+
+pub use contract::{
+    check_downcast, check_name, check_priority, check_provider_contract, check_supports,
+    ContractFixtures,
+};
+
+struct Script<T> {
+    queued: VecDeque<ProviderResult<T>>,
+    fallback: Option<ProviderResult<T>>,
+    calls: Vec<String>,
+}
+
+/// A provider for tests.
+///
+/// Name, extensions, and priority are set with builder methods. Implement
+/// your SPI trait for it by forwarding to [`call`](Self::call), which
+/// records the input and returns the next canned result: queued results
+/// first, in order, then the fallback.
+///
+/// Clones share the canned results and the call record, so keep a clone to
+/// inspect after boxing the provider into a registry.
+pub struct MockProvider<T = ()> {
+    name: String,
+    extensions: Vec<&'static str>,
+    mime_types: Vec<&'static str>,
+    schemes: Vec<&'static str>,
+    priority: i32,
+    script: Arc<Mutex<Script<T>>>,
+}
+
+impl<T: Clone> MockProvider<T> {
+    /// Create a mock named `name` with no extensions, priority 0, and no
+    /// canned results.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            extensions: Vec::new(),
+            mime_types: Vec::new(),
+            schemes: Vec::new(),
+            priority: 0,
+            script: Arc::new(Mutex::new(Script {
+                queued: VecDeque::new(),
+                fallback: None,
+                calls: Vec::new(),
+            })),
+        }
+    }
+
+    /// Set the extensions the mock supports.
+    pub fn with_extensions(mut self, extensions: &[&'static str]) -> Self {
+        self.extensions = extensions.to_vec();
+        self
+    }
+
+    /// Set the MIME types the mock handles.
+    pub fn with_mime_types(mut self, mime_types: &[&'static str]) -> Self {
+        self.mime_types = mime_types.to_vec();
+        self
+    }
+
+    /// Set the URI schemes the mock handles.
+    pub fn with_schemes(mut self, schemes: &[&'static str]) -> Self {
+        self.schemes = schemes.to_vec();
+        self
+    }
+
+    /// Set the priority.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Return `value` from every call without a queued result.
+    pub fn returning(self, value: T) -> Self {
+        self.script().fallback = Some(Ok(value));
+        self
+    }
+
+    /// Fail every call without a queued result with `error`.
+    pub fn failing(self, error: ProviderError) -> Self {
+        self.script().fallback = Some(Err(error));
+        self
+    }
+
+    /// Queue `result` for one call.
+    pub fn then(self, result: ProviderResult<T>) -> Self {
+        self.script().queued.push_back(result);
+        self
+    }
+
+    /// Record a call with `input` and return the next canned result.
+    ///
+    /// Calls with nothing canned fail with
+    /// [`ProviderError::ExecutionFailed`].
+    pub fn call(&self, input: impl Into<String>) -> ProviderResult<T> {
+        let mut script = self.script();
+        script.calls.push(input.into());
+        if let Some(result) = script.queued.pop_front() {
+            return result;
+        }
+        script.fallback.clone().unwrap_or_else(|| {
+            Err(ProviderError::ExecutionFailed(format!(
+                "mock provider `{}` has no canned result",
+                self.name
+            )))
+        })
+    }
+
+    /// The inputs of every call so far, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.script().calls.clone()
+    }
+
+    /// The number of calls so far.
+    pub fn call_count(&self) -> usize {
+        self.script().calls.len()
+    }
+
+    fn script(&self) -> MutexGuard<'_, Script<T>> {
+        self.script.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Clone for MockProvider<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            extensions: self.extensions.clone(),
+            mime_types: self.mime_types.clone(),
+            schemes: self.schemes.clone(),
+            priority: self.priority,
+            script: Arc::clone(&self.script),
+        }
+    }
+}
+
+impl<T> fmt::Debug for MockProvider<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockProvider")
+            .field("name", &self.name)
+            .field("extensions", &self.extensions)
+            .field("mime_types", &self.mime_types)
+            .field("schemes", &self.schemes)
+            .field("priority", &self.priority)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Send + 'static> Provider for MockProvider<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+
+    fn mime_types(&self) -> &[&str] {
+        &self.mime_types
+    }
+
+    fn schemes(&self) -> &[&str] {
+        &self.schemes
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Assertions on a [`Registry`], panicking with the registered names on
+/// failure.
+pub trait TestRegistry {
+    /// Assert that a provider named `name` is registered.
+    fn assert_registered(&self, name: &str);
+
+    /// Assert that no provider named `name` is registered.
+    fn assert_not_registered(&self, name: &str);
+
+    /// Assert that [`Registry::find`] resolves `key` to the provider named
+    /// `expected`.
+    fn assert_find_resolves_to(&self, key: &str, expected: &str);
+}
+
+impl<P: Provider + ?Sized, S: BuildHasher> TestRegistry for Registry<P, S> {
+    #[track_caller]
+    fn assert_registered(&self, name: &str) {
+        assert!(
+            self.contains(name),
+            "expected provider `{name}` to be registered; registered: {:?}",
+            self.names()
+        );
+    }
+
+    #[track_caller]
+    fn assert_not_registered(&self, name: &str) {
+        assert!(
+            !self.contains(name),
+            "expected provider `{name}` not to be registered"
+        );
+    }
+
+    #[track_caller]
+    fn assert_find_resolves_to(&self, key: &str, expected: &str) {
+        let found = self.find(key).map(|p| p.name().to_string());
+        assert_eq!(
+            found.as_deref(),
+            Some(expected),
+            "expected `{key}` to resolve to `{expected}`; registered: {:?}",
+            self.names()
+        );
+    }
+}
+
+/// Collect every event of `stream`, panicking if it has not ended within
+/// `timeout`.
+///
+/// Requires the `tokio` feature as well.
+#[cfg(feature = "tokio")]
+#[track_caller]
+pub fn collect_events_with_timeout<T: Send + 'static>(
+    stream: crate::stream::EventStream<T>,
+    timeout: std::time::Duration,
+) -> impl std::future::Future<Output = Vec<T>> {
+    // `#[track_caller]` does not apply to `async fn`, so capture it here
+    let caller = std::panic::Location::caller();
+    async move {
+        let mut stream = stream;
+        let mut events = Vec::new();
+        let drain = async {
+            while let Some(event) = crate::stream::NextItem(&mut stream).await {
+                events.push(event);
+            }
+        };
+        if tokio::time::timeout(timeout, drain).await.is_err() {
+            panic!(
+                "stream did not end within {timeout:?} after {} events (at {caller})",
+                events.len()
+            );
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_provider_scripts_results() {
+        let mock = MockProvider::<u32>::new("m")
+            .then(Ok(1))
+            .then(Err(ProviderError::NotSupported("x".into())))
+            .returning(7);
+        assert_eq!(mock.call("a").unwrap(), 1);
+        assert!(mock.call("b").is_err());
+        assert_eq!(mock.clone().call("c").unwrap(), 7);
+        assert_eq!(mock.calls(), vec!["a", "b", "c"]);
+        assert!(MockProvider::<()>::new("empty").call("x").is_err());
+    }
+
+    #[test]
+    fn test_registry_assertions() {
+        let mut registry: Registry<dyn Provider> = Registry::new();
+        registry.register(Box::new(
+            MockProvider::<()>::new("first").with_extensions(&[".rs"]),
+        ));
+        registry.register(Box::new(
+            MockProvider::<()>::new("second")
+                .with_extensions(&[".rs"])
+                .with_priority(10),
+        ));
+        registry.assert_registered("first");
+        registry.assert_not_registered("none");
+        // `find` takes the first match in registration order
+        registry.assert_find_resolves_to("lib.rs", "first");
+
+        let failed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            registry.assert_registered("none")
+        }));
+        assert!(failed.is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn test_collect_events_with_timeout() {
+        let (sender, stream) = crate::StreamBuilder::new().build();
+        sender.send(1).await.unwrap();
+        drop(sender);
+        let events = collect_events_with_timeout(stream, std::time::Duration::from_secs(1)).await;
+        assert_eq!(events, vec![1]);
+
+        let (_sender, stream) = crate::StreamBuilder::<u8>::new().build();
+        let stalled = tokio::spawn(collect_events_with_timeout(
+            stream,
+            std::time::Duration::from_secs(1),
+        ));
+        assert!(stalled.await.unwrap_err().is_panic());
+    }
+}
@@ -0,0 +1,318 @@
+//! Conformance checks for [`Provider`] implementations.
+
+use std::any::Any;
+use std::path::Path;
+
+use crate::provider::{Provider, ProviderExt};
+use crate::registry::Registry;
+
+/// Keys a provider under test must accept or reject.
+///
+/// Keys derived from [`Provider::extensions`] are always checked as well.
+#[derive(Debug, Clone, Default)]
+pub struct ContractFixtures {
+    supported: Vec<String>,
+    unsupported: Vec<String>,
+}
+
+impl ContractFixtures {
+    /// Create fixtures with no keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keys the provider must support.
+    pub fn supports(mut self, keys: &[&str]) -> Self {
+        self.supported.extend(keys.iter().map(|k| k.to_string()));
+        self
+    }
+
+    /// Keys the provider must not support.
+    pub fn rejects(mut self, keys: &[&str]) -> Self {
+        self.unsupported.extend(keys.iter().map(|k| k.to_string()));
+        self
+    }
+
+    fn supported_keys(&self, provider: &dyn Provider) -> Vec<String> {
+        let mut keys = self.supported.clone();
+        keys.extend(
+            provider
+                .extensions()
+                .iter()
+                .map(|ext| format!("fixture{ext}")),
+        );
+        keys
+    }
+}
+
+/// Check that the name is non-empty, trimmed, and the same on every call.
+#[track_caller]
+pub fn check_name<P: Provider>(provider: &P) {
+    let name = provider.name();
+    assert!(!name.is_empty(), "contract: provider name is empty");
+    assert_eq!(
+        name,
+        name.trim(),
+        "contract: provider name `{name}` has surrounding whitespace"
+    );
+    assert_eq!(
+        provider.name(),
+        name,
+        "contract: provider name changed between calls"
+    );
+}
+
+/// Check that every extension and supported fixture is accepted by
+/// [`supports`](Provider::supports) and
+/// [`supports_path`](Provider::supports_path), and every rejected fixture
+/// by neither.
+#[track_caller]
+pub fn check_supports<P: Provider>(provider: &P, fixtures: &ContractFixtures) {
+    let name = provider.name();
+    for ext in provider.extensions() {
+        assert!(
+            !ext.is_empty(),
+            "contract: `{name}` lists an empty extension"
+        );
+    }
+    for key in fixtures.supported_keys(provider) {
+        assert!(
+            provider.supports(&key),
+            "contract: `{name}` does not support `{key}`"
+        );
+        assert!(
+            provider.supports_path(Path::new(&key)),
+            "contract: `{name}` supports `{key}` but not the path `{key}`"
+        );
+    }
+    for key in &fixtures.unsupported {
+        assert!(
+            !provider.supports(key),
+            "contract: `{name}` supports rejected key `{key}`"
+        );
+        assert!(
+            !provider.supports_path(Path::new(key)),
+            "contract: `{name}` rejects `{key}` but supports the path `{key}`"
+        );
+    }
+}
+
+/// Check that the priority is stable and that
+/// [`Registry::find_best`] ranks the provider against competitors with
+/// priorities just above and below it.
+#[track_caller]
+pub fn check_priority<P: Provider + 'static>(provider: P, fixtures: &ContractFixtures) {
+    let name = provider.name().to_string();
+    let priority = provider.priority();
+    assert_eq!(
+        provider.priority(),
+        priority,
+        "contract: `{name}` priority changed between calls"
+    );
+    let keys = fixtures.supported_keys(&provider);
+
+    let mut registry: Registry<dyn Provider> = Registry::new();
+    if let Some(lower) = priority.checked_sub(1) {
+        registry.register(Box::new(Competitor::new("contract-lower", lower)));
+    }
+    registry.register(Box::new(provider));
+    for key in &keys {
+        let best = registry.find_best(key).map(|p| p.name());
+        assert_eq!(
+            best,
+            Some(name.as_str()),
+            "contract: `{name}` lost `{key}` to a lower-priority provider"
+        );
+    }
+    if let Some(higher) = priority.checked_add(1) {
+        registry.register(Box::new(Competitor::new("contract-higher", higher)));
+        for key in &keys {
+            let best = registry.find_best(key).map(|p| p.name());
+            assert_eq!(
+                best,
+                Some("contract-higher"),
+                "contract: `{name}` won `{key}` over a higher-priority provider"
+            );
+        }
+    }
+}
+
+/// Check that [`Provider::as_any`] returns the provider itself.
+#[track_caller]
+pub fn check_downcast<P: Provider + 'static>(provider: &P) {
+    let name = provider.name();
+    let any = provider.as_any();
+    assert!(
+        any.downcast_ref::<P>()
+            .is_some_and(|p| std::ptr::eq(p, provider)),
+        "contract: `{name}`.as_any() does not return the provider itself"
+    );
+    let object: &dyn Provider = provider;
+    assert!(
+        object.is::<P>(),
+        "contract: `{name}` cannot be downcast from `dyn Provider`"
+    );
+}
+
+/// Run every check against providers from `make`.
+#[track_caller]
+pub fn check_provider_contract<P, F>(make: F, fixtures: &ContractFixtures)
+where
+    P: Provider + 'static,
+    F: Fn() -> P,
+{
+    let provider = make();
+    check_name(&provider);
+    check_supports(&provider, fixtures);
+    check_downcast(&provider);
+    check_priority(make(), fixtures);
+}
+
+/// Generate `#[test]` functions checking a provider against the
+/// [`Provider`] contract.
+///
+/// Takes an expression building the provider, evaluated once per test, and
+/// a [`ContractFixtures`](crate::testing::ContractFixtures) expression.
+/// Invoke it once per module; the generated tests are named
+/// `spi_contract_*`.
+///
+/// Requires the `testing` feature.
+///
+/// ```rust
+/// use std::any::Any;
+///
+/// use rustratify::spi_contract_tests;
+/// use rustratify::testing::ContractFixtures;
+/// use rustratify::Provider;
+///
+/// #[derive(Debug)]
+/// struct RustProvider;
+///
+/// impl Provider for RustProvider {
+///     fn name(&self) -> &str {
+///         "rust"
+///     }
+///
+///     fn extensions(&self) -> &[&str] {
+///         &[".rs"]
+///     }
+///
+///     fn as_any(&self) -> &dyn Any {
+///         self
+///     }
+/// }
+///
+/// spi_contract_tests!(RustProvider, ContractFixtures::new().rejects(&["main.py"]));
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! spi_contract_tests {
+    ($provider:expr, $fixtures:expr $(,)?) => {
+        #[test]
+        fn spi_contract_name() {
+            $crate::testing::check_name(&$provider);
+        }
+
+        #[test]
+        fn spi_contract_supports() {
+            $crate::testing::check_supports(&$provider, &$fixtures);
+        }
+
+        #[test]
+        fn spi_contract_priority() {
+            $crate::testing::check_priority($provider, &$fixtures);
+        }
+
+        #[test]
+        fn spi_contract_downcast() {
+            $crate::testing::check_downcast(&$provider);
+        }
+    };
+}
+
+/// A provider supporting every key, for ranking against.
+#[derive(Debug)]
+struct Competitor {
+    name: &'static str,
+    priority: i32,
+}
+
+impl Competitor {
+    fn new(name: &'static str, priority: i32) -> Self {
+        Self { name, priority }
+    }
+}
+
+impl Provider for Competitor {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn supports(&self, _key: &str) -> bool {
+        true
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+
+    fn fixtures() -> ContractFixtures {
+        ContractFixtures::new()
+            .supports(&["src/main.rs"])
+            .rejects(&["main.py", "rs"])
+    }
+
+    mod mock {
+        use super::*;
+
+        crate::spi_contract_tests!(
+            MockProvider::<()>::new("mock")
+                .with_extensions(&[".rs"])
+                .with_priority(i32::MAX),
+            fixtures()
+        );
+    }
+
+    #[derive(Debug)]
+    struct Greedy;
+
+    impl Provider for Greedy {
+        fn name(&self) -> &str {
+            "greedy"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &[".rs"]
+        }
+
+        fn supports(&self, _key: &str) -> bool {
+            true
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            &()
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "supports rejected key `main.py`")]
+    fn test_rejected_key_supported() {
+        check_supports(&Greedy, &fixtures());
+    }
+
+    #[test]
+    #[should_panic(expected = "as_any() does not return the provider itself")]
+    fn test_wrong_as_any() {
+        check_downcast(&Greedy);
+    }
+}
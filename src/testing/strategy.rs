@@ -0,0 +1,255 @@
+//! [`proptest`](mod@proptest) strategies for core types.
+//!
+//! - [`ProviderInfo`] implements [`Arbitrary`]; [`mock_provider`] builds
+//!   registrable providers
+//! - [`registry_ops`] generates sequences of [`RegistryOp`]s drawn from a
+//!   small pool of names and extensions, so operations collide
+//! - [`config_map`] and [`env_config`] generate configuration inputs
+//! - [`event_sequence`] and, with the `tokio` feature, [`recording`]
+//!   generate event sequences for stream pipelines
+//!
+//! Requires the `proptest` feature.
+//!
+//! # Example
+//!
+//! ```rust
+//! use proptest::prelude::*;
+//! use proptest::test_runner::TestRunner;
+//! use rustratify::testing::strategy::registry_ops;
+//! use rustratify::{Provider, Registry};
+//!
+//! // Usually written with `proptest!` in a test module
+//! TestRunner::default()
+//!     .run(&registry_ops(32), |ops| {
+//!         let mut registry: Registry<dyn Provider> = Registry::new();
+//!         for op in &ops {
+//!             op.apply(&mut registry);
+//!         }
+//!         let mut names = registry.names();
+//!         names.sort_unstable();
+//!         names.dedup();
+//!         prop_assert_eq!(names.len(), registry.len());
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+use proptest::sample::select;
+use proptest::strategy::BoxedStrategy;
+
+use super::MockProvider;
+use crate::config::EnvConfig;
+use crate::provider::Provider;
+use crate::registry::{ProviderInfo, Registry};
+
+/// Provider names used by [`mock_provider`] and [`registry_ops`].
+pub const NAMES: &[&str] = &["alpha", "beta", "gamma", "delta", "epsilon"];
+
+/// Extensions used by [`mock_provider`] and [`registry_ops`].
+pub const EXTENSIONS: &[&str] = &[".rs", ".toml", ".json", ".py", ".md"];
+
+/// A provider name: lowercase ASCII, digits, and `-`, starting with a
+/// letter.
+pub fn provider_name() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9-]{0,15}"
+}
+
+/// A lookup key ending in one of [`EXTENSIONS`], or in none of them.
+pub fn key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => ("[a-z]{1,8}", select(EXTENSIONS)).prop_map(|(stem, ext)| format!("{stem}{ext}")),
+        1 => "[a-z]{1,8}(\\.txt)?",
+    ]
+}
+
+impl Arbitrary for ProviderInfo {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            provider_name(),
+            vec("\\.[a-z0-9]{1,5}", 0..4),
+            -100..=100i32,
+        )
+            .prop_map(|(name, extensions, priority)| ProviderInfo {
+                name,
+                extensions,
+                priority,
+            })
+            .boxed()
+    }
+}
+
+/// A [`MockProvider`] named from [`NAMES`], supporting up to three of
+/// [`EXTENSIONS`], with a priority in `-2..=2` so ties occur.
+pub fn mock_provider() -> impl Strategy<Value = MockProvider> {
+    (
+        select(NAMES),
+        proptest::sample::subsequence(EXTENSIONS, 0..=3),
+        -2..=2i32,
+    )
+        .prop_map(|(name, extensions, priority)| {
+            MockProvider::new(name)
+                .with_extensions(&extensions)
+                .with_priority(priority)
+        })
+}
+
+/// One operation on a `Registry<dyn Provider>`.
+#[derive(Debug, Clone)]
+pub enum RegistryOp {
+    /// [`Registry::register`]
+    Register(MockProvider),
+    /// [`Registry::remove`]
+    Remove(String),
+    /// [`Registry::find`]
+    Find(String),
+    /// [`Registry::find_best`]
+    FindBest(String),
+}
+
+impl RegistryOp {
+    /// Apply the operation, returning the name of the provider it
+    /// registered, removed, or found.
+    pub fn apply(&self, registry: &mut Registry<dyn Provider>) -> Option<String> {
+        match self {
+            Self::Register(provider) => {
+                let name = provider.name().to_string();
+                registry.register(Box::new(provider.clone()));
+                Some(name)
+            }
+            Self::Remove(name) => registry.remove(name).map(|p| p.name().to_string()),
+            Self::Find(key) => registry.find(key).map(|p| p.name().to_string()),
+            Self::FindBest(key) => registry.find_best(key).map(|p| p.name().to_string()),
+        }
+    }
+}
+
+/// A single [`RegistryOp`], weighted towards registration.
+pub fn registry_op() -> impl Strategy<Value = RegistryOp> {
+    prop_oneof![
+        3 => mock_provider().prop_map(RegistryOp::Register),
+        1 => select(NAMES).prop_map(|name| RegistryOp::Remove(name.to_string())),
+        2 => key().prop_map(RegistryOp::Find),
+        2 => key().prop_map(RegistryOp::FindBest),
+    ]
+}
+
+/// Up to `max_len` [`RegistryOp`]s.
+pub fn registry_ops(max_len: usize) -> impl Strategy<Value = Vec<RegistryOp>> {
+    vec(registry_op(), 0..=max_len)
+}
+
+/// A config field name: lowercase `snake_case`.
+pub fn config_key() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9]{0,7}(_[a-z0-9]{1,7}){0,2}"
+}
+
+/// Up to `max_len` config fields with printable ASCII values.
+pub fn config_map(max_len: usize) -> impl Strategy<Value = BTreeMap<String, String>> {
+    btree_map(config_key(), "[ -~]{0,16}", 0..=max_len)
+}
+
+/// An [`EnvConfig`] for `prefix` built from a [`config_map`] of up to
+/// `max_len` fields.
+pub fn env_config(prefix: impl Into<String>, max_len: usize) -> impl Strategy<Value = EnvConfig> {
+    let prefix = prefix.into().trim_end_matches('_').to_string();
+    config_map(max_len).prop_map(move |fields| {
+        let vars = fields
+            .into_iter()
+            .map(|(field, value)| (format!("{prefix}_{}", field.to_uppercase()), value));
+        EnvConfig::from_vars(prefix.clone(), vars)
+    })
+}
+
+/// Up to `max_len` events from `event` followed by one from `terminal`,
+/// for pipelines that stop at a terminal event.
+pub fn event_sequence<S, F>(
+    event: S,
+    terminal: F,
+    max_len: usize,
+) -> impl Strategy<Value = Vec<S::Value>>
+where
+    S: Strategy,
+    F: Strategy<Value = S::Value>,
+{
+    (vec(event, 0..=max_len), terminal).prop_map(|(mut events, last)| {
+        events.push(last);
+        events
+    })
+}
+
+/// A [`Recording`](crate::stream::testing::Recording) of up to `max_len`
+/// events from `event`, up to a second apart, for
+/// [`playback`](crate::stream::testing::playback).
+///
+/// Requires the `tokio` feature as well.
+#[cfg(feature = "tokio")]
+pub fn recording<S>(
+    event: S,
+    max_len: usize,
+) -> impl Strategy<Value = crate::stream::testing::Recording<S::Value>>
+where
+    S: Strategy,
+{
+    vec((0..1_000u64, event), 0..=max_len).prop_map(|events| {
+        let mut recording = crate::stream::testing::Recording::new();
+        let mut offset = std::time::Duration::ZERO;
+        for (gap_ms, event) in events {
+            offset += std::time::Duration::from_millis(gap_ms);
+            recording.push(offset, event);
+        }
+        recording
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_find_best_has_max_priority(ops in registry_ops(24), key in key()) {
+            let mut registry: Registry<dyn Provider> = Registry::new();
+            for op in &ops {
+                op.apply(&mut registry);
+            }
+            let max = registry
+                .iter()
+                .filter(|p| p.supports(&key))
+                .map(|p| p.priority())
+                .max();
+            prop_assert_eq!(registry.find_best(&key).map(|p| p.priority()), max);
+            prop_assert_eq!(registry.find(&key).is_some(), max.is_some());
+        }
+
+        #[test]
+        fn test_env_config_captures_every_field(config in env_config("APP_", 8)) {
+            prop_assert_eq!(config.prefix(), "APP");
+            prop_assert!(config.len() <= 8);
+        }
+
+        #[test]
+        fn test_event_sequence_ends_with_terminal(
+            events in event_sequence(0..10u8, Just(u8::MAX), 16),
+        ) {
+            prop_assert_eq!(events.last(), Some(&u8::MAX));
+            prop_assert!(events[..events.len() - 1].iter().all(|e| *e < 10));
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    proptest! {
+        #[test]
+        fn test_recording_offsets_are_ordered(recording in recording(any::<u8>(), 16)) {
+            let offsets: Vec<_> = recording.events.iter().map(|e| e.offset).collect();
+            prop_assert!(offsets.windows(2).all(|w| w[0] <= w[1]));
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! Event transports: publishing stream items to message brokers and
+//! consuming them on other nodes.
+//!
+//! An [`EventTransport`] moves opaque payloads between processes by topic.
+//! [`publish_stream`] and [`subscribe_stream`] (with the `serde` feature)
+//! encode and decode [`EventStream`] items as JSON on top of it, so a run on
+//! one node can be observed or continued by another.
+//!
+//! | Transport | Feature |
+//! |-----------|---------|
+//! | [`MemoryTransport`] | always available; in-process, for tests and single-node setups |
+//! | [`NatsTransport`] | `nats` |
+//! | [`KafkaTransport`] | `kafka` |
+//!
+//! Wrap any transport in a [`CompressedTransport`] (with the `gzip` or
+//! `zstd` feature) to compress payloads on the wire.
+//!
+//! # Example
+//!
+//! ```rust
+//! use rustratify::transport::{EventTransport, MemoryTransport};
+//! use futures::StreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let transport = MemoryTransport::new();
+//! let mut events = transport.subscribe("runs.42").await.unwrap();
+//!
+//! transport.publish("runs.42", b"started".to_vec()).await.unwrap();
+//! assert_eq!(events.next().await, Some(b"started".to_vec()));
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::error::ProviderResult;
+use crate::stream::{EventSender, EventStream, StreamBuilder};
+
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaTransport;
+#[cfg(feature = "nats")]
+pub use nats::NatsTransport;
+
+/// A message broker that carries event payloads by topic.
+///
+/// Delivery guarantees are those of the broker: [`MemoryTransport`] and
+/// NATS core deliver at most once to subscribers present at publish time,
+/// while Kafka retains messages for consumers that subscribe later.
+#[async_trait]
+pub trait EventTransport: Send + Sync {
+    /// Transport name, e.g. `nats`.
+    fn name(&self) -> &str;
+
+    /// Publish one payload to `topic`.
+    async fn publish(&self, topic: &str, payload: Vec<u8>) -> ProviderResult<()>;
+
+    /// Receive the payloads published to `topic` from now on.
+    ///
+    /// The stream ends when the transport disconnects.
+    async fn subscribe(&self, topic: &str) -> ProviderResult<EventStream<Vec<u8>>>;
+
+    /// Wait until published payloads have been handed to the broker.
+    async fn flush(&self) -> ProviderResult<()> {
+        Ok(())
+    }
+}
+
+/// Publish every item of `stream` to `topic` as JSON, then flush.
+///
+/// Items that fail to serialize are skipped and logged. Returns the number
+/// of items published; stops at the first publish error.
+///
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+pub async fn publish_stream<T>(
+    transport: &dyn EventTransport,
+    topic: &str,
+    mut stream: EventStream<T>,
+) -> ProviderResult<u64>
+where
+    T: serde::Serialize + Send + 'static,
+{
+    let mut published = 0;
+    while let Some(event) = crate::stream::NextItem(&mut stream).await {
+        match serde_json::to_vec(&event) {
+            Ok(payload) => {
+                transport.publish(topic, payload).await?;
+                published += 1;
+            }
+            Err(e) => {
+                tracing::warn!(topic, error = %e, "skipping event that failed to serialize");
+            }
+        }
+    }
+    transport.flush().await?;
+    Ok(published)
+}
+
+/// Subscribe to `topic`, decoding each payload as JSON.
+///
+/// Payloads that fail to decode are yielded as errors.
+///
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+pub async fn subscribe_stream<T>(
+    transport: &dyn EventTransport,
+    topic: &str,
+) -> ProviderResult<EventStream<Result<T, crate::error::RustratifyError>>>
+where
+    T: serde::de::DeserializeOwned + Send + 'static,
+{
+    let payloads = transport.subscribe(topic).await?;
+    Ok(Box::pin(decode::Decode::<T>::new(payloads)))
+}
+
+#[cfg(feature = "serde")]
+mod decode {
+    use std::marker::PhantomData;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures_core::Stream;
+
+    use crate::error::RustratifyError;
+    use crate::stream::EventStream;
+
+    pub(super) struct Decode<T> {
+        payloads: EventStream<Vec<u8>>,
+        _marker: PhantomData<fn() -> T>,
+    }
+
+    impl<T> Decode<T> {
+        pub(super) fn new(payloads: EventStream<Vec<u8>>) -> Self {
+            Self {
+                payloads,
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    impl<T: serde::de::DeserializeOwned> Stream for Decode<T> {
+        type Item = Result<T, RustratifyError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.payloads.as_mut().poll_next(cx).map(|payload| {
+                payload.map(|payload| {
+                    serde_json::from_slice(&payload)
+                        .map_err(|e| RustratifyError::Stream(e.to_string()))
+                })
+            })
+        }
+    }
+}
+
+/// Transport wrapper that compresses every payload.
+///
+/// Payloads are published as frames from
+/// [`stream::compress`](crate::stream::compress); received payloads that
+/// fail to decompress are skipped and logged. Every publisher and
+/// subscriber of a topic must use the wrapper. Requires the `gzip` or
+/// `zstd` feature.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[derive(Debug, Clone)]
+pub struct CompressedTransport<T> {
+    inner: T,
+    compression: crate::stream::compress::Compression,
+    limits: crate::stream::compress::FrameLimits,
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+impl<T: EventTransport> CompressedTransport<T> {
+    /// Compress the payloads of `inner` with `compression`.
+    pub fn new(inner: T, compression: crate::stream::compress::Compression) -> Self {
+        Self {
+            inner,
+            compression,
+            limits: Default::default(),
+        }
+    }
+
+    /// Set the size limits for received frames.
+    ///
+    /// Default is [`FrameLimits::default`](crate::stream::compress::FrameLimits).
+    pub fn with_limits(mut self, limits: crate::stream::compress::FrameLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Get the wrapped transport.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+#[async_trait]
+impl<T: EventTransport> EventTransport for CompressedTransport<T> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn publish(&self, topic: &str, payload: Vec<u8>) -> ProviderResult<()> {
+        let frame = crate::stream::compress::compress(&payload, self.compression).map_err(|e| {
+            crate::error::ProviderError::ExecutionFailed(format!("compress for {topic}: {e}"))
+        })?;
+        self.inner.publish(topic, frame).await
+    }
+
+    async fn subscribe(&self, topic: &str) -> ProviderResult<EventStream<Vec<u8>>> {
+        let frames = self.inner.subscribe(topic).await?;
+        Ok(Box::pin(decompress::Decompress {
+            frames,
+            limits: self.limits,
+        }))
+    }
+
+    async fn flush(&self) -> ProviderResult<()> {
+        self.inner.flush().await
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod decompress {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use futures_core::Stream;
+
+    use crate::stream::compress::{decompress_with_limits, FrameLimits};
+    use crate::stream::EventStream;
+
+    pub(super) struct Decompress {
+        pub(super) frames: EventStream<Vec<u8>>,
+        pub(super) limits: FrameLimits,
+    }
+
+    impl Stream for Decompress {
+        type Item = Vec<u8>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+            loop {
+                match self.frames.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(frame)) => match decompress_with_limits(&frame, self.limits) {
+                        Ok(payload) => return Poll::Ready(Some(payload)),
+                        Err(e) => {
+                            tracing::warn!(error = %e, "skipping payload that failed to decompress");
+                        }
+                    },
+                    other => return other,
+                }
+            }
+        }
+    }
+}
+
+type Subscribers = HashMap<String, Vec<EventSender<Vec<u8>>>>;
+
+/// In-process transport.
+///
+/// Each subscriber gets its own buffered stream; publishing waits for room
+/// in every live subscriber's buffer. Cloning is cheap; clones share
+/// topics.
+#[derive(Clone)]
+pub struct MemoryTransport {
+    buffer_size: usize,
+    topics: Arc<Mutex<Subscribers>>,
+}
+
+impl MemoryTransport {
+    /// Create a transport with the default subscriber buffer.
+    pub fn new() -> Self {
+        Self::with_buffer_size(64)
+    }
+
+    /// Create a transport whose subscribers buffer up to `buffer_size`
+    /// payloads.
+    pub fn with_buffer_size(buffer_size: usize) -> Self {
+        Self {
+            buffer_size,
+            topics: Arc::default(),
+        }
+    }
+
+    /// Number of live subscribers to `topic`.
+    pub fn subscribers(&self, topic: &str) -> usize {
+        let mut topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+        topics.get_mut(topic).map_or(0, |senders| {
+            senders.retain(|s| !s.is_closed());
+            senders.len()
+        })
+    }
+}
+
+impl Default for MemoryTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MemoryTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("MemoryTransport")
+            .field("buffer_size", &self.buffer_size)
+            .field("topics", &topics.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl EventTransport for MemoryTransport {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    async fn publish(&self, topic: &str, payload: Vec<u8>) -> ProviderResult<()> {
+        let senders = {
+            let mut topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+            match topics.get_mut(topic) {
+                Some(senders) => {
+                    senders.retain(|s| !s.is_closed());
+                    senders.clone()
+                }
+                None => return Ok(()),
+            }
+        };
+        for sender in senders {
+            // A subscriber dropped since the snapshot; nothing to deliver
+            let _ = sender.send(payload.clone()).await;
+        }
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> ProviderResult<EventStream<Vec<u8>>> {
+        let (sender, stream) = StreamBuilder::new().buffer_size(self.buffer_size).build();
+        let mut topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+        topics.entry(topic.to_string()).or_default().push(sender);
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_memory_transport_fans_out_by_topic() {
+        let transport = MemoryTransport::new();
+        let mut a = transport.subscribe("runs").await.unwrap();
+        let b = transport.subscribe("runs").await.unwrap();
+        let mut other = transport.subscribe("other").await.unwrap();
+
+        transport.publish("nobody", vec![0]).await.unwrap();
+        transport.publish("runs", vec![1]).await.unwrap();
+        assert_eq!(a.next().await, Some(vec![1]));
+        drop(b);
+        assert_eq!(transport.subscribers("runs"), 1);
+
+        transport.publish("other", vec![2]).await.unwrap();
+        assert_eq!(other.next().await, Some(vec![2]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn test_publish_and_subscribe_stream() {
+        let transport = MemoryTransport::new();
+        let mut received = subscribe_stream::<u32>(&transport, "runs").await.unwrap();
+        transport.publish("runs", b"oops".to_vec()).await.unwrap();
+
+        let (sender, stream) = crate::stream::create_stream();
+        tokio::spawn(async move {
+            for i in 0..3u32 {
+                sender.send(i).await.unwrap();
+            }
+        });
+        let published = publish_stream(&transport, "runs", stream).await.unwrap();
+        assert_eq!(published, 3);
+
+        assert!(received.next().await.unwrap().is_err());
+        let decoded: Vec<u32> = received.take(3).map(Result::unwrap).collect().await;
+        assert_eq!(decoded, vec![0, 1, 2]);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_compressed_transport() {
+        use crate::stream::compress::Compression;
+
+        let memory = MemoryTransport::new();
+        let transport = CompressedTransport::new(memory.clone(), Compression::Gzip);
+        let mut raw = memory.subscribe("runs").await.unwrap();
+        let mut received = transport.subscribe("runs").await.unwrap();
+
+        let payload = b"progress ".repeat(100);
+        transport.publish("runs", payload.clone()).await.unwrap();
+        assert!(raw.next().await.unwrap().len() < payload.len());
+        assert_eq!(received.next().await, Some(payload));
+
+        memory
+            .publish("runs", b"not a frame".to_vec())
+            .await
+            .unwrap();
+        transport.publish("runs", vec![1]).await.unwrap();
+        assert_eq!(received.next().await, Some(vec![1]));
+    }
+}
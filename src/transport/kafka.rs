@@ -0,0 +1,128 @@
+//! Kafka transport.
+
+use std::fmt;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::message::Message;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::util::Timeout;
+
+use super::EventTransport;
+use crate::error::{ProviderError, ProviderResult};
+use crate::stream::{EventStream, StreamBuilder};
+use crate::task::spawn_named;
+
+/// Transport over Kafka topics.
+///
+/// Each [`subscribe`](EventTransport::subscribe) creates a consumer in the
+/// configured group, so subscribers in one group share a topic's partitions
+/// and a subscriber that joins later resumes from the group's committed
+/// offset.
+///
+/// Requires the `kafka` feature.
+#[derive(Clone)]
+pub struct KafkaTransport {
+    config: ClientConfig,
+    producer: FutureProducer,
+    timeout: Duration,
+}
+
+impl KafkaTransport {
+    /// Connect to `brokers` (comma-separated `host:port`), consuming as
+    /// `group_id`.
+    pub fn new(brokers: &str, group_id: &str) -> ProviderResult<Self> {
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", brokers)
+            .set("group.id", group_id);
+        Self::from_config(config)
+    }
+
+    /// Use a full client configuration, shared by the producer and every
+    /// consumer. Consumers need `group.id`.
+    pub fn from_config(config: ClientConfig) -> ProviderResult<Self> {
+        let producer = config
+            .create()
+            .map_err(|e| ProviderError::InitializationFailed(format!("kafka: {e}")))?;
+        Ok(Self {
+            config,
+            producer,
+            timeout: Duration::from_secs(30),
+        })
+    }
+
+    /// How long publish and flush wait for the broker; defaults to 30
+    /// seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl fmt::Debug for KafkaTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KafkaTransport")
+            .field("brokers", &self.config.get("bootstrap.servers"))
+            .field("group_id", &self.config.get("group.id"))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl EventTransport for KafkaTransport {
+    fn name(&self) -> &str {
+        "kafka"
+    }
+
+    async fn publish(&self, topic: &str, payload: Vec<u8>) -> ProviderResult<()> {
+        let record = FutureRecord::<(), [u8]>::to(topic).payload(&payload);
+        self.producer
+            .send(record, Timeout::After(self.timeout))
+            .await
+            .map(|_| ())
+            .map_err(|(e, _)| {
+                ProviderError::ExecutionFailed(format!("kafka publish to {topic}: {e}"))
+            })
+    }
+
+    async fn subscribe(&self, topic: &str) -> ProviderResult<EventStream<Vec<u8>>> {
+        let consumer: StreamConsumer = self
+            .config
+            .create()
+            .map_err(|e| ProviderError::InitializationFailed(format!("kafka: {e}")))?;
+        consumer.subscribe(&[topic]).map_err(|e| {
+            ProviderError::ExecutionFailed(format!("kafka subscribe to {topic}: {e}"))
+        })?;
+
+        let (sender, stream) = StreamBuilder::new().build();
+        let topic = topic.to_string();
+        spawn_named(&format!("rustratify kafka {topic}"), async move {
+            loop {
+                let payload = match consumer.recv().await {
+                    Ok(message) => message.payload().map(<[u8]>::to_vec).unwrap_or_default(),
+                    Err(e) => {
+                        tracing::warn!(%topic, error = %e, "kafka consumer stopped");
+                        break;
+                    }
+                };
+                if sender.send(payload).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(stream)
+    }
+
+    async fn flush(&self) -> ProviderResult<()> {
+        let producer = self.producer.clone();
+        let timeout = self.timeout;
+        tokio::task::spawn_blocking(move || producer.flush(Timeout::After(timeout)))
+            .await
+            .map_err(|e| ProviderError::ExecutionFailed(format!("kafka flush: {e}")))?
+            .map_err(|e| ProviderError::ExecutionFailed(format!("kafka flush: {e}")))
+    }
+}
@@ -0,0 +1,72 @@
+//! NATS transport.
+
+use async_trait::async_trait;
+use tokio_stream::StreamExt;
+
+use super::EventTransport;
+use crate::error::{ProviderError, ProviderResult};
+use crate::stream::EventStream;
+
+/// Transport over NATS core subjects.
+///
+/// Topics are used as subjects, so `runs.42` and wildcards such as
+/// `runs.*` work as NATS defines them. Delivery is at most once, to
+/// subscribers connected at publish time.
+///
+/// Requires the `nats` feature.
+#[derive(Debug, Clone)]
+pub struct NatsTransport {
+    client: async_nats::Client,
+}
+
+impl NatsTransport {
+    /// Connect to the server at `url`, e.g. `nats://localhost:4222`.
+    pub async fn connect(url: &str) -> ProviderResult<Self> {
+        let client = async_nats::connect(url)
+            .await
+            .map_err(|e| ProviderError::InitializationFailed(format!("nats: {e}")))?;
+        Ok(Self::new(client))
+    }
+
+    /// Use an existing client, e.g. one built with custom options.
+    pub fn new(client: async_nats::Client) -> Self {
+        Self { client }
+    }
+
+    /// The underlying client.
+    pub fn client(&self) -> &async_nats::Client {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl EventTransport for NatsTransport {
+    fn name(&self) -> &str {
+        "nats"
+    }
+
+    async fn publish(&self, topic: &str, payload: Vec<u8>) -> ProviderResult<()> {
+        self.client
+            .publish(topic.to_string(), payload.into())
+            .await
+            .map_err(|e| ProviderError::ExecutionFailed(format!("nats publish to {topic}: {e}")))
+    }
+
+    async fn subscribe(&self, topic: &str) -> ProviderResult<EventStream<Vec<u8>>> {
+        let subscriber = self
+            .client
+            .subscribe(topic.to_string())
+            .await
+            .map_err(|e| {
+                ProviderError::ExecutionFailed(format!("nats subscribe to {topic}: {e}"))
+            })?;
+        Ok(Box::pin(subscriber.map(|message| message.payload.to_vec())))
+    }
+
+    async fn flush(&self) -> ProviderResult<()> {
+        self.client
+            .flush()
+            .await
+            .map_err(|e| ProviderError::ExecutionFailed(format!("nats flush: {e}")))
+    }
+}
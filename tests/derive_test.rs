@@ -0,0 +1,200 @@
+//! Tests for `#[derive(Config)]`.
+
+#![cfg(feature = "derive")]
+
+use rustratify::{
+    ByteSize, Config, ConfigBuilder, ConfigDiff, EnvConfig, HumanDuration, MergeableConfig,
+    SchemaType,
+};
+use std::time::Duration;
+
+/// Background worker settings.
+#[derive(Debug, Clone, PartialEq, Config)]
+#[config(name = "worker")]
+struct WorkerConfig {
+    #[config(default = 5000, env = "TIMEOUT_MS", validate(range(min = 1)))]
+    timeout_ms: u64,
+    /// Queue to consume from.
+    #[config(default = "jobs", env = "QUEUE", validate(non_empty))]
+    queue: String,
+    #[config(env = "WORKERS", validate(range(min = 1, max = 64)))]
+    workers: Option<u32>,
+    verbose: bool,
+}
+
+#[test]
+fn test_defaults() {
+    let config = WorkerConfig::default();
+    assert_eq!(config.timeout_ms, 5000);
+    assert_eq!(config.queue, "jobs");
+    assert_eq!(config.workers, None);
+    assert!(!config.verbose);
+    assert_eq!(config.name(), "worker");
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_builder_validates() {
+    let config = WorkerConfig::builder()
+        .queue("emails")
+        .workers(8)
+        .build()
+        .unwrap();
+    assert_eq!(config.queue, "emails");
+    assert_eq!(config.workers, Some(8));
+
+    let err = WorkerConfig::builder()
+        .timeout_ms(0)
+        .queue("")
+        .workers(100)
+        .build()
+        .unwrap_err();
+    assert!(err.contains("timeout_ms: must be at least 1"));
+    assert!(err.contains("queue: must not be empty"));
+    assert!(err.contains("workers: must be at most 64"));
+}
+
+#[test]
+fn test_merge_overrides_non_default_fields() {
+    let base = WorkerConfig::builder()
+        .queue("emails")
+        .workers(4)
+        .build()
+        .unwrap();
+    let overlay = WorkerConfig {
+        timeout_ms: 100,
+        verbose: true,
+        ..WorkerConfig::default()
+    };
+
+    let merged = WorkerConfig::merged(&base, &overlay);
+    assert_eq!(merged.timeout_ms, 100);
+    assert_eq!(merged.queue, "emails");
+    assert_eq!(merged.workers, Some(4));
+    assert!(merged.verbose);
+}
+
+#[test]
+fn test_diff_lists_changed_fields() {
+    let old = WorkerConfig::default();
+    let new = WorkerConfig::builder()
+        .queue("emails")
+        .workers(4)
+        .build()
+        .unwrap();
+
+    let diff = ConfigDiff::between(&old, &new);
+    assert_eq!(
+        diff.to_string(),
+        "queue: \"jobs\" -> \"emails\"\nworkers: None -> Some(4)"
+    );
+}
+
+#[test]
+fn test_env_overlay() {
+    let env = EnvConfig::from_vars(
+        "WORKER",
+        [
+            ("WORKER_TIMEOUT_MS", "250"),
+            ("WORKER_WORKERS", "2"),
+            ("WORKER_VERBOSE", "true"),
+        ],
+    );
+
+    let (config, report) = env.load::<WorkerConfig>();
+    assert_eq!(config.timeout_ms, 250);
+    assert_eq!(config.workers, Some(2));
+    assert!(!config.verbose);
+    assert_eq!(report.unknown, vec!["WORKER_VERBOSE".to_string()]);
+}
+
+#[derive(Debug, Clone, PartialEq, Config)]
+struct CacheConfig {
+    #[config(default = "5m", env = "TTL")]
+    ttl: HumanDuration,
+    #[config(default = "64MB", env = "MAX_SIZE")]
+    max_size: ByteSize,
+}
+
+#[test]
+fn test_human_units() {
+    let config = CacheConfig::default();
+    assert_eq!(config.ttl.get(), Duration::from_secs(300));
+    assert_eq!(config.max_size.bytes(), 64 << 20);
+
+    let env = EnvConfig::from_vars("CACHE", [("CACHE_TTL", "1h30m"), ("CACHE_MAX_SIZE", "1GB")]);
+    let (config, report) = env.load::<CacheConfig>();
+    assert!(report.is_clean());
+    assert_eq!(config.ttl.get(), Duration::from_secs(5400));
+    assert_eq!(config.max_size.to_string(), "1GB");
+}
+
+#[derive(Debug, Clone, PartialEq, Config)]
+struct ServiceConfig {
+    worker: WorkerConfig,
+    cache: CacheConfig,
+}
+
+#[test]
+fn test_schema() {
+    let schema = WorkerConfig::schema();
+    assert_eq!(schema.title.as_deref(), Some("worker"));
+    assert_eq!(
+        schema.description.as_deref(),
+        Some("Background worker settings.")
+    );
+
+    let queue = schema.get("queue").unwrap();
+    assert_eq!(queue.kind, SchemaType::String);
+    assert_eq!(queue.description.as_deref(), Some("Queue to consume from."));
+    assert_eq!(queue.default.as_deref(), Some("\"jobs\""));
+    assert!(queue.non_empty);
+
+    let workers = schema.get("workers").unwrap();
+    assert_eq!(workers.kind, SchemaType::Integer);
+    assert_eq!((workers.minimum, workers.maximum), (Some(1.0), Some(64.0)));
+
+    let service = ServiceConfig::schema();
+    assert_eq!(service.title.as_deref(), Some("ServiceConfig"));
+    assert_eq!(
+        service.get("worker").unwrap().kind,
+        SchemaType::Object(Box::new(schema))
+    );
+    let json = service.to_json();
+    assert!(json.contains("\"ttl\": {\n          \"oneOf\""), "{json}");
+}
+
+#[derive(Debug, Clone, PartialEq, Config)]
+struct ClientConfig {
+    #[config(required, validate(non_empty))]
+    endpoint: String,
+    #[config(required)]
+    api_key: Option<String>,
+    #[config(default = 3)]
+    retries: u32,
+}
+
+#[test]
+fn test_typestate_builder() {
+    let config = ClientConfig::builder()
+        .retries(5)
+        .api_key("k")
+        .endpoint("https://example.com")
+        .build()
+        .unwrap();
+    assert_eq!(config.endpoint, "https://example.com");
+    assert_eq!(config.api_key.as_deref(), Some("k"));
+    assert_eq!(config.retries, 5);
+
+    // Runtime checks still run once every required field is set.
+    let err = ClientConfig::builder()
+        .endpoint("")
+        .api_key("k")
+        .build()
+        .unwrap_err();
+    assert_eq!(err, "endpoint: must not be empty");
+
+    let schema = ClientConfig::schema();
+    assert!(schema.get("endpoint").unwrap().required);
+    assert!(!schema.get("retries").unwrap().required);
+}
@@ -0,0 +1,112 @@
+//! Tests for `#[derive(EventKind)]` and `#[derive(Event)]`.
+
+#![cfg(all(feature = "derive", feature = "tokio"))]
+
+use std::time::{Duration, SystemTime};
+
+use futures::StreamExt;
+use rustratify::stream::{create_stream, EventKind as _, EventStreamExt, Level, StreamBuilder};
+use rustratify::{Event, EventKind};
+
+#[derive(Debug, Clone, PartialEq, EventKind)]
+enum JobEvent {
+    #[event(level = "info")]
+    Started { path: String },
+    #[event(level = "trace", kind = "tick")]
+    Progress(u8),
+    #[event(terminal)]
+    Completed {
+        #[event(timestamp)]
+        at: SystemTime,
+    },
+    #[event(terminal, error, level = "error")]
+    Failed(String),
+}
+
+#[test]
+fn test_derived_classification() {
+    let at = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+    let started = JobEvent::Started {
+        path: "a.rs".into(),
+    };
+    let completed = JobEvent::Completed { at };
+    let failed = JobEvent::Failed("boom".into());
+
+    assert!(!started.is_terminal());
+    assert!(completed.is_terminal() && !completed.is_error());
+    assert!(failed.is_terminal() && failed.is_error());
+
+    assert_eq!(started.level(), Some(Level::Info));
+    assert_eq!(completed.level(), None);
+    assert_eq!(JobEvent::Progress(5).level(), Some(Level::Trace));
+
+    assert_eq!(started.kind(), "started");
+    assert_eq!(JobEvent::Progress(5).kind(), "tick");
+    assert_eq!(failed.kind(), "failed");
+
+    assert_eq!(completed.timestamp(), Some(at));
+    assert_eq!(started.timestamp(), None);
+}
+
+#[tokio::test]
+async fn test_derived_events_drive_stream_operators() {
+    let (sender, stream) = create_stream::<JobEvent>();
+    tokio::spawn(async move {
+        let _ = sender.send(JobEvent::Progress(10)).await;
+        let _ = sender
+            .send(JobEvent::Started {
+                path: "a.rs".into(),
+            })
+            .await;
+        let _ = sender.send(JobEvent::Failed("boom".into())).await;
+        let _ = sender.send(JobEvent::Progress(20)).await;
+    });
+
+    let events: Vec<_> = stream
+        .min_level(Level::Info)
+        .until_terminal()
+        .collect()
+        .await;
+    assert_eq!(
+        events,
+        [
+            JobEvent::Started {
+                path: "a.rs".into()
+            },
+            JobEvent::Failed("boom".into()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_envelopes_forward_derived_kind() {
+    let (sender, mut stream) = StreamBuilder::<JobEvent>::new().enveloped();
+    sender.send(JobEvent::Progress(1)).await.unwrap();
+
+    let envelope = stream.next().await.unwrap();
+    assert_eq!(envelope.kind(), "tick");
+    assert_eq!(envelope.timestamp(), Some(envelope.timestamp));
+}
+
+#[derive(Debug, Event)]
+enum ProcessorEvent {
+    Processed,
+    #[event(terminal)]
+    Completed,
+    #[event(terminal, error)]
+    Failed,
+}
+
+fn ends<E: rustratify::stream::Event>(event: &E) -> bool {
+    event.is_terminal()
+}
+
+#[test]
+fn test_derived_event() {
+    assert!(!ends(&ProcessorEvent::Processed));
+    assert!(ends(&ProcessorEvent::Completed));
+    assert!(ProcessorEvent::Failed.is_error());
+    assert_eq!(ProcessorEvent::Completed.kind(), "completed");
+    // Types deriving `EventKind` are events too
+    assert!(ends(&JobEvent::Failed("boom".into())));
+}
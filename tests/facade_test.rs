@@ -0,0 +1,89 @@
+//! Tests for `#[sea_facade]`.
+
+#![cfg(feature = "derive")]
+
+use rustratify::{async_trait, sea_facade, ProviderError, ProviderResult};
+
+// L3: API
+#[async_trait]
+pub trait Greeter: Send + Sync {
+    async fn greet(&self, name: &str) -> String;
+}
+
+// L4: Core
+pub struct DefaultGreeter {
+    greeting: String,
+}
+
+#[async_trait]
+impl Greeter for DefaultGreeter {
+    async fn greet(&self, name: &str) -> String {
+        format!("{}, {name}!", self.greeting)
+    }
+}
+
+/// Greeting options, re-exported by the facade.
+#[derive(Debug, Default)]
+pub struct GreeterOptions {
+    pub greeting: Option<String>,
+}
+
+// L5: Facade
+#[sea_facade(api = Greeter, core = DefaultGreeter, reexport(GreeterOptions))]
+pub mod greeter {
+    use super::*;
+
+    const DEFAULT_GREETING: &str = "Hello";
+
+    // Private helpers are left alone; factories return the handle, so they
+    // share setup through helpers rather than calling each other
+    fn build(options: GreeterOptions) -> ProviderResult<DefaultGreeter> {
+        let greeting = options.greeting.unwrap_or_else(|| DEFAULT_GREETING.into());
+        if greeting.is_empty() {
+            return Err(ProviderError::ConfigurationError("empty greeting".into()));
+        }
+        Ok(DefaultGreeter { greeting })
+    }
+
+    /// Create a greeter with the default greeting.
+    pub fn create_greeter() -> DefaultGreeter {
+        build(GreeterOptions::default()).expect("default options are valid")
+    }
+
+    /// Create a greeter from options.
+    pub fn create_with(options: GreeterOptions) -> ProviderResult<DefaultGreeter> {
+        build(options)
+    }
+
+    /// Create a greeter after asynchronous setup.
+    pub async fn connect(greeting: String) -> DefaultGreeter {
+        DefaultGreeter { greeting }
+    }
+}
+
+#[tokio::test]
+async fn test_factories_return_handles() {
+    let handle: greeter::GreeterHandle = greeter::create_greeter();
+    assert_eq!(handle.greet("Ada").await, "Hello, Ada!");
+
+    let options = greeter::GreeterOptions {
+        greeting: Some("Hi".into()),
+    };
+    let handle = greeter::create_with(options).unwrap();
+    assert_eq!(handle.greet("Bob").await, "Hi, Bob!");
+
+    let empty = greeter::GreeterOptions {
+        greeting: Some(String::new()),
+    };
+    assert!(greeter::create_with(empty).is_err());
+}
+
+#[tokio::test]
+async fn test_handle_derefs_to_api() {
+    let handle = greeter::connect("Hey".into()).await;
+    let api: &dyn greeter::Greeter = &*handle;
+    assert_eq!(api.greet("Cy").await, "Hey, Cy!");
+
+    let boxed: Box<dyn Greeter> = handle.boxed();
+    assert_eq!(boxed.greet("Di").await, "Hey, Di!");
+}
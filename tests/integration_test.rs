@@ -2,6 +2,8 @@
 //!
 //! These tests demonstrate real-world usage patterns of the SEA framework.
 
+#![cfg(feature = "std")]
+
 use rustratify::prelude::*;
 use std::any::Any;
 use std::path::Path;
@@ -374,6 +376,117 @@ fn test_provider_error_display() {
     assert!(msg.contains("test"));
 }
 
+#[derive(Debug)]
+struct QuotaError {
+    tenant: String,
+}
+
+impl std::fmt::Display for QuotaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "quota exceeded for {}", self.tenant)
+    }
+}
+
+impl std::error::Error for QuotaError {}
+
+#[test]
+fn test_custom_error_round_trip() {
+    let error = ProviderError::custom(QuotaError {
+        tenant: "acme".to_string(),
+    });
+    let cloned = error.clone();
+    assert_eq!(cloned.to_string(), "quota exceeded for acme");
+
+    let root: RustratifyError = error.into();
+    assert_eq!(root.downcast_ref::<QuotaError>().unwrap().tenant, "acme");
+
+    let boxed: BoxError = Box::new(QuotaError {
+        tenant: "beta".to_string(),
+    });
+    let root = RustratifyError::from(boxed);
+    assert!(matches!(root, RustratifyError::Custom(_)));
+    assert_eq!(root.to_string(), "quota exceeded for beta");
+    assert!(root.downcast_ref::<std::io::Error>().is_none());
+}
+
+#[test]
+fn test_error_codes_and_categories() {
+    let errors: Vec<RustratifyError> = vec![
+        ProviderError::NotFound("x".to_string()).into(),
+        ProviderError::Timeout(10).into(),
+        ProviderError::ConfigurationError("x".to_string()).into(),
+        RegistryError::Empty.into(),
+        RustratifyError::Stream("closed".to_string()),
+    ];
+    let codes: Vec<_> = errors.iter().map(|e| (e.code(), e.category())).collect();
+    assert_eq!(
+        codes,
+        vec![
+            ("RSTR-P-001", ErrorCategory::User),
+            ("RSTR-P-007", ErrorCategory::Transient),
+            ("RSTR-P-005", ErrorCategory::Configuration),
+            ("RSTR-R-003", ErrorCategory::Configuration),
+            ("RSTR-S-001", ErrorCategory::Internal),
+        ]
+    );
+    assert!(ErrorCategory::Transient.is_retryable());
+    assert!(!ErrorCategory::User.is_retryable());
+}
+
+#[test]
+fn test_error_severity_and_user_message() {
+    let error = ProviderError::ExecutionFailed("db password rejected for admin".to_string());
+    assert_eq!(error.severity(), ErrorSeverity::Error);
+    assert_eq!(error.user_message(), "Something went wrong. Please try again later.");
+    assert!(!error.user_message().contains("password"));
+
+    let error: RustratifyError = ProviderError::NotFound("secret-key".to_string()).into();
+    assert_eq!(error.severity(), ErrorSeverity::Info);
+    assert_eq!(error.severity().level(), tracing::Level::INFO);
+    assert_eq!(error.user_message(), "The requested item could not be found.");
+
+    assert_eq!(RegistryError::Empty.severity(), ErrorSeverity::Error);
+    assert_eq!(
+        RegistryError::NoMatchingProvider.user_message(),
+        "No handler is available for this request."
+    );
+}
+
+#[test]
+fn test_error_fields() {
+    let error = ProviderError::IoError("permission denied".to_string())
+        .with_field("path", Path::new("/etc/app.toml"))
+        .with_field("provider", "toml");
+
+    let error: RustratifyError = error.into();
+    let error = error.with_field("attempt", 2u32);
+    assert_eq!(error.to_string(), "Provider error: IO error: permission denied");
+    assert_eq!(error.code(), "RSTR-P-006");
+    assert_eq!(
+        error.fields().to_string(),
+        "attempt=2 path=/etc/app.toml provider=toml"
+    );
+    assert!(matches!(
+        error.root(),
+        RustratifyError::Provider(e) if matches!(e.root(), ProviderError::IoError(_))
+    ));
+}
+
+#[test]
+fn test_exit_codes() {
+    let ok: Result<(), RustratifyError> = Ok(());
+    assert_eq!(ExitStatus::of(&ok), ExitStatus::SUCCESS);
+    assert!(ExitStatus::of(&ok).is_success());
+
+    let error: RustratifyError = RegistryError::InvalidName("".to_string()).into();
+    assert_eq!(error.exit_code().code(), 64);
+
+    let error: RustratifyError = ProviderError::IoError("reset".to_string()).into();
+    assert_eq!(error.exit_code(), ExitStatus::TRANSIENT);
+    assert_eq!(ProviderError::ConfigurationError("x".to_string()).exit_code().code(), 78);
+    assert_eq!(RustratifyError::Other("bug".to_string()).exit_code().code(), 70);
+}
+
 // =============================================================================
 // Real-World Scenario Tests
 // =============================================================================
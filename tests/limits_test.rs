@@ -0,0 +1,42 @@
+//! Memory limits with `TrackingAllocator` installed as the global allocator.
+
+#![cfg(feature = "tokio")]
+
+use rustratify::limits::{RunLimiter, RunLimits, TrackingAllocator};
+use rustratify::ProviderError;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator::system();
+
+#[tokio::test]
+async fn test_memory_is_charged_to_the_run() {
+    let limiter = RunLimiter::new(RunLimits::new().max_memory(1 << 20));
+    let len = limiter
+        .run(async {
+            let buffer = vec![0u8; 64 * 1024];
+            tokio::task::yield_now().await;
+            Ok(buffer.len())
+        })
+        .await
+        .unwrap();
+    assert_eq!(len, 64 * 1024);
+    assert!(limiter.usage().peak_memory() >= 64 * 1024);
+}
+
+#[tokio::test]
+async fn test_memory_limit_terminates_run() {
+    let limiter = RunLimiter::new(RunLimits::new().max_memory(1 << 20));
+    let result = limiter
+        .run(async {
+            let mut chunks = Vec::new();
+            for _ in 0..64 {
+                chunks.push(vec![1u8; 256 * 1024]);
+                tokio::task::yield_now().await;
+            }
+            Ok(chunks.len())
+        })
+        .await;
+    let err = result.unwrap_err();
+    assert!(matches!(err, ProviderError::LimitExceeded(_)));
+    assert!(err.to_string().contains("memory limit"));
+}
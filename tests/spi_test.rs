@@ -0,0 +1,111 @@
+//! Tests for `#[spi]`.
+
+#![cfg(feature = "derive")]
+
+use std::any::Any;
+
+use rustratify::{Provider, ProviderError, ProviderResult, Registry};
+
+// Kept in a module so each test imports only the trait it calls through;
+// with both in scope, calls on a concrete type are ambiguous
+mod api {
+    use rustratify::{spi, Provider, ProviderResult};
+
+    #[spi]
+    pub trait Formatter: Provider {
+        /// Format `source`.
+        async fn format(&self, source: &str) -> ProviderResult<String>;
+
+        async fn format_all<'a>(&self, sources: &'a [&'a str]) -> Vec<ProviderResult<String>> {
+            let mut out = Vec::new();
+            for source in sources {
+                out.push(self.format(source).await);
+            }
+            out
+        }
+
+        fn line_width(&self) -> usize {
+            80
+        }
+    }
+
+    #[spi(dyn_trait = BoxedCounter)]
+    pub trait Counter: Send + Sync {
+        async fn bump(&mut self, by: u32) -> u32;
+    }
+}
+
+#[derive(Debug)]
+struct Upper;
+
+impl Provider for Upper {
+    fn name(&self) -> &str {
+        "upper"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl api::Formatter for Upper {
+    async fn format(&self, source: &str) -> ProviderResult<String> {
+        if source.is_empty() {
+            return Err(ProviderError::ExecutionFailed("empty source".into()));
+        }
+        Ok(source.to_uppercase())
+    }
+}
+
+struct Tally(u32);
+
+impl api::Counter for Tally {
+    async fn bump(&mut self, by: u32) -> u32 {
+        self.0 += by;
+        self.0
+    }
+}
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+#[tokio::test]
+async fn test_static_dispatch() {
+    use api::Formatter;
+
+    let formatter = Upper;
+    let result = assert_send(formatter.format("fn main() {}")).await;
+    assert_eq!(result.unwrap(), "FN MAIN() {}");
+
+    let results = formatter.format_all(&["a", ""]).await;
+    assert_eq!(results[0].as_ref().unwrap(), "A");
+    assert!(results[1].is_err());
+    assert_eq!(formatter.line_width(), 80);
+}
+
+#[tokio::test]
+async fn test_dyn_trait_in_registry() {
+    use api::DynFormatter;
+
+    let mut registry: Registry<dyn DynFormatter> = Registry::new();
+    registry.register(Box::new(Upper));
+
+    let formatter = registry.find_by_extension("TXT").unwrap();
+    assert_eq!(formatter.format("abc").await.unwrap(), "ABC");
+    assert_eq!(formatter.format_all(&["x"]).await[0].as_ref().unwrap(), "X");
+    assert_eq!(formatter.line_width(), 80);
+}
+
+#[tokio::test]
+async fn test_custom_dyn_name_and_mut_receiver() {
+    use api::BoxedCounter;
+
+    let mut counter: Box<dyn BoxedCounter> = Box::new(Tally(1));
+    assert_eq!(counter.bump(2).await, 3);
+    assert_eq!(counter.bump(4).await, 7);
+}
@@ -0,0 +1,99 @@
+//! Tests for `#[spi_trait]`.
+
+#![cfg(feature = "derive")]
+
+use std::any::Any;
+
+use rustratify::{async_trait, spi_trait, Provider, RegistryBuilder};
+
+#[spi_trait]
+#[async_trait]
+pub trait Formatter: Provider {
+    async fn format(&self, source: &str) -> String;
+}
+
+#[spi_trait(registry = Checkers, register = add_checker, cloneable = CheckerBox)]
+pub trait Checker: Provider {
+    fn check(&self, source: &str) -> bool;
+}
+
+#[derive(Debug, Clone, Default)]
+struct Upper;
+
+impl Provider for Upper {
+    fn name(&self) -> &str {
+        "upper"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["txt"]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[async_trait]
+impl Formatter for Upper {
+    async fn format(&self, source: &str) -> String {
+        source.to_uppercase()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NonEmpty(&'static str);
+
+impl Provider for NonEmpty {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Checker for NonEmpty {
+    fn check(&self, source: &str) -> bool {
+        !source.is_empty()
+    }
+}
+
+#[tokio::test]
+async fn test_registry_alias_and_register() {
+    let mut registry = FormatterRegistry::new();
+    register_formatter(&mut registry, Upper);
+
+    let formatter = registry.find("a.txt").unwrap();
+    assert_eq!(formatter.format("abc").await, "ABC");
+    assert!(formatter.is::<Upper>());
+    assert!(formatter.downcast_ref::<Upper>().is_some());
+}
+
+#[test]
+fn test_custom_names() {
+    let mut checkers = Checkers::new();
+    add_checker(&mut checkers, NonEmpty("non-empty"));
+    assert!(checkers.get("non-empty").unwrap().check("x"));
+    assert!(checkers.get("non-empty").unwrap().is::<NonEmpty>());
+
+    let boxed: Box<dyn CheckerBox> = Box::new(NonEmpty("copy"));
+    let cloned = boxed.clone();
+    assert_eq!(cloned.name(), "copy");
+    assert!(!cloned.check(""));
+}
+
+#[test]
+fn test_builder_with_default() {
+    let registry = RegistryBuilder::<dyn Formatter>::new()
+        .with_default::<Upper>()
+        .build();
+    assert!(registry.get("upper").unwrap().is::<Upper>());
+
+    let cloneable = RegistryBuilder::<dyn CloneableFormatter>::new()
+        .with_default::<Upper>()
+        .build();
+    let copy = cloneable.get("upper").unwrap().clone_box();
+    assert_eq!(copy.name(), "upper");
+}
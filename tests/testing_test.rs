@@ -0,0 +1,124 @@
+//! Tests for the `testing` helpers used from a downstream crate.
+
+#![cfg(feature = "testing")]
+
+use std::any::Any;
+
+use rustratify::testing::{MockProvider, TestRegistry};
+use rustratify::{Provider, ProviderError, ProviderResult, Registry};
+
+/// An SPI trait as a downstream crate would define it.
+trait Formatter: Provider {
+    fn format(&self, source: &str) -> ProviderResult<String>;
+}
+
+impl Formatter for MockProvider<String> {
+    fn format(&self, source: &str) -> ProviderResult<String> {
+        self.call(source)
+    }
+}
+
+/// Code under test: formats through whichever provider the registry picks.
+fn format_file(registry: &Registry<dyn Formatter>, path: &str) -> ProviderResult<String> {
+    let formatter = registry
+        .find(path)
+        .ok_or_else(|| ProviderError::NotFound(path.to_string()))?;
+    formatter.format(path)
+}
+
+#[test]
+fn test_mock_provider_behind_spi_trait() {
+    let rustfmt = MockProvider::new("rustfmt")
+        .with_extensions(&[".rs"])
+        .with_priority(5)
+        .then(Err(ProviderError::ExecutionFailed("syntax error".into())))
+        .returning("formatted".to_string());
+    let fallback = MockProvider::new("generic")
+        .with_extensions(&[".rs", ".toml"])
+        .returning("generic".to_string());
+    let (rustfmt_calls, fallback_calls) = (rustfmt.clone(), fallback.clone());
+
+    let mut registry: Registry<dyn Formatter> = Registry::new();
+    registry.register(Box::new(rustfmt));
+    registry.register(Box::new(fallback));
+    registry.assert_registered("rustfmt");
+    registry.assert_find_resolves_to("main.rs", "rustfmt");
+    registry.assert_find_resolves_to("Cargo.toml", "generic");
+
+    assert!(format_file(&registry, "main.rs").is_err());
+    assert_eq!(format_file(&registry, "lib.rs").unwrap(), "formatted");
+    assert_eq!(format_file(&registry, "Cargo.toml").unwrap(), "generic");
+
+    assert_eq!(rustfmt_calls.calls(), vec!["main.rs", "lib.rs"]);
+    assert_eq!(fallback_calls.call_count(), 1);
+    let provider: &dyn Any = registry.get("rustfmt").unwrap().as_any();
+    assert!(provider.is::<MockProvider<String>>());
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_collect_events_with_timeout() {
+    use std::time::Duration;
+
+    use rustratify::testing::collect_events_with_timeout;
+    use rustratify::StreamBuilder;
+
+    let (sender, stream) = StreamBuilder::new().build();
+    tokio::spawn(async move {
+        for i in 0..3 {
+            sender.send(i).await.unwrap();
+        }
+    });
+    let events = collect_events_with_timeout(stream, Duration::from_secs(5)).await;
+    assert_eq!(events, vec![0, 1, 2]);
+}
+
+mod contract {
+    use super::*;
+    use rustratify::spi_contract_tests;
+    use rustratify::testing::{check_provider_contract, ContractFixtures};
+
+    #[derive(Debug)]
+    struct ConfigProvider {
+        files: Vec<&'static str>,
+    }
+
+    impl ConfigProvider {
+        fn new() -> Self {
+            Self {
+                files: vec!["Cargo.toml", "rustfmt.toml"],
+            }
+        }
+    }
+
+    impl Provider for ConfigProvider {
+        fn name(&self) -> &str {
+            "config"
+        }
+
+        fn supports(&self, key: &str) -> bool {
+            self.files.iter().any(|file| key.ends_with(file))
+        }
+
+        fn priority(&self) -> i32 {
+            -3
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    fn fixtures() -> ContractFixtures {
+        ContractFixtures::new()
+            .supports(&["Cargo.toml", "crates/core/rustfmt.toml"])
+            .rejects(&["Cargo.lock", "main.rs"])
+    }
+
+    spi_contract_tests!(ConfigProvider::new(), fixtures());
+
+    #[test]
+    fn test_check_provider_contract() {
+        check_provider_contract(ConfigProvider::new, &fixtures());
+    }
+}